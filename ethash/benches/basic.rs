@@ -42,9 +42,9 @@ fn bench_light_compute_memmap(b: &mut Criterion) {
 	use std::env;
 
 	let builder = NodeCacheBuilder::new(OptimizeFor::Memory);
-	let light = builder.light(&env::temp_dir(), 486382);
+	let light = builder.light(&env::temp_dir(), 486382, u64::max_value());
 
-	b.bench_function("bench_light_compute_memmap", move |b| b.iter(|| light.compute(&HASH, NONCE)));
+	b.bench_function("bench_light_compute_memmap", move |b| b.iter(|| light.compute(&HASH, NONCE, 486382)));
 }
 
 
@@ -52,9 +52,9 @@ fn bench_light_compute_memory(b: &mut Criterion) {
 	use std::env;
 
 	let builder = NodeCacheBuilder::new(OptimizeFor::Cpu);
-	let light = builder.light(&env::temp_dir(), 486382);
+	let light = builder.light(&env::temp_dir(), 486382, u64::max_value());
 
-	b.bench_function("bench_light_compute_memmap", move |b| b.iter(|| light.compute(&HASH, NONCE)));
+	b.bench_function("bench_light_compute_memmap", move |b| b.iter(|| light.compute(&HASH, NONCE, 486382)));
 }
 
 
@@ -63,8 +63,8 @@ fn bench_light_new_round_trip_memmap(b: &mut Criterion) {
 
 	b.bench_function("bench_light_compute_memmap", move |b| b.iter(|| {
 		let builder = NodeCacheBuilder::new(OptimizeFor::Memory);
-		let light = builder.light(&env::temp_dir(), 486382);
-		light.compute(&HASH, NONCE);
+		let light = builder.light(&env::temp_dir(), 486382, u64::max_value());
+		light.compute(&HASH, NONCE, 486382);
 	}));
 }
 
@@ -74,8 +74,8 @@ fn bench_light_new_round_trip_memory(b: &mut Criterion) {
 
 	b.bench_function("bench_light_compute_memmap", move |b| b.iter(|| {
 		let builder = NodeCacheBuilder::new(OptimizeFor::Cpu);
-		let light = builder.light(&env::temp_dir(), 486382);
-		light.compute(&HASH, NONCE);
+		let light = builder.light(&env::temp_dir(), 486382, u64::max_value());
+		light.compute(&HASH, NONCE, 486382);
 	}));
 }
 
@@ -87,14 +87,14 @@ fn bench_light_from_file_round_trip_memory(b: &mut Criterion) {
 	let height = 486382;
 	{
 		let builder = NodeCacheBuilder::new(OptimizeFor::Cpu);
-		let mut dummy = builder.light(&dir, height);
+		let mut dummy = builder.light(&dir, height, u64::max_value());
 		dummy.to_file().unwrap();
 	}
 
 	b.bench_function("bench_light_compute_memmap", move |b| b.iter(|| {
 		let builder = NodeCacheBuilder::new(OptimizeFor::Cpu);
-		let light = builder.light_from_file(&dir, 486382).unwrap();
-		light.compute(&HASH, NONCE);
+		let light = builder.light_from_file(&dir, 486382, u64::max_value()).unwrap();
+		light.compute(&HASH, NONCE, 486382);
 	}));
 }
 
@@ -107,13 +107,13 @@ fn bench_light_from_file_round_trip_memmap(b: &mut Criterion) {
 
 	{
 		let builder = NodeCacheBuilder::new(OptimizeFor::Memory);
-		let mut dummy = builder.light(&dir, height);
+		let mut dummy = builder.light(&dir, height, u64::max_value());
 		dummy.to_file().unwrap();
 	}
 
 	b.bench_function("bench_light_compute_memmap", move |b| b.iter(|| {
 		let builder = NodeCacheBuilder::new(OptimizeFor::Memory);
-		let light = builder.light_from_file(&dir, 486382).unwrap();
-		light.compute(&HASH, NONCE);
+		let light = builder.light_from_file(&dir, 486382, u64::max_value()).unwrap();
+		light.compute(&HASH, NONCE, 486382);
 	}));
 }
@@ -81,12 +81,12 @@ pub struct NodeCache {
 }
 
 impl NodeCacheBuilder {
-	pub fn light(&self, cache_dir: &Path, block_number: u64) -> Light {
-		Light::new_with_builder(self, cache_dir, block_number)
+	pub fn light(&self, cache_dir: &Path, block_number: u64, progpow_transition: u64) -> Light {
+		Light::new_with_builder(self, cache_dir, block_number, progpow_transition)
 	}
 
-	pub fn light_from_file(&self, cache_dir: &Path, block_number: u64) -> io::Result<Light> {
-		Light::from_file_with_builder(self, cache_dir, block_number)
+	pub fn light_from_file(&self, cache_dir: &Path, block_number: u64, progpow_transition: u64) -> io::Result<Light> {
+		Light::from_file_with_builder(self, cache_dir, block_number, progpow_transition)
 	}
 
 	pub fn new<T: Into<Option<OptimizeFor>>>(optimize_for: T) -> Self {
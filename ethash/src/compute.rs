@@ -21,6 +21,7 @@
 
 use keccak::{keccak_512, keccak_256, H256};
 use cache::{NodeCache, NodeCacheBuilder};
+use progpow::{CDag, bytes_to_words, generate_cdag, keccak_f800_long, keccak_f800_short, progpow};
 use seed_compute::SeedHashCompute;
 use shared::*;
 use std::io;
@@ -30,7 +31,7 @@ use std::path::Path;
 
 const MIX_WORDS: usize = ETHASH_MIX_BYTES / 4;
 const MIX_NODES: usize = MIX_WORDS / NODE_WORDS;
-const FNV_PRIME: u32 = 0x01000193;
+pub const FNV_PRIME: u32 = 0x01000193;
 
 /// Computation result
 pub struct ProofOfWork {
@@ -43,6 +44,8 @@ pub struct ProofOfWork {
 pub struct Light {
 	block_number: u64,
 	cache: NodeCache,
+	progpow_transition: u64,
+	c_dag: Option<Box<CDag>>,
 }
 
 /// Light cache structure
@@ -51,34 +54,67 @@ impl Light {
 		builder: &NodeCacheBuilder,
 		cache_dir: &Path,
 		block_number: u64,
+		progpow_transition: u64,
 	) -> Self {
 		let cache = builder.new_cache(cache_dir.to_path_buf(), block_number);
+		let c_dag = Light::generate_cdag(&cache, block_number, progpow_transition);
 
 		Light {
 			block_number: block_number,
 			cache: cache,
+			progpow_transition: progpow_transition,
+			c_dag: c_dag,
 		}
 	}
 
 	/// Calculate the light boundary data
 	/// `header_hash` - The header hash to pack into the mix
 	/// `nonce` - The nonce to pack into the mix
-	pub fn compute(&self, header_hash: &H256, nonce: u64) -> ProofOfWork {
-		light_compute(self, header_hash, nonce)
+	/// `block_number` - The number of the block, selects the algorithm and the ProgPoW program
+	pub fn compute(&self, header_hash: &H256, nonce: u64, block_number: u64) -> ProofOfWork {
+		match self.c_dag {
+			Some(ref c_dag) if block_number >= self.progpow_transition => {
+				let (value, mix_hash) = progpow(
+					header_hash,
+					nonce,
+					block_number,
+					self.cache.as_ref(),
+					c_dag,
+				);
+
+				ProofOfWork { value: value, mix_hash: mix_hash }
+			}
+			_ => light_compute(self, header_hash, nonce),
+		}
 	}
 
 	pub fn from_file_with_builder(
 		builder: &NodeCacheBuilder,
 		cache_dir: &Path,
 		block_number: u64,
+		progpow_transition: u64,
 	) -> io::Result<Self> {
 		let cache = builder.from_file(cache_dir.to_path_buf(), block_number)?;
+		let c_dag = Light::generate_cdag(&cache, block_number, progpow_transition);
+
 		Ok(Light {
 			block_number: block_number,
 			cache: cache,
+			progpow_transition: progpow_transition,
+			c_dag: c_dag,
 		})
 	}
 
+	// The transition may fall in the middle of the epoch, in which case the cache serves both algorithms.
+	fn generate_cdag(cache: &NodeCache, block_number: u64, progpow_transition: u64) -> Option<Box<CDag>> {
+		let epoch_end = (block_number / ETHASH_EPOCH_LENGTH + 1) * ETHASH_EPOCH_LENGTH - 1;
+		if epoch_end >= progpow_transition {
+			Some(Box::new(generate_cdag(cache.as_ref())))
+		} else {
+			None
+		}
+	}
+
 	pub fn to_file(&mut self) -> io::Result<&Path> {
 		self.cache.flush()?;
 		Ok(self.cache.cache_path())
@@ -98,8 +134,14 @@ fn fnv_hash(x: u32, y: u32) -> u32 {
 /// `header_hash`      The hash of the header
 /// `nonce`            The block's nonce
 /// `mix_hash`         The mix digest hash
+/// `progpow`          Whether the block is sealed with ProgPoW
 /// Boundary recovered from mix hash
-pub fn quick_get_difficulty(header_hash: &H256, nonce: u64, mix_hash: &H256) -> H256 {
+pub fn quick_get_difficulty(header_hash: &H256, nonce: u64, mix_hash: &H256, progpow: bool) -> H256 {
+	if progpow {
+		let seed = keccak_f800_short(header_hash, nonce, &[0u32; 8]);
+		return keccak_f800_long(header_hash, seed, &bytes_to_words(mix_hash));
+	}
+
 	unsafe {
 		// This is safe - the `keccak_512` call below reads the first 40 bytes (which we explicitly set
 		// with two `copy_nonoverlapping` calls) but writes the first 64, and then we explicitly write
@@ -287,7 +329,7 @@ fn hash_compute(light: &Light, full_size: usize, header_hash: &H256, nonce: u64)
 }
 
 // TODO: Use the `simd` crate
-fn calculate_dag_item(node_index: u32, cache: &[Node]) -> Node {
+pub fn calculate_dag_item(node_index: u32, cache: &[Node]) -> Node {
 	let num_parent_nodes = cache.len();
 	let mut ret = cache[node_index as usize % num_parent_nodes].clone();
 	ret.as_words_mut()[0] ^= node_index;
@@ -361,13 +403,13 @@ mod test {
 			0x4a, 0x8e, 0x95, 0x69, 0xef, 0xc7, 0xd7, 0x1b, 0x33, 0x35, 0xdf, 0x36, 0x8c, 0x9a,
 			0xe9, 0x7e, 0x53, 0x84,
 		];
-		assert_eq!(quick_get_difficulty(&hash, nonce, &mix_hash)[..], boundary_good[..]);
+		assert_eq!(quick_get_difficulty(&hash, nonce, &mix_hash, false)[..], boundary_good[..]);
 		let boundary_bad = [
 			0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x3a, 0x9b, 0x6c, 0x69, 0xbc, 0x2c, 0xe2, 0xa2,
 			0x4a, 0x8e, 0x95, 0x69, 0xef, 0xc7, 0xd7, 0x1b, 0x33, 0x35, 0xdf, 0x36, 0x8c, 0x9a,
 			0xe9, 0x7e, 0x53, 0x84,
 		];
-		assert!(quick_get_difficulty(&hash, nonce, &mix_hash)[..] != boundary_bad[..]);
+		assert!(quick_get_difficulty(&hash, nonce, &mix_hash, false)[..] != boundary_bad[..]);
 	}
 
 	#[test]
@@ -391,26 +433,61 @@ mod test {
 
 		let tempdir = TempDir::new("").unwrap();
 		// difficulty = 0x085657254bd9u64;
-		let light = NodeCacheBuilder::new(None).light(tempdir.path(), 486382);
+		let light = NodeCacheBuilder::new(None).light(tempdir.path(), 486382, u64::max_value());
 		let result = light_compute(&light, &hash, nonce);
 		assert_eq!(result.mix_hash[..], mix_hash[..]);
 		assert_eq!(result.value[..], boundary[..]);
 	}
 
+	#[test]
+	fn test_light_compute_progpow() {
+		let hash = [
+			0xff, 0xee, 0xdd, 0xcc, 0xbb, 0xaa, 0x99, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22,
+			0x11, 0x00, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb,
+			0xcc, 0xdd, 0xee, 0xff,
+		];
+		let mix_hash = [
+			0x11, 0xf1, 0x98, 0x05, 0xc5, 0x8a, 0xb4, 0x66, 0x10, 0xff, 0x9c, 0x71, 0x9d, 0xcf,
+			0x0a, 0x5f, 0x18, 0xfa, 0x2f, 0x16, 0x05, 0x79, 0x8e, 0xef, 0x77, 0x0c, 0x47, 0x21,
+			0x92, 0x74, 0x76, 0x7d,
+		];
+		let boundary = [
+			0x5b, 0x7c, 0xcd, 0x47, 0x2d, 0xbe, 0xfd, 0xd9, 0x5b, 0x89, 0x5c, 0xac, 0x8e, 0xce,
+			0x67, 0xff, 0x0d, 0xeb, 0x5a, 0x6b, 0xd2, 0xec, 0xc6, 0xe1, 0x62, 0x38, 0x3d, 0x00,
+			0xc3, 0x72, 0x8e, 0xce,
+		];
+		let nonce = 0x123456789abcdef0;
+
+		let tempdir = TempDir::new("").unwrap();
+		let builder = NodeCacheBuilder::new(None);
+
+		let light = builder.light(tempdir.path(), 30000, 0);
+		let result = light.compute(&hash, nonce, 30000);
+		assert_eq!(result.mix_hash[..], mix_hash[..]);
+		assert_eq!(result.value[..], boundary[..]);
+
+		// the transition falls within the epoch of the cache, blocks 30000 and 30001 share the program
+		let light = builder.light(tempdir.path(), 30000, 30001);
+		assert!(light.compute(&hash, nonce, 30000).mix_hash[..] != mix_hash[..]);
+		assert_eq!(light.compute(&hash, nonce, 30001).mix_hash[..], mix_hash[..]);
+		assert_eq!(quick_get_difficulty(&hash, nonce, &mix_hash, true)[..], boundary[..]);
+		assert!(quick_get_difficulty(&hash, nonce, &mix_hash, false)[..] != boundary[..]);
+	}
+
 	#[test]
 	fn test_drop_old_data() {
 		let tempdir = TempDir::new("").unwrap();
 		let builder = NodeCacheBuilder::new(None);
-		let first = builder.light(tempdir.path(), 0).to_file().unwrap().to_owned();
+		let first = builder.light(tempdir.path(), 0, u64::max_value()).to_file().unwrap().to_owned();
 
-		let second = builder.light(tempdir.path(), ETHASH_EPOCH_LENGTH).to_file().unwrap().to_owned();
+		let second = builder.light(tempdir.path(), ETHASH_EPOCH_LENGTH, u64::max_value()).to_file().unwrap().to_owned();
 		assert!(fs::metadata(&first).is_ok());
 
-		let _ = builder.light(tempdir.path(), ETHASH_EPOCH_LENGTH * 2).to_file();
+		let _ = builder.light(tempdir.path(), ETHASH_EPOCH_LENGTH * 2, u64::max_value()).to_file();
 		assert!(fs::metadata(&first).is_err());
 		assert!(fs::metadata(&second).is_ok());
 
-		let _ = builder.light(tempdir.path(), ETHASH_EPOCH_LENGTH * 3).to_file();
+		let _ = builder.light(tempdir.path(), ETHASH_EPOCH_LENGTH * 3, u64::max_value()).to_file();
 		assert!(fs::metadata(&second).is_err());
 	}
 }
@@ -33,6 +33,7 @@ mod seed_compute;
 mod cache;
 mod keccak;
 mod shared;
+mod progpow;
 
 pub use cache::{NodeCacheBuilder, OptimizeFor};
 pub use compute::{ProofOfWork, quick_get_difficulty, slow_hash_block_number};
@@ -59,13 +60,16 @@ pub struct EthashManager {
 	nodecache_builder: NodeCacheBuilder,
 	cache: Mutex<LightCache>,
 	cache_dir: PathBuf,
+	progpow_transition: u64,
 }
 
 impl EthashManager {
 	/// Create a new new instance of ethash manager
-	pub fn new<T: Into<Option<OptimizeFor>>>(cache_dir: &Path, optimize_for: T) -> EthashManager {
+	/// `progpow_transition` - Number of the first block sealed with ProgPoW
+	pub fn new<T: Into<Option<OptimizeFor>>>(cache_dir: &Path, optimize_for: T, progpow_transition: u64) -> EthashManager {
 		EthashManager {
 			cache_dir: cache_dir.to_path_buf(),
+			progpow_transition: progpow_transition,
 			nodecache_builder: NodeCacheBuilder::new(optimize_for.into().unwrap_or_default()),
 			cache: Mutex::new(LightCache {
 				recent_epoch: None,
@@ -111,6 +115,7 @@ impl EthashManager {
 					let light = match self.nodecache_builder.light_from_file(
 						&self.cache_dir,
 						block_number,
+						self.progpow_transition,
 					) {
 						Ok(light) => Arc::new(light),
 						Err(e) => {
@@ -118,6 +123,7 @@ impl EthashManager {
 							let mut light = self.nodecache_builder.light(
 								&self.cache_dir,
 								block_number,
+								self.progpow_transition,
 							);
 							if let Err(e) = light.to_file() {
 								warn!("Light cache file write error: {}", e);
@@ -132,7 +138,7 @@ impl EthashManager {
 				Some(light) => light,
 			}
 		};
-		light.compute(header_hash, nonce)
+		light.compute(header_hash, nonce, block_number)
 	}
 }
 
@@ -164,7 +170,7 @@ fn test_lru() {
 	use tempdir::TempDir;
 
 	let tempdir = TempDir::new("").unwrap();
-	let ethash = EthashManager::new(tempdir.path(), None);
+	let ethash = EthashManager::new(tempdir.path(), None, u64::max_value());
 	let hash = [0u8; 32];
 	ethash.compute_light(1, &hash, 1);
 	ethash.compute_light(50000, &hash, 1);
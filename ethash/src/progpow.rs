@@ -0,0 +1,573 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! ProgPoW (version 0.9.2) implementation
+//! See https://github.com/ifdefelse/ProgPOW
+
+use compute::{calculate_dag_item, FNV_PRIME};
+use keccak::H256;
+use shared::{ETHASH_ACCESSES, ETHASH_MIX_BYTES, Node, get_data_size};
+
+const PROGPOW_CACHE_BYTES: usize = 16 * 1024;
+const PROGPOW_CACHE_WORDS: usize = PROGPOW_CACHE_BYTES / 4;
+const PROGPOW_CNT_CACHE: usize = 12;
+const PROGPOW_CNT_MATH: usize = 20;
+const PROGPOW_CNT_DAG: usize = ETHASH_ACCESSES;
+const PROGPOW_DAG_LOADS: usize = 4;
+const PROGPOW_MIX_BYTES: usize = 2 * ETHASH_MIX_BYTES;
+const PROGPOW_PERIOD_LENGTH: u64 = 50;
+const PROGPOW_LANES: usize = 16;
+const PROGPOW_REGS: usize = 32;
+
+const FNV_HASH: u32 = 0x811c9dc5;
+
+const KECCAKF_RNDC: [u32; 24] = [
+	0x00000001, 0x00008082, 0x0000808a, 0x80008000, 0x0000808b, 0x80000001,
+	0x80008081, 0x00008009, 0x0000008a, 0x00000088, 0x80008009, 0x8000000a,
+	0x8000808b, 0x0000008b, 0x00008089, 0x00008003, 0x00008002, 0x00000080,
+	0x0000800a, 0x8000000a, 0x80008081, 0x00008080, 0x80000001, 0x80008008,
+];
+
+const KECCAKF_ROTC: [u32; 24] = [
+	1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14,
+	27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+const KECCAKF_PILN: [usize; 24] = [
+	10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4,
+	15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+/// The first `PROGPOW_CACHE_BYTES` of the DAG, cached for random reads.
+pub type CDag = [u32; PROGPOW_CACHE_WORDS];
+
+fn keccak_f800_round(st: &mut [u32; 25], r: usize) {
+	// Theta
+	let mut bc = [0u32; 5];
+	for i in 0..bc.len() {
+		bc[i] = st[i] ^ st[i + 5] ^ st[i + 10] ^ st[i + 15] ^ st[i + 20];
+	}
+
+	for i in 0..bc.len() {
+		let t = bc[(i + 4) % 5] ^ bc[(i + 1) % 5].rotate_left(1);
+		for j in 0..5 {
+			st[5 * j + i] ^= t;
+		}
+	}
+
+	// Rho Pi
+	let mut t = st[1];
+	for i in 0..KECCAKF_ROTC.len() {
+		let j = KECCAKF_PILN[i];
+		bc[0] = st[j];
+		st[j] = t.rotate_left(KECCAKF_ROTC[i]);
+		t = bc[0];
+	}
+
+	// Chi
+	for j in 0..5 {
+		for i in 0..bc.len() {
+			bc[i] = st[5 * j + i];
+		}
+		for i in 0..bc.len() {
+			st[5 * j + i] ^= (!bc[(i + 1) % 5]) & bc[(i + 2) % 5];
+		}
+	}
+
+	// Iota
+	st[0] ^= KECCAKF_RNDC[r];
+}
+
+fn keccak_f800(header_hash: &H256, nonce: u64, result: &[u32; 8]) -> [u32; 25] {
+	let mut st = [0u32; 25];
+
+	// Absorb phase for fixed 18 words of input
+	for i in 0..8 {
+		st[i] = (header_hash[4 * i] as u32) +
+			((header_hash[4 * i + 1] as u32) << 8) +
+			((header_hash[4 * i + 2] as u32) << 16) +
+			((header_hash[4 * i + 3] as u32) << 24);
+	}
+	st[8] = nonce as u32;
+	st[9] = (nonce >> 32) as u32;
+	st[10..18].copy_from_slice(result);
+
+	for r in 0..22 {
+		keccak_f800_round(&mut st, r);
+	}
+
+	st
+}
+
+/// Keccak-f800 of the header and nonce, reduced to the 64-bit seed of the mix.
+pub fn keccak_f800_short(header_hash: &H256, nonce: u64, result: &[u32; 8]) -> u64 {
+	let st = keccak_f800(header_hash, nonce, result);
+	// byte swap so that byte 0 of the hash is the MSB of the result
+	(st[0].swap_bytes() as u64) << 32 | st[1].swap_bytes() as u64
+}
+
+/// Keccak-f800 of the header, seed and mix digest, the final hash compared against the boundary.
+pub fn keccak_f800_long(header_hash: &H256, seed: u64, result: &[u32; 8]) -> H256 {
+	let st = keccak_f800(header_hash, seed, result);
+	let mut words = [0u32; 8];
+	words.copy_from_slice(&st[..8]);
+	words_to_bytes(&words)
+}
+
+fn words_to_bytes(words: &[u32; 8]) -> H256 {
+	let mut bytes = [0u8; 32];
+	for (i, word) in words.iter().enumerate() {
+		for b in 0..4 {
+			bytes[4 * i + b] = (word >> (8 * b)) as u8;
+		}
+	}
+	bytes
+}
+
+/// Interprets a mix hash as the digest words it was created from.
+pub fn bytes_to_words(bytes: &H256) -> [u32; 8] {
+	let mut words = [0u32; 8];
+	for (i, word) in words.iter_mut().enumerate() {
+		for b in 0..4 {
+			*word |= (bytes[4 * i + b] as u32) << (8 * b);
+		}
+	}
+	words
+}
+
+#[inline]
+fn fnv1a_hash(h: &mut u32, d: u32) -> u32 {
+	*h = (*h ^ d).wrapping_mul(FNV_PRIME);
+	*h
+}
+
+/// KISS99 pseudo-random number generator.
+struct Kiss99 {
+	z: u32,
+	w: u32,
+	jsr: u32,
+	jcong: u32,
+}
+
+impl Kiss99 {
+	fn new(z: u32, w: u32, jsr: u32, jcong: u32) -> Kiss99 {
+		Kiss99 { z, w, jsr, jcong }
+	}
+
+	#[inline]
+	fn next_u32(&mut self) -> u32 {
+		self.z = 36969u32.wrapping_mul(self.z & 65535).wrapping_add(self.z >> 16);
+		self.w = 18000u32.wrapping_mul(self.w & 65535).wrapping_add(self.w >> 16);
+		let mwc = (self.z << 16).wrapping_add(self.w);
+		self.jsr ^= self.jsr << 17;
+		self.jsr ^= self.jsr >> 13;
+		self.jsr ^= self.jsr << 5;
+		self.jcong = 69069u32.wrapping_mul(self.jcong).wrapping_add(1234567);
+
+		(mwc ^ self.jcong).wrapping_add(self.jsr)
+	}
+}
+
+fn fill_mix(seed: u64, lane_id: u32) -> [u32; PROGPOW_REGS] {
+	// Use FNV to expand the per-warp seed to per-lane
+	// Use KISS to expand the per-lane seed to fill mix
+	let mut fnv_hash = FNV_HASH;
+	let mut rnd = Kiss99::new(
+		fnv1a_hash(&mut fnv_hash, seed as u32),
+		fnv1a_hash(&mut fnv_hash, (seed >> 32) as u32),
+		fnv1a_hash(&mut fnv_hash, lane_id),
+		fnv1a_hash(&mut fnv_hash, lane_id),
+	);
+
+	let mut mix = [0; PROGPOW_REGS];
+	for i in 0..mix.len() {
+		mix[i] = rnd.next_u32();
+	}
+
+	mix
+}
+
+// Merge new data from b into the value in a. Assuming A has high entropy only
+// do ops that retain entropy even if B is low entropy (i.e. do not do A & B)
+#[inline]
+fn merge(a: &mut u32, b: u32, r: u32) {
+	*a = match r % 4 {
+		0 => a.wrapping_mul(33).wrapping_add(b),
+		1 => (*a ^ b).wrapping_mul(33),
+		// prevent rotate by 0 which is a NOP
+		2 => a.rotate_left(((r >> 16) % 31) + 1) ^ b,
+		_ => a.rotate_right(((r >> 16) % 31) + 1) ^ b,
+	}
+}
+
+// Random math between two input values
+#[inline]
+fn math(a: u32, b: u32, r: u32) -> u32 {
+	match r % 11 {
+		0 => a.wrapping_add(b),
+		1 => a.wrapping_mul(b),
+		2 => ((a as u64).wrapping_mul(b as u64) >> 32) as u32,
+		3 => if a < b { a } else { b },
+		4 => a.rotate_left(b),
+		5 => a.rotate_right(b),
+		6 => a & b,
+		7 => a | b,
+		8 => a ^ b,
+		9 => a.leading_zeros() + b.leading_zeros(),
+		_ => a.count_ones() + b.count_ones(),
+	}
+}
+
+fn progpow_init(seed: u64) -> (Kiss99, [usize; PROGPOW_REGS], [usize; PROGPOW_REGS]) {
+	let mut fnv_hash = FNV_HASH;
+	let mut rnd = Kiss99::new(
+		fnv1a_hash(&mut fnv_hash, seed as u32),
+		fnv1a_hash(&mut fnv_hash, (seed >> 32) as u32),
+		fnv1a_hash(&mut fnv_hash, seed as u32),
+		fnv1a_hash(&mut fnv_hash, (seed >> 32) as u32),
+	);
+
+	// Create a random sequence of mix destinations for merge() and mix sources
+	// for cache reads guaranteeing every destination merged once and no
+	// duplicate cache reads, which could be optimized away. Uses Fisher-Yates
+	// shuffle.
+	let mut mix_seq_dst = [0usize; PROGPOW_REGS];
+	let mut mix_seq_cache = [0usize; PROGPOW_REGS];
+	for i in 0..PROGPOW_REGS {
+		mix_seq_dst[i] = i;
+		mix_seq_cache[i] = i;
+	}
+
+	for i in (1..PROGPOW_REGS).rev() {
+		let j = rnd.next_u32() as usize % (i + 1);
+		mix_seq_dst.swap(i, j);
+
+		let j = rnd.next_u32() as usize % (i + 1);
+		mix_seq_cache.swap(i, j);
+	}
+
+	(rnd, mix_seq_dst, mix_seq_cache)
+}
+
+fn progpow_loop(
+	seed: u64,
+	loop_: usize,
+	mix: &mut [[u32; PROGPOW_REGS]; PROGPOW_LANES],
+	cache: &[Node],
+	c_dag: &CDag,
+	data_size: usize,
+) {
+	// All lanes share a base address for the global load. Global offset uses
+	// mix[0] to guarantee it depends on the load result.
+	let dag_addr_base = mix[loop_ % PROGPOW_LANES][0] as usize % data_size;
+
+	// 256 bytes of dag data, fetched as four 64-byte DAG nodes.
+	let mut dag_item = [0u32; PROGPOW_LANES * PROGPOW_DAG_LOADS];
+	for (i, words) in dag_item.chunks_mut(16).enumerate() {
+		let node = calculate_dag_item((dag_addr_base * PROGPOW_DAG_LOADS + i) as u32, cache);
+		words.copy_from_slice(node.as_words());
+	}
+
+	// Initialize the program seed and sequences
+	// When mining these are evaluated on the CPU and compiled away
+	let (mut rnd, mix_seq_dst, mix_seq_cache) = progpow_init(seed);
+	let mut mix_seq_dst_cnt = 0;
+	let mut mix_seq_cache_cnt = 0;
+
+	for i in 0..PROGPOW_CNT_MATH {
+		if i < PROGPOW_CNT_CACHE {
+			// Cached memory access, lanes access random 32-bit locations
+			// within the first portion of the DAG
+			let src = mix_seq_cache[mix_seq_cache_cnt % PROGPOW_REGS];
+			mix_seq_cache_cnt += 1;
+			let dst = mix_seq_dst[mix_seq_dst_cnt % PROGPOW_REGS];
+			mix_seq_dst_cnt += 1;
+			let sel = rnd.next_u32();
+
+			for l in 0..PROGPOW_LANES {
+				let offset = mix[l][src] as usize % PROGPOW_CACHE_WORDS;
+				merge(&mut mix[l][dst], c_dag[offset], sel);
+			}
+		}
+
+		// Random math, generate 2 unique sources
+		let src_rnd = rnd.next_u32() as usize % (PROGPOW_REGS * (PROGPOW_REGS - 1));
+		let src1 = src_rnd % PROGPOW_REGS;
+		let mut src2 = src_rnd / PROGPOW_REGS;
+		if src2 >= src1 {
+			// src2 is now any reg other than src1
+			src2 += 1;
+		}
+		let sel1 = rnd.next_u32();
+		let dst = mix_seq_dst[mix_seq_dst_cnt % PROGPOW_REGS];
+		mix_seq_dst_cnt += 1;
+		let sel2 = rnd.next_u32();
+
+		for l in 0..PROGPOW_LANES {
+			let data = math(mix[l][src1], mix[l][src2], sel1);
+			merge(&mut mix[l][dst], data, sel2);
+		}
+	}
+
+	// Global load to sequential locations, consumed at the very end of the
+	// loop. Always merge into mix[0] to feed the offset calculation. Which
+	// words of the entry each lane uses is shuffled by the loop counter.
+	for i in 0..PROGPOW_DAG_LOADS {
+		let dst = if i == 0 {
+			0
+		} else {
+			let dst = mix_seq_dst[mix_seq_dst_cnt % PROGPOW_REGS];
+			mix_seq_dst_cnt += 1;
+			dst
+		};
+		let sel = rnd.next_u32();
+
+		for l in 0..PROGPOW_LANES {
+			let index = ((l ^ loop_) % PROGPOW_LANES) * PROGPOW_DAG_LOADS + i;
+			merge(&mut mix[l][dst], dag_item[index], sel);
+		}
+	}
+}
+
+/// Computes the ProgPoW hash of a header.
+/// Returns the final hash, which is compared against the boundary, and the mix digest.
+pub fn progpow(
+	header_hash: &H256,
+	nonce: u64,
+	block_number: u64,
+	cache: &[Node],
+	c_dag: &CDag,
+) -> (H256, H256) {
+	let mut mix = [[0u32; PROGPOW_REGS]; PROGPOW_LANES];
+	let data_size = get_data_size(block_number) / PROGPOW_MIX_BYTES;
+
+	// Initialize mix for all lanes
+	let seed = keccak_f800_short(header_hash, nonce, &[0u32; 8]);
+	for l in 0..PROGPOW_LANES {
+		mix[l] = fill_mix(seed, l as u32);
+	}
+
+	// Execute the randomly generated inner loop
+	let period = block_number / PROGPOW_PERIOD_LENGTH;
+	for i in 0..PROGPOW_CNT_DAG {
+		progpow_loop(period, i, &mut mix, cache, c_dag, data_size);
+	}
+
+	// Reduce mix data to a single per-lane result
+	let mut lane_results = [0u32; PROGPOW_LANES];
+	for l in 0..PROGPOW_LANES {
+		lane_results[l] = FNV_HASH;
+		for i in 0..PROGPOW_REGS {
+			fnv1a_hash(&mut lane_results[l], mix[l][i]);
+		}
+	}
+
+	// Reduce all lanes to a single 256-bit result
+	let mut result = [FNV_HASH; 8];
+	for l in 0..PROGPOW_LANES {
+		fnv1a_hash(&mut result[l % 8], lane_results[l]);
+	}
+
+	let digest = keccak_f800_long(header_hash, seed, &result);
+
+	(digest, words_to_bytes(&result))
+}
+
+/// Generates the cached part of the DAG from the light cache.
+pub fn generate_cdag(cache: &[Node]) -> CDag {
+	let mut c_dag = [0u32; PROGPOW_CACHE_WORDS];
+	for (i, words) in c_dag.chunks_mut(16).enumerate() {
+		let node = calculate_dag_item(i as u32, cache);
+		words.copy_from_slice(node.as_words());
+	}
+	c_dag
+}
+
+#[cfg(test)]
+mod test {
+	use tempdir::TempDir;
+
+	use cache::{NodeCacheBuilder, OptimizeFor};
+	use super::*;
+
+	fn from_hex(hex: &str) -> H256 {
+		let mut bytes = [0u8; 32];
+		for i in 0..32 {
+			bytes[i] = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+		}
+		bytes
+	}
+
+	#[test]
+	fn test_cdag() {
+		let builder = NodeCacheBuilder::new(OptimizeFor::Memory);
+		let tempdir = TempDir::new("").unwrap();
+		let cache = builder.new_cache(tempdir.into_path(), 0);
+
+		let c_dag = generate_cdag(cache.as_ref());
+		assert_eq!(&c_dag[..16], calculate_dag_item(0, cache.as_ref()).as_words());
+		assert_eq!(&c_dag[PROGPOW_CACHE_WORDS - 16..], calculate_dag_item(255, cache.as_ref()).as_words());
+	}
+
+	#[test]
+	fn test_random_merge() {
+		let tests = [
+			(1000000u32, 101u32, 33000101u32),
+			(2000000, 102, 66003366),
+			(3000000, 103, 6000103),
+			(4000000, 104, 2000104),
+			(1000000, 0, 33000000),
+			(2000000, 0, 66000000),
+			(3000000, 0, 6000000),
+			(4000000, 0, 2000000),
+		];
+
+		for (i, &(a, b, expected)) in tests.iter().enumerate() {
+			let mut tmp = a;
+			merge(&mut tmp, b, i as u32);
+			assert_eq!(tmp, expected);
+		}
+	}
+
+	#[test]
+	fn test_random_math() {
+		let tests = [
+			(20u32, 22u32, 42u32),
+			(70000, 80000, 1305032704),
+			(70000, 80000, 1),
+			(1, 2, 1),
+			(3, 10000, 196608),
+			(3, 0, 3),
+			(3, 6, 2),
+			(3, 6, 7),
+			(3, 6, 5),
+			(0, 0xffffffff, 32),
+			(3 << 13, 1 << 5, 3),
+			(22, 20, 42),
+			(80000, 70000, 1305032704),
+			(80000, 70000, 1),
+			(2, 1, 1),
+			(10000, 3, 80000),
+			(0, 3, 0),
+			(6, 3, 2),
+			(6, 3, 7),
+			(6, 3, 5),
+			(0, 0xffffffff, 32),
+			(3 << 13, 1 << 5, 3),
+		];
+
+		for (i, &(a, b, expected)) in tests.iter().enumerate() {
+			assert_eq!(math(a, b, i as u32), expected);
+		}
+	}
+
+	#[test]
+	fn test_spec_merge_and_math() {
+		// https://github.com/ifdefelse/ProgPOW/blob/master/test-vectors.md
+		assert_eq!(math(0x8626BB1F, 0xBBDFBC4E, 0x883E5B49), 0x4206776D);
+		assert_eq!(math(0x3F4BDFAC, 0xD79E414F, 0x36B71236), 0x4C5CB214);
+		assert_eq!(math(0x6D175B7E, 0xC4E89D4C, 0x944ECABB), 0x53E9023F);
+		assert_eq!(math(0x2EDDD94C, 0x7E70CB54, 0x3F472A85), 0x2EDDD94C);
+		assert_eq!(math(0x61AE0E62, 0xE0596B32, 0x3F472A85), 0x61AE0E62);
+		assert_eq!(math(0x8A81E396, 0x3F4BDFAC, 0xCEC46E67), 0x1E3968A8);
+		assert_eq!(math(0x8A81E396, 0x7E70CB54, 0xDBE71FF7), 0x1E3968A8);
+		assert_eq!(math(0xA7352F36, 0xA0EB7045, 0x59E7B9D8), 0xA0212004);
+		assert_eq!(math(0xC89805AF, 0x64291E2F, 0x1BDC84A9), 0xECB91FAF);
+		assert_eq!(math(0x760726D3, 0x79FC6A48, 0xC675CAC5), 0x0FFB4C9B);
+		assert_eq!(math(0x75551D43, 0x3383BA34, 0x2863AD31), 0x00000003);
+		assert_eq!(math(0xEA260841, 0xE92C44B7, 0xF83FFE7D), 0x0000001B);
+
+		let tests = [
+			(0x3B0BB37Du32, 0xA0212004u32, 0x9BD26AB0u32, 0x3CA34321u32),
+			(0x10C02F0D, 0x870FA227, 0xD4F45515, 0x91C1326A),
+			(0x24D2BAE4, 0x0FFB4C9B, 0x7FDBC2F2, 0x2EDDD94C),
+			(0xDA39E821, 0x089C4008, 0x8B6CD8C3, 0x8A81E396),
+		];
+		for &(a, b, r, expected) in tests.iter() {
+			let mut tmp = a;
+			merge(&mut tmp, b, r);
+			assert_eq!(tmp, expected);
+		}
+	}
+
+	#[test]
+	fn test_keccak_f800() {
+		let header_hash = [0u8; 32];
+		assert_eq!(keccak_f800_short(&header_hash, 0, &[0; 8]), 0x5dd431e5fbc604f4);
+	}
+
+	#[test]
+	fn test_fnv1a_hash() {
+		let mut h = FNV_HASH;
+		assert_eq!(fnv1a_hash(&mut h, 0xddd0a47b), 0xd37ee61a);
+		assert_eq!(fnv1a_hash(&mut h, 0xee304846), 0xdedc7ad4);
+		assert_eq!(fnv1a_hash(&mut h, 0x00000000), 0xa9155bbc);
+	}
+
+	#[test]
+	fn test_kiss99() {
+		let mut kiss = Kiss99::new(362436069, 521288629, 123456789, 380116160);
+		assert_eq!(kiss.next_u32(), 769445856);
+		assert_eq!(kiss.next_u32(), 742012328);
+		assert_eq!(kiss.next_u32(), 2121196314);
+		assert_eq!(kiss.next_u32(), 2805620942);
+
+		for _ in 0..100000 - 5 {
+			kiss.next_u32();
+		}
+
+		// the 100000th number
+		assert_eq!(kiss.next_u32(), 941074834);
+	}
+
+	#[test]
+	fn test_progpow_hash() {
+		// https://github.com/ifdefelse/ProgPOW/blob/master/test-vectors.md
+		let tests = [
+			(
+				0u64,
+				"0000000000000000000000000000000000000000000000000000000000000000",
+				0u64,
+				"faeb1be51075b03a4ff44b335067951ead07a3b078539ace76fd56fc410557a3",
+				"63155f732f2bf556967f906155b510c917e48e99685ead76ea83f4eca03ab12b",
+			),
+			(
+				30000,
+				"ffeeddccbbaa9988776655443322110000112233445566778899aabbccddeeff",
+				0x123456789abcdef0,
+				"11f19805c58ab46610ff9c719dcf0a5f18fa2f1605798eef770c47219274767d",
+				"5b7ccd472dbefdd95b895cac8ece67ff0deb5a6bd2ecc6e162383d00c3728ece",
+			),
+		];
+
+		let builder = NodeCacheBuilder::new(OptimizeFor::Memory);
+		let tempdir = TempDir::new("").unwrap();
+		for &(block_number, header_hash, nonce, mix_hash, final_hash) in tests.iter() {
+			let cache = builder.new_cache(tempdir.path().to_path_buf(), block_number);
+			let c_dag = generate_cdag(cache.as_ref());
+			let header_hash = from_hex(header_hash);
+
+			let (digest, result) = progpow(&header_hash, nonce, block_number, cache.as_ref(), &c_dag);
+
+			assert_eq!(result, from_hex(mix_hash));
+			assert_eq!(digest, from_hex(final_hash));
+
+			// the final hash can be recovered from the mix digest alone
+			let seed = keccak_f800_short(&header_hash, nonce, &[0; 8]);
+			assert_eq!(keccak_f800_long(&header_hash, seed, &bytes_to_words(&result)), digest);
+		}
+	}
+}
@@ -635,6 +635,8 @@ impl HeaderChain {
 			BlockId::Latest => {
 				Some(self.best_block.read().hash)
 			}
+			// finality isn't tracked by light clients.
+			BlockId::Finalized => None,
 		}
 	}
 
@@ -689,6 +691,7 @@ impl HeaderChain {
 
 				load_from_db(hash)
 			}
+			BlockId::Finalized => None,
 		}
 	}
 
@@ -710,7 +713,8 @@ impl HeaderChain {
 				if self.best_block.read().number < num { return None }
 				candidates.get(&num).map(|era| era.candidates[0].total_difficulty)
 			}
-			BlockId::Latest => Some(self.best_block.read().total_difficulty)
+			BlockId::Latest => Some(self.best_block.read().total_difficulty),
+			BlockId::Finalized => None,
 		}
 	}
 
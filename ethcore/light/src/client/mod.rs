@@ -617,4 +617,10 @@ impl<T: ChainDataFetcher> ::ethcore::client::EngineClient for Client<T> {
 	fn block_header(&self, id: BlockId) -> Option<encoded::Header> {
 		Client::block_header(self, id)
 	}
+
+	fn engine_data(&self, _key: &[u8]) -> Option<Vec<u8>> {
+		None
+	}
+
+	fn set_engine_data(&self, _key: &[u8], _value: Vec<u8>) { }
 }
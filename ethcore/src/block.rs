@@ -488,14 +488,15 @@ impl LockedBlock {
 	/// Provide a valid seal in order to turn this into a `SealedBlock`.
 	///
 	/// NOTE: This does not check the validity of `seal` with the engine.
-	pub fn seal(self, engine: &EthEngine, seal: Vec<Bytes>) -> Result<SealedBlock, BlockError> {
+	pub fn seal(self, engine: &EthEngine, seal: Vec<Bytes>) -> Result<SealedBlock, Error> {
 		let expected_seal_fields = engine.seal_fields(self.header());
 		let mut s = self;
 		if seal.len() != expected_seal_fields {
 			return Err(BlockError::InvalidSealArity(
-				Mismatch { expected: expected_seal_fields, found: seal.len() }));
+				Mismatch { expected: expected_seal_fields, found: seal.len() }).into());
 		}
 		s.block.header.set_seal(seal);
+		engine.on_seal_block(&mut s.block)?;
 		s.block.header.compute_hash();
 		Ok(SealedBlock {
 			block: s.block
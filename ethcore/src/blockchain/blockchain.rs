@@ -17,7 +17,7 @@
 //! Blockchain database.
 
 use std::collections::{HashMap, HashSet};
-use std::{mem, io};
+use std::{cmp, mem, io};
 use std::path::Path;
 use std::sync::Arc;
 
@@ -28,6 +28,7 @@ use blockchain::block_info::{BlockInfo, BlockLocation, BranchBecomingCanonChainD
 use blockchain::extras::{BlockReceipts, BlockDetails, TransactionAddress, EPOCH_KEY_PREFIX, EpochTransitions};
 use blockchain::update::{ExtrasUpdate, ExtrasInsert};
 use blooms_db;
+use freezer_db;
 use bytes::Bytes;
 use cache_manager::CacheManager;
 use db::{self, Writable, Readable, CacheUpdatePolicy};
@@ -51,6 +52,9 @@ use types::blockchain_info::BlockChainInfo;
 use types::tree_route::TreeRoute;
 use views::{BlockView, HeaderView};
 
+/// Key under which the number of the earliest block with a body and receipts is stored.
+const HISTORY_TAIL_KEY: &[u8] = b"history";
+
 /// Database backing `BlockChain`.
 pub trait BlockChainDB: Send + Sync {
 	/// Generic key value store.
@@ -62,18 +66,44 @@ pub trait BlockChainDB: Send + Sync {
 	/// Trace blooms database.
 	fn trace_blooms(&self) -> &blooms_db::Database;
 
+	/// Ancient blocks database, if there is one.
+	fn freezer(&self) -> Option<&freezer_db::Database> {
+		None
+	}
+
+	/// Copy the DB into a directory at `path` which doesn't exist yet. Once `backup_frozen`
+	/// has copied the ancient blocks, it can be opened like this one. Writes have to be
+	/// suspended for the copy to be consistent.
+	fn backup(&self, _path: &Path) -> io::Result<()> {
+		Err(io::Error::new(io::ErrorKind::Other, "Database does not support backups"))
+	}
+
+	/// Copy the first `len` ancient blocks into the backup at `path`. Ancient blocks
+	/// can be frozen meanwhile.
+	fn backup_frozen(&self, _path: &Path, _len: u64) -> io::Result<()> {
+		Ok(())
+	}
+
 	/// Restore the DB from the given path
 	fn restore(&self, new_db: &str) -> Result<(), EthcoreError> {
-		// First, close the Blooms databases
+		// First, close the Blooms and freezer databases
 		self.blooms().close()?;
 		self.trace_blooms().close()?;
+		if let Some(freezer) = self.freezer() {
+			freezer.close()?;
+		}
 
 		// Restore the key_value DB
 		self.key_value().restore(new_db)?;
 
-		// Re-open the Blooms databases
+		// Re-open the Blooms and freezer databases. Frozen blocks belong to the replaced
+		// chain, the restored one has all of its blocks in the key_value DB.
 		self.blooms().reopen()?;
 		self.trace_blooms().reopen()?;
+		if let Some(freezer) = self.freezer() {
+			freezer.reopen()?;
+			freezer.clear()?;
+		}
 		Ok(())
 	}
 }
@@ -214,6 +244,9 @@ pub struct BlockChain {
 	// Stores the last block of the last sequence of blocks. `None` if there are no gaps.
 	// This is calculated on start and does not get updated.
 	first_block: Option<H256>,
+	// Stores the number of the earliest block whose body and receipts are kept. `None` if no history has expired.
+	// Only updated with `expire_history`.
+	history_tail: RwLock<Option<BlockNumber>>,
 
 	// block cache
 	block_headers: RwLock<HashMap<H256, encoded::Header>>,
@@ -279,9 +312,12 @@ impl BlockProvider for BlockChain {
 			}
 		}
 
-		// Read from DB and populate cache
-		let b = self.db.key_value().get(db::COL_HEADERS, hash)
-			.expect("Low level database error when fetching block header data. Some issue with disk?")?;
+		// Read from DB or freezer and populate cache
+		let b = match self.db.key_value().get(db::COL_HEADERS, hash)
+			.expect("Low level database error when fetching block header data. Some issue with disk?") {
+			Some(b) => b.to_vec(),
+			None => self.frozen(hash, freezer_db::Kind::Header)?,
+		};
 
 		let header = encoded::Header::new(decompress(&b, blocks_swapper()).into_vec());
 		let mut write = self.block_headers.write();
@@ -293,6 +329,10 @@ impl BlockProvider for BlockChain {
 
 	/// Get block body data
 	fn block_body(&self, hash: &H256) -> Option<encoded::Body> {
+		if self.is_history_expired(hash) {
+			return None;
+		}
+
 		// Check cache first
 		{
 			let read = self.block_bodies.read();
@@ -309,9 +349,12 @@ impl BlockProvider for BlockChain {
 			}
 		}
 
-		// Read from DB and populate cache
-		let b = self.db.key_value().get(db::COL_BODIES, hash)
-			.expect("Low level database error when fetching block body data. Some issue with disk?")?;
+		// Read from DB or freezer and populate cache
+		let b = match self.db.key_value().get(db::COL_BODIES, hash)
+			.expect("Low level database error when fetching block body data. Some issue with disk?") {
+			Some(b) => b.to_vec(),
+			None => self.frozen(hash, freezer_db::Kind::Body)?,
+		};
 
 		let body = encoded::Body::new(decompress(&b, blocks_swapper()).into_vec());
 		let mut write = self.block_bodies.write();
@@ -330,7 +373,10 @@ impl BlockProvider for BlockChain {
 
 	/// Get the hash of given block's number.
 	fn block_hash(&self, index: BlockNumber) -> Option<H256> {
-		let result = self.db.key_value().read_with_cache(db::COL_EXTRA, &self.block_hashes, &index)?;
+		let result = match self.db.key_value().read_with_cache(db::COL_EXTRA, &self.block_hashes, &index) {
+			Some(hash) => hash,
+			None => self.frozen_hash(index)?,
+		};
 		self.cache_man.lock().note_used(CacheId::BlockHashes(index));
 		Some(result)
 	}
@@ -344,7 +390,20 @@ impl BlockProvider for BlockChain {
 
 	/// Get receipts of block with given hash.
 	fn block_receipts(&self, hash: &H256) -> Option<BlockReceipts> {
-		let result = self.db.key_value().read_with_cache(db::COL_EXTRA, &self.block_receipts, hash)?;
+		if self.is_history_expired(hash) {
+			return None;
+		}
+
+		let result = match self.db.key_value().read_with_cache(db::COL_EXTRA, &self.block_receipts, hash) {
+			Some(receipts) => receipts,
+			None => {
+				let raw = self.frozen(hash, freezer_db::Kind::Receipts)?;
+				if raw.is_empty() {
+					return None;
+				}
+				::rlp::decode(&raw).expect("decode error: the freezer is corrupted or the data structure has changed")
+			},
+		};
 		self.cache_man.lock().note_used(CacheId::BlockReceipts(*hash));
 		Some(result)
 	}
@@ -523,6 +582,7 @@ impl BlockChain {
 
 		let mut bc = BlockChain {
 			first_block: None,
+			history_tail: RwLock::new(None),
 			best_block: RwLock::new(BestBlock {
 				// BestBlock will be overwritten anyway.
 				header: Default::default(),
@@ -654,6 +714,11 @@ impl BlockChain {
 			}
 		}
 
+		// load history tail
+		*bc.history_tail.write() = bc.db.key_value().get(db::COL_EXTRA, HISTORY_TAIL_KEY)
+			.expect("Low level database error when fetching history tail. Some issue with disk?")
+			.map(|raw| ::rlp::decode(&raw).expect("decode error: the database is corrupted or the data structure has changed"));
+
 		bc
 	}
 
@@ -780,11 +845,19 @@ impl BlockChain {
 		assert!(self.pending_best_block.read().is_none());
 
 		let compressed_header = compress(block.header_view().rlp().as_raw(), blocks_swapper());
-		let compressed_body = compress(&Self::block_to_body(block.raw()), blocks_swapper());
 
 		// store block in db
 		batch.put(db::COL_HEADERS, &hash, &compressed_header);
-		batch.put(db::COL_BODIES, &hash, &compressed_body);
+
+		// ancient blocks older than the history tail are stored without body, receipts and transaction addresses.
+		let expired = self.history_tail().map_or(false, |tail| block_number < tail);
+		let (receipts, transaction_hashes) = if expired {
+			(None, Vec::new())
+		} else {
+			let compressed_body = compress(&Self::block_to_body(block.raw()), blocks_swapper());
+			batch.put(db::COL_BODIES, &hash, &compressed_body);
+			(Some(receipts), block.view().transaction_hashes())
+		};
 
 		let maybe_parent = self.block_details(&block_parent_hash);
 
@@ -800,9 +873,9 @@ impl BlockChain {
 			self.prepare_update(batch, ExtrasUpdate {
 				block_hashes: self.prepare_block_hashes_update(&info),
 				block_details: self.prepare_block_details_update(block_parent_hash, &info, false),
-				block_receipts: self.prepare_block_receipts_update(receipts, &info),
+				block_receipts: receipts.map_or_else(HashMap::new, |receipts| self.prepare_block_receipts_update(receipts, &info)),
 				blocks_blooms: self.prepare_block_blooms_update(block.header_view().log_bloom(), &info),
-				transactions_addresses: self.prepare_transaction_addresses_update(block.view().transaction_hashes(), &info),
+				transactions_addresses: self.prepare_transaction_addresses_update(transaction_hashes, &info),
 				info: info,
 				block,
 			}, is_best);
@@ -850,9 +923,9 @@ impl BlockChain {
 			self.prepare_update(batch, ExtrasUpdate {
 				block_hashes: self.prepare_block_hashes_update(&info),
 				block_details: update,
-				block_receipts: self.prepare_block_receipts_update(receipts, &info),
+				block_receipts: receipts.map_or_else(HashMap::new, |receipts| self.prepare_block_receipts_update(receipts, &info)),
 				blocks_blooms: self.prepare_block_blooms_update(block.header_view().log_bloom(), &info),
-				transactions_addresses: self.prepare_transaction_addresses_update(block.view().transaction_hashes(), &info),
+				transactions_addresses: self.prepare_transaction_addresses_update(transaction_hashes, &info),
 				info: info,
 				block,
 			}, is_best);
@@ -1155,6 +1228,228 @@ impl BlockChain {
 		}
 	}
 
+	/// Read data of given kind for the canonical block with given hash from the freezer.
+	fn frozen(&self, hash: &H256, kind: freezer_db::Kind) -> Option<Vec<u8>> {
+		let freezer = self.db.freezer()?;
+		let number = self.block_details(hash)?.number;
+		if self.frozen_hash(number)? != *hash {
+			return None;
+		}
+
+		freezer.get(kind, number)
+			.expect("Low level database error when fetching frozen block data. Some issue with disk?")
+	}
+
+	/// Read hash of the canonical block with given number from the freezer.
+	fn frozen_hash(&self, number: BlockNumber) -> Option<H256> {
+		let hash = self.db.freezer()?.get(freezer_db::Kind::Hash, number)
+			.expect("Low level database error when fetching frozen block hash. Some issue with disk?")?;
+		Some(H256::from_slice(&hash))
+	}
+
+	/// Move the oldest canonical blocks which are at least `threshold` blocks behind the best block
+	/// from the key-value database to the freezer. Headers, bodies, receipts and block hashes are
+	/// moved; block details and transaction addresses stay in the key-value database.
+	///
+	/// At most `max` blocks are moved at once. Returns the number of moved blocks.
+	pub fn freeze(&self, threshold: BlockNumber, max: u64) -> u64 {
+		let freezer = match self.db.freezer() {
+			Some(freezer) => freezer,
+			None => return 0,
+		};
+
+		// only the uninterrupted sequence of blocks starting at genesis can be frozen.
+		let end = self.best_block_number().saturating_sub(threshold);
+		let end = match self.best_ancient_number() {
+			Some(ancient) => cmp::min(end, ancient + 1),
+			None => end,
+		};
+		let from = freezer.len();
+		let to = cmp::min(end, from.saturating_add(max));
+
+		let kv = self.db.key_value();
+		let read = |col, key: &[u8]| kv.get(col, key)
+			.expect("Low level database error when fetching block data. Some issue with disk?")
+			.map(|value| value.to_vec());
+
+		let history_tail = self.history_tail().unwrap_or(0);
+		let mut blocks = Vec::new();
+		for number in from..to {
+			let hash = match self.block_hash(number) {
+				Some(hash) => hash,
+				None => break,
+			};
+			let header = read(db::COL_HEADERS, &hash);
+			// blocks with expired history get empty body and receipts items.
+			let body = match number < history_tail {
+				true => Some(Vec::new()),
+				false => read(db::COL_BODIES, &hash),
+			};
+			// blocks without receipts (e.g. genesis) get an empty item.
+			let receipts = match number < history_tail {
+				true => Vec::new(),
+				false => read(db::COL_EXTRA, &db::Key::<BlockReceipts>::key(&hash)).unwrap_or_default(),
+			};
+			match (header, body) {
+				(Some(header), Some(body)) => blocks.push((number, hash, header, body, receipts)),
+				_ => break,
+			}
+		}
+
+		if blocks.is_empty() {
+			return 0;
+		}
+
+		let appended = freezer.append(from, blocks.iter().map(|&(_, ref hash, ref header, ref body, ref receipts)| {
+			[&hash[..], &header[..], &body[..], &receipts[..]]
+		}));
+		if let Err(e) = appended {
+			warn!(target: "blockchain", "Failed to move ancient blocks to the freezer: {}", e);
+			return 0;
+		}
+
+		// the blocks are safely stored in the freezer, now they can be removed from the database.
+		let mut batch = DBTransaction::new();
+		for &(number, ref hash, _, _, _) in &blocks {
+			batch.delete(db::COL_HEADERS, hash);
+			batch.delete(db::COL_BODIES, hash);
+			batch.delete(db::COL_EXTRA, &db::Key::<BlockReceipts>::key(hash));
+			batch.delete(db::COL_EXTRA, &db::Key::<H256>::key(&number));
+		}
+		kv.write(batch).expect("Low level database error when removing frozen blocks. Some issue with disk?");
+
+		blocks.len() as u64
+	}
+
+	/// Returns the number of the earliest block whose body and receipts are kept,
+	/// or `None` if the history of no block has expired.
+	pub fn history_tail(&self) -> Option<BlockNumber> {
+		*self.history_tail.read()
+	}
+
+	/// Returns true if the body and receipts of the given block have been removed by `expire_history`.
+	fn is_history_expired(&self, hash: &H256) -> bool {
+		match self.history_tail() {
+			Some(tail) => self.block_number(hash).map_or(false, |number| number < tail),
+			None => false,
+		}
+	}
+
+	/// Remove bodies, receipts and transaction addresses of canonical blocks older than `tail`,
+	/// moving the history tail towards it. Blocks already moved to the freezer keep their data
+	/// on disk, but it is not served anymore. Blocks of the chain gap are skipped; they are
+	/// imported without history later.
+	///
+	/// At most `max` blocks are processed at once. Returns the number of blocks whose history expired.
+	pub fn expire_history(&self, tail: BlockNumber, max: u64) -> u64 {
+		let current = self.history_tail().unwrap_or(0);
+		let tail = cmp::min(tail, self.best_block_number());
+		if tail <= current {
+			return 0;
+		}
+
+		let mut from = current;
+		if let (Some(ancient), Some(first)) = (self.best_ancient_number(), self.first_block_number()) {
+			if from > ancient && from < first {
+				from = cmp::min(first, tail);
+			}
+		}
+		let to = cmp::min(tail, from.saturating_add(max));
+		let frozen = self.db.freezer().map_or(0, |freezer| freezer.len());
+
+		let mut batch = DBTransaction::new();
+		let mut hashes = Vec::new();
+		let mut transaction_hashes = Vec::new();
+		for number in from..to {
+			let hash = match self.block_hash(number) {
+				Some(hash) => hash,
+				None => continue,
+			};
+			if let Some(body) = self.block_body(&hash) {
+				transaction_hashes.extend(body.transaction_hashes());
+			}
+			// frozen items can't be removed from the append-only freezer files.
+			if number >= frozen {
+				batch.delete(db::COL_BODIES, &hash);
+				batch.delete(db::COL_EXTRA, &db::Key::<BlockReceipts>::key(&hash));
+			}
+			hashes.push(hash);
+		}
+		for hash in &transaction_hashes {
+			batch.delete(db::COL_EXTRA, &db::Key::<TransactionAddress>::key(hash));
+		}
+		batch.put(db::COL_EXTRA, HISTORY_TAIL_KEY, &::rlp::encode(&to));
+		self.db.key_value().write(batch).expect("Low level database error when expiring history. Some issue with disk?");
+
+		*self.history_tail.write() = Some(to);
+
+		let mut block_bodies = self.block_bodies.write();
+		let mut transaction_addresses = self.transaction_addresses.write();
+		let mut block_receipts = self.block_receipts.write();
+		for hash in &hashes {
+			block_bodies.remove(hash);
+			block_receipts.remove(hash);
+		}
+		for hash in &transaction_hashes {
+			transaction_addresses.remove(hash);
+		}
+
+		to - current
+	}
+
+	/// Makes the canonical block `to` the best block again, like a reorganization to it would.
+	/// The blocks above it stay known, but they are not part of the canonical chain anymore.
+	///
+	/// Returns the hashes of the retracted blocks, or `None` if `to` is not canonical or
+	/// one of the blocks above it is finalized or already moved to the freezer.
+	pub fn rewind(&self, to: H256) -> Option<Vec<H256>> {
+		let number = self.block_number(&to)?;
+		let frozen = self.db.freezer().map_or(0, |freezer| freezer.len());
+		if self.block_hash(number) != Some(to) || number + 1 < frozen {
+			return None;
+		}
+
+		let mut batch = DBTransaction::new();
+		let mut retracted = Vec::new();
+		let mut transaction_hashes = Vec::new();
+		for n in (number + 1)..(self.best_block_number() + 1) {
+			let hash = self.block_hash(n)?;
+			if self.block_details(&hash)?.is_finalized {
+				return None;
+			}
+			if let Some(body) = self.block_body(&hash) {
+				transaction_hashes.extend(body.transaction_hashes());
+			}
+			batch.delete(db::COL_EXTRA, &db::Key::<H256>::key(&n));
+			retracted.push(hash);
+		}
+		for hash in &transaction_hashes {
+			batch.delete(db::COL_EXTRA, &db::Key::<TransactionAddress>::key(hash));
+		}
+
+		let block = self.block(&to)?;
+		let total_difficulty = self.block_details(&to)?.total_difficulty;
+		batch.put(db::COL_EXTRA, b"best", &to);
+		self.db.key_value().write(batch).expect("Low level database error when rewinding. Some issue with disk?");
+
+		let mut best_block = self.best_block.write();
+		let mut block_hashes = self.block_hashes.write();
+		let mut transaction_addresses = self.transaction_addresses.write();
+		*best_block = BestBlock {
+			total_difficulty,
+			header: block.decode_header(),
+			block,
+		};
+		for n in (number + 1)..(number + 1 + retracted.len() as BlockNumber) {
+			block_hashes.remove(&n);
+		}
+		for hash in &transaction_hashes {
+			transaction_addresses.remove(hash);
+		}
+
+		Some(retracted)
+	}
+
 	/// Iterator that lists `first` and then all of `first`'s ancestors, by hash.
 	pub fn ancestry_iter(&self, first: H256) -> Option<AncestryIter> {
 		if self.is_known(&first) {
@@ -1937,6 +2232,153 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn freezes_ancient_blocks() {
+		let genesis = BlockBuilder::genesis();
+		let first_10 = genesis.add_blocks(10);
+		let generator = BlockGenerator::new(vec![first_10]);
+
+		let db = new_db();
+		let receipt = Receipt {
+			outcome: TransactionOutcome::StatusCode(1),
+			gas_used: 21_000.into(),
+			log_bloom: Default::default(),
+			logs: vec![],
+		};
+
+		let mut block_hashes = vec![genesis.last().hash()];
+		{
+			let bc = new_chain(genesis.last().encoded(), db.clone());
+			for block in generator {
+				block_hashes.push(block.hash());
+				insert_block(&db, &bc, block.encoded(), vec![receipt.clone()]);
+			}
+
+			// blocks 0 to 7 are at least 3 blocks behind the best block
+			assert_eq!(bc.freeze(3, 5), 5);
+			assert_eq!(bc.freeze(3, 5), 3);
+			assert_eq!(bc.freeze(3, 5), 0);
+		}
+
+		assert_eq!(db.freezer().unwrap().len(), 8);
+		assert!(db.key_value().get(::db::COL_HEADERS, &block_hashes[7]).unwrap().is_none());
+		assert!(db.key_value().get(::db::COL_HEADERS, &block_hashes[8]).unwrap().is_some());
+
+		let bc = new_chain(genesis.last().encoded(), db.clone());
+		for (number, hash) in block_hashes.iter().enumerate() {
+			assert_eq!(bc.block_hash(number as u64), Some(*hash));
+			assert_eq!(bc.block_header_data(hash).unwrap().hash(), *hash);
+			assert!(bc.block_body(hash).is_some());
+		}
+		assert!(bc.block_receipts(&block_hashes[0]).is_none());
+		assert_eq!(bc.block_receipts(&block_hashes[1]).unwrap().receipts, vec![receipt.clone()]);
+		assert_eq!(bc.block_receipts(&block_hashes[9]).unwrap().receipts, vec![receipt]);
+		assert_eq!(bc.block_hash(11), None);
+	}
+
+	#[test]
+	fn expires_history() {
+		let t1 = Transaction {
+			nonce: 0.into(),
+			gas_price: 0.into(),
+			gas: 100_000.into(),
+			action: Action::Create,
+			value: 100.into(),
+			data: "601080600c6000396000f3006000355415600957005b60203560003555".from_hex().unwrap(),
+		}.sign(&secret(), None);
+
+		let genesis = BlockBuilder::genesis();
+		let b1 = genesis.add_block_with_transactions(iter::once(t1.clone()));
+		let b10 = b1.add_blocks(9);
+		let generator = BlockGenerator::new(vec![b1, b10]);
+
+		let db = new_db();
+		let receipt = Receipt {
+			outcome: TransactionOutcome::StatusCode(1),
+			gas_used: 21_000.into(),
+			log_bloom: Default::default(),
+			logs: vec![],
+		};
+
+		let mut block_hashes = vec![genesis.last().hash()];
+		{
+			let bc = new_chain(genesis.last().encoded(), db.clone());
+			for block in generator {
+				block_hashes.push(block.hash());
+				insert_block(&db, &bc, block.encoded(), vec![receipt.clone()]);
+			}
+			assert_eq!(bc.freeze(8, 10), 2);
+			assert!(bc.transaction_address(&t1.hash()).is_some());
+
+			assert_eq!(bc.expire_history(5, 3), 3);
+			assert_eq!(bc.expire_history(5, 3), 2);
+			assert_eq!(bc.expire_history(5, 3), 0);
+			assert_eq!(bc.history_tail(), Some(5));
+		}
+
+		let bc = new_chain(genesis.last().encoded(), db.clone());
+		assert_eq!(bc.history_tail(), Some(5));
+		assert!(bc.transaction_address(&t1.hash()).is_none());
+		for hash in &block_hashes[..5] {
+			assert!(bc.block_header_data(hash).is_some());
+			assert!(bc.block_body(hash).is_none());
+			assert!(bc.block_receipts(hash).is_none());
+		}
+		assert!(db.key_value().get(::db::COL_BODIES, &block_hashes[4]).unwrap().is_none());
+		assert_eq!(bc.block_receipts(&block_hashes[5]).unwrap().receipts, vec![receipt]);
+
+		// blocks with expired history are frozen without body and receipts.
+		assert_eq!(bc.freeze(3, 10), 5);
+		assert!(bc.block_body(&block_hashes[4]).is_none());
+		assert!(bc.block_body(&block_hashes[6]).is_some());
+		assert!(bc.block_header_data(&block_hashes[3]).is_some());
+	}
+
+	#[test]
+	fn rewinds_to_canonical_block() {
+		let t1 = Transaction {
+			nonce: 0.into(),
+			gas_price: 0.into(),
+			gas: 100_000.into(),
+			action: Action::Create,
+			value: 100.into(),
+			data: "601080600c6000396000f3006000355415600957005b60203560003555".from_hex().unwrap(),
+		}.sign(&secret(), None);
+
+		let genesis = BlockBuilder::genesis();
+		let b3 = genesis.add_blocks(3);
+		let b4 = b3.add_block_with_transactions(iter::once(t1.clone()));
+		let b5 = b4.add_block();
+		let b4b = b3.add_block_with_difficulty(9);
+		let generator = BlockGenerator::new(vec![b3, b4, b5]);
+
+		let db = new_db();
+		let bc = new_chain(genesis.last().encoded(), db.clone());
+		let mut block_hashes = vec![genesis.last().hash()];
+		for block in generator {
+			block_hashes.push(block.hash());
+			insert_block(&db, &bc, block.encoded(), vec![]);
+		}
+		assert!(bc.transaction_address(&t1.hash()).is_some());
+		assert_eq!(bc.rewind(b4b.last().hash()), None);
+
+		assert_eq!(bc.rewind(block_hashes[3]), Some(vec![block_hashes[4], block_hashes[5]]));
+		assert_eq!(bc.best_block_hash(), block_hashes[3]);
+		assert_eq!(bc.best_block_number(), 3);
+		assert_eq!(bc.block_hash(4), None);
+		assert!(bc.transaction_address(&t1.hash()).is_none());
+		assert!(bc.is_known(&block_hashes[5]));
+
+		// the chain continues from the rewound block.
+		insert_block(&db, &bc, b4b.last().encoded(), vec![]);
+		assert_eq!(bc.best_block_hash(), b4b.last().hash());
+		assert_eq!(bc.block_hash(4), Some(b4b.last().hash()));
+
+		let bc = new_chain(genesis.last().encoded(), db.clone());
+		assert_eq!(bc.best_block_hash(), b4b.last().hash());
+		assert_eq!(bc.block_hash(5), None);
+	}
+
 	#[test]
 	fn can_contain_arbitrary_block_sequence() {
 		let bc = generate_dummy_blockchain(50);
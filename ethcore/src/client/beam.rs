@@ -0,0 +1,368 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Background download of the state left out by beam sync.
+//!
+//! Beam sync imports blocks on top of a pivot whose state is fetched from peers on demand.
+//! The `StateFiller` walks the state trie of the best block, account storage and code
+//! included, and requests the entries missing from the database in batches.
+//! A pass which finds nothing to download means the state is complete.
+
+use std::mem;
+use std::time::Instant;
+
+use account_db::combine_key;
+use basic_account::BasicAccount;
+use ethereum_types::H256;
+use hash::{KECCAK_EMPTY, KECCAK_NULL_RLP};
+use journaldb::NodeFetcher;
+use kvdb::{DBValue, KeyValueDB};
+use rlp::{Rlp, DecoderError};
+
+/// Number of entries requested from the network at once.
+const BATCH_SIZE: usize = 384;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+	/// Account trie node, with the nibble path leading to it.
+	Account(Vec<u8>),
+	/// Storage trie node of the account with the given address hash.
+	Storage(H256),
+	/// Contract code.
+	Code,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Item {
+	/// Database key, mangled with the address hash for storage and code.
+	key: H256,
+	kind: Kind,
+}
+
+/// Walks the state trie and requests missing entries.
+pub struct StateFiller {
+	stack: Vec<Item>,
+	waiting: Vec<Item>,
+	root: Option<H256>,
+	fetched: usize,
+	complete: bool,
+}
+
+impl StateFiller {
+	/// Create a new filler; the first pass starts with the next step.
+	pub fn new() -> Self {
+		StateFiller {
+			stack: Vec::new(),
+			waiting: Vec::new(),
+			root: None,
+			fetched: 0,
+			complete: false,
+		}
+	}
+
+	/// Whether a full pass over the state found no missing entries.
+	pub fn is_complete(&self) -> bool {
+		self.complete
+	}
+
+	/// Continue walking the state until `deadline` or until a batch of entries is missing.
+	/// Missing entries are requested from `fetcher` and expanded by a later step, once they
+	/// have been written to `db`. A new pass over the state with root `best_root` is started
+	/// when the previous one is finished.
+	pub fn step(&mut self, db: &KeyValueDB, best_root: H256, fetcher: &NodeFetcher, deadline: Instant) {
+		if self.complete {
+			return;
+		}
+
+		// entries requested by previous steps, the ones still missing are requested again.
+		let mut requests = Vec::new();
+		for item in mem::replace(&mut self.waiting, Vec::new()) {
+			match get(db, &item.key) {
+				Some(value) => {
+					self.expand(&item, &value);
+					self.fetched += 1;
+				},
+				None => {
+					requests.push(item.key);
+					self.waiting.push(item);
+				},
+			}
+		}
+
+		while self.waiting.len() < BATCH_SIZE && Instant::now() < deadline {
+			let item = match self.stack.pop() {
+				Some(item) => item,
+				None if !self.waiting.is_empty() => break,
+				None => {
+					if !self.next_pass(best_root) { break }
+					continue;
+				},
+			};
+
+			match get(db, &item.key) {
+				Some(value) => self.expand(&item, &value),
+				None => {
+					requests.push(item.key);
+					self.waiting.push(item);
+				},
+			}
+		}
+
+		if !requests.is_empty() {
+			trace!(target: "client", "Beam sync: requesting {} state entries", requests.len());
+			fetcher.request(&requests);
+		}
+	}
+
+	// Finish the current pass. Returns false if the state is complete.
+	fn next_pass(&mut self, best_root: H256) -> bool {
+		if self.root.is_some() {
+			info!(target: "client", "Beam sync: state pass finished, {} entries downloaded", self.fetched);
+			if self.fetched == 0 {
+				self.complete = true;
+				return false;
+			}
+		}
+
+		self.root = Some(best_root);
+		self.fetched = 0;
+		if best_root != KECCAK_NULL_RLP {
+			self.stack.push(Item { key: best_root, kind: Kind::Account(Vec::new()) });
+		}
+		true
+	}
+
+	// Queue the entries referenced by the given one.
+	fn expand(&mut self, item: &Item, value: &[u8]) {
+		let result = match item.kind {
+			Kind::Account(ref path) => self.expand_account_node(path, value),
+			Kind::Storage(ref address_hash) => self.expand_storage_node(address_hash, value),
+			Kind::Code => Ok(()),
+		};
+
+		if let Err(e) = result {
+			warn!(target: "client", "Beam sync: invalid state entry {:?}: {}", item.key, e);
+		}
+	}
+
+	fn expand_account_node(&mut self, path: &[u8], node: &[u8]) -> Result<(), DecoderError> {
+		let (children, leaf) = decode_node(node, path)?;
+		for (hash, path) in children {
+			self.stack.push(Item { key: hash, kind: Kind::Account(path) });
+		}
+
+		if let Some((path, value)) = leaf {
+			let address_hash = match nibbles_to_hash(&path) {
+				Some(hash) => hash,
+				None => return Err(DecoderError::Custom("Account key of invalid length")),
+			};
+			let account: BasicAccount = Rlp::new(&value).as_val()?;
+			if account.code_hash != KECCAK_EMPTY {
+				self.stack.push(Item { key: combine_key(&address_hash, &account.code_hash), kind: Kind::Code });
+			}
+			if account.storage_root != KECCAK_NULL_RLP {
+				let key = combine_key(&address_hash, &account.storage_root);
+				self.stack.push(Item { key, kind: Kind::Storage(address_hash) });
+			}
+		}
+		Ok(())
+	}
+
+	fn expand_storage_node(&mut self, address_hash: &H256, node: &[u8]) -> Result<(), DecoderError> {
+		let (children, _) = decode_node(node, &[])?;
+		for (hash, _) in children {
+			self.stack.push(Item { key: combine_key(address_hash, &hash), kind: Kind::Storage(*address_hash) });
+		}
+		Ok(())
+	}
+}
+
+/// Children referenced by hash and the leaf (full path and value) of a trie node.
+type DecodedNode = (Vec<(H256, Vec<u8>)>, Option<(Vec<u8>, Vec<u8>)>);
+
+// Decode a trie node found at the nibble path `path`.
+// Inline children can't contain hash references and are skipped.
+fn decode_node(node: &[u8], path: &[u8]) -> Result<DecodedNode, DecoderError> {
+	let rlp = Rlp::new(node);
+	let mut children = Vec::new();
+	let mut leaf = None;
+
+	match rlp.item_count()? {
+		17 => for i in 0..16 {
+			let child = rlp.at(i)?;
+			if child.is_data() && child.size() == 32 {
+				let mut child_path = path.to_vec();
+				child_path.push(i as u8);
+				children.push((child.as_val()?, child_path));
+			}
+		},
+		2 => {
+			let (partial, is_leaf) = decode_hex_prefix(rlp.at(0)?.data()?);
+			let mut child_path = path.to_vec();
+			child_path.extend(partial);
+			let child = rlp.at(1)?;
+			if is_leaf {
+				leaf = Some((child_path, child.data()?.to_vec()));
+			} else if child.is_data() && child.size() == 32 {
+				children.push((child.as_val()?, child_path));
+			}
+		},
+		_ => {},
+	}
+
+	Ok((children, leaf))
+}
+
+// Decode a hex-prefix encoded path into nibbles and the leaf flag.
+fn decode_hex_prefix(data: &[u8]) -> (Vec<u8>, bool) {
+	if data.is_empty() {
+		return (Vec::new(), false);
+	}
+
+	let flag = data[0] >> 4;
+	let mut nibbles = Vec::with_capacity(data.len() * 2);
+	if flag & 1 == 1 {
+		nibbles.push(data[0] & 0x0f);
+	}
+	for byte in &data[1..] {
+		nibbles.push(byte >> 4);
+		nibbles.push(byte & 0x0f);
+	}
+	(nibbles, flag & 2 == 2)
+}
+
+fn get(db: &KeyValueDB, key: &H256) -> Option<DBValue> {
+	db.get(::db::COL_STATE, key).expect("Low-level database error. Some issue with your hard disk?")
+}
+
+fn nibbles_to_hash(nibbles: &[u8]) -> Option<H256> {
+	if nibbles.len() != 64 {
+		return None;
+	}
+
+	let mut hash = H256::new();
+	for (i, pair) in nibbles.chunks(2).enumerate() {
+		hash[i] = (pair[0] << 4) | pair[1];
+	}
+	Some(hash)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::time::{Duration, Instant};
+	use ethereum_types::{H256, U256};
+	use hash::keccak;
+	use hashdb::HashDB;
+	use journaldb::{self, Algorithm, JournalDB, NodeFetcher};
+	use kvdb::{DBValue, DBTransaction, KeyValueDB};
+	use kvdb_memorydb;
+	use parking_lot::Mutex;
+	use account_db::AccountDBMut;
+	use basic_account::BasicAccount;
+	use trie::TrieMut;
+	use ethtrie::{SecTrieDBMut, TrieDBMut};
+	use super::*;
+
+	struct DbFetcher {
+		source: Arc<KeyValueDB>,
+		queue: Mutex<Vec<H256>>,
+		requests: Mutex<usize>,
+	}
+
+	impl NodeFetcher for DbFetcher {
+		fn request(&self, keys: &[H256]) {
+			*self.requests.lock() += 1;
+			self.queue.lock().extend_from_slice(keys);
+		}
+
+		fn take_fetched(&self) -> Vec<(H256, DBValue)> {
+			self.queue.lock().drain(..)
+				.filter_map(|key| self.source.get(::db::COL_STATE, &key).unwrap().map(|value| (key, value)))
+				.collect()
+		}
+
+		fn pending(&self) -> usize {
+			self.queue.lock().len()
+		}
+	}
+
+	#[test]
+	fn hex_prefix() {
+		assert_eq!(decode_hex_prefix(&[0x20, 0x12]), (vec![1, 2], true));
+		assert_eq!(decode_hex_prefix(&[0x13, 0x45]), (vec![3, 4, 5], false));
+		assert_eq!(decode_hex_prefix(&[0x00]), (vec![], false));
+	}
+
+	#[test]
+	fn downloads_whole_state() {
+		let source: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(::db::NUM_COLUMNS.unwrap()));
+		let mut jdb = journaldb::new(source.clone(), Algorithm::Archive, ::db::COL_STATE);
+		let mut root = H256::new();
+		{
+			let mut accounts = Vec::new();
+			for i in 0..200u64 {
+				let address_hash = keccak(&[i as u8]);
+				let mut storage_root = KECCAK_NULL_RLP;
+				let mut code_hash = KECCAK_EMPTY;
+				if i % 10 == 0 {
+					let mut account_db = AccountDBMut::from_hash(jdb.as_hashdb_mut(), address_hash);
+					{
+						let mut storage = SecTrieDBMut::new(&mut account_db, &mut storage_root);
+						for j in 0..50u8 {
+							storage.insert(&[j], &[j + 1; 40]).unwrap();
+						}
+					}
+					code_hash = account_db.insert(&[i as u8; 100]);
+				}
+				let account = BasicAccount { nonce: i.into(), balance: U256::from(1000), storage_root, code_hash };
+				accounts.push((address_hash, ::rlp::encode(&account)));
+			}
+
+			let mut trie = TrieDBMut::new(jdb.as_hashdb_mut(), &mut root);
+			for (address_hash, account) in accounts {
+				trie.insert(&address_hash, &account).unwrap();
+			}
+		}
+		let mut batch = DBTransaction::new();
+		jdb.inject(&mut batch).unwrap();
+		source.write(batch).unwrap();
+
+		let fetcher = DbFetcher { source: source.clone(), queue: Mutex::new(Vec::new()), requests: Mutex::new(0) };
+		let target: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(::db::NUM_COLUMNS.unwrap()));
+		let mut filler = StateFiller::new();
+		for _ in 0..1000 {
+			filler.step(&*target, root, &fetcher, Instant::now() + Duration::from_secs(1));
+			if filler.is_complete() {
+				break;
+			}
+
+			let mut batch = DBTransaction::new();
+			for (key, value) in fetcher.take_fetched() {
+				batch.put(::db::COL_STATE, &key, &value);
+			}
+			target.write(batch).unwrap();
+		}
+		assert!(filler.is_complete());
+		assert_eq!(fetcher.pending(), 0);
+
+		target.flush().unwrap();
+		let expected: Vec<_> = source.iter(::db::COL_STATE).collect();
+		let actual: Vec<_> = target.iter(::db::COL_STATE).collect();
+		assert_eq!(actual, expected);
+		assert!(*fetcher.requests.lock() > 1);
+	}
+}
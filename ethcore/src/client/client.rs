@@ -16,6 +16,9 @@
 
 use std::collections::{HashSet, BTreeMap, VecDeque};
 use std::cmp;
+use std::mem;
+use std::thread;
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicUsize, AtomicBool, Ordering as AtomicOrdering};
 use std::sync::{Arc, Weak};
@@ -44,10 +47,11 @@ use client::{
 use client::{
 	BlockId, TransactionId, UncleId, TraceId, ClientConfig, BlockChainClient,
 	TraceFilter, CallAnalytics, Mode,
-	ChainNotify, ChainRoute, PruningInfo, ProvingBlockChainClient, EngineInfo, ChainMessageType,
+	ChainNotify, ChainRoute, ChainRouteType, PruningInfo, ProvingBlockChainClient, EngineInfo, ChainMessageType,
 	IoClient, BadBlocks,
 };
 use client::bad_blocks;
+use client::beam::StateFiller;
 use encoded;
 use engines::{EthEngine, EpochTransition, ForkChoice};
 use error::{
@@ -58,6 +62,7 @@ use vm::{EnvInfo, LastHashes};
 use evm::Schedule;
 use executive::{Executive, Executed, TransactOptions, contract_address};
 use factory::{Factories, VmFactory};
+use flat_state::FlatState;
 use header::{BlockNumber, Header, ExtendedHeader};
 use io::IoChannel;
 use log_entry::LocalizedLogEntry;
@@ -93,6 +98,26 @@ const MAX_ANCIENT_BLOCKS_QUEUE_SIZE: usize = 4096;
 const MAX_ANCIENT_BLOCKS_TO_IMPORT: usize = 4;
 const MAX_QUEUE_SIZE_TO_SLEEP_ON: usize = 2;
 const MIN_HISTORY_SIZE: u64 = 8;
+/// Time spent walking the state on each round of the beam sync worker.
+const BEAM_FILL_DURATION: Duration = Duration::from_millis(200);
+/// Interval between the rounds of the beam sync worker.
+const BEAM_FILL_INTERVAL: Duration = Duration::from_millis(100);
+/// Database key of the beam sync pivot, present until the state is complete.
+const BEAM_PIVOT_KEY: &[u8] = b"beam";
+/// Maximal number of recent blocks kept as flat state diffs in memory.
+const FLAT_STATE_DEPTH: u64 = 32;
+/// Time spent generating the flat state on each client tick.
+const FLAT_STATE_GENERATION_DURATION: Duration = Duration::from_secs(1);
+/// Maximal number of blocks moved to the freezer on each client tick.
+const FREEZER_BATCH: u64 = 1024;
+/// Minimal distance from the best block of blocks moved to the freezer.
+const MIN_FREEZER_THRESHOLD: u64 = 1024;
+/// Maximal number of blocks whose history expires on each client tick.
+const HISTORY_EXPIRY_BATCH: u64 = 1024;
+/// Minimal number of recent blocks whose bodies and receipts are kept, enough to create snapshots.
+const MIN_HISTORY_RETENTION: u64 = 30_000;
+/// Maximal number of recent blocks searched for the latest finalized one on startup.
+const MAX_FINALIZED_SEARCH_DEPTH: usize = 10_000;
 
 /// Report on the status of a client.
 #[derive(Default, Clone, Debug, Eq, PartialEq)]
@@ -105,6 +130,8 @@ pub struct ClientReport {
 	pub gas_processed: U256,
 	/// Memory used by state DB
 	pub state_db_mem: usize,
+	/// Memory used by the shared trie node cache
+	pub trie_cache_mem: usize,
 }
 
 impl ClientReport {
@@ -122,11 +149,14 @@ impl<'a> ::std::ops::Sub<&'a ClientReport> for ClientReport {
 	fn sub(mut self, other: &'a ClientReport) -> Self {
 		let higher_mem = ::std::cmp::max(self.state_db_mem, other.state_db_mem);
 		let lower_mem = ::std::cmp::min(self.state_db_mem, other.state_db_mem);
+		let higher_trie_mem = ::std::cmp::max(self.trie_cache_mem, other.trie_cache_mem);
+		let lower_trie_mem = ::std::cmp::min(self.trie_cache_mem, other.trie_cache_mem);
 
 		self.blocks_imported -= other.blocks_imported;
 		self.transactions_applied -= other.transactions_applied;
 		self.gas_processed = self.gas_processed - other.gas_processed;
 		self.state_db_mem = higher_mem - lower_mem;
+		self.trie_cache_mem = higher_trie_mem - lower_trie_mem;
 
 		self
 	}
@@ -167,6 +197,12 @@ struct Importer {
 
 	/// A lru cache of recently detected bad blocks
 	pub bad_blocks: bad_blocks::BadBlocks,
+
+	/// Blocks waiting for state entries requested from the network while beam syncing
+	beam_waiting: Mutex<Vec<PreverifiedBlock>>,
+
+	/// Set when state entries arrived and the waiting blocks can be retried
+	beam_retry: AtomicBool,
 }
 
 /// Blockchain database client backed by a persistent database. Owns and manages a blockchain and a block queue.
@@ -198,6 +234,12 @@ pub struct Client {
 
 	state_db: RwLock<StateDB>,
 
+	/// Source of missing state entries while beam syncing
+	node_fetcher: journaldb::FetcherSlot,
+
+	/// Download of missing state by the beam sync worker, present while beam syncing
+	state_filler: Mutex<Option<StateFiller>>,
+
 	/// Report on the status of client
 	report: RwLock<ClientReport>,
 
@@ -237,6 +279,15 @@ pub struct Client {
 	/// A closure to call when we want to restart the client
 	exit_handler: Mutex<Option<Box<Fn(String) + 'static + Send>>>,
 
+	/// A closure to call when a backup of the database is requested
+	backup_handler: Mutex<Option<Box<Fn(&Client, &Path) -> Result<(), String> + 'static + Send>>>,
+
+	/// Held by background database writers, and exclusively while a backup is taken.
+	maintenance_lock: RwLock<()>,
+
+	/// Latest block finalized by the engine, for engines with finality.
+	finalized_block: RwLock<Option<H256>>,
+
 	importer: Importer,
 }
 
@@ -257,6 +308,8 @@ impl Importer {
 			ancient_verifier: AncientVerifier::new(engine.clone()),
 			engine,
 			bad_blocks: Default::default(),
+			beam_waiting: Mutex::new(Vec::new()),
+			beam_retry: AtomicBool::new(false),
 		})
 	}
 
@@ -275,14 +328,30 @@ impl Importer {
 			let mut import_results = Vec::with_capacity(max_blocks_to_import);
 
 			let _import_lock = self.import_lock.lock();
-			let blocks = self.block_queue.drain(max_blocks_to_import);
+			let blocks = {
+				let mut beam_waiting = self.beam_waiting.lock();
+				if beam_waiting.is_empty() {
+					self.block_queue.drain(max_blocks_to_import)
+				} else if self.beam_retry.swap(false, AtomicOrdering::SeqCst) {
+					mem::replace(&mut *beam_waiting, Vec::new())
+				} else {
+					// blocks are imported in order, the first one still waits for its state.
+					return 0;
+				}
+			};
 			if blocks.is_empty() {
 				return 0;
 			}
 			trace_time!("import_verified_blocks");
 			let start = Instant::now();
+			let mut beam_waiting = Vec::new();
 
 			for block in blocks {
+				if !beam_waiting.is_empty() {
+					beam_waiting.push(block);
+					continue;
+				}
+
 				let header = block.header.clone();
 				let bytes = block.bytes.clone();
 				let hash = header.hash();
@@ -293,6 +362,11 @@ impl Importer {
 					continue;
 				}
 
+				let missed_lookups = journaldb::missed_lookups();
+				let retry = match client.config.beam_sync {
+					true => Some(block.clone()),
+					false => None,
+				};
 				match self.check_and_lock_block(block, client) {
 					Ok(closed_block) => {
 						if self.engine.is_proposal(&header) {
@@ -310,12 +384,24 @@ impl Importer {
 						}
 					},
 					Err(err) => {
+						// state missing while beam syncing was requested, try again once it arrives.
+						if journaldb::missed_lookups() != missed_lookups {
+							if let Some(block) = retry {
+								debug!(target: "client", "Block #{} ({}) is waiting for state: {:?}", header.number(), hash, err);
+								beam_waiting.push(block);
+								continue;
+							}
+						}
 						self.bad_blocks.report(bytes, format!("{:?}", err));
 						invalid_blocks.insert(hash);
 					},
 				}
 			}
 
+			if !beam_waiting.is_empty() {
+				*self.beam_waiting.lock() = beam_waiting;
+			}
+
 			let imported = imported_blocks.len();
 			let invalid_blocks = invalid_blocks.into_iter().collect::<Vec<H256>>();
 
@@ -567,6 +653,17 @@ impl Importer {
 		client.db.read().key_value().write_buffered(batch);
 		chain.commit();
 
+		// finalized ancestors of a canon block are canon as well.
+		if is_canon {
+			let latest_finalized = finalized.iter()
+				.filter_map(|hash| chain.block_number(hash).map(|number| (number, *hash)))
+				.max_by_key(|&(number, _)| number);
+
+			if let Some((_, hash)) = latest_finalized {
+				*client.finalized_block.write() = Some(hash);
+			}
+		}
+
 		self.check_epoch_end(&header, &finalized, &chain, client);
 
 		client.update_last_hashes(&parent, hash);
@@ -722,7 +819,12 @@ impl Client {
 			accountdb: Default::default(),
 		};
 
-		let journal_db = journaldb::new(db.key_value().clone(), config.pruning, ::db::COL_STATE);
+		if config.beam_sync && !config.pruning.is_stable() {
+			bail!("Beam sync requires the archive or fast pruning algorithm");
+		}
+
+		let node_fetcher: journaldb::FetcherSlot = Default::default();
+		let journal_db = Self::journal_db(&config, db.key_value().clone(), &node_fetcher);
 		let mut state_db = StateDB::new(journal_db, config.state_cache_size);
 		if state_db.journal_db().is_empty() {
 			// Sets the correct state root.
@@ -751,12 +853,28 @@ impl Client {
 			warn!("State root not found for block #{} ({:x})", chain.best_block_number(), chain.best_block_hash());
 		}
 
+		let state_db = state_db
+			.with_flat_state(Self::flat_state(&config, history, db.key_value().clone(), &chain))
+			.with_node_cache(config.trie_cache_size);
+
 		let engine = spec.engine.clone();
 
+		let beam_pivot = db.key_value().get(::db::COL_EXTRA, BEAM_PIVOT_KEY)?;
+		let state_filler = match beam_pivot {
+			Some(_) if config.beam_sync => Some(StateFiller::new()),
+			Some(_) => {
+				warn!(target: "client", "State of the beam sync pivot is incomplete. Restart with beam sync enabled to download it.");
+				None
+			},
+			None => None,
+		};
+
 		let awake = match config.mode { Mode::Dark(..) | Mode::Off => false, _ => true };
 
 		let importer = Importer::new(&config, engine.clone(), message_channel.clone(), miner)?;
 
+		let finalized_block = Self::find_finalized_block(&*engine, &chain);
+
 		let registrar_address = engine.additional_params().get("registrar").and_then(|s| Address::from_str(s).ok());
 		if let Some(ref addr) = registrar_address {
 			trace!(target: "client", "Found registrar at {}", addr);
@@ -773,6 +891,8 @@ impl Client {
 			pruning: config.pruning.clone(),
 			db: RwLock::new(db.clone()),
 			state_db: RwLock::new(state_db),
+			node_fetcher,
+			state_filler: Mutex::new(state_filler),
 			report: RwLock::new(Default::default()),
 			io_channel: RwLock::new(message_channel),
 			notify: RwLock::new(Vec::new()),
@@ -787,6 +907,9 @@ impl Client {
 			on_user_defaults_change: Mutex::new(None),
 			registrar_address,
 			exit_handler: Mutex::new(None),
+			backup_handler: Mutex::new(None),
+			maintenance_lock: RwLock::new(()),
+			finalized_block: RwLock::new(finalized_block),
 			importer,
 			config,
 		});
@@ -830,6 +953,20 @@ impl Client {
 			}
 		}
 
+		// store fetched state and download the rest of it off the client IO threads.
+		if client.config.beam_sync {
+			let weak = Arc::downgrade(&client);
+			thread::Builder::new()
+				.name("Beam sync".into())
+				.spawn(move || loop {
+					thread::sleep(BEAM_FILL_INTERVAL);
+					match weak.upgrade() {
+						Some(client) => client.fill_beam_state(),
+						None => break,
+					}
+				})?;
+		}
+
 		// ensure buffered changes are flushed.
 		client.db.read().key_value().flush()?;
 		Ok(client)
@@ -860,6 +997,34 @@ impl Client {
 		*self.exit_handler.lock() = Some(Box::new(f));
 	}
 
+	/// Set a closure to call when a backup is requested with `BlockChainClient::backup`.
+	///
+	/// The closure is expected to lay out the backup at the given path and call
+	/// `backup_database` to copy the database itself.
+	pub fn set_backup_handler<F>(&self, f: F) where F: Fn(&Client, &Path) -> Result<(), String> + 'static + Send {
+		*self.backup_handler.lock() = Some(Box::new(f));
+	}
+
+	/// Copy the database into an empty directory at `path` while the client is running.
+	///
+	/// Block import and background database writes are suspended while the database is
+	/// flushed and checkpointed, so it holds a consistent chain and state. Ancient blocks
+	/// are copied afterwards.
+	pub fn backup_database(&self, path: &Path) -> Result<(), ::error::Error> {
+		let db = self.db.read().clone();
+		let frozen = {
+			let _import_lock = self.importer.import_lock.lock();
+			let _maintenance_lock = self.maintenance_lock.write();
+
+			db.key_value().flush()?;
+			self.state_db.read().journal_db().flush();
+			db.backup(path)?;
+			db.freezer().map_or(0, |freezer| freezer.len())
+		};
+		db.backup_frozen(path, frozen)?;
+		Ok(())
+	}
+
 	/// Returns engine reference.
 	pub fn engine(&self) -> &EthEngine {
 		&*self.engine
@@ -1077,23 +1242,142 @@ impl Client {
 	/// Get the report.
 	pub fn report(&self) -> ClientReport {
 		let mut report = self.report.read().clone();
-		report.state_db_mem = self.state_db.read().mem_used();
+		let state_db = self.state_db.read();
+		report.state_db_mem = state_db.mem_used();
+		report.trie_cache_mem = state_db.node_cache_size();
 		report
 	}
 
+	fn journal_db(config: &ClientConfig, db: Arc<KeyValueDB>, node_fetcher: &journaldb::FetcherSlot) -> Box<journaldb::JournalDB> {
+		if config.beam_sync {
+			journaldb::new_beam(db, config.pruning, ::db::COL_STATE, node_fetcher.clone())
+		} else {
+			journaldb::new(db, config.pruning, ::db::COL_STATE)
+		}
+	}
+
+	fn flat_state(config: &ClientConfig, history: u64, db: Arc<KeyValueDB>, chain: &BlockChain) -> Option<Arc<FlatState>> {
+		if !config.flat_state {
+			return None;
+		}
+
+		// diffs are flattened into the persisted layer at the state of the deepest diff, which must not be pruned.
+		let flat_state = FlatState::open(db, cmp::min(history, FLAT_STATE_DEPTH));
+		if !flat_state.is_initialized() {
+			let best = chain.best_block_header();
+			flat_state.reset(best.number(), best.hash(), *best.state_root());
+		}
+		Some(Arc::new(flat_state))
+	}
+
+	/// Set the source of state entries missing from the database, used when beam syncing.
+	pub fn set_node_fetcher(&self, fetcher: Arc<journaldb::NodeFetcher>) {
+		*self.node_fetcher.write() = Some(fetcher);
+	}
+
 	/// Tick the client.
 	// TODO: manage by real events.
 	pub fn tick(&self, prevent_sleep: bool) {
 		self.check_garbage();
+		{
+			let _maintenance_lock = self.maintenance_lock.read();
+			self.generate_flat_state();
+			self.freeze_ancient_blocks();
+		}
+		self.expire_history();
 		if !prevent_sleep {
 			self.check_snooze();
 		}
 	}
 
+	// runs on the beam sync worker thread.
+	fn fill_beam_state(&self) {
+		let fetcher = match self.node_fetcher.read().clone() {
+			Some(fetcher) => fetcher,
+			None => return,
+		};
+		let _maintenance_lock = self.maintenance_lock.read();
+		let db = self.db.read().key_value().clone();
+
+		// store the entries received since the last round, the blocks waiting for them can be imported now.
+		let fetched = fetcher.take_fetched();
+		if !fetched.is_empty() {
+			trace!(target: "client", "Beam sync: storing {} fetched state entries", fetched.len());
+			let mut batch = DBTransaction::new();
+			for &(ref key, ref value) in &fetched {
+				batch.put(::db::COL_STATE, key, value);
+			}
+			db.write_buffered(batch);
+
+			self.importer.beam_retry.store(true, AtomicOrdering::SeqCst);
+			if let Err(e) = self.io_channel.read().send(ClientIoMessage::BlockVerified) {
+				debug!(target: "client", "Failed to schedule the import of blocks waiting for state: {:?}", e);
+			}
+		}
+
+		let mut state_filler = self.state_filler.lock();
+		let complete = match *state_filler {
+			Some(ref mut filler) => {
+				let best_root = *self.chain.read().best_block_header().state_root();
+				filler.step(&*db, best_root, &*fetcher, Instant::now() + BEAM_FILL_DURATION);
+				filler.is_complete()
+			},
+			None => return,
+		};
+
+		if complete {
+			info!(target: "client", "Beam sync: state download complete");
+			let mut batch = DBTransaction::new();
+			batch.delete(::db::COL_EXTRA, BEAM_PIVOT_KEY);
+			if let Err(e) = db.write(batch) {
+				warn!(target: "client", "Failed to clear beam sync pivot: {}", e);
+			}
+			*state_filler = None;
+		}
+	}
+
+	fn generate_flat_state(&self) {
+		let state_db = self.state_db.read().boxed_clone();
+		let flat_state = match state_db.flat_state() {
+			Some(flat_state) if !flat_state.is_generated() => flat_state.clone(),
+			_ => return,
+		};
+		if let Err(e) = flat_state.generate(state_db.as_hashdb(), Instant::now() + FLAT_STATE_GENERATION_DURATION) {
+			warn!(target: "client", "Failed to generate flat state: {}", e);
+		}
+	}
+
+	fn freeze_ancient_blocks(&self) {
+		let threshold = match self.config.freezer_threshold {
+			Some(threshold) => cmp::max(threshold, MIN_FREEZER_THRESHOLD),
+			None => return,
+		};
+		let frozen = self.chain.read().freeze(threshold, FREEZER_BATCH);
+		if frozen > 0 {
+			debug!(target: "client", "Moved {} ancient blocks to the freezer", frozen);
+		}
+	}
+
+	fn expire_history(&self) {
+		let retention = match self.config.history_retention {
+			Some(retention) => cmp::max(retention, MIN_HISTORY_RETENTION),
+			None => return,
+		};
+		// ancient blocks are imported under the import lock, keep them from racing with the expiry.
+		let _import_lock = self.importer.import_lock.lock();
+		let chain = self.chain.read();
+		let tail = chain.best_block_number().saturating_sub(retention);
+		let expired = chain.expire_history(tail, HISTORY_EXPIRY_BATCH);
+		if expired > 0 {
+			debug!(target: "client", "Removed history of {} blocks", expired);
+		}
+	}
+
 	fn check_garbage(&self) {
 		self.chain.read().collect_garbage();
 		self.importer.block_queue.collect_garbage();
 		self.tracedb.read().collect_garbage();
+		self.state_db.read().collect_garbage();
 	}
 
 	fn check_snooze(&self) {
@@ -1162,7 +1446,8 @@ impl Client {
 		};
 
 		let processing_threads = self.config.snapshot.processing_threads;
-		snapshot::take_snapshot(&*self.engine, &self.chain.read(), start_hash, db.as_hashdb(), writer, p, processing_threads)?;
+		let codec = self.config.snapshot.codec;
+		snapshot::take_snapshot(&*self.engine, &self.chain.read(), start_hash, db.as_hashdb(), writer, p, processing_threads, codec)?;
 
 		Ok(())
 	}
@@ -1172,19 +1457,43 @@ impl Client {
 		self.history
 	}
 
-	fn block_hash(chain: &BlockChain, id: BlockId) -> Option<H256> {
+	fn chain_block_hash(&self, chain: &BlockChain, id: BlockId) -> Option<H256> {
 		match id {
 			BlockId::Hash(hash) => Some(hash),
 			BlockId::Number(number) => chain.block_hash(number),
 			BlockId::Earliest => chain.block_hash(0),
 			BlockId::Latest => Some(chain.best_block_hash()),
+			BlockId::Finalized => self.finalized_block_hash(chain),
+		}
+	}
+
+	/// Hash of the latest finalized block of the canon chain.
+	fn finalized_block_hash(&self, chain: &BlockChain) -> Option<H256> {
+		match self.engine.finality_depth(chain.best_block_number()) {
+			Some(depth) => chain.block_hash(chain.best_block_number().saturating_sub(depth)),
+			// the genesis block is final before the engine finalizes any other.
+			None => self.finalized_block.read().or_else(|| chain.block_hash(0)),
+		}
+	}
+
+	/// Find the latest block marked as finalized, for engines with finality.
+	/// Only recent blocks are searched: none may be marked after a restore, and an older
+	/// finalized block would be superseded by the engine soon anyway.
+	fn find_finalized_block(engine: &EthEngine, chain: &BlockChain) -> Option<H256> {
+		if engine.finality_depth(chain.best_block_number()).is_some() {
+			return None;
 		}
+
+		chain.ancestry_with_metadata_iter(chain.best_block_hash())
+			.take(MAX_FINALIZED_SEARCH_DEPTH)
+			.find(|header| header.is_finalized)
+			.map(|header| header.header.hash())
 	}
 
 	fn transaction_address(&self, id: TransactionId) -> Option<TransactionAddress> {
 		match id {
 			TransactionId::Hash(ref hash) => self.chain.read().transaction_address(hash),
-			TransactionId::Location(id, index) => Self::block_hash(&self.chain.read(), id).map(|hash| TransactionAddress {
+			TransactionId::Location(id, index) => self.chain_block_hash(&self.chain.read(), id).map(|hash| TransactionAddress {
 				block_hash: hash,
 				index: index,
 			})
@@ -1276,9 +1585,22 @@ impl Client {
 			BlockId::Hash(ref hash) => self.chain.read().block_number(hash),
 			BlockId::Earliest => Some(0),
 			BlockId::Latest => Some(self.chain.read().best_block_number()),
+			BlockId::Finalized => {
+				let chain = self.chain.read();
+				self.finalized_block_hash(&chain).and_then(|hash| chain.block_number(&hash))
+			},
 		}
 	}
 
+	/// Returns true if the body and receipts of the given block have been removed by history expiry.
+	fn is_history_pruned(&self, id: BlockId) -> bool {
+		let tail = match self.chain.read().history_tail() {
+			Some(tail) => tail,
+			None => return false,
+		};
+		self.block_number_ref(&id).map_or(false, |number| number < tail)
+	}
+
 	/// Retrieve a decoded header given `BlockId`
 	///
 	/// This method optimizes access patterns for latest block header
@@ -1310,9 +1632,13 @@ impl snapshot::DatabaseRestore for Client {
 		db.restore(new_db)?;
 
 		let cache_size = state_db.cache_size();
-		*state_db = StateDB::new(journaldb::new(db.key_value().clone(), self.pruning, ::db::COL_STATE), cache_size);
 		*chain = Arc::new(BlockChain::new(self.config.blockchain.clone(), &[], db.clone()));
+		let flat_state = Self::flat_state(&self.config, self.history, db.key_value().clone(), &chain);
+		*state_db = StateDB::new(Self::journal_db(&self.config, db.key_value().clone(), &self.node_fetcher), cache_size)
+			.with_flat_state(flat_state)
+			.with_node_cache(self.config.trie_cache_size);
 		*tracedb = TraceDB::new(self.config.tracing.clone(), db.clone(), chain.clone());
+		*self.finalized_block.write() = Self::find_finalized_block(&*self.engine, &chain);
 		Ok(())
 	}
 }
@@ -1346,7 +1672,7 @@ impl BlockInfo for Client {
 	fn block_header(&self, id: BlockId) -> Option<encoded::Header> {
 		let chain = self.chain.read();
 
-		Self::block_hash(&chain, id).and_then(|hash| chain.block_header_data(&hash))
+		self.chain_block_hash(&chain, id).and_then(|hash| chain.block_header_data(&hash))
 	}
 
 	fn best_block_header(&self) -> Header {
@@ -1356,7 +1682,7 @@ impl BlockInfo for Client {
 	fn block(&self, id: BlockId) -> Option<encoded::Block> {
 		let chain = self.chain.read();
 
-		Self::block_hash(&chain, id).and_then(|hash| chain.block(&hash))
+		self.chain_block_hash(&chain, id).and_then(|hash| chain.block(&hash))
 	}
 
 	fn code_hash(&self, address: &Address, id: BlockId) -> Option<H256> {
@@ -1423,6 +1749,45 @@ impl ImportBlock for Client {
 			Err((_, e)) => Err(e),
 		}
 	}
+
+	fn import_beam_pivot(&self, unverified: Unverified, total_difficulty: U256) -> EthcoreResult<H256> {
+		if !self.config.beam_sync {
+			bail!("Beam sync is disabled");
+		}
+		if self.chain.read().best_block_number() != 0 {
+			bail!("Beam sync pivot can only be imported on top of genesis");
+		}
+
+		let hash = unverified.hash();
+		let header = unverified.header.clone();
+		if total_difficulty < *header.difficulty() {
+			bail!("Total difficulty lower than block difficulty");
+		}
+		self.engine.verify_block_basic(&header)?;
+		self.engine.verify_block_unordered(&header)?;
+
+		let _import_lock = self.importer.import_lock.lock();
+		let mut state_db = self.state_db.write();
+		let mut chain = self.chain.write();
+		let db = self.db.read();
+
+		let mut batch = DBTransaction::new();
+		let parent_td = total_difficulty - *header.difficulty();
+		chain.insert_unordered_block(&mut batch, encoded::Block::new(unverified.bytes), Vec::new(), Some(parent_td), true, false);
+		state_db.journal_under(&mut batch, header.number(), &hash)?;
+		batch.put(::db::COL_EXTRA, BEAM_PIVOT_KEY, &hash);
+		db.key_value().write_buffered(batch);
+		chain.commit();
+		db.key_value().flush()?;
+
+		// reload the chain so that the gap below the pivot is filled with ancient blocks.
+		*chain = Arc::new(BlockChain::new(self.config.blockchain.clone(), &[], db.clone()));
+		*self.tracedb.write() = TraceDB::new(self.config.tracing.clone(), db.clone(), chain.clone());
+		*self.state_filler.lock() = Some(StateFiller::new());
+
+		info!(target: "client", "Beam sync pivot #{} ({:x}) imported", header.number(), hash);
+		Ok(hash)
+	}
 }
 
 impl StateClient for Client {
@@ -1581,7 +1946,11 @@ impl BlockChainClient for Client {
 
 	fn replay_block_transactions(&self, block: BlockId, analytics: CallAnalytics) -> Result<Box<Iterator<Item = (H256, Executed)>>, CallError> {
 		let mut env_info = self.env_info(block).ok_or(CallError::StatePruned)?;
-		let body = self.block_body(block).ok_or(CallError::StatePruned)?;
+		let body = match self.block_body(block) {
+			Some(body) => body,
+			None if self.is_history_pruned(block) => return Err(CallError::HistoryPruned),
+			None => return Err(CallError::StatePruned),
+		};
 		let mut state = self.state_at_beginning(block).ok_or(CallError::StatePruned)?;
 		let txs = body.transactions();
 		let engine = self.engine.clone();
@@ -1649,6 +2018,53 @@ impl BlockChainClient for Client {
 		}
 	}
 
+	fn backup(&self, path: &Path) -> Result<(), String> {
+		trace!(target: "client", "Client::backup({})", path.display());
+		match *self.backup_handler.lock() {
+			Some(ref h) => (*h)(self, path),
+			None => Err("Backups are not supported by this client".into()),
+		}
+	}
+
+	fn revert_to(&self, id: BlockId) -> Result<(), String> {
+		if !self.engine.can_seal_on_demand() {
+			return Err(format!("The chain of the {} engine can't be reverted", self.engine.name()));
+		}
+
+		let hash = self.block_hash(id).ok_or_else(|| "Unknown block".to_owned())?;
+		if self.state_at(BlockId::Hash(hash)).is_none() {
+			return Err("The state of the block is not available".into());
+		}
+
+		let retracted = {
+			let _import_lock = self.importer.import_lock.lock();
+			let retracted = self.chain.read().rewind(hash)
+				.ok_or_else(|| "Only unfinalized blocks of the canonical chain can be reverted to".to_owned())?;
+			self.state_db.write().sync_cache(&[], &retracted, false);
+			retracted
+		};
+		info!(target: "client", "Reverted the chain to {}, {} blocks retracted", hash, retracted.len());
+
+		// the transactions of the retracted blocks are dropped rather than returned to the queue.
+		self.importer.miner.chain_new_blocks(self, &[], &[], &[hash], &[], true);
+
+		// retracted from the former best block down, as a reorganization would.
+		let route = ChainRoute::new(retracted.into_iter().rev().map(|hash| (hash, ChainRouteType::Retracted)).collect());
+		self.notify(|notify| {
+			notify.new_blocks(
+				vec![],
+				vec![],
+				route.clone(),
+				vec![],
+				vec![],
+				Duration::from_millis(0),
+			);
+		});
+
+		self.db.read().key_value().flush().expect("DB flush failed.");
+		Ok(())
+	}
+
 	fn block_number(&self, id: BlockId) -> Option<BlockNumber> {
 		self.block_number_ref(&id)
 	}
@@ -1656,12 +2072,12 @@ impl BlockChainClient for Client {
 	fn block_body(&self, id: BlockId) -> Option<encoded::Body> {
 		let chain = self.chain.read();
 
-		Self::block_hash(&chain, id).and_then(|hash| chain.block_body(&hash))
+		self.chain_block_hash(&chain, id).and_then(|hash| chain.block_body(&hash))
 	}
 
 	fn block_status(&self, id: BlockId) -> BlockStatus {
 		let chain = self.chain.read();
-		match Self::block_hash(&chain, id) {
+		match self.chain_block_hash(&chain, id) {
 			Some(ref hash) if chain.is_known(hash) => BlockStatus::InChain,
 			Some(hash) => self.importer.block_queue.status(&hash).into(),
 			None => BlockStatus::Unknown
@@ -1671,7 +2087,7 @@ impl BlockChainClient for Client {
 	fn block_total_difficulty(&self, id: BlockId) -> Option<U256> {
 		let chain = self.chain.read();
 
-		Self::block_hash(&chain, id).and_then(|hash| chain.block_details(&hash)).map(|d| d.total_difficulty)
+		self.chain_block_hash(&chain, id).and_then(|hash| chain.block_details(&hash)).map(|d| d.total_difficulty)
 	}
 
 	fn storage_root(&self, address: &Address, id: BlockId) -> Option<H256> {
@@ -1680,7 +2096,7 @@ impl BlockChainClient for Client {
 
 	fn block_hash(&self, id: BlockId) -> Option<H256> {
 		let chain = self.chain.read();
-		Self::block_hash(&chain, id)
+		self.chain_block_hash(&chain, id)
 	}
 
 	fn code(&self, address: &Address, state: StateOrBlock) -> Option<Option<Bytes>> {
@@ -1853,7 +2269,9 @@ impl BlockChainClient for Client {
 	}
 
 	fn find_uncles(&self, hash: &H256) -> Option<Vec<H256>> {
-		self.chain.read().find_uncle_hashes(hash, self.engine.maximum_uncle_age())
+		let chain = self.chain.read();
+		let number = chain.block_number(hash)?;
+		chain.find_uncle_hashes(hash, self.engine.maximum_uncle_age(number + 1))
 	}
 
 	fn state_data(&self, hash: &H256) -> Option<Bytes> {
@@ -1884,7 +2302,7 @@ impl BlockChainClient for Client {
 		let is_canon = |id| {
 			match id {
 				// If it is referred by number, then it is always on the canon chain.
-				&BlockId::Earliest | &BlockId::Latest | &BlockId::Number(_) => true,
+				&BlockId::Earliest | &BlockId::Latest | &BlockId::Finalized | &BlockId::Number(_) => true,
 				// If it is referred by hash, we see whether a hash -> number -> hash conversion gives us the same
 				// result.
 				&BlockId::Hash(ref hash) => chain.is_canon(hash),
@@ -1918,7 +2336,7 @@ impl BlockChainClient for Client {
 				.collect::<Vec<H256>>()
 		} else {
 			// Otherwise, we use a slower version that finds a link between from_block and to_block.
-			let from_hash = match Self::block_hash(&chain, filter.from_block) {
+			let from_hash = match self.chain_block_hash(&chain, filter.from_block) {
 				Some(val) => val,
 				None => return Err(filter.from_block.clone()),
 			};
@@ -1926,7 +2344,7 @@ impl BlockChainClient for Client {
 				Some(val) => val,
 				None => return Err(BlockId::Hash(from_hash)),
 			};
-			let to_hash = match Self::block_hash(&chain, filter.to_block) {
+			let to_hash = match self.chain_block_hash(&chain, filter.to_block) {
 				Some(val) => val,
 				None => return Err(filter.to_block.clone()),
 			};
@@ -2077,6 +2495,7 @@ impl BlockChainClient for Client {
 		PruningInfo {
 			earliest_chain: self.chain.read().first_block_number().unwrap_or(1),
 			earliest_state: self.state_db.read().journal_db().earliest_era().unwrap_or(0),
+			earliest_history: self.chain.read().history_tail().unwrap_or(0),
 		}
 	}
 
@@ -2207,7 +2626,7 @@ impl ReopenBlock for Client {
 			let h = chain.best_block_hash();
 			// Add new uncles
 			let uncles = chain
-				.find_uncle_hashes(&h, engine.maximum_uncle_age())
+				.find_uncle_hashes(&h, engine.maximum_uncle_age(block.header().number()))
 				.unwrap_or_else(Vec::new);
 
 			for h in uncles {
@@ -2251,7 +2670,7 @@ impl PrepareOpenBlock for Client {
 
 		// Add uncles
 		chain
-			.find_uncle_headers(&h, engine.maximum_uncle_age())
+			.find_uncle_headers(&h, engine.maximum_uncle_age(best_header.number() + 1))
 			.unwrap_or_else(Vec::new)
 			.into_iter()
 			.take(engine.maximum_uncle_count(open_block.header().number()))
@@ -2367,6 +2786,20 @@ impl super::traits::EngineClient for Client {
 	fn block_header(&self, id: BlockId) -> Option<::encoded::Header> {
 		BlockChainClient::block_header(self, id)
 	}
+
+	fn engine_data(&self, key: &[u8]) -> Option<Bytes> {
+		self.db.read().key_value().get(::db::COL_NODE_INFO, key)
+			.expect("Low level database error. Some issue with disk?")
+			.map(|value| value.into_vec())
+	}
+
+	fn set_engine_data(&self, key: &[u8], value: Bytes) {
+		let mut batch = DBTransaction::new();
+		batch.put_vec(::db::COL_NODE_INFO, key, value);
+		// engines write outside of block import, keep them from racing with a backup.
+		let _maintenance_lock = self.maintenance_lock.read();
+		self.db.read().key_value().write(batch).expect("DB flush failed");
+	}
 }
 
 impl ProvingBlockChainClient for Client {
@@ -111,6 +111,8 @@ pub struct ClientConfig {
 	pub verifier_type: VerifierType,
 	/// State db cache-size.
 	pub state_cache_size: usize,
+	/// Maximal size of the trie node cache shared by all state instances. Zero disables it.
+	pub trie_cache_size: usize,
 	/// EVM jump-tables cache size.
 	pub jump_table_size: usize,
 	/// Minimum state pruning history size.
@@ -125,6 +127,17 @@ pub struct ClientConfig {
 	pub max_round_blocks_to_import: usize,
 	/// Snapshot configuration
 	pub snapshot: SnapshotConfiguration,
+	/// Fetch missing state from the network on demand (beam sync).
+	/// Requires the archive or fast pruning algorithm.
+	pub beam_sync: bool,
+	/// Keep a flat copy of the state for fast account and storage lookups.
+	pub flat_state: bool,
+	/// Move blocks this many blocks behind the best block out of the key-value database
+	/// into the append-only freezer files. At least 1024 blocks are always kept.
+	pub freezer_threshold: Option<u64>,
+	/// Remove bodies, receipts and transaction indexes of blocks this many blocks behind
+	/// the best block. At least 30000 blocks are always kept.
+	pub history_retention: Option<u64>,
 }
 
 impl Default for ClientConfig {
@@ -144,6 +157,7 @@ impl Default for ClientConfig {
 			spec_name: "".into(),
 			verifier_type: VerifierType::Canon,
 			state_cache_size: 1 * mb,
+			trie_cache_size: 0,
 			jump_table_size: 1 * mb,
 			history: 64,
 			history_mem: 32 * mb,
@@ -151,6 +165,10 @@ impl Default for ClientConfig {
 			transaction_verification_queue_size: 8192,
 			max_round_blocks_to_import: 12,
 			snapshot: Default::default(),
+			beam_sync: false,
+			flat_state: false,
+			freezer_threshold: None,
+			history_retention: None,
 		}
 	}
 }
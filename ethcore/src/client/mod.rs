@@ -18,6 +18,7 @@
 
 mod ancient_import;
 mod bad_blocks;
+mod beam;
 mod client;
 mod config;
 #[cfg(any(test, feature = "test-helpers"))]
@@ -32,6 +33,7 @@ pub use self::config::{Mode, ClientConfig, DatabaseCompactionProfile, BlockChain
 #[cfg(any(test, feature = "test-helpers"))]
 pub use self::evm_test_client::{EvmTestClient, EvmTestError, TransactResult};
 pub use self::io_message::ClientIoMessage;
+pub use journaldb::{NodeFetcher, is_value_for};
 #[cfg(any(test, feature = "test-helpers"))]
 pub use self::test_client::{TestBlockChainClient, EachBlockWith};
 pub use self::chain_notify::{ChainNotify, ChainRoute, ChainRouteType, ChainMessageType};
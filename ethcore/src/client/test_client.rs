@@ -20,6 +20,7 @@ use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrder};
 use std::sync::Arc;
 use std::collections::{HashMap, BTreeMap};
 use std::mem;
+use std::path::{Path, PathBuf};
 use itertools::Itertools;
 use rustc_hex::FromHex;
 use hash::keccak;
@@ -115,6 +116,12 @@ pub struct TestBlockChainClient {
 	pub history: RwLock<Option<u64>>,
 	/// Is disabled
 	pub disabled: AtomicBool,
+	/// Imported beam sync pivot and its total difficulty.
+	pub beam_pivot: RwLock<Option<(H256, U256)>>,
+	/// Paths of requested backups.
+	pub backups: RwLock<Vec<PathBuf>>,
+	/// Data persisted by the engine.
+	pub engine_data: RwLock<HashMap<Bytes, Bytes>>,
 }
 
 /// Used for generating test client blocks.
@@ -181,7 +188,10 @@ impl TestBlockChainClient {
 			traces: RwLock::new(None),
 			history: RwLock::new(None),
 			disabled: AtomicBool::new(false),
+			beam_pivot: RwLock::new(None),
 			error_on_logs: RwLock::new(None),
+			backups: RwLock::new(Vec::new()),
+			engine_data: RwLock::new(HashMap::new()),
 		};
 
 		// insert genesis hash.
@@ -330,10 +340,18 @@ impl TestBlockChainClient {
 			BlockId::Hash(hash) => Some(hash),
 			BlockId::Number(n) => self.numbers.read().get(&(n as usize)).cloned(),
 			BlockId::Earliest => self.numbers.read().get(&0).cloned(),
-			BlockId::Latest => self.numbers.read().get(&(self.numbers.read().len() - 1)).cloned()
+			BlockId::Latest => self.numbers.read().get(&(self.numbers.read().len() - 1)).cloned(),
+			BlockId::Finalized => self.numbers.read().get(&(self.finalized_block_number() as usize)).cloned(),
 		}
 	}
 
+	/// Number of the latest block final at the finality depth of the engine.
+	fn finalized_block_number(&self) -> BlockNumber {
+		let best_block_number = self.chain_info().best_block_number;
+		let depth = self.spec.engine.finality_depth(best_block_number).unwrap_or(0);
+		best_block_number.saturating_sub(depth)
+	}
+
 	/// Inserts a transaction with given gas price to miners transactions queue.
 	pub fn insert_transaction_with_gas_price_to_queue(&self, gas_price: U256) -> H256 {
 		let keypair = Random.generate().unwrap();
@@ -566,6 +584,12 @@ impl ImportBlock for TestBlockChainClient {
 		}
 		Ok(h)
 	}
+
+	fn import_beam_pivot(&self, unverified: Unverified, total_difficulty: U256) -> EthcoreResult<H256> {
+		let hash = unverified.hash();
+		*self.beam_pivot.write() = Some((hash, total_difficulty));
+		Ok(hash)
+	}
 }
 
 impl Call for TestBlockChainClient {
@@ -611,7 +635,7 @@ impl StateClient for TestBlockChainClient {
 
 impl EngineInfo for TestBlockChainClient {
 	fn engine(&self) -> &EthEngine {
-		unimplemented!()
+		&*self.spec.engine
 	}
 }
 
@@ -713,6 +737,7 @@ impl BlockChainClient for TestBlockChainClient {
 			BlockId::Number(number) => Some(number),
 			BlockId::Earliest => Some(0),
 			BlockId::Latest => Some(self.chain_info().best_block_number),
+			BlockId::Finalized => Some(self.finalized_block_number()),
 			BlockId::Hash(ref h) =>
 				self.numbers.read().iter().find(|&(_, hash)| hash == h).map(|e| *e.0 as u64)
 		}
@@ -738,7 +763,7 @@ impl BlockChainClient for TestBlockChainClient {
 		match id {
 			BlockId::Number(number) if (number as usize) < self.blocks.read().len() => BlockStatus::InChain,
 			BlockId::Hash(ref hash) if self.blocks.read().get(hash).is_some() => BlockStatus::InChain,
-			BlockId::Latest | BlockId::Earliest => BlockStatus::InChain,
+			BlockId::Latest | BlockId::Earliest | BlockId::Finalized => BlockStatus::InChain,
 			_ => BlockStatus::Unknown,
 		}
 	}
@@ -851,6 +876,28 @@ impl BlockChainClient for TestBlockChainClient {
 
 	fn set_spec_name(&self, _: String) { unimplemented!(); }
 
+	fn backup(&self, path: &Path) -> Result<(), String> {
+		self.backups.write().push(path.to_owned());
+		Ok(())
+	}
+
+	fn revert_to(&self, id: BlockId) -> Result<(), String> {
+		let hash = Self::block_hash(self, id).ok_or_else(|| "Unknown block".to_owned())?;
+		let mut numbers = self.numbers.write();
+		let number = match numbers.iter().find(|&(_, h)| *h == hash) {
+			Some((number, _)) => *number,
+			None => return Err("Only blocks of the canonical chain can be reverted to".into()),
+		};
+		let mut blocks = self.blocks.write();
+		for n in (number + 1)..numbers.len() {
+			if let Some(hash) = numbers.remove(&n) {
+				blocks.remove(&hash);
+			}
+		}
+		*self.last_hash.write() = hash;
+		Ok(())
+	}
+
 	fn disable(&self) { self.disabled.store(true, AtomicOrder::Relaxed); }
 
 	fn pruning_info(&self) -> PruningInfo {
@@ -858,6 +905,7 @@ impl BlockChainClient for TestBlockChainClient {
 		PruningInfo {
 			earliest_chain: 1,
 			earliest_state: self.history.read().as_ref().map(|x| best_num - x).unwrap_or(0),
+			earliest_history: 0,
 		}
 	}
 
@@ -940,4 +988,12 @@ impl super::traits::EngineClient for TestBlockChainClient {
 	fn block_header(&self, id: BlockId) -> Option<::encoded::Header> {
 		BlockChainClient::block_header(self, id)
 	}
+
+	fn engine_data(&self, key: &[u8]) -> Option<Bytes> {
+		self.engine_data.read().get(key).cloned()
+	}
+
+	fn set_engine_data(&self, key: &[u8], value: Bytes) {
+		self.engine_data.write().insert(key.to_vec(), value);
+	}
 }
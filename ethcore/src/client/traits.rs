@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::BTreeMap;
+use std::path::Path;
 use std::sync::Arc;
 
 use itertools::Itertools;
@@ -169,6 +170,11 @@ pub trait RegistryInfo {
 pub trait ImportBlock {
 	/// Import a block into the blockchain.
 	fn import_block(&self, block: Unverified) -> EthcoreResult<H256>;
+
+	/// Import a recent block with the given total difficulty as the new best block, without
+	/// its ancestors or parent state. Used by beam sync; the block's state is fetched from the
+	/// network on demand afterwards. Only possible while the chain is at genesis.
+	fn import_beam_pivot(&self, block: Unverified, total_difficulty: U256) -> EthcoreResult<H256>;
 }
 
 /// Provides `call_contract` method
@@ -369,6 +375,12 @@ pub trait BlockChainClient : Sync + Send + AccountData + BlockChain + CallContra
 	/// Set the chain via a spec name.
 	fn set_spec_name(&self, spec_name: String);
 
+	/// Back up the database to the given path while the client is running.
+	fn backup(&self, path: &Path) -> Result<(), String>;
+
+	/// Make the given canonical block the best block again, dropping the blocks above it.
+	fn revert_to(&self, id: BlockId) -> Result<(), String>;
+
 	/// Disable the client from importing blocks. This cannot be undone in this session and indicates
 	/// that a subsystem has reason to believe this executable incapable of syncing the chain.
 	fn disable(&self);
@@ -455,6 +467,12 @@ pub trait EngineClient: Sync + Send + ChainInfo {
 
 	/// Get raw block header data by block id.
 	fn block_header(&self, id: BlockId) -> Option<encoded::Header>;
+
+	/// Get engine data persisted under the given key.
+	fn engine_data(&self, key: &[u8]) -> Option<Bytes>;
+
+	/// Durably persist engine data under the given key, so that it survives restarts.
+	fn set_engine_data(&self, key: &[u8], value: Bytes);
 }
 
 /// Extended client interface for providing proofs of the state.
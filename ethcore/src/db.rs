@@ -19,6 +19,9 @@
 use std::ops::Deref;
 use std::hash::Hash;
 use std::collections::HashMap;
+use std::{io, fs};
+use std::io::Read;
+use std::path::Path;
 use parking_lot::RwLock;
 use kvdb::{DBTransaction, KeyValueDB};
 
@@ -41,8 +44,76 @@ pub const COL_ACCOUNT_BLOOM: Option<u32> = Some(5);
 pub const COL_NODE_INFO: Option<u32> = Some(6);
 /// Column for the light client chain.
 pub const COL_LIGHT_CHAIN: Option<u32> = Some(7);
+/// Column for the flat account and storage state.
+pub const COL_FLAT_STATE: Option<u32> = Some(8);
 /// Number of columns in DB
-pub const NUM_COLUMNS: Option<u32> = Some(8);
+pub const NUM_COLUMNS: Option<u32> = Some(9);
+
+/// Number of attempts to create a checkpoint while RocksDB keeps compacting the database.
+const CHECKPOINT_ATTEMPTS: usize = 5;
+
+/// Create a checkpoint of the RocksDB database in the directory `source` at `target`, which
+/// must not exist. The checkpoint can be opened like the source database.
+///
+/// Table files are never modified, so they are hard linked (or copied if they are on another
+/// filesystem); the manifest, write-ahead logs and options are copied. Writes have to be
+/// flushed and suspended. Background compactions are detected through the manifest and
+/// restart the checkpoint.
+///
+/// RocksDB's own checkpoints are not exposed by `kvdb-rocksdb`, hence the manual approach.
+pub fn checkpoint(source: &Path, target: &Path) -> io::Result<()> {
+	if let Some(parent) = target.parent() {
+		fs::create_dir_all(parent)?;
+	}
+
+	for _ in 0..CHECKPOINT_ATTEMPTS {
+		let manifest = current_manifest(source)?;
+		match link_files(source, target) {
+			Ok(()) => if current_manifest(source)? == manifest {
+				return Ok(());
+			},
+			// a table file was compacted away in the meantime.
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+			Err(e) => return Err(e),
+		}
+
+		debug!(target: "db", "Database was compacted while creating a checkpoint, retrying");
+		fs::remove_dir_all(target)?;
+	}
+
+	Err(io::Error::new(io::ErrorKind::Other, "Database kept changing while creating a checkpoint"))
+}
+
+/// Name and length of the current RocksDB manifest file.
+fn current_manifest(path: &Path) -> io::Result<(String, u64)> {
+	let mut name = String::new();
+	fs::File::open(path.join("CURRENT"))?.read_to_string(&mut name)?;
+	let name = name.trim().to_owned();
+	let len = fs::metadata(path.join(&name))?.len();
+	Ok((name, len))
+}
+
+fn link_files(source: &Path, target: &Path) -> io::Result<()> {
+	fs::create_dir(target)?;
+	for entry in fs::read_dir(source)? {
+		let entry = entry?;
+		if !entry.file_type()?.is_file() {
+			continue;
+		}
+
+		let name = entry.file_name();
+		let to = target.join(&name);
+		let name = name.to_string_lossy();
+		if name.ends_with(".sst") {
+			if fs::hard_link(entry.path(), &to).is_err() {
+				fs::copy(entry.path(), &to)?;
+			}
+		} else if name == "CURRENT" || name == "IDENTITY" || name.ends_with(".log") || name.starts_with("MANIFEST-") || name.starts_with("OPTIONS-") {
+			fs::copy(entry.path(), &to)?;
+		}
+	}
+	Ok(())
+}
 
 /// Modes for updating caches.
 #[derive(Clone, Copy)]
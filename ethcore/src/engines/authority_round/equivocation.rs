@@ -0,0 +1,96 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Evidence of `AuthorityRound` validators sealing two different blocks for the same step.
+//!
+//! The proof reported to the validator set is the RLP list of both sealed headers, from
+//! which anyone can recover the step and the signer of each seal.
+
+use bytes::Bytes;
+use ethereum_types::Address;
+use header::Header;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+
+/// Key of the collected evidence in the engine data of the client.
+pub const EQUIVOCATIONS_KEY: &'static [u8] = b"aura_equivocations";
+
+/// Maximum number of equivocations kept, the oldest ones are dropped first.
+pub const MAX_EQUIVOCATIONS: usize = 128;
+
+/// Two different headers sealed by the same validator for the same step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Equivocation {
+	/// Validator which sealed both headers.
+	pub author: Address,
+	/// Step both headers were sealed for.
+	pub step: u64,
+	/// Header received first.
+	pub first: Header,
+	/// Conflicting header received later.
+	pub second: Header,
+}
+
+impl Equivocation {
+	/// Proof of the misbehaviour reported to the validator set.
+	pub fn proof(&self) -> Bytes {
+		let mut s = RlpStream::new_list(2);
+		s.append(&self.first).append(&self.second);
+		s.out()
+	}
+}
+
+impl Encodable for Equivocation {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(4)
+			.append(&self.author)
+			.append(&self.step)
+			.append(&self.first)
+			.append(&self.second);
+	}
+}
+
+impl Decodable for Equivocation {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		Ok(Equivocation {
+			author: rlp.val_at(0)?,
+			step: rlp.val_at(1)?,
+			first: rlp.val_at(2)?,
+			second: rlp.val_at(3)?,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use header::Header;
+	use rlp::{self, Rlp};
+	use super::Equivocation;
+
+	#[test]
+	fn encodes_proof_and_evidence() {
+		let mut first = Header::default();
+		first.set_seal(vec![rlp::encode(&3u64), vec![0x80]]);
+		let mut second = first.clone();
+		second.set_number(2);
+
+		let equivocation = Equivocation { author: 5.into(), step: 3, first: first.clone(), second: second.clone() };
+		let proof = equivocation.proof();
+		let headers: Vec<Header> = Rlp::new(&proof).as_list().unwrap();
+		assert_eq!(headers, vec![first, second]);
+
+		assert_eq!(rlp::decode::<Equivocation>(&rlp::encode(&equivocation)).unwrap(), equivocation);
+	}
+}
@@ -37,17 +37,27 @@ use hash::keccak;
 use header::{Header, BlockNumber, ExtendedHeader};
 use super::signer::EngineSigner;
 use super::validator_set::{ValidatorSet, SimpleList, new_validator_set};
+use self::equivocation::{EQUIVOCATIONS_KEY, MAX_EQUIVOCATIONS};
 use self::finality::RollingFinality;
+use self::randomness::{PhaseError, RandomnessPhase};
+use ethabi::FunctionOutputDecoder;
 use ethkey::{self, Password, Signature};
 use io::{IoContext, IoHandler, TimerToken, IoService};
 use itertools::{self, Itertools};
+use lru_cache::LruCache;
 use rlp::{encode, Decodable, DecoderError, Encodable, RlpStream, Rlp};
 use ethereum_types::{H256, H520, Address, U128, U256};
 use parking_lot::{Mutex, RwLock};
+use rand::OsRng;
+use transaction::{Action, SignedTransaction, Transaction};
 use types::ancestry_action::AncestryAction;
 use unexpected::{Mismatch, OutOfBounds};
 
+mod equivocation;
 mod finality;
+mod randomness;
+
+pub use self::equivocation::Equivocation;
 
 /// `AuthorityRound` params.
 pub struct AuthorityRoundParams {
@@ -81,10 +91,21 @@ pub struct AuthorityRoundParams {
 	pub empty_steps_transition: u64,
 	/// Number of accepted empty steps.
 	pub maximum_empty_steps: usize,
+	/// Randomness contract validators commit and reveal secrets to.
+	pub randomness_contract_address: Option<Address>,
+	/// Block gas limit contract transition block.
+	pub block_gas_limit_contract_transition: u64,
+	/// Block gas limit contract.
+	pub block_gas_limit_contract_address: Option<Address>,
 }
 
 const U16_MAX: usize = ::std::u16::MAX as usize;
 
+// number of parent blocks to remember the block gas limit contract's answer, or failure, for.
+const GAS_LIMIT_OVERRIDE_CACHE_SIZE: usize = 1000;
+
+use_contract!(block_gas_limit_contract, "res/contracts/block_gas_limit.json");
+
 impl From<ethjson::spec::AuthorityRoundParams> for AuthorityRoundParams {
 	fn from(p: ethjson::spec::AuthorityRoundParams) -> Self {
 		let mut step_duration_usize: usize = p.step_duration.into();
@@ -110,6 +131,9 @@ impl From<ethjson::spec::AuthorityRoundParams> for AuthorityRoundParams {
 			maximum_uncle_count: p.maximum_uncle_count.map_or(0, Into::into),
 			empty_steps_transition: p.empty_steps_transition.map_or(u64::max_value(), |n| ::std::cmp::max(n.into(), 1)),
 			maximum_empty_steps: p.maximum_empty_steps.map_or(0, Into::into),
+			randomness_contract_address: p.randomness_contract_address.map(Into::into),
+			block_gas_limit_contract_transition: p.block_gas_limit_contract_transition.map_or(0, Into::into),
+			block_gas_limit_contract_address: p.block_gas_limit_contract_address.map(Into::into),
 		}
 	}
 }
@@ -409,6 +433,14 @@ pub struct AuthorityRound {
 	maximum_uncle_count: usize,
 	empty_steps_transition: u64,
 	maximum_empty_steps: usize,
+	randomness_contract_address: Option<Address>,
+	block_gas_limit_contract_transition: u64,
+	block_gas_limit_contract_address: Option<Address>,
+	gas_limit_override_cache: Mutex<LruCache<H256, Option<U256>>>,
+	/// Headers of recent steps by their author, used to detect equivocations.
+	sealed_step_headers: RwLock<BTreeMap<(usize, Address), Header>>,
+	/// Evidence of equivocations collected so far.
+	equivocations: RwLock<Vec<Equivocation>>,
 	machine: EthereumMachine,
 }
 
@@ -512,6 +544,16 @@ fn header_empty_steps_raw(header: &Header) -> &[u8] {
 	header.seal().get(2).expect("was checked with verify_block_basic; has 3 fields; qed")
 }
 
+// the hash signed by the author of the header, covering the empty steps included in the seal.
+fn header_signed_hash(header: &Header, empty_steps_transition: u64) -> H256 {
+	let empty_steps_rlp = if header.number() >= empty_steps_transition {
+		Some(header_empty_steps_raw(header))
+	} else {
+		None
+	};
+	header_seal_hash(header, empty_steps_rlp)
+}
+
 // extracts the empty steps from the header seal. should only be called when there are 3 fields in the seal
 // (i.e. header.number() >= self.empty_steps_transition).
 fn header_empty_steps(header: &Header) -> Result<Vec<EmptyStep>, ::rlp::DecoderError> {
@@ -661,6 +703,12 @@ impl AuthorityRound {
 				maximum_uncle_count: our_params.maximum_uncle_count,
 				empty_steps_transition: our_params.empty_steps_transition,
 				maximum_empty_steps: our_params.maximum_empty_steps,
+				randomness_contract_address: our_params.randomness_contract_address,
+				block_gas_limit_contract_transition: our_params.block_gas_limit_contract_transition,
+				block_gas_limit_contract_address: our_params.block_gas_limit_contract_address,
+				gas_limit_override_cache: Mutex::new(LruCache::new(GAS_LIMIT_OVERRIDE_CACHE_SIZE)),
+				sealed_step_headers: RwLock::new(BTreeMap::new()),
+				equivocations: RwLock::new(Vec::new()),
 				machine: machine,
 			});
 
@@ -699,6 +747,68 @@ impl AuthorityRound {
 		})
 	}
 
+	// remembers the signed header of the given step, reporting the author if it already sealed a
+	// different one. should only be called once the header signature was verified.
+	fn detect_equivocation(&self, header: &Header, step: usize, validators: &ValidatorSet, set_number: BlockNumber) {
+		let seal_hash = header_signed_hash(header, self.empty_steps_transition);
+
+		let equivocation = {
+			let mut sealed_step_headers = self.sealed_step_headers.write();
+
+			// keep the headers of the last two rounds of validators.
+			let oldest_step = self.step.inner.load().saturating_sub(2 * validators.count(header.parent_hash()));
+			if step < oldest_step {
+				return;
+			}
+			let recent = sealed_step_headers.split_off(&(oldest_step, Address::zero()));
+			*sealed_step_headers = recent;
+
+			let key = (step, *header.author());
+			let first = sealed_step_headers.get(&key).cloned();
+			match first {
+				None => {
+					sealed_step_headers.insert(key, header.clone());
+					return;
+				},
+				Some(first) => {
+					if header_signed_hash(&first, self.empty_steps_transition) == seal_hash {
+						return;
+					}
+
+					Equivocation { author: *header.author(), step: step as u64, first, second: header.clone() }
+				},
+			}
+		};
+
+		self.report_equivocation(equivocation, set_number, header.number());
+	}
+
+	fn report_equivocation(&self, equivocation: Equivocation, set_number: BlockNumber, block: BlockNumber) {
+		let is_known = self.equivocations.read().iter()
+			.any(|e| e.author == equivocation.author && e.step == equivocation.step);
+		if is_known {
+			return;
+		}
+
+		warn!(target: "engine", "Validator {} sealed two different blocks for step {}: {} and {}",
+			equivocation.author, equivocation.step, equivocation.first.hash(), equivocation.second.hash());
+		self.validators.report_malicious(&equivocation.author, set_number, block, equivocation.proof());
+
+		let encoded = {
+			let mut equivocations = self.equivocations.write();
+			equivocations.push(equivocation);
+			if equivocations.len() > MAX_EQUIVOCATIONS {
+				let excess = equivocations.len() - MAX_EQUIVOCATIONS;
+				equivocations.drain(..excess);
+			}
+			::rlp::encode_list(&*equivocations)
+		};
+
+		if let Some(client) = self.client.read().as_ref().and_then(|weak| weak.upgrade()) {
+			client.set_engine_data(EQUIVOCATIONS_KEY, encoded);
+		}
+	}
+
 	fn empty_steps(&self, from_step: U256, to_step: U256, parent_hash: H256) -> Vec<EmptyStep> {
 		self.empty_steps.lock().iter().filter(|e| {
 			U256::from(e.step) > from_step &&
@@ -942,6 +1052,49 @@ impl Engine<EthereumMachine> for AuthorityRound {
 
 		let score = calculate_score(parent_step.into(), current_step.into(), current_empty_steps_len.into());
 		header.set_difficulty(score);
+
+		match self.gas_limit_override(header) {
+			Ok(Some(gas_limit)) => header.set_gas_limit(gas_limit),
+			Ok(None) => {},
+			Err(e) => warn!(target: "engine", "Unable to apply the block gas limit contract: {}", e),
+		}
+	}
+
+	fn gas_limit_override(&self, header: &Header) -> Result<Option<U256>, Error> {
+		let address = match self.block_gas_limit_contract_address {
+			Some(address) if header.number() >= self.block_gas_limit_contract_transition => address,
+			_ => return Ok(None),
+		};
+
+		let parent_hash = *header.parent_hash();
+		if let Some(gas_limit) = self.gas_limit_override_cache.lock().get_mut(&parent_hash) {
+			return Ok(*gas_limit);
+		}
+
+		let client = match self.client.read().as_ref().and_then(|weak| weak.upgrade()) {
+			Some(client) => client,
+			None => {
+				debug!(target: "engine", "Unable to query block gas limit contract: missing client ref.");
+				return Err(EngineError::RequiresClient.into());
+			},
+		};
+
+		let full_client = match client.as_full_client() {
+			Some(full_client) => full_client,
+			None => return Err(EngineError::RequiresClient.into()),
+		};
+
+		let (data, decoder) = block_gas_limit_contract::functions::block_gas_limit::call();
+		let gas_limit = full_client.call_contract(::client::BlockId::Hash(parent_hash), address, data)
+			.and_then(|output| decoder.decode(&output).map_err(|e| e.to_string()))
+			.map_err(|e| EngineError::FailedSystemCall(format!("Failed to query block gas limit contract: {}", e)))?;
+
+		// a zero limit leaves the limit to the block author's gas targets.
+		let gas_limit = if gas_limit.is_zero() { None } else { Some(gas_limit) };
+		// the answer for a given parent state never changes, while a failed query
+		// (e.g. the parent state isn't available yet) may succeed later.
+		self.gas_limit_override_cache.lock().insert(parent_hash, gas_limit);
+		Ok(gas_limit)
 	}
 
 	fn seals_internally(&self) -> Option<bool> {
@@ -1113,6 +1266,70 @@ impl Engine<EthereumMachine> for AuthorityRound {
 		self.validators.on_epoch_begin(first, &header, &mut call)
 	}
 
+	/// Commit to or reveal our secret in the randomness contract, as the current phase of the
+	/// collection round requires. The nonce is taken from the state of the block, so it should
+	/// only be called once the signer's own transactions were pushed.
+	fn generate_engine_transactions(&self, block: &ExecutedBlock) -> Result<Vec<SignedTransaction>, Error> {
+		let contract_address = match self.randomness_contract_address {
+			Some(address) => address,
+			None => return Ok(Vec::new()),
+		};
+
+		let our_address = match self.signer.read().address() {
+			Some(address) => address,
+			None => return Ok(Vec::new()),
+		};
+
+		let client = match self.client.read().as_ref().and_then(|weak| weak.upgrade()) {
+			Some(client) => client,
+			None => {
+				debug!(target: "engine", "Unable to generate engine transactions: missing client ref.");
+				return Err(EngineError::RequiresClient.into())
+			},
+		};
+
+		let data = {
+			let full_client = match client.as_full_client() {
+				Some(full_client) => full_client,
+				None => return Ok(Vec::new()),
+			};
+
+			// the phase is determined by the state the block is built on.
+			let parent = ::client::BlockId::Hash(*block.header().parent_hash());
+			let call = |address, data| full_client.call_contract(parent, address, data);
+
+			let phase = RandomnessPhase::load(&call, contract_address, our_address)
+				.and_then(|phase| {
+					let mut rng = OsRng::new().map_err(|e| PhaseError::Call(e.to_string()))?;
+					phase.advance(&*self.signer.read(), &mut rng)
+				});
+
+			match phase {
+				Ok(Some(data)) => data,
+				Ok(None) => return Ok(Vec::new()),
+				Err(e) => {
+					warn!(target: "engine", "Skipping randomness contract transaction: {}", e);
+					return Ok(Vec::new());
+				},
+			}
+		};
+
+		let transaction = Transaction {
+			nonce: block.state.nonce(&our_address)?,
+			action: Action::Call(contract_address),
+			gas: *block.header().gas_limit() - *block.header().gas_used(),
+			gas_price: U256::zero(),
+			value: U256::zero(),
+			data: data,
+		};
+
+		let chain_id = self.machine.signing_chain_id(&block.env_info());
+		let signature = self.sign(transaction.hash(chain_id))?;
+		let signed = SignedTransaction::new(transaction.with_signature(signature, chain_id))?;
+
+		Ok(vec![signed])
+	}
+
 	/// Apply the block reward on finalisation of the block.
 	fn on_close_block(&self, block: &mut ExecutedBlock) -> Result<(), Error> {
 		let mut beneficiaries = Vec::new();
@@ -1205,7 +1422,19 @@ impl Engine<EthereumMachine> for AuthorityRound {
 			|| (header.number() >= self.validate_step_transition && step <= parent_step) {
 			trace!(target: "engine", "Multiple blocks proposed for step {}.", parent_step);
 
-			self.validators.report_malicious(header.author(), set_number, header.number(), Default::default());
+			// both blocks are signed by the same author, the pair proves the equivocation.
+			if step == parent_step && parent.author() == header.author()
+				&& verify_external(header, &*validators, self.empty_steps_transition).is_ok() {
+				let equivocation = Equivocation {
+					author: *header.author(),
+					step: step as u64,
+					first: parent.clone(),
+					second: header.clone(),
+				};
+				self.report_equivocation(equivocation, set_number, header.number());
+			} else {
+				self.validators.report_malicious(header.author(), set_number, header.number(), Default::default());
+			}
 			Err(EngineError::DoubleVote(*header.author()))?;
 		}
 
@@ -1254,6 +1483,12 @@ impl Engine<EthereumMachine> for AuthorityRound {
 			}
 		}
 
+		if let Some(gas_limit) = self.gas_limit_override(header)? {
+			if header.gas_limit() != &gas_limit {
+				return Err(From::from(BlockError::InvalidGasLimit(OutOfBounds { min: Some(gas_limit), max: Some(gas_limit), found: *header.gas_limit() })));
+			}
+		}
+
 		Ok(())
 	}
 
@@ -1272,6 +1507,7 @@ impl Engine<EthereumMachine> for AuthorityRound {
 				// we can drop all accumulated empty step messages that are older than this header's step
 				let header_step = header_step(header, self.empty_steps_transition)?;
 				self.clear_empty_steps(header_step.into());
+				self.detect_equivocation(header, header_step, &*validators, set_number);
 			},
 			_ => {},
 		}
@@ -1424,10 +1660,21 @@ impl Engine<EthereumMachine> for AuthorityRound {
 	}
 
 	fn register_client(&self, client: Weak<EngineClient>) {
+		if let Some(data) = client.upgrade().and_then(|c| c.engine_data(EQUIVOCATIONS_KEY)) {
+			match Rlp::new(&data).as_list::<Equivocation>() {
+				Ok(equivocations) => *self.equivocations.write() = equivocations,
+				Err(e) => warn!(target: "engine", "Failed to load the stored equivocations: {}", e),
+			}
+		}
+
 		*self.client.write() = Some(client.clone());
 		self.validators.register_client(client);
 	}
 
+	fn equivocations(&self) -> Vec<Equivocation> {
+		self.equivocations.read().clone()
+	}
+
 	fn set_signer(&self, ap: Arc<AccountProvider>, address: Address, password: Password) {
 		self.signer.write().set(ap, address, password);
 	}
@@ -1436,7 +1683,7 @@ impl Engine<EthereumMachine> for AuthorityRound {
 		Ok(self.signer.read().sign(hash)?)
 	}
 
-	fn snapshot_components(&self) -> Option<Box<::snapshot::SnapshotComponents>> {
+	fn snapshot_components(&self, _block: BlockNumber) -> Option<Box<::snapshot::SnapshotComponents>> {
 		if self.immediate_transitions {
 			None
 		} else {
@@ -1460,6 +1707,10 @@ impl Engine<EthereumMachine> for AuthorityRound {
 
 		finalized.into_iter().map(AncestryAction::MarkFinalized).collect()
 	}
+
+	fn finality_depth(&self, _best_block: BlockNumber) -> Option<BlockNumber> {
+		None
+	}
 }
 
 #[cfg(test)]
@@ -1471,18 +1722,19 @@ mod tests {
 	use ethereum_types::{Address, H520, H256, U256};
 	use ethkey::Signature;
 	use header::Header;
-	use rlp::encode;
+	use rlp::{encode, Rlp};
 	use block::*;
 	use test_helpers::{
 		generate_dummy_client_with_spec_and_accounts, get_temp_state_db,
 		TestNotify
 	};
 	use account_provider::AccountProvider;
+	use client::{PrepareOpenBlock, TestBlockChainClient};
 	use spec::Spec;
 	use transaction::{Action, Transaction};
 	use engines::{Seal, Engine, EngineError, EthEngine};
 	use engines::validator_set::TestSet;
-	use error::{Error, ErrorKind};
+	use error::{BlockError, Error, ErrorKind};
 	use super::{AuthorityRoundParams, AuthorityRound, EmptyStep, SealedEmptyStep, calculate_score};
 
 	#[test]
@@ -1672,6 +1924,9 @@ mod tests {
 			block_reward: Default::default(),
 			block_reward_contract_transition: 0,
 			block_reward_contract: Default::default(),
+			randomness_contract_address: None,
+			block_gas_limit_contract_transition: 0,
+			block_gas_limit_contract_address: None,
 		};
 
 		let aura = {
@@ -1706,6 +1961,76 @@ mod tests {
 		assert_eq!(last_benign.load(AtomicOrdering::SeqCst), 2);
 	}
 
+	#[test]
+	fn collects_equivocation_evidence() {
+		let new_aura = |last_malicious: Arc<AtomicUsize>| {
+			let params = AuthorityRoundParams {
+				step_duration: 1,
+				start_step: Some(3),
+				validators: Box::new(TestSet::new(last_malicious, Default::default())),
+				validate_score_transition: 0,
+				validate_step_transition: 0,
+				immediate_transitions: true,
+				maximum_uncle_count_transition: 0,
+				maximum_uncle_count: 0,
+				empty_steps_transition: u64::max_value(),
+				maximum_empty_steps: 0,
+				block_reward: Default::default(),
+				block_reward_contract_transition: 0,
+				block_reward_contract: Default::default(),
+				randomness_contract_address: None,
+				block_gas_limit_contract_transition: 0,
+				block_gas_limit_contract_address: None,
+			};
+			let machine = ::machine::EthereumMachine::regular(Default::default(), Default::default());
+			AuthorityRound::new(params, machine).unwrap()
+		};
+
+		let last_malicious = Arc::new(AtomicUsize::new(0));
+		let aura = new_aura(last_malicious.clone());
+		let client = Arc::new(TestBlockChainClient::new());
+		aura.register_client(Arc::downgrade(&client) as _);
+
+		let tap = AccountProvider::transient_provider();
+		let addr = tap.insert_account(keccak("0").into(), &"0".into()).unwrap();
+		let sealed_header = |gas_limit: u64| {
+			let mut header: Header = Header::default();
+			header.set_number(5);
+			header.set_author(addr);
+			header.set_gas_limit(gas_limit.into());
+			let signature = tap.sign(addr, Some("0".into()), header.bare_hash()).unwrap();
+			header.set_seal(vec![encode(&3usize), encode(&(&*signature as &[u8]))]);
+			header
+		};
+		let first = sealed_header(222222);
+		let second = sealed_header(333333);
+
+		assert!(aura.verify_block_external(&first).is_ok());
+		assert!(aura.verify_block_external(&first).is_ok());
+		assert_eq!(last_malicious.load(AtomicOrdering::SeqCst), 0);
+		assert!(aura.equivocations().is_empty());
+
+		assert!(aura.verify_block_external(&second).is_ok());
+		assert_eq!(last_malicious.load(AtomicOrdering::SeqCst), 5);
+		let equivocations = aura.equivocations();
+		assert_eq!(equivocations.len(), 1);
+		assert_eq!(equivocations[0].author, addr);
+		assert_eq!(equivocations[0].step, 3);
+		let proof: Vec<Header> = Rlp::new(&equivocations[0].proof()).as_list().unwrap();
+		assert_eq!(proof, vec![first, second.clone()]);
+
+		// Reported only once.
+		last_malicious.store(0, AtomicOrdering::SeqCst);
+		assert!(aura.verify_block_external(&second).is_ok());
+		assert_eq!(last_malicious.load(AtomicOrdering::SeqCst), 0);
+		assert_eq!(aura.equivocations().len(), 1);
+
+		// The evidence is restored from the client.
+		let aura = new_aura(Default::default());
+		aura.register_client(Arc::downgrade(&client) as _);
+		assert_eq!(aura.equivocations(), equivocations);
+	}
+
 	#[test]
 	fn test_uncles_transition() {
 		let last_benign = Arc::new(AtomicUsize::new(0));
@@ -1723,6 +2048,9 @@ mod tests {
 			block_reward: Default::default(),
 			block_reward_contract_transition: 0,
 			block_reward_contract: Default::default(),
+			randomness_contract_address: None,
+			block_gas_limit_contract_transition: 0,
+			block_gas_limit_contract_address: None,
 		};
 
 		let aura = {
@@ -1779,6 +2107,9 @@ mod tests {
 			block_reward: Default::default(),
 			block_reward_contract_transition: 0,
 			block_reward_contract: Default::default(),
+			randomness_contract_address: None,
+			block_gas_limit_contract_transition: 0,
+			block_gas_limit_contract_address: None,
 		};
 
 		let mut c_params = ::spec::CommonParams::default();
@@ -2136,6 +2467,85 @@ mod tests {
 		)
 	}
 
+	#[test]
+	fn block_gas_limit_contract() {
+		let client = generate_dummy_client_with_spec_and_accounts(
+			Spec::new_test_round_block_gas_limit_contract,
+			None,
+		);
+		let engine = client.engine();
+		engine.register_client(Arc::downgrade(&client) as _);
+
+		// the contract demands a gas limit of 0x1000000 regardless of the author's targets.
+		let open_block = client.prepare_open_block(Address::default(), (3141562.into(), 31415620.into()), vec![]).unwrap();
+		assert_eq!(open_block.block().header().gas_limit(), &U256::from(0x1000000));
+
+		let parent_header = Spec::new_test_round_block_gas_limit_contract().genesis_header();
+		let mut header: Header = Header::default();
+		header.set_number(1);
+		header.set_parent_hash(parent_header.hash());
+		header.set_difficulty(calculate_score(U256::from(0), U256::from(1), U256::from(0)));
+		header.set_seal(vec![encode(&1usize), encode(&H520::default())]);
+
+		header.set_gas_limit(0x222222.into());
+		assert!(match engine.verify_block_family(&header, &parent_header) {
+			Err(Error(ErrorKind::Block(BlockError::InvalidGasLimit(_)), _)) => true,
+			_ => false,
+		});
+
+		header.set_gas_limit(0x1000000.into());
+		assert!(engine.verify_block_family(&header, &parent_header).is_ok());
+	}
+
+	#[test]
+	fn rejects_block_if_gas_limit_contract_is_unavailable() {
+		// no client is registered, so the contract can't be queried.
+		let spec = Spec::new_test_round_block_gas_limit_contract();
+		let engine = &*spec.engine;
+		let parent_header = spec.genesis_header();
+
+		let mut header: Header = Header::default();
+		header.set_number(1);
+		header.set_parent_hash(parent_header.hash());
+		header.set_difficulty(calculate_score(U256::from(0), U256::from(1), U256::from(0)));
+		header.set_seal(vec![encode(&1usize), encode(&H520::default())]);
+		header.set_gas_limit(0x222222.into());
+
+		assert!(match engine.verify_block_family(&header, &parent_header) {
+			Err(Error(ErrorKind::Engine(EngineError::RequiresClient), _)) => true,
+			_ => false,
+		});
+	}
+
+	#[test]
+	fn retries_failed_gas_limit_contract_queries() {
+		let spec = Spec::new_test_round_block_gas_limit_contract();
+		let engine = &*spec.engine;
+		let parent_header = spec.genesis_header();
+
+		let mut header: Header = Header::default();
+		header.set_number(1);
+		header.set_parent_hash(parent_header.hash());
+		header.set_difficulty(calculate_score(U256::from(0), U256::from(1), U256::from(0)));
+		header.set_seal(vec![encode(&1usize), encode(&H520::default())]);
+		header.set_gas_limit(0x1000000.into());
+
+		// contract calls of the test client return no data.
+		let failing_client = Arc::new(TestBlockChainClient::new());
+		engine.register_client(Arc::downgrade(&failing_client) as _);
+		assert!(match engine.verify_block_family(&header, &parent_header) {
+			Err(Error(ErrorKind::Engine(EngineError::FailedSystemCall(_)), _)) => true,
+			_ => false,
+		});
+
+		let client = generate_dummy_client_with_spec_and_accounts(
+			Spec::new_test_round_block_gas_limit_contract,
+			None,
+		);
+		engine.register_client(Arc::downgrade(&client) as _);
+		assert!(engine.verify_block_family(&header, &parent_header).is_ok());
+	}
+
 	#[test]
 	fn extra_info_from_seal() {
 		let (spec, tap, accounts) = setup_empty_steps();
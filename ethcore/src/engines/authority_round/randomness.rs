@@ -0,0 +1,205 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! On-chain randomness beacon for `AuthorityRound`.
+//!
+//! The randomness contract splits time into collection rounds, each with a commit phase
+//! followed by a reveal phase. During the commit phase every validator commits the hash of a
+//! fresh random secret, along with the secret encrypted to its own key so it survives restarts.
+//! During the reveal phase it reveals the secret, which the contract combines with the others.
+//!
+//! `RandomnessPhase` is loaded from the contract state for our signer, and `advance` yields the
+//! call to send to the contract, if any.
+
+use std::fmt;
+
+use bytes::Bytes;
+use ethabi::FunctionOutputDecoder;
+use ethereum_types::{Address, H256, U256};
+use ethkey::crypto::{self, ecies};
+use hash::keccak;
+use rand::Rng;
+use engines::signer::EngineSigner;
+
+use_contract!(aura_random, "res/contracts/authority_round_random.json");
+
+/// A constant call to a contract at the given address.
+pub type ContractCall<'a> = Fn(Address, Bytes) -> Result<Bytes, String> + 'a;
+
+/// Our part in the current collection round of the randomness contract.
+#[derive(Debug, PartialEq)]
+pub enum RandomnessPhase {
+	/// Nothing to do until the next phase.
+	Waiting,
+	/// Commit phase, and we haven't committed yet.
+	BeforeCommit,
+	/// Reveal phase, and we have committed but not revealed yet.
+	Reveal {
+		/// Our committed secret hash.
+		our_hash: H256,
+		/// Our secret, encrypted to the signer's public key.
+		our_cipher: Bytes,
+	},
+}
+
+/// Errors while interacting with the randomness contract.
+#[derive(Debug)]
+pub enum PhaseError {
+	/// Calling the contract failed.
+	Call(String),
+	/// No engine signer with an unlocked account is set.
+	MissingSigner,
+	/// Encrypting our secret failed.
+	Encrypt(crypto::Error),
+	/// Decrypting our committed secret failed.
+	Decrypt(String),
+	/// The decrypted secret doesn't match our committed hash.
+	StaleSecret,
+}
+
+impl fmt::Display for PhaseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			PhaseError::Call(ref e) => write!(f, "Randomness contract call failed: {}", e),
+			PhaseError::MissingSigner => write!(f, "No engine signer to commit secrets with"),
+			PhaseError::Encrypt(ref e) => write!(f, "Failed to encrypt secret: {}", e),
+			PhaseError::Decrypt(ref e) => write!(f, "Failed to decrypt committed secret: {}", e),
+			PhaseError::StaleSecret => write!(f, "Committed secret doesn't match its hash"),
+		}
+	}
+}
+
+// call a constant contract function and decode its output.
+fn call_const<D: FunctionOutputDecoder>(call: &ContractCall, contract: Address, (data, decoder): (Bytes, D))
+	-> Result<D::Output, PhaseError>
+{
+	let output = call(contract, data).map_err(PhaseError::Call)?;
+	decoder.decode(&output).map_err(|e| PhaseError::Call(e.to_string()))
+}
+
+impl RandomnessPhase {
+	/// Determine our phase of the current collection round from the contract state.
+	pub fn load(call: &ContractCall, contract: Address, our_address: Address) -> Result<Self, PhaseError> {
+		let round = call_const(call, contract, aura_random::functions::current_collect_round::call())?;
+
+		if call_const(call, contract, aura_random::functions::is_commit_phase::call())? {
+			let committed = call_const(call, contract, aura_random::functions::is_committed::call(round, our_address))?;
+			return Ok(if committed { RandomnessPhase::Waiting } else { RandomnessPhase::BeforeCommit });
+		}
+
+		if !call_const(call, contract, aura_random::functions::is_reveal_phase::call())? {
+			return Ok(RandomnessPhase::Waiting);
+		}
+
+		// nothing to reveal if we missed the commit phase.
+		if !call_const(call, contract, aura_random::functions::is_committed::call(round, our_address))?
+			|| call_const(call, contract, aura_random::functions::sent_reveal::call(round, our_address))?
+		{
+			return Ok(RandomnessPhase::Waiting);
+		}
+
+		Ok(RandomnessPhase::Reveal {
+			our_hash: call_const(call, contract, aura_random::functions::get_commit::call(round, our_address))?,
+			our_cipher: call_const(call, contract, aura_random::functions::get_cipher::call(round, our_address))?,
+		})
+	}
+
+	/// Input of the contract call this phase requires from us, if any.
+	pub fn advance<R: Rng>(self, signer: &EngineSigner, rng: &mut R) -> Result<Option<Bytes>, PhaseError> {
+		match self {
+			RandomnessPhase::Waiting => Ok(None),
+			RandomnessPhase::BeforeCommit => {
+				let mut secret = [0u8; 32];
+				rng.fill_bytes(&mut secret);
+
+				let secret_hash = keccak(&secret[..]);
+				let public = signer.public().ok_or(PhaseError::MissingSigner)?;
+				let cipher = ecies::encrypt(&public, &secret_hash, &secret).map_err(PhaseError::Encrypt)?;
+
+				Ok(Some(aura_random::functions::commit_hash::encode_input(secret_hash, cipher)))
+			},
+			RandomnessPhase::Reveal { our_hash, our_cipher } => {
+				let secret = signer.decrypt(&our_hash, &our_cipher)
+					.map_err(|e| PhaseError::Decrypt(e.to_string()))?;
+
+				if secret.len() != 32 || keccak(&secret) != our_hash {
+					return Err(PhaseError::StaleSecret);
+				}
+
+				Ok(Some(aura_random::functions::reveal_number::encode_input(U256::from(&secret[..]))))
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use ethabi::{self, ParamType, Token};
+	use ethereum_types::{Address, H256, U256};
+	use hash::keccak;
+	use rand::{SeedableRng, XorShiftRng};
+	use account_provider::AccountProvider;
+	use bytes::Bytes;
+	use engines::signer::EngineSigner;
+	use super::{aura_random, PhaseError, RandomnessPhase};
+
+	fn signer() -> EngineSigner {
+		let tap = Arc::new(AccountProvider::transient_provider());
+		let address = tap.insert_account(keccak("1").into(), &"1".into()).unwrap();
+		let mut signer = EngineSigner::default();
+		signer.set(tap, address, "1".into());
+		signer
+	}
+
+	#[test]
+	fn loads_phase_from_contract() {
+		let our_address = Address::from(7);
+		let call = |_: Address, data: Bytes| -> Result<Bytes, String> {
+			if data == aura_random::functions::current_collect_round::encode_input() {
+				Ok(ethabi::encode(&[Token::Uint(U256::from(3))]))
+			} else if data == aura_random::functions::is_commit_phase::encode_input() {
+				Ok(ethabi::encode(&[Token::Bool(true)]))
+			} else if data == aura_random::functions::is_committed::encode_input(U256::from(3), our_address) {
+				Ok(ethabi::encode(&[Token::Bool(false)]))
+			} else {
+				Err("unexpected call".into())
+			}
+		};
+
+		assert_eq!(RandomnessPhase::load(&call, Address::from(1), our_address).unwrap(), RandomnessPhase::BeforeCommit);
+	}
+
+	#[test]
+	fn reveals_committed_secret() {
+		let signer = signer();
+		let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+		let commit = RandomnessPhase::BeforeCommit.advance(&signer, &mut rng).unwrap().unwrap();
+		let tokens = ethabi::decode(&[ParamType::FixedBytes(32), ParamType::Bytes], &commit[4..]).unwrap();
+		let our_hash = H256::from_slice(&tokens[0].clone().to_fixed_bytes().unwrap());
+		let our_cipher = tokens[1].clone().to_bytes().unwrap();
+
+		let reveal = RandomnessPhase::Reveal { our_hash: our_hash, our_cipher: our_cipher.clone() }
+			.advance(&signer, &mut rng).unwrap().unwrap();
+		assert_eq!(keccak(&reveal[4..]), our_hash);
+
+		match (RandomnessPhase::Reveal { our_hash: keccak("other"), our_cipher: our_cipher }).advance(&signer, &mut rng) {
+			Err(PhaseError::StaleSecret) => {},
+			_ => panic!("revealing a secret not matching the commitment must fail"),
+		}
+	}
+}
@@ -25,7 +25,7 @@ use block::*;
 use engines::{Engine, Seal, ConstructedVerifier, EngineError};
 use error::{BlockError, Error};
 use ethjson;
-use header::{Header, ExtendedHeader};
+use header::{BlockNumber, Header, ExtendedHeader};
 use client::EngineClient;
 use machine::{AuxiliaryData, Call, EthereumMachine};
 use super::signer::EngineSigner;
@@ -198,7 +198,7 @@ impl Engine<EthereumMachine> for BasicAuthority {
 		Ok(self.signer.read().sign(hash)?)
 	}
 
-	fn snapshot_components(&self) -> Option<Box<::snapshot::SnapshotComponents>> {
+	fn snapshot_components(&self, _block: BlockNumber) -> Option<Box<::snapshot::SnapshotComponents>> {
 		None
 	}
 
@@ -0,0 +1,124 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Clique signer set and pending votes at a given block.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use ethereum_types::{Address, H64};
+use engines::EngineError;
+use error::Error;
+use header::{Header, BlockNumber};
+use super::{vote_nonce, NONCE_AUTH_VOTE};
+
+/// Signer state after a block has been applied.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CliqueBlockState {
+	/// Signers authorized to seal the next block.
+	pub signers: BTreeSet<Address>,
+	/// Recent blocks and their signers, oldest first.
+	recents: VecDeque<(BlockNumber, Address)>,
+	/// Votes cast since the last checkpoint, keyed by (voter, candidate).
+	votes: BTreeMap<(Address, Address), bool>,
+}
+
+impl CliqueBlockState {
+	/// Create a state with the given signers and no history, as found at a checkpoint.
+	pub fn new(signers: BTreeSet<Address>) -> Self {
+		CliqueBlockState {
+			signers: signers,
+			recents: VecDeque::new(),
+			votes: BTreeMap::new(),
+		}
+	}
+
+	/// Whether `signer` is the in-turn signer for block `number`.
+	pub fn is_inturn(&self, number: BlockNumber, signer: &Address) -> bool {
+		let len = self.signers.len() as u64;
+		len > 0 && self.signers.iter().position(|s| s == signer).map_or(false, |index| number % len == index as u64)
+	}
+
+	/// Whether `signer` sealed one of the last `signers / 2 + 1` blocks before `number`.
+	pub fn recently_signed(&self, number: BlockNumber, signer: &Address) -> bool {
+		let limit = self.signers.len() as u64 / 2 + 1;
+		self.recents.iter().any(|&(n, ref s)| s == signer && n + limit > number)
+	}
+
+	/// Record that `signer` sealed block `number`, without applying its vote.
+	/// Used to restore recency from the blocks preceding a checkpoint.
+	pub fn note_recent(&mut self, number: BlockNumber, signer: Address) {
+		self.recents.push_back((number, signer));
+	}
+
+	/// Apply a block sealed by `signer`, updating the signer set with its vote.
+	pub fn apply(&mut self, header: &Header, signer: Address, epoch_length: u64) -> Result<(), Error> {
+		let number = header.number();
+		if number % epoch_length == 0 {
+			self.votes.clear();
+		}
+
+		if !self.signers.contains(&signer) {
+			return Err(EngineError::NotAuthorized(signer).into());
+		}
+		if self.recently_signed(number, &signer) {
+			return Err(EngineError::CliqueTooRecentlySigned(signer).into());
+		}
+		self.recents.push_back((number, signer));
+		self.trim_recents(number);
+
+		let candidate = *header.author();
+		let authorize = vote_nonce(header)? == H64::from(NONCE_AUTH_VOTE);
+
+		// a later vote by the same signer on the same candidate replaces the earlier one,
+		// and only votes which would change the signer set are counted.
+		self.votes.remove(&(signer, candidate));
+		if self.signers.contains(&candidate) == authorize {
+			return Ok(());
+		}
+		self.votes.insert((signer, candidate), authorize);
+
+		let tally = self.votes.iter()
+			.filter(|&(&(_, c), &a)| c == candidate && a == authorize)
+			.count();
+		if tally <= self.signers.len() / 2 {
+			return Ok(());
+		}
+
+		if authorize {
+			self.signers.insert(candidate);
+		} else {
+			self.signers.remove(&candidate);
+			self.trim_recents(number);
+			self.remove_votes(|&(voter, _)| voter == candidate);
+		}
+		self.remove_votes(|&(_, c)| c == candidate);
+
+		Ok(())
+	}
+
+	fn trim_recents(&mut self, number: BlockNumber) {
+		let limit = self.signers.len() as u64 / 2 + 1;
+		while self.recents.front().map_or(false, |&(n, _)| n + limit <= number) {
+			self.recents.pop_front();
+		}
+	}
+
+	fn remove_votes<F>(&mut self, f: F) where F: Fn(&(Address, Address)) -> bool {
+		let stale: Vec<_> = self.votes.keys().filter(|k| f(*k)).cloned().collect();
+		for key in stale {
+			self.votes.remove(&key);
+		}
+	}
+}
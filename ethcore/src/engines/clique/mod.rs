@@ -0,0 +1,647 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Clique proof-of-authority engine (EIP-225), compatible with geth-run networks.
+//!
+//! Signers seal blocks in turn, the signature lives in the last 65 bytes of the header's
+//! extra data and signer changes are voted on through the header's author and nonce.
+//! Every `epoch` blocks a checkpoint lists the current signers and resets pending votes.
+
+mod block_state;
+
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Weak, Arc};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use account_provider::AccountProvider;
+use block::ExecutedBlock;
+use client::{BlockId, EngineClient};
+use engines::{Engine, Seal, EngineError};
+use error::{BlockError, Error};
+use ethereum_types::{H64, H256, H520, U256, Address};
+use ethjson;
+use ethkey::{self, Password, Signature};
+use hash::{keccak, KECCAK_EMPTY_LIST_RLP};
+use header::{BlockNumber, Header, ExtendedHeader};
+use io::IoService;
+use lru_cache::LruCache;
+use machine::EthereumMachine;
+use parking_lot::{Mutex, RwLock};
+use rlp::{self, Rlp, RlpStream};
+use unexpected::{Mismatch, OutOfBounds};
+use super::signer::EngineSigner;
+use super::transition::{Timeouts, TransitionHandler};
+
+use self::block_state::CliqueBlockState;
+
+/// Fixed number of extra-data prefix bytes reserved for signer vanity.
+pub const VANITY_LENGTH: usize = 32;
+/// Fixed number of extra-data suffix bytes reserved for the signer seal.
+pub const SIGNATURE_LENGTH: usize = 65;
+/// Nonce of a block voting to add its author as a signer.
+pub const NONCE_AUTH_VOTE: [u8; 8] = [0xff; 8];
+/// Nonce of a block voting to remove its author from the signers.
+pub const NONCE_DROP_VOTE: [u8; 8] = [0; 8];
+/// Difficulty of a block sealed by the in-turn signer.
+pub const DIFF_INTURN: u64 = 2;
+/// Difficulty of a block sealed by an out-of-turn signer.
+pub const DIFF_NOTURN: u64 = 1;
+
+const DEFAULT_EPOCH_LENGTH: u64 = 30_000;
+const STATE_CACHE_NUM: usize = 128;
+
+/// `Clique` params.
+#[derive(Debug, PartialEq)]
+pub struct CliqueParams {
+	/// Minimum number of seconds between two consecutive blocks.
+	pub period: u64,
+	/// Number of blocks between checkpoints.
+	pub epoch: u64,
+}
+
+impl From<ethjson::spec::CliqueParams> for CliqueParams {
+	fn from(p: ethjson::spec::CliqueParams) -> Self {
+		CliqueParams {
+			period: p.period.map_or(0, Into::into),
+			epoch: p.epoch.map_or(DEFAULT_EPOCH_LENGTH, Into::into),
+		}
+	}
+}
+
+struct SealingTimeouts {
+	period: Duration,
+}
+
+impl Timeouts<()> for SealingTimeouts {
+	fn initial(&self) -> Duration { self.period }
+
+	fn timeout(&self, _step: &()) -> Duration { self.period }
+}
+
+/// Vote cast by the header: `NONCE_AUTH_VOTE` or `NONCE_DROP_VOTE`.
+fn vote_nonce(header: &Header) -> Result<H64, Error> {
+	Ok(Rlp::new(&header.seal()[1]).as_val::<H64>()?)
+}
+
+fn extra_data_too_short(header: &Header) -> Error {
+	BlockError::ExtraDataOutOfBounds(OutOfBounds {
+		min: Some(VANITY_LENGTH + SIGNATURE_LENGTH),
+		max: None,
+		found: header.extra_data().len(),
+	}).into()
+}
+
+/// Hash signed by the block's signer: the full header with the signature cut from the extra data.
+fn seal_hash(header: &Header) -> H256 {
+	let extra_data = header.extra_data();
+	let unsigned = extra_data.len().saturating_sub(SIGNATURE_LENGTH);
+
+	let mut s = RlpStream::new_list(13 + header.seal().len());
+	s.append(header.parent_hash());
+	s.append(header.uncles_hash());
+	s.append(header.author());
+	s.append(header.state_root());
+	s.append(header.transactions_root());
+	s.append(header.receipts_root());
+	s.append(header.log_bloom());
+	s.append(header.difficulty());
+	s.append(&header.number());
+	s.append(header.gas_limit());
+	s.append(header.gas_used());
+	s.append(&header.timestamp());
+	s.append(&&extra_data[..unsigned]);
+	for field in header.seal() {
+		s.append_raw(field, 1);
+	}
+	keccak(s.out())
+}
+
+/// Recover the address which sealed the block.
+fn recover_signer(header: &Header) -> Result<Address, Error> {
+	let extra_data = header.extra_data();
+	if extra_data.len() < VANITY_LENGTH + SIGNATURE_LENGTH {
+		return Err(extra_data_too_short(header));
+	}
+
+	let signature = H520::from_slice(&extra_data[extra_data.len() - SIGNATURE_LENGTH..]);
+	let public = ethkey::recover(&signature.into(), &seal_hash(header))?;
+	Ok(ethkey::public_to_address(&public))
+}
+
+/// Signers listed in the extra data of a checkpoint block.
+fn checkpoint_signers(header: &Header) -> Result<Vec<Address>, Error> {
+	let extra_data = header.extra_data();
+	if extra_data.len() < VANITY_LENGTH + SIGNATURE_LENGTH {
+		return Err(extra_data_too_short(header));
+	}
+
+	let list = &extra_data[VANITY_LENGTH..extra_data.len() - SIGNATURE_LENGTH];
+	if list.len() % 20 != 0 {
+		return Err(EngineError::CliqueCheckpointInvalidSigners(
+			format!("signer list of {} bytes is not a multiple of 20", list.len())
+		).into());
+	}
+	Ok(list.chunks(20).map(Address::from_slice).collect())
+}
+
+fn unix_now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Engine using `Clique` proof-of-authority consensus.
+pub struct Clique {
+	machine: EthereumMachine,
+	period: u64,
+	epoch_length: u64,
+	client: RwLock<Option<Weak<EngineClient>>>,
+	block_state_by_hash: Mutex<LruCache<H256, CliqueBlockState>>,
+	proposals: RwLock<BTreeMap<Address, bool>>,
+	signer: RwLock<EngineSigner>,
+	step_service: IoService<()>,
+}
+
+impl Clique {
+	/// Create a new instance of Clique engine.
+	pub fn new(our_params: CliqueParams, machine: EthereumMachine) -> Result<Arc<Self>, Error> {
+		let engine = Arc::new(
+			Clique {
+				machine: machine,
+				period: our_params.period,
+				epoch_length: cmp::max(our_params.epoch, 1),
+				client: RwLock::new(None),
+				block_state_by_hash: Mutex::new(LruCache::new(STATE_CACHE_NUM)),
+				proposals: RwLock::new(BTreeMap::new()),
+				signer: Default::default(),
+				step_service: IoService::<()>::start()?,
+			});
+
+		// with a zero period blocks are only sealed when there are transactions to include.
+		if our_params.period > 0 {
+			let timeouts = SealingTimeouts { period: Duration::from_secs(our_params.period) };
+			let handler = TransitionHandler::new(Arc::downgrade(&engine) as Weak<Engine<_>>, Box::new(timeouts));
+			engine.step_service.register_handler(Arc::new(handler))?;
+		}
+
+		Ok(engine)
+	}
+
+	fn is_checkpoint(&self, header: &Header) -> bool {
+		header.number() % self.epoch_length == 0
+	}
+
+	fn parent_header(&self, header: &Header) -> Result<Header, Error> {
+		let client = match self.client.read().as_ref().and_then(|weak| weak.upgrade()) {
+			Some(client) => client,
+			None => return Err(EngineError::RequiresClient.into()),
+		};
+		match client.block_header(BlockId::Hash(*header.parent_hash())) {
+			Some(parent) => Ok(parent.decode()?),
+			None => Err(BlockError::UnknownParent(*header.parent_hash()).into()),
+		}
+	}
+
+	/// Signer state after applying `header`.
+	///
+	/// Walks back to the closest cached state or checkpoint and replays the blocks from there.
+	/// Checkpoints carry the signer list but not who sealed the blocks before them, so the
+	/// signers of the blocks still within the recency window are recovered from their seals.
+	fn state(&self, header: &Header) -> Result<CliqueBlockState, Error> {
+		let mut pending = Vec::new();
+		let mut current = header.clone();
+
+		let mut state = loop {
+			if let Some(state) = self.block_state_by_hash.lock().get_mut(&current.hash()) {
+				break state.clone();
+			}
+
+			if self.is_checkpoint(&current) {
+				let signers: BTreeSet<_> = checkpoint_signers(&current)?.into_iter().collect();
+				let limit = signers.len() as u64 / 2 + 1;
+				let mut state = CliqueBlockState::new(signers);
+				if current.number() > 0 {
+					let mut recents = Vec::new();
+					let mut ancestor = current.clone();
+					while (recents.len() as u64) + 1 < limit && ancestor.number() > 1 {
+						ancestor = self.parent_header(&ancestor)?;
+						recents.push((ancestor.number(), recover_signer(&ancestor)?));
+					}
+					for (number, signer) in recents.into_iter().rev() {
+						state.note_recent(number, signer);
+					}
+					state.apply(&current, recover_signer(&current)?, self.epoch_length)?;
+				}
+				self.block_state_by_hash.lock().insert(current.hash(), state.clone());
+				break state;
+			}
+
+			let parent = self.parent_header(&current)?;
+			pending.push(current);
+			current = parent;
+		};
+
+		for header in pending.into_iter().rev() {
+			state.apply(&header, recover_signer(&header)?, self.epoch_length)?;
+			self.block_state_by_hash.lock().insert(header.hash(), state.clone());
+		}
+
+		Ok(state)
+	}
+}
+
+impl Engine<EthereumMachine> for Clique {
+	fn name(&self) -> &str { "Clique" }
+
+	fn machine(&self) -> &EthereumMachine { &self.machine }
+
+	// Two fields - mix hash (always zero) and the vote nonce
+	fn seal_fields(&self, _header: &Header) -> usize { 2 }
+
+	fn seals_internally(&self) -> Option<bool> {
+		Some(self.signer.read().is_some())
+	}
+
+	fn populate_from_parent(&self, header: &mut Header, parent: &Header) {
+		let state = match self.state(parent) {
+			Ok(state) => state,
+			Err(e) => {
+				debug!(target: "engine", "Unable to load Clique signers at {}: {}", parent.hash(), e);
+				return;
+			}
+		};
+
+		let number = header.number();
+		let inturn = self.signer.read().address().map_or(false, |signer| state.is_inturn(number, &signer));
+		header.set_difficulty(U256::from(if inturn { DIFF_INTURN } else { DIFF_NOTURN }));
+
+		let mut extra_data = header.extra_data().clone();
+		extra_data.resize(VANITY_LENGTH, 0);
+
+		let (candidate, nonce) = if self.is_checkpoint(header) {
+			for signer in &state.signers {
+				extra_data.extend_from_slice(&**signer);
+			}
+			(Address::zero(), NONCE_DROP_VOTE)
+		} else {
+			// only propose votes which would still change the signer set.
+			let proposal = self.proposals.read().iter()
+				.find(|&(address, &authorize)| state.signers.contains(address) != authorize)
+				.map(|(address, &authorize)| (*address, authorize));
+			match proposal {
+				Some((address, true)) => (address, NONCE_AUTH_VOTE),
+				Some((address, false)) => (address, NONCE_DROP_VOTE),
+				None => (Address::zero(), NONCE_DROP_VOTE),
+			}
+		};
+
+		// room for the signature, filled in once the block is sealed.
+		extra_data.resize(extra_data.len() + SIGNATURE_LENGTH, 0);
+		header.set_extra_data(extra_data);
+		header.set_author(candidate);
+		header.set_seal(vec![rlp::encode(&H256::zero()), rlp::encode(&H64::from(nonce))]);
+	}
+
+	fn open_block_header_timestamp(&self, parent_timestamp: u64) -> u64 {
+		cmp::max(unix_now(), parent_timestamp + self.period)
+	}
+
+	fn is_timestamp_valid(&self, header_timestamp: u64, parent_timestamp: u64, _block: BlockNumber) -> bool {
+		header_timestamp >= parent_timestamp.saturating_add(self.period)
+	}
+
+	/// Attempt to seal the block internally.
+	fn generate_seal(&self, block: &ExecutedBlock, parent: &Header) -> Seal {
+		let header = block.header();
+		let signer = match self.signer.read().address() {
+			Some(signer) => signer,
+			None => return Seal::None,
+		};
+		let state = match self.state(parent) {
+			Ok(state) => state,
+			Err(e) => {
+				warn!(target: "engine", "generate_seal: unable to load Clique signers: {}", e);
+				return Seal::None;
+			}
+		};
+
+		if !state.signers.contains(&signer) {
+			trace!(target: "engine", "generate_seal: {} is not an authorized signer", signer);
+			return Seal::None;
+		}
+		if state.recently_signed(header.number(), &signer) {
+			trace!(target: "engine", "generate_seal: {} signed recently, waiting for others", signer);
+			return Seal::None;
+		}
+
+		// out-of-turn signers give the in-turn signer an extra period to seal first.
+		let mut ready_at = parent.timestamp() + self.period;
+		if !state.is_inturn(header.number(), &signer) {
+			ready_at += self.period;
+		}
+		if unix_now() < ready_at {
+			return Seal::None;
+		}
+
+		Seal::Regular(header.seal().to_vec())
+	}
+
+	fn on_seal_block(&self, block: &mut ExecutedBlock) -> Result<(), Error> {
+		let mut extra_data = block.header.extra_data().clone();
+		if extra_data.len() < VANITY_LENGTH + SIGNATURE_LENGTH {
+			return Err(extra_data_too_short(&block.header));
+		}
+
+		let signature = self.sign(seal_hash(&block.header))?;
+		let offset = extra_data.len() - SIGNATURE_LENGTH;
+		extra_data[offset..].copy_from_slice(&*H520::from(signature));
+		block.header.set_extra_data(extra_data);
+		Ok(())
+	}
+
+	fn verify_local_seal(&self, _header: &Header) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn verify_block_basic(&self, header: &Header) -> Result<(), Error> {
+		if header.number() == 0 {
+			return Ok(());
+		}
+
+		let checkpoint = self.is_checkpoint(header);
+		let nonce = vote_nonce(header)?;
+		if checkpoint && !header.author().is_zero() {
+			return Err(EngineError::CliqueWrongAuthorCheckpoint(Mismatch {
+				expected: Address::zero(),
+				found: *header.author(),
+			}).into());
+		}
+		if nonce != H64::from(NONCE_DROP_VOTE) && (checkpoint || nonce != H64::from(NONCE_AUTH_VOTE)) {
+			return Err(EngineError::CliqueInvalidNonce(nonce).into());
+		}
+
+		let extra_data_len = header.extra_data().len();
+		if extra_data_len < VANITY_LENGTH + SIGNATURE_LENGTH {
+			return Err(extra_data_too_short(header));
+		}
+		let signers_len = extra_data_len - VANITY_LENGTH - SIGNATURE_LENGTH;
+		if !checkpoint && signers_len != 0 {
+			return Err(EngineError::CliqueCheckpointInvalidSigners(
+				format!("non-checkpoint block lists {} bytes of signers", signers_len)
+			).into());
+		}
+		if checkpoint && signers_len % 20 != 0 {
+			return Err(EngineError::CliqueCheckpointInvalidSigners(
+				format!("signer list of {} bytes is not a multiple of 20", signers_len)
+			).into());
+		}
+
+		let mix_hash = Rlp::new(&header.seal()[0]).as_val::<H256>()?;
+		if !mix_hash.is_zero() {
+			return Err(BlockError::InvalidSeal.into());
+		}
+
+		if *header.uncles_hash() != KECCAK_EMPTY_LIST_RLP {
+			return Err(BlockError::InvalidUnclesHash(Mismatch {
+				expected: KECCAK_EMPTY_LIST_RLP,
+				found: *header.uncles_hash(),
+			}).into());
+		}
+
+		let difficulty = *header.difficulty();
+		if difficulty != U256::from(DIFF_INTURN) && difficulty != U256::from(DIFF_NOTURN) {
+			return Err(BlockError::DifficultyOutOfBounds(OutOfBounds {
+				min: Some(U256::from(DIFF_NOTURN)),
+				max: Some(U256::from(DIFF_INTURN)),
+				found: difficulty,
+			}).into());
+		}
+
+		Ok(())
+	}
+
+	fn verify_block_family(&self, header: &Header, parent: &Header) -> Result<(), Error> {
+		let mut state = self.state(parent)?;
+		let signer = recover_signer(header)?;
+		let number = header.number();
+
+		let expected = U256::from(if state.is_inturn(number, &signer) { DIFF_INTURN } else { DIFF_NOTURN });
+		if *header.difficulty() != expected {
+			return Err(BlockError::InvalidDifficulty(Mismatch {
+				expected: expected,
+				found: *header.difficulty(),
+			}).into());
+		}
+
+		if self.is_checkpoint(header) {
+			let listed = checkpoint_signers(header)?;
+			if listed.iter().ne(state.signers.iter()) {
+				return Err(EngineError::CliqueCheckpointInvalidSigners(
+					format!("checkpoint lists {:?}, expected {:?}", listed, state.signers)
+				).into());
+			}
+		}
+
+		state.apply(header, signer, self.epoch_length)?;
+		self.block_state_by_hash.lock().insert(header.hash(), state);
+		Ok(())
+	}
+
+	fn register_client(&self, client: Weak<EngineClient>) {
+		*self.client.write() = Some(client);
+	}
+
+	fn step(&self) {
+		let client = self.client.read().as_ref().and_then(|weak| weak.upgrade());
+		if let Some(client) = client {
+			client.update_sealing();
+		}
+		if let Err(io_err) = self.step_service.send_message(()) {
+			warn!(target: "engine", "Could not restart the Clique sealing timer: {}.", io_err)
+		}
+	}
+
+	fn set_signer(&self, ap: Arc<AccountProvider>, address: Address, password: Password) {
+		self.signer.write().set(ap, address, password);
+	}
+
+	fn sign(&self, hash: H256) -> Result<Signature, Error> {
+		Ok(self.signer.read().sign(hash)?)
+	}
+
+	fn signers(&self, header: &Header) -> Option<Vec<Address>> {
+		match self.state(header) {
+			Ok(state) => Some(state.signers.into_iter().collect()),
+			Err(e) => {
+				debug!(target: "engine", "Unable to load Clique signers at {}: {}", header.hash(), e);
+				None
+			}
+		}
+	}
+
+	fn propose_signer(&self, address: Address, authorize: bool) -> bool {
+		self.proposals.write().insert(address, authorize);
+		true
+	}
+
+	fn fork_choice(&self, new: &ExtendedHeader, current: &ExtendedHeader) -> super::ForkChoice {
+		super::total_difficulty_fork_choice(new, current)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeSet;
+	use std::sync::Arc;
+	use ethereum_types::{Address, H64, H256};
+	use ethkey::{self, Secret};
+	use hash::keccak;
+	use rustc_hex::FromHex;
+	use account_provider::AccountProvider;
+	use block::*;
+	use engines::{Seal, EngineError};
+	use error::{Error, ErrorKind};
+	use header::Header;
+	use spec::Spec;
+	use test_helpers::get_temp_state_db;
+	use super::{CliqueBlockState, NONCE_AUTH_VOTE, NONCE_DROP_VOTE, SIGNATURE_LENGTH, checkpoint_signers, recover_signer, seal_hash};
+
+	fn vote(number: u64, candidate: Address, authorize: bool) -> Header {
+		let nonce = if authorize { NONCE_AUTH_VOTE } else { NONCE_DROP_VOTE };
+		let mut header = Header::default();
+		header.set_number(number);
+		header.set_author(candidate);
+		header.set_seal(vec![::rlp::encode(&H256::zero()), ::rlp::encode(&H64::from(nonce))]);
+		header
+	}
+
+	// the genesis header of the Görli testnet, a checkpoint sealed by geth.
+	fn goerli_genesis() -> Header {
+		let mut header = Header::default();
+		header.set_state_root("5d6cded585e73c4e322c30c2f782a336316f17dd85a4863b9d838d2d4b8b3008".into());
+		header.set_difficulty(1.into());
+		header.set_gas_limit(0xa00000.into());
+		header.set_timestamp(0x5c51a607);
+		header.set_extra_data("22466c6578692069732061207468696e6722202d204166726900000000000000e0a2bd4258d2768837baa26a28fe71dc079f84c70000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000".from_hex().unwrap());
+		header.set_seal(vec![::rlp::encode(&H256::zero()), ::rlp::encode(&H64::zero())]);
+		assert_eq!(header.hash(), "bf7e331f7f7c1dd2e05159666b3bf8bc7a8a3a9eb1d518969eab529dd9b88c1a".into());
+		header
+	}
+
+	#[test]
+	fn reads_signers_of_goerli_checkpoint() {
+		let header = goerli_genesis();
+		assert_eq!(checkpoint_signers(&header).unwrap(), vec![Address::from("e0a2bd4258d2768837baa26a28fe71dc079f84c7")]);
+	}
+
+	#[test]
+	fn recovers_signer_over_geth_seal_hash() {
+		let mut header = goerli_genesis();
+		// the hash geth signs: the header without the signature in the extra data,
+		// computed independently of the engine.
+		let hash = seal_hash(&header);
+		assert_eq!(hash, "baa62eb9b6da4396c5e1a399b0b3584aa3cd14ad9eb6946c5871ec8c1a55b617".into());
+
+		let secret = Secret::from(keccak("1"));
+		let signature = ethkey::sign(&secret, &hash).unwrap();
+		let mut extra_data = header.extra_data().clone();
+		let signed = extra_data.len() - SIGNATURE_LENGTH;
+		extra_data[signed..].copy_from_slice(&signature[..]);
+		header.set_extra_data(extra_data);
+
+		assert_eq!(seal_hash(&header), hash);
+		let address = ethkey::public_to_address(&ethkey::KeyPair::from_secret(secret).unwrap().public());
+		assert_eq!(recover_signer(&header).unwrap(), address);
+	}
+
+	#[test]
+	fn tallies_votes() {
+		let (a, b, c) = (Address::from(1), Address::from(2), Address::from(3));
+		let mut state = CliqueBlockState::new(vec![a, b].into_iter().collect());
+
+		// a majority of two signers is two votes.
+		state.apply(&vote(1, c, true), a, 30_000).unwrap();
+		assert!(!state.signers.contains(&c));
+		state.apply(&vote(2, c, true), b, 30_000).unwrap();
+		assert!(state.signers.contains(&c));
+
+		// b may not seal twice within two blocks.
+		match state.apply(&vote(3, Address::zero(), false), b, 30_000) {
+			Err(Error(ErrorKind::Engine(EngineError::CliqueTooRecentlySigned(signer)), _)) => assert_eq!(signer, b),
+			other => panic!("unexpected result: {:?}", other),
+		}
+
+		// voting again for an existing signer is ignored.
+		state.apply(&vote(3, c, true), c, 30_000).unwrap();
+		assert_eq!(state.signers.len(), 3);
+
+		// dropping c takes two of three votes.
+		state.apply(&vote(4, c, false), a, 30_000).unwrap();
+		state.apply(&vote(5, c, false), b, 30_000).unwrap();
+		let expected: BTreeSet<_> = vec![a, b].into_iter().collect();
+		assert_eq!(state.signers, expected);
+
+		assert!(state.apply(&vote(6, Address::zero(), false), c, 30_000).is_err());
+	}
+
+	#[test]
+	fn checkpoint_state_keeps_recent_signers() {
+		let (a, b, c) = (Address::from(1), Address::from(2), Address::from(3));
+		let mut state = CliqueBlockState::new(vec![a, b, c].into_iter().collect());
+
+		// c sealed the block before the checkpoint, so it may not seal the checkpoint itself.
+		state.note_recent(29_999, c);
+		match state.apply(&vote(30_000, Address::zero(), false), c, 30_000) {
+			Err(Error(ErrorKind::Engine(EngineError::CliqueTooRecentlySigned(signer)), _)) => assert_eq!(signer, c),
+			other => panic!("unexpected result: {:?}", other),
+		}
+		state.apply(&vote(30_000, Address::zero(), false), a, 30_000).unwrap();
+	}
+
+	#[test]
+	fn seals_and_verifies_blocks() {
+		let tap = Arc::new(AccountProvider::transient_provider());
+		let addr = tap.insert_account(keccak("1").into(), &"".into()).unwrap();
+
+		let spec = Spec::new_test_clique();
+		let engine = &*spec.engine;
+		engine.set_signer(tap, addr, "".into());
+
+		let genesis_header = spec.genesis_header();
+		let signers = checkpoint_signers(&genesis_header).unwrap();
+		assert_eq!(engine.signers(&genesis_header), Some(signers));
+		assert!(engine.propose_signer(Address::from(5), true));
+
+		let db = spec.ensure_db_good(get_temp_state_db(), &Default::default()).unwrap();
+		let last_hashes = Arc::new(vec![genesis_header.hash()]);
+		let b = OpenBlock::new(engine, Default::default(), false, db, &genesis_header, last_hashes, addr, (3141562.into(), 31415620.into()), vec![], false, &mut Vec::new().into_iter()).unwrap();
+		let b = b.close_and_lock().unwrap();
+
+		// signer #1 of the sorted list is in turn for block 1.
+		assert_eq!(*b.header().difficulty(), 2.into());
+		assert_eq!(*b.header().author(), Address::from(5));
+
+		let seal = match engine.generate_seal(b.block(), &genesis_header) {
+			Seal::Regular(seal) => seal,
+			_ => panic!("in-turn signer should seal"),
+		};
+		let sealed = b.seal(engine, seal).unwrap();
+		let header = sealed.header();
+
+		assert_eq!(recover_signer(header).unwrap(), addr);
+		engine.verify_block_basic(header).unwrap();
+		engine.verify_block_family(header, &genesis_header).unwrap();
+	}
+}
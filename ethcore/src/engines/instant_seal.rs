@@ -14,41 +14,95 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
+use std::time::Duration;
+
+use client::EngineClient;
 use engines::{Engine, Seal};
+use error::Error;
+use header::BlockNumber;
+use io::IoService;
 use parity_machine::{Machine, Transactions, TotalScoredHeader};
+use parking_lot::RwLock;
+use super::transition::{Timeouts, TransitionHandler};
 
 /// `InstantSeal` params.
 #[derive(Default, Debug, PartialEq)]
 pub struct InstantSealParams {
 	/// Whether to use millisecond timestamp
 	pub millisecond_timestamp: bool,
+	/// Seal a block every given number of seconds, even if it's empty. Zero disables it.
+	pub block_period: u64,
 }
 
 impl From<::ethjson::spec::InstantSealParams> for InstantSealParams {
 	fn from(p: ::ethjson::spec::InstantSealParams) -> Self {
 		InstantSealParams {
 			millisecond_timestamp: p.millisecond_timestamp,
+			block_period: p.block_period.map_or(0, Into::into),
 		}
 	}
 }
 
+/// Sealing timer reading the current block period, it's only restarted while the period is set.
+struct PeriodTimeouts {
+	period: Arc<AtomicUsize>,
+}
+
+impl Timeouts<()> for PeriodTimeouts {
+	fn initial(&self) -> Duration { self.timeout(&()) }
+
+	fn timeout(&self, _step: &()) -> Duration {
+		Duration::from_secs(self.period.load(AtomicOrdering::SeqCst) as u64)
+	}
+}
+
 /// An engine which does not provide any consensus mechanism, just seals blocks internally.
-/// Only seals blocks which have transactions.
+/// Seals blocks which have transactions, and empty ones every block period or when requested.
 pub struct InstantSeal<M> {
 	params: InstantSealParams,
 	machine: M,
+	client: RwLock<Option<Weak<EngineClient>>>,
+	block_period: Arc<AtomicUsize>,
+	time_offset: AtomicUsize,
+	seal_requested: AtomicBool,
+	step_service: IoService<()>,
 }
 
-impl<M> InstantSeal<M> {
+impl<M: Machine<EngineClient = EngineClient> + 'static> InstantSeal<M>
+  where M::LiveBlock: Transactions,
+        M::ExtendedHeader: TotalScoredHeader,
+        <M::ExtendedHeader as TotalScoredHeader>::Value: Ord
+{
 	/// Returns new instance of InstantSeal over the given state machine.
-	pub fn new(params: InstantSealParams, machine: M) -> Self {
-		InstantSeal {
+	pub fn new(params: InstantSealParams, machine: M) -> Result<Arc<Self>, Error> {
+		let block_period = Arc::new(AtomicUsize::new(params.block_period as usize));
+		let engine = Arc::new(InstantSeal {
 			params, machine,
+			client: RwLock::new(None),
+			block_period: block_period.clone(),
+			time_offset: AtomicUsize::new(0),
+			seal_requested: AtomicBool::new(false),
+			step_service: IoService::<()>::start()?,
+		});
+
+		let timeouts = PeriodTimeouts { period: block_period };
+		let handler = TransitionHandler::new(Arc::downgrade(&engine) as Weak<Engine<_>>, Box::new(timeouts));
+		engine.step_service.register_handler(Arc::new(handler))?;
+
+		Ok(engine)
+	}
+
+	fn update_sealing(&self) {
+		let client = self.client.read().as_ref().and_then(|weak| weak.upgrade());
+		if let Some(client) = client {
+			client.update_sealing();
 		}
 	}
 }
 
-impl<M: Machine> Engine<M> for InstantSeal<M>
+impl<M: Machine<EngineClient = EngineClient> + 'static> Engine<M> for InstantSeal<M>
   where M::LiveBlock: Transactions,
         M::ExtendedHeader: TotalScoredHeader,
         <M::ExtendedHeader as TotalScoredHeader>::Value: Ord
@@ -62,7 +116,11 @@ impl<M: Machine> Engine<M> for InstantSeal<M>
 	fn seals_internally(&self) -> Option<bool> { Some(true) }
 
 	fn generate_seal(&self, block: &M::LiveBlock, _parent: &M::Header) -> Seal {
-		if block.transactions().is_empty() { Seal::None } else { Seal::Regular(Vec::new()) }
+		if self.seal_requested.swap(false, AtomicOrdering::SeqCst) || !block.transactions().is_empty() {
+			Seal::Regular(Vec::new())
+		} else {
+			Seal::None
+		}
 	}
 
 	fn verify_local_seal(&self, _header: &M::Header) -> Result<(), M::Error> {
@@ -73,17 +131,58 @@ impl<M: Machine> Engine<M> for InstantSeal<M>
 		use std::{time, cmp};
 
 		let dur = time::SystemTime::now().duration_since(time::UNIX_EPOCH).unwrap_or_default();
-		let mut now = dur.as_secs();
+		let offset = self.time_offset.load(AtomicOrdering::SeqCst) as u64;
+		let mut now = dur.as_secs() + offset;
 		if self.params.millisecond_timestamp {
 			now = now * 1000 + dur.subsec_millis() as u64;
 		}
 		cmp::max(now, parent_timestamp)
 	}
 
-	fn is_timestamp_valid(&self, header_timestamp: u64, parent_timestamp: u64) -> bool {
+	fn is_timestamp_valid(&self, header_timestamp: u64, parent_timestamp: u64, _block: BlockNumber) -> bool {
 		header_timestamp >= parent_timestamp
 	}
 
+	fn register_client(&self, client: Weak<EngineClient>) {
+		*self.client.write() = Some(client);
+	}
+
+	fn step(&self) {
+		if self.block_period.load(AtomicOrdering::SeqCst) == 0 {
+			return;
+		}
+		self.seal_requested.store(true, AtomicOrdering::SeqCst);
+		self.update_sealing();
+		if let Err(io_err) = self.step_service.send_message(()) {
+			warn!(target: "engine", "Could not restart the InstantSeal sealing timer: {}.", io_err)
+		}
+	}
+
+	fn seal_on_demand(&self) -> bool {
+		self.seal_requested.store(true, AtomicOrdering::SeqCst);
+		self.update_sealing();
+		true
+	}
+
+	fn can_seal_on_demand(&self) -> bool { true }
+
+	fn seal_requested(&self) -> bool {
+		self.seal_requested.load(AtomicOrdering::SeqCst)
+	}
+
+	fn set_block_period(&self, period: u64) -> bool {
+		self.block_period.store(period as usize, AtomicOrdering::SeqCst);
+		if let Err(io_err) = self.step_service.send_message(()) {
+			warn!(target: "engine", "Could not restart the InstantSeal sealing timer: {}.", io_err)
+		}
+		true
+	}
+
+	fn increase_time(&self, seconds: u64) -> Option<u64> {
+		let previous = self.time_offset.fetch_add(seconds as usize, AtomicOrdering::SeqCst);
+		Some(previous as u64 + seconds)
+	}
+
 	fn fork_choice(&self, new: &M::ExtendedHeader, current: &M::ExtendedHeader) -> super::ForkChoice {
 		super::total_difficulty_fork_choice(new, current)
 	}
@@ -124,4 +223,31 @@ mod tests {
 
 		assert!(engine.verify_block_unordered(&header).is_ok());
 	}
+
+	#[test]
+	fn instant_seals_empty_block_on_demand() {
+		let spec = Spec::new_instant();
+		let engine = &*spec.engine;
+		let db = spec.ensure_db_good(get_temp_state_db(), &Default::default()).unwrap();
+		let genesis_header = spec.genesis_header();
+		let last_hashes = Arc::new(vec![genesis_header.hash()]);
+		let b = OpenBlock::new(engine, Default::default(), false, db, &genesis_header, last_hashes, Address::default(), (3141562.into(), 31415620.into()), vec![], false, &mut Vec::new().into_iter()).unwrap();
+		let b = b.close_and_lock().unwrap();
+
+		assert_eq!(engine.generate_seal(b.block(), &genesis_header), Seal::None);
+		assert!(engine.seal_on_demand());
+		assert_eq!(engine.generate_seal(b.block(), &genesis_header), Seal::Regular(Vec::new()));
+		// the request is used up by the sealed block.
+		assert_eq!(engine.generate_seal(b.block(), &genesis_header), Seal::None);
+	}
+
+	#[test]
+	fn instant_increases_time() {
+		let engine = Spec::new_instant().engine;
+		let now = engine.open_block_header_timestamp(0);
+
+		assert_eq!(engine.increase_time(100), Some(100));
+		assert_eq!(engine.increase_time(50), Some(150));
+		assert!(engine.open_block_header_timestamp(0) >= now + 150);
+	}
 }
@@ -18,7 +18,9 @@
 
 mod authority_round;
 mod basic_authority;
+mod clique;
 mod instant_seal;
+mod multi;
 mod null_engine;
 mod signer;
 mod tendermint;
@@ -29,10 +31,12 @@ mod vote_collector;
 pub mod block_reward;
 pub mod epoch;
 
-pub use self::authority_round::AuthorityRound;
+pub use self::authority_round::{AuthorityRound, Equivocation};
 pub use self::basic_authority::BasicAuthority;
+pub use self::clique::{Clique, CliqueParams};
 pub use self::epoch::{EpochVerifier, Transition as EpochTransition};
 pub use self::instant_seal::{InstantSeal, InstantSealParams};
+pub use self::multi::MultiEngine;
 pub use self::null_engine::NullEngine;
 pub use self::tendermint::Tendermint;
 
@@ -53,7 +57,7 @@ use transaction::{self, UnverifiedTransaction, SignedTransaction};
 
 use ethkey::{Password, Signature};
 use parity_machine::{Machine, LocalizedMachine as Localized, TotalScoredHeader};
-use ethereum_types::{H256, U256, Address};
+use ethereum_types::{H64, H256, U256, Address};
 use unexpected::{Mismatch, OutOfBounds};
 use bytes::Bytes;
 use types::ancestry_action::AncestryAction;
@@ -62,6 +66,9 @@ use types::ancestry_action::AncestryAction;
 /// As defined in https://github.com/ethereum/EIPs/pull/210
 pub const DEFAULT_BLOCKHASH_CONTRACT: &'static str = "73fffffffffffffffffffffffffffffffffffffffe33141561006a5760014303600035610100820755610100810715156100455760003561010061010083050761010001555b6201000081071515610064576000356101006201000083050761020001555b5061013e565b4360003512151561008457600060405260206040f361013d565b61010060003543031315156100a857610100600035075460605260206060f361013c565b6101006000350715156100c55762010000600035430313156100c8565b60005b156100ea576101006101006000350507610100015460805260206080f361013b565b620100006000350715156101095763010000006000354303131561010c565b60005b1561012f57610100620100006000350507610200015460a052602060a0f361013a565b600060c052602060c0f35b5b5b5b5b";
 
+/// Depth below the best block at which blocks of engines without finality are considered final.
+pub const DEFAULT_FINALITY_DEPTH: BlockNumber = 12;
+
 /// Fork choice.
 #[derive(Debug, PartialEq, Eq)]
 pub enum ForkChoice {
@@ -92,6 +99,14 @@ pub enum EngineError {
 	MalformedMessage(String),
 	/// Requires client ref, but none registered.
 	RequiresClient,
+	/// The signer sealed one of the last `signers / 2 + 1` Clique blocks.
+	CliqueTooRecentlySigned(Address),
+	/// The Clique nonce is neither an authorization nor a deauthorization vote.
+	CliqueInvalidNonce(H64),
+	/// Clique checkpoint blocks must not vote on a signer.
+	CliqueWrongAuthorCheckpoint(Mismatch<Address>),
+	/// Malformed or unexpected signer list in a Clique block's extra data.
+	CliqueCheckpointInvalidSigners(String),
 }
 
 impl fmt::Display for EngineError {
@@ -107,6 +122,10 @@ impl fmt::Display for EngineError {
 			FailedSystemCall(ref msg) => format!("Failed to make system call: {}", msg),
 			MalformedMessage(ref msg) => format!("Received malformed consensus message: {}", msg),
 			RequiresClient => format!("Call requires client but none registered"),
+			CliqueTooRecentlySigned(ref address) => format!("Signer {} has signed a block too recently.", address),
+			CliqueInvalidNonce(ref nonce) => format!("Invalid vote nonce: {}", nonce),
+			CliqueWrongAuthorCheckpoint(ref mis) => format!("Checkpoint block must not vote on a signer: {}", mis),
+			CliqueCheckpointInvalidSigners(ref msg) => format!("Invalid checkpoint signers: {}", msg),
 		};
 
 		f.write_fmt(format_args!("Engine error ({})", msg))
@@ -251,11 +270,11 @@ pub trait Engine<M: Machine>: Sync + Send {
 	/// Maximum number of uncles a block is allowed to declare.
 	fn maximum_uncle_count(&self, _block: BlockNumber) -> usize { 0 }
 
-	/// The number of generations back that uncles can be.
-	fn maximum_uncle_age(&self) -> usize { 6 }
+	/// The number of generations back that uncles of the given block can be.
+	fn maximum_uncle_age(&self, _block: BlockNumber) -> usize { 6 }
 
-	/// Optional maximum gas limit.
-	fn maximum_gas_limit(&self) -> Option<U256> { None }
+	/// Optional maximum gas limit of the given block.
+	fn maximum_gas_limit(&self, _block: BlockNumber) -> Option<U256> { None }
 
 	/// Block transformation functions, before the transactions.
 	/// `epoch_begin` set to true if this block kicks off an epoch.
@@ -273,6 +292,19 @@ pub trait Engine<M: Machine>: Sync + Send {
 		Ok(())
 	}
 
+	/// Block transformation functions, after the seal is set and before the block hash is computed.
+	/// Used by engines which keep part of the seal outside of the seal fields.
+	fn on_seal_block(&self, _block: &mut M::LiveBlock) -> Result<(), M::Error> {
+		Ok(())
+	}
+
+	/// Transactions the engine needs included in a block it is about to seal, e.g. calls to
+	/// consensus contracts signed by the engine signer. Requested once the queued transactions
+	/// were pushed, so nonces may be read from the block state.
+	fn generate_engine_transactions(&self, _block: &M::LiveBlock) -> Result<Vec<SignedTransaction>, M::Error> {
+		Ok(Vec::new())
+	}
+
 	/// None means that it requires external input (e.g. PoW) to seal a block.
 	/// Some(true) means the engine is currently prime for seal generation (i.e. node is the current validator).
 	/// Some(false) means that the node might seal internally but is not qualified now.
@@ -380,6 +412,12 @@ pub trait Engine<M: Machine>: Sync + Send {
 	/// Usually implements the chain scoring rule based on weight.
 	fn populate_from_parent(&self, _header: &mut M::Header, _parent: &M::Header) { }
 
+	/// Gas limit the engine demands for the given block, overriding the one derived from the
+	/// parent's gas limit and the block author's gas targets. The header's parent hash and
+	/// number must be set. Fails if the engine demands a gas limit but it can't be determined,
+	/// in which case the block must be rejected.
+	fn gas_limit_override(&self, _header: &M::Header) -> Result<Option<U256>, M::Error> { Ok(None) }
+
 	/// Handle any potential consensus messages;
 	/// updating consensus state and potentially issuing a new one.
 	fn handle_message(&self, _message: &[u8]) -> Result<(), EngineError> { Err(EngineError::UnexpectedMessage) }
@@ -394,6 +432,13 @@ pub trait Engine<M: Machine>: Sync + Send {
 	/// Sign using the EngineSigner, to be used for consensus tx signing.
 	fn sign(&self, _hash: H256) -> Result<Signature, M::Error> { unimplemented!() }
 
+	/// Signers allowed to seal the child of the given block, for engines that vote on signers.
+	fn signers(&self, _header: &M::Header) -> Option<Vec<Address>> { None }
+
+	/// Vote to authorize (`true`) or drop (`false`) a signer in the blocks we seal.
+	/// Returns `false` if the engine doesn't vote on signers.
+	fn propose_signer(&self, _address: Address, _authorize: bool) -> bool { false }
+
 	/// Add Client which can be used for sealing, potentially querying the state and sending messages.
 	fn register_client(&self, _client: Weak<M::EngineClient>) {}
 
@@ -403,15 +448,15 @@ pub trait Engine<M: Machine>: Sync + Send {
 	/// Stops any services that the may hold the Engine and makes it safe to drop.
 	fn stop(&self) {}
 
-	/// Create a factory for building snapshot chunks and restoring from them.
+	/// Create a factory for building snapshot chunks of the given block and restoring from them.
 	/// Returning `None` indicates that this engine doesn't support snapshot creation.
-	fn snapshot_components(&self) -> Option<Box<SnapshotComponents>> {
+	fn snapshot_components(&self, _block: BlockNumber) -> Option<Box<SnapshotComponents>> {
 		None
 	}
 
-	/// Whether this engine supports warp sync.
+	/// Whether this engine supports warp sync to recent snapshots.
 	fn supports_warp(&self) -> bool {
-		self.snapshot_components().is_some()
+		self.snapshot_components(BlockNumber::max_value()).is_some()
 	}
 
 	/// Return a new open block header timestamp based on the parent timestamp.
@@ -422,8 +467,8 @@ pub trait Engine<M: Machine>: Sync + Send {
 		cmp::max(now.as_secs() as u64, parent_timestamp + 1)
 	}
 
-	/// Check whether the parent timestamp is valid.
-	fn is_timestamp_valid(&self, header_timestamp: u64, parent_timestamp: u64) -> bool {
+	/// Check whether the timestamp of the given block is valid given the parent timestamp.
+	fn is_timestamp_valid(&self, header_timestamp: u64, parent_timestamp: u64, _block: BlockNumber) -> bool {
 		header_timestamp > parent_timestamp
 	}
 
@@ -433,6 +478,40 @@ pub trait Engine<M: Machine>: Sync + Send {
 		Vec::new()
 	}
 
+	/// Depth below the given best block at which blocks are considered final. Engines which
+	/// finalize blocks through `ancestry_actions` return `None`.
+	fn finality_depth(&self, _best_block: BlockNumber) -> Option<BlockNumber> {
+		Some(DEFAULT_FINALITY_DEPTH)
+	}
+
+	/// Number of the first block sealed with ProgPoW, for proof-of-work engines scheduled to switch to it.
+	fn progpow_transition(&self) -> Option<BlockNumber> { None }
+
+	/// Whether blocks are sealed with an Ethash proof-of-work, so the built-in CPU miner can seal them.
+	fn seals_with_ethash(&self) -> bool { false }
+
+	/// Seal the next block even if it's empty, for engines sealing blocks on demand.
+	/// Returns `false` if the engine doesn't support it.
+	fn seal_on_demand(&self) -> bool { false }
+
+	/// Whether the engine seals blocks on demand, as on the development chain. Only such chains
+	/// may be reverted, since no other node depends on their blocks.
+	fn can_seal_on_demand(&self) -> bool { false }
+
+	/// Whether the next block was requested to be sealed even if it's empty.
+	fn seal_requested(&self) -> bool { false }
+
+	/// Seal blocks every given number of seconds, zero only seals blocks with transactions.
+	/// Returns `false` if the engine's block period can't be changed.
+	fn set_block_period(&self, _period: u64) -> bool { false }
+
+	/// Move the timestamps of the blocks we seal forward by given number of seconds.
+	/// Returns the total time offset or `None` if the engine doesn't support it.
+	fn increase_time(&self, _seconds: u64) -> Option<u64> { None }
+
+	/// Evidence of validators sealing conflicting blocks collected by the engine.
+	fn equivocations(&self) -> Vec<Equivocation> { Vec::new() }
+
 	/// Check whether the given new block is the best block, after finalization check.
 	fn fork_choice(&self, new: &M::ExtendedHeader, best: &M::ExtendedHeader) -> ForkChoice;
 }
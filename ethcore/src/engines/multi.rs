@@ -0,0 +1,497 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Consensus engine switching between other engines at fork blocks.
+//!
+//! Each engine is active from its activation block until the next one takes over.
+//! The last block of an engine ends its epoch with the initial epoch data of the
+//! engine taking over, generated on that block's state, so that the new engine starts
+//! out as if its first block was built on top of genesis.
+
+use std::cmp;
+use std::collections::BTreeMap;
+use std::sync::{Weak, Arc};
+use ethereum_types::{H256, U256, Address};
+use parking_lot::RwLock;
+use ethkey::{Password, Signature};
+use account_provider::AccountProvider;
+use block::ExecutedBlock;
+use client::EngineClient;
+use engines::{
+	Engine, EthEngine, Seal, EngineError, ConstructedVerifier, EpochChange, Proof,
+	StateDependentProof, ForkChoice, Headers, PendingTransitionStore, Equivocation,
+};
+use error::Error;
+use header::{BlockNumber, Header, ExtendedHeader};
+use machine::{AuxiliaryData, Call, EthereumMachine};
+use snapshot::SnapshotComponents;
+use super::signer::EngineSigner;
+use transaction::SignedTransaction;
+use types::ancestry_action::AncestryAction;
+
+// the initial epoch data of the engine taking over, proven on the state of the
+// last block sealed by the previous engine.
+struct HandoverProof {
+	engine: Arc<EthEngine>,
+	header: Header,
+}
+
+impl StateDependentProof<EthereumMachine> for HandoverProof {
+	fn generate_proof(&self, caller: &Call) -> Result<Vec<u8>, String> {
+		self.engine.genesis_epoch_data(&self.header, caller)
+	}
+
+	fn check_proof(&self, _machine: &EthereumMachine, proof: &[u8]) -> Result<(), String> {
+		match self.engine.epoch_verifier(&self.header, proof) {
+			ConstructedVerifier::Err(e) => Err(format!("invalid handover proof: {}", e)),
+			_ => Ok(()),
+		}
+	}
+}
+
+/// Engine delegating to one of several engines, each active from its activation block.
+pub struct MultiEngine {
+	machine: EthereumMachine,
+	engines: BTreeMap<BlockNumber, Arc<EthEngine>>,
+	client: RwLock<Option<Weak<EngineClient>>>,
+	signer: RwLock<EngineSigner>,
+}
+
+impl MultiEngine {
+	/// Create a new engine from a map of activation blocks to engines.
+	pub fn new(engines: BTreeMap<BlockNumber, Arc<EthEngine>>, machine: EthereumMachine) -> Self {
+		assert!(engines.get(&0u64).is_some(), "Engine has to be specified from block 0.");
+		MultiEngine {
+			machine: machine,
+			engines: engines,
+			client: RwLock::new(None),
+			signer: Default::default(),
+		}
+	}
+
+	// get the engine which is active at the given block number.
+	fn engine_by_number(&self, number: BlockNumber) -> &EthEngine {
+		let (_, engine) = self.engines.iter()
+			.rev()
+			.find(|&(block, _)| *block <= number)
+			.expect("constructor validation ensures that there is an engine for block 0;
+					 block 0 is less than any uint;
+					 qed");
+
+		&**engine
+	}
+
+	// get the engine which seals the block on top of the current best block.
+	// only used for operations which aren't tied to a given block.
+	fn current_engine(&self) -> &EthEngine {
+		let number = self.client.read().as_ref()
+			.and_then(|weak| weak.upgrade())
+			.map_or(0, |client| client.chain_info().best_block_number + 1);
+
+		self.engine_by_number(number)
+	}
+
+	// the engine taking over at the given block, if any.
+	fn activated_at(&self, number: BlockNumber) -> Option<&EthEngine> {
+		if number == 0 {
+			return None;
+		}
+
+		self.engines.get(&number).map(|engine| &**engine)
+	}
+
+	// seal fields of the previous engine cannot be interpreted by the one taking over,
+	// so the parent of an activation block is presented with blank seal fields.
+	fn handover_parent(engine: &EthEngine, parent: &Header) -> Header {
+		let mut parent = parent.clone();
+		let fields = engine.seal_fields(&parent);
+		parent.set_seal(vec![::rlp::encode(&0u8); fields]);
+		parent
+	}
+}
+
+impl Engine<EthereumMachine> for MultiEngine {
+	fn name(&self) -> &str {
+		self.current_engine().name()
+	}
+
+	fn machine(&self) -> &EthereumMachine { &self.machine }
+
+	fn seal_fields(&self, header: &Header) -> usize {
+		self.engine_by_number(header.number()).seal_fields(header)
+	}
+
+	fn extra_info(&self, header: &Header) -> BTreeMap<String, String> {
+		self.engine_by_number(header.number()).extra_info(header)
+	}
+
+	fn maximum_uncle_count(&self, block: BlockNumber) -> usize {
+		self.engine_by_number(block).maximum_uncle_count(block)
+	}
+
+	fn maximum_uncle_age(&self, block: BlockNumber) -> usize {
+		self.engine_by_number(block).maximum_uncle_age(block)
+	}
+
+	fn maximum_gas_limit(&self, block: BlockNumber) -> Option<U256> {
+		self.engine_by_number(block).maximum_gas_limit(block)
+	}
+
+	fn on_new_block(
+		&self,
+		block: &mut ExecutedBlock,
+		epoch_begin: bool,
+		ancestry: &mut Iterator<Item=ExtendedHeader>,
+	) -> Result<(), Error> {
+		let number = block.header.number();
+		self.engine_by_number(number).on_new_block(block, epoch_begin, ancestry)
+	}
+
+	fn on_close_block(&self, block: &mut ExecutedBlock) -> Result<(), Error> {
+		let number = block.header.number();
+		self.engine_by_number(number).on_close_block(block)
+	}
+
+	fn on_seal_block(&self, block: &mut ExecutedBlock) -> Result<(), Error> {
+		let number = block.header.number();
+		self.engine_by_number(number).on_seal_block(block)
+	}
+
+	fn generate_engine_transactions(&self, block: &ExecutedBlock) -> Result<Vec<SignedTransaction>, Error> {
+		self.engine_by_number(block.header.number()).generate_engine_transactions(block)
+	}
+
+	fn seals_internally(&self) -> Option<bool> {
+		self.current_engine().seals_internally()
+	}
+
+	fn generate_seal(&self, block: &ExecutedBlock, parent: &Header) -> Seal {
+		match self.activated_at(block.header.number()) {
+			Some(engine) => engine.generate_seal(block, &Self::handover_parent(engine, parent)),
+			None => self.engine_by_number(block.header.number()).generate_seal(block, parent),
+		}
+	}
+
+	fn verify_local_seal(&self, header: &Header) -> Result<(), Error> {
+		self.engine_by_number(header.number()).verify_local_seal(header)
+	}
+
+	fn verify_block_basic(&self, header: &Header) -> Result<(), Error> {
+		self.engine_by_number(header.number()).verify_block_basic(header)
+	}
+
+	fn verify_block_unordered(&self, header: &Header) -> Result<(), Error> {
+		self.engine_by_number(header.number()).verify_block_unordered(header)
+	}
+
+	fn verify_block_family(&self, header: &Header, parent: &Header) -> Result<(), Error> {
+		match self.activated_at(header.number()) {
+			Some(engine) => engine.verify_block_family(header, &Self::handover_parent(engine, parent)),
+			None => self.engine_by_number(header.number()).verify_block_family(header, parent),
+		}
+	}
+
+	fn verify_block_external(&self, header: &Header) -> Result<(), Error> {
+		self.engine_by_number(header.number()).verify_block_external(header)
+	}
+
+	fn genesis_epoch_data(&self, header: &Header, call: &Call) -> Result<Vec<u8>, String> {
+		// an engine activated at block 1 takes over right from genesis.
+		self.engine_by_number(header.number() + 1).genesis_epoch_data(header, call)
+	}
+
+	fn signals_epoch_end(&self, header: &Header, aux: AuxiliaryData)
+		-> EpochChange<EthereumMachine>
+	{
+		match self.engines.get(&(header.number() + 1)) {
+			Some(next) => {
+				let proof = Arc::new(HandoverProof {
+					engine: next.clone(),
+					header: header.clone(),
+				});
+				EpochChange::Yes(Proof::WithState(proof as Arc<_>))
+			},
+			None => self.engine_by_number(header.number()).signals_epoch_end(header, aux),
+		}
+	}
+
+	fn is_epoch_end(
+		&self,
+		chain_head: &Header,
+		finalized: &[H256],
+		chain: &Headers<Header>,
+		transition_store: &PendingTransitionStore,
+	) -> Option<Vec<u8>> {
+		// handovers take effect immediately.
+		if self.engines.contains_key(&(chain_head.number() + 1)) {
+			return transition_store(chain_head.hash()).map(|pending| pending.proof);
+		}
+
+		self.engine_by_number(chain_head.number()).is_epoch_end(chain_head, finalized, chain, transition_store)
+	}
+
+	fn is_epoch_end_light(
+		&self,
+		chain_head: &Header,
+		chain: &Headers<Header>,
+		transition_store: &PendingTransitionStore,
+	) -> Option<Vec<u8>> {
+		if self.engines.contains_key(&(chain_head.number() + 1)) {
+			return transition_store(chain_head.hash()).map(|pending| pending.proof);
+		}
+
+		self.engine_by_number(chain_head.number()).is_epoch_end_light(chain_head, chain, transition_store)
+	}
+
+	fn epoch_verifier<'a>(&self, header: &Header, proof: &'a [u8]) -> ConstructedVerifier<'a, EthereumMachine> {
+		// the proof of a transition at `header` verifies the blocks after it.
+		self.engine_by_number(header.number() + 1).epoch_verifier(header, proof)
+	}
+
+	fn populate_from_parent(&self, header: &mut Header, parent: &Header) {
+		match self.activated_at(header.number()) {
+			Some(engine) => engine.populate_from_parent(header, &Self::handover_parent(engine, parent)),
+			None => self.engine_by_number(header.number()).populate_from_parent(header, parent),
+		}
+	}
+
+	fn gas_limit_override(&self, header: &Header) -> Result<Option<U256>, Error> {
+		self.engine_by_number(header.number()).gas_limit_override(header)
+	}
+
+	fn handle_message(&self, message: &[u8]) -> Result<(), EngineError> {
+		// peers around a handover may still be on the previous engine, so the message
+		// goes to the latest engine expecting messages at all.
+		for engine in self.engines.values().rev() {
+			match engine.handle_message(message) {
+				Err(EngineError::UnexpectedMessage) => continue,
+				result => return result,
+			}
+		}
+
+		Err(EngineError::UnexpectedMessage)
+	}
+
+	fn is_proposal(&self, verified_header: &Header) -> bool {
+		self.engine_by_number(verified_header.number()).is_proposal(verified_header)
+	}
+
+	fn set_signer(&self, ap: Arc<AccountProvider>, address: Address, password: Password) {
+		for engine in self.engines.values() {
+			engine.set_signer(ap.clone(), address, password.clone());
+		}
+		self.signer.write().set(ap, address, password);
+	}
+
+	fn sign(&self, hash: H256) -> Result<Signature, Error> {
+		Ok(self.signer.read().sign(hash)?)
+	}
+
+	fn signers(&self, header: &Header) -> Option<Vec<Address>> {
+		self.engine_by_number(header.number() + 1).signers(header)
+	}
+
+	fn propose_signer(&self, address: Address, authorize: bool) -> bool {
+		self.current_engine().propose_signer(address, authorize)
+	}
+
+	fn register_client(&self, client: Weak<EngineClient>) {
+		for engine in self.engines.values() {
+			engine.register_client(client.clone());
+		}
+
+		*self.client.write() = Some(client);
+	}
+
+	fn step(&self) {
+		self.current_engine().step()
+	}
+
+	fn stop(&self) {
+		for engine in self.engines.values() {
+			engine.stop();
+		}
+	}
+
+	fn snapshot_components(&self, block: BlockNumber) -> Option<Box<SnapshotComponents>> {
+		self.engine_by_number(block).snapshot_components(block)
+	}
+
+	fn supports_warp(&self) -> bool {
+		self.engines.values().all(|engine| engine.supports_warp())
+	}
+
+	fn open_block_header_timestamp(&self, parent_timestamp: u64) -> u64 {
+		self.current_engine().open_block_header_timestamp(parent_timestamp)
+	}
+
+	fn is_timestamp_valid(&self, header_timestamp: u64, parent_timestamp: u64, block: BlockNumber) -> bool {
+		self.engine_by_number(block).is_timestamp_valid(header_timestamp, parent_timestamp, block)
+	}
+
+	fn ancestry_actions(&self, block: &ExecutedBlock, ancestry: &mut Iterator<Item=ExtendedHeader>) -> Vec<AncestryAction> {
+		self.engine_by_number(block.header.number()).ancestry_actions(block, ancestry)
+	}
+
+	fn finality_depth(&self, best_block: BlockNumber) -> Option<BlockNumber> {
+		self.engine_by_number(best_block).finality_depth(best_block)
+	}
+
+	fn progpow_transition(&self) -> Option<BlockNumber> {
+		// the first transition taking place while its engine is active.
+		let mut activations = self.engines.iter().peekable();
+		while let Some((start, engine)) = activations.next() {
+			let end = activations.peek().map_or(BlockNumber::max_value(), |&(next, _)| *next);
+			if let Some(transition) = engine.progpow_transition() {
+				let transition = cmp::max(transition, *start);
+				if transition < end {
+					return Some(transition);
+				}
+			}
+		}
+
+		None
+	}
+
+	fn seals_with_ethash(&self) -> bool {
+		self.current_engine().seals_with_ethash()
+	}
+
+	fn seal_on_demand(&self) -> bool {
+		// engines which don't seal on demand ignore the request.
+		self.engines.values().fold(false, |requested, engine| engine.seal_on_demand() || requested)
+	}
+
+	fn can_seal_on_demand(&self) -> bool {
+		self.current_engine().can_seal_on_demand()
+	}
+
+	fn seal_requested(&self) -> bool {
+		self.current_engine().seal_requested()
+	}
+
+	fn set_block_period(&self, period: u64) -> bool {
+		self.current_engine().set_block_period(period)
+	}
+
+	fn increase_time(&self, seconds: u64) -> Option<u64> {
+		self.current_engine().increase_time(seconds)
+	}
+
+	fn equivocations(&self) -> Vec<Equivocation> {
+		self.engines.values().flat_map(|engine| engine.equivocations()).collect()
+	}
+
+	fn fork_choice(&self, new: &ExtendedHeader, current: &ExtendedHeader) -> ForkChoice {
+		self.engine_by_number(new.header.number()).fork_choice(new, current)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ethereum_types::{H64, H256, H520, U128, U256, Address};
+	use rlp::encode;
+	use engines::{EpochChange, Proof};
+	use engines::epoch::PendingTransition;
+	use header::Header;
+	use machine::AuxiliaryData;
+	use spec::Spec;
+
+	fn proof_of_work_header(number: u64) -> Header {
+		let mut header = Header::default();
+		header.set_number(number);
+		header.set_seal(vec![encode(&H256::zero()), encode(&H64::zero())]);
+		header
+	}
+
+	#[test]
+	fn delegates_to_engine_active_at_block() {
+		let spec = Spec::new_test_engine_multi();
+		let engine = &*spec.engine;
+		assert_eq!(engine.name(), "Ethash");
+
+		let parent = proof_of_work_header(1);
+		assert!(engine.extra_info(&parent).contains_key("mixHash"));
+		assert_eq!(engine.maximum_uncle_count(1), 2);
+
+		let mut header = Header::default();
+		header.set_number(2);
+		header.set_parent_hash(parent.hash());
+		header.set_seal(vec![encode(&10usize), encode(&H520::default())]);
+		assert!(engine.extra_info(&header).contains_key("step"));
+		assert_eq!(engine.maximum_uncle_count(2), 0);
+
+		assert!(engine.maximum_gas_limit(1).is_some());
+		assert!(engine.maximum_gas_limit(2).is_none());
+		assert!(engine.snapshot_components(1).is_some());
+		assert_eq!(engine.progpow_transition(), None);
+	}
+
+	#[test]
+	fn ends_ethash_rules_at_handover() {
+		let spec = Spec::new_test_engine_multi();
+		let machine = spec.engine.machine();
+		assert!(machine.ethash_extensions_at(1).is_some());
+		assert!(machine.ethash_extensions_at(2).is_none());
+	}
+
+	#[test]
+	fn verifies_activation_block_against_previous_engine() {
+		let spec = Spec::new_test_engine_multi();
+		let engine = &*spec.engine;
+
+		let parent = proof_of_work_header(1);
+		let mut header = Header::default();
+		header.set_number(2);
+		header.set_parent_hash(parent.hash());
+		header.set_seal(vec![encode(&10usize), encode(&H520::default())]);
+		header.set_difficulty(U256::from(U128::max_value()) - U256::from(10));
+		assert!(engine.verify_block_family(&header, &parent).is_ok());
+
+		let mut header = Header::default();
+		header.set_number(2);
+		engine.populate_from_parent(&mut header, &parent);
+		assert!(*header.difficulty() < U256::from(U128::max_value()));
+	}
+
+	#[test]
+	fn hands_over_epoch_before_activation_block() {
+		let spec = Spec::new_test_engine_multi();
+		let engine = &*spec.engine;
+		let aux = || AuxiliaryData { bytes: None, receipts: None };
+
+		match engine.signals_epoch_end(&proof_of_work_header(0), aux()) {
+			EpochChange::No => {},
+			_ => panic!("no handover before the last block of an engine"),
+		}
+
+		match engine.signals_epoch_end(&proof_of_work_header(1), aux()) {
+			EpochChange::Yes(Proof::WithState(proof)) => {
+				let call = |_: Address, _: Vec<u8>| -> Result<(Vec<u8>, Vec<Vec<u8>>), String> {
+					Err("no state".into())
+				};
+				let bytes = proof.generate_proof(&call).unwrap();
+				assert!(proof.check_proof(engine.machine(), &bytes).is_ok());
+			},
+			_ => panic!("last block of an engine has to end its epoch"),
+		}
+
+		let headers = |_: H256| -> Option<Header> { None };
+		let transition_store = |_: H256| Some(PendingTransition { proof: vec![1, 2, 3] });
+		assert_eq!(engine.is_epoch_end(&proof_of_work_header(0), &[], &headers, &transition_store), None);
+		assert_eq!(engine.is_epoch_end(&proof_of_work_header(1), &[], &headers, &transition_store), Some(vec![1, 2, 3]));
+	}
+}
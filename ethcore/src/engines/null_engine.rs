@@ -101,7 +101,7 @@ impl<M: WithBalances + WithRewards> Engine<M> for NullEngine<M>
 		Ok(())
 	}
 
-	fn snapshot_components(&self) -> Option<Box<::snapshot::SnapshotComponents>> {
+	fn snapshot_components(&self, _block: BlockNumber) -> Option<Box<::snapshot::SnapshotComponents>> {
 		Some(Box::new(::snapshot::PowSnapshot::new(10000, 10000)))
 	}
 
@@ -18,7 +18,7 @@
 
 use std::sync::Arc;
 use ethereum_types::{H256, Address};
-use ethkey::{Password, Signature};
+use ethkey::{Password, Public, Signature};
 use account_provider::{self, AccountProvider};
 
 /// Everything that an Engine needs to sign messages.
@@ -52,6 +52,19 @@ impl EngineSigner {
 		self.account_provider.sign(self.address.unwrap_or_else(Default::default), self.password.clone(), hash)
 	}
 
+	/// Decrypt a message encrypted to the signing account's public key.
+	pub fn decrypt(&self, auth_data: &[u8], cipher: &[u8]) -> Result<Vec<u8>, account_provider::SignError> {
+		self.account_provider.decrypt(self.address.unwrap_or_else(Default::default), self.password.clone(), auth_data, cipher)
+	}
+
+	/// Public key of the signing account.
+	pub fn public(&self) -> Option<Public> {
+		match (self.address, self.password.as_ref()) {
+			(Some(address), Some(password)) => self.account_provider.account_public(address, password).ok(),
+			_ => None,
+		}
+	}
+
 	/// Signing address.
 	pub fn address(&self) -> Option<Address> {
 		self.address.clone()
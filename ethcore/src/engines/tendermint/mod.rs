@@ -24,6 +24,7 @@
 
 mod message;
 mod params;
+mod wal;
 
 use std::sync::{Weak, Arc};
 use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
@@ -49,6 +50,7 @@ use super::transition::TransitionHandler;
 use super::vote_collector::VoteCollector;
 use self::message::*;
 use self::params::TendermintParams;
+use self::wal::{Wal, WAL_KEY};
 use machine::{AuxiliaryData, EthereumMachine};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
@@ -96,6 +98,8 @@ pub struct Tendermint {
 	proposal_parent: RwLock<H256>,
 	/// Last block proposed by this validator.
 	last_proposed: RwLock<H256>,
+	/// Our votes and lock at the current height, persisted before they take effect.
+	wal: RwLock<Wal>,
 	/// Set used to determine the current validators.
 	validators: Box<ValidatorSet>,
 	/// Reward per block, in base units.
@@ -182,6 +186,7 @@ impl Tendermint {
 				proposal: RwLock::new(None),
 				proposal_parent: Default::default(),
 				last_proposed: Default::default(),
+				wal: RwLock::new(Wal::new(1)),
 				validators: our_params.validators,
 				block_reward: our_params.block_reward,
 				machine: machine,
@@ -217,15 +222,82 @@ impl Tendermint {
 		}
 	}
 
+	/// Update the write-ahead log and persist it, if we are a validator.
+	fn update_wal<F: FnOnce(&mut Wal)>(&self, f: F) {
+		f(&mut *self.wal.write());
+
+		if !self.signer.read().is_some() { return }
+		if let Some(ref weak) = *self.client.read() {
+			if let Some(c) = weak.upgrade() {
+				c.set_engine_data(WAL_KEY, ::rlp::encode(&*self.wal.read()));
+			}
+		}
+	}
+
+	/// Restore the consensus state logged before a restart at the current height.
+	fn replay_wal(&self) {
+		let height = self.height.load(AtomicOrdering::SeqCst);
+		let logged = match *self.client.read() {
+			Some(ref weak) => weak.upgrade().and_then(|c| c.engine_data(WAL_KEY)),
+			None => None,
+		};
+
+		let wal = match logged.map(|bytes| ::rlp::decode::<Wal>(&bytes)) {
+			Some(Ok(ref wal)) if wal.height == height => wal.clone(),
+			Some(Err(e)) => {
+				warn!(target: "engine", "Discarding malformed consensus log: {:?}", e);
+				Wal::new(height)
+			},
+			// nothing logged or the logged height has been committed already.
+			_ => Wal::new(height),
+		};
+
+		for vote in &wal.votes {
+			match vote.verify() {
+				Ok(voter) => { self.votes.vote(vote.clone(), voter); },
+				Err(e) => warn!(target: "engine", "Discarding logged vote {:?}: {}", vote, e),
+			}
+		}
+
+		let last_vote = wal.last_vote().map(|vote| vote.vote_step.clone());
+		let last_proposal = wal.votes.iter()
+			.filter(|vote| vote.vote_step.step == Step::Propose)
+			.max_by_key(|vote| vote.vote_step.view)
+			.cloned();
+		*self.lock_change.write() = wal.lock_change.clone();
+		self.last_lock.store(wal.last_lock, AtomicOrdering::SeqCst);
+		*self.wal.write() = wal;
+
+		if let Some(vote_step) = last_vote {
+			debug!(target: "engine", "Resuming at {:?} from the consensus log.", vote_step);
+			self.view.store(vote_step.view, AtomicOrdering::SeqCst);
+			if let Some(proposal) = last_proposal {
+				*self.last_proposed.write() = proposal.block_hash.unwrap_or_else(H256::zero);
+				if proposal.vote_step.view == vote_step.view {
+					*self.proposal.write() = proposal.block_hash;
+				}
+			}
+			// Entering the step of our last vote broadcasts it again instead of signing a new one.
+			self.to_step(vote_step.step);
+		}
+	}
+
 	fn generate_message(&self, block_hash: Option<BlockHash>) -> Option<Bytes> {
 		let h = self.height.load(AtomicOrdering::SeqCst);
 		let r = self.view.load(AtomicOrdering::SeqCst);
 		let s = *self.step.read();
-		let vote_info = message_info_rlp(&VoteStep::new(h, r, s), block_hash);
+		let vote_step = VoteStep::new(h, r, s);
+		if let Some(previous) = self.wal.read().vote_at(&vote_step) {
+			debug!(target: "engine", "Already voted {:?} at {:?}, repeating the vote.", previous.block_hash, vote_step);
+			return Some(::rlp::encode(previous));
+		}
+
+		let vote_info = message_info_rlp(&vote_step, block_hash);
 		match (self.signer.read().address(), self.sign(keccak(&vote_info)).map(Into::into)) {
 			(Some(validator), Ok(signature)) => {
 				let message_rlp = message_full_rlp(&signature, &vote_info);
 				let message = ConsensusMessage::new(signature, h, r, s, block_hash);
+				self.update_wal(|wal| wal.votes.push(message.clone()));
 				self.votes.vote(message.clone(), validator);
 				debug!(target: "engine", "Generated {:?} as {}.", message, validator);
 				self.handle_valid_message(&message);
@@ -264,6 +336,7 @@ impl Tendermint {
 		self.view.store(0, AtomicOrdering::SeqCst);
 		*self.lock_change.write() = None;
 		*self.proposal.write() = None;
+		self.update_wal(|wal| *wal = Wal::new(new_height));
 	}
 
 	/// Use via step_service to transition steps.
@@ -289,6 +362,7 @@ impl Tendermint {
 					Some(ref m) if self.is_view(m) && m.block_hash.is_some() => {
 						trace!(target: "engine", "Setting last lock: {}", m.vote_step.view);
 						self.last_lock.store(m.vote_step.view, AtomicOrdering::SeqCst);
+						self.update_wal(|wal| wal.last_lock = m.vote_step.view);
 						m.block_hash
 					},
 					_ => None,
@@ -390,6 +464,7 @@ impl Tendermint {
 			&& self.has_enough_aligned_votes(message);
 		if lock_change {
 			trace!(target: "engine", "handle_valid_message: Lock change.");
+			self.update_wal(|wal| wal.lock_change = Some(message.clone()));
 			*self.lock_change.write() = Some(message.clone());
 		}
 		// Check if it can affect the step transition.
@@ -449,7 +524,7 @@ impl Engine<EthereumMachine> for Tendermint {
 
 	fn maximum_uncle_count(&self, _block: BlockNumber) -> usize { 0 }
 
-	fn maximum_uncle_age(&self) -> usize { 0 }
+	fn maximum_uncle_age(&self, _block: BlockNumber) -> usize { 0 }
 
 	fn populate_from_parent(&self, header: &mut Header, parent: &Header) {
 		// Chain scoring: total weight is sqrt(U256::max_value())*height - view
@@ -484,7 +559,9 @@ impl Engine<EthereumMachine> for Tendermint {
 		if let Ok(signature) = self.sign(keccak(&vote_info)).map(Into::into) {
 			// Insert Propose vote.
 			debug!(target: "engine", "Submitting proposal {} at height {} view {}.", header.bare_hash(), height, view);
-			self.votes.vote(ConsensusMessage::new(signature, height, view, Step::Propose, bh), *author);
+			let proposal = ConsensusMessage::new(signature, height, view, Step::Propose, bh);
+			self.update_wal(|wal| wal.votes.push(proposal.clone()));
+			self.votes.vote(proposal, *author);
 			// Remember the owned block.
 			*self.last_proposed.write() = header.bare_hash();
 			// Remember proposal for later seal submission.
@@ -698,7 +775,7 @@ impl Engine<EthereumMachine> for Tendermint {
 		Ok(self.signer.read().sign(hash)?)
 	}
 
-	fn snapshot_components(&self) -> Option<Box<::snapshot::SnapshotComponents>> {
+	fn snapshot_components(&self, _block: BlockNumber) -> Option<Box<::snapshot::SnapshotComponents>> {
 		Some(Box::new(::snapshot::PoaSnapshot))
 	}
 
@@ -772,11 +849,17 @@ impl Engine<EthereumMachine> for Tendermint {
 		}
 		*self.client.write() = Some(client.clone());
 		self.validators.register_client(client);
+		self.replay_wal();
 	}
 
 	fn fork_choice(&self, new: &ExtendedHeader, current: &ExtendedHeader) -> super::ForkChoice {
 		super::total_difficulty_fork_choice(new, current)
 	}
+
+	/// Blocks are only issued once committed by a supermajority.
+	fn finality_depth(&self, _best_block: BlockNumber) -> Option<BlockNumber> {
+		Some(0)
+	}
 }
 
 #[cfg(test)]
@@ -788,7 +871,7 @@ mod tests {
 	use block::*;
 	use error::{Error, ErrorKind, BlockError};
 	use header::Header;
-	use client::ChainInfo;
+	use client::{ChainInfo, Client};
 	use miner::MinerService;
 	use test_helpers::{
 		TestNotify, get_temp_state_db, generate_dummy_client,
@@ -851,6 +934,39 @@ mod tests {
 		addr
 	}
 
+	/// Start a fresh engine for the validator "1" on the database of the given client.
+	fn restart(client: &Arc<Client>, tap: &Arc<AccountProvider>, validator: Address) -> Arc<EthEngine> {
+		let engine = Spec::new_test_tendermint().engine;
+		engine.set_signer(tap.clone(), validator, "1".into());
+		engine.register_client(Arc::downgrade(client) as _);
+		engine
+	}
+
+	/// Import a transaction, making the registered signer propose a block with it.
+	fn propose_transaction(client: &Client) {
+		use ethkey::{Generator, Random};
+		use transaction::{Transaction, Action};
+
+		let keypair = Random.generate().unwrap();
+		let transaction = Transaction {
+			action: Action::Create,
+			value: U256::zero(),
+			data: "3331600055".from_hex().unwrap(),
+			gas: U256::from(100_000),
+			gas_price: U256::zero(),
+			nonce: U256::zero(),
+		}.sign(keypair.secret(), None);
+		client.miner().import_own_transaction(client, transaction.into()).unwrap();
+	}
+
+	/// Votes of the given validator broadcast so far.
+	fn broadcast_votes(notify: &TestNotify, validator: Address) -> Vec<ConsensusMessage> {
+		notify.messages.read().iter()
+			.map(|m| ::rlp::decode::<ConsensusMessage>(m).unwrap())
+			.filter(|m| m.verify().unwrap() == validator)
+			.collect()
+	}
+
 	#[test]
 	fn has_valid_metadata() {
 		let engine = Spec::new_test_tendermint().engine;
@@ -1070,6 +1186,67 @@ mod tests {
 		assert_eq!(client.chain_info().best_block_number, 1);
 	}
 
+	#[test]
+	fn restarted_validator_repeats_its_votes() {
+		let tap = Arc::new(AccountProvider::transient_provider());
+		insert_and_unlock(&tap, "0");
+		let v1 = insert_and_unlock(&tap, "1");
+		let client = generate_dummy_client_with_spec_and_accounts(Spec::new_test_tendermint, Some(tap.clone()));
+		let engine = client.engine();
+
+		client.miner().set_author(v1.clone(), Some("1".into())).unwrap();
+
+		let notify = Arc::new(TestNotify::default());
+		client.add_notify(notify.clone());
+		engine.register_client(Arc::downgrade(&client) as _);
+
+		// Propose and prevote for the proposal.
+		propose_transaction(&client);
+		let proposal = Some(client.miner().pending_block(0).unwrap().header.bare_hash());
+		engine.step();
+		assert_eq!(broadcast_votes(&notify, v1).len(), 1);
+
+		// The restarted validator doesn't know the proposal, but must not prevote nil.
+		let restarted = restart(&client, &tap, v1);
+		let votes = broadcast_votes(&notify, v1);
+		assert_eq!(votes.len(), 2);
+		assert!(votes.iter().all(|vote| vote.vote_step == VoteStep::new(1, 0, Step::Prevote) && vote.block_hash == proposal));
+
+		// Nor propose another block in the same view.
+		let spec = Spec::new_test_tendermint();
+		let db = spec.ensure_db_good(get_temp_state_db(), &Default::default()).unwrap();
+		let genesis_header = spec.genesis_header();
+		let last_hashes = Arc::new(vec![genesis_header.hash()]);
+		let b = OpenBlock::new(restarted.as_ref(), Default::default(), false, db, &genesis_header, last_hashes, v1, (3141562.into(), 31415620.into()), vec![], false, &mut Vec::new().into_iter()).unwrap();
+		let b = b.close().unwrap();
+		assert_eq!(restarted.generate_seal(b.block(), &genesis_header), Seal::None);
+	}
+
+	#[test]
+	fn restarted_validator_completes_commit() {
+		let tap = Arc::new(AccountProvider::transient_provider());
+		let v0 = insert_and_unlock(&tap, "0");
+		let v1 = insert_and_unlock(&tap, "1");
+		let client = generate_dummy_client_with_spec_and_accounts(Spec::new_test_tendermint, Some(tap.clone()));
+		let engine = client.engine();
+
+		client.miner().set_author(v1.clone(), Some("1".into())).unwrap();
+		engine.register_client(Arc::downgrade(&client) as _);
+
+		// Propose, prevote and precommit.
+		propose_transaction(&client);
+		let proposal = Some(client.miner().pending_block(0).unwrap().header.bare_hash());
+		engine.step();
+		vote(engine, |mh| tap.sign(v0, None, mh).map(H520::from), 1, 0, Step::Prevote, proposal);
+
+		// Restart before the last precommit arrives.
+		let restarted = restart(&client, &tap, v1);
+		assert_eq!(client.chain_info().best_block_number, 0);
+
+		vote(restarted.as_ref(), |mh| tap.sign(v0, None, mh).map(H520::from), 1, 0, Step::Precommit, proposal);
+		assert_eq!(client.chain_info().best_block_number, 1);
+	}
+
 	#[test]
 	fn epoch_verifier_verify_light() {
 		use ethkey::Error as EthkeyError;
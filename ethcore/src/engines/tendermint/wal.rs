@@ -0,0 +1,113 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Write-ahead log of the consensus state of this validator.
+//!
+//! Our votes and the locked proposal are persisted before they take effect, so that a
+//! validator restarted in the middle of a round neither signs a conflicting vote nor
+//! forgets a lock it has to honour.
+
+use rlp::{Rlp, RlpStream, Encodable, Decodable, DecoderError};
+use super::{Height, View};
+use super::message::{ConsensusMessage, VoteStep};
+
+/// Key of the log in the engine data of the client.
+pub const WAL_KEY: &'static [u8] = b"tendermint_wal";
+
+/// Consensus state of this validator at the current height.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Wal {
+	/// Height the log is for.
+	pub height: Height,
+	/// Votes signed by us at this height, in order.
+	pub votes: Vec<ConsensusMessage>,
+	/// Prevote majority we are locked on.
+	pub lock_change: Option<ConsensusMessage>,
+	/// View of the last lock.
+	pub last_lock: View,
+}
+
+impl Wal {
+	/// Empty log for the given height.
+	pub fn new(height: Height) -> Self {
+		Wal { height: height, ..Default::default() }
+	}
+
+	/// Our vote for the given step, if we have signed one.
+	pub fn vote_at(&self, vote_step: &VoteStep) -> Option<&ConsensusMessage> {
+		self.votes.iter().find(|v| v.vote_step == *vote_step)
+	}
+
+	/// Our most recent vote.
+	pub fn last_vote(&self) -> Option<&ConsensusMessage> {
+		self.votes.iter().max_by_key(|v| v.vote_step.clone())
+	}
+}
+
+impl Encodable for Wal {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(4)
+			.append(&self.height)
+			.append_list(&self.votes);
+		match self.lock_change {
+			Some(ref lock) => s.begin_list(1).append(lock),
+			None => s.begin_list(0),
+		};
+		s.append(&self.last_lock);
+	}
+}
+
+impl Decodable for Wal {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		let lock = rlp.at(2)?;
+		Ok(Wal {
+			height: rlp.val_at(0)?,
+			votes: rlp.list_at(1)?,
+			lock_change: match lock.item_count()? {
+				0 => None,
+				1 => Some(lock.val_at(0)?),
+				_ => return Err(DecoderError::RlpIncorrectListLen),
+			},
+			last_lock: rlp.val_at(3)?,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ethereum_types::{H256, H520};
+	use super::super::Step;
+	use super::super::message::{ConsensusMessage, VoteStep};
+	use super::Wal;
+
+	#[test]
+	fn wal_roundtrip() {
+		let prevote = ConsensusMessage::new(H520::from(1), 3, 1, Step::Prevote, Some(H256::from(7)));
+		let precommit = ConsensusMessage::new(H520::from(2), 3, 1, Step::Precommit, None);
+
+		let mut wal = Wal::new(3);
+		assert_eq!(::rlp::decode::<Wal>(&::rlp::encode(&wal)).unwrap(), wal);
+
+		wal.votes = vec![prevote.clone(), precommit.clone()];
+		wal.lock_change = Some(prevote.clone());
+		wal.last_lock = 1;
+		assert_eq!(::rlp::decode::<Wal>(&::rlp::encode(&wal)).unwrap(), wal);
+
+		assert_eq!(wal.vote_at(&VoteStep::new(3, 1, Step::Prevote)), Some(&prevote));
+		assert_eq!(wal.vote_at(&VoteStep::new(3, 2, Step::Prevote)), None);
+		assert_eq!(wal.last_vote(), Some(&precommit));
+	}
+}
@@ -110,6 +110,8 @@ pub struct EthashParams {
 	pub block_reward_contract: Option<BlockRewardContract>,
 	/// Difficulty bomb delays.
 	pub difficulty_bomb_delays: BTreeMap<BlockNumber, BlockNumber>,
+	/// Block to transition to ProgPoW.
+	pub progpow_transition: u64,
 }
 
 impl From<ethjson::spec::EthashParams> for EthashParams {
@@ -158,7 +160,8 @@ impl From<ethjson::spec::EthashParams> for EthashParams {
 			},
 			difficulty_bomb_delays: p.difficulty_bomb_delays.unwrap_or_default().into_iter()
 				.map(|(block, delay)| (block.into(), delay.into()))
-				.collect()
+				.collect(),
+			progpow_transition: p.progpow_transition.map_or(u64::max_value(), Into::into),
 		}
 	}
 }
@@ -179,10 +182,12 @@ impl Ethash {
 		machine: EthereumMachine,
 		optimize_for: T,
 	) -> Arc<Self> {
+		let progpow_transition = ethash_params.progpow_transition;
+
 		Arc::new(Ethash {
 			ethash_params,
 			machine,
-			pow: EthashManager::new(cache_dir.as_ref(), optimize_for.into()),
+			pow: EthashManager::new(cache_dir.as_ref(), optimize_for.into(), progpow_transition),
 		})
 	}
 }
@@ -222,7 +227,7 @@ impl Engine<EthereumMachine> for Arc<Ethash> {
 
 	fn maximum_uncle_count(&self, _block: BlockNumber) -> usize { 2 }
 
-	fn maximum_gas_limit(&self) -> Option<U256> { Some(0x7fff_ffff_ffff_ffffu64.into()) }
+	fn maximum_gas_limit(&self, _block: BlockNumber) -> Option<U256> { Some(0x7fff_ffff_ffff_ffffu64.into()) }
 
 	fn populate_from_parent(&self, header: &mut Header, parent: &Header) {
 		let difficulty = self.calculate_difficulty(header, parent);
@@ -317,7 +322,8 @@ impl Engine<EthereumMachine> for Arc<Ethash> {
 		let difficulty = ethash::boundary_to_difficulty(&H256(quick_get_difficulty(
 			&header.bare_hash().0,
 			seal.nonce.low_u64(),
-			&seal.mix_hash.0
+			&seal.mix_hash.0,
+			header.number() >= self.ethash_params.progpow_transition
 		)));
 
 		if &difficulty < header.difficulty() {
@@ -368,13 +374,20 @@ impl Engine<EthereumMachine> for Arc<Ethash> {
 		engines::ConstructedVerifier::Trusted(Box::new(self.clone()))
 	}
 
-	fn snapshot_components(&self) -> Option<Box<::snapshot::SnapshotComponents>> {
+	fn snapshot_components(&self, _block: BlockNumber) -> Option<Box<::snapshot::SnapshotComponents>> {
 		Some(Box::new(::snapshot::PowSnapshot::new(SNAPSHOT_BLOCKS, MAX_SNAPSHOT_BLOCKS)))
 	}
 
 	fn fork_choice(&self, new: &ExtendedHeader, current: &ExtendedHeader) -> engines::ForkChoice {
 		engines::total_difficulty_fork_choice(new, current)
 	}
+
+	fn progpow_transition(&self) -> Option<BlockNumber> {
+		let transition = self.ethash_params.progpow_transition;
+		if transition == u64::max_value() { None } else { Some(transition) }
+	}
+
+	fn seals_with_ethash(&self) -> bool { true }
 }
 
 impl Ethash {
@@ -520,6 +533,7 @@ mod tests {
 			block_reward_contract: None,
 			block_reward_contract_transition: 0,
 			difficulty_bomb_delays: BTreeMap::new(),
+			progpow_transition: u64::max_value(),
 		}
 	}
 
@@ -145,6 +145,9 @@ pub fn new_mainnet_like() -> Spec { load(None, include_bytes!("../../res/ethereu
 /// Create a new Foundation Byzantium era spec.
 pub fn new_byzantium_test() -> Spec { load(None, include_bytes!("../../res/ethereum/byzantium_test.json")) }
 
+/// Create a new Byzantium era spec with a difficulty low enough to mine blocks on a CPU right away.
+pub fn new_low_difficulty_test() -> Spec { load(None, include_bytes!("../../res/ethereum/low_difficulty_test.json")) }
+
 /// Create a new Foundation Constantinople era spec.
 pub fn new_constantinople_test() -> Spec { load(None, include_bytes!("../../res/ethereum/constantinople_test.json")) }
 
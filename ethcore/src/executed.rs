@@ -166,6 +166,8 @@ pub enum CallError {
 	TransactionNotFound,
 	/// Couldn't find requested block's state in the chain.
 	StatePruned,
+	/// Couldn't find requested block's body, its history has expired.
+	HistoryPruned,
 	/// Couldn't find an amount of gas that didn't result in an exception.
 	Exceptional,
 	/// Corrupt state.
@@ -187,6 +189,7 @@ impl fmt::Display for CallError {
 		let msg = match *self {
 			TransactionNotFound => "Transaction couldn't be found in the chain".into(),
 			StatePruned => "Couldn't find the transaction block's state in the chain".into(),
+			HistoryPruned => "Couldn't find the transaction block's body in the chain, its history has expired".into(),
 			Exceptional => "An exception happened in the execution".into(),
 			StateCorrupt => "Stored state found to be corrupted.".into(),
 			Execution(ref e) => format!("{}", e),
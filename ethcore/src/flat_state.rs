@@ -0,0 +1,861 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Flat state: accounts and storage stored directly under their hashed keys.
+//!
+//! The flat state consists of a persisted layer holding the state of a canonical block
+//! (the base) and in-memory diff layers for the blocks imported on top of it. Reads for
+//! a given block walk the diff layers down to the base and fall back to the database,
+//! which answers with a single lookup instead of a trie traversal.
+//!
+//! Diff layers deeper than the configured depth on the canonical chain are flattened
+//! into the persisted layer. Diff layers are journalled in the database as well, so that
+//! they survive restarts.
+//!
+//! A fresh persisted layer is generated in the background by iterating the state trie
+//! of the base block. Until generation is complete, only the accounts already generated
+//! (ordered by address hash) are served from the persisted layer.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Instant;
+
+use bytes::Bytes;
+use db::{COL_EXTRA, COL_FLAT_STATE};
+use ethereum_types::{H256, U256};
+use error::Error;
+use ethtrie::TrieDB;
+use hash::KECCAK_NULL_RLP;
+use hashdb::HashDB;
+use header::BlockNumber;
+use keccak_hasher::KeccakHasher;
+use kvdb::{DBTransaction, DBValue, KeyValueDB};
+use parking_lot::RwLock;
+use rlp::{self, Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use trie::Trie;
+use types::basic_account::BasicAccount;
+use account_db::AccountDB;
+
+/// Key of the persisted layer description in the extras column.
+const FLAT_BASE_KEY: &'static [u8] = b"flat_base";
+/// Prefix of the journalled diff layers in the extras column.
+const FLAT_DIFF_PREFIX: &'static [u8] = b"flat_diff";
+/// Number of accounts generated before the progress is written out.
+const GENERATION_BATCH: usize = 1024;
+
+/// Whether `key` of the extras column belongs to the flat state. It describes the flat
+/// state column and has to be dropped together with it.
+pub fn is_metadata_key(key: &[u8]) -> bool {
+	key == FLAT_BASE_KEY || (key.starts_with(FLAT_DIFF_PREFIX) && key.len() == FLAT_DIFF_PREFIX.len() + 32)
+}
+
+/// Changes made to the flat state, e.g. by a block.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FlatDiff {
+	/// Account RLPs by address hash, `None` for removed accounts.
+	pub accounts: HashMap<H256, Option<Bytes>>,
+	/// Accounts whose storage was cleared before `storage` was applied.
+	pub wiped: HashSet<H256>,
+	/// Storage values by address hash and key hash. Zero values are removed entries.
+	pub storage: HashMap<(H256, H256), H256>,
+}
+
+impl FlatDiff {
+	/// Note an account update. `None` removes the account along with its storage.
+	pub fn set_account(&mut self, address_hash: H256, account: Option<Bytes>) {
+		if account.is_none() {
+			self.wipe_storage(address_hash);
+		}
+		self.accounts.insert(address_hash, account);
+	}
+
+	/// Note that the storage of an account was cleared.
+	pub fn wipe_storage(&mut self, address_hash: H256) {
+		self.storage.retain(|&(ref address, _), _| *address != address_hash);
+		self.wiped.insert(address_hash);
+	}
+
+	/// Note a storage update.
+	pub fn set_storage(&mut self, address_hash: H256, key_hash: H256, value: H256) {
+		self.storage.insert((address_hash, key_hash), value);
+	}
+
+	/// Apply changes made after the ones in `self`.
+	pub fn extend(&mut self, other: FlatDiff) {
+		for address_hash in other.wiped {
+			self.wipe_storage(address_hash);
+		}
+		self.accounts.extend(other.accounts);
+		self.storage.extend(other.storage);
+	}
+
+	/// Check if there are no changes.
+	pub fn is_empty(&self) -> bool {
+		self.accounts.is_empty() && self.wiped.is_empty() && self.storage.is_empty()
+	}
+}
+
+impl Encodable for FlatDiff {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(3);
+		s.begin_list(self.accounts.len());
+		for (address_hash, account) in &self.accounts {
+			s.begin_list(2).append(address_hash);
+			match *account {
+				Some(ref account) => s.append(account),
+				None => s.append_empty_data(),
+			};
+		}
+		s.begin_list(self.wiped.len());
+		for address_hash in &self.wiped {
+			s.append(address_hash);
+		}
+		s.begin_list(self.storage.len());
+		for (&(ref address_hash, ref key_hash), value) in &self.storage {
+			s.begin_list(3).append(address_hash).append(key_hash).append(value);
+		}
+	}
+}
+
+impl Decodable for FlatDiff {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		let mut diff = FlatDiff::default();
+		for item in rlp.at(0)?.iter() {
+			let account: Bytes = item.val_at(1)?;
+			diff.accounts.insert(item.val_at(0)?, if account.is_empty() { None } else { Some(account) });
+		}
+		for item in rlp.at(1)?.iter() {
+			diff.wiped.insert(item.as_val()?);
+		}
+		for item in rlp.at(2)?.iter() {
+			diff.storage.insert((item.val_at(0)?, item.val_at(1)?), item.val_at(2)?);
+		}
+		Ok(diff)
+	}
+}
+
+/// Changes made by a block on top of its parent.
+struct DiffLayer {
+	number: BlockNumber,
+	parent: H256,
+	/// State root after the block.
+	root: H256,
+	diff: FlatDiff,
+}
+
+impl DiffLayer {
+	fn rlp_bytes(&self) -> Bytes {
+		let mut s = RlpStream::new_list(4);
+		s.append(&self.number).append(&self.parent).append(&self.root).append(&self.diff);
+		s.out()
+	}
+
+	fn decode(bytes: &[u8]) -> Result<Self, DecoderError> {
+		let rlp = Rlp::new(bytes);
+		Ok(DiffLayer {
+			number: rlp.val_at(0)?,
+			parent: rlp.val_at(1)?,
+			root: rlp.val_at(2)?,
+			diff: rlp.val_at(3)?,
+		})
+	}
+}
+
+/// The block the persisted layer corresponds to.
+#[derive(Debug, Clone, PartialEq)]
+struct Base {
+	number: BlockNumber,
+	hash: H256,
+	root: H256,
+	/// Generation progress: `None` when complete, otherwise the address hash of the last
+	/// generated account, if any.
+	generating: Option<Option<H256>>,
+	/// Account following the last generated one whose storage is partially generated,
+	/// with the hash of the last generated storage key.
+	partial: Option<(H256, H256)>,
+}
+
+impl Base {
+	/// Check if the persisted layer contains the given account and its storage.
+	fn covers(&self, address_hash: &H256) -> bool {
+		match self.generating {
+			None => true,
+			Some(None) => false,
+			Some(Some(ref last)) => address_hash <= last,
+		}
+	}
+
+	/// Check if the persisted layer contains the given storage entry.
+	fn covers_storage(&self, address_hash: &H256, key_hash: &H256) -> bool {
+		self.covers(address_hash) || match self.partial {
+			Some((ref address, ref last)) => address == address_hash && key_hash <= last,
+			None => false,
+		}
+	}
+
+	/// Check if the persisted layer contains some of the storage of the given account.
+	fn covers_some_storage(&self, address_hash: &H256) -> bool {
+		self.covers(address_hash) || self.partial.as_ref().map_or(false, |&(ref address, _)| address == address_hash)
+	}
+}
+
+impl Encodable for Base {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		let len = match self.generating {
+			None => 3,
+			Some(_) if self.partial.is_some() => 6,
+			Some(_) => 4,
+		};
+		s.begin_list(len);
+		match self.generating {
+			None => {},
+			Some(None) => { s.append_empty_data(); },
+			Some(Some(ref last)) => { s.append(last); },
+		}
+		s.append(&self.number).append(&self.hash).append(&self.root);
+		if len == 6 {
+			let &(ref address_hash, ref key_hash) = self.partial.as_ref().expect("length is 6 only with partial storage; qed");
+			s.append(address_hash).append(key_hash);
+		}
+	}
+}
+
+impl Decodable for Base {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		let (generating, offset) = match rlp.item_count()? {
+			3 => (None, 0),
+			4 | 6 if rlp.at(0)?.is_empty() => (Some(None), 1),
+			4 | 6 => (Some(Some(rlp.val_at(0)?)), 1),
+			_ => return Err(DecoderError::RlpIncorrectListLen),
+		};
+		let partial = match rlp.item_count()? {
+			6 => Some((rlp.val_at(4)?, rlp.val_at(5)?)),
+			_ => None,
+		};
+		Ok(Base {
+			number: rlp.val_at(offset)?,
+			hash: rlp.val_at(offset + 1)?,
+			root: rlp.val_at(offset + 2)?,
+			generating,
+			partial,
+		})
+	}
+}
+
+#[derive(Default)]
+struct Layers {
+	/// `None` if the flat state was not initialized yet.
+	base: Option<Base>,
+	/// Diff layers by block hash.
+	diffs: HashMap<H256, DiffLayer>,
+}
+
+impl Layers {
+	/// Diff layers from the given block down to the base, provided the block's state
+	/// root is `root`. `None` if the block is not known to the flat state.
+	fn path(&self, block: &H256, root: &H256) -> Option<Vec<&DiffLayer>> {
+		let base = self.base.as_ref()?;
+		let mut path = Vec::new();
+		let mut at = *block;
+		while at != base.hash {
+			let layer = self.diffs.get(&at)?;
+			path.push(layer);
+			at = layer.parent;
+		}
+		let block_root = path.first().map_or(&base.root, |layer| &layer.root);
+		match block_root == root {
+			true => Some(path),
+			false => None,
+		}
+	}
+}
+
+/// Flat account and storage layer, shared by all `StateDB` instances.
+pub struct FlatState {
+	db: Arc<KeyValueDB>,
+	/// Number of blocks kept as diff layers on top of the persisted layer.
+	depth: u64,
+	layers: RwLock<Layers>,
+}
+
+impl FlatState {
+	/// Load the flat state from the database. Diff layers deeper than `depth` blocks on
+	/// the canonical chain are flattened into the persisted layer.
+	pub fn open(db: Arc<KeyValueDB>, depth: u64) -> Self {
+		let base = db.get(COL_EXTRA, FLAT_BASE_KEY)
+			.expect("Low-level database error")
+			.map(|base| rlp::decode::<Base>(&base).expect("Flat state base is written by us; qed"));
+
+		let diffs = match base {
+			Some(_) => db.iter_from_prefix(COL_EXTRA, FLAT_DIFF_PREFIX)
+				.take_while(|&(ref key, _)| key.starts_with(FLAT_DIFF_PREFIX))
+				.filter(|&(ref key, _)| key.len() == FLAT_DIFF_PREFIX.len() + 32)
+				.map(|(key, value)| {
+					let layer = DiffLayer::decode(&value).expect("Flat state diffs are written by us; qed");
+					(H256::from_slice(&key[FLAT_DIFF_PREFIX.len()..]), layer)
+				})
+				.collect(),
+			None => HashMap::new(),
+		};
+
+		FlatState {
+			db,
+			depth,
+			layers: RwLock::new(Layers { base, diffs }),
+		}
+	}
+
+	/// Check if the flat state was initialized.
+	pub fn is_initialized(&self) -> bool {
+		self.layers.read().base.is_some()
+	}
+
+	/// Check if the persisted layer is fully generated.
+	pub fn is_generated(&self) -> bool {
+		self.layers.read().base.as_ref().map_or(false, |base| base.generating.is_none())
+	}
+
+	/// Drop all flat state data and start generating the persisted layer anew from the
+	/// state of the given block.
+	pub fn reset(&self, number: BlockNumber, hash: H256, root: H256) {
+		let mut layers = self.layers.write();
+		info!(target: "flat_state", "Generating flat state from block #{} ({})", number, hash);
+
+		let mut batch = DBTransaction::new();
+		for (key, _) in self.db.iter(COL_FLAT_STATE) {
+			batch.delete(COL_FLAT_STATE, &key);
+		}
+		for hash in layers.diffs.keys() {
+			batch.delete(COL_EXTRA, &diff_key(hash));
+		}
+
+		let base = Base { number, hash, root, generating: Some(None), partial: None };
+		batch.put(COL_EXTRA, FLAT_BASE_KEY, &rlp::encode(&base));
+		self.db.write_buffered(batch);
+
+		layers.base = Some(base);
+		layers.diffs.clear();
+	}
+
+	/// Get an account RLP at the given block, provided its state root is `root`.
+	/// Returns `None` if the flat state can't answer, `Some(None)` if the account doesn't exist.
+	pub fn account(&self, block: &H256, root: &H256, address_hash: &H256) -> Option<Option<Bytes>> {
+		let layers = self.layers.read();
+		let path = layers.path(block, root)?;
+		self.account_at(&layers, &path, address_hash)
+	}
+
+	/// Get a storage value at the given block, provided its state root is `root` and the
+	/// storage root of the account is `storage_root`. Returns `None` if the flat state can't answer.
+	pub fn storage(&self, block: &H256, root: &H256, address_hash: &H256, storage_root: &H256, key_hash: &H256) -> Option<H256> {
+		let layers = self.layers.read();
+		let path = layers.path(block, root)?;
+
+		// values are only valid for the storage the caller is looking at.
+		let account = self.account_at(&layers, &path, address_hash)??;
+		let account: BasicAccount = rlp::decode(&account).ok()?;
+		if account.storage_root != *storage_root {
+			return None;
+		}
+
+		for layer in path {
+			if let Some(value) = layer.diff.storage.get(&(*address_hash, *key_hash)) {
+				return Some(*value);
+			}
+			if layer.diff.wiped.contains(address_hash) {
+				return Some(H256::zero());
+			}
+		}
+
+		let value = self.db.get(COL_FLAT_STATE, &storage_key(address_hash, key_hash))
+			.expect("Low-level database error")
+			.map_or_else(H256::zero, |value| rlp::decode::<U256>(&value).expect("Flat storage values are written by us; qed").into());
+		Some(value)
+	}
+
+	fn account_at(&self, layers: &Layers, path: &[&DiffLayer], address_hash: &H256) -> Option<Option<Bytes>> {
+		for layer in path {
+			if let Some(account) = layer.diff.accounts.get(address_hash) {
+				return Some(account.clone());
+			}
+		}
+
+		let base = layers.base.as_ref().expect("path exists only if the base is known; qed");
+		if !base.covers(address_hash) {
+			return None;
+		}
+		let account = self.db.get(COL_FLAT_STATE, address_hash).expect("Low-level database error");
+		Some(account.map(|account| account.to_vec()))
+	}
+
+	/// Add the changes made by a block, journalling them in the given batch.
+	pub fn insert_diff(&self, batch: &mut DBTransaction, number: BlockNumber, hash: H256, parent: H256, root: H256, diff: FlatDiff) {
+		let mut layers = self.layers.write();
+		if layers.base.is_none() {
+			return;
+		}
+		trace!(target: "flat_state", "Inserting diff for #{} ({}): {} accounts, {} storage entries", number, hash, diff.accounts.len(), diff.storage.len());
+
+		let layer = DiffLayer { number, parent, root, diff };
+		batch.put(COL_EXTRA, &diff_key(&hash), &layer.rlp_bytes());
+		layers.diffs.insert(hash, layer);
+	}
+
+	/// Flatten diff layers deeper than the configured depth below the given canonical block
+	/// into the persisted layer.
+	pub fn flatten(&self, head: &H256) {
+		let mut layers = self.layers.write();
+		let base_hash = match layers.base {
+			Some(ref base) => base.hash,
+			None => return,
+		};
+
+		let mut chain = Vec::new();
+		let mut at = *head;
+		while at != base_hash {
+			let parent = layers.diffs.get(&at).map(|layer| layer.parent);
+			match parent {
+				Some(parent) => {
+					chain.push(at);
+					at = parent;
+				},
+				None => {
+					// the base was retracted or some diffs are missing: start over.
+					let head_layer = layers.diffs.get(head).map(|layer| (layer.number, layer.root));
+					drop(layers);
+					if let Some((number, root)) = head_layer {
+						warn!(target: "flat_state", "Flat state is inconsistent with the canonical chain");
+						self.reset(number, *head, root);
+					}
+					return;
+				},
+			}
+		}
+
+		if chain.len() as u64 <= self.depth {
+			return;
+		}
+
+		let mut batch = DBTransaction::new();
+		while chain.len() as u64 > self.depth {
+			let hash = chain.pop().expect("chain is longer than depth; qed");
+			let layer = layers.diffs.remove(&hash).expect("chain consists of known diffs; qed");
+			let base = layers.base.as_mut().expect("base checked above; qed");
+			self.write_diff(&mut batch, base, &layer.diff);
+			batch.delete(COL_EXTRA, &diff_key(&hash));
+
+			trace!(target: "flat_state", "Flattened diff for #{} ({})", layer.number, hash);
+			base.number = layer.number;
+			base.hash = hash;
+			base.root = layer.root;
+		}
+
+		// drop diffs which are not descendants of the new base.
+		let base_hash = layers.base.as_ref().expect("base checked above; qed").hash;
+		loop {
+			let orphans: Vec<_> = layers.diffs.iter()
+				.filter(|&(_, layer)| layer.parent != base_hash && !layers.diffs.contains_key(&layer.parent))
+				.map(|(hash, _)| *hash)
+				.collect();
+			if orphans.is_empty() {
+				break;
+			}
+			for hash in orphans {
+				layers.diffs.remove(&hash);
+				batch.delete(COL_EXTRA, &diff_key(&hash));
+			}
+		}
+
+		batch.put(COL_EXTRA, FLAT_BASE_KEY, &rlp::encode(layers.base.as_ref().expect("base checked above; qed")));
+		self.db.write_buffered(batch);
+	}
+
+	/// Write the changes to the part of the persisted layer which is already generated.
+	fn write_diff(&self, batch: &mut DBTransaction, base: &Base, diff: &FlatDiff) {
+		for address_hash in diff.wiped.iter().filter(|a| base.covers_some_storage(a)) {
+			for (key, _) in self.storage_iter(address_hash) {
+				batch.delete(COL_FLAT_STATE, &key);
+			}
+		}
+		for (address_hash, account) in diff.accounts.iter().filter(|&(a, _)| base.covers(a)) {
+			match *account {
+				Some(ref account) => batch.put(COL_FLAT_STATE, address_hash, account),
+				None => batch.delete(COL_FLAT_STATE, address_hash),
+			}
+		}
+		for (&(ref address_hash, ref key_hash), value) in diff.storage.iter().filter(|&(&(ref a, ref k), _)| base.covers_storage(a, k)) {
+			let key = storage_key(address_hash, key_hash);
+			match value.is_zero() {
+				true => batch.delete(COL_FLAT_STATE, &key),
+				false => batch.put(COL_FLAT_STATE, &key, &rlp::encode(&U256::from(&**value))),
+			}
+		}
+	}
+
+	fn storage_iter<'a>(&'a self, address_hash: &'a H256) -> impl Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a {
+		self.db.iter_from_prefix(COL_FLAT_STATE, address_hash)
+			.take_while(move |&(ref key, _)| key.starts_with(address_hash))
+			.filter(|&(ref key, _)| key.len() == 64)
+	}
+
+	/// Generate a part of the persisted layer from the state trie in `db`, until the deadline
+	/// passes. The deadline is checked between batches of storage entries as well, generation
+	/// of a large storage resumes where it stopped. Returns `true` if the persisted layer is complete.
+	pub fn generate(&self, db: &HashDB<KeccakHasher, DBValue>, deadline: Instant) -> Result<bool, Error> {
+		let base = match self.layers.read().base.clone() {
+			Some(base) => base,
+			None => return Ok(false),
+		};
+		let last = match base.generating {
+			Some(last) => last,
+			None => return Ok(true),
+		};
+
+		let trie = TrieDB::new(db, &base.root)?;
+		let mut iter = trie.iter()?;
+		match base.partial {
+			Some((ref address_hash, _)) => iter.seek(address_hash)?,
+			None => if let Some(ref last) = last {
+				iter.seek(last)?;
+			},
+		}
+
+		let mut batch = DBTransaction::new();
+		let mut generated = last;
+		let mut partial = None;
+		let mut complete = true;
+		let mut count = 0;
+		for item in iter {
+			let (key, value) = item?;
+			let address_hash = H256::from_slice(&key);
+			if Some(address_hash) == last {
+				continue;
+			}
+
+			// the account may be gone since its storage was partially generated.
+			let storage_from = match base.partial {
+				Some((ref address, ref key_hash)) if *address == address_hash => Some(*key_hash),
+				_ => None,
+			};
+
+			let account: BasicAccount = rlp::decode(&value)?;
+			if account.storage_root != KECCAK_NULL_RLP {
+				let account_db = AccountDB::from_hash(db, address_hash);
+				let storage = TrieDB::new(&account_db, &account.storage_root)?;
+				let mut storage_iter = storage.iter()?;
+				if let Some(ref from) = storage_from {
+					storage_iter.seek(from)?;
+				}
+
+				let mut storage_count = 0;
+				for item in storage_iter {
+					let (key_hash, value) = item?;
+					let key_hash = H256::from_slice(&key_hash);
+					if Some(key_hash) == storage_from {
+						continue;
+					}
+
+					batch.put(COL_FLAT_STATE, &storage_key(&address_hash, &key_hash), &value);
+					storage_count += 1;
+					if storage_count % GENERATION_BATCH == 0 && Instant::now() > deadline {
+						partial = Some((address_hash, key_hash));
+						break;
+					}
+				}
+			}
+			if partial.is_some() {
+				complete = false;
+				break;
+			}
+
+			batch.put(COL_FLAT_STATE, &address_hash, &value);
+			generated = Some(address_hash);
+			count += 1;
+
+			if count == GENERATION_BATCH || Instant::now() > deadline {
+				complete = false;
+				break;
+			}
+		}
+
+		let mut layers = self.layers.write();
+		match layers.base {
+			// diffs were flattened in the meantime, the generated accounts may be stale.
+			Some(ref current) if *current != base => return Ok(false),
+			None => return Ok(false),
+			Some(_) => {},
+		}
+
+		let base = layers.base.as_mut().expect("base checked above; qed");
+		base.generating = match complete {
+			true => None,
+			false => Some(generated),
+		};
+		base.partial = partial;
+		batch.put(COL_EXTRA, FLAT_BASE_KEY, &rlp::encode(&*base));
+		self.db.write_buffered(batch);
+
+		if complete {
+			info!(target: "flat_state", "Flat state generated at block #{}", base.number);
+		}
+		Ok(complete)
+	}
+
+	/// Block number, hash and state root of the persisted layer, if it's fully generated.
+	pub fn base(&self) -> Option<(BlockNumber, H256, H256)> {
+		match self.layers.read().base {
+			Some(ref base) if base.generating.is_none() => Some((base.number, base.hash, base.root)),
+			_ => None,
+		}
+	}
+
+	/// Iterate over the accounts of the persisted layer, ordered by address hash, as
+	/// `(address hash, account RLP)`. The base must not move while iterating, e.g. by
+	/// importing blocks.
+	pub fn accounts<'a>(&'a self) -> impl Iterator<Item = (H256, Bytes)> + 'a {
+		self.db.iter(COL_FLAT_STATE)
+			.filter(|&(ref key, _)| key.len() == 32)
+			.map(|(key, value)| (H256::from_slice(&key), value.into_vec()))
+	}
+
+	/// Iterate over the storage of an account in the persisted layer, ordered by key hash,
+	/// as `(key hash, value RLP)`. The base must not move while iterating.
+	pub fn account_storage<'a>(&'a self, address_hash: &'a H256) -> impl Iterator<Item = (H256, Bytes)> + 'a {
+		self.storage_iter(address_hash)
+			.map(|(key, value)| (H256::from_slice(&key[32..]), value.into_vec()))
+	}
+}
+
+fn diff_key(hash: &H256) -> Vec<u8> {
+	let mut key = FLAT_DIFF_PREFIX.to_vec();
+	key.extend_from_slice(hash);
+	key
+}
+
+fn storage_key(address_hash: &H256, key_hash: &H256) -> [u8; 64] {
+	let mut key = [0u8; 64];
+	key[..32].copy_from_slice(address_hash);
+	key[32..].copy_from_slice(key_hash);
+	key
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::time::{Duration, Instant};
+	use ethereum_types::{H256, U256};
+	use ethtrie::SecTrieDBMut;
+	use hash::{keccak, KECCAK_EMPTY, KECCAK_NULL_RLP};
+	use hashdb::HashDB;
+	use journaldb::{self, Algorithm, JournalDB};
+	use kvdb::{DBTransaction, KeyValueDB};
+	use kvdb_memorydb;
+	use rlp;
+	use trie::TrieMut;
+	use types::basic_account::BasicAccount;
+	use account_db::AccountDBMut;
+	use super::*;
+
+	fn account(balance: u64, storage_root: H256) -> Bytes {
+		rlp::encode(&BasicAccount {
+			nonce: U256::zero(),
+			balance: balance.into(),
+			storage_root,
+			code_hash: KECCAK_EMPTY,
+		}).into_vec()
+	}
+
+	fn new_db() -> Arc<KeyValueDB> {
+		Arc::new(kvdb_memorydb::create(::db::NUM_COLUMNS.unwrap()))
+	}
+
+	fn commit(flat: &FlatState, db: &Arc<KeyValueDB>, number: u64, hash: H256, parent: H256, root: H256, diff: FlatDiff) {
+		let mut batch = DBTransaction::new();
+		flat.insert_diff(&mut batch, number, hash, parent, root, diff);
+		db.write(batch).unwrap();
+	}
+
+	#[test]
+	fn diffs_and_flattening() {
+		let db = new_db();
+		let flat = FlatState::open(db.clone(), 1);
+		let (a, b) = (keccak("a"), keccak("b"));
+		let (h0, h1, h2, h3) = (H256::from(10), H256::from(11), H256::from(12), H256::from(13));
+		let (r0, r1, r2, r3) = (H256::from(20), H256::from(21), H256::from(22), H256::from(23));
+
+		assert!(flat.account(&h0, &r0, &a).is_none());
+		flat.reset(0, h0, r0);
+		assert!(flat.account(&h0, &r0, &a).is_none());
+		let journal = journaldb::new(db.clone(), Algorithm::Archive, ::db::COL_STATE);
+		assert!(flat.generate(journal.as_hashdb(), Instant::now()).is_err());
+
+		// pretend the empty state was generated.
+		flat.layers.write().base.as_mut().unwrap().generating = None;
+		assert_eq!(flat.account(&h0, &r0, &a), Some(None));
+
+		let mut diff = FlatDiff::default();
+		diff.set_account(a, Some(account(1, KECCAK_NULL_RLP)));
+		commit(&flat, &db, 1, h1, h0, r1, diff);
+		let mut diff = FlatDiff::default();
+		diff.set_account(b, Some(account(2, KECCAK_NULL_RLP)));
+		commit(&flat, &db, 2, h2, h1, r2, diff);
+
+		assert_eq!(flat.account(&h2, &r2, &a), Some(Some(account(1, KECCAK_NULL_RLP))));
+		assert_eq!(flat.account(&h1, &r1, &b), Some(None));
+		// wrong state root
+		assert_eq!(flat.account(&h2, &r1, &a), None);
+
+		flat.flatten(&h2);
+		assert_eq!(flat.base().unwrap(), (1, h1, r1));
+		assert_eq!(db.get(COL_FLAT_STATE, &a).unwrap().unwrap().into_vec(), account(1, KECCAK_NULL_RLP));
+		assert_eq!(flat.account(&h0, &r0, &a), None);
+		assert_eq!(flat.account(&h2, &r2, &b), Some(Some(account(2, KECCAK_NULL_RLP))));
+
+		// diffs are reloaded from the journal
+		let flat = FlatState::open(db.clone(), 1);
+		assert_eq!(flat.account(&h2, &r2, &b), Some(Some(account(2, KECCAK_NULL_RLP))));
+
+		// a block not building on the base resets the flat state
+		commit(&flat, &db, 3, h3, H256::from(99), r3, FlatDiff::default());
+		flat.flatten(&h3);
+		assert_eq!(flat.account(&h2, &r2, &b), None);
+		assert!(!flat.is_generated());
+		assert!(db.get(COL_FLAT_STATE, &a).unwrap().is_none());
+	}
+
+	#[test]
+	fn storage_lookups() {
+		let db = new_db();
+		let flat = FlatState::open(db.clone(), 0);
+		let a = keccak("a");
+		let (k1, k2) = (keccak("k1"), keccak("k2"));
+		let (h0, h1, h2) = (H256::from(10), H256::from(11), H256::from(12));
+		let (r0, r1, r2) = (H256::from(20), H256::from(21), H256::from(22));
+		let (s1, s2) = (H256::from(31), H256::from(32));
+		flat.reset(0, h0, r0);
+		flat.layers.write().base.as_mut().unwrap().generating = None;
+
+		let mut diff = FlatDiff::default();
+		diff.set_account(a, Some(account(1, s1)));
+		diff.set_storage(a, k1, H256::from(1));
+		diff.set_storage(a, k2, H256::from(2));
+		commit(&flat, &db, 1, h1, h0, r1, diff);
+		flat.flatten(&h1);
+		assert_eq!(flat.storage(&h1, &r1, &a, &s1, &k1), Some(H256::from(1)));
+		// storage root mismatch
+		assert_eq!(flat.storage(&h1, &r1, &a, &s2, &k1), None);
+
+		let mut diff = FlatDiff::default();
+		diff.wipe_storage(a);
+		diff.set_account(a, Some(account(1, s2)));
+		diff.set_storage(a, k2, H256::from(3));
+		commit(&flat, &db, 2, h2, h1, r2, diff);
+		assert_eq!(flat.storage(&h2, &r2, &a, &s2, &k1), Some(H256::zero()));
+		assert_eq!(flat.storage(&h2, &r2, &a, &s2, &k2), Some(H256::from(3)));
+
+		flat.flatten(&h2);
+		assert_eq!(flat.storage(&h2, &r2, &a, &s2, &k1), Some(H256::zero()));
+		assert_eq!(flat.storage(&h2, &r2, &a, &s2, &k2), Some(H256::from(3)));
+		assert_eq!(flat.account_storage(&a).count(), 1);
+	}
+
+	#[test]
+	fn generates_from_trie() {
+		let db = new_db();
+		let mut journal = journaldb::new(db.clone(), Algorithm::Archive, ::db::COL_STATE);
+		let mut root = H256::new();
+		let mut accounts = Vec::new();
+		{
+			for i in 0..3000u64 {
+				let address_hash = keccak(&H256::from(i));
+				let mut storage_root = KECCAK_NULL_RLP;
+				if i % 1000 == 0 {
+					let mut account_db = AccountDBMut::from_hash(journal.as_hashdb_mut(), address_hash);
+					let mut storage = SecTrieDBMut::new(&mut account_db, &mut storage_root);
+					storage.insert(&H256::from(1), &rlp::encode(&U256::from(i + 1))).unwrap();
+				}
+				accounts.push((address_hash, account(i, storage_root)));
+			}
+			let mut trie = ::ethtrie::TrieDBMut::new(journal.as_hashdb_mut(), &mut root);
+			for &(ref address_hash, ref account) in &accounts {
+				trie.insert(address_hash, account).unwrap();
+			}
+		}
+		let mut batch = DBTransaction::new();
+		journal.inject(&mut batch).unwrap();
+		db.write(batch).unwrap();
+
+		let flat = FlatState::open(db.clone(), 0);
+		flat.reset(0, H256::from(1), root);
+		let deadline = Instant::now() + Duration::from_secs(60);
+		assert!(!flat.generate(journal.as_hashdb(), deadline).unwrap());
+		assert_eq!(flat.accounts().count(), GENERATION_BATCH);
+		assert!(!flat.generate(journal.as_hashdb(), deadline).unwrap());
+		assert!(flat.generate(journal.as_hashdb(), deadline).unwrap());
+		assert!(flat.is_generated());
+
+		for &(ref address_hash, ref account) in &accounts {
+			assert_eq!(flat.account(&H256::from(1), &root, address_hash), Some(Some(account.clone())));
+		}
+		let rich = keccak(&H256::from(2000));
+		let storage_root = rlp::decode::<BasicAccount>(&accounts[2000].1).unwrap().storage_root;
+		assert_eq!(flat.storage(&H256::from(1), &root, &rich, &storage_root, &keccak(&H256::from(1))), Some(H256::from(2001)));
+		assert_eq!(flat.accounts().count(), 3000);
+	}
+
+	#[test]
+	fn generates_large_storage_in_steps() {
+		let db = new_db();
+		let mut journal = journaldb::new(db.clone(), Algorithm::Archive, ::db::COL_STATE);
+		let mut root = H256::new();
+		let address_hash = keccak(&H256::from(1));
+		let mut storage_root = KECCAK_NULL_RLP;
+		{
+			let mut account_db = AccountDBMut::from_hash(journal.as_hashdb_mut(), address_hash);
+			let mut storage = SecTrieDBMut::new(&mut account_db, &mut storage_root);
+			for i in 0..3000u64 {
+				storage.insert(&H256::from(i), &rlp::encode(&U256::from(i + 1))).unwrap();
+			}
+		}
+		{
+			let mut trie = ::ethtrie::TrieDBMut::new(journal.as_hashdb_mut(), &mut root);
+			trie.insert(&address_hash, &account(1, storage_root)).unwrap();
+		}
+		let mut batch = DBTransaction::new();
+		journal.inject(&mut batch).unwrap();
+		db.write(batch).unwrap();
+
+		let flat = FlatState::open(db.clone(), 0);
+		flat.reset(0, H256::from(1), root);
+
+		// the deadline has passed already, so every call generates a single batch of storage.
+		assert!(!flat.generate(journal.as_hashdb(), Instant::now()).unwrap());
+		assert_eq!(flat.account_storage(&address_hash).count(), GENERATION_BATCH);
+		assert_eq!(flat.account(&H256::from(1), &root, &address_hash), None);
+
+		// progress survives a restart.
+		let flat = FlatState::open(db.clone(), 0);
+		let mut steps = 1;
+		while !flat.generate(journal.as_hashdb(), Instant::now()).unwrap() {
+			steps += 1;
+		}
+		assert!(steps >= 3);
+		assert_eq!(flat.account_storage(&address_hash).count(), 3000);
+		assert_eq!(flat.account(&H256::from(1), &root, &address_hash), Some(Some(account(1, storage_root))));
+		let key_hash = keccak(&H256::from(2999));
+		assert_eq!(flat.storage(&H256::from(1), &root, &address_hash, &storage_root, &key_hash), Some(H256::from(3000)));
+	}
+}
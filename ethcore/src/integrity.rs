@@ -0,0 +1,353 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Offline database integrity checks.
+//!
+//! Walks the canonical chain checking that every block is complete and consistent with
+//! its header, and that the extras and blooms databases agree with it. Optionally checks
+//! that the whole state trie of the best block is reachable.
+
+use std::fmt;
+use std::sync::Arc;
+
+use account_db::AccountDB;
+use blockchain::{BlockChain, BlockChainDB, BlockProvider, Config as BlockChainConfig, TransactionAddress};
+use db;
+use ethereum_types::H256;
+use ethtrie::TrieDB;
+use hash::{keccak, KECCAK_EMPTY, KECCAK_NULL_RLP};
+use hashdb::HashDB;
+use header::BlockNumber;
+use journaldb::{self, Algorithm};
+use rlp::{self, Encodable};
+use spec::Spec;
+use trie::Trie;
+use triehash::ordered_trie_root;
+use types::basic_account::BasicAccount;
+
+/// Number of blocks checked between cache cleanups.
+const BLOCKS_PER_GARBAGE_COLLECTION: u64 = 1000;
+
+/// Problem found with a canonical block.
+#[derive(Debug, PartialEq)]
+pub enum BlockProblem {
+	/// Hash of the canonical block is missing.
+	MissingHash,
+	/// Header is missing.
+	MissingHeader,
+	/// Header does not hash to the canonical hash or has a different number.
+	InvalidHeader,
+	/// Parent hash of the header is not the hash of the previous canonical block.
+	ParentMismatch,
+	/// Body is missing.
+	MissingBody,
+	/// Transactions do not match the transactions root of the header.
+	TransactionsRootMismatch,
+	/// Uncles do not match the uncles hash of the header.
+	UnclesHashMismatch,
+	/// Receipts are missing.
+	MissingReceipts,
+	/// Receipts do not match the receipts root of the header.
+	ReceiptsRootMismatch,
+	/// Block details are missing.
+	MissingDetails,
+	/// Block details have a different number or parent.
+	InvalidDetails,
+	/// Block is not among the children of its parent.
+	MissingChild,
+	/// Address of the transaction with given hash is missing or points elsewhere.
+	InvalidTransactionAddress(H256),
+	/// Header bloom is missing in the blooms database.
+	MissingBloom,
+}
+
+impl fmt::Display for BlockProblem {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			BlockProblem::MissingHash => write!(f, "canonical hash is missing"),
+			BlockProblem::MissingHeader => write!(f, "header is missing"),
+			BlockProblem::InvalidHeader => write!(f, "header does not match the canonical hash"),
+			BlockProblem::ParentMismatch => write!(f, "parent is not the previous canonical block"),
+			BlockProblem::MissingBody => write!(f, "body is missing"),
+			BlockProblem::TransactionsRootMismatch => write!(f, "transactions do not match the transactions root"),
+			BlockProblem::UnclesHashMismatch => write!(f, "uncles do not match the uncles hash"),
+			BlockProblem::MissingReceipts => write!(f, "receipts are missing"),
+			BlockProblem::ReceiptsRootMismatch => write!(f, "receipts do not match the receipts root"),
+			BlockProblem::MissingDetails => write!(f, "block details are missing"),
+			BlockProblem::InvalidDetails => write!(f, "block details do not match the header"),
+			BlockProblem::MissingChild => write!(f, "block is not a child of its parent"),
+			BlockProblem::InvalidTransactionAddress(ref hash) => write!(f, "address of transaction {:?} is invalid", hash),
+			BlockProblem::MissingBloom => write!(f, "bloom is missing in the blooms database"),
+		}
+	}
+}
+
+/// Inconsistency found in the database.
+#[derive(Debug, PartialEq)]
+pub enum Inconsistency {
+	/// The database does not contain a chain.
+	NoChain,
+	/// A canonical block is corrupted.
+	Block {
+		/// Block number.
+		number: BlockNumber,
+		/// Block hash, if known.
+		hash: Option<H256>,
+		/// What is wrong with the block.
+		problem: BlockProblem,
+	},
+	/// The state trie of the best block is incomplete or corrupted.
+	State {
+		/// State root of the best block.
+		root: H256,
+		/// Description of the problem.
+		error: String,
+	},
+}
+
+impl fmt::Display for Inconsistency {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Inconsistency::NoChain => write!(f, "Database does not contain a chain"),
+			Inconsistency::Block { number, hash: Some(ref hash), ref problem } => write!(f, "Block #{} ({:?}): {}", number, hash, problem),
+			Inconsistency::Block { number, hash: None, ref problem } => write!(f, "Block #{}: {}", number, problem),
+			Inconsistency::State { ref root, ref error } => write!(f, "State {:?}: {}", root, error),
+		}
+	}
+}
+
+/// Summary of a successful check.
+#[derive(Debug, Default, PartialEq)]
+pub struct Report {
+	/// Number of checked blocks.
+	pub blocks: u64,
+	/// Number of checked accounts, if the state was checked.
+	pub accounts: Option<u64>,
+}
+
+/// Check the chain in the database, and the state of the best block if the pruning
+/// `algorithm` of the state database is given. Stops at the first inconsistency.
+pub fn check_database(database: Arc<BlockChainDB>, spec: &Spec, algorithm: Option<Algorithm>) -> Result<Report, Inconsistency> {
+	// opening an empty database would insert the genesis block.
+	match database.key_value().get(db::COL_EXTRA, b"best") {
+		Ok(Some(_)) => {},
+		_ => return Err(Inconsistency::NoChain),
+	}
+
+	let chain = BlockChain::new(BlockChainConfig::default(), &spec.genesis_block(), database.clone());
+	let best = chain.best_block_number();
+
+	// blocks between the last ancient block and the first block are not downloaded yet.
+	let ranges = match (chain.best_ancient_number(), chain.first_block_number()) {
+		(Some(ancient), Some(first)) if ancient + 1 < first => vec![(0, ancient), (first, best)],
+		_ => vec![(0, best)],
+	};
+
+	let mut report = Report::default();
+	for (from, to) in ranges {
+		let mut parent = None;
+		for number in from..(to + 1) {
+			parent = Some(check_block(&chain, number, parent)?);
+			report.blocks += 1;
+
+			if number % BLOCKS_PER_GARBAGE_COLLECTION == 0 {
+				chain.collect_garbage();
+			}
+			if number % 10_000 == 0 {
+				info!(target: "integrity", "Checked block #{}", number);
+			}
+		}
+	}
+
+	if let Some(algorithm) = algorithm {
+		let root = *chain.best_block_header().state_root();
+		report.accounts = Some(check_state(&*database, algorithm, root)?);
+	}
+
+	Ok(report)
+}
+
+/// Check canonical block with given number, which is a child of `parent` if given. Returns its hash.
+fn check_block(chain: &BlockChain, number: BlockNumber, parent: Option<H256>) -> Result<H256, Inconsistency> {
+	let hash = chain.block_hash(number).ok_or_else(|| Inconsistency::Block {
+		number,
+		hash: None,
+		problem: BlockProblem::MissingHash,
+	})?;
+	let fail = |problem| Inconsistency::Block { number, hash: Some(hash), problem };
+
+	let header = chain.block_header_data(&hash).ok_or_else(|| fail(BlockProblem::MissingHeader))?;
+	if header.hash() != hash || header.number() != number {
+		return Err(fail(BlockProblem::InvalidHeader));
+	}
+	if let Some(parent) = parent {
+		if header.parent_hash() != parent {
+			return Err(fail(BlockProblem::ParentMismatch));
+		}
+	}
+
+	// bodies, receipts and transaction addresses of blocks before the history tail are removed.
+	let has_history = chain.history_tail().map_or(true, |tail| number >= tail);
+	let body = match has_history {
+		true => Some(chain.block_body(&hash).ok_or_else(|| fail(BlockProblem::MissingBody))?),
+		false => None,
+	};
+	if let Some(ref body) = body {
+		if ordered_trie_root(body.transactions_rlp().iter().map(|tx| tx.as_raw())) != header.transactions_root() {
+			return Err(fail(BlockProblem::TransactionsRootMismatch));
+		}
+		if keccak(body.uncles_rlp().as_raw()) != header.uncles_hash() {
+			return Err(fail(BlockProblem::UnclesHashMismatch));
+		}
+	}
+
+	// receipts of the genesis block are never stored.
+	if has_history && number != 0 {
+		let receipts = chain.block_receipts(&hash).ok_or_else(|| fail(BlockProblem::MissingReceipts))?;
+		if ordered_trie_root(receipts.receipts.iter().map(|r| r.rlp_bytes())) != header.receipts_root() {
+			return Err(fail(BlockProblem::ReceiptsRootMismatch));
+		}
+	}
+
+	let details = chain.block_details(&hash).ok_or_else(|| fail(BlockProblem::MissingDetails))?;
+	if details.number != number || (number != 0 && details.parent != header.parent_hash()) {
+		return Err(fail(BlockProblem::InvalidDetails));
+	}
+	if let Some(parent) = parent {
+		let is_child = chain.block_details(&parent).map_or(false, |details| details.children.contains(&hash));
+		if !is_child {
+			return Err(fail(BlockProblem::MissingChild));
+		}
+	}
+
+	let transaction_hashes = body.map_or_else(Vec::new, |body| body.transaction_hashes());
+	for (index, tx_hash) in transaction_hashes.into_iter().enumerate() {
+		let expected = TransactionAddress { block_hash: hash, index };
+		if chain.transaction_address(&tx_hash) != Some(expected) {
+			return Err(fail(BlockProblem::InvalidTransactionAddress(tx_hash)));
+		}
+	}
+
+	let bloom = header.log_bloom();
+	if !chain.blocks_with_bloom(Some(&bloom), number, number).contains(&number) {
+		return Err(fail(BlockProblem::MissingBloom));
+	}
+
+	Ok(hash)
+}
+
+/// Check that all accounts, their code and storage are reachable from the state root.
+/// Returns the number of accounts.
+fn check_state(database: &BlockChainDB, algorithm: Algorithm, root: H256) -> Result<u64, Inconsistency> {
+	let journal_db = journaldb::new(database.key_value().clone(), algorithm, db::COL_STATE);
+	let state_db = journal_db.as_hashdb();
+	let fail = |error: String| Inconsistency::State { root, error };
+
+	let trie = TrieDB::new(state_db, &root).map_err(|e| fail(e.to_string()))?;
+	let mut accounts = 0;
+	for item in trie.iter().map_err(|e| fail(e.to_string()))? {
+		let (key, value) = item.map_err(|e| fail(e.to_string()))?;
+		let address_hash = H256::from_slice(&key);
+		let account: BasicAccount = rlp::decode(&value)
+			.map_err(|e| fail(format!("account {:?} is invalid: {}", address_hash, e)))?;
+
+		let account_db = AccountDB::from_hash(state_db, address_hash);
+		if account.code_hash != KECCAK_EMPTY && !account_db.contains(&account.code_hash) {
+			return Err(fail(format!("code of account {:?} is missing", address_hash)));
+		}
+		if account.storage_root != KECCAK_NULL_RLP {
+			let storage = TrieDB::new(&account_db, &account.storage_root)
+				.map_err(|e| fail(format!("storage of account {:?}: {}", address_hash, e)))?;
+			let iter = storage.iter()
+				.map_err(|e| fail(format!("storage of account {:?}: {}", address_hash, e)))?;
+			for item in iter {
+				item.map_err(|e| fail(format!("storage of account {:?}: {}", address_hash, e)))?;
+			}
+		}
+
+		accounts += 1;
+		if accounts % 100_000 == 0 {
+			info!(target: "integrity", "Checked {} accounts", accounts);
+		}
+	}
+
+	Ok(accounts)
+}
+
+#[cfg(test)]
+mod tests {
+	use client::{BlockChainClient, BlockId};
+	use db::{self, Key};
+	use journaldb::Algorithm;
+	use kvdb::DBTransaction;
+	use spec::Spec;
+	use test_helpers::{new_db, generate_dummy_client_with_spec_accounts_data_and_db};
+	use super::*;
+
+	#[test]
+	fn accepts_consistent_database() {
+		let db = new_db();
+		let _client = generate_dummy_client_with_spec_accounts_data_and_db(Spec::new_null, None, 5, 2, &[1.into()], db.clone());
+
+		let report = check_database(db, &Spec::new_null(), Some(Algorithm::OverlayRecent)).unwrap();
+		assert_eq!(report.blocks, 6);
+		assert!(report.accounts.unwrap() > 0);
+	}
+
+	#[test]
+	fn accepts_expired_history() {
+		let db = new_db();
+		let spec = Spec::new_null();
+		let _client = generate_dummy_client_with_spec_accounts_data_and_db(Spec::new_null, None, 5, 2, &[1.into()], db.clone());
+		{
+			let chain = BlockChain::new(BlockChainConfig::default(), &spec.genesis_block(), db.clone());
+			assert_eq!(chain.expire_history(3, 10), 3);
+		}
+
+		let report = check_database(db, &spec, None).unwrap();
+		assert_eq!(report.blocks, 6);
+	}
+
+	#[test]
+	fn reports_first_inconsistent_block() {
+		let spec = Spec::new_null();
+		assert_eq!(check_database(new_db(), &spec, None), Err(Inconsistency::NoChain));
+
+		let db = new_db();
+		let client = generate_dummy_client_with_spec_accounts_data_and_db(Spec::new_null, None, 5, 2, &[1.into()], db.clone());
+		let hash_2 = client.block_hash(BlockId::Number(2)).unwrap();
+		let hash_4 = client.block_hash(BlockId::Number(4)).unwrap();
+		let tx_hash = client.block_body(BlockId::Number(2)).unwrap().transaction_hashes()[1];
+
+		let mut batch = DBTransaction::new();
+		batch.delete(db::COL_BODIES, &hash_4);
+		db.key_value().write(batch).unwrap();
+		assert_eq!(check_database(db.clone(), &spec, None), Err(Inconsistency::Block {
+			number: 4,
+			hash: Some(hash_4),
+			problem: BlockProblem::MissingBody,
+		}));
+
+		let mut batch = DBTransaction::new();
+		batch.delete(db::COL_EXTRA, &Key::<TransactionAddress>::key(&tx_hash));
+		db.key_value().write(batch).unwrap();
+		assert_eq!(check_database(db, &spec, None), Err(Inconsistency::Block {
+			number: 2,
+			hash: Some(hash_2),
+			problem: BlockProblem::InvalidTransactionAddress(tx_hash),
+		}));
+	}
+}
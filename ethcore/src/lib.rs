@@ -75,6 +75,7 @@ extern crate ethcore_transaction as transaction;
 extern crate ethereum_types;
 extern crate ethjson;
 extern crate ethkey;
+extern crate freezer_db;
 
 extern crate hashdb;
 extern crate itertools;
@@ -100,8 +101,11 @@ extern crate triehash_ethereum as triehash;
 extern crate ansi_term;
 extern crate unexpected;
 extern crate parity_snappy as snappy;
+extern crate zstd;
 extern crate ethabi;
 extern crate rustc_hex;
+extern crate serde;
+extern crate serde_json;
 extern crate stats;
 extern crate stop_guard;
 extern crate using_queue;
@@ -154,15 +158,19 @@ pub mod error;
 pub mod ethereum;
 pub mod executed;
 pub mod executive;
+pub mod flat_state;
 pub mod header;
+pub mod integrity;
 pub mod machine;
 pub mod miner;
 pub mod pod_state;
 pub mod pod_account;
+pub mod prune;
 pub mod snapshot;
 pub mod spec;
 pub mod state;
 pub mod state_db;
+pub mod state_import;
 pub mod trace;
 pub mod verification;
 
@@ -72,7 +72,8 @@ pub struct EthereumMachine {
 	params: CommonParams,
 	builtins: Arc<BTreeMap<Address, Builtin>>,
 	tx_filter: Option<Arc<TransactionFilter>>,
-	ethash_extensions: Option<EthashExtensions>,
+	// ethash-specific extensions by the block they apply from, if any.
+	ethash_extensions: BTreeMap<BlockNumber, Option<EthashExtensions>>,
 	schedule_rules: Option<Box<ScheduleCreationRules>>,
 }
 
@@ -84,7 +85,7 @@ impl EthereumMachine {
 			params: params,
 			builtins: Arc::new(builtins),
 			tx_filter: tx_filter,
-			ethash_extensions: None,
+			ethash_extensions: BTreeMap::new(),
 			schedule_rules: None,
 		}
 	}
@@ -93,7 +94,7 @@ impl EthereumMachine {
 	// TODO: either unify or specify to mainnet specifically and include other specific-chain HFs?
 	pub fn with_ethash_extensions(params: CommonParams, builtins: BTreeMap<Address, Builtin>, extensions: EthashExtensions) -> EthereumMachine {
 		let mut machine = EthereumMachine::regular(params, builtins);
-		machine.ethash_extensions = Some(extensions);
+		machine.ethash_extensions.insert(0, Some(extensions));
 		machine
 	}
 
@@ -102,9 +103,23 @@ impl EthereumMachine {
 		self.schedule_rules = Some(rules);
 	}
 
-	/// Get a reference to the ethash-specific extensions.
+	/// Apply the given ethash-specific extensions, or none, from the given block on,
+	/// for chains switching between ethash and other engines.
+	pub fn set_ethash_extensions_from(&mut self, block: BlockNumber, extensions: Option<EthashExtensions>) {
+		self.ethash_extensions.insert(block, extensions);
+	}
+
+	/// Get a reference to the ethash-specific extensions applying to the genesis block.
 	pub fn ethash_extensions(&self) -> Option<&EthashExtensions> {
-		self.ethash_extensions.as_ref()
+		self.ethash_extensions_at(0)
+	}
+
+	/// Get a reference to the ethash-specific extensions applying to the given block.
+	pub fn ethash_extensions_at(&self, block: BlockNumber) -> Option<&EthashExtensions> {
+		self.ethash_extensions.iter()
+			.rev()
+			.find(|&(from, _)| *from <= block)
+			.and_then(|(_, extensions)| extensions.as_ref())
 	}
 }
 
@@ -212,7 +227,7 @@ impl EthereumMachine {
 	pub fn on_new_block(&self, block: &mut ExecutedBlock) -> Result<(), Error> {
 		self.push_last_hash(block)?;
 
-		if let Some(ref ethash_params) = self.ethash_extensions {
+		if let Some(ethash_params) = self.ethash_extensions_at(block.header().number()) {
 			if block.header().number() == ethash_params.dao_hardfork_transition {
 				let state = block.state_mut();
 				for child in &ethash_params.dao_hardfork_accounts {
@@ -234,7 +249,7 @@ impl EthereumMachine {
 		let gas_limit = parent.gas_limit().clone();
 		assert!(!gas_limit.is_zero(), "Gas limit should be > 0");
 
-		if let Some(ref ethash_params) = self.ethash_extensions {
+		if let Some(ethash_params) = self.ethash_extensions_at(header.number()) {
 			let gas_limit = {
 				let bound_divisor = self.params().gas_limit_bound_divisor;
 				let lower_limit = gas_limit - gas_limit / bound_divisor + 1;
@@ -283,9 +298,9 @@ impl EthereumMachine {
 
 	/// Get the EVM schedule for the given block number.
 	pub fn schedule(&self, block_number: BlockNumber) -> Schedule {
-		let mut schedule = match self.ethash_extensions {
+		let mut schedule = match self.ethash_extensions_at(block_number) {
 			None => self.params.schedule(block_number),
-			Some(ref ext) => {
+			Some(ext) => {
 				if block_number < ext.homestead_transition {
 					Schedule::new_frontier()
 				} else {
@@ -353,8 +368,8 @@ impl EthereumMachine {
 
 	/// Does basic verification of the transaction.
 	pub fn verify_transaction_basic(&self, t: &UnverifiedTransaction, header: &Header) -> Result<(), transaction::Error> {
-		let check_low_s = match self.ethash_extensions {
-			Some(ref ext) => header.number() >= ext.homestead_transition,
+		let check_low_s = match self.ethash_extensions_at(header.number()) {
+			Some(ext) => header.number() >= ext.homestead_transition,
 			None => true,
 		};
 
@@ -0,0 +1,268 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! In-process Ethash CPU miner.
+//!
+//! Searches for seals of the work packages announced by the miner using light
+//! verification caches, so it's only practical for chains with a low difficulty,
+//! e.g. development and test networks.
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
+use std::thread;
+
+use client::{Client, ImportSealedBlock};
+use ethash::{self, EthashManager};
+use ethcore_miner::work_notify::NotifyWork;
+use ethereum_types::{H64, H256, U256};
+use miner::{Miner, MinerService};
+use parking_lot::{Mutex, Condvar};
+use rlp::encode;
+
+/// Number of nonces tried before checking for a new work package.
+const NONCES_PER_ROUND: u64 = 128;
+
+/// Configures the CPU miner.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Options {
+	/// Number of mining threads.
+	pub threads: usize,
+	/// Directory of the Ethash caches.
+	pub cache_dir: PathBuf,
+}
+
+/// Work package being mined.
+#[derive(Debug, Clone)]
+struct Work {
+	pow_hash: H256,
+	boundary: H256,
+	number: u64,
+}
+
+#[derive(Default)]
+struct Job {
+	work: Option<Work>,
+	/// Bumped every time the work changes, so that the threads abandon stale packages.
+	version: u64,
+	exit: bool,
+}
+
+struct Shared {
+	job: Mutex<Job>,
+	new_job: Condvar,
+	pow: EthashManager,
+	client: Weak<Client>,
+	miner: Weak<Miner>,
+}
+
+impl Shared {
+	fn set_work(&self, work: Work) {
+		let mut job = self.job.lock();
+		job.work = Some(work);
+		job.version += 1;
+		self.new_job.notify_all();
+	}
+
+	/// Waits for a work package, returns `None` if the miner is shutting down.
+	fn next_work(&self) -> Option<(Work, u64)> {
+		let mut job = self.job.lock();
+		loop {
+			if job.exit {
+				return None;
+			}
+			if let Some(work) = job.work.clone() {
+				return Some((work, job.version));
+			}
+			self.new_job.wait(&mut job);
+		}
+	}
+
+	fn is_stale(&self, version: u64) -> bool {
+		self.job.lock().version != version
+	}
+
+	/// Makes the other threads stop working on a solved package.
+	fn solved(&self, version: u64) {
+		let mut job = self.job.lock();
+		if job.version == version {
+			job.work = None;
+			job.version += 1;
+		}
+	}
+
+	/// Imports the block sealed with given nonce and mix hash.
+	fn submit(&self, work: &Work, nonce: u64, mix_hash: H256) {
+		let (client, miner) = match (self.client.upgrade(), self.miner.upgrade()) {
+			(Some(client), Some(miner)) => (client, miner),
+			_ => return,
+		};
+
+		let seal = vec![encode(&mix_hash), encode(&H64::from(nonce))];
+		let import = miner.submit_seal(work.pow_hash, seal)
+			.and_then(|block| client.import_sealed_block(block));
+		match import {
+			Ok(hash) => debug!(target: "miner", "Mined block #{} ({})", work.number, hash),
+			Err(e) => warn!(target: "miner", "Failed to submit a seal for block #{}: {:?}", work.number, e),
+		}
+	}
+
+	fn run(&self, index: u64, threads: u64) {
+		while let Some((work, version)) = self.next_work() {
+			let mut nonce = ::rand::random::<u64>().wrapping_add(index);
+			loop {
+				if let Some((found, mix_hash)) = search(&self.pow, &work, nonce, threads, NONCES_PER_ROUND) {
+					trace!(target: "miner", "Found a seal for {}: nonce {}", work.pow_hash, found);
+					self.solved(version);
+					self.submit(&work, found, mix_hash);
+					break;
+				}
+				if self.is_stale(version) {
+					break;
+				}
+				nonce = nonce.wrapping_add(threads * NONCES_PER_ROUND);
+			}
+		}
+	}
+}
+
+/// Tries `count` nonces starting at `nonce` and advancing by `step`.
+/// Returns the first one satisfying the work's boundary with its mix hash.
+fn search(pow: &EthashManager, work: &Work, mut nonce: u64, step: u64, count: u64) -> Option<(u64, H256)> {
+	for _ in 0..count {
+		let result = pow.compute_light(work.number, &work.pow_hash.0, nonce);
+		if H256(result.value) <= work.boundary {
+			return Some((nonce, H256(result.mix_hash)));
+		}
+		nonce = nonce.wrapping_add(step);
+	}
+	None
+}
+
+/// Multi-threaded CPU miner sealing the work packages it's notified about.
+pub struct CpuMiner {
+	shared: Arc<Shared>,
+}
+
+impl NotifyWork for CpuMiner {
+	fn notify(&self, pow_hash: H256, difficulty: U256, number: u64) {
+		trace!(target: "miner", "CPU miner: new work {} (#{})", pow_hash, number);
+
+		self.shared.set_work(Work {
+			pow_hash,
+			boundary: ethash::difficulty_to_boundary(&difficulty),
+			number,
+		});
+	}
+}
+
+impl Drop for CpuMiner {
+	fn drop(&mut self) {
+		let mut job = self.shared.job.lock();
+		job.exit = true;
+		job.version += 1;
+		self.shared.new_job.notify_all();
+	}
+}
+
+impl CpuMiner {
+	/// Start the mining threads.
+	pub fn start(options: &Options, miner: Weak<Miner>, client: Weak<Client>) -> Result<CpuMiner, io::Error> {
+		let progpow_transition = client.upgrade()
+			.and_then(|client| client.engine().progpow_transition())
+			.unwrap_or(u64::max_value());
+
+		let shared = Arc::new(Shared {
+			job: Mutex::new(Job::default()),
+			new_job: Condvar::new(),
+			pow: EthashManager::new(&options.cache_dir, None, progpow_transition),
+			client,
+			miner,
+		});
+
+		let threads = options.threads as u64;
+		for index in 0..threads {
+			let shared = shared.clone();
+			thread::Builder::new()
+				.name(format!("CPU Miner #{}", index))
+				.spawn(move || shared.run(index, threads))?;
+		}
+
+		Ok(CpuMiner { shared })
+	}
+
+	/// Start the CPU miner and register it in the miner.
+	pub fn register(options: &Options, miner: Arc<Miner>, client: Weak<Client>) -> Result<(), io::Error> {
+		let cpu_miner = CpuMiner::start(options, Arc::downgrade(&miner), client.clone())?;
+		miner.add_work_listener(Box::new(cpu_miner) as Box<NotifyWork>);
+
+		// there might be no new blocks for a while, so request the first package right away.
+		if let Some(client) = client.upgrade() {
+			miner.update_sealing(&*client);
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::thread;
+	use std::time::Duration;
+	use client::ChainInfo;
+	use ethash::{self, EthashManager};
+	use ethereum;
+	use ethereum_types::{H256, U256};
+	use tempdir::TempDir;
+	use test_helpers::generate_dummy_client_with_spec_and_accounts;
+	use super::{search, CpuMiner, Options, Work};
+
+	#[test]
+	fn search_finds_seal_within_boundary() {
+		let tempdir = TempDir::new("").unwrap();
+		let pow = EthashManager::new(tempdir.path(), None, u64::max_value());
+		let work = Work {
+			pow_hash: H256::from(1),
+			boundary: ethash::difficulty_to_boundary(&U256::from(4)),
+			number: 1,
+		};
+
+		let (nonce, mix_hash) = search(&pow, &work, 0, 1, 1024).expect("a nonce is found for a tiny difficulty");
+		let result = pow.compute_light(1, &work.pow_hash.0, nonce);
+		assert_eq!(H256(result.mix_hash), mix_hash);
+		assert!(ethash::boundary_to_difficulty(&H256(result.value)) >= U256::from(4));
+
+		let impossible = Work { boundary: H256::zero(), ..work };
+		assert_eq!(search(&pow, &impossible, 0, 1, 16), None);
+	}
+
+	#[test]
+	fn seals_and_imports_blocks() {
+		let tempdir = TempDir::new("").unwrap();
+		let client = generate_dummy_client_with_spec_and_accounts(ethereum::new_low_difficulty_test, None);
+		let options = Options { threads: 2, cache_dir: tempdir.path().to_owned() };
+		CpuMiner::register(&options, client.miner(), Arc::downgrade(&client)).unwrap();
+
+		// any nonce satisfies the difficulty, so it's only a matter of building the caches.
+		for _ in 0..600 {
+			if client.chain_info().best_block_number > 0 {
+				break;
+			}
+			thread::sleep(Duration::from_millis(100));
+		}
+		assert!(client.chain_info().best_block_number > 0);
+	}
+}
@@ -368,7 +368,7 @@ impl Miner {
 		let chain_info = chain.chain_info();
 
 		// Open block
-		let (mut open_block, original_work_hash) = {
+		let (mut open_block, original_work_hash, is_new_block) = {
 			let mut sealing = self.sealing.lock();
 			let last_work_hash = sealing.queue.peek_last_ref().map(|pb| pb.block().header().hash());
 			let best_hash = chain_info.best_block_hash;
@@ -379,11 +379,11 @@ impl Miner {
 			//   if at least one was pushed successfully, close and enqueue new ClosedBlock;
 			//   otherwise, leave everything alone.
 			// otherwise, author a fresh block.
-			let mut open_block = match sealing.queue.get_pending_if(|b| b.block().header().parent_hash() == &best_hash) {
+			let (mut open_block, is_new_block) = match sealing.queue.get_pending_if(|b| b.block().header().parent_hash() == &best_hash) {
 				Some(old_block) => {
 					trace!(target: "miner", "prepare_block: Already have previous work; updating and returning");
 					// add transactions to old_block
-					chain.reopen_block(old_block)
+					(chain.reopen_block(old_block), false)
 				}
 				None => {
 					// block not found - create it.
@@ -395,7 +395,7 @@ impl Miner {
 						params.gas_range_target,
 						params.extra_data,
 					) {
-						Ok(block) => block,
+						Ok(block) => (block, true),
 						Err(err) => {
 							warn!(target: "miner", "Open new block failed with error {:?}. This is likely an error in chain specificiations or on-chain consensus smart contracts.", err);
 							return None;
@@ -408,7 +408,7 @@ impl Miner {
 				open_block.remove_gas_limit();
 			}
 
-			(open_block, last_work_hash)
+			(open_block, last_work_hash, is_new_block)
 		};
 
 		let mut invalid_transactions = HashSet::new();
@@ -527,6 +527,35 @@ impl Miner {
 		let elapsed = block_start.elapsed();
 		debug!(target: "miner", "Pushed {} transactions in {} ms", tx_count, took_ms(&elapsed));
 
+		// engine transactions go last, only once per block, so they take their nonces after
+		// the ones of the signer's transactions included above.
+		if is_new_block {
+			let engine_transactions = match self.engine.generate_engine_transactions(open_block.block()) {
+				Ok(transactions) => transactions,
+				Err(err) => {
+					warn!(target: "miner", "Generating engine transactions failed with error {:?}.", err);
+					Vec::new()
+				}
+			};
+
+			for transaction in engine_transactions {
+				let hash = transaction.hash();
+
+				// a queued transaction left out of the block would be invalidated by the same nonce.
+				match self.transaction_queue.next_nonce(client.clone(), &transaction.sender()) {
+					Some(next_nonce) if next_nonce > transaction.nonce => {
+						debug!(target: "miner", "Deferring engine transaction {:?}: the signer has queued transactions with nonce {}", hash, transaction.nonce);
+						continue;
+					},
+					_ => {},
+				}
+
+				if let Err(err) = open_block.push_transaction(transaction, None) {
+					warn!(target: "miner", "Pushing engine transaction {:?} failed with error {:?}.", hash, err);
+				}
+			}
+		}
+
 		let block = match open_block.close() {
 			Ok(block) => block,
 			Err(err) => {
@@ -616,6 +645,7 @@ impl Miner {
 			let sealing = self.sealing.lock();
 			if block.transactions().is_empty()
 				&& !self.forced_sealing()
+				&& !self.engine.seal_requested()
 				&& Instant::now() <= sealing.next_mandatory_reseal
 			{
 				return false
@@ -1287,7 +1317,7 @@ mod tests {
 	use header::BlockNumber;
 	use rustc_hex::FromHex;
 
-	use client::{TestBlockChainClient, EachBlockWith, ChainInfo, ImportSealedBlock};
+	use client::{TestBlockChainClient, EachBlockWith, ChainInfo, BlockInfo, ImportSealedBlock};
 	use miner::{MinerService, PendingOrdering};
 	use test_helpers::{generate_dummy_client, generate_dummy_client_with_spec_and_accounts};
 	use transaction::{Transaction};
@@ -1543,6 +1573,47 @@ mod tests {
 		assert_eq!(client.chain_info().best_block_number, 4 as BlockNumber);
 	}
 
+	#[test]
+	fn internal_seals_empty_block_on_demand() {
+		let spec = Spec::new_instant();
+		let miner = Miner::new_for_tests(&spec, None);
+
+		let client = generate_dummy_client(2);
+
+		miner.update_sealing(&*client);
+		client.flush_queue();
+		assert_eq!(client.chain_info().best_block_number, 2 as BlockNumber);
+
+		assert!(spec.engine.seal_on_demand());
+		miner.update_sealing(&*client);
+		client.flush_queue();
+		assert_eq!(client.chain_info().best_block_number, 3 as BlockNumber);
+	}
+
+	#[test]
+	fn seals_randomness_contract_commitment() {
+		let tap = Arc::new(AccountProvider::transient_provider());
+		let addr = tap.insert_account(keccak("1").into(), &"".into()).unwrap();
+		let client = generate_dummy_client_with_spec_and_accounts(Spec::new_test_round_randomness_contract, Some(tap));
+		let miner = client.miner();
+		miner.set_author(addr, Some("".into())).unwrap();
+
+		// the validators take turns, so wait for our step.
+		for _ in 0..10 {
+			miner.update_sealing(&*client);
+			if client.chain_info().best_block_number == 1 {
+				break;
+			}
+			::std::thread::sleep(Duration::from_millis(500));
+		}
+
+		let block = client.block(BlockId::Number(1)).expect("our validator seals within a round");
+		let transactions = block.transactions();
+		assert_eq!(transactions.len(), 1);
+		assert_eq!(transactions[0].action, Action::Call(Address::from(0x42)));
+		assert_eq!(&transactions[0].data[..4], &keccak("commitHash(bytes32,bytes)")[..4]);
+	}
+
 	#[test]
 	fn should_fail_setting_engine_signer_without_account_provider() {
 		let spec = Spec::new_instant;
@@ -23,6 +23,8 @@ mod miner;
 mod service_transaction_checker;
 
 pub mod pool_client;
+#[cfg(feature = "work-notify")]
+pub mod cpu_miner;
 #[cfg(feature = "stratum")]
 pub mod stratum;
 
@@ -0,0 +1,396 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Offline conversion of archive databases to pruned ones.
+//!
+//! The chain is copied unchanged. Only the state reachable from the most recent canonical
+//! state roots is copied, and the journal of the pruning algorithm is rebuilt for them.
+
+use std::mem;
+use std::sync::Arc;
+
+use account_db::{AccountDB, AccountDBMut};
+use blockchain::{BlockChain, BlockChainDB, BlockProvider, Config as BlockChainConfig};
+use db;
+use error::Error;
+use ethereum_types::H256;
+use ethtrie::TrieError;
+use flat_state;
+use hash::{KECCAK_EMPTY, KECCAK_NULL_RLP};
+use hashdb::HashDB;
+use journaldb::{self, Algorithm, JournalDB};
+use keccak_hasher::KeccakHasher;
+use kvdb::{DBTransaction, DBValue, KeyValueDB};
+use rlp::{self, Prototype, Rlp};
+use spec::Spec;
+use types::basic_account::BasicAccount;
+
+/// Number of key-value pairs copied in a single transaction.
+const COPY_BATCH: usize = 16 * 1024;
+/// Number of state nodes of the oldest kept state written out at once.
+const NODE_BATCH: u64 = 16 * 1024;
+
+/// Columns copied unchanged. The flat state is regenerated by the client.
+const COPIED_COLUMNS: [Option<u32>; 7] = [
+	db::COL_HEADERS,
+	db::COL_BODIES,
+	db::COL_EXTRA,
+	db::COL_TRACE,
+	db::COL_ACCOUNT_BLOOM,
+	db::COL_NODE_INFO,
+	db::COL_LIGHT_CHAIN,
+];
+
+/// Copy the archive database in `source` into the empty database in `target`, keeping
+/// the states of the last `history` canonical blocks under the pruning `algorithm`.
+///
+/// Blooms and ancient blocks are stored outside of the key-value database and have to be
+/// copied by the caller. Returns the number of copied state nodes.
+pub fn prune_database(source: Arc<BlockChainDB>, target: Arc<BlockChainDB>, spec: &Spec, algorithm: Algorithm, history: u64) -> Result<u64, Error> {
+	match algorithm {
+		Algorithm::OverlayRecent | Algorithm::EarlyMerge => {},
+		other => bail!("Cannot convert to the {} pruning algorithm", other),
+	}
+	if history == 0 {
+		bail!("At least one state has to be kept");
+	}
+	if source.key_value().get(db::COL_EXTRA, b"best")?.is_none() {
+		bail!("Source database does not contain a chain");
+	}
+
+	let source_state = journaldb::new(source.key_value().clone(), Algorithm::Archive, db::COL_STATE);
+	let mut target_state = journaldb::new(target.key_value().clone(), algorithm, db::COL_STATE);
+	if !target_state.is_empty() {
+		bail!("Target database is not empty");
+	}
+
+	copy_columns(&**source.key_value(), &**target.key_value())?;
+
+	let chain = BlockChain::new(BlockChainConfig::default(), &spec.genesis_block(), source.clone());
+	let best = chain.best_block_number();
+	let first = best.saturating_sub(history - 1);
+
+	let mut nodes = 0;
+	for number in first..(best + 1) {
+		let hash = chain.block_hash(number).ok_or_else(|| format!("Hash of block #{} is missing", number))?;
+		let header = chain.block_header_data(&hash).ok_or_else(|| format!("Header of block #{} is missing", number))?;
+
+		// the oldest state is never pruned, so it goes straight into the backing database.
+		nodes += copy_state(source_state.as_hashdb(), &mut *target_state, &header.state_root(), number == first)?;
+
+		let mut batch = DBTransaction::new();
+		if number == first {
+			target_state.inject(&mut batch)?;
+		}
+		target_state.journal_under(&mut batch, number, &hash)?;
+		target.key_value().write(batch)?;
+		target_state.flush();
+
+		chain.collect_garbage();
+		info!(target: "prune", "Copied state of block #{} ({} nodes)", number, nodes);
+	}
+
+	target.key_value().flush()?;
+	Ok(nodes)
+}
+
+/// Copy all columns but the state ones into `target`.
+fn copy_columns(source: &KeyValueDB, target: &KeyValueDB) -> Result<(), Error> {
+	for column in COPIED_COLUMNS.iter().cloned() {
+		let mut batch = DBTransaction::new();
+		let mut pending = 0;
+		for (key, value) in source.iter(column) {
+			if column == db::COL_EXTRA && flat_state::is_metadata_key(&key) {
+				continue;
+			}
+
+			batch.put(column, &key, &value);
+			pending += 1;
+			if pending == COPY_BATCH {
+				target.write(mem::replace(&mut batch, DBTransaction::new()))?;
+				pending = 0;
+			}
+		}
+		target.write(batch)?;
+	}
+
+	Ok(())
+}
+
+/// Copy the state with given root, skipping the subtries `target` already contains.
+/// If `inject` is set, the copied nodes are regularly injected into the backing database.
+/// Returns the number of copied nodes.
+fn copy_state(source: &HashDB<KeccakHasher, DBValue>, target: &mut JournalDB, root: &H256, inject: bool) -> Result<u64, Error> {
+	let mut accounts = TrieCopy::new(source, target.as_hashdb_mut(), root)?;
+	let mut copied = 0;
+	let mut pending = 0;
+
+	while let Some((key, value)) = accounts.next_leaf(source, target.as_hashdb_mut())? {
+		let address_hash = match key_from_nibbles(&key) {
+			Some(hash) => hash,
+			None => bail!("Invalid account key in state {:?}", root),
+		};
+		let account: BasicAccount = rlp::decode(&value)?;
+
+		{
+			let account_db = AccountDB::from_hash(source, address_hash);
+			let mut target_db = AccountDBMut::from_hash(target.as_hashdb_mut(), address_hash);
+
+			if account.code_hash != KECCAK_EMPTY && !target_db.contains(&account.code_hash) {
+				let code = account_db.get(&account.code_hash).ok_or_else(|| TrieError::IncompleteDatabase(account.code_hash))?;
+				target_db.emplace(account.code_hash, code);
+				copied += 1;
+			}
+
+			let mut storage = TrieCopy::new(&account_db, &mut target_db, &account.storage_root)?;
+			while storage.next_leaf(&account_db, &mut target_db)?.is_some() {}
+			copied += storage.copied;
+		}
+
+		if inject && accounts.copied + copied - pending >= NODE_BATCH {
+			let mut batch = DBTransaction::new();
+			target.inject(&mut batch)?;
+			target.backing().write(batch)?;
+			pending = accounts.copied + copied;
+		}
+	}
+
+	Ok(accounts.copied + copied)
+}
+
+/// Depth-first copy of the nodes of a trie which are missing in the target.
+struct TrieCopy {
+	/// Copied nodes which are not visited yet, with the nibbles leading to them.
+	stack: Vec<(DBValue, Vec<u8>)>,
+	/// Number of copied nodes.
+	copied: u64,
+}
+
+impl TrieCopy {
+	fn new(source: &HashDB<KeccakHasher, DBValue>, target: &mut HashDB<KeccakHasher, DBValue>, root: &H256) -> Result<Self, Error> {
+		let mut copy = TrieCopy {
+			stack: Vec::new(),
+			copied: 0,
+		};
+		copy.copy_node(source, target, root, Vec::new())?;
+		Ok(copy)
+	}
+
+	/// Copy nodes until the next leaf is reached. Returns its key nibbles and value.
+	fn next_leaf(&mut self, source: &HashDB<KeccakHasher, DBValue>, target: &mut HashDB<KeccakHasher, DBValue>) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+		while let Some((node, path)) = self.stack.pop() {
+			let rlp = Rlp::new(&node);
+			match rlp.prototype()? {
+				Prototype::List(2) => {
+					let (nibbles, is_leaf) = decode_partial(rlp.at(0)?.data()?);
+					let mut key = path;
+					key.extend(nibbles);
+					if is_leaf {
+						return Ok(Some((key, rlp.at(1)?.data()?.to_vec())));
+					}
+					self.copy_child(source, target, &rlp.at(1)?, key)?;
+				},
+				Prototype::List(17) => {
+					for i in 0..16 {
+						let mut child_path = path.clone();
+						child_path.push(i as u8);
+						self.copy_child(source, target, &rlp.at(i)?, child_path)?;
+					}
+					let value = rlp.at(16)?.data()?;
+					if !value.is_empty() {
+						return Ok(Some((path, value.to_vec())));
+					}
+				},
+				// empty node.
+				_ => {},
+			}
+		}
+
+		Ok(None)
+	}
+
+	/// Copy the node with given hash unless the target already contains it.
+	fn copy_node(&mut self, source: &HashDB<KeccakHasher, DBValue>, target: &mut HashDB<KeccakHasher, DBValue>, hash: &H256, path: Vec<u8>) -> Result<(), Error> {
+		if *hash == KECCAK_NULL_RLP || target.contains(hash) {
+			return Ok(());
+		}
+
+		let node = source.get(hash).ok_or_else(|| TrieError::IncompleteDatabase(*hash))?;
+		target.emplace(*hash, node.clone());
+		self.copied += 1;
+		self.stack.push((node, path));
+		Ok(())
+	}
+
+	/// Copy the child referenced by a node.
+	fn copy_child(&mut self, source: &HashDB<KeccakHasher, DBValue>, target: &mut HashDB<KeccakHasher, DBValue>, child: &Rlp, path: Vec<u8>) -> Result<(), Error> {
+		if child.is_list() {
+			// inline nodes are stored within their parent.
+			self.stack.push((DBValue::from_slice(child.as_raw()), path));
+			return Ok(());
+		}
+
+		let data = child.data()?;
+		if data.len() == 32 {
+			self.copy_node(source, target, &H256::from_slice(data), path)?;
+		}
+		Ok(())
+	}
+}
+
+/// Decode a hex-prefix encoded path. Returns its nibbles and whether it belongs to a leaf.
+fn decode_partial(encoded: &[u8]) -> (Vec<u8>, bool) {
+	let mut nibbles = Vec::with_capacity(encoded.len() * 2);
+	if encoded.is_empty() {
+		return (nibbles, false);
+	}
+
+	let flags = encoded[0] >> 4;
+	if flags & 1 == 1 {
+		nibbles.push(encoded[0] & 0x0f);
+	}
+	for byte in &encoded[1..] {
+		nibbles.push(byte >> 4);
+		nibbles.push(byte & 0x0f);
+	}
+	(nibbles, flags & 2 == 2)
+}
+
+fn key_from_nibbles(nibbles: &[u8]) -> Option<H256> {
+	if nibbles.len() != 64 {
+		return None;
+	}
+
+	let mut key = H256::zero();
+	for (i, pair) in nibbles.chunks(2).enumerate() {
+		key[i] = pair[0] << 4 | pair[1];
+	}
+	Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use client::{BlockId, BlockInfo, ChainInfo, Client, ClientConfig, ImportBlock};
+	use ethtrie::TrieDB;
+	use io::IoChannel;
+	use miner::Miner;
+	use test_helpers::{new_db, generate_dummy_client_with_data};
+	use trie::Trie;
+	use verification::queue::kind::blocks::Unverified;
+	use super::*;
+
+	fn archive_client(blocks: u64) -> (Arc<Client>, Arc<BlockChainDB>) {
+		let source = generate_dummy_client_with_data(blocks as u32, 2, &[1.into()]);
+		let spec = Spec::new_null();
+		let db = new_db();
+		let client = Client::new(
+			ClientConfig { pruning: Algorithm::Archive, ..Default::default() },
+			&spec,
+			db.clone(),
+			Arc::new(Miner::new_for_tests(&spec, None)),
+			IoChannel::disconnected(),
+		).unwrap();
+
+		for number in 1..(blocks + 1) {
+			let block = source.block(BlockId::Number(number)).unwrap().into_inner();
+			client.import_block(Unverified::from_rlp(block).unwrap()).unwrap();
+		}
+		client.flush_queue();
+		client.import_verified_blocks();
+		assert_eq!(client.chain_info().best_block_number, blocks);
+		(client, db)
+	}
+
+	fn count_accounts(db: &HashDB<KeccakHasher, DBValue>, root: &H256) -> usize {
+		TrieDB::new(db, root).unwrap().iter().unwrap().map(|item| item.unwrap()).count()
+	}
+
+	#[test]
+	fn keeps_recent_states() {
+		let (client, source) = archive_client(8);
+		let spec = Spec::new_null();
+		let target = new_db();
+
+		let nodes = prune_database(source.clone(), target.clone(), &spec, Algorithm::OverlayRecent, 3).unwrap();
+		assert!(nodes > 0);
+
+		let source_state = journaldb::new(source.key_value().clone(), Algorithm::Archive, db::COL_STATE);
+		let target_state = journaldb::new(target.key_value().clone(), Algorithm::OverlayRecent, db::COL_STATE);
+		assert_eq!(target_state.earliest_era(), Some(6));
+		assert_eq!(target_state.latest_era(), Some(8));
+
+		for number in 6..9 {
+			let root = client.block_header(BlockId::Number(number)).unwrap().state_root();
+			assert_eq!(count_accounts(target_state.as_hashdb(), &root), count_accounts(source_state.as_hashdb(), &root));
+		}
+		let old_root = client.block_header(BlockId::Number(1)).unwrap().state_root();
+		assert!(!target_state.contains(&old_root));
+
+		let pruned = Client::new(
+			ClientConfig::default(),
+			&spec,
+			target,
+			Arc::new(Miner::new_for_tests(&spec, None)),
+			IoChannel::disconnected(),
+		).unwrap();
+		assert_eq!(pruned.chain_info().best_block_hash, client.chain_info().best_block_hash);
+		assert!(pruned.state_at(BlockId::Latest).is_some());
+	}
+
+	#[test]
+	fn converts_to_early_merge() {
+		let (client, source) = archive_client(8);
+		let spec = Spec::new_null();
+		let target = new_db();
+
+		let nodes = prune_database(source.clone(), target.clone(), &spec, Algorithm::EarlyMerge, 3).unwrap();
+		assert!(nodes > 0);
+
+		let source_state = journaldb::new(source.key_value().clone(), Algorithm::Archive, db::COL_STATE);
+		let target_state = journaldb::new(target.key_value().clone(), Algorithm::EarlyMerge, db::COL_STATE);
+		assert_eq!(target_state.latest_era(), Some(8));
+
+		for number in 6..9 {
+			let root = client.block_header(BlockId::Number(number)).unwrap().state_root();
+			assert_eq!(count_accounts(target_state.as_hashdb(), &root), count_accounts(source_state.as_hashdb(), &root));
+		}
+		let old_root = client.block_header(BlockId::Number(1)).unwrap().state_root();
+		assert!(!target_state.contains(&old_root));
+
+		let pruned = Client::new(
+			ClientConfig { pruning: Algorithm::EarlyMerge, history: 3, ..Default::default() },
+			&spec,
+			target,
+			Arc::new(Miner::new_for_tests(&spec, None)),
+			IoChannel::disconnected(),
+		).unwrap();
+		assert_eq!(pruned.chain_info().best_block_hash, client.chain_info().best_block_hash);
+		assert!(pruned.state_at(BlockId::Latest).is_some());
+		assert!(pruned.state_at(BlockId::Number(6)).is_some());
+	}
+
+	#[test]
+	fn rejects_unsupported_conversions() {
+		let (_client, source) = archive_client(1);
+		let spec = Spec::new_null();
+
+		assert!(prune_database(source.clone(), new_db(), &spec, Algorithm::Archive, 1).is_err());
+		assert!(prune_database(source.clone(), new_db(), &spec, Algorithm::OverlayRecent, 0).is_err());
+		assert!(prune_database(new_db(), new_db(), &spec, Algorithm::EarlyMerge, 1).is_err());
+	}
+}
@@ -29,9 +29,12 @@ use bytes::Bytes;
 use ethereum_types::H256;
 use rlp::{RlpStream, Rlp};
 
-use super::ManifestData;
+use super::{ManifestData, ChunkMetadata};
 
-const SNAPSHOT_VERSION: u64 = 2;
+// Packed snapshots without chunk metadata, always snappy-compressed.
+const LEGACY_SNAPSHOT_VERSION: u64 = 2;
+// Packed snapshots carrying the chunk codec and per-chunk metadata.
+const SNAPSHOT_VERSION: u64 = 3;
 
 /// Something which can write snapshots.
 /// Writing the same chunk multiple times will lead to implementation-defined
@@ -61,7 +64,8 @@ struct ChunkInfo(H256, u64, u64);
 ///
 /// The manifest contains all the same information as a standard `ManifestData`,
 /// but also maps chunk hashes to their lengths and offsets in the file
-/// for easy reading.
+/// for easy reading. Snapshots with chunk metadata are written as format
+/// version 3, which appends the metadata and the chunk version to the manifest.
 pub struct PackedWriter {
 	file: File,
 	state_hashes: Vec<ChunkInfo>,
@@ -105,15 +109,23 @@ impl SnapshotWriter for PackedWriter {
 	fn finish(mut self, manifest: ManifestData) -> io::Result<()> {
 		// we ignore the hashes fields of the manifest under the assumption that
 		// they are consistent with ours.
-		let mut stream = RlpStream::new_list(6);
+		// snapshots with chunk metadata get a new format version, so that older
+		// readers refuse them instead of decompressing their chunks as snappy.
+		let version = if manifest.metadata.is_some() { SNAPSHOT_VERSION } else { LEGACY_SNAPSHOT_VERSION };
+		let mut stream = RlpStream::new_list(if manifest.metadata.is_some() { 8 } else { 6 });
 		stream
-			.append(&SNAPSHOT_VERSION)
+			.append(&version)
 			.append_list(&self.state_hashes)
 			.append_list(&self.block_hashes)
 			.append(&manifest.state_root)
 			.append(&manifest.block_number)
 			.append(&manifest.block_hash);
 
+		if let Some(ref metadata) = manifest.metadata {
+			stream.append(metadata);
+			stream.append(&manifest.version);
+		}
+
 		let manifest_rlp = stream.out();
 
 		self.file.write_all(&manifest_rlp)?;
@@ -239,7 +251,8 @@ impl PackedReader {
 
 		let rlp = Rlp::new(&manifest_buf);
 
-		let (start, version) = if rlp.item_count()? == 5 {
+		let item_count = rlp.item_count()?;
+		let (start, version) = if item_count == 5 {
 			(0, 1)
 		} else {
 			(1, rlp.val_at(0)?)
@@ -249,6 +262,14 @@ impl PackedReader {
 			return Err(::snapshot::error::Error::VersionNotSupported(version));
 		}
 
+		// version 3 snapshots carry the chunk metadata and the version of the
+		// chunks themselves.
+		let (metadata, version) = if version >= SNAPSHOT_VERSION {
+			(Some(rlp.val_at::<ChunkMetadata>(5 + start)?), rlp.val_at(6 + start)?)
+		} else {
+			(None, version)
+		};
+
 		let state: Vec<ChunkInfo> = rlp.list_at(0 + start)?;
 		let blocks: Vec<ChunkInfo> = rlp.list_at(1 + start)?;
 
@@ -259,6 +280,7 @@ impl PackedReader {
 			state_root: rlp.val_at(2 + start)?,
 			block_number: rlp.val_at(3 + start)?,
 			block_hash: rlp.val_at(4 + start)?,
+			metadata: metadata,
 		};
 
 		Ok(Some(PackedReader {
@@ -336,12 +358,22 @@ mod tests {
 	use tempdir::TempDir;
 	use hash::keccak;
 
-	use snapshot::ManifestData;
-	use super::{SnapshotWriter, SnapshotReader, PackedWriter, PackedReader, LooseWriter, LooseReader, SNAPSHOT_VERSION};
+	use snapshot::{ManifestData, ChunkCodec, ChunkInfo, ChunkMetadata};
+	use rlp::Rlp;
+	use super::{SnapshotWriter, SnapshotReader, PackedWriter, PackedReader, LooseWriter, LooseReader, SNAPSHOT_VERSION, LEGACY_SNAPSHOT_VERSION};
 
 	const STATE_CHUNKS: &'static [&'static [u8]] = &[b"dog", b"cat", b"hello world", b"hi", b"notarealchunk"];
 	const BLOCK_CHUNKS: &'static [&'static [u8]] = &[b"hello!", b"goodbye!", b"abcdefg", b"hijklmnop", b"qrstuvwxy", b"and", b"z"];
 
+	// format version stored at the head of a packed snapshot's manifest.
+	fn packed_version(path: &::std::path::Path) -> u64 {
+		let data = ::std::fs::read(path).unwrap();
+		let mut off_bytes = [0u8; 8];
+		off_bytes.copy_from_slice(&data[data.len() - 8..]);
+		let off = off_bytes.iter().rev().fold(0u64, |acc, b| (acc << 8) + *b as u64) as usize;
+		Rlp::new(&data[off..data.len() - 8]).val_at(0).unwrap()
+	}
+
 	#[test]
 	fn packed_write_and_read() {
 		let tempdir = TempDir::new("").unwrap();
@@ -364,15 +396,27 @@ mod tests {
 		}
 
 		let manifest = ManifestData {
-			version: SNAPSHOT_VERSION,
+			version: 2,
 			state_hashes: state_hashes,
 			block_hashes: block_hashes,
 			state_root: keccak(b"notarealroot"),
 			block_number: 12345678987654321,
 			block_hash: keccak(b"notarealblock"),
+			metadata: Some(ChunkMetadata {
+				codec: ChunkCodec::Zstd,
+				state_chunks: STATE_CHUNKS.iter().map(|c| ChunkInfo {
+					uncompressed_size: c.len() as u64,
+					account_range: Some((keccak(c), keccak(c))),
+				}).collect(),
+				block_chunks: BLOCK_CHUNKS.iter().map(|c| ChunkInfo {
+					uncompressed_size: c.len() as u64,
+					account_range: None,
+				}).collect(),
+			}),
 		};
 
 		writer.finish(manifest.clone()).unwrap();
+		assert_eq!(packed_version(&path), SNAPSHOT_VERSION);
 
 		let reader = PackedReader::new(&path).unwrap().unwrap();
 		assert_eq!(reader.manifest(), &manifest);
@@ -403,12 +447,13 @@ mod tests {
 		}
 
 		let manifest = ManifestData {
-			version: SNAPSHOT_VERSION,
+			version: 2,
 			state_hashes: state_hashes,
 			block_hashes: block_hashes,
 			state_root: keccak(b"notarealroot"),
 			block_number: 12345678987654321,
 			block_hash: keccak(b"notarealblock)"),
+			metadata: None,
 		};
 
 		writer.finish(manifest.clone()).unwrap();
@@ -420,4 +465,30 @@ mod tests {
 			reader.chunk(hash.clone()).unwrap();
 		}
 	}
+
+	#[test]
+	fn packed_snappy_snapshot_keeps_legacy_version() {
+		let tempdir = TempDir::new("").unwrap();
+		let path = tempdir.path().join("packed");
+		let mut writer = PackedWriter::new(&path).unwrap();
+
+		let hash = keccak(STATE_CHUNKS[0]);
+		writer.write_state_chunk(hash, STATE_CHUNKS[0]).unwrap();
+
+		let manifest = ManifestData {
+			version: 2,
+			state_hashes: vec![hash],
+			block_hashes: Vec::new(),
+			state_root: keccak(b"notarealroot"),
+			block_number: 1,
+			block_hash: keccak(b"notarealblock"),
+			metadata: None,
+		};
+
+		writer.finish(manifest.clone()).unwrap();
+		assert_eq!(packed_version(&path), LEGACY_SNAPSHOT_VERSION);
+
+		let reader = PackedReader::new(&path).unwrap().unwrap();
+		assert_eq!(reader.manifest(), &manifest);
+	}
 }
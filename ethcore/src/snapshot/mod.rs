@@ -35,6 +35,7 @@ use ethereum_types::{H256, U256};
 use hashdb::HashDB;
 use keccak_hasher::KeccakHasher;
 use snappy;
+use zstd;
 use bytes::Bytes;
 use parking_lot::Mutex;
 use journaldb::{self, Algorithm, JournalDB};
@@ -59,7 +60,7 @@ pub use self::consensus::*;
 pub use self::service::{Service, DatabaseRestore};
 pub use self::traits::SnapshotService;
 pub use self::watcher::Watcher;
-pub use types::snapshot_manifest::ManifestData;
+pub use types::snapshot_manifest::{ManifestData, ChunkCodec, ChunkInfo, ChunkMetadata};
 pub use types::restoration_status::RestorationStatus;
 pub use types::basic_account::BasicAccount;
 
@@ -93,6 +94,8 @@ const STATE_CHUNK_VERSION: u64 = 2;
 const SNAPSHOT_SUBPARTS: usize = 16;
 /// Maximum number of snapshot subparts (must be a multiple of `SNAPSHOT_SUBPARTS`)
 const MAX_SNAPSHOT_SUBPARTS: usize = 256;
+/// Compression level used for zstd chunks.
+const ZSTD_COMPRESSION_LEVEL: i32 = 9;
 
 /// Configuration for the Snapshot service
 #[derive(Debug, Clone, PartialEq)]
@@ -101,6 +104,8 @@ pub struct SnapshotConfiguration {
 	pub no_periodic: bool,
 	/// Number of threads for creating snapshots
 	pub processing_threads: usize,
+	/// Codec used to compress created chunks
+	pub codec: ChunkCodec,
 }
 
 impl Default for SnapshotConfiguration {
@@ -108,6 +113,7 @@ impl Default for SnapshotConfiguration {
 		SnapshotConfiguration {
 			no_periodic: false,
 			processing_threads: ::std::cmp::max(1, num_cpus::get() / 2),
+			codec: ChunkCodec::default(),
 		}
 	}
 }
@@ -155,6 +161,7 @@ pub fn take_snapshot<W: SnapshotWriter + Send>(
 	writer: W,
 	p: &Progress,
 	processing_threads: usize,
+	codec: ChunkCodec,
 ) -> Result<(), Error> {
 	let start_header = chain.block_header_data(&block_at)
 		.ok_or(Error::InvalidStartingBlock(BlockId::Hash(block_at)))?;
@@ -164,47 +171,23 @@ pub fn take_snapshot<W: SnapshotWriter + Send>(
 	info!("Taking snapshot starting at block {}", number);
 
 	let writer = Mutex::new(writer);
-	let chunker = engine.snapshot_components().ok_or(Error::SnapshotsUnsupported)?;
+	let chunker = engine.snapshot_components(number).ok_or(Error::SnapshotsUnsupported)?;
 	let snapshot_version = chunker.current_version();
-	let (state_hashes, block_hashes) = scope(|scope| -> Result<(Vec<H256>, Vec<H256>), Error> {
+	let (state_chunks, block_chunks) = scope(|scope| -> Result<(Vec<(H256, ChunkInfo)>, Vec<(H256, ChunkInfo)>), Error> {
 		let writer = &writer;
-		let block_guard = scope.spawn(move || chunk_secondary(chunker, chain, block_at, writer, p));
+		let block_guard = scope.spawn(move || chunk_secondary(chunker, chain, block_at, writer, p, codec));
 
-		// The number of threads must be between 1 and SNAPSHOT_SUBPARTS
-		assert!(processing_threads >= 1, "Cannot use less than 1 threads for creating snapshots");
-		let num_threads: usize = cmp::min(processing_threads, SNAPSHOT_SUBPARTS);
-		info!(target: "snapshot", "Using {} threads for Snapshot creation.", num_threads);
-
-		let mut state_guards = Vec::with_capacity(num_threads as usize);
-
-		for thread_idx in 0..num_threads {
-			let state_guard = scope.spawn(move || -> Result<Vec<H256>, Error> {
-				let mut chunk_hashes = Vec::new();
-
-				for part in (thread_idx..SNAPSHOT_SUBPARTS).step_by(num_threads) {
-					debug!(target: "snapshot", "Chunking part {} in thread {}", part, thread_idx);
-					let mut hashes = chunk_state(state_db, &state_root, writer, p, Some(part))?;
-					chunk_hashes.append(&mut hashes);
-				}
-
-				Ok(chunk_hashes)
-			});
-			state_guards.push(state_guard);
-		}
-
-		let block_hashes = block_guard.join()?;
-		let mut state_hashes = Vec::new();
-
-		for guard in state_guards {
-			let part_state_hashes = guard.join()?;
-			state_hashes.extend(part_state_hashes);
-		}
+		let state_chunks = chunk_state_parts(state_db, &state_root, writer, p, processing_threads, codec)?;
+		let block_chunks = block_guard.join()?;
 
 		debug!(target: "snapshot", "Took a snapshot of {} accounts", p.accounts.load(Ordering::SeqCst));
-		Ok((state_hashes, block_hashes))
+		Ok((state_chunks, block_chunks))
 	})?;
 
-	info!(target: "snapshot", "produced {} state chunks and {} block chunks.", state_hashes.len(), block_hashes.len());
+	info!(target: "snapshot", "produced {} state chunks and {} block chunks.", state_chunks.len(), block_chunks.len());
+
+	let (state_hashes, state_infos): (Vec<_>, Vec<_>) = state_chunks.into_iter().unzip();
+	let (block_hashes, block_infos): (Vec<_>, Vec<_>) = block_chunks.into_iter().unzip();
 
 	let manifest_data = ManifestData {
 		version: snapshot_version,
@@ -213,6 +196,11 @@ pub fn take_snapshot<W: SnapshotWriter + Send>(
 		state_root: state_root,
 		block_number: number,
 		block_hash: block_at,
+		metadata: Some(ChunkMetadata {
+			codec: codec,
+			state_chunks: state_infos,
+			block_chunks: block_infos,
+		}),
 	};
 
 	writer.into_inner().finish(manifest_data)?;
@@ -222,20 +210,135 @@ pub fn take_snapshot<W: SnapshotWriter + Send>(
 	Ok(())
 }
 
+/// Compress a raw chunk with the given codec into `buffer`, returning the compressed size.
+fn compress_chunk(codec: ChunkCodec, raw_data: &[u8], buffer: &mut Bytes) -> ::std::io::Result<usize> {
+	match codec {
+		ChunkCodec::Snappy => Ok(snappy::compress_into(raw_data, buffer)),
+		ChunkCodec::Zstd => {
+			*buffer = zstd::block::compress(raw_data, ZSTD_COMPRESSION_LEVEL)?;
+			Ok(buffer.len())
+		}
+	}
+}
+
+/// Decompress a chunk with the given codec into `buffer`.
+///
+/// `expected_size` is the uncompressed size announced by the manifest, if any.
+/// Chunks which would decompress to more than `MAX_CHUNK_SIZE` bytes are rejected
+/// before decompression.
+pub fn decompress_chunk<'a>(
+	codec: ChunkCodec,
+	chunk: &[u8],
+	expected_size: Option<usize>,
+	buffer: &'a mut Bytes,
+) -> Result<&'a [u8], ::error::Error> {
+	let len = match codec {
+		ChunkCodec::Snappy => {
+			let expected_len = snappy::decompressed_len(chunk)?;
+			if expected_len > MAX_CHUNK_SIZE {
+				trace!(target: "snapshot", "Discarding large chunk: {} vs {}", expected_len, MAX_CHUNK_SIZE);
+				return Err(Error::ChunkTooLarge.into());
+			}
+			snappy::decompress_into(chunk, buffer)?
+		}
+		ChunkCodec::Zstd => {
+			// zstd fails rather than write past the given capacity.
+			let capacity = expected_size.unwrap_or(MAX_CHUNK_SIZE);
+			if capacity > MAX_CHUNK_SIZE {
+				return Err(Error::ChunkTooLarge.into());
+			}
+			*buffer = zstd::block::decompress(chunk, capacity)?;
+			buffer.len()
+		}
+	};
+
+	match expected_size {
+		Some(expected) if expected != len => Err(Error::WrongChunkFormat(
+			format!("Chunk decompressed to {} bytes, manifest announced {}.", len, expected)
+		).into()),
+		_ => Ok(&buffer[..len]),
+	}
+}
+
+/// Check that the first and the last account of a decompressed state chunk
+/// match the range announced by the manifest.
+pub fn verify_account_range(chunk: &[u8], first: &H256, last: &H256) -> Result<(), Error> {
+	let rlp = Rlp::new(chunk);
+	let count = rlp.item_count()?;
+	if count == 0 {
+		return Err(Error::WrongChunkFormat("Empty state chunk.".into()));
+	}
+
+	let found_first: H256 = rlp.at(0)?.val_at(0)?;
+	let found_last: H256 = rlp.at(count - 1)?.val_at(0)?;
+	if &found_first != first || &found_last != last {
+		return Err(Error::WrongChunkFormat(format!(
+			"State chunk spans accounts {:?}..{:?}, manifest announced {:?}..{:?}.",
+			found_first, found_last, first, last
+		)));
+	}
+
+	Ok(())
+}
+
+/// Check the per-chunk metadata of a manifest before any chunk is fetched:
+/// one entry per distinct chunk hash, no entry larger than `MAX_CHUNK_SIZE`, and state chunks
+/// covering ascending, non-overlapping account ranges. Adjacent ranges may share an
+/// endpoint, since large accounts are split across chunks.
+pub fn verify_chunk_metadata(manifest: &ManifestData) -> Result<(), Error> {
+	let metadata = match manifest.metadata {
+		Some(ref metadata) => metadata,
+		None => return Ok(()),
+	};
+
+	if metadata.state_chunks.len() != manifest.state_hashes.len() || metadata.block_chunks.len() != manifest.block_hashes.len() {
+		return Err(Error::InvalidChunkMetadata("chunk count mismatch".into()));
+	}
+
+	// entries are matched to hashes by position, so every hash must name a single chunk.
+	let mut hashes = HashSet::new();
+	if let Some(hash) = manifest.state_hashes.iter().chain(manifest.block_hashes.iter()).find(|hash| !hashes.insert(*hash)) {
+		return Err(Error::InvalidChunkMetadata(format!("chunk {:x} is listed more than once", hash)));
+	}
+
+	let oversized = metadata.state_chunks.iter().chain(metadata.block_chunks.iter())
+		.any(|info| info.uncompressed_size > MAX_CHUNK_SIZE as u64);
+	if oversized {
+		return Err(Error::ChunkTooLarge);
+	}
+
+	if metadata.block_chunks.iter().any(|info| info.account_range.is_some()) {
+		return Err(Error::InvalidChunkMetadata("account range on a block chunk".into()));
+	}
+
+	let mut prev_last: Option<H256> = None;
+	for (i, info) in metadata.state_chunks.iter().enumerate() {
+		let (first, last) = info.account_range
+			.ok_or_else(|| Error::InvalidChunkMetadata(format!("state chunk {} has no account range", i)))?;
+
+		if first > last || prev_last.map_or(false, |prev| prev > first) {
+			return Err(Error::InvalidChunkMetadata(format!("state chunk {} is out of order", i)));
+		}
+		prev_last = Some(last);
+	}
+
+	Ok(())
+}
+
 /// Create and write out all secondary chunks to disk, returning a vector of all
-/// the hashes of secondary chunks created.
+/// the hashes of secondary chunks created, along with their metadata.
 ///
 /// Secondary chunks are engine-specific, but they intend to corroborate the state data
 /// in the state chunks.
 /// Returns a list of chunk hashes, with the first having the blocks furthest from the genesis.
-pub fn chunk_secondary<'a>(mut chunker: Box<SnapshotComponents>, chain: &'a BlockChain, start_hash: H256, writer: &Mutex<SnapshotWriter + 'a>, progress: &'a Progress) -> Result<Vec<H256>, Error> {
-	let mut chunk_hashes = Vec::new();
-	let mut snappy_buffer = vec![0; snappy::max_compressed_len(PREFERRED_CHUNK_SIZE)];
+pub fn chunk_secondary<'a>(mut chunker: Box<SnapshotComponents>, chain: &'a BlockChain, start_hash: H256, writer: &Mutex<SnapshotWriter + 'a>, progress: &'a Progress, codec: ChunkCodec) -> Result<Vec<(H256, ChunkInfo)>, Error> {
+	let mut chunks = Vec::new();
+	let mut compress_buffer = vec![0; snappy::max_compressed_len(PREFERRED_CHUNK_SIZE)];
 
 	{
 		let mut chunk_sink = |raw_data: &[u8]| {
-			let compressed_size = snappy::compress_into(raw_data, &mut snappy_buffer);
-			let compressed = &snappy_buffer[..compressed_size];
+			let compressed_size = compress_chunk(codec, raw_data, &mut compress_buffer)?;
+			let compressed = &compress_buffer[..compressed_size];
 			let hash = keccak(&compressed);
 			let size = compressed.len();
 
@@ -244,7 +347,10 @@ pub fn chunk_secondary<'a>(mut chunker: Box<SnapshotComponents>, chain: &'a Bloc
 				hash, size, raw_data.len());
 
 			progress.size.fetch_add(size, Ordering::SeqCst);
-			chunk_hashes.push(hash);
+			chunks.push((hash, ChunkInfo {
+				uncompressed_size: raw_data.len() as u64,
+				account_range: None,
+			}));
 			Ok(())
 		};
 
@@ -257,15 +363,18 @@ pub fn chunk_secondary<'a>(mut chunker: Box<SnapshotComponents>, chain: &'a Bloc
 		)?;
 	}
 
-	Ok(chunk_hashes)
+	Ok(chunks)
 }
 
 /// State trie chunker.
 struct StateChunker<'a> {
-	hashes: Vec<H256>,
+	chunks: Vec<(H256, ChunkInfo)>,
 	rlps: Vec<Bytes>,
 	cur_size: usize,
-	snappy_buffer: Vec<u8>,
+	// first and last account key of the current chunk.
+	cur_range: Option<(H256, H256)>,
+	codec: ChunkCodec,
+	compress_buffer: Vec<u8>,
 	writer: &'a Mutex<SnapshotWriter + 'a>,
 	progress: &'a Progress,
 }
@@ -275,9 +384,13 @@ impl<'a> StateChunker<'a> {
 	//
 	// If the buffer is greater than the desired chunk size,
 	// this will write out the data to disk.
-	fn push(&mut self, data: Bytes) -> Result<(), Error> {
+	fn push(&mut self, account_key: H256, data: Bytes) -> Result<(), Error> {
 		self.cur_size += data.len();
 		self.rlps.push(data);
+		self.cur_range = match self.cur_range {
+			Some((first, _)) => Some((first, account_key)),
+			None => Some((account_key, account_key)),
+		};
 		Ok(())
 	}
 
@@ -292,17 +405,19 @@ impl<'a> StateChunker<'a> {
 
 		let raw_data = stream.out();
 
-		let compressed_size = snappy::compress_into(&raw_data, &mut self.snappy_buffer);
-		let compressed = &self.snappy_buffer[..compressed_size];
-		let hash = keccak(&compressed);
+		let compressed_size = compress_chunk(self.codec, &raw_data, &mut self.compress_buffer)?;
+		let hash = keccak(&self.compress_buffer[..compressed_size]);
 
-		self.writer.lock().write_state_chunk(hash, compressed)?;
+		self.writer.lock().write_state_chunk(hash, &self.compress_buffer[..compressed_size])?;
 		trace!(target: "snapshot", "wrote state chunk. size: {}, uncompressed size: {}", compressed_size, raw_data.len());
 
 		self.progress.accounts.fetch_add(num_entries, Ordering::SeqCst);
 		self.progress.size.fetch_add(compressed_size, Ordering::SeqCst);
 
-		self.hashes.push(hash);
+		self.chunks.push((hash, ChunkInfo {
+			uncompressed_size: raw_data.len() as u64,
+			account_range: self.cur_range.take(),
+		}));
 		self.cur_size = 0;
 
 		Ok(())
@@ -319,16 +434,18 @@ impl<'a> StateChunker<'a> {
 /// `part` is a number between 0 and 15, which describe which part of
 /// the tree should be chunked.
 ///
-/// Returns a list of hashes of chunks created, or any error it may
-/// have encountered.
-pub fn chunk_state<'a>(db: &HashDB<KeccakHasher, DBValue>, root: &H256, writer: &Mutex<SnapshotWriter + 'a>, progress: &'a Progress, part: Option<usize>) -> Result<Vec<H256>, Error> {
+/// Returns a list of hashes of chunks created along with their metadata,
+/// or any error it may have encountered.
+pub fn chunk_state<'a>(db: &HashDB<KeccakHasher, DBValue>, root: &H256, writer: &Mutex<SnapshotWriter + 'a>, progress: &'a Progress, part: Option<usize>, codec: ChunkCodec) -> Result<Vec<(H256, ChunkInfo)>, Error> {
 	let account_trie = TrieDB::new(db, &root)?;
 
 	let mut chunker = StateChunker {
-		hashes: Vec::new(),
+		chunks: Vec::new(),
 		rlps: Vec::new(),
 		cur_size: 0,
-		snappy_buffer: vec![0; snappy::max_compressed_len(PREFERRED_CHUNK_SIZE)],
+		cur_range: None,
+		codec: codec,
+		compress_buffer: vec![0; snappy::max_compressed_len(PREFERRED_CHUNK_SIZE)],
 		writer: writer,
 		progress: progress,
 	};
@@ -370,7 +487,7 @@ pub fn chunk_state<'a>(db: &HashDB<KeccakHasher, DBValue>, root: &H256, writer:
 			if i > 0 {
 				chunker.write_chunk()?;
 			}
-			chunker.push(fat_rlp)?;
+			chunker.push(account_key_hash, fat_rlp)?;
 		}
 	}
 
@@ -378,7 +495,62 @@ pub fn chunk_state<'a>(db: &HashDB<KeccakHasher, DBValue>, root: &H256, writer:
 		chunker.write_chunk()?;
 	}
 
-	Ok(chunker.hashes)
+	Ok(chunker.chunks)
+}
+
+/// Walk the account trie in `SNAPSHOT_SUBPARTS` ranges of the account keyspace, chunking them
+/// in parallel on `processing_threads` threads.
+///
+/// Returns the hashes and metadata of all state chunks in keyspace order, so the result
+/// does not depend on the number of threads.
+pub fn chunk_state_parts<W: SnapshotWriter + Send>(
+	db: &HashDB<KeccakHasher, DBValue>,
+	root: &H256,
+	writer: &Mutex<W>,
+	progress: &Progress,
+	processing_threads: usize,
+	codec: ChunkCodec,
+) -> Result<Vec<(H256, ChunkInfo)>, Error> {
+	// The number of threads must be between 1 and SNAPSHOT_SUBPARTS
+	assert!(processing_threads >= 1, "Cannot use less than 1 threads for creating snapshots");
+	let num_threads: usize = cmp::min(processing_threads, SNAPSHOT_SUBPARTS);
+	info!(target: "snapshot", "Using {} threads for Snapshot creation.", num_threads);
+
+	// threads take the next unprocessed part until all of them are done.
+	let next_part = AtomicUsize::new(0);
+	let mut parts = scope(|scope| -> Result<Vec<(usize, Vec<(H256, ChunkInfo)>)>, Error> {
+		let next_part = &next_part;
+		let mut state_guards = Vec::with_capacity(num_threads);
+
+		for thread_idx in 0..num_threads {
+			let state_guard = scope.spawn(move || -> Result<Vec<(usize, Vec<(H256, ChunkInfo)>)>, Error> {
+				let mut parts = Vec::new();
+
+				loop {
+					let part = next_part.fetch_add(1, Ordering::SeqCst);
+					if part >= SNAPSHOT_SUBPARTS {
+						break;
+					}
+
+					debug!(target: "snapshot", "Chunking part {} in thread {}", part, thread_idx);
+					let chunks = chunk_state(db, root, writer, progress, Some(part), codec)?;
+					parts.push((part, chunks));
+				}
+
+				Ok(parts)
+			});
+			state_guards.push(state_guard);
+		}
+
+		let mut parts = Vec::with_capacity(SNAPSHOT_SUBPARTS);
+		for guard in state_guards {
+			parts.extend(guard.join()?);
+		}
+		Ok(parts)
+	})?;
+
+	parts.sort_by_key(|&(part, _)| part);
+	Ok(parts.into_iter().flat_map(|(_, chunks)| chunks).collect())
 }
 
 /// Used to rebuild the state trie piece by piece.
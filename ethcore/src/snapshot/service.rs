@@ -16,14 +16,14 @@
 
 //! Snapshot network service implementation.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io::{self, Read, ErrorKind};
 use std::fs::{self, File};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
-use super::{ManifestData, StateRebuilder, Rebuilder, RestorationStatus, SnapshotService, MAX_CHUNK_SIZE};
+use super::{ManifestData, StateRebuilder, Rebuilder, RestorationStatus, SnapshotService, ChunkCodec, ChunkInfo};
 use super::io::{SnapshotReader, LooseReader, SnapshotWriter, LooseWriter};
 
 use blockchain::{BlockChain, BlockChainDB, BlockChainDBHandler};
@@ -40,7 +40,6 @@ use ethereum_types::H256;
 use parking_lot::{Mutex, RwLock, RwLockReadGuard};
 use bytes::Bytes;
 use journaldb::Algorithm;
-use snappy;
 
 /// Helper for removing directories in case of error.
 struct Guard(bool, PathBuf);
@@ -73,10 +72,12 @@ struct Restoration {
 	manifest: ManifestData,
 	state_chunks_left: HashSet<H256>,
 	block_chunks_left: HashSet<H256>,
+	chunk_infos: HashMap<H256, ChunkInfo>,
+	codec: ChunkCodec,
 	state: StateRebuilder,
 	secondary: Box<Rebuilder>,
 	writer: Option<LooseWriter>,
-	snappy_buffer: Bytes,
+	decompress_buffer: Bytes,
 	final_state_root: H256,
 	guard: Guard,
 	db: Arc<BlockChainDB>,
@@ -96,14 +97,24 @@ impl Restoration {
 	// make a new restoration using the given parameters.
 	fn new(params: RestorationParams) -> Result<Self, Error> {
 		let manifest = params.manifest;
+		::snapshot::verify_chunk_metadata(&manifest)?;
 
 		let state_chunks = manifest.state_hashes.iter().cloned().collect();
 		let block_chunks = manifest.block_hashes.iter().cloned().collect();
 
+		let (chunk_infos, codec) = match manifest.metadata {
+			Some(ref metadata) => {
+				let hashes = manifest.state_hashes.iter().chain(manifest.block_hashes.iter()).cloned();
+				let infos = metadata.state_chunks.iter().chain(metadata.block_chunks.iter()).cloned();
+				(hashes.zip(infos).collect(), metadata.codec)
+			}
+			None => (HashMap::new(), ChunkCodec::Snappy),
+		};
+
 		let raw_db = params.db;
 
 		let chain = BlockChain::new(Default::default(), params.genesis, raw_db.clone());
-		let components = params.engine.snapshot_components()
+		let components = params.engine.snapshot_components(manifest.block_number)
 			.ok_or_else(|| ::snapshot::Error::SnapshotsUnsupported)?;
 
 		let secondary = components.rebuilder(chain, raw_db.clone(), &manifest)?;
@@ -114,10 +125,12 @@ impl Restoration {
 			manifest: manifest,
 			state_chunks_left: state_chunks,
 			block_chunks_left: block_chunks,
+			chunk_infos: chunk_infos,
+			codec: codec,
 			state: StateRebuilder::new(raw_db.key_value().clone(), params.pruning),
 			secondary: secondary,
 			writer: params.writer,
-			snappy_buffer: Vec::new(),
+			decompress_buffer: Vec::new(),
 			final_state_root: root,
 			guard: params.guard,
 			db: raw_db,
@@ -127,14 +140,15 @@ impl Restoration {
 	// feeds a state chunk, aborts early if `flag` becomes false.
 	fn feed_state(&mut self, hash: H256, chunk: &[u8], flag: &AtomicBool) -> Result<(), Error> {
 		if self.state_chunks_left.contains(&hash) {
-			let expected_len = snappy::decompressed_len(chunk)?;
-			if expected_len > MAX_CHUNK_SIZE {
-				trace!(target: "snapshot", "Discarding large chunk: {} vs {}", expected_len, MAX_CHUNK_SIZE);
-				return Err(::snapshot::Error::ChunkTooLarge.into());
+			let info = self.chunk_infos.get(&hash).cloned();
+			let expected_size = info.as_ref().map(|info| info.uncompressed_size as usize);
+			let raw = ::snapshot::decompress_chunk(self.codec, chunk, expected_size, &mut self.decompress_buffer)?;
+
+			if let Some((first, last)) = info.and_then(|info| info.account_range) {
+				::snapshot::verify_account_range(raw, &first, &last)?;
 			}
-			let len = snappy::decompress_into(chunk, &mut self.snappy_buffer)?;
 
-			self.state.feed(&self.snappy_buffer[..len], flag)?;
+			self.state.feed(raw, flag)?;
 
 			if let Some(ref mut writer) = self.writer.as_mut() {
 				writer.write_state_chunk(hash, chunk)?;
@@ -149,14 +163,10 @@ impl Restoration {
 	// feeds a block chunk
 	fn feed_blocks(&mut self, hash: H256, chunk: &[u8], engine: &EthEngine, flag: &AtomicBool) -> Result<(), Error> {
 		if self.block_chunks_left.contains(&hash) {
-			let expected_len = snappy::decompressed_len(chunk)?;
-			if expected_len > MAX_CHUNK_SIZE {
-				trace!(target: "snapshot", "Discarding large chunk: {} vs {}", expected_len, MAX_CHUNK_SIZE);
-				return Err(::snapshot::Error::ChunkTooLarge.into());
-			}
-			let len = snappy::decompress_into(chunk, &mut self.snappy_buffer)?;
+			let expected_size = self.chunk_infos.get(&hash).map(|info| info.uncompressed_size as usize);
+			let raw = ::snapshot::decompress_chunk(self.codec, chunk, expected_size, &mut self.decompress_buffer)?;
 
-			self.secondary.feed(&self.snappy_buffer[..len], engine, flag)?;
+			self.secondary.feed(raw, engine, flag)?;
 			if let Some(ref mut writer) = self.writer.as_mut() {
 				 writer.write_block_chunk(hash, chunk)?;
 			}
@@ -665,8 +675,9 @@ impl SnapshotService for Service {
 		self.reader.read().as_ref().map(|r| r.manifest().clone())
 	}
 
+	// snapshots are taken close to the head of the chain.
 	fn supported_versions(&self) -> Option<(u64, u64)> {
-		self.engine.snapshot_components()
+		self.engine.snapshot_components(u64::max_value())
 			.map(|c| (c.min_supported_version(), c.current_version()))
 	}
 
@@ -801,6 +812,7 @@ mod tests {
 			state_root: Default::default(),
 			block_number: 0,
 			block_hash: Default::default(),
+			metadata: None,
 		};
 
 		service.begin_restore(manifest);
@@ -831,6 +843,7 @@ mod tests {
 				state_root: H256::default(),
 				block_number: 100000,
 				block_hash: H256::default(),
+				metadata: None,
 			},
 			pruning: Algorithm::Archive,
 			db: restoration_db_handler(db_config).open(&tempdir.path().to_owned()).unwrap(),
@@ -169,8 +169,8 @@ pub fn restore(
 	use snappy;
 
 	let flag = AtomicBool::new(true);
-	let components = engine.snapshot_components().unwrap();
 	let manifest = reader.manifest();
+	let components = engine.snapshot_components(manifest.block_number).unwrap();
 
 	let mut state = StateRebuilder::new(db.key_value().clone(), journaldb::Algorithm::Archive);
 	let mut secondary = {
@@ -22,7 +22,7 @@ use error::{Error, ErrorKind};
 
 use blockchain::generator::{BlockGenerator, BlockBuilder};
 use blockchain::{BlockChain, ExtrasInsert};
-use snapshot::{chunk_secondary, Error as SnapshotError, Progress, SnapshotComponents};
+use snapshot::{chunk_secondary, ChunkCodec, Error as SnapshotError, Progress, SnapshotComponents};
 use snapshot::io::{PackedReader, PackedWriter, SnapshotReader, SnapshotWriter};
 
 use parking_lot::Mutex;
@@ -66,8 +66,9 @@ fn chunk_and_restore(amount: u64) {
 		&bc,
 		best_hash,
 		&writer,
-		&Progress::default()
-	).unwrap();
+		&Progress::default(),
+		ChunkCodec::Snappy,
+	).unwrap().into_iter().map(|(hash, _)| hash).collect();
 
 	let manifest = ::snapshot::ManifestData {
 		version: 2,
@@ -76,6 +77,7 @@ fn chunk_and_restore(amount: u64) {
 		state_root: ::hash::KECCAK_NULL_RLP,
 		block_number: amount,
 		block_hash: best_hash,
+		metadata: None,
 	};
 
 	writer.into_inner().finish(manifest.clone()).unwrap();
@@ -138,6 +140,7 @@ fn checks_flag() {
 		state_root: ::hash::KECCAK_NULL_RLP,
 		block_number: 102,
 		block_hash: H256::default(),
+		metadata: None,
 	};
 
 	let mut rebuilder = SNAPSHOT_MODE.rebuilder(chain, db.clone(), &manifest).unwrap();
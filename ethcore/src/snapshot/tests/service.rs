@@ -133,6 +133,7 @@ fn guards_delete_folders() {
 		block_number: 0,
 		block_hash: Default::default(),
 		state_root: Default::default(),
+		metadata: None,
 	};
 
 	service.init_restore(manifest.clone(), true).unwrap();
@@ -22,8 +22,9 @@ use hash::{KECCAK_NULL_RLP, keccak};
 
 use basic_account::BasicAccount;
 use snapshot::account;
-use snapshot::{chunk_state, Error as SnapshotError, Progress, StateRebuilder, SNAPSHOT_SUBPARTS};
-use snapshot::io::{PackedReader, PackedWriter, SnapshotReader, SnapshotWriter};
+use snapshot::{chunk_state, chunk_state_parts, Error as SnapshotError, Progress, StateRebuilder, SNAPSHOT_SUBPARTS};
+use snapshot::{ChunkCodec, ChunkInfo, ChunkMetadata, ManifestData};
+use snapshot::io::{LooseWriter, PackedReader, PackedWriter, SnapshotReader, SnapshotWriter};
 use super::helpers::{compare_dbs, StateProducer};
 
 use error::{Error, ErrorKind};
@@ -55,8 +56,8 @@ fn snap_and_restore() {
 
 	let mut state_hashes = Vec::new();
 	for part in 0..SNAPSHOT_SUBPARTS {
-		let mut hashes = chunk_state(&old_db, &state_root, &writer, &Progress::default(), Some(part)).unwrap();
-		state_hashes.append(&mut hashes);
+		let chunks = chunk_state(&old_db, &state_root, &writer, &Progress::default(), Some(part), ChunkCodec::Snappy).unwrap();
+		state_hashes.extend(chunks.into_iter().map(|(hash, _)| hash));
 	}
 
 	writer.into_inner().finish(::snapshot::ManifestData {
@@ -66,6 +67,7 @@ fn snap_and_restore() {
 		state_root: state_root,
 		block_number: 1000,
 		block_hash: H256::default(),
+		metadata: None,
 	}).unwrap();
 
 	let db_path = tempdir.path().join("db");
@@ -95,6 +97,120 @@ fn snap_and_restore() {
 	compare_dbs(&old_db, new_db.as_hashdb());
 }
 
+#[test]
+fn parallel_chunking_is_deterministic() {
+	use std::fs;
+
+	let mut producer = StateProducer::new();
+	let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+	let mut old_db = MemoryDB::new();
+
+	for _ in 0..150 {
+		producer.tick(&mut rng, &mut old_db);
+	}
+
+	let tempdir = TempDir::new("").unwrap();
+	let state_root = producer.state_root();
+	let chunk_with_threads = |threads: usize| {
+		let path = tempdir.path().join(format!("threads-{}", threads));
+		let writer = Mutex::new(LooseWriter::new(path.clone()).unwrap());
+		let chunks = chunk_state_parts(&old_db, &state_root, &writer, &Progress::default(), threads, ChunkCodec::Snappy).unwrap();
+		let contents: Vec<_> = chunks.iter().map(|&(hash, _)| fs::read(path.join(format!("{:x}", hash))).unwrap()).collect();
+		(chunks, contents)
+	};
+
+	let (single_chunks, single_contents) = chunk_with_threads(1);
+	assert!(single_chunks.len() > 1);
+
+	for threads in &[2, 3, SNAPSHOT_SUBPARTS] {
+		let (chunks, contents) = chunk_with_threads(*threads);
+		assert_eq!(chunks, single_chunks);
+		assert_eq!(contents, single_contents);
+	}
+}
+
+#[test]
+fn zstd_chunks_match_metadata() {
+	use std::fs;
+	use snapshot::{decompress_chunk, verify_account_range, verify_chunk_metadata};
+
+	let mut producer = StateProducer::new();
+	let mut rng = XorShiftRng::from_seed([9, 10, 11, 12]);
+	let mut old_db = MemoryDB::new();
+	let db_cfg = DatabaseConfig::with_columns(::db::NUM_COLUMNS);
+
+	for _ in 0..150 {
+		producer.tick(&mut rng, &mut old_db);
+	}
+
+	let tempdir = TempDir::new("").unwrap();
+	let chunk_dir = tempdir.path().join("chunks");
+	let state_root = producer.state_root();
+	let writer = Mutex::new(LooseWriter::new(chunk_dir.clone()).unwrap());
+
+	let chunks = chunk_state_parts(&old_db, &state_root, &writer, &Progress::default(), 4, ChunkCodec::Zstd).unwrap();
+	let (state_hashes, state_infos): (Vec<_>, Vec<_>) = chunks.into_iter().unzip();
+	assert!(state_hashes.len() > 1);
+
+	let mut manifest = ManifestData {
+		version: 2,
+		state_hashes: state_hashes,
+		block_hashes: Vec::new(),
+		state_root: state_root,
+		block_number: 1000,
+		block_hash: H256::default(),
+		metadata: Some(ChunkMetadata {
+			codec: ChunkCodec::Zstd,
+			state_chunks: state_infos,
+			block_chunks: Vec::new(),
+		}),
+	};
+	verify_chunk_metadata(&manifest).unwrap();
+
+	let db_path = tempdir.path().join("db");
+	let new_db = Arc::new(Database::open(&db_cfg, &db_path.to_string_lossy()).unwrap());
+	{
+		let mut rebuilder = StateRebuilder::new(new_db.clone(), Algorithm::OverlayRecent);
+		let flag = AtomicBool::new(true);
+		let metadata = manifest.metadata.clone().unwrap();
+		let mut buffer = Vec::new();
+
+		for (hash, info) in manifest.state_hashes.iter().zip(&metadata.state_chunks) {
+			let compressed = fs::read(chunk_dir.join(format!("{:x}", hash))).unwrap();
+			let size = info.uncompressed_size as usize;
+
+			// a wrong announced size is caught.
+			assert!(decompress_chunk(ChunkCodec::Zstd, &compressed, Some(size - 1), &mut buffer).is_err());
+
+			let raw = decompress_chunk(ChunkCodec::Zstd, &compressed, Some(size), &mut buffer).unwrap();
+			let (first, last) = info.account_range.unwrap();
+			verify_account_range(raw, &first, &last).unwrap();
+			assert!(verify_account_range(raw, &last, &first).is_err() || first == last);
+
+			rebuilder.feed(raw, &flag).unwrap();
+		}
+
+		assert_eq!(rebuilder.state_root(), state_root);
+	}
+
+	// chunks announced out of keyspace order are rejected up front.
+	let mut unordered = manifest.clone();
+	unordered.metadata.as_mut().unwrap().state_chunks.swap(0, 1);
+	match verify_chunk_metadata(&unordered) {
+		Err(SnapshotError::InvalidChunkMetadata(_)) => {},
+		other => panic!("unexpected result: {:?}", other),
+	}
+
+	// so are manifests listing a chunk twice.
+	let duplicate = manifest.state_hashes[0];
+	manifest.block_hashes.push(duplicate);
+	manifest.metadata.as_mut().unwrap().block_chunks.push(ChunkInfo { uncompressed_size: 0, account_range: None });
+	match verify_chunk_metadata(&manifest) {
+		Err(SnapshotError::InvalidChunkMetadata(_)) => {},
+		other => panic!("unexpected result: {:?}", other),
+	}
+}
+
 #[test]
 fn get_code_from_prev_chunk() {
 	use std::collections::HashSet;
@@ -168,7 +284,8 @@ fn checks_flag() {
 	let state_root = producer.state_root();
 	let writer = Mutex::new(PackedWriter::new(&snap_file).unwrap());
 
-	let state_hashes = chunk_state(&old_db, &state_root, &writer, &Progress::default(), None).unwrap();
+	let state_hashes = chunk_state(&old_db, &state_root, &writer, &Progress::default(), None, ChunkCodec::Snappy).unwrap()
+		.into_iter().map(|(hash, _)| hash).collect();
 
 	writer.into_inner().finish(::snapshot::ManifestData {
 		version: 2,
@@ -177,6 +294,7 @@ fn checks_flag() {
 		state_root: state_root,
 		block_number: 0,
 		block_hash: H256::default(),
+		metadata: None,
 	}).unwrap();
 
 	let tempdir = TempDir::new("").unwrap();
@@ -35,7 +35,7 @@ use builtin::Builtin;
 use encoded;
 use engines::{
 	EthEngine, NullEngine, InstantSeal, InstantSealParams, BasicAuthority,
-	AuthorityRound, Tendermint, DEFAULT_BLOCKHASH_CONTRACT
+	AuthorityRound, Tendermint, Clique, MultiEngine, DEFAULT_BLOCKHASH_CONTRACT
 };
 use error::Error;
 use executive::Executive;
@@ -64,8 +64,7 @@ fn fmt_err<F: ::std::fmt::Display>(f: F) -> String {
 ///
 /// we define a "bugfix" hard fork as any hard fork which
 /// you would put on-by-default in a new chain.
-#[derive(Debug, PartialEq, Default)]
-#[cfg_attr(test, derive(Clone))]
+#[derive(Debug, PartialEq, Default, Clone)]
 pub struct CommonParams {
 	/// Account start nonce.
 	pub account_start_nonce: U256,
@@ -480,8 +479,12 @@ impl From<SpecHardcodedSync> for ethjson::spec::HardcodedSync {
 	}
 }
 
+fn convert_builtins(builtins: &BTreeMap<ethjson::hash::Address, ethjson::spec::Builtin>) -> BTreeMap<Address, Builtin> {
+	builtins.iter().map(|(address, builtin)| (address.clone().into(), From::from(builtin.clone()))).collect()
+}
+
 fn load_machine_from(s: ethjson::spec::Spec) -> EthereumMachine {
-	let builtins = s.accounts.builtins().into_iter().map(|p| (p.0.into(), From::from(p.1))).collect();
+	let builtins = convert_builtins(&s.accounts.builtins());
 	let params = CommonParams::from(s.params);
 
 	Spec::machine(&s.engine, params, builtins)
@@ -489,11 +492,7 @@ fn load_machine_from(s: ethjson::spec::Spec) -> EthereumMachine {
 
 /// Load from JSON object.
 fn load_from(spec_params: SpecParams, s: ethjson::spec::Spec) -> Result<Spec, Error> {
-	let builtins = s.accounts
-		.builtins()
-		.into_iter()
-		.map(|p| (p.0.into(), From::from(p.1)))
-		.collect();
+	let builtins = s.accounts.builtins();
 	let g = Genesis::from(s.genesis);
 	let GenericSeal(seal_rlp) = g.seal.into();
 	let params = CommonParams::from(s.params);
@@ -517,7 +516,7 @@ fn load_from(spec_params: SpecParams, s: ethjson::spec::Spec) -> Result<Spec, Er
 
 	let mut s = Spec {
 		name: s.name.clone().into(),
-		engine: Spec::engine(spec_params, s.engine, params, builtins),
+		engine: Spec::engine(spec_params, s.engine, params, &builtins),
 		data_dir: s.data_dir.unwrap_or(s.name).into(),
 		nodes: s.nodes.unwrap_or_else(Vec::new),
 		parent_hash: g.parent_hash,
@@ -579,10 +578,22 @@ impl Spec {
 		params: CommonParams,
 		builtins: BTreeMap<Address, Builtin>,
 	) -> EthereumMachine {
-		if let ethjson::spec::Engine::Ethash(ref ethash) = *engine_spec {
-			EthereumMachine::with_ethash_extensions(params, builtins, ethash.params.clone().into())
-		} else {
-			EthereumMachine::regular(params, builtins)
+		match *engine_spec {
+			ethjson::spec::Engine::Ethash(ref ethash) =>
+				EthereumMachine::with_ethash_extensions(params, builtins, ethash.params.clone().into()),
+			// ethash rules apply while an ethash engine is active.
+			ethjson::spec::Engine::Multi(ref engines) => {
+				let mut machine = EthereumMachine::regular(params, builtins);
+				for (block, engine_spec) in engines {
+					let extensions = match *engine_spec {
+						ethjson::spec::Engine::Ethash(ref ethash) => Some(ethash.params.clone().into()),
+						_ => None,
+					};
+					machine.set_ethash_extensions_from((*block).into(), extensions);
+				}
+				machine
+			},
+			_ => EthereumMachine::regular(params, builtins),
 		}
 	}
 
@@ -592,20 +603,30 @@ impl Spec {
 		spec_params: SpecParams,
 		engine_spec: ethjson::spec::Engine,
 		params: CommonParams,
-		builtins: BTreeMap<Address, Builtin>,
+		builtins: &BTreeMap<ethjson::hash::Address, ethjson::spec::Builtin>,
 	) -> Arc<EthEngine> {
-		let machine = Self::machine(&engine_spec, params, builtins);
+		let machine = Self::machine(&engine_spec, params.clone(), convert_builtins(builtins));
 
 		match engine_spec {
 			ethjson::spec::Engine::Null(null) => Arc::new(NullEngine::new(null.params.into(), machine)),
 			ethjson::spec::Engine::Ethash(ethash) => Arc::new(::ethereum::Ethash::new(spec_params.cache_dir, ethash.params.into(), machine, spec_params.optimization_setting)),
-			ethjson::spec::Engine::InstantSeal(Some(instant_seal)) => Arc::new(InstantSeal::new(instant_seal.params.into(), machine)),
-			ethjson::spec::Engine::InstantSeal(None) => Arc::new(InstantSeal::new(InstantSealParams::default(), machine)),
+			ethjson::spec::Engine::InstantSeal(Some(instant_seal)) => InstantSeal::new(instant_seal.params.into(), machine)
+				.expect("Failed to start the InstantSeal consensus engine."),
+			ethjson::spec::Engine::InstantSeal(None) => InstantSeal::new(InstantSealParams::default(), machine)
+				.expect("Failed to start the InstantSeal consensus engine."),
 			ethjson::spec::Engine::BasicAuthority(basic_authority) => Arc::new(BasicAuthority::new(basic_authority.params.into(), machine)),
 			ethjson::spec::Engine::AuthorityRound(authority_round) => AuthorityRound::new(authority_round.params.into(), machine)
 				.expect("Failed to start AuthorityRound consensus engine."),
 			ethjson::spec::Engine::Tendermint(tendermint) => Tendermint::new(tendermint.params.into(), machine)
 				.expect("Failed to start the Tendermint consensus engine."),
+			ethjson::spec::Engine::Clique(clique) => Clique::new(clique.params.into(), machine)
+				.expect("Failed to start the Clique consensus engine."),
+			ethjson::spec::Engine::Multi(engines) => Arc::new(MultiEngine::new(
+				engines.into_iter()
+					.map(|(block, engine_spec)| (block.into(), Self::engine(spec_params, engine_spec, params.clone(), builtins)))
+					.collect(),
+				machine,
+			)),
 		}
 	}
 
@@ -936,6 +957,14 @@ impl Spec {
 		load_bundled!("authority_round")
 	}
 
+	/// Create a new Spec with AuthorityRound consensus which does internal sealing (not
+	/// requiring work) and finalizes blocks, as validator set changes aren't immediate.
+	/// Accounts with secrets keccak("0") and keccak("1") are the validators.
+	#[cfg(any(test, feature = "test-helpers"))]
+	pub fn new_test_round_finality() -> Self {
+		load_bundled!("authority_round_finality")
+	}
+
 	/// Create a new Spec with AuthorityRound consensus which does internal sealing (not
 	/// requiring work) with empty step messages enabled.
 	/// Accounts with secrets keccak("0") and keccak("1") are the validators.
@@ -952,6 +981,20 @@ impl Spec {
 		load_bundled!("authority_round_block_reward_contract")
 	}
 
+	/// Create a new Spec with AuthorityRound consensus using a block gas limit contract which
+	/// always demands a gas limit of 0x1000000.
+	#[cfg(any(test, feature = "test-helpers"))]
+	pub fn new_test_round_block_gas_limit_contract() -> Self {
+		load_bundled!("authority_round_block_gas_limit_contract")
+	}
+
+	/// Create a new Spec with AuthorityRound consensus using a randomness contract which is
+	/// always in the commit phase and reports no commitments, so validators keep committing.
+	#[cfg(any(test, feature = "test-helpers"))]
+	pub fn new_test_round_randomness_contract() -> Self {
+		load_bundled!("authority_round_randomness_contract")
+	}
+
 	/// Create a new Spec with Tendermint consensus which does internal sealing (not requiring
 	/// work).
 	/// Account keccak("0") and keccak("1") are a authorities.
@@ -960,6 +1003,21 @@ impl Spec {
 		load_bundled!("tendermint")
 	}
 
+	/// Create a new Spec with Clique consensus which does internal sealing (not requiring
+	/// work).
+	/// Accounts keccak("0") and keccak("1") are the signers.
+	#[cfg(any(test, feature = "test-helpers"))]
+	pub fn new_test_clique() -> Self {
+		load_bundled!("clique")
+	}
+
+	/// Create a new Spec which switches from Ethash to AuthorityRound consensus at block 2.
+	/// Accounts with secrets keccak("0") and keccak("1") are the AuthorityRound validators.
+	#[cfg(any(test, feature = "test-helpers"))]
+	pub fn new_test_engine_multi() -> Self {
+		load_bundled!("engine_multi")
+	}
+
 	/// TestList.sol used in both specs: https://github.com/paritytech/contracts/pull/30/files
 	/// Accounts with secrets keccak("0") and keccak("1") are initially the validators.
 	/// Create a new Spec with BasicAuthority which uses a contract at address 5 to determine
@@ -1039,4 +1097,53 @@ mod tests {
 		assert_eq!(state.storage_at(&address, &H256::zero()).unwrap(), expected);
 		assert_eq!(state.balance(&address).unwrap(), 1.into());
 	}
+
+	#[test]
+	fn applies_ethash_rules_of_later_multi_engine_segments() {
+		let spec = r#"{
+			"name": "TestEngineMultiEthashSecond",
+			"engine": {
+				"multi": {
+					"0": { "instantSeal": { "params": {} } },
+					"2": {
+						"Ethash": {
+							"params": {
+								"minimumDifficulty": "0x01",
+								"difficultyBoundDivisor": "0x0800",
+								"durationLimit": "0x0d",
+								"blockReward": "0x4563918244F40000",
+								"homesteadTransition": "0x5"
+							}
+						}
+					},
+					"10": { "instantSeal": { "params": {} } }
+				}
+			},
+			"params": {
+				"gasLimitBoundDivisor": "0x0400",
+				"accountStartNonce": "0x0",
+				"maximumExtraDataSize": "0x20",
+				"minGasLimit": "0x1388",
+				"networkID" : "0x69"
+			},
+			"genesis": {
+				"seal": { "generic": "0x0" },
+				"difficulty": "0x01",
+				"author": "0x0000000000000000000000000000000000000000",
+				"timestamp": "0x00",
+				"parentHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+				"extraData": "0x",
+				"gasLimit": "0x222222"
+			},
+			"accounts": {}
+		}"#;
+		let tempdir = TempDir::new("").unwrap();
+		let spec = Spec::load(&tempdir.path(), spec.as_bytes()).unwrap();
+		let machine = spec.engine.machine();
+
+		assert!(machine.ethash_extensions_at(1).is_none());
+		assert_eq!(machine.ethash_extensions_at(2).map(|e| e.homestead_transition), Some(5));
+		assert!(machine.ethash_extensions_at(9).is_some());
+		assert!(machine.ethash_extensions_at(10).is_none());
+	}
 }
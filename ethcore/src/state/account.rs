@@ -456,6 +456,12 @@ impl Account {
 		self.storage_root
 	}
 
+	/// Check if the storage was cleared since the last commit, i.e. the account was
+	/// re-created or had its storage reset.
+	pub fn is_storage_reset(&self) -> bool {
+		self.original_storage_cache.is_some()
+	}
+
 	/// Return the storage overlay.
 	pub fn storage_changes(&self) -> &HashMap<H256, H256> { &self.storage_changes }
 
@@ -24,6 +24,7 @@
 use std::collections::{HashSet, HashMap};
 use std::sync::Arc;
 
+use flat_state::FlatDiff;
 use state::Account;
 use parking_lot::Mutex;
 use ethereum_types::{Address, H256};
@@ -68,6 +69,17 @@ pub trait Backend: Send {
 	/// Check whether an account is known to be empty. Returns true if known to be
 	/// empty, false otherwise.
 	fn is_known_null(&self, address: &Address) -> bool;
+
+	/// Get an account from the flat state, provided the state root is `root`.
+	/// Returns `None` if there's no flat state or it doesn't know the given state.
+	fn get_flat_account(&self, root: &H256, addr: &Address) -> Option<Option<Account>>;
+
+	/// Get a storage value of the account with the given storage root from the flat state,
+	/// provided the state root is `root`. Returns `None` if the flat state can't tell.
+	fn get_flat_storage(&self, root: &H256, addr: &Address, storage_root: &H256, key: &H256) -> Option<H256>;
+
+	/// Note changes committed to the trie, resulting in the state root `root`.
+	fn note_flat_changes(&mut self, root: &H256, changes: FlatDiff);
 }
 
 /// A raw backend used to check proofs of execution.
@@ -128,6 +140,9 @@ impl Backend for ProofCheck {
 	fn get_cached_code(&self, _hash: &H256) -> Option<Arc<Vec<u8>>> { None }
 	fn note_non_null_account(&self, _address: &Address) {}
 	fn is_known_null(&self, _address: &Address) -> bool { false }
+	fn get_flat_account(&self, _root: &H256, _addr: &Address) -> Option<Option<Account>> { None }
+	fn get_flat_storage(&self, _root: &H256, _addr: &Address, _storage_root: &H256, _key: &H256) -> Option<H256> { None }
+	fn note_flat_changes(&mut self, _root: &H256, _changes: FlatDiff) {}
 }
 
 /// Proving state backend.
@@ -203,6 +218,9 @@ impl<H: AsHashDB<KeccakHasher, DBValue> + Send + Sync> Backend for Proving<H> {
 	fn get_cached_code(&self, _: &H256) -> Option<Arc<Vec<u8>>> { None }
 	fn note_non_null_account(&self, _: &Address) { }
 	fn is_known_null(&self, _: &Address) -> bool { false }
+	fn get_flat_account(&self, _: &H256, _: &Address) -> Option<Option<Account>> { None }
+	fn get_flat_storage(&self, _: &H256, _: &Address, _: &H256, _: &H256) -> Option<H256> { None }
+	fn note_flat_changes(&mut self, _: &H256, _: FlatDiff) { }
 }
 
 impl<H: AsHashDB<KeccakHasher, DBValue>> Proving<H> {
@@ -261,4 +279,7 @@ impl<H: AsHashDB<KeccakHasher, DBValue> + Send + Sync> Backend for Basic<H> {
 	fn get_cached_code(&self, _: &H256) -> Option<Arc<Vec<u8>>> { None }
 	fn note_non_null_account(&self, _: &Address) { }
 	fn is_known_null(&self, _: &Address) -> bool { false }
+	fn get_flat_account(&self, _: &H256, _: &Address) -> Option<Option<Account>> { None }
+	fn get_flat_storage(&self, _: &H256, _: &Address, _: &H256, _: &H256) -> Option<H256> { None }
+	fn note_flat_changes(&mut self, _: &H256, _: FlatDiff) { }
 }
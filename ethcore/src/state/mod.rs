@@ -24,7 +24,7 @@ use std::collections::hash_map::Entry;
 use std::collections::{HashMap, BTreeMap, BTreeSet, HashSet};
 use std::fmt;
 use std::sync::Arc;
-use hash::{KECCAK_NULL_RLP, KECCAK_EMPTY};
+use hash::{KECCAK_NULL_RLP, KECCAK_EMPTY, keccak};
 
 use receipt::{Receipt, TransactionOutcome};
 use machine::EthereumMachine as Machine;
@@ -32,6 +32,7 @@ use vm::EnvInfo;
 use error::Error;
 use executive::{Executive, TransactOptions};
 use factory::Factories;
+use flat_state::FlatDiff;
 use trace::{self, FlatTrace, VMTrace};
 use pod_account::*;
 use pod_state::{self, PodState};
@@ -677,9 +678,7 @@ impl<B: Backend> State<B> {
 		if self.db.is_known_null(address) { return Ok(H256::zero()) }
 
 		// account is not found in the global cache, get from the DB and insert into local
-		let db = self.factories.trie.readonly(self.db.as_hashdb(), &self.root).expect(SEC_TRIE_DB_UNWRAP_STR);
-		let from_rlp = |b: &[u8]| Account::from_rlp(b).expect("decoding db value failed");
-		let maybe_acc = db.get_with(address, from_rlp)?;
+		let maybe_acc = self.load_account(address)?;
 		let r = maybe_acc.as_ref().map_or(Ok(H256::new()), |a| {
 			let account_db = self.factories.accountdb.readonly(self.db.as_hashdb(), a.address_hash(address));
 			f_at(a, account_db.as_hashdb(), key)
@@ -694,7 +693,14 @@ impl<B: Backend> State<B> {
 			address,
 			key,
 			|account, key| { account.cached_storage_at(key) },
-			|account, db, key| { account.storage_at(db, key) },
+			|account, db, key| {
+				let flat = account.cached_storage_at(key)
+					.or_else(|| self.db.get_flat_storage(&self.root, address, &account.base_storage_root(), key));
+				match flat {
+					Some(value) => Ok(value),
+					None => account.storage_at(db, key),
+				}
+			},
 		)
 	}
 
@@ -704,7 +710,14 @@ impl<B: Backend> State<B> {
 			address,
 			key,
 			|account, key| { account.cached_original_storage_at(key) },
-			|account, db, key| { account.original_storage_at(db, key) },
+			|account, db, key| {
+				let flat = account.cached_original_storage_at(key)
+					.or_else(|| self.db.get_flat_storage(&self.root, address, &account.original_storage_root(), key));
+				match flat {
+					Some(value) => Ok(value),
+					None => account.original_storage_at(db, key),
+				}
+			},
 		)
 	}
 
@@ -871,9 +884,16 @@ impl<B: Backend> State<B> {
 		assert!(self.checkpoints.borrow().is_empty());
 		// first, commit the sub trees.
 		let mut accounts = self.cache.borrow_mut();
+		let mut flat_changes = FlatDiff::default();
 		for (address, ref mut a) in accounts.iter_mut().filter(|&(_, ref a)| a.is_dirty()) {
 			if let Some(ref mut account) = a.account {
 				let addr_hash = account.address_hash(address);
+				if account.is_storage_reset() {
+					flat_changes.wipe_storage(addr_hash);
+				}
+				for (key, value) in account.storage_changes() {
+					flat_changes.set_storage(addr_hash, keccak(key), *value);
+				}
 				{
 					let mut account_db = self.factories.accountdb.create(self.db.as_hashdb_mut(), addr_hash);
 					account.commit_storage(&self.factories.trie, account_db.as_hashdb_mut())?;
@@ -891,15 +911,19 @@ impl<B: Backend> State<B> {
 				a.state = AccountState::Committed;
 				match a.account {
 					Some(ref mut account) => {
-						trie.insert(address, &account.rlp())?;
+						let rlp = account.rlp();
+						trie.insert(address, &rlp)?;
+						flat_changes.set_account(account.address_hash(address), Some(rlp));
 					},
 					None => {
 						trie.remove(address)?;
+						flat_changes.set_account(keccak(address), None);
 					},
 				};
 			}
 		}
 
+		self.db.note_flat_changes(&self.root, flat_changes);
 		Ok(())
 	}
 
@@ -1091,9 +1115,7 @@ impl<B: Backend> State<B> {
 				if check_null && self.db.is_known_null(a) { return Ok(f(None)); }
 
 				// not found in the global cache, get from the DB and insert into local
-				let db = self.factories.trie.readonly(self.db.as_hashdb(), &self.root)?;
-				let from_rlp = |b: &[u8]| Account::from_rlp(b).expect("decoding db value failed");
-				let mut maybe_acc = db.get_with(a, from_rlp)?;
+				let mut maybe_acc = self.load_account(a)?;
 				if let Some(ref mut account) = maybe_acc.as_mut() {
 					let accountdb = self.factories.accountdb.readonly(self.db.as_hashdb(), account.address_hash(a));
 					if !Self::update_account_cache(require, account, &self.db, accountdb.as_hashdb()) {
@@ -1107,6 +1129,16 @@ impl<B: Backend> State<B> {
 		}
 	}
 
+	/// Load account `a` from the flat state if it knows the current state, from the trie otherwise.
+	fn load_account(&self, a: &Address) -> TrieResult<Option<Account>> {
+		if let Some(account) = self.db.get_flat_account(&self.root, a) {
+			return Ok(account);
+		}
+		let db = self.factories.trie.readonly(self.db.as_hashdb(), &self.root).expect(SEC_TRIE_DB_UNWRAP_STR);
+		let from_rlp = |b: &[u8]| Account::from_rlp(b).expect("decoding db value failed");
+		db.get_with(a, from_rlp)
+	}
+
 	/// Pull account `a` in our cache from the trie DB. `require_code` requires that the code be cached, too.
 	fn require<'a>(&'a self, a: &Address, require_code: bool) -> TrieResult<RefMut<'a, Account>> {
 		self.require_or_from(a, require_code, || Account::new_basic(0u8.into(), self.account_start_nonce), |_| {})
@@ -1123,9 +1155,7 @@ impl<B: Backend> State<B> {
 				Some(acc) => self.insert_cache(a, AccountEntry::new_clean_cached(acc)),
 				None => {
 					let maybe_acc = if !self.db.is_known_null(a) {
-						let db = self.factories.trie.readonly(self.db.as_hashdb(), &self.root)?;
-						let from_rlp = |b:&[u8]| { Account::from_rlp(b).expect("decoding db value failed") };
-						AccountEntry::new_clean(db.get_with(a, from_rlp)?)
+						AccountEntry::new_clean(self.load_account(a)?)
 					} else {
 						AccountEntry::new_clean(None)
 					};
@@ -16,16 +16,18 @@
 
 //! State database abstraction. For more info, see the doc for `StateDB`
 
-use std::collections::{VecDeque, HashSet};
+use std::collections::{VecDeque, HashSet, HashMap};
 use std::io;
+use std::mem;
 use std::sync::Arc;
 
 use bloom_journal::{Bloom, BloomJournal};
 use byteorder::{LittleEndian, ByteOrder};
 use db::COL_ACCOUNT_BLOOM;
 use ethereum_types::{H256, Address};
+use flat_state::{FlatDiff, FlatState};
 use hash::keccak;
-use hashdb::HashDB;
+use hashdb::{HashDB, AsHashDB};
 use keccak_hasher::KeccakHasher;
 use header::BlockNumber;
 use journaldb::JournalDB;
@@ -75,6 +77,146 @@ struct CacheQueueItem {
 	modified: bool,
 }
 
+/// Shared cache of trie nodes read from the journal database.
+///
+/// Nodes are keyed by their hash, so an entry never goes stale; it is safe to
+/// share between canonical and non-canonical state instances.
+struct NodeCache {
+	nodes: LruCache<H256, DBValue>,
+	/// Approximate memory used by `nodes`, in bytes.
+	size: usize,
+	/// Size the cache is trimmed to on garbage collection.
+	pref_size: usize,
+	/// Size the cache never grows past.
+	max_size: usize,
+}
+
+impl NodeCache {
+	fn new(pref_size: usize, max_size: usize) -> Self {
+		NodeCache {
+			nodes: LruCache::new(usize::max_value()),
+			size: 0,
+			pref_size: pref_size,
+			max_size: max_size,
+		}
+	}
+
+	fn node_size(node: &DBValue) -> usize {
+		mem::size_of::<H256>() + mem::size_of::<DBValue>() + node.len()
+	}
+
+	fn get(&mut self, key: &H256) -> Option<DBValue> {
+		self.nodes.get_mut(key).map(|node| node.clone())
+	}
+
+	fn insert(&mut self, key: H256, node: DBValue) {
+		if self.nodes.contains_key(&key) {
+			return;
+		}
+
+		self.size += Self::node_size(&node);
+		self.nodes.insert(key, node);
+		let max_size = self.max_size;
+		self.shrink_to(max_size);
+	}
+
+	fn collect_garbage(&mut self) {
+		let pref_size = self.pref_size;
+		self.shrink_to(pref_size);
+	}
+
+	// evict least recently used nodes until the cache fits into `target` bytes.
+	fn shrink_to(&mut self, target: usize) {
+		while self.size > target {
+			match self.nodes.remove_lru() {
+				Some((_, node)) => self.size -= Self::node_size(&node),
+				None => break,
+			}
+		}
+	}
+}
+
+/// Journal database whose node reads go through the shared node cache, if any.
+/// Writes and existence checks always hit the journal database, so a pruned node
+/// is never reported as present. Only journalled nodes enter the cache: nodes still
+/// in this instance's overlay are served from the overlay alone.
+struct CachingJournalDB {
+	db: Box<JournalDB>,
+	node_cache: Option<Arc<Mutex<NodeCache>>>,
+	uncommitted: HashSet<H256>,
+}
+
+impl CachingJournalDB {
+	fn boxed_clone(&self) -> Self {
+		CachingJournalDB {
+			db: self.db.boxed_clone(),
+			node_cache: self.node_cache.clone(),
+			uncommitted: self.uncommitted.clone(),
+		}
+	}
+
+	fn journal_under(&mut self, batch: &mut DBTransaction, now: u64, id: &H256) -> io::Result<u32> {
+		let records = self.db.journal_under(batch, now, id)?;
+		self.uncommitted.clear();
+		Ok(records)
+	}
+
+	fn note_uncommitted(&mut self, key: H256) {
+		if self.node_cache.is_some() {
+			self.uncommitted.insert(key);
+		}
+	}
+}
+
+impl AsHashDB<KeccakHasher, DBValue> for CachingJournalDB {
+	fn as_hashdb(&self) -> &HashDB<KeccakHasher, DBValue> { self }
+	fn as_hashdb_mut(&mut self) -> &mut HashDB<KeccakHasher, DBValue> { self }
+}
+
+impl HashDB<KeccakHasher, DBValue> for CachingJournalDB {
+	fn keys(&self) -> HashMap<H256, i32> {
+		self.db.keys()
+	}
+
+	fn get(&self, key: &H256) -> Option<DBValue> {
+		let node_cache = match self.node_cache {
+			Some(ref node_cache) => node_cache,
+			None => return self.db.get(key),
+		};
+
+		if self.uncommitted.contains(key) {
+			return self.db.get(key);
+		}
+
+		if let Some(node) = node_cache.lock().get(key) {
+			return Some(node);
+		}
+
+		let node = self.db.get(key)?;
+		node_cache.lock().insert(*key, node.clone());
+		Some(node)
+	}
+
+	fn contains(&self, key: &H256) -> bool {
+		self.db.contains(key)
+	}
+
+	fn insert(&mut self, value: &[u8]) -> H256 {
+		let key = self.db.insert(value);
+		self.note_uncommitted(key);
+		key
+	}
+
+	fn emplace(&mut self, key: H256, value: DBValue) {
+		self.note_uncommitted(key);
+		self.db.emplace(key, value)
+	}
+
+	fn remove(&mut self, key: &H256) {
+		self.db.remove(key)
+	}
+}
+
 #[derive(Debug)]
 /// Accumulates a list of accounts changed in a block.
 struct BlockChanges {
@@ -105,8 +247,8 @@ struct BlockChanges {
 /// Then, after the block has been added to the chain the local cache in the
 /// `StateDB` is propagated into the global cache.
 pub struct StateDB {
-	/// Backing database.
-	db: Box<JournalDB>,
+	/// Backing database, with the shared trie node cache.
+	db: CachingJournalDB,
 	/// Shared canonical state cache.
 	account_cache: Arc<Mutex<AccountCache>>,
 	/// DB Code cache. Maps code hashes to shared bytes.
@@ -123,6 +265,10 @@ pub struct StateDB {
 	commit_hash: Option<H256>,
 	/// Number of the committing block or `None` if not committed yet.
 	commit_number: Option<BlockNumber>,
+	/// Shared flat state or `None` if disabled.
+	flat_state: Option<Arc<FlatState>>,
+	/// Flat state changes made on top of the parent block and the resulting state root.
+	flat_changes: Option<(H256, FlatDiff)>,
 }
 
 impl StateDB {
@@ -138,7 +284,11 @@ impl StateDB {
 		let cache_items = acc_cache_size / ::std::mem::size_of::<Option<Account>>();
 
 		StateDB {
-			db: db,
+			db: CachingJournalDB {
+				db: db,
+				node_cache: None,
+				uncommitted: HashSet::new(),
+			},
 			account_cache: Arc::new(Mutex::new(AccountCache {
 				accounts: LruCache::new(cache_items),
 				modifications: VecDeque::new(),
@@ -150,9 +300,46 @@ impl StateDB {
 			parent_hash: None,
 			commit_hash: None,
 			commit_number: None,
+			flat_state: None,
+			flat_changes: None,
 		}
 	}
 
+	/// Use the given flat state for account and storage lookups.
+	pub fn with_flat_state(mut self, flat_state: Option<Arc<FlatState>>) -> StateDB {
+		self.flat_state = flat_state;
+		self
+	}
+
+	/// Serve trie node reads from a cache shared with all clones of this instance.
+	/// The cache is kept under `max_size` bytes and trimmed towards three quarters
+	/// of it by `collect_garbage`.
+	pub fn with_node_cache(mut self, max_size: usize) -> StateDB {
+		self.db.node_cache = if max_size > 0 {
+			Some(Arc::new(Mutex::new(NodeCache::new(max_size * 3 / 4, max_size))))
+		} else {
+			None
+		};
+		self
+	}
+
+	/// Evict least recently used trie nodes until the node cache is back under its preferred size.
+	pub fn collect_garbage(&self) {
+		if let Some(ref node_cache) = self.db.node_cache {
+			node_cache.lock().collect_garbage();
+		}
+	}
+
+	/// Memory used by the shared trie node cache, in bytes.
+	pub fn node_cache_size(&self) -> usize {
+		self.db.node_cache.as_ref().map_or(0, |node_cache| node_cache.lock().size)
+	}
+
+	/// Returns the shared flat state, if enabled.
+	pub fn flat_state(&self) -> Option<&Arc<FlatState>> {
+		self.flat_state.as_ref()
+	}
+
 	/// Loads accounts bloom from the database
 	/// This bloom is used to handle request for the non-existant account fast
 	pub fn load_bloom(db: &KeyValueDB) -> Bloom {
@@ -203,6 +390,9 @@ impl StateDB {
  			Self::commit_bloom(batch, bloom_lock.drain_journal())?;
  		}
 		let records = self.db.journal_under(batch, now, id)?;
+		if let (Some(flat_state), Some(parent), Some((root, changes))) = (self.flat_state.as_ref(), self.parent_hash, self.flat_changes.take()) {
+			flat_state.insert_diff(batch, now, *id, parent, root, changes);
+		}
 		self.commit_hash = Some(id.clone());
 		self.commit_number = Some(now);
 		Ok(records)
@@ -211,7 +401,7 @@ impl StateDB {
 	/// Mark a given candidate from an ancient era as canonical, enacting its removals from the
 	/// backing database and reverting any non-canonical historical commit's insertions.
 	pub fn mark_canonical(&mut self, batch: &mut DBTransaction, end_era: u64, canon_id: &H256) -> io::Result<u32> {
-		self.db.mark_canonical(batch, end_era, canon_id)
+		self.db.db.mark_canonical(batch, end_era, canon_id)
 	}
 
 	/// Propagate local cache into the global cache and synchonize
@@ -309,16 +499,21 @@ impl StateDB {
 				cache.modifications.push_back(block_changes);
 			}
 		}
+
+		// Flatten old diffs once the canonical chain moves on.
+		if let (true, Some(flat_state), Some(hash)) = (is_best, self.flat_state.as_ref(), self.commit_hash) {
+			flat_state.flatten(&hash);
+		}
 	}
 
 	/// Conversion method to interpret self as `HashDB` reference
 	pub fn as_hashdb(&self) -> &HashDB<KeccakHasher, DBValue> {
-		self.db.as_hashdb()
+		&self.db
 	}
 
 	/// Conversion method to interpret self as mutable `HashDB` reference
 	pub fn as_hashdb_mut(&mut self) -> &mut HashDB<KeccakHasher, DBValue> {
-		self.db.as_hashdb_mut()
+		&mut self.db
 	}
 
 	/// Clone the database.
@@ -333,6 +528,8 @@ impl StateDB {
 			parent_hash: None,
 			commit_hash: None,
 			commit_number: None,
+			flat_state: self.flat_state.clone(),
+			flat_changes: None,
 		}
 	}
 
@@ -348,18 +545,20 @@ impl StateDB {
 			parent_hash: Some(parent.clone()),
 			commit_hash: None,
 			commit_number: None,
+			flat_state: self.flat_state.clone(),
+			flat_changes: None,
 		}
 	}
 
 	/// Check if pruning is enabled on the database.
 	pub fn is_pruned(&self) -> bool {
-		self.db.is_pruned()
+		self.db.db.is_pruned()
 	}
 
 	/// Heap size used.
 	pub fn mem_used(&self) -> usize {
 		// TODO: account for LRU-cache overhead; this is a close approximation.
-		self.db.mem_used() + {
+		self.db.db.mem_used() + {
 			let accounts = self.account_cache.lock().accounts.len();
 			let code_size = self.code_cache.lock().current_size();
 			code_size + accounts * ::std::mem::size_of::<Option<Account>>()
@@ -368,7 +567,7 @@ impl StateDB {
 
 	/// Returns underlying `JournalDB`.
 	pub fn journal_db(&self) -> &JournalDB {
-		&*self.db
+		&*self.db.db
 	}
 
 	/// Query how much memory is set aside for the accounts cache (in bytes).
@@ -412,10 +611,10 @@ impl StateDB {
 }
 
 impl state::Backend for StateDB {
-	fn as_hashdb(&self) -> &HashDB<KeccakHasher, DBValue> { self.db.as_hashdb() }
+	fn as_hashdb(&self) -> &HashDB<KeccakHasher, DBValue> { &self.db }
 
 	fn as_hashdb_mut(&mut self) -> &mut HashDB<KeccakHasher, DBValue> {
-		self.db.as_hashdb_mut()
+		&mut self.db
 	}
 
 	fn add_to_account_cache(&mut self, addr: Address, data: Option<Account>, modified: bool) {
@@ -467,6 +666,30 @@ impl state::Backend for StateDB {
 		let is_null = !bloom.check(&*keccak(address));
 		is_null
 	}
+
+	fn get_flat_account(&self, root: &H256, addr: &Address) -> Option<Option<Account>> {
+		let flat_state = self.flat_state.as_ref()?;
+		let parent = self.parent_hash.as_ref()?;
+		match flat_state.account(parent, root, &keccak(addr))? {
+			Some(rlp) => Account::from_rlp(&rlp).ok().map(Some),
+			None => Some(None),
+		}
+	}
+
+	fn get_flat_storage(&self, root: &H256, addr: &Address, storage_root: &H256, key: &H256) -> Option<H256> {
+		let flat_state = self.flat_state.as_ref()?;
+		let parent = self.parent_hash.as_ref()?;
+		flat_state.storage(parent, root, &keccak(addr), storage_root, &keccak(key))
+	}
+
+	fn note_flat_changes(&mut self, root: &H256, changes: FlatDiff) {
+		if self.flat_state.is_none() || self.parent_hash.is_none() {
+			return;
+		}
+		let pending = self.flat_changes.get_or_insert_with(Default::default);
+		pending.0 = *root;
+		pending.1.extend(changes);
+	}
 }
 
 /// Sync wrapper for the account.
@@ -478,10 +701,13 @@ unsafe impl Sync for SyncAccount {}
 
 #[cfg(test)]
 mod tests {
+	use std::sync::Arc;
+	use std::time::Instant;
 	use ethereum_types::{H256, U256, Address};
+	use flat_state::FlatState;
 	use kvdb::DBTransaction;
 	use test_helpers::get_temp_state_db;
-	use state::{Account, Backend};
+	use state::{Account, Backend, CleanupMode, State};
 	use ethcore_logger::init_log;
 
 	#[test]
@@ -550,4 +776,81 @@ mod tests {
 		let s = state_db.boxed_clone_canon(&h3a);
 		assert!(s.get_cached_account(&address).is_none());
 	}
+
+	#[test]
+	fn flat_state_follows_commits() {
+		let state_db = get_temp_state_db();
+		let backing = state_db.journal_db().backing().clone();
+		let flat_state = Arc::new(FlatState::open(backing.clone(), 8));
+		let state_db = state_db.with_flat_state(Some(flat_state.clone()));
+		let address = Address::random();
+		let h0 = H256::random();
+		let h1 = H256::random();
+
+		let mut state = State::new(state_db.boxed_clone_canon(&H256::random()), U256::zero(), Default::default());
+		state.add_balance(&address, &2.into(), CleanupMode::NoEmpty).unwrap();
+		state.commit().unwrap();
+		let (root0, mut s) = state.drop();
+		let mut batch = DBTransaction::new();
+		s.journal_under(&mut batch, 0, &h0).unwrap();
+		backing.write(batch).unwrap();
+
+		flat_state.reset(0, h0, root0);
+		assert!(flat_state.generate(s.as_hashdb(), Instant::now()).unwrap());
+		let s = state_db.boxed_clone_canon(&h0);
+		assert_eq!(s.get_flat_account(&root0, &address).unwrap().unwrap().balance(), &U256::from(2));
+
+		let mut state = State::from_existing(s, root0, U256::zero(), Default::default()).unwrap();
+		assert_eq!(state.balance(&address).unwrap(), U256::from(2));
+		state.add_balance(&address, &3.into(), CleanupMode::NoEmpty).unwrap();
+		state.set_storage(&address, H256::from(1), H256::from(7)).unwrap();
+		state.commit().unwrap();
+		let (root1, mut s) = state.drop();
+		let mut batch = DBTransaction::new();
+		s.journal_under(&mut batch, 1, &h1).unwrap();
+		s.sync_cache(&[], &[], true);
+		backing.write(batch).unwrap();
+
+		let s = state_db.boxed_clone_canon(&h1);
+		let account = s.get_flat_account(&root1, &address).unwrap().unwrap();
+		assert_eq!(account.balance(), &U256::from(5));
+		assert_eq!(s.get_flat_storage(&root1, &address, &account.base_storage_root(), &H256::from(1)), Some(H256::from(7)));
+		// the state root must match
+		assert!(s.get_flat_account(&root0, &address).is_none());
+	}
+
+	#[test]
+	fn node_cache_is_shared_and_bounded() {
+		let state_db = get_temp_state_db().with_node_cache(4096);
+
+		// a node only present in one clone's overlay is not leaked to other clones.
+		let mut writer = state_db.boxed_clone();
+		let hash = writer.as_hashdb_mut().insert(b"trie node");
+		assert_eq!(writer.as_hashdb().get(&hash).map(|node| node.to_vec()), Some(b"trie node".to_vec()));
+		assert_eq!(state_db.node_cache_size(), 0);
+
+		let reader = state_db.boxed_clone();
+		assert!(reader.as_hashdb().get(&hash).is_none());
+
+		// once journalled, it is cached on the next read.
+		let mut batch = DBTransaction::new();
+		writer.journal_under(&mut batch, 1, &H256::from(1)).unwrap();
+		state_db.journal_db().backing().write(batch).unwrap();
+		assert_eq!(writer.as_hashdb().get(&hash).map(|node| node.to_vec()), Some(b"trie node".to_vec()));
+		assert!(state_db.node_cache_size() > 0);
+
+		// the cache never outgrows its maximal size and garbage collection trims it further.
+		for i in 0..1000u32 {
+			let hash = writer.as_hashdb_mut().insert(&[i as u8, (i >> 8) as u8]);
+			let mut batch = DBTransaction::new();
+			writer.journal_under(&mut batch, 2 + i as u64, &H256::from(2 + i as u64)).unwrap();
+			state_db.journal_db().backing().write(batch).unwrap();
+			assert!(writer.as_hashdb().get(&hash).is_some());
+			assert!(state_db.node_cache_size() <= 4096);
+		}
+
+		state_db.collect_garbage();
+		assert!(state_db.node_cache_size() > 0);
+		assert!(state_db.node_cache_size() <= 3072);
+	}
 }
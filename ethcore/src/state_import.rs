@@ -0,0 +1,450 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Import of state dumps written by `parity export state`.
+//!
+//! The dump is streamed, so neither the accounts nor the storage of a single account have
+//! to fit into memory. The state is injected straight into the backing database, leaving
+//! the journal empty, so a client started on a genesis with the resulting state root
+//! picks it up as its genesis state.
+
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use account_db::AccountDBMut;
+use blockchain::BlockChainDB;
+use bloom_journal::Bloom;
+use bytes::Bytes;
+use db;
+use error::Error;
+use ethereum_types::{Address, H256, U256};
+use ethtrie::TrieDBMut;
+use hash::{keccak, KECCAK_EMPTY, KECCAK_NULL_RLP};
+use hashdb::HashDB;
+use journaldb::{self, Algorithm, JournalDB};
+use kvdb::{DBTransaction, DBValue};
+use rlp;
+use rustc_hex::FromHex;
+use serde::de::{DeserializeSeed, Deserializer, Error as DeError, IgnoredAny, MapAccess, Visitor};
+use serde_json;
+use state_db::StateDB;
+use trie::TrieMut;
+use types::basic_account::BasicAccount;
+
+/// Number of accounts and storage slots inserted before the changes are written out.
+const WRITE_BATCH: usize = 64 * 1024;
+
+/// Outcome of a state import.
+#[derive(Debug, PartialEq)]
+pub struct ImportedState {
+	/// Root of the imported state.
+	pub root: H256,
+	/// Number of imported accounts.
+	pub accounts: u64,
+}
+
+/// Import the state dump read from `reader` into the empty state of `db`, stored under
+/// the pruning `algorithm`.
+///
+/// Code and storage of every account with a `code_hash` or `storage_root` have to be
+/// part of the dump. They are checked against the given hashes.
+pub fn import_state<R: Read>(reader: R, db: Arc<BlockChainDB>, algorithm: Algorithm) -> Result<ImportedState, Error> {
+	if db.key_value().iter(db::COL_STATE).next().is_some() {
+		bail!("Target database is not empty");
+	}
+
+	let mut importer = Importer {
+		state: journaldb::new(db.key_value().clone(), algorithm, db::COL_STATE),
+		root: KECCAK_NULL_RLP,
+		bloom: StateDB::load_bloom(&**db.key_value()),
+		accounts: Vec::new(),
+		unwritten: 0,
+		imported: 0,
+	};
+
+	{
+		let mut deserializer = serde_json::Deserializer::from_reader(reader);
+		DumpSeed(&mut importer).deserialize(&mut deserializer).map_err(|e| format!("Invalid state dump: {}", e))?;
+		deserializer.end().map_err(|e| format!("Invalid state dump: {}", e))?;
+	}
+	importer.commit()?;
+	db.key_value().flush()?;
+
+	Ok(ImportedState {
+		root: importer.root,
+		accounts: importer.imported,
+	})
+}
+
+/// Builds the state tries while the dump is read.
+struct Importer {
+	state: Box<JournalDB>,
+	root: H256,
+	bloom: Bloom,
+	/// Hashed addresses and RLP of accounts not inserted into the account trie yet.
+	accounts: Vec<(H256, Bytes)>,
+	/// Number of accounts and storage slots not written to the database yet.
+	unwritten: usize,
+	imported: u64,
+}
+
+impl Importer {
+	/// Insert storage slots into the storage trie of the account with given address hash.
+	fn insert_storage(&mut self, address_hash: H256, root: &mut H256, slots: &mut Vec<(H256, U256)>) -> Result<(), Error> {
+		self.unwritten += slots.len();
+		{
+			let mut account_db = AccountDBMut::from_hash(self.state.as_hashdb_mut(), address_hash);
+			let mut trie = TrieDBMut::from_existing(&mut account_db, root)?;
+			for (key, value) in slots.drain(..) {
+				if !value.is_zero() {
+					trie.insert(&keccak(key), &rlp::encode(&value))?;
+				}
+			}
+		}
+
+		if self.unwritten >= WRITE_BATCH {
+			self.write()?;
+		}
+		Ok(())
+	}
+
+	/// Add a fully read account. `storage_root` is the root of the imported storage, if
+	/// the dump contained any.
+	fn insert_account(&mut self, address: Address, dump: DumpAccount, storage_root: Option<H256>) -> Result<(), Error> {
+		let address_hash = keccak(&address);
+		let code_hash = match (dump.code, dump.code_hash) {
+			(Some(code), expected) => {
+				let code_hash = keccak(&code);
+				if expected.map_or(false, |expected| expected != code_hash) {
+					bail!("Code of account {:?} does not match its hash", address);
+				}
+				if !code.is_empty() {
+					AccountDBMut::from_hash(self.state.as_hashdb_mut(), address_hash).emplace(code_hash, DBValue::from_slice(&code));
+				}
+				code_hash
+			},
+			(None, Some(code_hash)) if code_hash != KECCAK_EMPTY => bail!("Code of account {:?} is missing", address),
+			(None, _) => KECCAK_EMPTY,
+		};
+		let storage_root = match (storage_root, dump.storage_root) {
+			(Some(root), Some(expected)) if root != expected => bail!("Storage of account {:?} does not match its root", address),
+			(Some(root), _) => root,
+			(None, Some(expected)) if expected != KECCAK_NULL_RLP => bail!("Storage of account {:?} is missing", address),
+			(None, _) => KECCAK_NULL_RLP,
+		};
+
+		let account = BasicAccount {
+			nonce: dump.nonce,
+			balance: dump.balance,
+			storage_root: storage_root,
+			code_hash: code_hash,
+		};
+		self.bloom.set(&*address_hash);
+		self.accounts.push((address_hash, rlp::encode(&account)));
+		self.imported += 1;
+		self.unwritten += 1;
+
+		if self.unwritten >= WRITE_BATCH {
+			self.commit()?;
+		}
+		Ok(())
+	}
+
+	/// Insert the pending accounts into the account trie and write all changes out.
+	fn commit(&mut self) -> Result<(), Error> {
+		{
+			let mut account_trie = if self.root != KECCAK_NULL_RLP {
+				TrieDBMut::from_existing(self.state.as_hashdb_mut(), &mut self.root)?
+			} else {
+				TrieDBMut::new(self.state.as_hashdb_mut(), &mut self.root)
+			};
+
+			for (address_hash, account) in self.accounts.drain(..) {
+				account_trie.insert(&address_hash, &account)?;
+			}
+		}
+
+		self.write()?;
+		info!(target: "import", "Imported {} accounts", self.imported);
+		Ok(())
+	}
+
+	/// Write all changes to the backing database.
+	fn write(&mut self) -> Result<(), Error> {
+		let mut batch = DBTransaction::new();
+		StateDB::commit_bloom(&mut batch, self.bloom.drain_journal())?;
+		self.state.inject(&mut batch)?;
+		self.state.backing().write(batch)?;
+		self.unwritten = 0;
+		Ok(())
+	}
+}
+
+/// Account fields read from the dump.
+#[derive(Default)]
+struct DumpAccount {
+	balance: U256,
+	nonce: U256,
+	code: Option<Bytes>,
+	code_hash: Option<H256>,
+	storage_root: Option<H256>,
+}
+
+/// Reads the top-level object of the dump.
+struct DumpSeed<'a>(&'a mut Importer);
+
+impl<'de, 'a> DeserializeSeed<'de> for DumpSeed<'a> {
+	type Value = ();
+
+	fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+		deserializer.deserialize_map(self)
+	}
+}
+
+impl<'de, 'a> Visitor<'de> for DumpSeed<'a> {
+	type Value = ();
+
+	fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "a state dump")
+	}
+
+	fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+		let importer = self.0;
+		let mut found = false;
+		while let Some(key) = map.next_key::<String>()? {
+			if key == "state" {
+				map.next_value_seed(AccountsSeed(&mut *importer))?;
+				found = true;
+			} else {
+				map.next_value::<IgnoredAny>()?;
+			}
+		}
+
+		match found {
+			true => Ok(()),
+			false => Err(A::Error::missing_field("state")),
+		}
+	}
+}
+
+/// Reads the accounts of the dump, keyed by address.
+struct AccountsSeed<'a>(&'a mut Importer);
+
+impl<'de, 'a> DeserializeSeed<'de> for AccountsSeed<'a> {
+	type Value = ();
+
+	fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+		deserializer.deserialize_map(self)
+	}
+}
+
+impl<'de, 'a> Visitor<'de> for AccountsSeed<'a> {
+	type Value = ();
+
+	fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "a map of accounts")
+	}
+
+	fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+		let importer = self.0;
+		while let Some(key) = map.next_key::<String>()? {
+			let address = Address::from_str(clean_hex(&key))
+				.map_err(|_| A::Error::custom(format!("invalid address {}", key)))?;
+			map.next_value_seed(AccountSeed {
+				importer: &mut *importer,
+				address: address,
+			})?;
+		}
+		Ok(())
+	}
+}
+
+/// Reads a single account and adds it to the state.
+struct AccountSeed<'a> {
+	importer: &'a mut Importer,
+	address: Address,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for AccountSeed<'a> {
+	type Value = ();
+
+	fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+		deserializer.deserialize_map(self)
+	}
+}
+
+impl<'de, 'a> Visitor<'de> for AccountSeed<'a> {
+	type Value = ();
+
+	fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "an account")
+	}
+
+	fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+		let importer = self.importer;
+		let address_hash = keccak(&self.address);
+		let mut dump = DumpAccount::default();
+		let mut storage_root = None;
+
+		while let Some(key) = map.next_key::<String>()? {
+			match key.as_str() {
+				"balance" => dump.balance = parse_u256(&map.next_value::<String>()?).map_err(A::Error::custom)?,
+				"nonce" => dump.nonce = parse_u256(&map.next_value::<String>()?).map_err(A::Error::custom)?,
+				"code" => dump.code = Some(parse_bytes(&map.next_value::<String>()?).map_err(A::Error::custom)?),
+				"code_hash" => dump.code_hash = Some(parse_h256(&map.next_value::<String>()?).map_err(A::Error::custom)?),
+				"storage_root" => dump.storage_root = Some(parse_h256(&map.next_value::<String>()?).map_err(A::Error::custom)?),
+				"storage" => {
+					storage_root = Some(map.next_value_seed(StorageSeed {
+						importer: &mut *importer,
+						address_hash: address_hash,
+					})?);
+				},
+				_ => { map.next_value::<IgnoredAny>()?; },
+			}
+		}
+
+		importer.insert_account(self.address, dump, storage_root).map_err(A::Error::custom)
+	}
+}
+
+/// Reads the storage of an account into its storage trie. Yields the storage root.
+struct StorageSeed<'a> {
+	importer: &'a mut Importer,
+	address_hash: H256,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for StorageSeed<'a> {
+	type Value = H256;
+
+	fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<H256, D::Error> {
+		deserializer.deserialize_map(self)
+	}
+}
+
+impl<'de, 'a> Visitor<'de> for StorageSeed<'a> {
+	type Value = H256;
+
+	fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "a map of storage slots")
+	}
+
+	fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<H256, A::Error> {
+		let mut root = KECCAK_NULL_RLP;
+		let mut slots = Vec::new();
+		while let Some(key) = map.next_key::<String>()? {
+			let key = parse_u256(&key).map_err(A::Error::custom)?;
+			let value = parse_u256(&map.next_value::<String>()?).map_err(A::Error::custom)?;
+			slots.push((H256::from(key), value));
+			if slots.len() == WRITE_BATCH {
+				self.importer.insert_storage(self.address_hash, &mut root, &mut slots).map_err(A::Error::custom)?;
+			}
+		}
+
+		self.importer.insert_storage(self.address_hash, &mut root, &mut slots).map_err(A::Error::custom)?;
+		Ok(root)
+	}
+}
+
+/// Strip the optional `0x` prefix.
+fn clean_hex(s: &str) -> &str {
+	if s.starts_with("0x") { &s[2..] } else { s }
+}
+
+fn parse_u256(s: &str) -> Result<U256, String> {
+	U256::from_str(clean_hex(s)).map_err(|_| format!("invalid number {}", s))
+}
+
+fn parse_h256(s: &str) -> Result<H256, String> {
+	H256::from_str(clean_hex(s)).map_err(|_| format!("invalid hash {}", s))
+}
+
+fn parse_bytes(s: &str) -> Result<Bytes, String> {
+	clean_hex(s).from_hex().map_err(|_| format!("invalid hex data {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+
+	use pod_account::PodAccount;
+	use pod_state::PodState;
+	use state::State;
+	use test_helpers::new_db;
+	use super::*;
+
+	fn expected_state() -> PodState {
+		let mut storage = BTreeMap::new();
+		storage.insert(H256::from(0), H256::from(1));
+		storage.insert(H256::from(2), H256::from(0xff));
+
+		let mut accounts = BTreeMap::new();
+		accounts.insert(Address::from(1), PodAccount {
+			balance: U256::from(1_000_000_000_000_000_000u64),
+			nonce: 0.into(),
+			code: Some(Vec::new()),
+			storage: BTreeMap::new(),
+		});
+		accounts.insert(Address::from(0xaa), PodAccount {
+			balance: 16.into(),
+			nonce: 2.into(),
+			code: Some(vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00]),
+			storage: storage,
+		});
+		PodState::from(accounts)
+	}
+
+	fn dump() -> String {
+		let contract: BasicAccount = rlp::decode(&expected_state().get()[&Address::from(0xaa)].rlp()).unwrap();
+		format!(r#"{{ "state": {{
+"0x0000000000000000000000000000000000000001": {{"balance": "de0b6b3a7640000", "nonce": "0"}},
+"0x00000000000000000000000000000000000000aa": {{"balance": "0x10", "nonce": "2", "code_hash": "0x{:x}", "code": "600160005500", "storage_root": "0x{:x}", "storage": {{
+	"0x0000000000000000000000000000000000000000000000000000000000000000": "0x0000000000000000000000000000000000000000000000000000000000000001",
+	"0x0000000000000000000000000000000000000000000000000000000000000002": "0x00000000000000000000000000000000000000000000000000000000000000ff"
+}}}}
+}}}}"#, contract.code_hash, contract.storage_root)
+	}
+
+	#[test]
+	fn imports_dump() {
+		let db = new_db();
+		let imported = import_state(dump().as_bytes(), db.clone(), Algorithm::OverlayRecent).unwrap();
+		assert_eq!(imported, ImportedState { root: expected_state().root(), accounts: 2 });
+
+		let state_db = StateDB::new(journaldb::new(db.key_value().clone(), Algorithm::OverlayRecent, db::COL_STATE), 1024 * 1024);
+		let state = State::from_existing(state_db, imported.root, 0.into(), Default::default()).unwrap();
+		let contract = Address::from(0xaa);
+		assert_eq!(state.balance(&Address::from(1)).unwrap(), U256::from(1_000_000_000_000_000_000u64));
+		assert_eq!(state.nonce(&contract).unwrap(), 2.into());
+		assert_eq!(*state.code(&contract).unwrap().unwrap(), vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00]);
+		assert_eq!(state.storage_at(&contract, &H256::from(2)).unwrap(), H256::from(0xff));
+	}
+
+	#[test]
+	fn rejects_inconsistent_dumps() {
+		let db = new_db();
+		import_state(dump().as_bytes(), db.clone(), Algorithm::OverlayRecent).unwrap();
+		assert!(import_state(dump().as_bytes(), db, Algorithm::OverlayRecent).is_err());
+
+		let wrong_root = dump().replace("\"0x00000000000000000000000000000000000000000000000000000000000000ff\"", "\"0x01\"");
+		assert!(import_state(wrong_root.as_bytes(), new_db(), Algorithm::OverlayRecent).is_err());
+
+		let no_storage = r#"{ "state": { "0x00000000000000000000000000000000000000aa": {"balance": "0", "nonce": "0", "storage_root": "0x1111111111111111111111111111111111111111111111111111111111111111"} } }"#;
+		assert!(import_state(no_storage.as_bytes(), new_db(), Algorithm::OverlayRecent).is_err());
+		assert!(import_state(&b"{}"[..], new_db(), Algorithm::OverlayRecent).is_err());
+	}
+}
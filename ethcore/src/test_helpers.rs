@@ -16,7 +16,7 @@
 
 //! Set of different helpers for client tests
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::{fs, io};
 use account_provider::AccountProvider;
@@ -40,6 +40,7 @@ use state::*;
 use transaction::{Action, Transaction, SignedTransaction};
 use views::BlockView;
 use blooms_db;
+use freezer_db;
 use kvdb::KeyValueDB;
 use kvdb_rocksdb;
 use tempdir::TempDir;
@@ -118,8 +119,12 @@ pub fn generate_dummy_client_with_spec_and_accounts<F>(test_spec: F, accounts: O
 
 /// Generates dummy client (not test client) with corresponding blocks, accounts and spec
 pub fn generate_dummy_client_with_spec_accounts_and_data<F>(test_spec: F, accounts: Option<Arc<AccountProvider>>, block_number: u32, txs_per_block: usize, tx_gas_prices: &[U256]) -> Arc<Client> where F: Fn()->Spec {
+	generate_dummy_client_with_spec_accounts_data_and_db(test_spec, accounts, block_number, txs_per_block, tx_gas_prices, new_db())
+}
+
+/// Generates dummy client (not test client) with corresponding blocks, accounts and spec in the given database
+pub fn generate_dummy_client_with_spec_accounts_data_and_db<F>(test_spec: F, accounts: Option<Arc<AccountProvider>>, block_number: u32, txs_per_block: usize, tx_gas_prices: &[U256], client_db: Arc<BlockChainDB>) -> Arc<Client> where F: Fn()->Spec {
 	let test_spec = test_spec();
-	let client_db = new_db();
 
 	let client = Client::new(
 		ClientConfig::default(),
@@ -268,8 +273,10 @@ pub fn new_db() -> Arc<BlockChainDB> {
 	struct TestBlockChainDB {
 		_blooms_dir: TempDir,
 		_trace_blooms_dir: TempDir,
+		_freezer_dir: TempDir,
 		blooms: blooms_db::Database,
 		trace_blooms: blooms_db::Database,
+		freezer: freezer_db::Database,
 		key_value: Arc<KeyValueDB>,
 	}
 
@@ -285,16 +292,23 @@ pub fn new_db() -> Arc<BlockChainDB> {
 		fn trace_blooms(&self) -> &blooms_db::Database {
 			&self.trace_blooms
 		}
+
+		fn freezer(&self) -> Option<&freezer_db::Database> {
+			Some(&self.freezer)
+		}
 	}
 
 	let blooms_dir = TempDir::new("").unwrap();
 	let trace_blooms_dir = TempDir::new("").unwrap();
+	let freezer_dir = TempDir::new("").unwrap();
 
 	let db = TestBlockChainDB {
 		blooms: blooms_db::Database::open(blooms_dir.path()).unwrap(),
 		trace_blooms: blooms_db::Database::open(trace_blooms_dir.path()).unwrap(),
 		_blooms_dir: blooms_dir,
+		freezer: freezer_db::Database::open(freezer_dir.path()).unwrap(),
 		_trace_blooms_dir: trace_blooms_dir,
+		_freezer_dir: freezer_dir,
 		key_value: Arc::new(::kvdb_memorydb::create(::db::NUM_COLUMNS.unwrap()))
 	};
 
@@ -311,6 +325,7 @@ pub fn restoration_db_handler(config: kvdb_rocksdb::DatabaseConfig) -> Box<Block
 		blooms: blooms_db::Database,
 		trace_blooms: blooms_db::Database,
 		key_value: Arc<KeyValueDB>,
+		path: PathBuf,
 	}
 
 	impl BlockChainDB for RestorationDB {
@@ -325,6 +340,15 @@ pub fn restoration_db_handler(config: kvdb_rocksdb::DatabaseConfig) -> Box<Block
 		fn trace_blooms(&self) -> &blooms_db::Database {
 			&self.trace_blooms
 		}
+
+		fn backup(&self, path: &Path) -> io::Result<()> {
+			if path.exists() {
+				return Err(io::Error::new(io::ErrorKind::AlreadyExists, "Backup directory already exists"));
+			}
+			::db::checkpoint(&self.path, path)?;
+			self.blooms.backup(path.join("blooms"))?;
+			self.trace_blooms.backup(path.join("trace_blooms"))
+		}
 	}
 
 	impl BlockChainDBHandler for RestorationDBHandler {
@@ -340,6 +364,7 @@ pub fn restoration_db_handler(config: kvdb_rocksdb::DatabaseConfig) -> Box<Block
 				blooms,
 				trace_blooms,
 				key_value,
+				path: db_path.to_path_buf(),
 			};
 			Ok(Arc::new(db))
 		}
@@ -37,6 +37,7 @@ use transaction::{PendingTransaction, Transaction, Action, Condition};
 use miner::MinerService;
 use tempdir::TempDir;
 use test_helpers;
+use kvdb_rocksdb::DatabaseConfig;
 use verification::queue::kind::blocks::Unverified;
 
 #[test]
@@ -55,6 +56,31 @@ fn imports_from_empty() {
 	client.flush_queue();
 }
 
+#[test]
+fn restores_live_backup() {
+	let tempdir = TempDir::new("").unwrap();
+	let handler = test_helpers::restoration_db_handler(DatabaseConfig::with_columns(::db::NUM_COLUMNS));
+	let db = handler.open(&tempdir.path().join("db")).unwrap();
+	let client = test_helpers::generate_dummy_client_with_spec_accounts_data_and_db(Spec::new_null, None, 5, 1, &[1.into()], db);
+
+	let backup_path = tempdir.path().join("backup");
+	assert!(BlockChainClient::backup(&*client, &backup_path).is_err());
+	client.backup_database(&backup_path).unwrap();
+	assert!(client.backup_database(&backup_path).is_err());
+
+	let spec = Spec::new_null();
+	let restored = Client::new(
+		ClientConfig::default(),
+		&spec,
+		handler.open(&backup_path).unwrap(),
+		Arc::new(Miner::new_for_tests(&spec, None)),
+		IoChannel::disconnected(),
+	).unwrap();
+	assert_eq!(restored.chain_info().best_block_number, 5);
+	assert_eq!(restored.chain_info().best_block_hash, client.chain_info().best_block_hash);
+	assert!(restored.state_at(BlockId::Latest).is_some());
+}
+
 #[test]
 fn should_return_registrar() {
 	let db = test_helpers::new_db();
@@ -365,3 +391,162 @@ fn transaction_proof() {
 	assert_eq!(state.balance(&Address::default()).unwrap(), 5.into());
 	assert_eq!(state.balance(&address).unwrap(), 95.into());
 }
+
+#[test]
+fn imports_blocks_across_engine_handover() {
+	use account_provider::AccountProvider;
+	use engines::Seal;
+	use ethash::EthashManager;
+	use ethereum_types::{H64, H256};
+
+	let tempdir = TempDir::new("").unwrap();
+	let pow = EthashManager::new(tempdir.path(), None, u64::max_value());
+	let tap = Arc::new(AccountProvider::transient_provider());
+	let validators = vec![
+		tap.insert_account(keccak("0").into(), &"".into()).unwrap(),
+		tap.insert_account(keccak("1").into(), &"".into()).unwrap(),
+	];
+
+	// seal the last ethash block and the first authority round block on one client...
+	let spec = Spec::new_test_engine_multi();
+	let engine = &*spec.engine;
+	let sealer = Client::new(
+		ClientConfig::default(),
+		&spec,
+		test_helpers::new_db(),
+		Arc::new(Miner::new_for_tests(&spec, None)),
+		IoChannel::disconnected(),
+	).unwrap();
+	let mut blocks = Vec::new();
+
+	let b = sealer.prepare_open_block(Address::default(), (3141562.into(), 31415620.into()), vec![]).unwrap().close_and_lock().unwrap();
+	let pow_hash = b.header().bare_hash();
+	let result = pow.compute_light(1, &pow_hash.0, 0);
+	let b = b.seal(engine, vec![::rlp::encode(&H256(result.mix_hash)), ::rlp::encode(&H64::zero())]).unwrap();
+	blocks.push(b.rlp_bytes());
+	sealer.import_sealed_block(b).unwrap();
+
+	let parent = sealer.best_block_header().decode().unwrap();
+	let b = validators.iter().filter_map(|validator| {
+		engine.set_signer(tap.clone(), *validator, "".into());
+		let b = sealer.prepare_open_block(*validator, (3141562.into(), 31415620.into()), vec![]).unwrap().close_and_lock().unwrap();
+		match engine.generate_seal(b.block(), &parent) {
+			Seal::Regular(seal) => Some(b.seal(engine, seal).unwrap()),
+			_ => None,
+		}
+	}).next().expect("one of the validators proposes at the current step");
+	blocks.push(b.rlp_bytes());
+
+	// ...and verify both in full on another.
+	let spec = Spec::new_test_engine_multi();
+	let client = Client::new(
+		ClientConfig::default(),
+		&spec,
+		test_helpers::new_db(),
+		Arc::new(Miner::new_for_tests(&spec, None)),
+		IoChannel::disconnected(),
+	).unwrap();
+
+	for block in blocks {
+		client.import_block(Unverified::from_rlp(block).unwrap()).unwrap();
+	}
+	client.flush_queue();
+	client.import_verified_blocks();
+
+	assert_eq!(client.chain_info().best_block_number, 2);
+	assert_eq!(client.engine().name(), "AuthorityRound");
+}
+
+#[test]
+fn marks_blocks_finalized_by_authority_round() {
+	use account_provider::AccountProvider;
+	use engines::Seal;
+
+	let tap = Arc::new(AccountProvider::transient_provider());
+	let validators = vec![
+		tap.insert_account(keccak("0").into(), &"".into()).unwrap(),
+		tap.insert_account(keccak("1").into(), &"".into()).unwrap(),
+	];
+
+	let spec = Spec::new_test_round_finality();
+	let db = test_helpers::new_db();
+	let client = Client::new(
+		ClientConfig::default(),
+		&spec,
+		db.clone(),
+		Arc::new(Miner::new_for_tests(&spec, None)),
+		IoChannel::disconnected(),
+	).unwrap();
+	assert_eq!(client.block_number(BlockId::Finalized), Some(0));
+
+	// a block is final once both validators sealed on top of it, so seal at every step,
+	// moving on to the next step right away.
+	let mut steps = 0;
+	while client.block_number(BlockId::Finalized) == Some(0) {
+		assert!(steps < 10, "the validators take turns at every step");
+		steps += 1;
+
+		let engine = client.engine();
+		let parent = client.best_block_header().decode().unwrap();
+		for validator in &validators {
+			engine.set_signer(tap.clone(), *validator, "".into());
+			let b = client.prepare_open_block(*validator, (3141562.into(), 31415620.into()), vec![]).unwrap().close_and_lock().unwrap();
+			if let Seal::Regular(seal) = engine.generate_seal(b.block(), &parent) {
+				client.import_sealed_block(b.seal(engine, seal).unwrap()).unwrap();
+				break;
+			}
+		}
+
+		engine.step();
+	}
+
+	let finalized = client.block_hash(BlockId::Finalized).unwrap();
+	assert!(client.block_number(BlockId::Finalized).unwrap() < client.chain_info().best_block_number);
+
+	// the finalized block is found again on startup.
+	drop(client);
+	let spec = Spec::new_test_round_finality();
+	let client = Client::new(
+		ClientConfig::default(),
+		&spec,
+		db,
+		Arc::new(Miner::new_for_tests(&spec, None)),
+		IoChannel::disconnected(),
+	).unwrap();
+	assert_eq!(client.block_hash(BlockId::Finalized), Some(finalized));
+}
+
+#[test]
+fn reverts_chain_only_when_sealing_on_demand() {
+	use std::time::Duration;
+	use bytes::Bytes;
+	use client::{ChainNotify, ChainRoute, ChainRouteType};
+	use ethereum_types::H256;
+	use parking_lot::RwLock;
+
+	#[derive(Default)]
+	struct RetractedBlocks(RwLock<Vec<H256>>);
+
+	impl ChainNotify for RetractedBlocks {
+		fn new_blocks(&self, _: Vec<H256>, _: Vec<H256>, route: ChainRoute, _: Vec<H256>, _: Vec<Bytes>, _: Duration) {
+			let retracted = route.route().iter().filter(|&&(_, ref kind)| match *kind {
+				ChainRouteType::Retracted => true,
+				ChainRouteType::Enacted => false,
+			});
+			self.0.write().extend(retracted.map(|&(hash, _)| hash));
+		}
+	}
+
+	let client = generate_dummy_client(3);
+	assert!(client.revert_to(BlockId::Number(1)).is_err());
+	assert_eq!(client.chain_info().best_block_number, 3);
+
+	let client = test_helpers::generate_dummy_client_with_spec_and_data(Spec::new_instant, 3, 0, &[]);
+	let notify = Arc::new(RetractedBlocks::default());
+	client.add_notify(notify.clone());
+	let retracted = vec![client.block_hash(BlockId::Number(3)).unwrap(), client.block_hash(BlockId::Number(2)).unwrap()];
+
+	client.revert_to(BlockId::Number(1)).unwrap();
+	assert_eq!(client.chain_info().best_block_number, 1);
+	assert_eq!(*notify.0.read(), retracted);
+}
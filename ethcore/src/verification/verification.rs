@@ -40,6 +40,7 @@ use transaction::SignedTransaction;
 use verification::queue::kind::blocks::Unverified;
 
 /// Preprocessed block data gathered in `verify_block_unordered` call
+#[derive(Clone)]
 pub struct PreverifiedBlock {
 	/// Populated block header
 	pub header: Header,
@@ -170,7 +171,7 @@ fn verify_uncles(block: &PreverifiedBlock, bc: &BlockProvider, engine: &EthEngin
 		excluded.insert(header.hash());
 		let mut hash = header.parent_hash().clone();
 		excluded.insert(hash.clone());
-		for _ in 0..engine.maximum_uncle_age() {
+		for _ in 0..engine.maximum_uncle_age(header.number()) {
 			match bc.block_details(&hash) {
 				Some(details) => {
 					excluded.insert(details.parent);
@@ -203,7 +204,7 @@ fn verify_uncles(block: &PreverifiedBlock, bc: &BlockProvider, engine: &EthEngin
 			//												(8 Invalid)
 
 			let depth = if header.number() > uncle.number() { header.number() - uncle.number() } else { 0 };
-			if depth > engine.maximum_uncle_age() as u64 {
+			if depth > engine.maximum_uncle_age(header.number()) as u64 {
 				return Err(From::from(BlockError::UncleTooOld(OutOfBounds { min: Some(header.number() - depth), max: Some(header.number() - 1), found: uncle.number() })));
 			}
 			else if depth < 1 {
@@ -281,7 +282,7 @@ pub fn verify_header_params(header: &Header, engine: &EthEngine, is_full: bool,
 	if header.gas_limit() < &min_gas_limit {
 		return Err(From::from(BlockError::InvalidGasLimit(OutOfBounds { min: Some(min_gas_limit), max: None, found: *header.gas_limit() })));
 	}
-	if let Some(limit) = engine.maximum_gas_limit() {
+	if let Some(limit) = engine.maximum_gas_limit(header.number()) {
 		if header.gas_limit() > &limit {
 			return Err(From::from(::error::BlockError::InvalidGasLimit(OutOfBounds { min: None, max: Some(limit), found: *header.gas_limit() })));
 		}
@@ -291,7 +292,7 @@ pub fn verify_header_params(header: &Header, engine: &EthEngine, is_full: bool,
 		return Err(From::from(BlockError::ExtraDataOutOfBounds(OutOfBounds { min: None, max: Some(maximum_extra_data_size), found: header.extra_data().len() })));
 	}
 
-	if let Some(ref ext) = engine.machine().ethash_extensions() {
+	if let Some(ext) = engine.machine().ethash_extensions_at(header.number()) {
 		if header.number() >= ext.dao_hardfork_transition &&
 			header.number() <= ext.dao_hardfork_transition + 9 &&
 			header.extra_data()[..] != b"dao-hard-fork"[..] {
@@ -324,7 +325,7 @@ fn verify_parent(header: &Header, parent: &Header, engine: &EthEngine) -> Result
 
 	let gas_limit_divisor = engine.params().gas_limit_bound_divisor;
 
-	if !engine.is_timestamp_valid(header.timestamp(), parent.timestamp()) {
+	if !engine.is_timestamp_valid(header.timestamp(), parent.timestamp(), header.number()) {
 		let min = SystemTime::now() + Duration::from_secs(parent.timestamp() + 1);
 		let found = SystemTime::now() + Duration::from_secs(header.timestamp());
 		return Err(From::from(BlockError::InvalidTimestamp(OutOfBounds { max: None, min: Some(min), found })))
@@ -337,11 +338,14 @@ fn verify_parent(header: &Header, parent: &Header, engine: &EthEngine) -> Result
 		return Err(BlockError::RidiculousNumber(OutOfBounds { min: Some(1), max: None, found: header.number() }).into());
 	}
 
-	let parent_gas_limit = *parent.gas_limit();
-	let min_gas = parent_gas_limit - parent_gas_limit / gas_limit_divisor;
-	let max_gas = parent_gas_limit + parent_gas_limit / gas_limit_divisor;
-	if header.gas_limit() <= &min_gas || header.gas_limit() >= &max_gas {
-		return Err(From::from(BlockError::InvalidGasLimit(OutOfBounds { min: Some(min_gas), max: Some(max_gas), found: *header.gas_limit() })));
+	// a gas limit governed by the engine is checked by the engine itself.
+	if engine.gas_limit_override(header)?.is_none() {
+		let parent_gas_limit = *parent.gas_limit();
+		let min_gas = parent_gas_limit - parent_gas_limit / gas_limit_divisor;
+		let max_gas = parent_gas_limit + parent_gas_limit / gas_limit_divisor;
+		if header.gas_limit() <= &min_gas || header.gas_limit() >= &max_gas {
+			return Err(From::from(BlockError::InvalidGasLimit(OutOfBounds { min: Some(min_gas), max: Some(max_gas), found: *header.gas_limit() })));
+		}
 	}
 
 	Ok(())
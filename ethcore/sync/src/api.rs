@@ -23,22 +23,23 @@ use bytes::Bytes;
 use devp2p::NetworkService;
 use network::{NetworkProtocolHandler, NetworkContext, PeerId, ProtocolId,
 	NetworkConfiguration as BasicNetworkConfiguration, NonReservedPeerMode, Error, ErrorKind,
-	ConnectionFilter};
+	ConnectionFilter, NodeReputation, TrafficStats, NatType};
 
 use types::pruning_info::PruningInfo;
 use ethereum_types::{H256, H512, U256};
 use io::{TimerToken};
 use ethcore::ethstore::ethkey::Secret;
-use ethcore::client::{BlockChainClient, ChainNotify, ChainRoute, ChainMessageType};
+use ethcore::client::{BlockChainClient, ChainNotify, ChainRoute, ChainMessageType, NodeFetcher};
 use ethcore::snapshot::SnapshotService;
 use ethcore::header::BlockNumber;
 use sync_io::NetSyncIo;
-use chain::{ChainSync, SyncStatus as EthSyncStatus};
+use beam::BeamFetcher;
+use chain::{ChainSync, SyncStatus as EthSyncStatus, GET_NODE_DATA_PACKET, NODE_DATA_PACKET};
 use std::net::{SocketAddr, AddrParseError};
 use std::str::FromStr;
 use parking_lot::RwLock;
 use chain::{ETH_PROTOCOL_VERSION_63, ETH_PROTOCOL_VERSION_62,
-	PAR_PROTOCOL_VERSION_1, PAR_PROTOCOL_VERSION_2, PAR_PROTOCOL_VERSION_3,
+	PAR_PROTOCOL_VERSION_1, PAR_PROTOCOL_VERSION_2, PAR_PROTOCOL_VERSION_3, PAR_PROTOCOL_VERSION_4, PAR_PROTOCOL_VERSION_5,
 	PRIVATE_TRANSACTION_PACKET, SIGNED_PRIVATE_TRANSACTION_PACKET};
 use light::client::AsLightClient;
 use light::Provider;
@@ -111,6 +112,8 @@ pub struct SyncConfig {
 	pub warp_sync: WarpSync,
 	/// Enable light client server.
 	pub serve_light: bool,
+	/// Enable beam sync.
+	pub beam_sync: bool,
 }
 
 impl Default for SyncConfig {
@@ -124,6 +127,7 @@ impl Default for SyncConfig {
 			fork_block: None,
 			warp_sync: WarpSync::Disabled,
 			serve_light: false,
+			beam_sync: false,
 		}
 	}
 }
@@ -169,6 +173,10 @@ pub struct PeerInfo {
 	pub eth_info: Option<EthProtocolInfo>,
 	/// Light protocol info.
 	pub pip_info: Option<PipProtocolInfo>,
+	/// Total traffic exchanged with the peer.
+	pub traffic: TrafficStats,
+	/// Traffic exchanged with the peer per subprotocol.
+	pub protocol_traffic: BTreeMap<String, TrafficStats>,
 }
 
 /// Ethereum protocol info.
@@ -203,6 +211,13 @@ impl From<light_net::Status> for PipProtocolInfo {
 	}
 }
 
+// Key per-protocol traffic counters by protocol name.
+fn protocol_traffic(traffic: HashMap<ProtocolId, TrafficStats>) -> BTreeMap<String, TrafficStats> {
+	traffic.into_iter()
+		.map(|(protocol, stats)| (String::from_utf8_lossy(&protocol).into_owned(), stats))
+		.collect()
+}
+
 /// Configuration to attach alternate protocol handlers.
 /// Only works when IPC is disabled.
 pub struct AttachedProtocol {
@@ -249,7 +264,7 @@ pub struct Params {
 /// Ethereum network protocol handler
 pub struct EthSync {
 	/// Network service
-	network: NetworkService,
+	network: Arc<NetworkService>,
 	/// Main (eth/par) protocol handler
 	eth_handler: Arc<SyncProtocolHandler>,
 	/// Light (pip) protocol handler
@@ -316,7 +331,23 @@ impl EthSync {
 		};
 
 		let chain_sync = ChainSync::new(params.config, &*params.chain, params.private_tx_handler.clone());
-		let service = NetworkService::new(params.network_config.clone().into_basic()?, connection_filter)?;
+		let service = Arc::new(NetworkService::new(params.network_config.clone().into_basic()?, connection_filter)?);
+
+		let beam = match params.config.beam_sync {
+			false => None,
+			true => {
+				let network = Arc::downgrade(&service);
+				Some(Arc::new(BeamFetcher::new(Box::new(move |peer, protocol, packet| {
+					if let Some(network) = network.upgrade() {
+						network.with_context(protocol, |context| {
+							if let Err(e) = context.send(peer, GET_NODE_DATA_PACKET, packet) {
+								debug!(target: "sync", "Error sending node data request: {:?}", e);
+							}
+						});
+					}
+				}))))
+			},
+		};
 
 		let sync = Arc::new(EthSync {
 			network: service,
@@ -325,6 +356,7 @@ impl EthSync {
 				chain: params.chain,
 				snapshot_service: params.snapshot_service,
 				overlay: RwLock::new(HashMap::new()),
+				beam,
 			}),
 			light_proto: light_proto,
 			subprotocol_name: params.config.subprotocol_name,
@@ -334,6 +366,11 @@ impl EthSync {
 
 		Ok(sync)
 	}
+
+	/// State fetcher for the client when beam syncing.
+	pub fn node_fetcher(&self) -> Option<Arc<NodeFetcher>> {
+		self.eth_handler.beam.clone().map(|beam| beam as Arc<NodeFetcher>)
+	}
 }
 
 impl SyncProvider for EthSync {
@@ -363,6 +400,8 @@ impl SyncProvider for EthSync {
 					local_address: session_info.local_address,
 					eth_info: eth_sync.peer_info(&peer_id),
 					pip_info: light_proto.as_ref().and_then(|lp| lp.peer_status(peer_id)).map(Into::into),
+					traffic: session_info.traffic,
+					protocol_traffic: protocol_traffic(session_info.protocol_traffic),
 				})
 			}).collect()
 		}).unwrap_or_else(Vec::new)
@@ -394,6 +433,8 @@ struct SyncProtocolHandler {
 	sync: RwLock<ChainSync>,
 	/// Chain overlay used to cache data such as fork block.
 	overlay: RwLock<HashMap<BlockNumber, Bytes>>,
+	/// On-demand state fetcher, when beam syncing.
+	beam: Option<Arc<BeamFetcher>>,
 }
 
 impl NetworkProtocolHandler for SyncProtocolHandler {
@@ -406,6 +447,10 @@ impl NetworkProtocolHandler for SyncProtocolHandler {
 	}
 
 	fn read(&self, io: &NetworkContext, peer: &PeerId, packet_id: u8, data: &[u8]) {
+		// node data for the client is collected by the beam fetcher without locking sync.
+		if packet_id == NODE_DATA_PACKET && self.beam.as_ref().map_or(false, |beam| beam.on_node_data(*peer, data)) {
+			return;
+		}
 		ChainSync::dispatch_packet(&self.sync, &mut NetSyncIo::new(io, &*self.chain, &*self.snapshot_service, &self.overlay), *peer, packet_id, data);
 	}
 
@@ -415,12 +460,21 @@ impl NetworkProtocolHandler for SyncProtocolHandler {
 		let warp_protocol = io.protocol_version(WARP_SYNC_PROTOCOL_ID, *peer).unwrap_or(0) != 0;
 		let warp_context = io.subprotocol_name() == WARP_SYNC_PROTOCOL_ID;
 		if warp_protocol == warp_context {
+			if let Some(ref beam) = self.beam {
+				let eth_63 = io.protocol_version(io.subprotocol_name(), *peer).map_or(false, |v| v >= ETH_PROTOCOL_VERSION_63.0);
+				if warp_context || eth_63 {
+					beam.add_peer(*peer, io.subprotocol_name());
+				}
+			}
 			self.sync.write().on_peer_connected(&mut NetSyncIo::new(io, &*self.chain, &*self.snapshot_service, &self.overlay), *peer);
 		}
 	}
 
 	fn disconnected(&self, io: &NetworkContext, peer: &PeerId) {
 		trace_time!("sync::disconnected");
+		if let Some(ref beam) = self.beam {
+			beam.remove_peer(*peer);
+		}
 		if io.subprotocol_name() != WARP_SYNC_PROTOCOL_ID {
 			self.sync.write().on_peer_aborting(&mut NetSyncIo::new(io, &*self.chain, &*self.snapshot_service, &self.overlay), *peer);
 		}
@@ -430,7 +484,12 @@ impl NetworkProtocolHandler for SyncProtocolHandler {
 		trace_time!("sync::timeout");
 		let mut io = NetSyncIo::new(io, &*self.chain, &*self.snapshot_service, &self.overlay);
 		match timer {
-			PEERS_TIMER => self.sync.write().maintain_peers(&mut io),
+			PEERS_TIMER => {
+				if let Some(ref beam) = self.beam {
+					beam.maintain();
+				}
+				self.sync.write().maintain_peers(&mut io)
+			},
 			SYNC_TIMER => self.sync.write().maintain_sync(&mut io),
 			TX_TIMER => {
 				self.sync.write().propagate_new_transactions(&mut io);
@@ -500,7 +559,7 @@ impl ChainNotify for EthSync {
 		self.network.register_protocol(self.eth_handler.clone(), self.subprotocol_name, &[ETH_PROTOCOL_VERSION_62, ETH_PROTOCOL_VERSION_63])
 			.unwrap_or_else(|e| warn!("Error registering ethereum protocol: {:?}", e));
 		// register the warp sync subprotocol
-		self.network.register_protocol(self.eth_handler.clone(), WARP_SYNC_PROTOCOL_ID, &[PAR_PROTOCOL_VERSION_1, PAR_PROTOCOL_VERSION_2, PAR_PROTOCOL_VERSION_3])
+		self.network.register_protocol(self.eth_handler.clone(), WARP_SYNC_PROTOCOL_ID, &[PAR_PROTOCOL_VERSION_1, PAR_PROTOCOL_VERSION_2, PAR_PROTOCOL_VERSION_3, PAR_PROTOCOL_VERSION_4, PAR_PROTOCOL_VERSION_5])
 			.unwrap_or_else(|e| warn!("Error registering snapshot sync protocol: {:?}", e));
 
 		// register the light protocol.
@@ -568,6 +627,8 @@ pub trait ManageNetwork : Send + Sync {
 	fn num_peers_range(&self) -> Range<u32>;
 	/// Get network context for protocol.
 	fn with_proto_context(&self, proto: ProtocolId, f: &mut FnMut(&NetworkContext));
+	/// Returns reputation of all known nodes.
+	fn node_reputations(&self) -> Vec<NodeReputation>;
 }
 
 impl ManageNetwork for EthSync {
@@ -611,6 +672,10 @@ impl ManageNetwork for EthSync {
 	fn with_proto_context(&self, proto: ProtocolId, f: &mut FnMut(&NetworkContext)) {
 		self.network.with_context_eval(proto, f);
 	}
+
+	fn node_reputations(&self) -> Vec<NodeReputation> {
+		self.network.node_reputations()
+	}
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -628,6 +693,8 @@ pub struct NetworkConfiguration {
 	pub udp_port: Option<u16>,
 	/// Enable NAT configuration
 	pub nat_enabled: bool,
+	/// Port mapping mechanism to use when NAT configuration is enabled
+	pub nat_type: NatType,
 	/// Enable discovery
 	pub discovery_enabled: bool,
 	/// List of initial node addresses
@@ -650,6 +717,10 @@ pub struct NetworkConfiguration {
 	pub ip_filter: IpFilter,
 	/// Client version string
 	pub client_version: String,
+	/// Maximum upload rate across all peers in bytes per second.
+	pub max_egress_rate: Option<u64>,
+	/// Maximum upload rate to a single peer in bytes per second.
+	pub max_peer_egress_rate: Option<u64>,
 }
 
 impl NetworkConfiguration {
@@ -672,6 +743,7 @@ impl NetworkConfiguration {
 			public_address: match self.public_address { None => None, Some(addr) => Some(SocketAddr::from_str(&addr)?) },
 			udp_port: self.udp_port,
 			nat_enabled: self.nat_enabled,
+			nat_type: self.nat_type,
 			discovery_enabled: self.discovery_enabled,
 			boot_nodes: self.boot_nodes,
 			use_secret: self.use_secret,
@@ -683,6 +755,8 @@ impl NetworkConfiguration {
 			ip_filter: self.ip_filter,
 			non_reserved_mode: if self.allow_non_reserved { NonReservedPeerMode::Accept } else { NonReservedPeerMode::Deny },
 			client_version: self.client_version,
+			max_egress_rate: self.max_egress_rate,
+			max_peer_egress_rate: self.max_peer_egress_rate,
 		})
 	}
 }
@@ -696,6 +770,7 @@ impl From<BasicNetworkConfiguration> for NetworkConfiguration {
 			public_address: other.public_address.and_then(|addr| Some(format!("{}", addr))),
 			udp_port: other.udp_port,
 			nat_enabled: other.nat_enabled,
+			nat_type: other.nat_type,
 			discovery_enabled: other.discovery_enabled,
 			boot_nodes: other.boot_nodes,
 			use_secret: other.use_secret,
@@ -707,6 +782,8 @@ impl From<BasicNetworkConfiguration> for NetworkConfiguration {
 			ip_filter: other.ip_filter,
 			allow_non_reserved: match other.non_reserved_mode { NonReservedPeerMode::Accept => true, _ => false } ,
 			client_version: other.client_version,
+			max_egress_rate: other.max_egress_rate,
+			max_peer_egress_rate: other.max_peer_egress_rate,
 		}
 	}
 }
@@ -890,6 +967,10 @@ impl ManageNetwork for LightSync {
 	fn with_proto_context(&self, proto: ProtocolId, f: &mut FnMut(&NetworkContext)) {
 		self.network.with_context_eval(proto, f);
 	}
+
+	fn node_reputations(&self) -> Vec<NodeReputation> {
+		self.network.node_reputations()
+	}
 }
 
 impl LightSyncProvider for LightSync {
@@ -923,6 +1004,8 @@ impl LightSyncProvider for LightSync {
 					local_address: session_info.local_address,
 					eth_info: None,
 					pip_info: self.proto.peer_status(peer_id).map(Into::into),
+					traffic: session_info.traffic,
+					protocol_traffic: protocol_traffic(session_info.protocol_traffic),
 				})
 			}).collect()
 		}).unwrap_or_else(Vec::new)
@@ -0,0 +1,422 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! On-demand state download for beam sync.
+//!
+//! The client queues the keys of state entries missing from its database while importing
+//! blocks or serving RPC. They are requested from peers with `GetNodeData` (eth/63) in the
+//! background and handed back to the client, which retries the work that needed them.
+//!
+//! The block beam sync starts from is only trusted once several peers announced it.
+
+use std::cmp;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use ethcore::client::{NodeFetcher, is_value_for};
+use ethcore::header::BlockNumber;
+use ethcore::verification::queue::kind::blocks::Unverified;
+use ethereum_types::{H256, U256};
+use kvdb::DBValue;
+use network::{PeerId, ProtocolId};
+use parking_lot::Mutex;
+use rlp::{Rlp, RlpStream};
+
+/// Maximum number of entries requested from a peer at once.
+const MAX_NODE_DATA_TO_REQUEST: usize = 384;
+/// Time to wait for a peer to respond.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Number of times an entry is requested before giving up on it.
+const MAX_ATTEMPTS: usize = 3;
+/// Number of distinct peers which must announce a block before it's used as the pivot.
+pub const PIVOT_CONFIRMATIONS: usize = 3;
+/// Maximum number of announced blocks considered for the pivot at once.
+const MAX_PIVOT_CANDIDATES: usize = 32;
+
+/// Sends a `GetNodeData` packet payload to a peer on the given subprotocol.
+pub type SendRequest = Fn(PeerId, ProtocolId, Bytes) + Send + Sync;
+
+struct Request {
+	keys: Vec<H256>,
+	sent_at: Instant,
+}
+
+#[derive(Default)]
+struct State {
+	/// Peers able to serve node data, with the subprotocol they are connected with.
+	peers: Vec<(PeerId, ProtocolId)>,
+	/// Outstanding request for each peer.
+	asking: HashMap<PeerId, Request>,
+	/// Keys waiting for an idle peer.
+	queue: VecDeque<H256>,
+	/// Number of failed attempts for each key queued or being requested.
+	attempts: HashMap<H256, usize>,
+	/// Entries received and not taken by the client yet.
+	fetched: Vec<(H256, DBValue)>,
+	next_peer: usize,
+}
+
+impl State {
+	/// Queue keys again after a failed request, dropping the ones requested too many times.
+	fn requeue(&mut self, keys: Vec<H256>) {
+		for key in keys {
+			let give_up = match self.attempts.get_mut(&key) {
+				Some(attempts) => {
+					*attempts += 1;
+					*attempts >= MAX_ATTEMPTS
+				},
+				None => continue,
+			};
+			if give_up {
+				trace!(target: "sync", "Giving up on state entry {:?}", key);
+				self.attempts.remove(&key);
+			} else {
+				self.queue.push_back(key);
+			}
+		}
+	}
+
+	fn idle_peer(&mut self) -> Option<(PeerId, ProtocolId)> {
+		if self.peers.is_empty() {
+			return None;
+		}
+
+		self.next_peer = self.next_peer.wrapping_add(1);
+		let start = self.next_peer % self.peers.len();
+		let asking = &self.asking;
+		self.peers[start..].iter().chain(self.peers[..start].iter())
+			.find(|&&(peer, _)| !asking.contains_key(&peer))
+			.cloned()
+	}
+
+	/// Hand the queued keys to idle peers, returning the requests to send.
+	fn assign(&mut self) -> Vec<(PeerId, ProtocolId, Vec<H256>)> {
+		let mut requests = Vec::new();
+		while !self.queue.is_empty() {
+			let (peer, protocol) = match self.idle_peer() {
+				Some(peer) => peer,
+				None => break,
+			};
+			let count = cmp::min(self.queue.len(), MAX_NODE_DATA_TO_REQUEST);
+			let keys: Vec<_> = self.queue.drain(..count).collect();
+			self.asking.insert(peer, Request { keys: keys.clone(), sent_at: Instant::now() });
+			requests.push((peer, protocol, keys));
+		}
+		requests
+	}
+}
+
+/// Fetches state entries from peers for the client.
+pub struct BeamFetcher {
+	send: Box<SendRequest>,
+	state: Mutex<State>,
+}
+
+impl BeamFetcher {
+	/// Create a new fetcher sending requests with the given function.
+	pub fn new(send: Box<SendRequest>) -> Self {
+		BeamFetcher {
+			send,
+			state: Mutex::new(State::default()),
+		}
+	}
+
+	/// Register a peer able to serve node data.
+	pub fn add_peer(&self, peer: PeerId, protocol: ProtocolId) {
+		{
+			let mut state = self.state.lock();
+			if !state.peers.iter().any(|&(p, _)| p == peer) {
+				state.peers.push((peer, protocol));
+			}
+		}
+		self.dispatch();
+	}
+
+	/// Forget a disconnected peer, queueing its outstanding request again.
+	pub fn remove_peer(&self, peer: PeerId) {
+		{
+			let mut state = self.state.lock();
+			state.peers.retain(|&(p, _)| p != peer);
+			if let Some(request) = state.asking.remove(&peer) {
+				state.requeue(request.keys);
+			}
+		}
+		self.dispatch();
+	}
+
+	/// Handle a `NodeData` packet. Returns false if there was no outstanding beam sync
+	/// request for the peer, i.e. the packet should be handled elsewhere.
+	pub fn on_node_data(&self, peer: PeerId, data: &[u8]) -> bool {
+		{
+			let mut state = self.state.lock();
+			let request = match state.asking.remove(&peer) {
+				Some(request) => request,
+				None => return false,
+			};
+
+			let values = Rlp::new(data).iter()
+				.filter_map(|item| item.data().ok().map(DBValue::from_slice))
+				.collect::<Vec<_>>();
+			trace!(target: "sync", "{} -> NodeData: {} entries", peer, values.len());
+
+			let mut missing = Vec::new();
+			for key in request.keys {
+				match values.iter().find(|value| is_value_for(&key, value)) {
+					Some(value) => {
+						state.attempts.remove(&key);
+						state.fetched.push((key, value.clone()));
+					},
+					None => missing.push(key),
+				}
+			}
+			state.requeue(missing);
+		}
+		self.dispatch();
+		true
+	}
+
+	/// Expire the requests peers failed to answer in time and send the queued ones.
+	pub fn maintain(&self) {
+		{
+			let mut state = self.state.lock();
+			let now = Instant::now();
+			let expired: Vec<_> = state.asking.iter()
+				.filter(|&(_, request)| now.duration_since(request.sent_at) > REQUEST_TIMEOUT)
+				.map(|(peer, _)| *peer)
+				.collect();
+			for peer in expired {
+				debug!(target: "sync", "{} -> GetNodeData timeout", peer);
+				if let Some(request) = state.asking.remove(&peer) {
+					state.requeue(request.keys);
+				}
+			}
+		}
+		self.dispatch();
+	}
+
+	fn dispatch(&self) {
+		let requests = self.state.lock().assign();
+		for (peer, protocol, keys) in requests {
+			let mut packet = RlpStream::new_list(keys.len());
+			for key in &keys {
+				packet.append(key);
+			}
+
+			trace!(target: "sync", "{} <- GetNodeData: {} entries", peer, keys.len());
+			(self.send)(peer, protocol, packet.out());
+		}
+	}
+}
+
+impl NodeFetcher for BeamFetcher {
+	fn request(&self, keys: &[H256]) {
+		{
+			let mut state = self.state.lock();
+			for key in keys {
+				if !state.attempts.contains_key(key) {
+					state.attempts.insert(*key, 0);
+					state.queue.push_back(*key);
+				}
+			}
+		}
+		self.dispatch();
+	}
+
+	fn take_fetched(&self) -> Vec<(H256, DBValue)> {
+		mem::replace(&mut self.state.lock().fetched, Vec::new())
+	}
+
+	fn pending(&self) -> usize {
+		self.state.lock().attempts.len()
+	}
+}
+
+struct Candidate {
+	number: BlockNumber,
+	/// The block and the lowest total difficulty it was announced with, once received with `NewBlock`.
+	block: Option<(Unverified, U256)>,
+	peers: HashSet<PeerId>,
+}
+
+/// Blocks announced while waiting for the beam sync pivot.
+///
+/// The pivot is imported without its ancestors, so a block is only used once
+/// `PIVOT_CONFIRMATIONS` distinct peers announced it; a single peer can't choose the chain.
+#[derive(Default)]
+pub struct PivotCandidates {
+	candidates: HashMap<H256, Candidate>,
+}
+
+impl PivotCandidates {
+	/// Record a block announced by a peer with `NewBlock`. Returns the block with its total
+	/// difficulty once enough peers announced it.
+	pub fn on_new_block(&mut self, peer: PeerId, block: Unverified, total_difficulty: U256) -> Option<(Unverified, U256)> {
+		let hash = block.header.hash();
+		{
+			let candidate = self.announce(peer, hash, block.header.number());
+			let lower = candidate.block.as_ref().map_or(true, |&(_, ref td)| total_difficulty < *td);
+			if lower {
+				candidate.block = Some((block, total_difficulty));
+			}
+		}
+		self.take_confirmed(&hash)
+	}
+
+	/// Record a block hash announced by a peer with `NewHashes`. Returns the block with its total
+	/// difficulty once enough peers announced it and its body was received.
+	pub fn on_new_hash(&mut self, peer: PeerId, hash: H256, number: BlockNumber) -> Option<(Unverified, U256)> {
+		self.announce(peer, hash, number);
+		self.take_confirmed(&hash)
+	}
+
+	/// Forget the announcements of a disconnected peer.
+	pub fn remove_peer(&mut self, peer: PeerId) {
+		for candidate in self.candidates.values_mut() {
+			candidate.peers.remove(&peer);
+		}
+	}
+
+	fn announce(&mut self, peer: PeerId, hash: H256, number: BlockNumber) -> &mut Candidate {
+		if !self.candidates.contains_key(&hash) && self.candidates.len() >= MAX_PIVOT_CANDIDATES {
+			let oldest = self.candidates.iter().min_by_key(|&(_, c)| c.number).map(|(hash, _)| *hash);
+			if let Some(oldest) = oldest {
+				self.candidates.remove(&oldest);
+			}
+		}
+
+		let candidate = self.candidates.entry(hash).or_insert_with(|| Candidate {
+			number,
+			block: None,
+			peers: HashSet::new(),
+		});
+		candidate.peers.insert(peer);
+		candidate
+	}
+
+	fn take_confirmed(&mut self, hash: &H256) -> Option<(Unverified, U256)> {
+		let confirmed = self.candidates.get(hash)
+			.map_or(false, |c| c.block.is_some() && c.peers.len() >= PIVOT_CONFIRMATIONS);
+		if !confirmed {
+			return None;
+		}
+
+		let block = self.candidates.remove(hash).and_then(|c| c.block);
+		self.candidates.clear();
+		block
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use ethereum_types::{H256, U256};
+	use ethcore::client::NodeFetcher;
+	use ethcore::verification::queue::kind::blocks::Unverified;
+	use hash::keccak;
+	use kvdb::DBValue;
+	use network::PeerId;
+	use parking_lot::Mutex;
+	use rlp::{Rlp, RlpStream};
+	use chain::tests::get_dummy_block;
+	use super::{BeamFetcher, PivotCandidates, MAX_ATTEMPTS};
+
+	fn fetcher() -> (Arc<BeamFetcher>, Arc<Mutex<Vec<(PeerId, Vec<H256>)>>>) {
+		let sent = Arc::new(Mutex::new(Vec::new()));
+		let log = sent.clone();
+		let fetcher = BeamFetcher::new(Box::new(move |peer, _protocol, packet| {
+			log.lock().push((peer, Rlp::new(&packet).as_list().unwrap()));
+		}));
+		(Arc::new(fetcher), sent)
+	}
+
+	fn node_data(values: &[&str]) -> Vec<u8> {
+		let mut stream = RlpStream::new_list(values.len());
+		for value in values {
+			stream.append(&value.as_bytes().to_vec());
+		}
+		stream.out()
+	}
+
+	#[test]
+	fn fetches_from_peer() {
+		let (fetcher, sent) = fetcher();
+		let (one, two) = (keccak("one"), keccak("two"));
+		fetcher.request(&[one, two]);
+		assert!(sent.lock().is_empty());
+		assert_eq!(fetcher.pending(), 2);
+
+		fetcher.add_peer(1, *b"eth");
+		assert!(!fetcher.on_node_data(2, &node_data(&["unexpected"])));
+		assert_eq!(*sent.lock(), vec![(1, vec![one, two])]);
+
+		// already requested keys are not requested again.
+		fetcher.request(&[one]);
+		assert_eq!(sent.lock().len(), 1);
+
+		assert!(fetcher.on_node_data(1, &node_data(&["two", "one", "three"])));
+		assert_eq!(fetcher.take_fetched(), vec![
+			(one, DBValue::from_slice(b"one")),
+			(two, DBValue::from_slice(b"two")),
+		]);
+		assert!(fetcher.take_fetched().is_empty());
+		assert_eq!(fetcher.pending(), 0);
+	}
+
+	#[test]
+	fn retries_with_other_peer() {
+		let (fetcher, sent) = fetcher();
+		let key = keccak("one");
+		fetcher.add_peer(1, *b"eth");
+		fetcher.add_peer(2, *b"par");
+
+		fetcher.request(&[key]);
+		let (first, _) = sent.lock()[0].clone();
+		// the first peer doesn't have the entry, the second one disconnects.
+		assert!(fetcher.on_node_data(first, &node_data(&[])));
+		let (second, _) = sent.lock()[1].clone();
+		assert!(second != first);
+		fetcher.remove_peer(second);
+
+		// the entry is given up on after too many attempts.
+		assert_eq!(sent.lock().len(), MAX_ATTEMPTS);
+		assert!(fetcher.on_node_data(first, &node_data(&[])));
+		assert_eq!(sent.lock().len(), MAX_ATTEMPTS);
+		assert_eq!(fetcher.pending(), 0);
+		assert!(fetcher.take_fetched().is_empty());
+	}
+
+	#[test]
+	fn pivot_needs_several_peers() {
+		let block = |parent: u64| Unverified::from_rlp(get_dummy_block(100, H256::from(parent))).unwrap();
+		let hash = block(1).header.hash();
+		let mut candidates = PivotCandidates::default();
+
+		// the same peer announcing twice counts once, other blocks don't count.
+		assert!(candidates.on_new_block(0, block(1), U256::from(5000)).is_none());
+		assert!(candidates.on_new_block(0, block(1), U256::from(5000)).is_none());
+		assert!(candidates.on_new_block(1, block(2), U256::from(5000)).is_none());
+		// disconnected peers don't count.
+		assert!(candidates.on_new_hash(2, hash, 100).is_none());
+		candidates.remove_peer(2);
+		assert!(candidates.on_new_block(3, block(1), U256::from(4000)).is_none());
+
+		let (pivot, total_difficulty) = candidates.on_new_hash(4, hash, 100).expect("three peers announced the block");
+		assert_eq!(pivot.header.hash(), hash);
+		assert_eq!(total_difficulty, U256::from(4000));
+	}
+}
@@ -20,11 +20,11 @@ use bytes::Bytes;
 use ethcore::client::{BlockId, BlockStatus};
 use ethcore::error::{Error as EthcoreError, ErrorKind as EthcoreErrorKind, ImportErrorKind, BlockError};
 use ethcore::header::BlockNumber;
-use ethcore::snapshot::{ManifestData, RestorationStatus};
+use ethcore::snapshot::{verify_chunk_metadata, ManifestData, RestorationStatus};
 use ethcore::verification::queue::kind::blocks::Unverified;
 use ethereum_types::{H256, U256};
 use hash::keccak;
-use network::PeerId;
+use network::{PeerId, PeerBehaviour};
 use rlp::Rlp;
 use snapshot::ChunkType;
 use std::cmp;
@@ -47,7 +47,7 @@ use super::{
 	MAX_NEW_BLOCK_AGE,
 	MAX_NEW_HASHES,
 	PAR_PROTOCOL_VERSION_1,
-	PAR_PROTOCOL_VERSION_3,
+	PAR_PROTOCOL_VERSION_5,
 	BLOCK_BODIES_PACKET,
 	BLOCK_HEADERS_PACKET,
 	NEW_BLOCK_HASHES_PACKET,
@@ -93,10 +93,12 @@ impl SyncHandler {
 		match result {
 			Err(DownloaderImportError::Invalid) => {
 				debug!(target:"sync", "{} -> Invalid packet {}", peer, packet_id);
+				io.report_peer(peer, PeerBehaviour::InvalidBlock);
 				io.disable_peer(peer);
 				sync.deactivate_peer(io, peer);
 			},
 			Err(DownloaderImportError::Useless) => {
+				io.report_peer(peer, PeerBehaviour::UselessResponse);
 				sync.deactivate_peer(io, peer);
 			},
 			Ok(()) => {
@@ -119,6 +121,7 @@ impl SyncHandler {
 	pub fn on_peer_aborting(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId) {
 		trace!(target: "sync", "== Disconnecting {}: {}", peer_id, io.peer_info(peer_id));
 		sync.handshaking_peers.remove(&peer_id);
+		sync.beam_pivots.remove_peer(peer_id);
 		if sync.peers.contains_key(&peer_id) {
 			debug!(target: "sync", "Disconnected {}", peer_id);
 			sync.clear_peer_download(peer_id);
@@ -177,6 +180,13 @@ impl SyncHandler {
 			peer.latest_hash = hash;
 		}
 
+		if sync.state == SyncState::BeamPivot {
+			return match sync.beam_pivots.on_new_block(peer_id, block, difficulty) {
+				Some((pivot, total_difficulty)) => SyncHandler::import_beam_pivot(sync, io, pivot, total_difficulty),
+				None => Ok(()),
+			};
+		}
+
 		let last_imported_number = sync.new_blocks.last_imported_block_number();
 		if last_imported_number > number && last_imported_number - number > MAX_NEW_BLOCK_AGE {
 			trace!(target: "sync", "Ignored ancient new block {:?}", hash);
@@ -216,6 +226,23 @@ impl SyncHandler {
 		Ok(())
 	}
 
+	/// Use a freshly announced block, confirmed by several peers, as the pivot to beam sync from.
+	fn import_beam_pivot(sync: &mut ChainSync, io: &mut SyncIo, block: Unverified, total_difficulty: U256) -> Result<(), DownloaderImportError> {
+		let hash = block.header.hash();
+		let number = block.header.number();
+		match io.chain().import_beam_pivot(block, total_difficulty) {
+			Ok(_) => {
+				info!(target: "sync", "Beam syncing from block #{} ({:?})", number, hash);
+				sync.restart(io);
+				Ok(())
+			},
+			Err(e) => {
+				debug!(target: "sync", "Bad beam sync pivot {:?}: {:?}", hash, e);
+				Err(DownloaderImportError::Invalid)
+			},
+		}
+	}
+
 	/// Handles `NewHashes` packet. Initiates headers download for any unknown hashes.
 	pub fn on_peer_new_hashes(sync: &mut ChainSync, io: &mut SyncIo, peer_id: PeerId, r: &Rlp) -> Result<(), DownloaderImportError> {
 		if !sync.peers.get(&peer_id).map_or(false, |p| p.can_sync()) {
@@ -230,6 +257,14 @@ impl SyncHandler {
 				peer.latest_hash = h.clone();
 			}
 		}
+		if sync.state == SyncState::BeamPivot {
+			for (rh, rn) in hashes {
+				if let Some((pivot, total_difficulty)) = sync.beam_pivots.on_new_hash(peer_id, rh?, rn?) {
+					return SyncHandler::import_beam_pivot(sync, io, pivot, total_difficulty);
+				}
+			}
+			return Ok(());
+		}
 		if sync.state != SyncState::Idle {
 			trace!(target: "sync", "Ignoring new hashes since we're already downloading.");
 			let max = r.iter().take(MAX_NEW_HASHES).map(|item| item.val_at::<BlockNumber>(1).unwrap_or(0)).fold(0u64, cmp::max);
@@ -327,6 +362,7 @@ impl SyncHandler {
 				};
 				downloader.import_bodies(r, expected_blocks.as_slice())?;
 			}
+			io.report_peer(peer_id, PeerBehaviour::GoodDelivery);
 			sync.collect_blocks(io, block_set);
 			Ok(())
 		}
@@ -421,6 +457,7 @@ impl SyncHandler {
 			};
 			downloader.import_headers(io, r, expected_hash)?
 		};
+		io.report_peer(peer_id, PeerBehaviour::GoodDelivery);
 
 		if result == DownloadAction::Reset {
 			sync.reset_downloads(block_set);
@@ -467,6 +504,7 @@ impl SyncHandler {
 				};
 				downloader.import_receipts(r, expected_blocks.as_slice())?;
 			}
+			io.report_peer(peer_id, PeerBehaviour::GoodDelivery);
 			sync.collect_blocks(io, block_set);
 			Ok(())
 		}
@@ -494,6 +532,11 @@ impl SyncHandler {
 			trace!(target: "sync", "{}: Snapshot manifest version not supported: {}", peer_id, manifest.version);
 			return Err(DownloaderImportError::Invalid);
 		}
+
+		if let Err(e) = verify_chunk_metadata(&manifest) {
+			trace!(target: "sync", "{}: Snapshot manifest rejected: {}", peer_id, e);
+			return Err(DownloaderImportError::Invalid);
+		}
 		sync.snapshot.reset_to(&manifest, &keccak(manifest_rlp.as_raw()));
 		io.snapshot_service().begin_restore(manifest);
 		sync.state = SyncState::SnapshotData;
@@ -550,10 +593,12 @@ impl SyncHandler {
 			}
 			Err(()) => {
 				trace!(target: "sync", "{}: Got bad snapshot chunk", peer_id);
+				io.report_peer(peer_id, PeerBehaviour::UselessResponse);
 				io.disconnect_peer(peer_id);
 				return Ok(());
 			}
 		}
+		io.report_peer(peer_id, PeerBehaviour::GoodDelivery);
 
 		if sync.snapshot.is_complete() {
 			// wait for snapshot restoration process to complete
@@ -585,6 +630,7 @@ impl SyncHandler {
 			asking_snapshot_data: None,
 			snapshot_hash: if warp_protocol { Some(r.val_at(5)?) } else { None },
 			snapshot_number: if warp_protocol { Some(r.val_at(6)?) } else { None },
+			history_tail: if warp_protocol && protocol_version >= PAR_PROTOCOL_VERSION_5.0 { Some(r.val_at(7)?) } else { None },
 			block_set: None,
 		};
 
@@ -610,7 +656,7 @@ impl SyncHandler {
 		}
 
 		if false
-			|| (warp_protocol && (peer.protocol_version < PAR_PROTOCOL_VERSION_1.0 || peer.protocol_version > PAR_PROTOCOL_VERSION_3.0))
+			|| (warp_protocol && (peer.protocol_version < PAR_PROTOCOL_VERSION_1.0 || peer.protocol_version > PAR_PROTOCOL_VERSION_5.0))
 			|| (!warp_protocol && (peer.protocol_version < ETH_PROTOCOL_VERSION_62.0 || peer.protocol_version > ETH_PROTOCOL_VERSION_63.0))
 		{
 			trace!(target: "sync", "Peer {} unsupported eth protocol ({})", peer_id, peer.protocol_version);
@@ -719,6 +765,7 @@ mod tests {
 		get_dummy_block,
 		get_dummy_blocks,
 		get_dummy_hashes,
+		insert_dummy_peer,
 	};
 
 	#[test]
@@ -775,6 +822,30 @@ mod tests {
 		SyncHandler::on_peer_new_block(&mut sync, &mut io, 0, &block).expect("result to be ok");
 	}
 
+	#[test]
+	fn handles_beam_pivot() {
+		let mut client = TestBlockChainClient::new();
+		let config = ::SyncConfig { beam_sync: true, ..Default::default() };
+		let mut sync = ChainSync::new(config, &client, ::std::sync::Arc::new(::private_tx::NoopPrivateTxHandler));
+		for peer in 0..3 {
+			insert_dummy_peer(&mut sync, peer, H256::random());
+		}
+		assert_eq!(sync.state, SyncState::BeamPivot);
+
+		// the block is only used once several peers announced it.
+		let block_data = get_dummy_blocks(100, H256::random());
+		for peer in 0..3 {
+			assert!(client.beam_pivot.read().is_none());
+			let queue = RwLock::new(VecDeque::new());
+			let ss = TestSnapshotService::new();
+			let mut io = TestIo::new(&mut client, &ss, &queue, None);
+			SyncHandler::on_peer_new_block(&mut sync, &mut io, peer, &Rlp::new(&block_data)).expect("result to be ok");
+		}
+
+		let pivot_hash = keccak(Rlp::new(&block_data).at(0).unwrap().at(0).unwrap().as_raw());
+		assert_eq!(*client.beam_pivot.read(), Some((pivot_hash, U256::from(10000))));
+	}
+
 	#[test]
 	fn handles_peer_new_block_empty() {
 		let mut client = TestBlockChainClient::new();
@@ -103,16 +103,17 @@ use fastmap::H256FastMap;
 use parking_lot::RwLock;
 use bytes::Bytes;
 use rlp::{Rlp, RlpStream, DecoderError};
-use network::{self, PeerId, PacketId};
+use network::{self, PeerId, PacketId, PeerBehaviour};
 use ethcore::header::{BlockNumber};
 use ethcore::client::{BlockChainClient, BlockStatus, BlockId, BlockChainInfo, BlockQueueInfo};
-use ethcore::snapshot::{RestorationStatus};
+use ethcore::snapshot::{ManifestData, RestorationStatus};
 use sync_io::SyncIo;
 use super::{WarpSync, SyncConfig};
 use block_sync::{BlockDownloader, DownloadAction};
 use rand::Rng;
 use snapshot::{Snapshot};
 use api::{EthProtocolInfo as PeerInfoDigest, WARP_SYNC_PROTOCOL_ID};
+use beam::PivotCandidates;
 use private_tx::PrivateTxHandler;
 use transactions_stats::{TransactionsStats, Stats as TransactionStats};
 use transaction::UnverifiedTransaction;
@@ -136,6 +137,10 @@ pub const PAR_PROTOCOL_VERSION_1: (u8, u8) = (1, 0x15);
 pub const PAR_PROTOCOL_VERSION_2: (u8, u8) = (2, 0x16);
 /// 3 version of Parity protocol (private transactions messages added).
 pub const PAR_PROTOCOL_VERSION_3: (u8, u8) = (3, 0x18);
+/// 4 version of Parity protocol (snapshot chunks may use codecs other than snappy).
+pub const PAR_PROTOCOL_VERSION_4: (u8, u8) = (4, 0x18);
+/// 5 version of Parity protocol (status carries the first block with body and receipts).
+pub const PAR_PROTOCOL_VERSION_5: (u8, u8) = (5, 0x18);
 
 pub const MAX_BODIES_TO_SEND: usize = 256;
 pub const MAX_HEADERS_TO_SEND: usize = 512;
@@ -160,8 +165,8 @@ const TRANSACTIONS_PACKET: u8 = 0x02;
 pub const GET_BLOCK_HEADERS_PACKET: u8 = 0x03;
 pub const BLOCK_HEADERS_PACKET: u8 = 0x04;
 pub const GET_BLOCK_BODIES_PACKET: u8 = 0x05;
-const BLOCK_BODIES_PACKET: u8 = 0x06;
-const NEW_BLOCK_PACKET: u8 = 0x07;
+pub const BLOCK_BODIES_PACKET: u8 = 0x06;
+pub const NEW_BLOCK_PACKET: u8 = 0x07;
 
 pub const GET_NODE_DATA_PACKET: u8 = 0x0d;
 pub const NODE_DATA_PACKET: u8 = 0x0e;
@@ -206,6 +211,8 @@ pub enum SyncState {
 	Waiting,
 	/// Downloading blocks learned from `NewHashes` packet
 	NewBlocks,
+	/// Waiting for a new block announcement to use as the beam sync pivot
+	BeamPivot,
 }
 
 /// Syncing status and statistics
@@ -334,6 +341,8 @@ pub struct PeerInfo {
 	snapshot_hash: Option<H256>,
 	/// Best snapshot block number
 	snapshot_number: Option<BlockNumber>,
+	/// Earliest block whose body and receipts are served, if known
+	history_tail: Option<BlockNumber>,
 	/// Block set requested
 	block_set: Option<BlockSet>,
 }
@@ -413,6 +422,10 @@ pub struct ChainSync {
 	private_tx_handler: Arc<PrivateTxHandler>,
 	/// Enable warp sync.
 	warp_sync: WarpSync,
+	/// Enable beam sync.
+	beam_sync: bool,
+	/// Blocks announced while waiting for the beam sync pivot.
+	beam_pivots: PivotCandidates,
 }
 
 impl ChainSync {
@@ -420,7 +433,7 @@ impl ChainSync {
 	pub fn new(config: SyncConfig, chain: &BlockChainClient, private_tx_handler: Arc<PrivateTxHandler>) -> ChainSync {
 		let chain_info = chain.chain_info();
 		let best_block = chain.chain_info().best_block_number;
-		let state = ChainSync::get_init_state(config.warp_sync, chain);
+		let state = ChainSync::get_init_state(config.warp_sync, config.beam_sync, chain);
 
 		let mut sync = ChainSync {
 			state,
@@ -440,13 +453,18 @@ impl ChainSync {
 			transactions_stats: TransactionsStats::default(),
 			private_tx_handler,
 			warp_sync: config.warp_sync,
+			beam_sync: config.beam_sync,
+			beam_pivots: PivotCandidates::default(),
 		};
 		sync.update_targets(chain);
 		sync
 	}
 
-	fn get_init_state(warp_sync: WarpSync, chain: &BlockChainClient) -> SyncState {
+	fn get_init_state(warp_sync: WarpSync, beam_sync: bool, chain: &BlockChainClient) -> SyncState {
 		let best_block = chain.chain_info().best_block_number;
+		if beam_sync && best_block == 0 {
+			return SyncState::BeamPivot;
+		}
 		match warp_sync {
 			WarpSync::Enabled => SyncState::WaitingPeers,
 			WarpSync::OnlyAndAfter(block) if block > best_block => SyncState::WaitingPeers,
@@ -521,7 +539,7 @@ impl ChainSync {
 				}
 			}
 		}
-		self.state = state.unwrap_or_else(|| ChainSync::get_init_state(self.warp_sync, io.chain()));
+		self.state = state.unwrap_or_else(|| ChainSync::get_init_state(self.warp_sync, self.beam_sync, io.chain()));
 		// Reactivate peers only if some progress has been made
 		// since the last sync round of if starting fresh.
 		self.active_peers = self.peers.keys().cloned().collect();
@@ -715,13 +733,13 @@ impl ChainSync {
 			trace!(target: "sync", "Skipping deactivated peer {}", peer_id);
 			return;
 		}
-		let (peer_latest, peer_difficulty, peer_snapshot_number, peer_snapshot_hash) = {
+		let (peer_latest, peer_difficulty, peer_snapshot_number, peer_snapshot_hash, peer_history_tail) = {
 			if let Some(peer) = self.peers.get_mut(&peer_id) {
 				if peer.asking != PeerAsking::Nothing || !peer.can_sync() {
 					trace!(target: "sync", "Skipping busy peer {}", peer_id);
 					return;
 				}
-				(peer.latest_hash.clone(), peer.difficulty.clone(), peer.snapshot_number.as_ref().cloned().unwrap_or(0), peer.snapshot_hash.as_ref().cloned())
+				(peer.latest_hash.clone(), peer.difficulty.clone(), peer.snapshot_number.as_ref().cloned().unwrap_or(0), peer.snapshot_hash.as_ref().cloned(), peer.history_tail)
 			} else {
 				return;
 			}
@@ -767,7 +785,14 @@ impl ChainSync {
 					let equal_or_higher_difficulty = peer_difficulty.map_or(false, |pd| pd >= syncing_difficulty);
 
 					if force || equal_or_higher_difficulty {
-						if let Some(request) = self.old_blocks.as_mut().and_then(|d| d.request_blocks(io, num_active_peers)) {
+						// the peer must still serve bodies and receipts of the next old blocks.
+						let has_history = match (peer_history_tail, self.old_blocks.as_ref()) {
+							(Some(tail), Some(downloader)) => downloader.last_imported_block_number() + 1 >= tail,
+							_ => true,
+						};
+						if !has_history {
+							trace!(target: "sync", "peer {:?} has removed the history of old blocks", peer_id);
+						} else if let Some(request) = self.old_blocks.as_mut().and_then(|d| d.request_blocks(io, num_active_peers)) {
 							SyncRequester::request_blocks(self, io, peer_id, request, BlockSet::OldBlocks);
 							return;
 						}
@@ -809,7 +834,8 @@ impl ChainSync {
 				},
 				SyncState::SnapshotManifest | //already downloading from other peer
 					SyncState::Waiting |
-					SyncState::SnapshotWaiting => ()
+					SyncState::SnapshotWaiting |
+					SyncState::BeamPivot => ()
 			}
 		} else {
 			trace!(target: "sync", "Skipping peer {}, force={}, td={:?}, our td={}, state={:?}", peer_id, force, peer_difficulty, syncing_difficulty, self.state);
@@ -911,9 +937,10 @@ impl ChainSync {
 	fn send_status(&mut self, io: &mut SyncIo, peer: PeerId) -> Result<(), network::Error> {
 		let warp_protocol_version = io.protocol_version(&WARP_SYNC_PROTOCOL_ID, peer);
 		let warp_protocol = warp_protocol_version != 0;
+		let history_tail = warp_protocol_version >= PAR_PROTOCOL_VERSION_5.0;
 		let protocol = if warp_protocol { warp_protocol_version } else { ETH_PROTOCOL_VERSION_63.0 };
 		trace!(target: "sync", "Sending status to {}, protocol version {}", peer, protocol);
-		let mut packet = RlpStream::new_list(if warp_protocol { 7 } else { 5 });
+		let mut packet = RlpStream::new_list(if history_tail { 8 } else if warp_protocol { 7 } else { 5 });
 		let chain = io.chain().chain_info();
 		packet.append(&(protocol as u32));
 		packet.append(&self.network_id);
@@ -921,12 +948,15 @@ impl ChainSync {
 		packet.append(&chain.best_block_hash);
 		packet.append(&chain.genesis_hash);
 		if warp_protocol {
-			let manifest = io.snapshot_service().manifest();
+			let manifest = ChainSync::snapshot_manifest_for(io, peer);
 			let block_number = manifest.as_ref().map_or(0, |m| m.block_number);
 			let manifest_hash = manifest.map_or(H256::new(), |m| keccak(m.into_rlp()));
 			packet.append(&manifest_hash);
 			packet.append(&block_number);
 		}
+		if history_tail {
+			packet.append(&io.chain().pruning_info().earliest_history);
+		}
 		io.respond(STATUS_PACKET, packet.out())
 	}
 
@@ -946,6 +976,7 @@ impl ChainSync {
 			};
 			if timeout {
 				debug!(target:"sync", "Timeout {}", peer_id);
+				io.report_peer(*peer_id, PeerBehaviour::Timeout);
 				io.disconnect_peer(*peer_id);
 				aborting.push(*peer_id);
 			}
@@ -1078,6 +1109,19 @@ impl ChainSync {
 		peers
 	}
 
+	/// Local snapshot manifest, if the given peer's warp protocol version can restore from it.
+	/// Peers before `PAR_PROTOCOL_VERSION_4` only understand snappy-compressed chunks.
+	fn snapshot_manifest_for(io: &SyncIo, peer_id: PeerId) -> Option<ManifestData> {
+		let warp_protocol_version = io.protocol_version(&WARP_SYNC_PROTOCOL_ID, peer_id);
+		io.snapshot_service().manifest().and_then(|manifest| {
+			if warp_protocol_version >= PAR_PROTOCOL_VERSION_4.0 || manifest.is_snappy() {
+				Some(manifest)
+			} else {
+				None
+			}
+		})
+	}
+
 	fn get_consensus_peers(&self) -> Vec<PeerId> {
 		self.peers.iter().filter_map(|(id, p)| if p.protocol_version >= PAR_PROTOCOL_VERSION_2.0 { Some(*id) } else { None }).collect()
 	}
@@ -1291,12 +1335,19 @@ pub mod tests {
 				confirmation: super::ForkConfirmation::Confirmed,
 				snapshot_number: None,
 				snapshot_hash: None,
+				history_tail: None,
 				asking_snapshot_data: None,
 				block_set: None,
 			});
 
 	}
 
+	pub fn set_peer_asking(sync: &mut ChainSync, peer_id: PeerId, asking: PeerAsking, ask_time: Instant) {
+		let peer = sync.peers.get_mut(&peer_id).expect("peer was inserted by the test; qed");
+		peer.asking = asking;
+		peer.ask_time = ask_time;
+	}
+
 	#[test]
 	fn finds_lagging_peers() {
 		let mut client = TestBlockChainClient::new();
@@ -425,6 +425,7 @@ mod tests {
 				confirmation: ForkConfirmation::Confirmed,
 				snapshot_number: None,
 				snapshot_hash: None,
+				history_tail: None,
 				asking_snapshot_data: None,
 				block_set: None,
 			});
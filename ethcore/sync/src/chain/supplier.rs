@@ -246,7 +246,7 @@ impl SyncSupplier {
 			debug!(target: "warp", "Invalid GetSnapshotManifest request, ignoring.");
 			return Ok(None);
 		}
-		let rlp = match io.snapshot_service().manifest() {
+		let rlp = match ChainSync::snapshot_manifest_for(io, peer_id) {
 			Some(manifest) => {
 				trace!(target: "warp", "{} <- SnapshotManifest", peer_id);
 				let mut rlp = RlpStream::new_list(1);
@@ -31,6 +31,7 @@ extern crate ethcore;
 extern crate ethereum_types;
 extern crate env_logger;
 extern crate fastmap;
+extern crate kvdb;
 extern crate rand;
 extern crate parking_lot;
 extern crate rlp;
@@ -53,6 +54,7 @@ extern crate heapsize;
 #[macro_use]
 extern crate trace_time;
 
+mod beam;
 mod chain;
 mod blocks;
 mod block_sync;
@@ -71,5 +73,6 @@ mod api;
 pub use api::*;
 pub use chain::{SyncStatus, SyncState};
 pub use devp2p::validate_node_url;
-pub use network::{NonReservedPeerMode, Error, ErrorKind, ConnectionFilter, ConnectionDirection};
+pub use network::{NonReservedPeerMode, Error, ErrorKind, ConnectionFilter, ConnectionDirection,
+	NodeReputation, PeerBehaviour, TrafficStats, NatType};
 pub use private_tx::{PrivateTxHandler, NoopPrivateTxHandler, SimplePrivateTxHandler};
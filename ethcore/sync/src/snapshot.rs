@@ -197,6 +197,7 @@ mod test {
 			state_root: H256::new(),
 			block_number: 42,
 			block_hash: H256::new(),
+			metadata: None,
 		};
 		let mhash = keccak(manifest.clone().into_rlp());
 		(manifest, mhash, state_chunks, block_chunks)
@@ -14,10 +14,17 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::VecDeque;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use ethcore::client::{TestBlockChainClient, BlockChainClient, BlockId, EachBlockWith, ChainInfo, BlockInfo};
-use chain::{SyncState};
+use network::PeerBehaviour;
+use parking_lot::RwLock;
+use rlp::RlpStream;
+use chain::{SyncState, PeerAsking, BLOCK_BODIES_PACKET, NEW_BLOCK_PACKET};
+use chain::tests::{dummy_sync_with_peer, get_dummy_block, set_peer_asking};
 use super::helpers::*;
+use super::snapshot::TestSnapshotService;
 use {SyncConfig, WarpSync};
 
 #[test]
@@ -262,3 +269,50 @@ fn disconnect_on_unrelated_chain() {
 	net.sync();
 	assert_eq!(net.disconnect_events, vec![(0, 0)]);
 }
+
+#[test]
+fn reports_peer_sending_invalid_block() {
+	let mut client = TestBlockChainClient::new();
+	client.add_blocks(10, EachBlockWith::Nothing);
+	let queue = RwLock::new(VecDeque::new());
+	let ss = TestSnapshotService::new();
+	let mut sync = dummy_sync_with_peer(client.block_hash_delta_minus(5), &client);
+	let mut io = TestIo::new(&client, &ss, &queue, None);
+
+	// a new block packet lacking the total difficulty
+	let block = get_dummy_block(11, client.chain_info().best_block_hash);
+	sync.on_packet(&mut io, 0, NEW_BLOCK_PACKET, &block);
+
+	assert_eq!(io.reports, vec![(0, PeerBehaviour::InvalidBlock)]);
+}
+
+#[test]
+fn reports_peer_sending_useless_response() {
+	let mut client = TestBlockChainClient::new();
+	client.add_blocks(10, EachBlockWith::Nothing);
+	let queue = RwLock::new(VecDeque::new());
+	let ss = TestSnapshotService::new();
+	let mut sync = dummy_sync_with_peer(client.block_hash_delta_minus(5), &client);
+	set_peer_asking(&mut sync, 0, PeerAsking::BlockBodies, Instant::now());
+	let mut io = TestIo::new(&client, &ss, &queue, None);
+
+	let bodies = RlpStream::new_list(0).out();
+	sync.on_packet(&mut io, 0, BLOCK_BODIES_PACKET, &bodies);
+
+	assert_eq!(io.reports, vec![(0, PeerBehaviour::UselessResponse)]);
+}
+
+#[test]
+fn reports_peer_timing_out() {
+	let mut client = TestBlockChainClient::new();
+	client.add_blocks(10, EachBlockWith::Nothing);
+	let queue = RwLock::new(VecDeque::new());
+	let ss = TestSnapshotService::new();
+	let mut sync = dummy_sync_with_peer(client.block_hash_delta_minus(5), &client);
+	set_peer_asking(&mut sync, 0, PeerAsking::BlockHeaders, Instant::now() - Duration::from_secs(60));
+	let mut io = TestIo::new(&client, &ss, &queue, None);
+
+	sync.maintain_peers(&mut io);
+
+	assert_eq!(io.reports, vec![(0, PeerBehaviour::Timeout)]);
+}
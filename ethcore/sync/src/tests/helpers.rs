@@ -20,7 +20,7 @@ use std::time::Duration;
 use ethereum_types::H256;
 use parking_lot::{RwLock, Mutex};
 use bytes::Bytes;
-use network::{self, PeerId, ProtocolId, PacketId, SessionInfo};
+use network::{self, PeerId, ProtocolId, PacketId, SessionInfo, PeerBehaviour};
 use tests::snapshot::*;
 use ethcore::client::{TestBlockChainClient, BlockChainClient, Client as EthcoreClient,
 	ClientConfig, ChainNotify, ChainRoute, ChainMessageType, ClientIoMessage};
@@ -57,6 +57,7 @@ pub struct TestIo<'p, C> where C: FlushingBlockChainClient, C: 'p {
 	pub to_disconnect: HashSet<PeerId>,
 	pub packets: Vec<TestPacket>,
 	pub peers_info: HashMap<PeerId, String>,
+	pub reports: Vec<(PeerId, PeerBehaviour)>,
 	overlay: RwLock<HashMap<BlockNumber, Bytes>>,
 }
 
@@ -71,6 +72,7 @@ impl<'p, C> TestIo<'p, C> where C: FlushingBlockChainClient, C: 'p {
 			overlay: RwLock::new(HashMap::new()),
 			packets: Vec::new(),
 			peers_info: HashMap::new(),
+			reports: Vec::new(),
 		}
 	}
 }
@@ -90,6 +92,10 @@ impl<'p, C> SyncIo for TestIo<'p, C> where C: FlushingBlockChainClient, C: 'p {
 		self.to_disconnect.insert(peer_id);
 	}
 
+	fn report_peer(&mut self, peer_id: PeerId, behaviour: PeerBehaviour) {
+		self.reports.push((peer_id, behaviour));
+	}
+
 	fn is_expired(&self) -> bool {
 		false
 	}
@@ -31,6 +31,8 @@ pub enum BlockId {
 	Earliest,
 	/// Latest mined block.
 	Latest,
+	/// Latest finalized block within canon blockchain.
+	Finalized,
 }
 
 /// Uniquely identifies transaction.
@@ -17,9 +17,116 @@
 //! Snapshot manifest type definition
 
 use ethereum_types::H256;
-use rlp::{Rlp, RlpStream, DecoderError};
+use rlp::{Rlp, RlpStream, Encodable, Decodable, DecoderError};
 use bytes::Bytes;
 
+/// Compression codec used for snapshot chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkCodec {
+	/// Snappy compression. Understood by all peers.
+	Snappy,
+	/// Zstandard compression. Better ratio, requires `par` protocol v4.
+	Zstd,
+}
+
+impl Default for ChunkCodec {
+	fn default() -> Self {
+		ChunkCodec::Snappy
+	}
+}
+
+impl Encodable for ChunkCodec {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		let id: u8 = match *self {
+			ChunkCodec::Snappy => 0,
+			ChunkCodec::Zstd => 1,
+		};
+		s.append(&id);
+	}
+}
+
+impl Decodable for ChunkCodec {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		match rlp.as_val::<u8>()? {
+			0 => Ok(ChunkCodec::Snappy),
+			1 => Ok(ChunkCodec::Zstd),
+			_ => Err(DecoderError::Custom("Unknown chunk codec")),
+		}
+	}
+}
+
+/// Verification metadata of a single chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkInfo {
+	/// Size of the chunk after decompression.
+	pub uncompressed_size: u64,
+	/// Hashed keys of the first and the last account in a state chunk.
+	/// `None` for block chunks.
+	pub account_range: Option<(H256, H256)>,
+}
+
+impl Encodable for ChunkInfo {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		match self.account_range {
+			Some((ref first, ref last)) => {
+				s.begin_list(3);
+				s.append(&self.uncompressed_size);
+				s.append(first);
+				s.append(last);
+			},
+			None => {
+				s.begin_list(1);
+				s.append(&self.uncompressed_size);
+			},
+		}
+	}
+}
+
+impl Decodable for ChunkInfo {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		let account_range = match rlp.item_count()? {
+			1 => None,
+			3 => Some((rlp.val_at(1)?, rlp.val_at(2)?)),
+			_ => return Err(DecoderError::RlpIncorrectListLen),
+		};
+
+		Ok(ChunkInfo {
+			uncompressed_size: rlp.val_at(0)?,
+			account_range: account_range,
+		})
+	}
+}
+
+/// Per-chunk metadata carried by extended manifests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkMetadata {
+	/// Codec all chunks are compressed with.
+	pub codec: ChunkCodec,
+	/// Metadata of state chunks, in the order of `ManifestData::state_hashes`.
+	pub state_chunks: Vec<ChunkInfo>,
+	/// Metadata of block chunks, in the order of `ManifestData::block_hashes`.
+	pub block_chunks: Vec<ChunkInfo>,
+}
+
+impl Encodable for ChunkMetadata {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(3);
+		s.append(&self.codec);
+		s.append_list(&self.state_chunks);
+		s.append_list(&self.block_chunks);
+	}
+}
+
+impl Decodable for ChunkMetadata {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		Ok(ChunkMetadata {
+			codec: rlp.val_at(0)?,
+			state_chunks: rlp.list_at(1)?,
+			block_chunks: rlp.list_at(2)?,
+		})
+	}
+}
+
 /// Manifest data.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ManifestData {
@@ -35,18 +142,24 @@ pub struct ManifestData {
 	pub block_number: u64,
 	/// Block hash this snapshot was taken at.
 	pub block_hash: H256,
+	/// Chunk codec and per-chunk verification data.
+	/// Manifests without it use snappy and can only be checked by hash.
+	pub metadata: Option<ChunkMetadata>,
 }
 
 impl ManifestData {
 	/// Encode the manifest data to rlp.
 	pub fn into_rlp(self) -> Bytes {
-		let mut stream = RlpStream::new_list(6);
+		let mut stream = RlpStream::new_list(if self.metadata.is_some() { 7 } else { 6 });
 		stream.append(&self.version);
 		stream.append_list(&self.state_hashes);
 		stream.append_list(&self.block_hashes);
 		stream.append(&self.state_root);
 		stream.append(&self.block_number);
 		stream.append(&self.block_hash);
+		if let Some(ref metadata) = self.metadata {
+			stream.append(metadata);
+		}
 
 		stream.out()
 	}
@@ -54,7 +167,8 @@ impl ManifestData {
 	/// Try to restore manifest data from raw bytes, interpreted as RLP.
 	pub fn from_rlp(raw: &[u8]) -> Result<Self, DecoderError> {
 		let decoder = Rlp::new(raw);
-		let (start, version) = if decoder.item_count()? == 5 {
+		let item_count = decoder.item_count()?;
+		let (start, version) = if item_count == 5 {
 			(0, 1)
 		} else {
 			(1, decoder.val_at(0)?)
@@ -65,6 +179,11 @@ impl ManifestData {
 		let state_root: H256 = decoder.val_at(start + 2)?;
 		let block_number: u64 = decoder.val_at(start + 3)?;
 		let block_hash: H256 = decoder.val_at(start + 4)?;
+		let metadata: Option<ChunkMetadata> = if item_count > start + 5 {
+			Some(decoder.val_at(start + 5)?)
+		} else {
+			None
+		};
 
 		Ok(ManifestData {
 			version: version,
@@ -73,6 +192,58 @@ impl ManifestData {
 			state_root: state_root,
 			block_number: block_number,
 			block_hash: block_hash,
+			metadata: metadata,
 		})
 	}
+
+	/// Whether the chunks of this snapshot can be understood by peers which only support snappy.
+	pub fn is_snappy(&self) -> bool {
+		self.metadata.as_ref().map_or(true, |m| m.codec == ChunkCodec::Snappy)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn manifest(metadata: Option<ChunkMetadata>) -> ManifestData {
+		ManifestData {
+			version: 2,
+			state_hashes: vec![H256::from(1), H256::from(2)],
+			block_hashes: vec![H256::from(3)],
+			state_root: H256::from(4),
+			block_number: 1234,
+			block_hash: H256::from(5),
+			metadata: metadata,
+		}
+	}
+
+	#[test]
+	fn legacy_manifest_roundtrip() {
+		let manifest = manifest(None);
+		let raw = manifest.clone().into_rlp();
+		assert_eq!(Rlp::new(&raw).item_count().unwrap(), 6);
+		assert_eq!(ManifestData::from_rlp(&raw).unwrap(), manifest);
+	}
+
+	#[test]
+	fn extended_manifest_roundtrip() {
+		let manifest = manifest(Some(ChunkMetadata {
+			codec: ChunkCodec::Zstd,
+			state_chunks: vec![
+				ChunkInfo { uncompressed_size: 100, account_range: Some((H256::from(10), H256::from(20))) },
+				ChunkInfo { uncompressed_size: 200, account_range: Some((H256::from(21), H256::from(30))) },
+			],
+			block_chunks: vec![ChunkInfo { uncompressed_size: 300, account_range: None }],
+		}));
+
+		let raw = manifest.clone().into_rlp();
+		assert_eq!(ManifestData::from_rlp(&raw).unwrap(), manifest);
+		assert!(!manifest.is_snappy());
+
+		// decoders predating the metadata only look at the first six items.
+		let legacy = Rlp::new(&raw);
+		assert_eq!(legacy.val_at::<u64>(0).unwrap(), 2);
+		assert_eq!(legacy.val_at::<H256>(5).unwrap(), H256::from(5));
+	}
 }
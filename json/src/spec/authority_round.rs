@@ -55,6 +55,13 @@ pub struct AuthorityRoundParams {
 	pub empty_steps_transition: Option<Uint>,
 	/// Maximum number of accepted empty steps.
 	pub maximum_empty_steps: Option<Uint>,
+	/// Address of the randomness contract validators commit and reveal secrets to.
+	pub randomness_contract_address: Option<Address>,
+	/// Block at which the block gas limit contract should start being used.
+	pub block_gas_limit_contract_transition: Option<Uint>,
+	/// Block gas limit contract address (setting it overrides the gas limit adjustment
+	/// by the block author's gas floor and ceil targets).
+	pub block_gas_limit_contract_address: Option<Address>,
 }
 
 /// Authority engine deserialization.
@@ -85,7 +92,10 @@ mod tests {
 				"validateStepTransition": 150,
 				"blockReward": 5000000,
 				"maximumUncleCountTransition": 10000000,
-				"maximumUncleCount": 5
+				"maximumUncleCount": 5,
+				"randomnessContractAddress": "0x2000000000000000000000000000000000000001",
+				"blockGasLimitContractTransition": 50,
+				"blockGasLimitContractAddress": "0x2000000000000000000000000000000000000002"
 			}
 		}"#;
 
@@ -96,6 +106,9 @@ mod tests {
 		assert_eq!(deserialized.params.immediate_transitions, None);
 		assert_eq!(deserialized.params.maximum_uncle_count_transition, Some(Uint(10_000_000.into())));
 		assert_eq!(deserialized.params.maximum_uncle_count, Some(Uint(5.into())));
+		assert_eq!(deserialized.params.randomness_contract_address, Some(Address(H160::from("0x2000000000000000000000000000000000000001"))));
+		assert_eq!(deserialized.params.block_gas_limit_contract_transition, Some(Uint(50.into())));
+		assert_eq!(deserialized.params.block_gas_limit_contract_address, Some(Address(H160::from("0x2000000000000000000000000000000000000002"))));
 
 	}
 }
@@ -16,7 +16,9 @@
 
 //! Engine deserialization.
 
-use super::{Ethash, BasicAuthority, AuthorityRound, Tendermint, NullEngine, InstantSeal};
+use std::collections::BTreeMap;
+use uint::Uint;
+use super::{Ethash, BasicAuthority, AuthorityRound, Tendermint, Clique, NullEngine, InstantSeal};
 
 /// Engine deserialization.
 #[derive(Debug, PartialEq, Deserialize)]
@@ -34,12 +36,18 @@ pub enum Engine {
 	/// AuthorityRound engine.
 	AuthorityRound(AuthorityRound),
 	/// Tendermint engine.
-	Tendermint(Tendermint)
+	Tendermint(Tendermint),
+	/// Clique engine.
+	Clique(Clique),
+	/// A map of activation blocks for each engine.
+	Multi(BTreeMap<Uint, Engine>),
 }
 
 #[cfg(test)]
 mod tests {
 	use serde_json;
+	use ethereum_types::U256;
+	use uint::Uint;
 	use spec::Engine;
 
 	#[test]
@@ -148,5 +156,59 @@ mod tests {
 			Engine::Tendermint(_) => {}, // Tendermint is unit tested in its own file.
 			_ => panic!(),
 		};
+
+		let s = r#"{
+			"clique": {
+				"params": {
+					"period": 15,
+					"epoch": 30000
+				}
+			}
+		}"#;
+		let deserialized: Engine = serde_json::from_str(s).unwrap();
+		match deserialized {
+			Engine::Clique(_) => {}, // Clique is unit tested in its own file.
+			_ => panic!(),
+		};
+
+		let s = r#"{
+			"multi": {
+				"0": {
+					"Ethash": {
+						"params": {
+							"minimumDifficulty": "0x020000",
+							"difficultyBoundDivisor": "0x0800",
+							"durationLimit": "0x0d",
+							"blockReward": "0x4563918244F40000"
+						}
+					}
+				},
+				"100": {
+					"authorityRound": {
+						"params": {
+							"stepDuration": 5,
+							"validators": {
+								"list": ["0xc6d9d2cd449a754c494264e1809c50e34d64562b"]
+							}
+						}
+					}
+				}
+			}
+		}"#;
+		let deserialized: Engine = serde_json::from_str(s).unwrap();
+		match deserialized {
+			Engine::Multi(ref map) => {
+				assert_eq!(map.len(), 2);
+				match map[&Uint(U256::from(0))] {
+					Engine::Ethash(_) => {},
+					_ => panic!(),
+				}
+				match map[&Uint(U256::from(100))] {
+					Engine::AuthorityRound(_) => {},
+					_ => panic!(),
+				}
+			},
+			_ => panic!(),
+		};
 	}
 }
@@ -93,6 +93,8 @@ pub struct EthashParams {
 	pub expip2_transition: Option<Uint>,
 	/// EXPIP-2 duration limit
 	pub expip2_duration_limit: Option<Uint>,
+	/// Block to transition to ProgPoW
+	pub progpow_transition: Option<Uint>,
 }
 
 /// Ethash engine deserialization.
@@ -146,7 +148,8 @@ mod tests {
 				"difficultyHardforkTransition": "0x59d9",
 				"difficultyHardforkBoundDivisor": "0x0200",
 				"bombDefuseTransition": "0x41",
-				"eip100bTransition": "0x42"
+				"eip100bTransition": "0x42",
+				"progpowTransition": "0x1000"
 			}
 		}"#;
 
@@ -197,6 +200,7 @@ mod tests {
 				ecip1017_era_rounds: None,
 				expip2_transition: None,
 				expip2_duration_limit: None,
+				progpow_transition: Some(Uint(U256::from(0x1000))),
 				difficulty_bomb_delays: None,
 			}
 		});
@@ -236,6 +240,7 @@ mod tests {
 				ecip1017_era_rounds: None,
 				expip2_transition: None,
 				expip2_duration_limit: None,
+				progpow_transition: None,
 				difficulty_bomb_delays: None,
 			}
 		});
@@ -16,6 +16,8 @@
 
 //! Instant seal engine params deserialization.
 
+use uint::Uint;
+
 /// Instant seal engine params deserialization.
 #[derive(Debug, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -23,6 +25,8 @@ pub struct InstantSealParams {
 	/// Whether to enable millisecond timestamp.
 	#[serde(default)]
 	pub millisecond_timestamp: bool,
+	/// Seal a block every given number of seconds, even if it's empty.
+	pub block_period: Option<Uint>,
 }
 
 /// Instant seal engine descriptor.
@@ -29,6 +29,7 @@ pub mod validator_set;
 pub mod basic_authority;
 pub mod authority_round;
 pub mod tendermint;
+pub mod clique;
 pub mod null_engine;
 pub mod instant_seal;
 pub mod hardcoded_sync;
@@ -46,6 +47,7 @@ pub use self::validator_set::ValidatorSet;
 pub use self::basic_authority::{BasicAuthority, BasicAuthorityParams};
 pub use self::authority_round::{AuthorityRound, AuthorityRoundParams};
 pub use self::tendermint::{Tendermint, TendermintParams};
+pub use self::clique::{Clique, CliqueParams};
 pub use self::null_engine::{NullEngine, NullEngineParams};
 pub use self::instant_seal::{InstantSeal, InstantSealParams};
 pub use self::hardcoded_sync::HardcodedSync;
@@ -16,6 +16,7 @@
 
 use std::str::{FromStr, from_utf8};
 use std::{io, fs};
+use std::path::Path;
 use std::io::{BufReader, BufRead};
 use std::time::{Instant, Duration};
 use std::thread::sleep;
@@ -24,16 +25,22 @@ use rustc_hex::FromHex;
 use hash::{keccak, KECCAK_NULL_RLP};
 use ethereum_types::{U256, H256, Address};
 use bytes::ToPretty;
-use rlp::PayloadInfo;
+use rlp::{PayloadInfo, RlpStream};
 use ethcore::account_provider::AccountProvider;
 use ethcore::client::{Mode, DatabaseCompactionProfile, VMType, Nonce, Balance, BlockChainClient, BlockId, BlockInfo, ImportBlock};
 use ethcore::error::{ImportErrorKind, ErrorKind as EthcoreErrorKind, Error as EthcoreError};
+use ethcore::header::Header;
+use ethcore::integrity;
 use ethcore::miner::Miner;
+use ethcore::prune;
+use ethcore::spec::Spec;
+use ethcore::state_import;
 use ethcore::verification::queue::VerifierSettings;
 use ethcore::verification::queue::kind::blocks::Unverified;
 use ethcore_service::ClientService;
 use cache::CacheConfig;
 use informant::{Informant, FullNodeInformantData, MillisecondDuration};
+use journaldb::Algorithm;
 use params::{SpecType, Pruning, Switch, tracing_switch_to_bool, fatdb_switch_to_bool};
 use helpers::{to_client_config, execute_upgrades};
 use dir::Directories;
@@ -68,9 +75,13 @@ impl FromStr for DataFormat {
 #[derive(Debug, PartialEq)]
 pub enum BlockchainCmd {
 	Kill(KillBlockchain),
+	Verify(VerifyBlockchain),
+	Prune(PruneBlockchain),
+	Backup(BackupBlockchain),
 	Import(ImportBlockchain),
 	Export(ExportBlockchain),
 	ExportState(ExportState),
+	ImportState(ImportState),
 }
 
 #[derive(Debug, PartialEq)]
@@ -80,6 +91,36 @@ pub struct KillBlockchain {
 	pub pruning: Pruning,
 }
 
+#[derive(Debug, PartialEq)]
+pub struct VerifyBlockchain {
+	pub spec: SpecType,
+	pub cache_config: CacheConfig,
+	pub dirs: Directories,
+	pub pruning: Pruning,
+	pub compaction: DatabaseCompactionProfile,
+	pub check_state: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PruneBlockchain {
+	pub spec: SpecType,
+	pub cache_config: CacheConfig,
+	pub dirs: Directories,
+	pub pruning: Pruning,
+	pub pruning_history: u64,
+	pub compaction: DatabaseCompactionProfile,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BackupBlockchain {
+	pub spec: SpecType,
+	pub cache_config: CacheConfig,
+	pub dirs: Directories,
+	pub pruning: Pruning,
+	pub compaction: DatabaseCompactionProfile,
+	pub path: String,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct ImportBlockchain {
 	pub spec: SpecType,
@@ -141,9 +182,23 @@ pub struct ExportState {
 	pub max_round_blocks_to_import: usize,
 }
 
+#[derive(Debug, PartialEq)]
+pub struct ImportState {
+	pub spec: SpecType,
+	pub cache_config: CacheConfig,
+	pub dirs: Directories,
+	pub pruning: Pruning,
+	pub compaction: DatabaseCompactionProfile,
+	pub file_path: Option<String>,
+	pub output_path: Option<String>,
+}
+
 pub fn execute(cmd: BlockchainCmd) -> Result<(), String> {
 	match cmd {
 		BlockchainCmd::Kill(kill_cmd) => kill_db(kill_cmd),
+		BlockchainCmd::Verify(verify_cmd) => verify_db(verify_cmd),
+		BlockchainCmd::Prune(prune_cmd) => prune_db(prune_cmd),
+		BlockchainCmd::Backup(backup_cmd) => backup_db(backup_cmd),
 		BlockchainCmd::Import(import_cmd) => {
 			if import_cmd.light {
 				execute_import_light(import_cmd)
@@ -153,6 +208,7 @@ pub fn execute(cmd: BlockchainCmd) -> Result<(), String> {
 		}
 		BlockchainCmd::Export(export_cmd) => execute_export(export_cmd),
 		BlockchainCmd::ExportState(export_cmd) => execute_export_state(export_cmd),
+		BlockchainCmd::ImportState(import_cmd) => execute_import_state(import_cmd),
 	}
 }
 
@@ -709,6 +765,103 @@ fn execute_export_state(cmd: ExportState) -> Result<(), String> {
 	Ok(())
 }
 
+fn execute_import_state(cmd: ImportState) -> Result<(), String> {
+	let spec = cmd.spec.spec(&cmd.dirs.cache)?;
+	let db_dirs = cmd.dirs.database(spec.genesis_header().hash(), None, spec.data_dir.clone());
+	let user_defaults = UserDefaults::load(&db_dirs.user_defaults_path())?;
+	let algorithm = cmd.pruning.to_algorithm(&user_defaults);
+
+	// the database is named after the genesis hash, which is only known once the state is built.
+	let staging_path = db_dirs.spec_root_path().join("import-state");
+	if staging_path.exists() {
+		fs::remove_dir_all(&staging_path).map_err(|e| format!("Error removing {}: {:?}", staging_path.display(), e))?;
+	}
+	db::migrate(&staging_path, &cmd.compaction).map_err(|e| format!("{}", e))?;
+
+	info!("Importing state into a {} database", algorithm);
+	let imported = match build_state(&staging_path, &cmd, algorithm) {
+		Ok(imported) => imported,
+		Err(e) => {
+			let _ = fs::remove_dir_all(&staging_path);
+			return Err(e);
+		}
+	};
+
+	let mut genesis = spec.genesis_header();
+	genesis.set_state_root(imported.root);
+	let target_dirs = cmd.dirs.database(genesis.hash(), None, spec.data_dir.clone());
+	let target_path = target_dirs.db_path(algorithm);
+	if target_path.exists() {
+		let _ = fs::remove_dir_all(&staging_path);
+		return Err(format!("Database already exists at {}. Remove it with `parity db kill` first.", target_path.display()));
+	}
+	fs::create_dir_all(target_dirs.db_root_path())
+		.and_then(|_| fs::rename(&staging_path, &target_path))
+		.map_err(|e| format!("Failed to move database to {}: {:?}", target_path.display(), e))?;
+
+	let fragment = genesis_json(&genesis);
+	match cmd.output_path {
+		Some(path) => fs::File::create(&path)
+			.and_then(|mut file| io::Write::write_all(&mut file, fragment.as_bytes()))
+			.map_err(|_| format!("Cannot write to file given: {}", path))?,
+		None => println!("{}", fragment),
+	}
+
+	info!("Imported {} accounts with state root {:x}. Use the printed genesis in the chain specification to start on genesis {:x}.", imported.accounts, imported.root, genesis.hash());
+	Ok(())
+}
+
+fn build_state(path: &Path, cmd: &ImportState, algorithm: Algorithm) -> Result<state_import::ImportedState, String> {
+	let instream: Box<io::Read> = match cmd.file_path {
+		Some(ref f) => Box::new(fs::File::open(f).map_err(|_| format!("Cannot open given file: {}", f))?),
+		None => Box::new(io::stdin()),
+	};
+
+	let db = db::open_db(&path.join("db").to_str().expect("DB path could not be converted to string."), &cmd.cache_config, &cmd.compaction)
+		.map_err(|e| format!("Failed to open database: {:?}", e))?;
+
+	state_import::import_state(BufReader::new(instream), db, algorithm)
+		.map_err(|e| format!("Failed to import state: {}", e))
+}
+
+/// Genesis section of a chain specification for the given header, with the seal in its generic form.
+fn genesis_json(header: &Header) -> String {
+	let mut seal = RlpStream::new_list(header.seal().len());
+	for field in header.seal() {
+		seal.append_raw(field, 1);
+	}
+
+	format!(r#"{{
+	"genesis": {{
+		"seal": {{
+			"generic": "0x{}"
+		}},
+		"difficulty": "0x{:x}",
+		"author": "0x{:x}",
+		"timestamp": "0x{:x}",
+		"parentHash": "0x{:x}",
+		"extraData": "0x{}",
+		"gasLimit": "0x{:x}",
+		"gasUsed": "0x{:x}",
+		"transactionsRoot": "0x{:x}",
+		"receiptsRoot": "0x{:x}",
+		"stateRoot": "0x{:x}"
+	}}
+}}"#,
+		seal.out().to_hex(),
+		header.difficulty(),
+		header.author(),
+		header.timestamp(),
+		header.parent_hash(),
+		header.extra_data().to_hex(),
+		header.gas_limit(),
+		header.gas_used(),
+		header.transactions_root(),
+		header.receipts_root(),
+		header.state_root(),
+	)
+}
+
 pub fn kill_db(cmd: KillBlockchain) -> Result<(), String> {
 	let spec = cmd.spec.spec(&cmd.dirs.cache)?;
 	let genesis_hash = spec.genesis_header().hash();
@@ -724,6 +877,144 @@ pub fn kill_db(cmd: KillBlockchain) -> Result<(), String> {
 	Ok(())
 }
 
+pub fn verify_db(cmd: VerifyBlockchain) -> Result<(), String> {
+	let spec = cmd.spec.spec(&cmd.dirs.cache)?;
+	let genesis_hash = spec.genesis_header().hash();
+	let db_dirs = cmd.dirs.database(genesis_hash, None, spec.data_dir.clone());
+	let user_defaults = UserDefaults::load(&db_dirs.user_defaults_path())?;
+	let algorithm = cmd.pruning.to_algorithm(&user_defaults);
+	let client_path = db_dirs.client_path(algorithm);
+	if !client_path.exists() {
+		return Err(format!("Database not found at {}", client_path.display()));
+	}
+
+	let db = db::open_db(&client_path.to_str().expect("DB path could not be converted to string."), &cmd.cache_config, &cmd.compaction)
+		.map_err(|e| format!("Failed to open database: {:?}", e))?;
+
+	info!("Checking database at {}", client_path.display());
+	let state_algorithm = match cmd.check_state {
+		true => Some(algorithm),
+		false => None,
+	};
+	let report = integrity::check_database(db, &spec, state_algorithm)
+		.map_err(|e| format!("Database is inconsistent. {}", e))?;
+
+	match report.accounts {
+		Some(accounts) => info!("Database is consistent: checked {} blocks and {} accounts.", report.blocks, accounts),
+		None => info!("Database is consistent: checked {} blocks.", report.blocks),
+	}
+	Ok(())
+}
+
+pub fn prune_db(cmd: PruneBlockchain) -> Result<(), String> {
+	let spec = cmd.spec.spec(&cmd.dirs.cache)?;
+	let genesis_hash = spec.genesis_header().hash();
+	let db_dirs = cmd.dirs.database(genesis_hash, None, spec.data_dir.clone());
+	let user_defaults_path = db_dirs.user_defaults_path();
+	let mut user_defaults = UserDefaults::load(&user_defaults_path)?;
+	let algorithm = match cmd.pruning {
+		Pruning::Specific(algorithm) => algorithm,
+		Pruning::Auto => Algorithm::OverlayRecent,
+	};
+
+	let source_path = db_dirs.client_path(Algorithm::Archive);
+	if !source_path.exists() {
+		return Err(format!("Archive database not found at {}", source_path.display()));
+	}
+	let target_path = db_dirs.client_path(algorithm);
+	if target_path.exists() {
+		return Err(format!("Database already exists at {}. Remove it with `parity db kill --pruning={}` first.", target_path.display(), algorithm));
+	}
+
+	execute_upgrades(&cmd.dirs.base, &db_dirs, Algorithm::Archive, &cmd.compaction)?;
+	execute_upgrades(&cmd.dirs.base, &db_dirs, algorithm, &cmd.compaction)?;
+
+	// the database is converted next to the target and moved into place once complete.
+	let temp_path = db_dirs.db_path(algorithm).join("db-prune");
+	if temp_path.exists() {
+		fs::remove_dir_all(&temp_path).map_err(|e| format!("Failed to remove {}: {}", temp_path.display(), e))?;
+	}
+
+	info!("Converting archive database to {} pruning, keeping the last {} states", algorithm, cmd.pruning_history);
+	let result = convert_db(&source_path, &temp_path, &cmd, &spec, algorithm)
+		.and_then(|nodes| {
+			fs::rename(&temp_path, &target_path)
+				.map(|_| nodes)
+				.map_err(|e| format!("Failed to move {} to {}: {}", temp_path.display(), target_path.display(), e))
+		});
+	let nodes = match result {
+		Ok(nodes) => nodes,
+		Err(e) => {
+			let _ = fs::remove_dir_all(&temp_path);
+			return Err(e);
+		}
+	};
+
+	user_defaults.pruning = algorithm;
+	user_defaults.save(&user_defaults_path)?;
+	info!("Database converted, copied {} state nodes. The archive database can be removed with `parity db kill --pruning=archive`.", nodes);
+	Ok(())
+}
+
+fn convert_db(source_path: &Path, target_path: &Path, cmd: &PruneBlockchain, spec: &Spec, algorithm: Algorithm) -> Result<u64, String> {
+	// blooms and ancient blocks are stored outside of the key-value database.
+	for dir in &["blooms", "trace_blooms", "freezer"] {
+		copy_files(&source_path.join(dir), &target_path.join(dir))
+			.map_err(|e| format!("Failed to copy {}: {}", dir, e))?;
+	}
+
+	let source = db::open_db(&source_path.to_str().expect("DB path could not be converted to string."), &cmd.cache_config, &cmd.compaction)
+		.map_err(|e| format!("Failed to open database: {:?}", e))?;
+	let target = db::open_db(&target_path.to_str().expect("DB path could not be converted to string."), &cmd.cache_config, &cmd.compaction)
+		.map_err(|e| format!("Failed to open database: {:?}", e))?;
+
+	prune::prune_database(source, target, spec, algorithm, cmd.pruning_history)
+		.map_err(|e| format!("Failed to convert database: {}", e))
+}
+
+pub fn backup_db(cmd: BackupBlockchain) -> Result<(), String> {
+	let spec = cmd.spec.spec(&cmd.dirs.cache)?;
+	let genesis_hash = spec.genesis_header().hash();
+	let db_dirs = cmd.dirs.database(genesis_hash, None, spec.data_dir.clone());
+	let user_defaults = UserDefaults::load(&db_dirs.user_defaults_path())?;
+	let algorithm = cmd.pruning.to_algorithm(&user_defaults);
+	let client_path = db_dirs.client_path(algorithm);
+	if !client_path.exists() {
+		return Err(format!("Database not found at {}", client_path.display()));
+	}
+
+	execute_upgrades(&cmd.dirs.base, &db_dirs, algorithm, &cmd.compaction)?;
+
+	let db = db::open_db(&client_path.to_str().expect("DB path could not be converted to string."), &cmd.cache_config, &cmd.compaction)
+		.map_err(|e| format!("Failed to open database: {:?}", e))?;
+
+	info!("Backing up database at {} to {}", client_path.display(), cmd.path);
+	db::backup(&db_dirs, algorithm, Path::new(&cmd.path), |backup_path| {
+		let frozen = db.freezer().map_or(0, |freezer| freezer.len());
+		db.backup(backup_path)
+			.and_then(|_| db.backup_frozen(backup_path, frozen))
+			.map_err(|e| format!("Failed to back up database: {}", e))
+	})?;
+
+	info!("Database backed up. Start with --db-path={} to use the backup.", cmd.path);
+	Ok(())
+}
+
+fn copy_files(from: &Path, to: &Path) -> io::Result<()> {
+	fs::create_dir_all(to)?;
+	if !from.exists() {
+		return Ok(());
+	}
+
+	for entry in fs::read_dir(from)? {
+		let entry = entry?;
+		if entry.file_type()?.is_file() {
+			fs::copy(entry.path(), to.join(entry.file_name()))?;
+		}
+	}
+	Ok(())
+}
+
 #[cfg(test)]
 mod test {
 	use super::DataFormat;
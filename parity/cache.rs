@@ -24,6 +24,7 @@ const DEFAULT_BC_CACHE_SIZE: u32 = 8;
 const DEFAULT_BLOCK_QUEUE_SIZE_LIMIT_MB: u32 = 40;
 const DEFAULT_TRACE_CACHE_SIZE: u32 = 20;
 const DEFAULT_STATE_CACHE_SIZE: u32 = 25;
+const DEFAULT_TRIE_CACHE_SIZE: u32 = 32;
 
 /// Configuration for application cache sizes.
 /// All	values are represented in MB.
@@ -39,6 +40,8 @@ pub struct CacheConfig {
 	traces: u32,
 	/// Size of the state cache.
 	state: u32,
+	/// Size of the trie node cache.
+	trie: u32,
 }
 
 impl Default for CacheConfig {
@@ -47,7 +50,8 @@ impl Default for CacheConfig {
 			DEFAULT_DB_CACHE_SIZE,
 			DEFAULT_BC_CACHE_SIZE,
 			DEFAULT_BLOCK_QUEUE_SIZE_LIMIT_MB,
-			DEFAULT_STATE_CACHE_SIZE)
+			DEFAULT_STATE_CACHE_SIZE,
+			DEFAULT_TRIE_CACHE_SIZE)
 	}
 }
 
@@ -55,22 +59,24 @@ impl CacheConfig {
 	/// Creates new cache config with cumulative size equal `total`.
 	pub fn new_with_total_cache_size(total: u32) -> Self {
 		CacheConfig {
-			db: total * 7 / 10,
+			db: total * 6 / 10,
 			blockchain: total / 10,
 			queue: DEFAULT_BLOCK_QUEUE_SIZE_LIMIT_MB,
 			traces: DEFAULT_TRACE_CACHE_SIZE,
 			state: total * 2 / 10,
+			trie: total / 10,
 		}
 	}
 
 	/// Creates new cache config with gitven details.
-	pub fn new(db: u32, blockchain: u32, queue: u32, state: u32) -> Self {
+	pub fn new(db: u32, blockchain: u32, queue: u32, state: u32, trie: u32) -> Self {
 		CacheConfig {
 			db: db,
 			blockchain: blockchain,
 			queue: queue,
 			traces: DEFAULT_TRACE_CACHE_SIZE,
 			state: state,
+			trie: trie,
 		}
 	}
 
@@ -99,6 +105,11 @@ impl CacheConfig {
 		self.state * 3 / 4
 	}
 
+	/// Size of the trie node cache.
+	pub fn trie(&self) -> u32 {
+		self.trie
+	}
+
 	/// Size of the jump-tables cache.
 	pub fn jump_tables(&self) -> u32 {
 		self.state / 4
@@ -112,18 +123,19 @@ mod tests {
 	#[test]
 	fn test_cache_config_constructor() {
 		let config = CacheConfig::new_with_total_cache_size(200);
-		assert_eq!(config.db, 140);
+		assert_eq!(config.db, 120);
 		assert_eq!(config.blockchain(), 20);
 		assert_eq!(config.queue(), 40);
 		assert_eq!(config.state(), 30);
+		assert_eq!(config.trie(), 20);
 		assert_eq!(config.jump_tables(), 10);
 	}
 
 	#[test]
 	fn test_cache_config_db_cache_sizes() {
 		let config = CacheConfig::new_with_total_cache_size(400);
-		assert_eq!(config.db, 280);
-		assert_eq!(config.db_cache_size(), 280);
+		assert_eq!(config.db, 240);
+		assert_eq!(config.db_cache_size(), 240);
 	}
 
 	#[test]
@@ -133,6 +145,7 @@ mod tests {
 					   super::DEFAULT_DB_CACHE_SIZE,
 					   super::DEFAULT_BC_CACHE_SIZE,
 					   super::DEFAULT_BLOCK_QUEUE_SIZE_LIMIT_MB,
-					   super::DEFAULT_STATE_CACHE_SIZE));
+					   super::DEFAULT_STATE_CACHE_SIZE,
+					   super::DEFAULT_TRIE_CACHE_SIZE));
 	}
 }
@@ -84,6 +84,19 @@ usage! {
 			"Path to the file to import from",
 		}
 
+		CMD cmd_import_state
+		{
+			"Build the genesis state of the given --chain database (default: mainnet) from a dump written by `export state` and print the resulting genesis.",
+
+			ARG arg_import_state_output: (Option<String>) = None,
+			"--output=[FILE]",
+			"Write the genesis with the imported state root to FILE instead of the standard output. It replaces the genesis of the chain specification.",
+
+			ARG arg_import_state_file: (Option<String>) = None,
+			"[FILE]",
+			"Path to the state dump",
+		}
+
 		CMD cmd_export
 		{
 			"Export blockchain",
@@ -217,6 +230,26 @@ usage! {
 			CMD cmd_db_kill {
 				"Clean the database of the given --chain (default: mainnet)",
 			}
+
+			CMD cmd_db_verify {
+				"Check the database of the given --chain (default: mainnet) for consistency and report the first inconsistent block",
+
+				FLAG flag_db_verify_state: (bool) = false,
+				"--state",
+				"Also check that the whole state of the best block is present.",
+			}
+
+			CMD cmd_db_prune {
+				"Convert the archive database of the given --chain (default: mainnet) to the --pruning algorithm (default: fast), keeping the last --pruning-history states",
+			}
+
+			CMD cmd_db_backup {
+				"Back up the database of the given --chain (default: mainnet) while the node is stopped. Use the parity_backup RPC while it is running.",
+
+				ARG arg_db_backup_path: (Option<String>) = None,
+				"<PATH>",
+				"Directory to write the backup to. Start with --db-path=<PATH> to use it.",
+			}
 		}
 
 		CMD cmd_export_hardcoded_sync
@@ -403,6 +436,10 @@ usage! {
 			"--warp-barrier=[NUM]",
 			"When warp enabled never attempt regular sync before warping to block NUM.",
 
+			FLAG flag_beam_sync: (bool) = false, or |c: &Config| c.network.as_ref()?.beam_sync.clone(),
+			"--beam-sync",
+			"Start importing new blocks right away on a fresh database, fetching their state from peers on demand and downloading the rest of the state in the background. Requires archive or fast pruning; disables warp sync.",
+
 			ARG arg_port: (u16) = 30303u16, or |c: &Config| c.network.as_ref()?.port.clone(),
 			"--port=[PORT]",
 			"Override the port on which the node should listen.",
@@ -425,7 +462,7 @@ usage! {
 
 			ARG arg_nat: (String) = "any", or |c: &Config| c.network.as_ref()?.nat.clone(),
 			"--nat=[METHOD]",
-			"Specify method to use for determining public address. Must be one of: any, none, upnp, extip:<IP>.",
+			"Specify method to use for determining public address. Must be one of: any, none, upnp, natpmp, pcp, extip:<IP>. With any, UPnP, PCP and NAT-PMP are tried in turn.",
 
 			ARG arg_allow_ips: (String) = "all", or |c: &Config| c.network.as_ref()?.allow_ips.clone(),
 			"--allow-ips=[FILTER]",
@@ -435,13 +472,21 @@ usage! {
 			"--max-pending-peers=[NUM]",
 			"Allow up to NUM pending connections.",
 
+			ARG arg_max_upload_rate: (Option<u64>) = None, or |c: &Config| c.network.as_ref()?.max_upload_rate.clone(),
+			"--max-upload-rate=[KBPS]",
+			"Limit the total upload rate to all peers to KBPS kilobytes per second. Bandwidth is shared fairly between the eth, par, pip and shh protocols.",
+
+			ARG arg_max_peer_upload_rate: (Option<u64>) = None, or |c: &Config| c.network.as_ref()?.max_peer_upload_rate.clone(),
+			"--max-peer-upload-rate=[KBPS]",
+			"Limit the upload rate to any single peer to KBPS kilobytes per second.",
+
 			ARG arg_network_id: (Option<u64>) = None, or |c: &Config| c.network.as_ref()?.id.clone(),
 			"--network-id=[INDEX]",
 			"Override the network identifier from the chain we are on.",
 
 			ARG arg_bootnodes: (Option<String>) = None, or |c: &Config| c.network.as_ref()?.bootnodes.as_ref().map(|vec| vec.join(",")),
 			"--bootnodes=[NODES]",
-			"Override the bootnodes from our chain. NODES should be comma-delimited enodes.",
+			"Override the bootnodes from our chain. NODES should be comma-delimited enodes or enrtree:// node list URLs.",
 
 			ARG arg_node_key: (Option<String>) = None, or |c: &Config| c.network.as_ref()?.node_key.clone(),
 			"--node-key=[KEY]",
@@ -481,7 +526,7 @@ usage! {
 
 			ARG arg_jsonrpc_apis: (String) = "web3,eth,pubsub,net,parity,private,parity_pubsub,traces,rpc,shh,shh_pubsub", or |c: &Config| c.rpc.as_ref()?.apis.as_ref().map(|vec| vec.join(",")),
 			"--jsonrpc-apis=[APIS]",
-			"Specify the APIs available through the HTTP JSON-RPC interface using a comma-delimited list of API names. Possible names are: all, safe, debug, web3, net, eth, pubsub, personal, signer, parity, parity_pubsub, parity_accounts, parity_set, traces, rpc, secretstore, shh, shh_pubsub. You can also disable a specific API by putting '-' in the front, example: all,-personal. 'safe' enables the following APIs: web3, net, eth, pubsub, parity, parity_pubsub, traces, rpc, shh, shh_pubsub",
+			"Specify the APIs available through the HTTP JSON-RPC interface using a comma-delimited list of API names. Possible names are: all, safe, debug, clique, evm, web3, net, eth, pubsub, personal, signer, parity, parity_pubsub, parity_accounts, parity_set, traces, rpc, secretstore, shh, shh_pubsub. You can also disable a specific API by putting '-' in the front, example: all,-personal. 'safe' enables the following APIs: web3, net, eth, pubsub, parity, parity_pubsub, traces, rpc, shh, shh_pubsub",
 
 			ARG arg_jsonrpc_hosts: (String) = "none", or |c: &Config| c.rpc.as_ref()?.hosts.as_ref().map(|vec| vec.join(",")),
 			"--jsonrpc-hosts=[HOSTS]",
@@ -685,6 +730,22 @@ usage! {
 			"--stratum",
 			"Run Stratum server for miner push notification.",
 
+			FLAG flag_cpu_miner: (bool) = false, or |c: &Config| c.mining.as_ref()?.cpu_miner.clone(),
+			"--cpu-miner",
+			"Run a built-in CPU miner sealing blocks of Ethash chains. Only practical for chains with a low difficulty, e.g. development and test networks.",
+
+			ARG arg_cpu_miner_threads: (usize) = 1usize, or |c: &Config| c.mining.as_ref()?.cpu_miner_threads.clone(),
+			"--cpu-miner-threads=[NUM]",
+			"Number of threads used by the built-in CPU miner.",
+
+			ARG arg_dev_accounts: (usize) = 0usize, or |c: &Config| c.mining.as_ref()?.dev_accounts.clone(),
+			"--dev-accounts=[NUM]",
+			"Fund NUM deterministic accounts in the genesis of the development chain and unlock them with an empty password.",
+
+			ARG arg_dev_block_period: (u64) = 0u64, or |c: &Config| c.mining.as_ref()?.dev_block_period.clone(),
+			"--dev-block-period=[SEC]",
+			"Seal a block every SEC seconds on the development chain, even if it's empty. With 0 blocks are only sealed for new transactions or on request.",
+
 			ARG arg_reseal_on_txs: (String) = "own", or |c: &Config| c.mining.as_ref()?.reseal_on_txs.clone(),
 			"--reseal-on-txs=[SET]",
 			"Specify which transactions should force the node to reseal a block. SET is one of: none - never reseal on new transactions; own - reseal only on a new local transaction; ext - reseal only on a new external transaction; all - reseal on all new transactions.",
@@ -856,6 +917,10 @@ usage! {
 			"--cache-size-state=[MB]",
 			"Specify the maximum size of memory to use for the state cache.",
 
+			ARG arg_cache_size_trie: (u32) = 32u32, or |c: &Config| c.footprint.as_ref()?.cache_size_trie.clone(),
+			"--cache-size-trie=[MB]",
+			"Specify the maximum size of memory to use for the cache of trie nodes shared by all states.",
+
 			ARG arg_db_compaction: (String) = "auto", or |c: &Config| c.footprint.as_ref()?.db_compaction.clone(),
 			"--db-compaction=[TYPE]",
 			"Database compaction type. TYPE may be one of: ssd - suitable for SSDs and fast HDDs; hdd - suitable for slow HDDs; auto - determine automatically.",
@@ -864,6 +929,18 @@ usage! {
 			"--fat-db=[BOOL]",
 			"Build appropriate information to allow enumeration of all accounts and storage keys. Doubles the size of the state database. BOOL may be one of on, off or auto.",
 
+			FLAG flag_flat_state: (bool) = false, or |c: &Config| c.footprint.as_ref()?.flat_state.clone(),
+			"--flat-state",
+			"Keep a flat copy of the recent state in the database for faster account and storage lookups. The copy is generated in the background on first use.",
+
+			ARG arg_freezer_threshold: (Option<u64>) = None, or |c: &Config| c.footprint.as_ref()?.freezer_threshold.clone(),
+			"--freezer-threshold=[BLOCKS]",
+			"Move headers, bodies and receipts of canonical blocks older than BLOCKS blocks out of the database into compressed append-only files. At least 1024 recent blocks are always kept in the database.",
+
+			ARG arg_history_retention: (Option<u64>) = None, or |c: &Config| c.footprint.as_ref()?.history_retention.clone(),
+			"--history-retention=[BLOCKS]",
+			"Delete bodies, receipts and transaction indexes of blocks older than BLOCKS blocks. Headers are kept. At least 30000 recent blocks are always kept.",
+
 			ARG arg_cache_size: (Option<u32>) = None, or |c: &Config| c.footprint.as_ref()?.cache_size.clone(),
 			"--cache-size=[MB]",
 			"Set total amount of discretionary memory to use for the entire system, overrides other cache and queue options.",
@@ -884,7 +961,11 @@ usage! {
 
 			ARG arg_snapshot_threads: (Option<usize>) = None, or |c: &Config| c.snapshots.as_ref()?.processing_threads,
 			"--snapshot-threads=[NUM]",
-			"Enables multiple threads for snapshots creation.",
+			"Number of threads used to chunk the state when creating snapshots, between 1 and 16. The snapshot does not depend on the number of threads.",
+
+			ARG arg_snapshot_codec: (String) = "snappy", or |c: &Config| c.snapshots.as_ref()?.codec.clone(),
+			"--snapshot-codec=[CODEC]",
+			"Compression codec used for snapshot chunks. CODEC may be one of: snappy - understood by all peers; zstd - smaller chunks, only served to peers supporting warp protocol version 4 and unreadable by older versions of Parity.",
 
 		["Whisper Options"]
 			FLAG flag_whisper: (bool) = false, or |c: &Config| c.whisper.as_ref()?.enabled,
@@ -1194,12 +1275,15 @@ struct Ui {
 struct Network {
 	warp: Option<bool>,
 	warp_barrier: Option<u64>,
+	beam_sync: Option<bool>,
 	port: Option<u16>,
 	interface: Option<String>,
 	min_peers: Option<u16>,
 	max_peers: Option<u16>,
 	snapshot_peers: Option<u16>,
 	max_pending_peers: Option<u16>,
+	max_upload_rate: Option<u64>,
+	max_peer_upload_rate: Option<u64>,
 	nat: Option<String>,
 	allow_ips: Option<String>,
 	id: Option<u64>,
@@ -1336,6 +1420,10 @@ struct Mining {
 	refuse_service_transactions: Option<bool>,
 	infinite_pending_block: Option<bool>,
 	max_round_blocks_to_import: Option<usize>,
+	cpu_miner: Option<bool>,
+	cpu_miner_threads: Option<usize>,
+	dev_accounts: Option<usize>,
+	dev_block_period: Option<u64>,
 }
 
 #[derive(Default, Debug, PartialEq, Deserialize)]
@@ -1359,8 +1447,12 @@ struct Footprint {
 	cache_size_blocks: Option<u32>,
 	cache_size_queue: Option<u32>,
 	cache_size_state: Option<u32>,
+	cache_size_trie: Option<u32>,
 	db_compaction: Option<String>,
 	fat_db: Option<String>,
+	flat_state: Option<bool>,
+	freezer_threshold: Option<u64>,
+	history_retention: Option<u64>,
 	scale_verifiers: Option<bool>,
 	num_verifiers: Option<usize>,
 }
@@ -1370,6 +1462,7 @@ struct Footprint {
 struct Snapshots {
 	disable_periodic: Option<bool>,
 	processing_threads: Option<usize>,
+	codec: Option<String>,
 }
 
 #[derive(Default, Debug, PartialEq, Deserialize)]
@@ -1574,6 +1667,7 @@ mod tests {
 			cmd_wallet: false,
 			cmd_wallet_import: false,
 			cmd_import: false,
+			cmd_import_state: false,
 			cmd_export: false,
 			cmd_export_blocks: false,
 			cmd_export_state: false,
@@ -1588,12 +1682,17 @@ mod tests {
 			cmd_tools_hash: false,
 			cmd_db: false,
 			cmd_db_kill: false,
+			cmd_db_verify: false,
+			cmd_db_prune: false,
+			cmd_db_backup: false,
 			cmd_export_hardcoded_sync: false,
 
 			// Arguments
 			arg_daemon_pid_file: None,
 			arg_import_file: None,
 			arg_import_format: None,
+			arg_import_state_file: None,
+			arg_import_state_output: None,
 			arg_export_blocks_file: None,
 			arg_export_blocks_format: None,
 			arg_export_state_file: None,
@@ -1601,6 +1700,7 @@ mod tests {
 			arg_snapshot_file: None,
 			arg_restore_file: None,
 			arg_tools_hash_file: None,
+			arg_db_backup_path: None,
 
 			arg_signer_sign_id: None,
 			arg_signer_reject_id: None,
@@ -1666,6 +1766,8 @@ mod tests {
 			arg_min_peers: Some(25u16),
 			arg_max_peers: Some(50u16),
 			arg_max_pending_peers: 64u16,
+			arg_max_upload_rate: None,
+			arg_max_peer_upload_rate: None,
 			arg_snapshot_peers: 0u16,
 			arg_allow_ips: "all".into(),
 			arg_nat: "any".into(),
@@ -1772,6 +1874,10 @@ mod tests {
 			arg_max_round_blocks_to_import: 12usize,
 
 			flag_stratum: false,
+			flag_cpu_miner: false,
+			arg_cpu_miner_threads: 1usize,
+			arg_dev_accounts: 0usize,
+			arg_dev_block_period: 0u64,
 			arg_stratum_interface: "local".to_owned(),
 			arg_stratum_port: 8008u16,
 			arg_stratum_secret: None,
@@ -1785,10 +1891,14 @@ mod tests {
 			arg_cache_size_blocks: 8u32,
 			arg_cache_size_queue: 50u32,
 			arg_cache_size_state: 25u32,
+			arg_cache_size_trie: 32u32,
 			arg_cache_size: Some(128),
 			flag_fast_and_loose: false,
 			arg_db_compaction: "ssd".into(),
 			arg_fat_db: "auto".into(),
+			flag_flat_state: false,
+			arg_freezer_threshold: None,
+			arg_history_retention: None,
 			flag_scale_verifiers: true,
 			arg_num_verifiers: Some(6),
 
@@ -1801,11 +1911,15 @@ mod tests {
 			arg_export_state_min_balance: None,
 			arg_export_state_max_balance: None,
 
+			// -- Database Options
+			flag_db_verify_state: false,
+
 			// -- Snapshot Optons
 			arg_export_state_at: "latest".into(),
 			arg_snapshot_at: "latest".into(),
 			flag_no_periodic_snapshot: false,
 			arg_snapshot_threads: None,
+			arg_snapshot_codec: "snappy".into(),
 
 			// -- Light options.
 			arg_on_demand_retry_count: Some(15),
@@ -1821,6 +1935,7 @@ mod tests {
 			flag_testnet: false,
 			flag_import_geth_keys: false,
 			arg_warp_barrier: None,
+			flag_beam_sync: false,
 			arg_datadir: None,
 			arg_networkid: None,
 			arg_peers: None,
@@ -1929,11 +2044,14 @@ mod tests {
 			network: Some(Network {
 				warp: Some(false),
 				warp_barrier: None,
+				beam_sync: None,
 				port: None,
 				interface: None,
 				min_peers: Some(10),
 				max_peers: Some(20),
 				max_pending_peers: Some(30),
+				max_upload_rate: None,
+				max_peer_upload_rate: None,
 				snapshot_peers: Some(40),
 				allow_ips: Some("public".into()),
 				nat: Some("any".into()),
@@ -2043,6 +2161,10 @@ mod tests {
 				refuse_service_transactions: None,
 				infinite_pending_block: None,
 				max_round_blocks_to_import: None,
+				cpu_miner: None,
+				cpu_miner_threads: None,
+				dev_accounts: None,
+				dev_block_period: None,
 			}),
 			footprint: Some(Footprint {
 				tracing: Some("on".into()),
@@ -2055,8 +2177,12 @@ mod tests {
 				cache_size_blocks: Some(16),
 				cache_size_queue: Some(100),
 				cache_size_state: Some(25),
+				cache_size_trie: None,
 				db_compaction: Some("ssd".into()),
 				fat_db: Some("off".into()),
+				flat_state: None,
+				freezer_threshold: None,
+				history_retention: None,
 				scale_verifiers: Some(false),
 				num_verifiers: None,
 			}),
@@ -2067,6 +2193,7 @@ mod tests {
 			snapshots: Some(Snapshots {
 				disable_periodic: Some(true),
 				processing_threads: None,
+				codec: None,
 			}),
 			misc: Some(Misc {
 				logging: Some("own_tx=trace".into()),
@@ -26,10 +26,10 @@ use ethereum_types::{U256, H256, Address};
 use parity_version::{version_data, version};
 use bytes::Bytes;
 use ansi_term::Colour;
-use sync::{NetworkConfiguration, validate_node_url, self};
+use sync::{NetworkConfiguration, NatType, validate_node_url, self};
 use ethcore::ethstore::ethkey::{Secret, Public};
 use ethcore::client::{VMType};
-use ethcore::miner::{stratum, MinerOptions};
+use ethcore::miner::{cpu_miner, stratum, MinerOptions};
 use ethcore::snapshot::SnapshotConfiguration;
 use ethcore::verification::queue::VerifierSettings;
 use miner::pool;
@@ -38,7 +38,7 @@ use num_cpus;
 use rpc::{IpcConfiguration, HttpConfiguration, WsConfiguration};
 use parity_rpc::NetworkSettings;
 use cache::CacheConfig;
-use helpers::{to_duration, to_mode, to_block_id, to_u256, to_pending_set, to_price, geth_ipc_path, parity_ipc_path, to_bootnodes, to_addresses, to_address, to_queue_strategy, to_queue_penalization, passwords_from_files};
+use helpers::{to_duration, to_mode, to_block_id, to_u256, to_pending_set, to_price, geth_ipc_path, parity_ipc_path, to_bootnodes, to_addresses, to_address, to_queue_strategy, to_queue_penalization, to_snapshot_codec, passwords_from_files};
 use dir::helpers::{replace_home, replace_home_and_local};
 use params::{ResealPolicy, AccountsConfig, GasPricerConfig, MinerExtras, SpecType};
 use ethcore_logger::Config as LogConfig;
@@ -48,7 +48,7 @@ use ethcore_private_tx::{ProviderConfig, EncryptorConfig};
 use secretstore::{NodeSecretKey, Configuration as SecretStoreConfiguration, ContractAddress as SecretStoreContractAddress};
 use updater::{UpdatePolicy, UpdateFilter, ReleaseTrack};
 use run::RunCmd;
-use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, KillBlockchain, ExportState, DataFormat};
+use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, KillBlockchain, VerifyBlockchain, PruneBlockchain, BackupBlockchain, ExportState, ImportState, DataFormat};
 use export_hardcoded_sync::ExportHsyncCmd;
 use presale::ImportWallet;
 use account::{AccountCmd, NewAccount, ListAccounts, ImportAccounts, ImportFromGethAccounts};
@@ -181,6 +181,33 @@ impl Configuration {
 				dirs: dirs,
 				pruning: pruning,
 			}))
+		} else if self.args.cmd_db && self.args.cmd_db_verify {
+			Cmd::Blockchain(BlockchainCmd::Verify(VerifyBlockchain {
+				spec: spec,
+				cache_config: cache_config,
+				dirs: dirs,
+				pruning: pruning,
+				compaction: compaction,
+				check_state: self.args.flag_db_verify_state,
+			}))
+		} else if self.args.cmd_db && self.args.cmd_db_prune {
+			Cmd::Blockchain(BlockchainCmd::Prune(PruneBlockchain {
+				spec: spec,
+				cache_config: cache_config,
+				dirs: dirs,
+				pruning: pruning,
+				pruning_history: pruning_history,
+				compaction: compaction,
+			}))
+		} else if self.args.cmd_db && self.args.cmd_db_backup {
+			Cmd::Blockchain(BlockchainCmd::Backup(BackupBlockchain {
+				spec: spec,
+				cache_config: cache_config,
+				dirs: dirs,
+				pruning: pruning,
+				compaction: compaction,
+				path: self.args.arg_db_backup_path.clone().ok_or_else(|| "Backup path is required".to_owned())?,
+			}))
 		} else if self.args.cmd_account {
 			let account_cmd = if self.args.cmd_account_new {
 				let new_acc = NewAccount {
@@ -246,6 +273,16 @@ impl Configuration {
 				max_round_blocks_to_import: self.args.arg_max_round_blocks_to_import,
 			};
 			Cmd::Blockchain(BlockchainCmd::Import(import_cmd))
+		} else if self.args.cmd_import_state {
+			Cmd::Blockchain(BlockchainCmd::ImportState(ImportState {
+				spec: spec,
+				cache_config: cache_config,
+				dirs: dirs,
+				pruning: pruning,
+				compaction: compaction,
+				file_path: self.args.arg_import_state_file.clone(),
+				output_path: self.args.arg_import_state_output.clone(),
+			}))
 		} else if self.args.cmd_export {
 			if self.args.cmd_export_blocks {
 				let export_cmd = ExportBlockchain {
@@ -345,6 +382,7 @@ impl Configuration {
 			let verifier_settings = self.verifier_settings();
 			let whisper_config = self.whisper_config();
 			let (private_provider_conf, private_enc_conf, private_tx_enabled) = self.private_provider_config()?;
+			let (dev_accounts, dev_block_period) = self.dev_options()?;
 
 			let run_cmd = RunCmd {
 				cache_config: cache_config,
@@ -368,6 +406,9 @@ impl Configuration {
 				gas_pricer_conf: self.gas_pricer_config()?,
 				miner_extras: self.miner_extras()?,
 				stratum: self.stratum_options()?,
+				cpu_miner: self.cpu_miner_options(),
+				dev_accounts: dev_accounts,
+				dev_block_period: dev_block_period,
 				update_policy: update_policy,
 				mode: mode,
 				tracing: tracing,
@@ -376,6 +417,10 @@ impl Configuration {
 				vm_type: vm_type,
 				warp_sync: warp_sync,
 				warp_barrier: self.args.arg_warp_barrier,
+				beam_sync: self.args.flag_beam_sync,
+				flat_state: self.args.flag_flat_state,
+				freezer_threshold: self.args.arg_freezer_threshold,
+				history_retention: self.args.arg_history_retention,
 				geth_compatibility: geth_compatibility,
 				net_settings: self.network_settings()?,
 				ipfs_conf: ipfs_conf,
@@ -449,6 +494,7 @@ impl Configuration {
 				self.args.arg_cache_size_blocks,
 				self.args.arg_cache_size_queue,
 				self.args.arg_cache_size_state,
+				self.args.arg_cache_size_trie,
 			),
 		}
 	}
@@ -502,6 +548,14 @@ impl Configuration {
 		self.args.arg_snapshot_peers as u32
 	}
 
+	fn max_upload_rate(&self) -> Option<u64> {
+		self.args.arg_max_upload_rate.map(|kbps| kbps * 1024)
+	}
+
+	fn max_peer_upload_rate(&self) -> Option<u64> {
+		self.args.arg_max_peer_upload_rate.map(|kbps| kbps * 1024)
+	}
+
 	fn work_notify(&self) -> Vec<String> {
 		self.args.arg_notify_work.as_ref().map_or_else(Vec::new, |s| s.split(',').map(|s| s.to_owned()).collect())
 	}
@@ -531,6 +585,23 @@ impl Configuration {
 		} else { Ok(None) }
 	}
 
+	fn cpu_miner_options(&self) -> Option<cpu_miner::Options> {
+		if self.args.flag_cpu_miner {
+			Some(cpu_miner::Options {
+				threads: cmp::max(1, self.args.arg_cpu_miner_threads),
+				cache_dir: self.directories().cache.into(),
+			})
+		} else { None }
+	}
+
+	fn dev_options(&self) -> Result<(usize, u64), String> {
+		let (accounts, block_period) = (self.args.arg_dev_accounts, self.args.arg_dev_block_period);
+		if (accounts > 0 || block_period > 0) && !self.is_dev_chain()? {
+			return Err("Dev accounts and block period can only be used with the development chain".into());
+		}
+		Ok((accounts, block_period))
+	}
+
 	fn miner_options(&self) -> Result<MinerOptions, String> {
 		let is_dev_chain = self.is_dev_chain()?;
 		if is_dev_chain && self.args.flag_force_sealing && self.args.arg_reseal_min_period == 0 {
@@ -709,7 +780,9 @@ impl Configuration {
 
 	fn net_config(&self) -> Result<NetworkConfiguration, String> {
 		let mut ret = NetworkConfiguration::new();
-		ret.nat_enabled = self.args.arg_nat == "any" || self.args.arg_nat == "upnp";
+		let nat_type = NatType::parse(&self.args.arg_nat);
+		ret.nat_enabled = nat_type.is_some();
+		ret.nat_type = nat_type.unwrap_or(NatType::Any);
 		ret.boot_nodes = to_bootnodes(&self.args.arg_bootnodes)?;
 		let (listen, public) = self.net_addresses()?;
 		ret.listen_address = Some(format!("{}", listen));
@@ -727,6 +800,8 @@ impl Configuration {
 		ret.snapshot_peers = self.snapshot_peers();
 		ret.ip_filter = self.ip_filter()?;
 		ret.max_pending_peers = self.max_pending_peers();
+		ret.max_egress_rate = self.max_upload_rate();
+		ret.max_peer_egress_rate = self.max_peer_upload_rate();
 		let mut net_path = PathBuf::from(self.directories().base);
 		net_path.push("network");
 		ret.config_path = Some(net_path.to_str().unwrap().to_owned());
@@ -915,6 +990,7 @@ impl Configuration {
 				Some(threads) if threads > 0 => threads,
 				_ => ::std::cmp::max(1, num_cpus::get() / 2),
 			},
+			codec: to_snapshot_codec(&self.args.arg_snapshot_codec)?,
 		};
 
 		Ok(conf)
@@ -1173,11 +1249,12 @@ mod tests {
 	use updater::{UpdatePolicy, UpdateFilter, ReleaseTrack};
 
 	use account::{AccountCmd, NewAccount, ImportAccounts, ListAccounts};
-	use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, DataFormat, ExportState};
+	use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, VerifyBlockchain, PruneBlockchain, BackupBlockchain, DataFormat, ExportState, ImportState};
 	use cli::Args;
 	use dir::{Directories, default_hypervisor_path};
 	use helpers::{default_network_config};
-	use params::SpecType;
+	use journaldb::Algorithm;
+	use params::{SpecType, Pruning};
 	use presale::ImportWallet;
 	use rpc::WsConfiguration;
 	use rpc_apis::ApiSet;
@@ -1302,6 +1379,63 @@ mod tests {
 		})));
 	}
 
+	#[test]
+	fn test_command_db_verify() {
+		let args = vec!["parity", "db", "verify", "--state"];
+		let conf = parse(&args);
+		assert_eq!(conf.into_command().unwrap().cmd, Cmd::Blockchain(BlockchainCmd::Verify(VerifyBlockchain {
+			spec: Default::default(),
+			cache_config: Default::default(),
+			dirs: Default::default(),
+			pruning: Default::default(),
+			compaction: Default::default(),
+			check_state: true,
+		})));
+	}
+
+	#[test]
+	fn test_command_db_prune() {
+		let args = vec!["parity", "db", "prune", "--pruning", "light", "--pruning-history", "128"];
+		let conf = parse(&args);
+		assert_eq!(conf.into_command().unwrap().cmd, Cmd::Blockchain(BlockchainCmd::Prune(PruneBlockchain {
+			spec: Default::default(),
+			cache_config: Default::default(),
+			dirs: Default::default(),
+			pruning: Pruning::Specific(Algorithm::EarlyMerge),
+			pruning_history: 128,
+			compaction: Default::default(),
+		})));
+	}
+
+	#[test]
+	fn test_command_db_backup() {
+		let args = vec!["parity", "db", "backup", "/tmp/backup"];
+		let conf = parse(&args);
+		assert_eq!(conf.into_command().unwrap().cmd, Cmd::Blockchain(BlockchainCmd::Backup(BackupBlockchain {
+			spec: Default::default(),
+			cache_config: Default::default(),
+			dirs: Default::default(),
+			pruning: Default::default(),
+			compaction: Default::default(),
+			path: "/tmp/backup".into(),
+		})));
+	}
+
+	#[test]
+	fn test_command_state_import() {
+		let args = vec!["parity", "import-state", "--output", "genesis.json", "state.json"];
+		let conf = parse(&args);
+		assert_eq!(conf.into_command().unwrap().cmd, Cmd::Blockchain(BlockchainCmd::ImportState(ImportState {
+			spec: Default::default(),
+			cache_config: Default::default(),
+			dirs: Default::default(),
+			pruning: Default::default(),
+			compaction: Default::default(),
+			file_path: Some("state.json".into()),
+			output_path: Some("genesis.json".into()),
+		})));
+	}
+
 	#[test]
 	fn test_command_state_export() {
 		let args = vec!["parity", "export", "state", "state.json"];
@@ -1406,6 +1540,10 @@ mod tests {
 			network_id: None,
 			warp_sync: true,
 			warp_barrier: None,
+			beam_sync: false,
+			flat_state: false,
+			freezer_threshold: None,
+			history_retention: None,
 			acc_conf: Default::default(),
 			gas_pricer_conf: Default::default(),
 			miner_extras: Default::default(),
@@ -1435,6 +1573,9 @@ mod tests {
 			fat_db: Default::default(),
 			snapshot_conf: Default::default(),
 			stratum: None,
+			cpu_miner: None,
+			dev_accounts: 0,
+			dev_block_period: 0,
 			check_seal: true,
 			download_old_blocks: true,
 			verifier_settings: Default::default(),
@@ -1810,6 +1951,40 @@ mod tests {
 		assert_eq!(conf1.ipfs_config().port, 5002);
 	}
 
+	#[test]
+	fn should_parse_cpu_miner_options() {
+		// given
+
+		// when
+		let conf0 = parse(&["parity"]);
+		let conf1 = parse(&["parity", "--cpu-miner"]);
+		let conf2 = parse(&["parity", "--cpu-miner", "--cpu-miner-threads", "4"]);
+		let conf3 = parse(&["parity", "--cpu-miner", "--cpu-miner-threads", "0"]);
+
+		// then
+		assert_eq!(conf0.cpu_miner_options(), None);
+		assert_eq!(conf1.cpu_miner_options().unwrap().threads, 1);
+		assert_eq!(conf2.cpu_miner_options().unwrap().threads, 4);
+		assert_eq!(conf3.cpu_miner_options().unwrap().threads, 1);
+	}
+
+	#[test]
+	fn should_parse_dev_options() {
+		// given
+
+		// when
+		let conf0 = parse(&["parity", "--chain", "dev", "--dev-accounts", "3", "--dev-block-period", "5"]);
+		let conf1 = parse(&["parity", "--config", "dev", "--dev-accounts", "3"]);
+		let conf2 = parse(&["parity", "--dev-accounts", "3"]);
+		let conf3 = parse(&["parity"]);
+
+		// then
+		assert_eq!(conf0.dev_options(), Ok((3, 5)));
+		assert_eq!(conf1.dev_options(), Ok((3, 0)));
+		assert!(conf2.dev_options().is_err());
+		assert_eq!(conf3.dev_options(), Ok((0, 0)));
+	}
+
 	#[test]
 	fn should_expose_all_servers() {
 		// given
@@ -1940,4 +2115,27 @@ mod tests {
 			_ => panic!("Should be Cmd::Run"),
 		}
 	}
+	#[test]
+	fn should_parse_nat_method() {
+		let conf = Configuration::parse_cli(&["parity", "--nat=pcp"]).unwrap();
+		let net_conf = conf.net_config().unwrap();
+		assert!(net_conf.nat_enabled);
+		assert_eq!(net_conf.nat_type, NatType::Pcp);
+
+		let conf = Configuration::parse_cli(&["parity", "--nat=none"]).unwrap();
+		assert!(!conf.net_config().unwrap().nat_enabled);
+	}
+
+	#[test]
+	fn should_parse_upload_rate_limits() {
+		let args = vec!["parity", "--max-upload-rate=512", "--max-peer-upload-rate=64"];
+		let conf = Configuration::parse_cli(&args).unwrap();
+		match conf.into_command().unwrap().cmd {
+			Cmd::Run(c) => {
+				assert_eq!(c.net_conf.max_egress_rate, Some(512 * 1024));
+				assert_eq!(c.net_conf.max_peer_egress_rate, Some(64 * 1024));
+			},
+			_ => panic!("Should be Cmd::Run"),
+		}
+	}
 }
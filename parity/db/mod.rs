@@ -19,7 +19,7 @@
 #[path="rocksdb/mod.rs"]
 mod impls;
 
-pub use self::impls::{open_db, restoration_db_handler, migrate};
+pub use self::impls::{open_db, restoration_db_handler, migrate, backup};
 
 #[cfg(feature = "secretstore")]
 pub use self::impls::open_secretstore_db;
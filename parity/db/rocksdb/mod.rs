@@ -19,11 +19,14 @@ extern crate migration_rocksdb;
 
 use std::{io, fs};
 use std::sync::Arc;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use blooms_db;
+use freezer_db;
 use ethcore::{BlockChainDBHandler, BlockChainDB};
-use ethcore::db::NUM_COLUMNS;
+use ethcore::db::{self, NUM_COLUMNS};
 use ethcore::client::{ClientConfig, DatabaseCompactionProfile};
+use dir::DatabaseDirectories;
+use journaldb::Algorithm;
 use kvdb::KeyValueDB;
 use self::kvdb_rocksdb::{Database, DatabaseConfig};
 
@@ -39,6 +42,8 @@ struct AppDB {
 	key_value: Arc<KeyValueDB>,
 	blooms: blooms_db::Database,
 	trace_blooms: blooms_db::Database,
+	freezer: freezer_db::Database,
+	path: PathBuf,
 }
 
 impl BlockChainDB for AppDB {
@@ -53,6 +58,59 @@ impl BlockChainDB for AppDB {
 	fn trace_blooms(&self) -> &blooms_db::Database {
 		&self.trace_blooms
 	}
+
+	fn freezer(&self) -> Option<&freezer_db::Database> {
+		Some(&self.freezer)
+	}
+
+	fn backup(&self, path: &Path) -> io::Result<()> {
+		if path.exists() {
+			return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", path.display())));
+		}
+
+		db::checkpoint(&self.path, path)?;
+		self.blooms.backup(path.join("blooms"))?;
+		self.trace_blooms.backup(path.join("trace_blooms"))
+	}
+
+	fn backup_frozen(&self, path: &Path, len: u64) -> io::Result<()> {
+		self.freezer.backup(path.join("freezer"), len)
+	}
+}
+
+/// Back up the database for `algorithm` in `dirs` to `path`, together with the user defaults
+/// and the node table, so that `path` can be used as `--db-path`. The database itself is
+/// copied by `backup_db`, given the client path of the backup.
+pub fn backup<F>(dirs: &DatabaseDirectories, algorithm: Algorithm, path: &Path, backup_db: F) -> Result<(), String>
+	where F: FnOnce(&Path) -> Result<(), String>
+{
+	let mut target = dirs.clone();
+	target.path = path.to_string_lossy().into_owned();
+
+	let client_path = target.client_path(algorithm);
+	if client_path.exists() {
+		return Err(format!("Database already exists at {}", client_path.display()));
+	}
+
+	// the database is copied at the current version.
+	migrate(&target.db_path(algorithm), &DatabaseCompactionProfile::default()).map_err(|e| format!("{}", e))?;
+	backup_db(&client_path)?;
+
+	let files = [
+		(dirs.user_defaults_path(), target.user_defaults_path()),
+		(dirs.network_path().join("nodes.json"), target.network_path().join("nodes.json")),
+	];
+	for &(ref from, ref to) in files.iter() {
+		if !from.exists() {
+			continue;
+		}
+		if let Some(parent) = to.parent() {
+			fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+		}
+		fs::copy(from, to).map_err(|e| format!("Failed to copy {}: {}", from.display(), e))?;
+	}
+
+	Ok(())
 }
 
 /// Open a secret store DB using the given secret store data path. The DB path is one level beneath the data path.
@@ -103,13 +161,17 @@ pub fn open_database(client_path: &str, config: &DatabaseConfig) -> io::Result<A
 
 	let blooms_path = path.join("blooms");
 	let trace_blooms_path = path.join("trace_blooms");
+	let freezer_path = path.join("freezer");
 	fs::create_dir_all(&blooms_path)?;
 	fs::create_dir_all(&trace_blooms_path)?;
+	fs::create_dir_all(&freezer_path)?;
 
 	let db = AppDB {
 		key_value: Arc::new(Database::open(&config, client_path)?),
 		blooms: blooms_db::Database::open(blooms_path)?,
 		trace_blooms: blooms_db::Database::open(trace_blooms_path)?,
+		freezer: freezer_db::Database::open(freezer_path)?,
+		path: path.to_path_buf(),
 	};
 
 	Ok(Arc::new(db))
@@ -22,6 +22,7 @@ use ethereum_types::{U256, clean_0x, Address};
 use journaldb::Algorithm;
 use ethcore::client::{Mode, BlockId, VMType, DatabaseCompactionProfile, ClientConfig, VerifierType};
 use ethcore::miner::{PendingSet, Penalization};
+use ethcore::snapshot::ChunkCodec;
 use miner::pool::PrioritizationStrategy;
 use cache::CacheConfig;
 use dir::DatabaseDirectories;
@@ -104,6 +105,14 @@ pub fn to_queue_strategy(s: &str) -> Result<PrioritizationStrategy, String> {
 	}
 }
 
+pub fn to_snapshot_codec(s: &str) -> Result<ChunkCodec, String> {
+	match s {
+		"snappy" => Ok(ChunkCodec::Snappy),
+		"zstd" => Ok(ChunkCodec::Zstd),
+		other => Err(format!("Invalid snapshot codec: {}", other)),
+	}
+}
+
 pub fn to_queue_penalization(time: Option<u64>) -> Result<Penalization, String> {
 	Ok(match time {
 		Some(threshold_ms) => Penalization::Enabled {
@@ -180,7 +189,7 @@ pub fn to_bootnodes(bootnodes: &Option<String>) -> Result<Vec<String>, String> {
 
 #[cfg(test)]
 pub fn default_network_config() -> ::sync::NetworkConfiguration {
-	use sync::{NetworkConfiguration};
+	use sync::{NetworkConfiguration, NatType};
 	use super::network::IpFilter;
 	NetworkConfiguration {
 		config_path: Some(replace_home(&::dir::default_data_path(), "$BASE/network")),
@@ -189,6 +198,7 @@ pub fn default_network_config() -> ::sync::NetworkConfiguration {
 		public_address: None,
 		udp_port: None,
 		nat_enabled: true,
+		nat_type: NatType::Any,
 		discovery_enabled: true,
 		boot_nodes: Vec::new(),
 		use_secret: None,
@@ -200,6 +210,8 @@ pub fn default_network_config() -> ::sync::NetworkConfiguration {
 		reserved_nodes: Vec::new(),
 		allow_non_reserved: true,
 		client_version: ::parity_version::version(),
+		max_egress_rate: None,
+		max_peer_egress_rate: None,
 	}
 }
 
@@ -236,6 +248,8 @@ pub fn to_client_config(
 	// in bytes
 	client_config.state_cache_size = cache_config.state() as usize * mb;
 	// in bytes
+	client_config.trie_cache_size = cache_config.trie() as usize * mb;
+	// in bytes
 	client_config.jump_table_size = cache_config.jump_tables() as usize * mb;
 	// in bytes
 	client_config.history_mem = pruning_memory * mb;
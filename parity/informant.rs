@@ -139,6 +139,7 @@ impl InformantData for FullNodeInformantData {
 
 		let mut cache_sizes = CacheSizes::default();
 		cache_sizes.insert("db", client_report.state_db_mem);
+		cache_sizes.insert("trie", client_report.trie_cache_mem);
 		cache_sizes.insert("queue", queue_info.mem_used);
 		cache_sizes.insert("chain", blockchain_cache_info.total());
 
@@ -80,6 +80,10 @@ pub enum Api {
 	/// Geth-compatible (best-effort) debug API (Potentially UNSAFE)
 	/// NOTE We don't aim to support all methods, only the ones that are useful.
 	Debug,
+	/// Geth-compatible Clique signer voting API (UNSAFE: Side Effects affecting sealed blocks)
+	Clique,
+	/// Ganache-compatible development chain API (UNSAFE: Side Effects affecting the chain)
+	Evm,
 }
 
 impl FromStr for Api {
@@ -89,8 +93,10 @@ impl FromStr for Api {
 		use self::Api::*;
 
 		match s {
+			"clique" => Ok(Clique),
 			"debug" => Ok(Debug),
 			"eth" => Ok(Eth),
+			"evm" => Ok(Evm),
 			"net" => Ok(Net),
 			"parity" => Ok(Parity),
 			"parity_accounts" => Ok(ParityAccounts),
@@ -174,9 +180,11 @@ fn to_modules(apis: &HashSet<Api>) -> BTreeMap<String, String> {
 	let mut modules = BTreeMap::new();
 	for api in apis {
 		let (name, version) = match *api {
+			Api::Clique => ("clique", "1.0"),
 			Api::Debug => ("debug", "1.0"),
 			Api::Eth => ("eth", "1.0"),
 			Api::EthPubSub => ("pubsub", "1.0"),
+			Api::Evm => ("evm", "1.0"),
 			Api::Net => ("net", "1.0"),
 			Api::Parity => ("parity", "1.0"),
 			Api::ParityAccounts => ("parity_accounts", "1.0"),
@@ -268,9 +276,20 @@ impl FullDependencies {
 		);
 		for api in apis {
 			match *api {
+				Api::Clique => {
+					handler.extend_with(CliqueClient::new(self.client.clone()).to_delegate());
+				},
 				Api::Debug => {
 					handler.extend_with(DebugClient::new(self.client.clone()).to_delegate());
 				},
+				Api::Evm => {
+					// rewinding the chain is only safe where no other node depends on it.
+					if self.client.engine().can_seal_on_demand() {
+						handler.extend_with(EvmClient::new(self.client.clone()).to_delegate());
+					} else {
+						warn!(target: "rpc", "Evm API is only available on chains sealing blocks on demand, like the development chain.")
+					}
+				},
 				Api::Web3 => {
 					handler.extend_with(Web3Client::new().to_delegate());
 				},
@@ -486,9 +505,15 @@ impl<C: LightChainClient + 'static> LightDependencies<C> {
 
 		for api in apis {
 			match *api {
+				Api::Clique => {
+					warn!(target: "rpc", "Clique API is not available in light client mode.")
+				},
 				Api::Debug => {
 					warn!(target: "rpc", "Debug API is not available in light client mode.")
 				},
+				Api::Evm => {
+					warn!(target: "rpc", "Evm API is not available in light client mode.")
+				},
 				Api::Web3 => {
 					handler.extend_with(Web3Client::new().to_delegate());
 				},
@@ -653,6 +678,7 @@ impl ApiSet {
 				public_list
 			},
 			ApiSet::SafeContext => {
+				public_list.insert(Api::Clique);
 				public_list.insert(Api::Debug);
 				public_list.insert(Api::Traces);
 				public_list.insert(Api::ParityPubSub);
@@ -663,6 +689,8 @@ impl ApiSet {
 				public_list
 			},
 			ApiSet::All => {
+				public_list.insert(Api::Clique);
+				public_list.insert(Api::Evm);
 				public_list.insert(Api::Debug);
 				public_list.insert(Api::Traces);
 				public_list.insert(Api::ParityPubSub);
@@ -690,7 +718,9 @@ mod test {
 
 	#[test]
 	fn test_api_parsing() {
+		assert_eq!(Api::Clique, "clique".parse().unwrap());
 		assert_eq!(Api::Debug, "debug".parse().unwrap());
+		assert_eq!(Api::Evm, "evm".parse().unwrap());
 		assert_eq!(Api::Web3, "web3".parse().unwrap());
 		assert_eq!(Api::Net, "net".parse().unwrap());
 		assert_eq!(Api::Eth, "eth".parse().unwrap());
@@ -747,7 +777,7 @@ mod test {
 			// semi-safe
 			Api::ParityAccounts,
 			// Unsafe
-			Api::ParitySet, Api::Signer, Api::Debug
+			Api::ParitySet, Api::Signer, Api::Debug, Api::Clique
 		].into_iter().collect();
 		assert_eq!(ApiSet::SafeContext.list_apis(), expected);
 	}
@@ -761,6 +791,8 @@ mod test {
 			Api::Personal,
 			Api::Private,
 			Api::Debug,
+			Api::Clique,
+			Api::Evm,
 		].into_iter().collect()));
 	}
 
@@ -770,7 +802,7 @@ mod test {
 			Api::Web3, Api::Net, Api::Eth, Api::EthPubSub, Api::Parity, Api::ParityPubSub, Api::Traces, Api::Rpc, Api::SecretStore, Api::Whisper, Api::WhisperPubSub,
 			Api::ParityAccounts,
 			Api::ParitySet, Api::Signer,
-			Api::Private, Api::Debug,
+			Api::Private, Api::Debug, Api::Clique, Api::Evm,
 		].into_iter().collect()));
 	}
 
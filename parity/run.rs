@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::any::Any;
+use std::path::Path;
 use std::sync::{Arc, Weak};
 use std::time::{Duration, Instant};
 use std::thread;
@@ -24,13 +25,16 @@ use bytes::Bytes;
 use ethcore::account_provider::{AccountProvider, AccountProviderSettings};
 use ethcore::client::{BlockId, CallContract, Client, Mode, DatabaseCompactionProfile, VMType, BlockChainClient, BlockInfo};
 use ethcore::ethstore::ethkey;
-use ethcore::miner::{stratum, Miner, MinerService, MinerOptions};
+use ethcore::miner::{cpu_miner, stratum, Miner, MinerService, MinerOptions};
 use ethcore::snapshot::{self, SnapshotConfiguration};
-use ethcore::spec::{SpecParams, OptimizeFor};
+use ethcore::ethereum::ether;
+use ethcore::pod_account::PodAccount;
+use ethcore::pod_state::PodState;
+use ethcore::spec::{Spec, SpecParams, OptimizeFor};
 use ethcore::verification::queue::VerifierSettings;
 use ethcore_logger::{Config as LogConfig, RotatingLogger};
 use ethcore_service::ClientService;
-use ethereum_types::Address;
+use ethereum_types::{Address, U256};
 use sync::{self, SyncConfig};
 use miner::work_notify::WorkPoster;
 use futures::IntoFuture;
@@ -84,6 +88,9 @@ const FETCH_FULL_NUM_DNS_THREADS: usize = 4;
 // Light client number of DNS threads
 const FETCH_LIGHT_NUM_DNS_THREADS: usize = 1;
 
+// Ether given to each of the development accounts
+const DEV_ACCOUNT_BALANCE: u64 = 10_000;
+
 #[derive(Debug, PartialEq)]
 pub struct RunCmd {
 	pub cache_config: CacheConfig,
@@ -105,6 +112,10 @@ pub struct RunCmd {
 	pub network_id: Option<u64>,
 	pub warp_sync: bool,
 	pub warp_barrier: Option<u64>,
+	pub beam_sync: bool,
+	pub flat_state: bool,
+	pub freezer_threshold: Option<u64>,
+	pub history_retention: Option<u64>,
 	pub acc_conf: AccountsConfig,
 	pub gas_pricer_conf: GasPricerConfig,
 	pub miner_extras: MinerExtras,
@@ -124,6 +135,9 @@ pub struct RunCmd {
 	pub name: String,
 	pub custom_bootnodes: bool,
 	pub stratum: Option<stratum::Options>,
+	pub cpu_miner: Option<cpu_miner::Options>,
+	pub dev_accounts: usize,
+	pub dev_block_period: u64,
 	pub snapshot_conf: SnapshotConfiguration,
 	pub check_seal: bool,
 	pub download_old_blocks: bool,
@@ -361,7 +375,13 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 		Rr: Fn() + 'static + Send
 {
 	// load spec
-	let spec = cmd.spec.spec(&cmd.dirs.cache)?;
+	let mut spec = cmd.spec.spec(&cmd.dirs.cache)?;
+
+	// fund the development accounts, it changes the genesis.
+	let dev_accounts = dev_accounts(cmd.dev_accounts);
+	if !dev_accounts.is_empty() {
+		fund_dev_accounts(&mut spec, &dev_accounts)?;
+	}
 
 	// load genesis hash
 	let genesis_hash = spec.genesis_header().hash();
@@ -442,7 +462,12 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 	}
 
 	sync_config.fork_block = spec.fork_block();
-	let mut warp_sync = spec.engine.supports_warp() && cmd.warp_sync;
+	let mut beam_sync = cmd.beam_sync;
+	if beam_sync && !algorithm.is_stable() {
+		warn!("Warning: Beam Sync is disabled because it requires archive or fast pruning mode.");
+		beam_sync = false;
+	}
+	let mut warp_sync = spec.engine.supports_warp() && cmd.warp_sync && !beam_sync;
 	if warp_sync {
 		// Logging is not initialized yet, so we print directly to stderr
 		if fat_db {
@@ -463,11 +488,15 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 	};
 	sync_config.download_old_blocks = cmd.download_old_blocks;
 	sync_config.serve_light = cmd.serve_light;
+	sync_config.beam_sync = beam_sync;
 
 	let passwords = passwords_from_files(&cmd.acc_conf.password_files)?;
 
 	// prepare account provider
 	let account_provider = Arc::new(prepare_account_provider(&cmd.spec, &cmd.dirs, &spec.data_dir, cmd.acc_conf, &passwords)?);
+	for account in dev_accounts {
+		unlock_dev_account(&account_provider, account)?;
+	}
 
 	// spin up event loop
 	let runtime = Runtime::with_default_thread_count();
@@ -537,6 +566,10 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 	client_config.queue.verifier_settings = cmd.verifier_settings;
 	client_config.transaction_verification_queue_size = ::std::cmp::max(2048, txpool_size / 4);
 	client_config.snapshot = cmd.snapshot_conf.clone();
+	client_config.beam_sync = beam_sync;
+	client_config.flat_state = cmd.flat_state;
+	client_config.freezer_threshold = cmd.freezer_threshold;
+	client_config.history_retention = cmd.history_retention;
 
 	// set up bootnodes
 	let mut net_conf = cmd.net_conf;
@@ -627,6 +660,20 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 			.map_err(|e| format!("Stratum start error: {:?}", e))?;
 	}
 
+	// seal blocks periodically on the development chain
+	if cmd.dev_block_period > 0 && !client.engine().set_block_period(cmd.dev_block_period) {
+		return Err(format!("The block period of the {} engine can't be changed.", client.engine().name()));
+	}
+
+	// start the built-in CPU miner
+	if let Some(ref cpu_miner_config) = cmd.cpu_miner {
+		if !client.engine().seals_with_ethash() {
+			return Err("The built-in CPU miner is only supported by Ethash chains.".into());
+		}
+		cpu_miner::CpuMiner::register(cpu_miner_config, miner.clone(), Arc::downgrade(&client))
+			.map_err(|e| format!("CPU miner start error: {}", e))?;
+	}
+
 	let mut attached_protos = Vec::new();
 
 	let whisper_factory = if cmd.whisper.enabled {
@@ -639,7 +686,7 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 	};
 
 	// create sync object
-	let (sync_provider, manage_network, chain_notify) = modules::sync(
+	let (sync_provider, manage_network, chain_notify, node_fetcher) = modules::sync(
 		sync_config,
 		net_conf.clone().into(),
 		client.clone(),
@@ -653,6 +700,10 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 
 	service.add_notify(chain_notify.clone());
 
+	if let Some(fetcher) = node_fetcher {
+		client.set_node_fetcher(fetcher);
+	}
+
 	// provider not added to a notification center is effectively disabled
 	// TODO [debris] refactor it later on
 	if cmd.private_tx_enabled {
@@ -798,6 +849,13 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 	client.set_exit_handler(on_client_rq);
 	updater.set_exit_handler(on_updater_rq);
 
+	// tell client how to lay out database backups.
+	client.set_backup_handler(move |client: &Client, path: &Path| {
+		db::backup(&db_dirs, algorithm, path, |client_path| {
+			client.backup_database(client_path).map_err(|e| format!("Failed to back up database: {}", e))
+		})
+	});
+
 	Ok(RunningClient {
 		inner: RunningClientInner::Full {
 			rpc: rpc_direct,
@@ -1000,6 +1058,41 @@ fn insert_dev_account(account_provider: &AccountProvider) {
 	}
 }
 
+/// Deterministic development accounts, so that they are the same across restarts.
+fn dev_accounts(count: usize) -> Vec<ethkey::KeyPair> {
+	use ethcore::ethstore::ethkey::Generator;
+
+	(0..count)
+		.map(|i| ethkey::Brain::new(format!("parity dev account {}", i)).generate().expect("Brain wallets are always generated; qed"))
+		.collect()
+}
+
+/// Fund the development accounts in the genesis state.
+fn fund_dev_accounts(spec: &mut Spec, accounts: &[ethkey::KeyPair]) -> Result<(), String> {
+	let mut state = spec.genesis_state().get().clone();
+	for account in accounts {
+		state.insert(account.address(), PodAccount {
+			balance: ether() * U256::from(DEV_ACCOUNT_BALANCE),
+			nonce: spec.params().account_start_nonce,
+			code: Some(Vec::new()),
+			storage: Default::default(),
+		});
+	}
+	spec.set_genesis_state(PodState::from(state))
+		.map_err(|e| format!("Unable to fund the development accounts: {}", e))
+}
+
+fn unlock_dev_account(account_provider: &AccountProvider, account: ethkey::KeyPair) -> Result<(), String> {
+	let address = account.address();
+	let password = Password::from(String::new());
+	if !account_provider.has_account(address) {
+		account_provider.insert_account(account.secret().clone(), &password)
+			.map_err(|e| format!("Unable to add development account {}: {}", address, e))?;
+	}
+	account_provider.unlock_account_permanently(address, password)
+		.map_err(|e| format!("Unable to unlock development account {}: {}", address, e))
+}
+
 // Construct an error `String` with an adaptive hint on how to create an account.
 fn build_create_account_hint(spec: &SpecType, keys: &str) -> String {
 	format!("You can create an account via RPC, UI or `parity account new --chain {} --keys-path {}`.", spec, keys)
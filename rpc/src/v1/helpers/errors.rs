@@ -155,6 +155,14 @@ pub fn state_pruned() -> Error {
 	}
 }
 
+pub fn history_pruned() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::UNSUPPORTED_REQUEST),
+		message: "This request is not supported because your node has removed the history of old blocks. Run with a larger --history-retention.".into(),
+		data: None,
+	}
+}
+
 pub fn state_corrupt() -> Error {
 	internal("State corrupt", "")
 }
@@ -396,6 +404,7 @@ pub fn rlp(error: DecoderError) -> Error {
 pub fn call(error: CallError) -> Error {
 	match error {
 		CallError::StatePruned => state_pruned(),
+		CallError::HistoryPruned => history_pruned(),
 		CallError::StateCorrupt => state_corrupt(),
 		CallError::Exceptional => exceptional(),
 		CallError::Execution(e) => execution(e),
@@ -459,6 +468,7 @@ pub fn filter_block_not_found(id: BlockId) -> Error {
 			BlockId::Number(number) => format!("0x{:x}", number),
 			BlockId::Earliest => "earliest".to_string(),
 			BlockId::Latest => "latest".to_string(),
+			BlockId::Finalized => "finalized".to_string(),
 		})),
 	}
 }
@@ -215,6 +215,7 @@ impl LightFetch {
 			BlockNumber::Num(n) => BlockId::Number(n),
 			BlockNumber::Earliest => BlockId::Earliest,
 			BlockNumber::Latest => BlockId::Latest,
+			BlockNumber::Finalized => BlockId::Finalized,
 			BlockNumber::Pending => {
 				warn!("`Pending` is deprecated and may be removed in future versions. Falling back to `Latest`");
 				BlockId::Latest
@@ -478,14 +479,20 @@ impl LightFetch {
 		self.headers_by_hash(&fetch_hashes[..]).and_then(move |mut header_map| {
 			let (from_block_num, to_block_num) = {
 				let block_number = |id| match id {
-					&BlockId::Earliest => 0,
-					&BlockId::Latest => best_number,
+					&BlockId::Earliest => Some(0),
+					&BlockId::Latest => Some(best_number),
 					&BlockId::Hash(ref h) =>
-						header_map.get(h).map(|hdr| hdr.number())
-						.expect("from_block and to_block headers are fetched by hash; this closure is only called on from_block and to_block; qed"),
-					&BlockId::Number(x) => x,
+						Some(header_map.get(h).map(|hdr| hdr.number())
+						.expect("from_block and to_block headers are fetched by hash; this closure is only called on from_block and to_block; qed")),
+					&BlockId::Number(x) => Some(x),
+					// finality isn't tracked by light clients.
+					&BlockId::Finalized => None,
 				};
-				(block_number(&from_block), block_number(&to_block))
+				match (block_number(&from_block), block_number(&to_block)) {
+					(Some(from), Some(to)) => (from, to),
+					(None, _) => return Either::A(future::err(errors::filter_block_not_found(from_block))),
+					(_, None) => return Either::A(future::err(errors::filter_block_not_found(to_block))),
+				}
 			};
 
 			if to_block_num < from_block_num {
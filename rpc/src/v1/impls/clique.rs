@@ -0,0 +1,67 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Clique APIs RPC implementation
+
+use std::sync::Arc;
+
+use ethcore::client::{BlockChainClient, EngineInfo};
+
+use jsonrpc_core::Result;
+use jsonrpc_macros::Trailing;
+use v1::helpers::errors;
+use v1::traits::Clique;
+use v1::types::{BlockNumber, H160, block_number_to_id};
+
+/// Clique rpc implementation.
+pub struct CliqueClient<C> {
+	client: Arc<C>,
+}
+
+impl<C> CliqueClient<C> {
+	/// Creates new clique client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self {
+			client,
+		}
+	}
+}
+
+impl<C: BlockChainClient + EngineInfo + 'static> Clique for CliqueClient<C> {
+	fn signers(&self, block_number: Trailing<BlockNumber>) -> Result<Vec<H160>> {
+		let id = match block_number.unwrap_or_default() {
+			BlockNumber::Pending => return Err(errors::unsupported("BlockNumber::Pending is unsupported", None)),
+			num => block_number_to_id(num),
+		};
+
+		let header = self.client.block_header(id)
+			.ok_or_else(errors::unknown_block)?
+			.decode()
+			.map_err(errors::rlp)?;
+
+		self.client.engine().signers(&header)
+			.map(|signers| signers.into_iter().map(Into::into).collect())
+			.ok_or_else(|| errors::unsupported("Signers are not available for this engine or block.", None))
+	}
+
+	fn propose(&self, address: H160, authorize: bool) -> Result<bool> {
+		if self.client.engine().propose_signer(address.into(), authorize) {
+			Ok(true)
+		} else {
+			Err(errors::unsupported("The engine does not vote on signers.", None))
+		}
+	}
+}
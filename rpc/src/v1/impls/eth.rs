@@ -173,9 +173,9 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> EthClient<C, SN, S
 	fn rich_block(&self, id: BlockNumberOrId, include_txs: bool) -> Result<Option<RichBlock>> {
 		let client = &self.client;
 
-		let client_query = |id| (client.block(id), client.block_total_difficulty(id), client.block_extra_info(id), false);
+		let client_query = |id| (client.block(id), client.block_total_difficulty(id), client.block_extra_info(id), false, Some(id));
 
-		let (block, difficulty, extra, is_pending) = match id {
+		let (block, difficulty, extra, is_pending, queried_id) = match id {
 			BlockNumberOrId::Number(BlockNumber::Pending) => {
 				let info = self.client.chain_info();
 				match self.miner.pending_block(info.best_block_number) {
@@ -195,7 +195,7 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> EthClient<C, SN, S
 
 						let extra = self.client.engine().extra_info(&pending_block.header);
 
-						(Some(encoded::Block::new(pending_block.rlp_bytes())), Some(difficulty), Some(extra), true)
+						(Some(encoded::Block::new(pending_block.rlp_bytes())), Some(difficulty), Some(extra), true, None)
 					},
 					None => {
 						warn!("`Pending` is deprecated and may be removed in future versions. Falling back to `Latest`");
@@ -207,6 +207,7 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> EthClient<C, SN, S
 			BlockNumberOrId::Number(num) => {
 				let id = match num {
 					BlockNumber::Latest => BlockId::Latest,
+					BlockNumber::Finalized => BlockId::Finalized,
 					BlockNumber::Earliest => BlockId::Earliest,
 					BlockNumber::Num(n) => BlockId::Number(n),
 					BlockNumber::Pending => unreachable!() // Already covered
@@ -259,6 +260,7 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> EthClient<C, SN, S
 					extra_info: extra.expect(EXTRA_INFO_PROOF),
 				}))
 			},
+			(None, Some(_)) if queried_id.map_or(false, |id| is_history_pruned(&*self.client, id)) => Err(errors::history_pruned()),
 			_ => Ok(None)
 		}
 	}
@@ -273,7 +275,10 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> EthClient<C, SN, S
 			PendingTransactionId::Hash(hash) => client_transaction(TransactionId::Hash(hash)),
 
 			PendingTransactionId::Location(PendingOrBlock::Block(block), index) => {
-				client_transaction(TransactionId::Location(block, index))
+				match client_transaction(TransactionId::Location(block, index))? {
+					None if is_history_pruned(&*self.client, block) => Err(errors::history_pruned()),
+					transaction => Ok(transaction),
+				}
 			},
 
 			PendingTransactionId::Location(PendingOrBlock::Pending, index) => {
@@ -402,6 +407,7 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> EthClient<C, SN, S
 			BlockNumber::Num(num) => BlockId::Number(num).into(),
 			BlockNumber::Earliest => BlockId::Earliest.into(),
 			BlockNumber::Latest => BlockId::Latest.into(),
+			BlockNumber::Finalized => BlockId::Finalized.into(),
 
 			BlockNumber::Pending => {
 				let info = self.client.chain_info();
@@ -416,6 +422,18 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> EthClient<C, SN, S
 	}
 }
 
+/// Checks whether the body and receipts of the given block have been removed by history expiry.
+pub fn is_history_pruned<C>(client: &C, id: BlockId) -> bool where C: BlockChainClient {
+	let earliest_history = client.pruning_info().earliest_history;
+	earliest_history > 0 && client.block_number(id).map_or(false, |number| number < earliest_history)
+}
+
+/// Checks whether transactions looked up by hash may be missing because the
+/// transaction index of old blocks has been removed by history expiry.
+fn is_transaction_index_pruned<C>(client: &C) -> bool where C: BlockChainClient {
+	client.pruning_info().earliest_history > 0
+}
+
 pub fn pending_logs<M>(miner: &M, best_block: EthBlockNumber, filter: &EthcoreFilter) -> Vec<Log> where M: MinerService {
 	let receipts = miner.pending_receipts(best_block).unwrap_or_default();
 
@@ -441,6 +459,7 @@ fn check_known<C>(client: &C, number: BlockNumber) -> Result<()> where C: BlockC
 
 		BlockNumber::Num(n) => BlockId::Number(n),
 		BlockNumber::Latest => BlockId::Latest,
+		BlockNumber::Finalized => BlockId::Finalized,
 		BlockNumber::Earliest => BlockId::Earliest,
 	};
 
@@ -656,6 +675,9 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<
 				.map(|t| Transaction::from_pending(t.pending().clone()))
 		});
 
+		if tx.is_none() && is_transaction_index_pruned(&*self.client) {
+			return Box::new(future::err(errors::history_pruned()));
+		}
 		Box::new(future::ok(tx))
 	}
 
@@ -667,6 +689,7 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<
 	fn transaction_by_block_number_and_index(&self, num: BlockNumber, index: Index) -> BoxFuture<Option<Transaction>> {
 		let block_id = match num {
 			BlockNumber::Latest => PendingOrBlock::Block(BlockId::Latest),
+			BlockNumber::Finalized => PendingOrBlock::Block(BlockId::Finalized),
 			BlockNumber::Earliest => PendingOrBlock::Block(BlockId::Earliest),
 			BlockNumber::Num(num) => PendingOrBlock::Block(BlockId::Number(num)),
 			BlockNumber::Pending => PendingOrBlock::Pending,
@@ -687,6 +710,10 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<
 		}
 
 		let receipt = self.client.transaction_receipt(TransactionId::Hash(hash));
+		// transactions still in the queue just aren't mined yet.
+		if receipt.is_none() && is_transaction_index_pruned(&*self.client) && self.miner.transaction(&hash).is_none() {
+			return Box::new(future::err(errors::history_pruned()));
+		}
 		Box::new(future::ok(receipt.map(Into::into)))
 	}
 
@@ -700,6 +727,7 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<
 	fn uncle_by_block_number_and_index(&self, num: BlockNumber, index: Index) -> BoxFuture<Option<RichBlock>> {
 		let id = match num {
 			BlockNumber::Latest => PendingUncleId { id: PendingOrBlock::Block(BlockId::Latest), position: index.value() },
+			BlockNumber::Finalized => PendingUncleId { id: PendingOrBlock::Block(BlockId::Finalized), position: index.value() },
 			BlockNumber::Earliest => PendingUncleId { id: PendingOrBlock::Block(BlockId::Earliest), position: index.value() },
 			BlockNumber::Num(num) => PendingUncleId { id: PendingOrBlock::Block(BlockId::Number(num)), position: index.value() },
 
@@ -719,6 +747,9 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<
 			Ok(value) => value,
 			Err(err) => return Box::new(future::err(err)),
 		};
+		if is_history_pruned(&*self.client, filter.from_block) {
+			return Box::new(future::err(errors::history_pruned()));
+		}
 		let mut logs = match self.client.logs(filter.clone()) {
 			Ok(logs) => logs
 				.into_iter()
@@ -773,10 +804,13 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<
 		let target = ethash::difficulty_to_boundary(&difficulty);
 		let seed_hash = self.seed_compute.lock().hash_block_number(number);
 
+		// ProgPoW programs change with the block number, so miners always need it past the transition.
+		let progpow = self.client.engine().progpow_transition().map_or(false, |transition| number >= transition);
+
 		let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
 		if no_new_work_timeout > 0 && timestamp + no_new_work_timeout < now {
 			Err(errors::no_new_work())
-		} else if self.options.send_block_number_in_get_work {
+		} else if self.options.send_block_number_in_get_work || progpow {
 			Ok(Work {
 				pow_hash: pow_hash.into(),
 				seed_hash: seed_hash.into(),
@@ -841,6 +875,7 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<
 				BlockNumber::Num(num) => BlockId::Number(num),
 				BlockNumber::Earliest => BlockId::Earliest,
 				BlockNumber::Latest => BlockId::Latest,
+				BlockNumber::Finalized => BlockId::Finalized,
 				BlockNumber::Pending => unreachable!(), // Already covered
 			};
 
@@ -880,6 +915,7 @@ impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<
 				BlockNumber::Num(num) => BlockId::Number(num),
 				BlockNumber::Earliest => BlockId::Earliest,
 				BlockNumber::Latest => BlockId::Latest,
+				BlockNumber::Finalized => BlockId::Finalized,
 				BlockNumber::Pending => unreachable!(), // Already covered
 			};
 
@@ -31,7 +31,7 @@ use jsonrpc_core::futures::future::Either;
 use v1::traits::EthFilter;
 use v1::types::{BlockNumber, Index, Filter, FilterChanges, Log, H256 as RpcH256, U256 as RpcU256};
 use v1::helpers::{errors, SyncPollFilter, PollFilter, PollManager, limit_logs};
-use v1::impls::eth::pending_logs;
+use v1::impls::eth::{pending_logs, is_history_pruned};
 
 /// Something which provides data that can be filtered over.
 pub trait Filterable {
@@ -92,6 +92,9 @@ impl<C, M> Filterable for EthFilterClient<C, M> where
 	}
 
 	fn logs(&self, filter: EthcoreFilter) -> BoxFuture<Vec<Log>> {
+		if is_history_pruned(&*self.client, filter.from_block) {
+			return Box::new(future::err(errors::history_pruned()));
+		}
 		Box::new(future::ok(self.client.logs(filter).unwrap_or_default().into_iter().map(Into::into).collect()))
 	}
 
@@ -16,6 +16,7 @@
 
 //! Eth PUB-SUB rpc implementation.
 
+use std::cmp;
 use std::sync::{Arc, Weak};
 use std::collections::BTreeMap;
 use std::time::Duration;
@@ -46,10 +47,14 @@ use parking_lot::{RwLock, Mutex};
 
 type Client = Sink<pubsub::Result>;
 
+/// Maximal number of finalized headers notified at once, e.g. after syncing.
+const MAX_FINALIZED_HEADS: u64 = 256;
+
 /// Eth PubSub implementation.
 pub struct EthPubSubClient<C> {
 	handler: Arc<ChainNotificationHandler<C>>,
 	heads_subscribers: Arc<RwLock<Subscribers<Client>>>,
+	finalized_heads_subscribers: Arc<RwLock<Subscribers<Client>>>,
 	logs_subscribers: Arc<RwLock<Subscribers<(Client, EthFilter)>>>,
 	transactions_subscribers: Arc<RwLock<Subscribers<Client>>>,
 }
@@ -58,6 +63,7 @@ impl<C> EthPubSubClient<C> {
 	/// Creates new `EthPubSubClient`.
 	pub fn new(client: Arc<C>, executor: Executor) -> Self {
 		let heads_subscribers = Arc::new(RwLock::new(Subscribers::default()));
+		let finalized_heads_subscribers = Arc::new(RwLock::new(Subscribers::default()));
 		let logs_subscribers = Arc::new(RwLock::new(Subscribers::default()));
 		let transactions_subscribers = Arc::new(RwLock::new(Subscribers::default()));
 
@@ -66,10 +72,13 @@ impl<C> EthPubSubClient<C> {
 				client,
 				executor,
 				heads_subscribers: heads_subscribers.clone(),
+				finalized_heads_subscribers: finalized_heads_subscribers.clone(),
+				last_finalized: Mutex::new(None),
 				logs_subscribers: logs_subscribers.clone(),
 				transactions_subscribers: transactions_subscribers.clone(),
 			}),
 			heads_subscribers,
+			finalized_heads_subscribers,
 			logs_subscribers,
 			transactions_subscribers,
 		}
@@ -80,6 +89,7 @@ impl<C> EthPubSubClient<C> {
 	pub fn new_test(client: Arc<C>, executor: Executor) -> Self {
 		let client = Self::new(client, executor);
 		*client.heads_subscribers.write() = Subscribers::new_test();
+		*client.finalized_heads_subscribers.write() = Subscribers::new_test();
 		*client.logs_subscribers.write() = Subscribers::new_test();
 		*client.transactions_subscribers.write() = Subscribers::new_test();
 		client
@@ -117,6 +127,9 @@ pub struct ChainNotificationHandler<C> {
 	client: Arc<C>,
 	executor: Executor,
 	heads_subscribers: Arc<RwLock<Subscribers<Client>>>,
+	finalized_heads_subscribers: Arc<RwLock<Subscribers<Client>>>,
+	/// Number of the last finalized block subscribers were notified about.
+	last_finalized: Mutex<Option<u64>>,
 	logs_subscribers: Arc<RwLock<Subscribers<(Client, EthFilter)>>>,
 	transactions_subscribers: Arc<RwLock<Subscribers<Client>>>,
 }
@@ -130,8 +143,8 @@ impl<C> ChainNotificationHandler<C> {
 		);
 	}
 
-	fn notify_heads(&self, headers: &[(encoded::Header, BTreeMap<String, String>)]) {
-		for subscriber in self.heads_subscribers.read().values() {
+	fn notify_heads(&self, subscribers: &RwLock<Subscribers<Client>>, headers: &[(encoded::Header, BTreeMap<String, String>)]) {
+		for subscriber in subscribers.read().values() {
 			for &(ref header, ref extra_info) in headers {
 				Self::notify(&self.executor, subscriber, pubsub::Result::Header(RichHeader {
 					inner: header.into(),
@@ -214,11 +227,48 @@ impl<C: LightClient> LightChainNotify for ChainNotificationHandler<C> {
 			.map(|header| (header, Default::default()))
 			.collect::<Vec<_>>();
 
-		self.notify_heads(&headers);
+		self.notify_heads(&self.heads_subscribers, &headers);
 		self.notify_logs(&enacted.iter().map(|h| (*h, ())).collect::<Vec<_>>(), |filter, _| self.client.logs(filter))
 	}
 }
 
+impl<C: BlockChainClient> ChainNotificationHandler<C> {
+	/// Notify about the blocks finalized since the last notification.
+	fn notify_finalized_heads(&self) {
+		const EXTRA_INFO_PROOF: &'static str = "Object exists in in blockchain (fetched earlier), extra_info is always available if object exists; qed";
+
+		let finalized = match self.client.block_number(BlockId::Finalized) {
+			Some(number) => number,
+			None => return,
+		};
+
+		let from = {
+			let mut last = self.last_finalized.lock();
+			let from = match *last {
+				Some(number) if number >= finalized => return,
+				Some(number) => cmp::max(number + 1, (finalized + 1).saturating_sub(MAX_FINALIZED_HEADS)),
+				None => finalized,
+			};
+			*last = Some(finalized);
+			from
+		};
+
+		if self.finalized_heads_subscribers.read().is_empty() {
+			return;
+		}
+
+		let headers = (from..finalized + 1)
+			.filter_map(|number| self.client.block_header(BlockId::Number(number)))
+			.map(|header| {
+				let hash = header.hash();
+				(header, self.client.block_extra_info(BlockId::Hash(hash)).expect(EXTRA_INFO_PROOF))
+			})
+			.collect::<Vec<_>>();
+
+		self.notify_heads(&self.finalized_heads_subscribers, &headers);
+	}
+}
+
 impl<C: BlockChainClient> ChainNotify for ChainNotificationHandler<C> {
 	fn new_blocks(
 		&self,
@@ -246,7 +296,8 @@ impl<C: BlockChainClient> ChainNotify for ChainNotificationHandler<C> {
 			.collect::<Vec<_>>();
 
 		// Headers
-		self.notify_heads(&headers);
+		self.notify_heads(&self.heads_subscribers, &headers);
+		self.notify_finalized_heads();
 
 		// We notify logs enacting and retracting as the order in route.
 		self.notify_logs(route.route(), |filter, ex| {
@@ -282,6 +333,13 @@ impl<C: Send + Sync + 'static> EthPubSub for EthPubSubClient<C> {
 			(pubsub::Kind::NewHeads, _) => {
 				errors::invalid_params("newHeads", "Expected no parameters.")
 			},
+			(pubsub::Kind::NewFinalizedHeads, None) => {
+				self.finalized_heads_subscribers.write().push(subscriber);
+				return;
+			},
+			(pubsub::Kind::NewFinalizedHeads, _) => {
+				errors::invalid_params("newFinalizedHeads", "Expected no parameters.")
+			},
 			(pubsub::Kind::Logs, Some(pubsub::Params::Logs(filter))) => {
 				match filter.try_into() {
 					Ok(filter) => {
@@ -313,7 +371,8 @@ impl<C: Send + Sync + 'static> EthPubSub for EthPubSubClient<C> {
 		let res = self.heads_subscribers.write().remove(&id).is_some();
 		let res2 = self.logs_subscribers.write().remove(&id).is_some();
 		let res3 = self.transactions_subscribers.write().remove(&id).is_some();
+		let res4 = self.finalized_heads_subscribers.write().remove(&id).is_some();
 
-		Ok(res || res2 || res3)
+		Ok(res || res2 || res3 || res4)
 	}
 }
@@ -0,0 +1,79 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Development chain RPC implementation
+
+use std::sync::Arc;
+
+use ethcore::client::{BlockChainClient, BlockId, EngineInfo};
+use ethereum_types::{H256, U256 as EthU256};
+use parking_lot::Mutex;
+
+use jsonrpc_core::Result;
+use v1::helpers::errors;
+use v1::traits::Evm;
+use v1::types::U256;
+
+/// Evm rpc implementation.
+pub struct EvmClient<C> {
+	client: Arc<C>,
+	snapshots: Mutex<Vec<H256>>,
+}
+
+impl<C> EvmClient<C> {
+	/// Creates new evm client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self {
+			client,
+			snapshots: Mutex::new(Vec::new()),
+		}
+	}
+}
+
+impl<C: BlockChainClient + EngineInfo + 'static> Evm for EvmClient<C> {
+	fn mine(&self) -> Result<bool> {
+		if self.client.engine().seal_on_demand() {
+			Ok(true)
+		} else {
+			Err(errors::unsupported("The engine does not seal blocks on demand.", None))
+		}
+	}
+
+	fn increase_time(&self, seconds: u64) -> Result<u64> {
+		self.client.engine().increase_time(seconds)
+			.ok_or_else(|| errors::unsupported("The engine does not support changing the time.", None))
+	}
+
+	fn snapshot(&self) -> Result<U256> {
+		let mut snapshots = self.snapshots.lock();
+		snapshots.push(self.client.chain_info().best_block_hash);
+		Ok((snapshots.len() as u64).into())
+	}
+
+	fn revert(&self, id: U256) -> Result<bool> {
+		let mut snapshots = self.snapshots.lock();
+		let id: EthU256 = id.into();
+		if id.is_zero() || id > EthU256::from(snapshots.len() as u64) {
+			return Ok(false);
+		}
+
+		let index = id.low_u64() as usize - 1;
+		self.client.revert_to(BlockId::Hash(snapshots[index]))
+			.map_err(|e| errors::internal("Could not revert the chain", e))?;
+		snapshots.truncate(index);
+		Ok(true)
+	}
+}
@@ -28,7 +28,7 @@ use ethcore::account_provider::AccountProvider;
 use ethcore_logger::RotatingLogger;
 
 use jsonrpc_core::{Result, BoxFuture};
-use jsonrpc_core::futures::Future;
+use jsonrpc_core::futures::{future, Future};
 use jsonrpc_macros::Trailing;
 use v1::helpers::{self, errors, ipfs, SigningQueue, SignerService, NetworkSettings};
 use v1::helpers::dispatch::LightDispatcher;
@@ -37,11 +37,11 @@ use v1::metadata::Metadata;
 use v1::traits::Parity;
 use v1::types::{
 	Bytes, U256, H64, H160, H256, H512, CallRequest,
-	Peers, Transaction, RpcSettings, Histogram,
+	Peers, PeerReputation, Transaction, RpcSettings, Histogram,
 	TransactionStats, LocalTransactionStatus,
 	BlockNumber, LightBlockNumber, ConsensusCapability, VersionInfo,
 	OperationsInfo, ChainStatus,
-	AccountInfo, HwAccountInfo, Header, RichHeader, Receipt,
+	AccountInfo, HwAccountInfo, Header, RichHeader, Receipt, Equivocation,
 };
 use Host;
 
@@ -181,6 +181,10 @@ impl Parity for ParityClient {
 		})
 	}
 
+	fn peer_reputation(&self) -> Result<Vec<PeerReputation>> {
+		Err(errors::light_unimplemented(None))
+	}
+
 	fn net_port(&self) -> Result<u16> {
 		Ok(self.settings.network_port)
 	}
@@ -398,6 +402,14 @@ impl Parity for ParityClient {
 		Box::new(self.fetcher().header(id).and_then(from_encoded))
 	}
 
+	fn finalized_block(&self) -> BoxFuture<RichHeader> {
+		Box::new(future::err(errors::light_unimplemented(Some("Light clients don't track finality.".into()))))
+	}
+
+	fn equivocations(&self) -> Result<Vec<Equivocation>> {
+		Err(errors::light_unimplemented(Some("Light clients don't collect equivocation evidence.".into())))
+	}
+
 	fn block_receipts(&self, number: Trailing<BlockNumber>) -> BoxFuture<Vec<Receipt>> {
 		let id = number.unwrap_or_default().to_block_id();
 		Box::new(self.fetcher().receipts(id).and_then(|receipts| Ok(receipts.into_iter().map(Into::into).collect())))
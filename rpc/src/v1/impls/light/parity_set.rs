@@ -25,7 +25,7 @@ use fetch::{self, Fetch};
 use hash::keccak_buffer;
 
 use jsonrpc_core::{Result, BoxFuture};
-use jsonrpc_core::futures::Future;
+use jsonrpc_core::futures::{future, Future};
 use v1::helpers::errors;
 use v1::traits::ParitySet;
 use v1::types::{Bytes, H160, H256, U256, ReleaseInfo, Transaction};
@@ -121,6 +121,10 @@ impl<F: Fetch> ParitySet for ParitySetClient<F> {
 		Err(errors::light_unimplemented(None))
 	}
 
+	fn backup(&self, _path: String) -> BoxFuture<bool> {
+		Box::new(future::err(errors::light_unimplemented(None)))
+	}
+
 	fn hash_content(&self, url: String) -> BoxFuture<H256> {
 		let future = self.fetch.get(&url, Default::default()).then(move |result| {
 			result
@@ -27,7 +27,7 @@ use ethkey::{crypto::ecies, Brain, Generator};
 use ethstore::random_phrase;
 use sync::{SyncProvider, ManageNetwork};
 use ethcore::account_provider::AccountProvider;
-use ethcore::client::{BlockChainClient, StateClient, Call};
+use ethcore::client::{BlockChainClient, StateClient, Call, EngineInfo};
 use ethcore::ids::BlockId;
 use ethcore::miner::{self, MinerService};
 use ethcore::snapshot::{SnapshotService, RestorationStatus};
@@ -44,11 +44,11 @@ use v1::metadata::Metadata;
 use v1::traits::Parity;
 use v1::types::{
 	Bytes, U256, H64, H160, H256, H512, CallRequest,
-	Peers, Transaction, RpcSettings, Histogram,
+	Peers, PeerReputation, Transaction, RpcSettings, Histogram,
 	TransactionStats, LocalTransactionStatus,
 	BlockNumber, ConsensusCapability, VersionInfo,
 	OperationsInfo, ChainStatus,
-	AccountInfo, HwAccountInfo, RichHeader, Receipt,
+	AccountInfo, HwAccountInfo, RichHeader, Receipt, Equivocation,
 	block_number_to_id
 };
 use Host;
@@ -103,7 +103,7 @@ impl<C, M, U> ParityClient<C, M, U> where
 
 impl<C, M, U, S> Parity for ParityClient<C, M, U> where
 	S: StateInfo + 'static,
-	C: miner::BlockChainClient + BlockChainClient + StateClient<State=S> + Call<State=S> + 'static,
+	C: miner::BlockChainClient + BlockChainClient + StateClient<State=S> + Call<State=S> + EngineInfo + 'static,
 	M: MinerService<State=S> + 'static,
 	U: UpdateService + 'static,
 {
@@ -197,6 +197,10 @@ impl<C, M, U, S> Parity for ParityClient<C, M, U> where
 		})
 	}
 
+	fn peer_reputation(&self) -> Result<Vec<PeerReputation>> {
+		Ok(self.net.node_reputations().into_iter().map(Into::into).collect())
+	}
+
 	fn net_port(&self) -> Result<u16> {
 		Ok(self.settings.network_port)
 	}
@@ -408,6 +412,7 @@ impl<C, M, U, S> Parity for ParityClient<C, M, U> where
 				BlockNumber::Num(num) => BlockId::Number(num),
 				BlockNumber::Earliest => BlockId::Earliest,
 				BlockNumber::Latest => BlockId::Latest,
+				BlockNumber::Finalized => BlockId::Finalized,
 				BlockNumber::Pending => unreachable!(), // Already covered
 			};
 
@@ -423,6 +428,22 @@ impl<C, M, U, S> Parity for ParityClient<C, M, U> where
 		}))
 	}
 
+	fn finalized_block(&self) -> BoxFuture<RichHeader> {
+		const EXTRA_INFO_PROOF: &str = "Object exists in blockchain (fetched earlier), extra_info is always available if object exists; qed";
+
+		let header = try_bf!(self.client.block_header(BlockId::Finalized).ok_or_else(errors::unknown_block));
+		let extra_info = self.client.block_extra_info(BlockId::Hash(header.hash())).expect(EXTRA_INFO_PROOF);
+
+		Box::new(future::ok(RichHeader {
+			inner: header.into(),
+			extra_info: extra_info,
+		}))
+	}
+
+	fn equivocations(&self) -> Result<Vec<Equivocation>> {
+		Ok(self.client.engine().equivocations().into_iter().map(Into::into).collect())
+	}
+
 	fn block_receipts(&self, number: Trailing<BlockNumber>) -> BoxFuture<Vec<Receipt>> {
 		let number = number.unwrap_or_default();
 
@@ -439,6 +460,7 @@ impl<C, M, U, S> Parity for ParityClient<C, M, U> where
 			BlockNumber::Num(num) => BlockId::Number(num),
 			BlockNumber::Earliest => BlockId::Earliest,
 			BlockNumber::Latest => BlockId::Latest,
+			BlockNumber::Finalized => BlockId::Finalized,
 		};
 		let receipts = try_bf!(self.client.block_receipts(id).ok_or_else(errors::unknown_block));
 		Box::new(future::ok(receipts.into_iter().map(Into::into).collect()))
@@ -470,6 +492,7 @@ impl<C, M, U, S> Parity for ParityClient<C, M, U> where
 				BlockNumber::Num(num) => BlockId::Number(num),
 				BlockNumber::Earliest => BlockId::Earliest,
 				BlockNumber::Latest => BlockId::Latest,
+				BlockNumber::Finalized => BlockId::Finalized,
 				BlockNumber::Pending => unreachable!(), // Already covered
 			};
 
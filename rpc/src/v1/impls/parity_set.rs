@@ -15,7 +15,8 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 /// Parity-specific rpc interface for operations altering the settings.
-use std::io;
+use std::{io, thread};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -27,8 +28,8 @@ use hash::keccak_buffer;
 use updater::{Service as UpdateService};
 
 use jsonrpc_core::{BoxFuture, Result};
-use jsonrpc_core::futures::Future;
-use v1::helpers::errors;
+use jsonrpc_core::futures::{future, Future};
+use v1::helpers::{errors, oneshot};
 use v1::traits::ParitySet;
 use v1::types::{Bytes, H160, H256, U256, ReleaseInfo, Transaction};
 
@@ -163,6 +164,21 @@ impl<C, M, U, F> ParitySet for ParitySetClient<C, M, U, F> where
 		Ok(true)
 	}
 
+	fn backup(&self, path: String) -> BoxFuture<bool> {
+		// copying the ancient blocks takes a while, don't block the RPC threads.
+		let client = self.client.clone();
+		let (sender, receiver) = oneshot::oneshot();
+		let spawned = thread::Builder::new().name("Backup".into()).spawn(move || {
+			let result = client.backup(&PathBuf::from(path)).map(|_| true).map_err(errors::database);
+			sender.send(result);
+		});
+
+		match spawned {
+			Ok(_) => Box::new(receiver),
+			Err(e) => Box::new(future::err(errors::internal("Failed to start the backup", e))),
+		}
+	}
+
 	fn hash_content(&self, url: String) -> BoxFuture<H256> {
 		let future = self.fetch.get(&url, Default::default()).then(move |result| {
 			result
@@ -97,6 +97,7 @@ impl<C, S> Traces for TracesClient<C> where
 			BlockNumber::Num(num) => BlockId::Number(num),
 			BlockNumber::Earliest => BlockId::Earliest,
 			BlockNumber::Latest => BlockId::Latest,
+			BlockNumber::Finalized => BlockId::Finalized,
 
 			BlockNumber::Pending => return Err(errors::invalid_params("`BlockNumber::Pending` is not supported", ())),
 		};
@@ -124,6 +125,7 @@ impl<C, S> Traces for TracesClient<C> where
 			BlockNumber::Num(num) => BlockId::Number(num),
 			BlockNumber::Earliest => BlockId::Earliest,
 			BlockNumber::Latest => BlockId::Latest,
+			BlockNumber::Finalized => BlockId::Finalized,
 
 			BlockNumber::Pending => return Err(errors::invalid_params("`BlockNumber::Pending` is not supported", ())),
 		};
@@ -146,6 +148,7 @@ impl<C, S> Traces for TracesClient<C> where
 			BlockNumber::Num(num) => BlockId::Number(num),
 			BlockNumber::Earliest => BlockId::Earliest,
 			BlockNumber::Latest => BlockId::Latest,
+			BlockNumber::Finalized => BlockId::Finalized,
 
 			BlockNumber::Pending => return Err(errors::invalid_params("`BlockNumber::Pending` is not supported", ())),
 		};
@@ -169,6 +172,7 @@ impl<C, S> Traces for TracesClient<C> where
 			BlockNumber::Num(num) => BlockId::Number(num),
 			BlockNumber::Earliest => BlockId::Earliest,
 			BlockNumber::Latest => BlockId::Latest,
+			BlockNumber::Finalized => BlockId::Finalized,
 
 			BlockNumber::Pending => return Err(errors::invalid_params("`BlockNumber::Pending` is not supported", ())),
 		};
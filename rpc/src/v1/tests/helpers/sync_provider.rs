@@ -19,7 +19,7 @@
 use std::collections::BTreeMap;
 use ethereum_types::H256;
 use parking_lot::RwLock;
-use sync::{SyncProvider, EthProtocolInfo, SyncStatus, SyncState, PeerInfo, TransactionStats};
+use sync::{SyncProvider, EthProtocolInfo, SyncStatus, SyncState, PeerInfo, TransactionStats, TrafficStats};
 
 /// TestSyncProvider config.
 pub struct Config {
@@ -85,6 +85,10 @@ impl SyncProvider for TestSyncProvider {
 					head: 50.into(),
 				}),
 				pip_info: None,
+				traffic: TrafficStats { bytes_in: 1024, bytes_out: 4096 },
+				protocol_traffic: map![
+					"eth".to_owned() => TrafficStats { bytes_in: 1000, bytes_out: 4000 }
+				],
 			},
 			PeerInfo {
 				id: None,
@@ -98,6 +102,8 @@ impl SyncProvider for TestSyncProvider {
 					head: 60.into()
 				}),
 				pip_info: None,
+				traffic: Default::default(),
+				protocol_traffic: BTreeMap::new(),
 			}
 		]
 	}
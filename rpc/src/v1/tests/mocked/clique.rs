@@ -0,0 +1,52 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use ethcore::client::TestBlockChainClient;
+use ethcore::spec::Spec;
+
+use jsonrpc_core::IoHandler;
+use v1::{Clique, CliqueClient};
+
+fn io(spec: Spec) -> IoHandler {
+	let client = Arc::new(TestBlockChainClient::new_with_spec(spec));
+
+	let mut io = IoHandler::new();
+	io.extend_with(CliqueClient::new(client).to_delegate());
+	io
+}
+
+#[test]
+fn rpc_clique_get_signers() {
+	let request = r#"{"jsonrpc": "2.0", "method": "clique_getSigners", "params": ["latest"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":["0x7d577a597b2742b498cb5cf0c26cdcd726d39e6e","0x82a978b3f5962a5b0957d9ee9eef472ee55b42f1"],"id":1}"#;
+	assert_eq!(io(Spec::new_test_clique()).handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_clique_propose() {
+	let request = r#"{"jsonrpc": "2.0", "method": "clique_propose", "params": ["0x0000000000000000000000000000000000000005", true], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	assert_eq!(io(Spec::new_test_clique()).handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_clique_propose_unsupported() {
+	let request = r#"{"jsonrpc": "2.0", "method": "clique_propose", "params": ["0x0000000000000000000000000000000000000005", true], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32000,"message":"The engine does not vote on signers."},"id":1}"#;
+	assert_eq!(io(Spec::new_test()).handle_request_sync(request), Some(response.to_owned()));
+}
@@ -82,6 +82,55 @@ fn should_subscribe_to_new_heads() {
 	assert_eq!(res, None);
 }
 
+#[test]
+fn should_subscribe_to_new_finalized_heads() {
+	// given
+	let el = Runtime::with_thread_count(1);
+	let client = Arc::new(TestBlockChainClient::new());
+	// Insert some blocks, the last 12 aren't final yet
+	client.add_blocks(14, EachBlockWith::Nothing);
+
+	let pubsub = EthPubSubClient::new_test(client.clone(), el.executor());
+	let handler = pubsub.handler().upgrade().unwrap();
+	let pubsub = pubsub.to_delegate();
+
+	let mut io = MetaIoHandler::default();
+	io.extend_with(pubsub);
+
+	let mut metadata = Metadata::default();
+	let (sender, receiver) = futures::sync::mpsc::channel(8);
+	metadata.session = Some(Arc::new(Session::new(sender)));
+
+	// Subscribe
+	let request = r#"{"jsonrpc": "2.0", "method": "eth_subscribe", "params": ["newFinalizedHeads"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":"0x416d77337e24399d","id":1}"#;
+	assert_eq!(io.handle_request_sync(request, metadata.clone()), Some(response.to_owned()));
+
+	// Only the latest finalized block is notified at first
+	handler.new_blocks(vec![], vec![], ChainRoute::new(vec![]), vec![], vec![], DURATION_ZERO);
+	let (res, receiver) = receiver.into_future().wait().unwrap();
+	assert!(res.unwrap().contains(r#""number":"0x2""#));
+
+	// Nothing new got finalized
+	handler.new_blocks(vec![], vec![], ChainRoute::new(vec![]), vec![], vec![], DURATION_ZERO);
+
+	// Two more blocks are finalized
+	client.add_blocks(2, EachBlockWith::Nothing);
+	handler.new_blocks(vec![], vec![], ChainRoute::new(vec![]), vec![], vec![], DURATION_ZERO);
+	let (res, receiver) = receiver.into_future().wait().unwrap();
+	assert!(res.unwrap().contains(r#""number":"0x3""#));
+	let (res, receiver) = receiver.into_future().wait().unwrap();
+	assert!(res.unwrap().contains(r#""number":"0x4""#));
+
+	// And unsubscribe
+	let request = r#"{"jsonrpc": "2.0", "method": "eth_unsubscribe", "params": ["0x416d77337e24399d"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	assert_eq!(io.handle_request_sync(request, metadata), Some(response.to_owned()));
+
+	let (res, _receiver) = receiver.into_future().wait().unwrap();
+	assert_eq!(res, None);
+}
+
 #[test]
 fn should_subscribe_to_logs() {
 	use ethcore::log_entry::{LocalizedLogEntry, LogEntry};
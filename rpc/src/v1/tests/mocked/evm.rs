@@ -0,0 +1,87 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use ethcore::client::{ChainInfo, EachBlockWith, TestBlockChainClient};
+use ethcore::spec::Spec;
+
+use jsonrpc_core::IoHandler;
+use v1::{Evm, EvmClient};
+
+fn io(client: Arc<TestBlockChainClient>) -> IoHandler {
+	let mut io = IoHandler::new();
+	io.extend_with(EvmClient::new(client).to_delegate());
+	io
+}
+
+#[test]
+fn rpc_evm_mine() {
+	let io = io(Arc::new(TestBlockChainClient::new_with_spec(Spec::new_instant())));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "evm_mine", "params": [], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_evm_mine_unsupported() {
+	let io = io(Arc::new(TestBlockChainClient::new()));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "evm_mine", "params": [], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32000,"message":"The engine does not seal blocks on demand."},"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_evm_increase_time() {
+	let io = io(Arc::new(TestBlockChainClient::new_with_spec(Spec::new_instant())));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "evm_increaseTime", "params": [60], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":60,"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "evm_increaseTime", "params": [30], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":90,"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_evm_snapshot_and_revert() {
+	let client = Arc::new(TestBlockChainClient::new());
+	let io = io(client.clone());
+	client.add_blocks(2, EachBlockWith::Nothing);
+
+	let request = r#"{"jsonrpc": "2.0", "method": "evm_snapshot", "params": [], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":"0x1","id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+
+	client.add_blocks(3, EachBlockWith::Nothing);
+	let response = r#"{"jsonrpc":"2.0","result":"0x2","id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+	client.add_blocks(1, EachBlockWith::Nothing);
+	assert_eq!(client.chain_info().best_block_number, 6);
+
+	let request = r#"{"jsonrpc": "2.0", "method": "evm_revert", "params": ["0x1"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+	assert_eq!(client.chain_info().best_block_number, 2);
+
+	// the later snapshot was dropped along with the reverted one.
+	let request = r#"{"jsonrpc": "2.0", "method": "evm_revert", "params": ["0x2"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":false,"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
@@ -15,7 +15,8 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::ops::Range;
-use sync::ManageNetwork;
+use std::time::{Duration, UNIX_EPOCH};
+use sync::{ManageNetwork, NodeReputation};
 use self::ethcore_network::{ProtocolId, NetworkContext};
 
 extern crate ethcore_network;
@@ -32,4 +33,12 @@ impl ManageNetwork for TestManageNetwork {
 	fn stop_network(&self) {}
 	fn num_peers_range(&self) -> Range<u32> { 25 .. 51 }
 	fn with_proto_context(&self, _: ProtocolId, _: &mut FnMut(&NetworkContext)) { }
+	fn node_reputations(&self) -> Vec<NodeReputation> {
+		vec![NodeReputation {
+			id: 1.into(),
+			url: "enode://0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001@127.0.0.1:30303".into(),
+			score: -120,
+			banned_until: Some(UNIX_EPOCH + Duration::from_secs(1_500_000_000)),
+		}]
+	}
 }
@@ -16,7 +16,7 @@
 
 use std::sync::Arc;
 use ethcore::account_provider::AccountProvider;
-use ethcore::client::{TestBlockChainClient, Executed, TransactionId};
+use ethcore::client::{BlockChainClient, BlockId, EachBlockWith, TestBlockChainClient, Executed, TransactionId};
 use ethcore::receipt::{LocalizedReceipt, TransactionOutcome};
 use ethcore_logger::RotatingLogger;
 use ethereum_types::{Address, U256, H256};
@@ -295,7 +295,18 @@ fn rpc_parity_net_peers() {
 	let io = deps.default_client();
 
 	let request = r#"{"jsonrpc": "2.0", "method": "parity_netPeers", "params":[], "id": 1}"#;
-	let response = r#"{"jsonrpc":"2.0","result":{"active":0,"connected":120,"max":50,"peers":[{"caps":["eth/62","eth/63"],"id":"node1","name":"Parity-Ethereum/1","network":{"localAddress":"127.0.0.1:8888","remoteAddress":"127.0.0.1:7777"},"protocols":{"eth":{"difficulty":"0x28","head":"0000000000000000000000000000000000000000000000000000000000000032","version":62},"pip":null}},{"caps":["eth/63","eth/64"],"id":null,"name":"Parity-Ethereum/2","network":{"localAddress":"127.0.0.1:3333","remoteAddress":"Handshake"},"protocols":{"eth":{"difficulty":null,"head":"000000000000000000000000000000000000000000000000000000000000003c","version":64},"pip":null}}]},"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"active":0,"connected":120,"max":50,"peers":[{"caps":["eth/62","eth/63"],"id":"node1","name":"Parity-Ethereum/1","network":{"bytesIn":1024,"bytesOut":4096,"localAddress":"127.0.0.1:8888","remoteAddress":"127.0.0.1:7777","traffic":{"eth":{"bytesIn":1000,"bytesOut":4000}}},"protocols":{"eth":{"difficulty":"0x28","head":"0000000000000000000000000000000000000000000000000000000000000032","version":62},"pip":null}},{"caps":["eth/63","eth/64"],"id":null,"name":"Parity-Ethereum/2","network":{"bytesIn":0,"bytesOut":0,"localAddress":"127.0.0.1:3333","remoteAddress":"Handshake","traffic":{}},"protocols":{"eth":{"difficulty":null,"head":"000000000000000000000000000000000000000000000000000000000000003c","version":64},"pip":null}}]},"id":1}"#;
+
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_parity_peer_reputation() {
+	let deps = Dependencies::new();
+	let io = deps.default_client();
+
+	let request = r#"{"jsonrpc": "2.0", "method": "parity_peerReputation", "params":[], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":[{"bannedUntil":1500000000,"id":"0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001","score":-120,"url":"enode://0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001@127.0.0.1:30303"}],"id":1}"#;
 
 	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
 }
@@ -555,6 +566,37 @@ fn rpc_parity_block_receipts() {
 	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
 }
 
+#[test]
+fn rpc_parity_finalized_block() {
+	let deps = Dependencies::new();
+	deps.client.add_blocks(20, EachBlockWith::Nothing);
+	let io = deps.default_client();
+
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "parity_getFinalizedBlock",
+		"params": [],
+		"id": 1
+	}"#;
+
+	// the test engine has no finality, blocks are final at the default depth of 12.
+	let finalized_hash = deps.client.block_header(BlockId::Number(8)).unwrap().hash();
+	let response = io.handle_request_sync(request).unwrap();
+	assert!(response.contains(r#""number":"0x8""#));
+	assert!(response.contains(&format!(r#""hash":"0x{:x}""#, finalized_hash)));
+}
+
+#[test]
+fn rpc_parity_equivocations() {
+	let deps = Dependencies::new();
+	let io = deps.default_client();
+
+	let request = r#"{"jsonrpc": "2.0", "method": "parity_equivocations", "params":[], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":[],"id":1}"#;
+
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
 #[test]
 fn rpc_status_ok() {
 	let deps = Dependencies::new();
@@ -0,0 +1,35 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Clique RPC interface.
+
+use jsonrpc_core::Result;
+use jsonrpc_macros::Trailing;
+
+use v1::types::{BlockNumber, H160};
+
+build_rpc_trait! {
+	/// Geth-compatible Clique RPC interface.
+	pub trait Clique {
+		/// Returns the signers authorized to seal the block after the given one.
+		#[rpc(name = "clique_getSigners")]
+		fn signers(&self, Trailing<BlockNumber>) -> Result<Vec<H160>>;
+
+		/// Votes to add (`true`) or remove (`false`) a signer in the blocks this node seals.
+		#[rpc(name = "clique_propose")]
+		fn propose(&self, H160, bool) -> Result<bool>;
+	}
+}
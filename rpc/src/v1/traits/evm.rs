@@ -0,0 +1,44 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Development chain RPC interface.
+
+use jsonrpc_core::Result;
+
+use v1::types::U256;
+
+build_rpc_trait! {
+	/// Ganache-compatible RPC interface controlling the development chain.
+	pub trait Evm {
+		/// Seals a new block, even if there are no transactions to include.
+		#[rpc(name = "evm_mine")]
+		fn mine(&self) -> Result<bool>;
+
+		/// Moves the timestamps of the next blocks forward by given number of seconds.
+		/// Returns the total time offset.
+		#[rpc(name = "evm_increaseTime")]
+		fn increase_time(&self, u64) -> Result<u64>;
+
+		/// Saves the current best block, returns an id to revert to it.
+		#[rpc(name = "evm_snapshot")]
+		fn snapshot(&self) -> Result<U256>;
+
+		/// Reverts the chain to a snapshot, dropping it and the ones taken after it.
+		/// Returns `false` if the snapshot is unknown.
+		#[rpc(name = "evm_revert")]
+		fn revert(&self, U256) -> Result<bool>;
+	}
+}
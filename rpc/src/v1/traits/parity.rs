@@ -23,11 +23,11 @@ use jsonrpc_macros::Trailing;
 
 use v1::types::{
 	H64, H160, H256, H512, U256, Bytes, CallRequest,
-	Peers, Transaction, RpcSettings, Histogram,
+	Peers, PeerReputation, Transaction, RpcSettings, Histogram,
 	TransactionStats, LocalTransactionStatus,
 	BlockNumber, ConsensusCapability, VersionInfo,
 	OperationsInfo, ChainStatus,
-	AccountInfo, HwAccountInfo, RichHeader, Receipt,
+	AccountInfo, HwAccountInfo, RichHeader, Receipt, Equivocation,
 };
 
 build_rpc_trait! {
@@ -87,6 +87,10 @@ build_rpc_trait! {
 		#[rpc(name = "parity_netPeers")]
 		fn net_peers(&self) -> Result<Peers>;
 
+		/// Returns reputation of all known nodes
+		#[rpc(name = "parity_peerReputation")]
+		fn peer_reputation(&self) -> Result<Vec<PeerReputation>>;
+
 		/// Returns network port
 		#[rpc(name = "parity_netPort")]
 		fn net_port(&self) -> Result<u16>;
@@ -209,6 +213,16 @@ build_rpc_trait! {
 		#[rpc(name = "parity_getBlockHeaderByNumber")]
 		fn block_header(&self, Trailing<BlockNumber>) -> BoxFuture<RichHeader>;
 
+		/// Get the header of the latest finalized block.
+		/// Engines without finality consider blocks final at a fixed confirmation depth.
+		#[rpc(name = "parity_getFinalizedBlock")]
+		fn finalized_block(&self) -> BoxFuture<RichHeader>;
+
+		/// Get the evidence of validators sealing two different blocks for the same step,
+		/// collected by engines which support it.
+		#[rpc(name = "parity_equivocations")]
+		fn equivocations(&self) -> Result<Vec<Equivocation>>;
+
 		/// Get block receipts.
 		/// Allows you to fetch receipts from the entire block at once.
 		/// If no parameter is provided defaults to `latest`.
@@ -91,6 +91,11 @@ build_rpc_trait! {
 		#[rpc(name = "parity_setChain")]
 		fn set_spec_name(&self, String) -> Result<bool>;
 
+		/// Back up the database to given directory while the node is running.
+		/// The backup can be used by starting with `--db-path` set to that directory.
+		#[rpc(name = "parity_backup")]
+		fn backup(&self, String) -> BoxFuture<bool>;
+
 		/// Hash a file content under given URL.
 		#[rpc(name = "parity_hashContent")]
 		fn hash_content(&self, String) -> BoxFuture<H256>;
@@ -30,6 +30,8 @@ pub enum BlockNumber {
 	Earliest,
 	/// Pending block (being mined)
 	Pending,
+	/// Latest finalized block
+	Finalized,
 }
 
 impl Default for BlockNumber {
@@ -71,6 +73,7 @@ impl LightBlockNumber for BlockNumber {
 			BlockNumber::Num(n) => BlockId::Number(n),
 			BlockNumber::Earliest => BlockId::Earliest,
 			BlockNumber::Latest => BlockId::Latest,
+			BlockNumber::Finalized => BlockId::Finalized,
 			BlockNumber::Pending => {
 				warn!("`Pending` is deprecated and may be removed in future versions. Falling back to `Latest`");
 				BlockId::Latest
@@ -86,6 +89,7 @@ impl Serialize for BlockNumber {
 			BlockNumber::Latest => serializer.serialize_str("latest"),
 			BlockNumber::Earliest => serializer.serialize_str("earliest"),
 			BlockNumber::Pending => serializer.serialize_str("pending"),
+			BlockNumber::Finalized => serializer.serialize_str("finalized"),
 		}
 	}
 }
@@ -96,7 +100,7 @@ impl<'a> Visitor<'a> for BlockNumberVisitor {
 	type Value = BlockNumber;
 
 	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-		write!(formatter, "a block number or 'latest', 'earliest' or 'pending'")
+		write!(formatter, "a block number or 'latest', 'earliest', 'pending' or 'finalized'")
 	}
 
 	fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> where E: Error {
@@ -104,6 +108,7 @@ impl<'a> Visitor<'a> for BlockNumberVisitor {
 			"latest" => Ok(BlockNumber::Latest),
 			"earliest" => Ok(BlockNumber::Earliest),
 			"pending" => Ok(BlockNumber::Pending),
+			"finalized" => Ok(BlockNumber::Finalized),
 			_ if value.starts_with("0x") => u64::from_str_radix(&value[2..], 16).map(BlockNumber::Num).map_err(|e| {
 				Error::custom(format!("Invalid block number: {}", e))
 			}),
@@ -122,6 +127,7 @@ pub fn block_number_to_id(number: BlockNumber) -> BlockId {
 		BlockNumber::Num(num) => BlockId::Number(num),
 		BlockNumber::Earliest => BlockId::Earliest,
 		BlockNumber::Latest => BlockId::Latest,
+		BlockNumber::Finalized => BlockId::Finalized,
 
 		BlockNumber::Pending => panic!("`BlockNumber::Pending` should be handled manually")
 	}
@@ -135,9 +141,9 @@ mod tests {
 
 	#[test]
 	fn block_number_deserialization() {
-		let s = r#"["0xa", "latest", "earliest", "pending"]"#;
+		let s = r#"["0xa", "latest", "earliest", "pending", "finalized"]"#;
 		let deserialized: Vec<BlockNumber> = serde_json::from_str(s).unwrap();
-		assert_eq!(deserialized, vec![BlockNumber::Num(10), BlockNumber::Latest, BlockNumber::Earliest, BlockNumber::Pending])
+		assert_eq!(deserialized, vec![BlockNumber::Num(10), BlockNumber::Latest, BlockNumber::Earliest, BlockNumber::Pending, BlockNumber::Finalized])
 	}
 
 	#[test]
@@ -151,6 +157,7 @@ mod tests {
 		assert_eq!(block_number_to_id(BlockNumber::Num(100)), BlockId::Number(100));
 		assert_eq!(block_number_to_id(BlockNumber::Earliest), BlockId::Earliest);
 		assert_eq!(block_number_to_id(BlockNumber::Latest), BlockId::Latest);
+		assert_eq!(block_number_to_id(BlockNumber::Finalized), BlockId::Finalized);
 	}
 
 	#[test]
@@ -0,0 +1,45 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Evidence of a validator sealing two different blocks for the same step.
+
+use ethcore::engines;
+use v1::types::{Bytes, H160, H256, U256};
+
+/// Equivocation of a validator.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Equivocation {
+	/// Validator which sealed both blocks.
+	pub author: H160,
+	/// Step both blocks were sealed for.
+	pub step: U256,
+	/// Hashes of the conflicting blocks.
+	pub block_hashes: Vec<H256>,
+	/// RLP encoded list of both sealed headers, as reported to the validator set.
+	pub proof: Bytes,
+}
+
+impl From<engines::Equivocation> for Equivocation {
+	fn from(e: engines::Equivocation) -> Self {
+		Equivocation {
+			author: e.author.into(),
+			step: e.step.into(),
+			block_hashes: vec![e.first.hash().into(), e.second.hash().into()],
+			proof: e.proof().into(),
+		}
+	}
+}
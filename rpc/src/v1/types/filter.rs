@@ -83,6 +83,7 @@ impl Filter {
 			BlockNumber::Num(n) => BlockId::Number(n),
 			BlockNumber::Earliest => BlockId::Earliest,
 			BlockNumber::Latest | BlockNumber::Pending => BlockId::Latest,
+			BlockNumber::Finalized => BlockId::Finalized,
 		};
 
 		let (from_block, to_block) = match self.block_hash {
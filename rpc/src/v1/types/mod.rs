@@ -24,6 +24,7 @@ mod call_request;
 mod confirmations;
 mod consensus_status;
 mod derivation;
+mod equivocation;
 mod filter;
 mod hash;
 mod histogram;
@@ -57,6 +58,7 @@ pub use self::confirmations::{
 };
 pub use self::consensus_status::*;
 pub use self::derivation::{DeriveHash, DeriveHierarchical, Derive};
+pub use self::equivocation::Equivocation;
 pub use self::filter::{Filter, FilterChanges};
 pub use self::hash::{H64, H160, H256, H512, H520, H2048};
 pub use self::histogram::Histogram;
@@ -68,7 +70,7 @@ pub use self::receipt::Receipt;
 pub use self::rpc_settings::RpcSettings;
 pub use self::secretstore::EncryptedDocumentKey;
 pub use self::sync::{
-	SyncStatus, SyncInfo, Peers, PeerInfo, PeerNetworkInfo, PeerProtocolsInfo,
+	SyncStatus, SyncInfo, Peers, PeerInfo, PeerNetworkInfo, PeerProtocolsInfo, PeerReputation, PeerTrafficInfo,
 	TransactionStats, ChainStatus, EthProtocolInfo, PipProtocolInfo,
 };
 pub use self::trace::{LocalizedTrace, TraceResults, TraceResultsWithTransactionHash};
@@ -51,6 +51,8 @@ impl Serialize for Result {
 pub enum Kind {
 	/// New block headers subscription.
 	NewHeads,
+	/// Newly finalized block headers subscription.
+	NewFinalizedHeads,
 	/// Logs subscription.
 	Logs,
 	/// New Pending Transactions subscription.
@@ -98,6 +100,7 @@ mod tests {
 	#[test]
 	fn should_deserialize_kind() {
 		assert_eq!(serde_json::from_str::<Kind>(r#""newHeads""#).unwrap(), Kind::NewHeads);
+		assert_eq!(serde_json::from_str::<Kind>(r#""newFinalizedHeads""#).unwrap(), Kind::NewFinalizedHeads);
 		assert_eq!(serde_json::from_str::<Kind>(r#""logs""#).unwrap(), Kind::Logs);
 		assert_eq!(serde_json::from_str::<Kind>(r#""newPendingTransactions""#).unwrap(), Kind::NewPendingTransactions);
 		assert_eq!(serde_json::from_str::<Kind>(r#""syncing""#).unwrap(), Kind::Syncing);
@@ -15,7 +15,8 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::BTreeMap;
-use sync::{self, PeerInfo as SyncPeerInfo, TransactionStats as SyncTransactionStats};
+use std::time::UNIX_EPOCH;
+use sync::{self, PeerInfo as SyncPeerInfo, TransactionStats as SyncTransactionStats, NodeReputation, TrafficStats};
 use serde::{Serialize, Serializer};
 use v1::types::{U256, H512};
 
@@ -71,6 +72,31 @@ pub struct PeerNetworkInfo {
 	pub remote_address: String,
 	/// Local endpoint address
 	pub local_address: String,
+	/// Total bytes received from the peer
+	pub bytes_in: u64,
+	/// Total bytes sent to the peer
+	pub bytes_out: u64,
+	/// Traffic broken down by subprotocol
+	pub traffic: BTreeMap<String, PeerTrafficInfo>,
+}
+
+/// Peer traffic of a single subprotocol
+#[derive(Default, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerTrafficInfo {
+	/// Bytes received from the peer
+	pub bytes_in: u64,
+	/// Bytes sent to the peer
+	pub bytes_out: u64,
+}
+
+impl From<TrafficStats> for PeerTrafficInfo {
+	fn from(stats: TrafficStats) -> Self {
+		PeerTrafficInfo {
+			bytes_in: stats.bytes_in,
+			bytes_out: stats.bytes_out,
+		}
+	}
 }
 
 /// Peer protocols information
@@ -162,6 +188,9 @@ impl From<SyncPeerInfo> for PeerInfo {
 			network: PeerNetworkInfo {
 				remote_address: p.remote_address,
 				local_address: p.local_address,
+				bytes_in: p.traffic.bytes_in,
+				bytes_out: p.traffic.bytes_out,
+				traffic: p.protocol_traffic.into_iter().map(|(protocol, stats)| (protocol, stats.into())).collect(),
 			},
 			protocols: PeerProtocolsInfo {
 				eth: p.eth_info.map(Into::into),
@@ -183,6 +212,33 @@ impl From<SyncTransactionStats> for TransactionStats {
 	}
 }
 
+/// Reputation of a known node.
+#[derive(Default, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerReputation {
+	/// Public node id
+	pub id: H512,
+	/// Node URL
+	pub url: String,
+	/// Current reputation score
+	pub score: i32,
+	/// UNIX timestamp (in seconds) until which the node is banned
+	pub banned_until: Option<u64>,
+}
+
+impl From<NodeReputation> for PeerReputation {
+	fn from(r: NodeReputation) -> Self {
+		PeerReputation {
+			id: r.id.into(),
+			url: r.url,
+			score: r.score,
+			banned_until: r.banned_until
+				.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+				.map(|d| d.as_secs()),
+		}
+	}
+}
+
 /// Chain status.
 #[derive(Default, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -195,7 +251,7 @@ pub struct ChainStatus {
 mod tests {
 	use serde_json;
 	use std::collections::BTreeMap;
-	use super::{SyncInfo, SyncStatus, Peers, TransactionStats, ChainStatus};
+	use super::{SyncInfo, SyncStatus, Peers, TransactionStats, ChainStatus, PeerReputation};
 
 	#[test]
 	fn test_serialize_sync_info() {
@@ -234,6 +290,18 @@ mod tests {
 		assert_eq!(serialized, r#"{"blockGap":["0x1","0x5"]}"#);
 	}
 
+	#[test]
+	fn test_serialize_peer_reputation() {
+		let t = PeerReputation {
+			id: 1.into(),
+			url: "enode://01@127.0.0.1:30303".into(),
+			score: -5,
+			banned_until: None,
+		};
+		let serialized = serde_json::to_string(&t).unwrap();
+		assert_eq!(serialized, r#"{"id":"0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001","url":"enode://01@127.0.0.1:30303","score":-5,"bannedUntil":null}"#);
+	}
+
 	#[test]
 	fn test_serialize_transaction_stats() {
 		let stats = TransactionStats {
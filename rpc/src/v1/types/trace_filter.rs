@@ -45,6 +45,7 @@ impl Into<client::TraceFilter> for TraceFilter {
 			BlockNumber::Num(n) => BlockId::Number(n),
 			BlockNumber::Earliest => BlockId::Earliest,
 			BlockNumber::Latest => BlockId::Latest,
+			BlockNumber::Finalized => BlockId::Finalized,
 			BlockNumber::Pending => {
 				warn!("Pending traces are not supported and might be removed in future versions. Falling back to Latest");
 				BlockId::Latest
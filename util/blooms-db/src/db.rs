@@ -14,13 +14,16 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{error, io, fmt};
+use std::{error, io, fmt, fs};
 use std::path::{Path, PathBuf};
 
 use ethbloom;
 
 use file::{File, FileIterator};
 
+/// Names of the database files.
+const FILE_NAMES: [&'static str; 3] = ["top.bdb", "mid.bdb", "bot.bdb"];
+
 fn other_io_err<E>(e: E) -> io::Error where E: Into<Box<error::Error + Send + Sync>> {
 	io::Error::new(io::ErrorKind::Other, e)
 }
@@ -69,9 +72,9 @@ impl DatabaseFiles {
 	/// Open the blooms db files
 	pub fn open(path: &Path) -> io::Result<DatabaseFiles> {
 		Ok(DatabaseFiles {
-			top: File::open(path.join("top.bdb"))?,
-			mid: File::open(path.join("mid.bdb"))?,
-			bot: File::open(path.join("bot.bdb"))?,
+			top: File::open(path.join(FILE_NAMES[0]))?,
+			mid: File::open(path.join(FILE_NAMES[1]))?,
+			bot: File::open(path.join(FILE_NAMES[2]))?,
 		})
 	}
 
@@ -137,6 +140,20 @@ impl Database {
 		Ok(())
 	}
 
+	/// Copy the database files into the directory at `path`.
+	pub fn backup(&mut self, path: &Path) -> io::Result<()> {
+		match self.db_files {
+			Some(ref mut db_files) => db_files.flush()?,
+			None => return Err(other_io_err("Database is closed")),
+		}
+
+		fs::create_dir_all(path)?;
+		for name in FILE_NAMES.iter() {
+			fs::copy(self.path.join(name), path.join(name))?;
+		}
+		Ok(())
+	}
+
 	/// Insert consecutive blooms into database starting at the given positon.
 	pub fn insert_blooms<'a, I, B>(&mut self, from: u64, blooms: I) -> io::Result<()>
 	where ethbloom::BloomRef<'a>: From<B>, I: Iterator<Item = B> {
@@ -366,4 +383,21 @@ mod tests {
 		database.reopen().unwrap();
 		assert!(database.insert_blooms(254, blooms.iter()).is_ok());
 	}
+
+	#[test]
+	fn test_backup() {
+		let tempdir = TempDir::new("").unwrap();
+		let backup_dir = TempDir::new("").unwrap();
+		let backup_path = backup_dir.path().join("blooms");
+		let mut database = Database::open(tempdir.path()).unwrap();
+		database.insert_blooms(0, vec![Bloom::from(0x01), Bloom::from(0x10), Bloom::from(0x11)].iter()).unwrap();
+		database.backup(&backup_path).unwrap();
+
+		// changes after the backup are not part of it
+		database.insert_blooms(3, vec![Bloom::from(0x01)].iter()).unwrap();
+
+		let mut backup = Database::open(&backup_path).unwrap();
+		let matches = backup.iterate_matching(0, 3, Some(&Bloom::from(0x01))).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+		assert_eq!(matches, vec![0, 2]);
+	}
 }
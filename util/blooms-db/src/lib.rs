@@ -64,6 +64,11 @@ impl Database {
 		self.database.lock().reopen()
 	}
 
+	/// Copies the database files into the directory at `path`.
+	pub fn backup<P>(&self, path: P) -> io::Result<()> where P: AsRef<Path> {
+		self.database.lock().backup(path.as_ref())
+	}
+
 	/// Inserts one or more blooms into database.
 	///
 	/// # Arguments
@@ -138,7 +138,7 @@ impl Directories {
 	}
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 /// Database directories for the given fork.
 pub struct DatabaseDirectories {
 	/// Base path
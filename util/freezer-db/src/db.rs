@@ -0,0 +1,288 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{error, io, fs};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use file::Table;
+
+/// Names of the tables, in `Kind` order.
+const TABLE_NAMES: [&'static str; 4] = ["hashes", "headers", "bodies", "receipts"];
+
+fn other_io_err<E>(e: E) -> io::Error where E: Into<Box<error::Error + Send + Sync>> {
+	io::Error::new(io::ErrorKind::Other, e)
+}
+
+/// Copy the parts of the files given with their lengths into the directory at `path`.
+pub fn copy_files(files: &[(PathBuf, u64)], path: &Path) -> io::Result<()> {
+	fs::create_dir_all(path)?;
+	for &(ref file, len) in files {
+		let name = file.file_name().ok_or_else(|| other_io_err("Invalid file name"))?;
+		let mut source = fs::File::open(file)?.take(len);
+		let mut target = fs::File::create(path.join(name))?;
+		io::copy(&mut source, &mut target)?;
+		target.sync_all()?;
+	}
+	Ok(())
+}
+
+/// Kinds of data kept for every frozen block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+	/// Block hash.
+	Hash,
+	/// Block header.
+	Header,
+	/// Block body.
+	Body,
+	/// Block receipts.
+	Receipts,
+}
+
+/// Freezer database files.
+struct DatabaseFiles {
+	hashes: Table,
+	headers: Table,
+	bodies: Table,
+	receipts: Table,
+}
+
+impl DatabaseFiles {
+	/// Open the freezer db files, dropping items not present in all of them.
+	pub fn open(path: &Path) -> io::Result<DatabaseFiles> {
+		let mut files = DatabaseFiles {
+			hashes: Table::open(path, TABLE_NAMES[0])?,
+			headers: Table::open(path, TABLE_NAMES[1])?,
+			bodies: Table::open(path, TABLE_NAMES[2])?,
+			receipts: Table::open(path, TABLE_NAMES[3])?,
+		};
+
+		files.truncate_to_len()?;
+		Ok(files)
+	}
+
+	/// Drop items of blocks which are not complete, left by a failed write.
+	fn truncate_to_len(&mut self) -> io::Result<()> {
+		let len = self.len();
+		self.hashes.truncate(len)?;
+		self.headers.truncate(len)?;
+		self.bodies.truncate(len)?;
+		self.receipts.truncate(len)
+	}
+
+	fn len(&self) -> u64 {
+		[self.hashes.len(), self.headers.len(), self.bodies.len(), self.receipts.len()]
+			.iter()
+			.cloned()
+			.min()
+			.expect("array is not empty; qed")
+	}
+
+	fn table(&self, kind: Kind) -> &Table {
+		match kind {
+			Kind::Hash => &self.hashes,
+			Kind::Header => &self.headers,
+			Kind::Body => &self.bodies,
+			Kind::Receipts => &self.receipts,
+		}
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.hashes.flush()?;
+		self.headers.flush()?;
+		self.bodies.flush()?;
+		self.receipts.flush()?;
+		Ok(())
+	}
+}
+
+impl Drop for DatabaseFiles {
+	/// Flush the database files on drop
+	fn drop(&mut self) {
+		self.flush().ok();
+	}
+}
+
+/// Freezer database.
+pub struct Database {
+	/// Database files
+	db_files: Option<DatabaseFiles>,
+	/// Database path
+	path: PathBuf,
+}
+
+impl Database {
+	/// Opens freezer database.
+	pub fn open<P>(path: P) -> io::Result<Database> where P: AsRef<Path> {
+		let path: PathBuf = path.as_ref().to_path_buf();
+		let database = Database {
+			db_files: Some(DatabaseFiles::open(&path)?),
+			path: path,
+		};
+
+		Ok(database)
+	}
+
+	/// Close the inner-files
+	pub fn close(&mut self) -> io::Result<()> {
+		self.db_files = None;
+		Ok(())
+	}
+
+	/// Reopens the database at the same location.
+	pub fn reopen(&mut self) -> io::Result<()> {
+		self.db_files = Some(DatabaseFiles::open(&self.path)?);
+		Ok(())
+	}
+
+	/// Returns number of frozen blocks.
+	pub fn len(&self) -> u64 {
+		self.db_files.as_ref().map_or(0, DatabaseFiles::len)
+	}
+
+	/// Returns data of given kind for block with given number.
+	pub fn get(&self, kind: Kind, number: u64) -> io::Result<Option<Vec<u8>>> {
+		match self.db_files {
+			Some(ref db_files) => db_files.table(kind).get(number),
+			None => Err(other_io_err("Database is closed")),
+		}
+	}
+
+	/// Drops all frozen blocks.
+	pub fn clear(&mut self) -> io::Result<()> {
+		match self.db_files {
+			Some(ref mut db_files) => {
+				db_files.hashes.truncate(0)?;
+				db_files.headers.truncate(0)?;
+				db_files.bodies.truncate(0)?;
+				db_files.receipts.truncate(0)?;
+				db_files.flush()
+			},
+			None => Err(other_io_err("Database is closed")),
+		}
+	}
+
+	/// Flush the table files and return them with the lengths of their parts holding the
+	/// first `len` blocks. Frozen items are never modified, so these parts can be copied
+	/// while further blocks are appended.
+	pub fn backup_files(&mut self, len: u64) -> io::Result<Vec<(PathBuf, u64)>> {
+		let db_files = match self.db_files {
+			Some(ref mut db_files) => db_files,
+			None => return Err(other_io_err("Database is closed")),
+		};
+		db_files.flush()?;
+
+		let kinds = [Kind::Hash, Kind::Header, Kind::Body, Kind::Receipts];
+		let mut files = Vec::with_capacity(TABLE_NAMES.len() * 2);
+		for (name, kind) in TABLE_NAMES.iter().zip(kinds.iter()) {
+			let (index_len, data_len) = db_files.table(*kind).file_lens(len)?;
+			files.push((self.path.join(format!("{}.idx", name)), index_len));
+			files.push((self.path.join(format!("{}.dat", name)), data_len));
+		}
+		Ok(files)
+	}
+
+	/// Appends consecutive blocks starting with block number `from`, which must be
+	/// the number of blocks already frozen. Items of every block are given in
+	/// `Hash`, `Header`, `Body`, `Receipts` order.
+	pub fn append<'a, I>(&mut self, from: u64, blocks: I) -> io::Result<()> where I: IntoIterator<Item = [&'a [u8]; 4]> {
+		match self.db_files {
+			Some(ref mut db_files) => {
+				if from != db_files.len() {
+					return Err(io::Error::new(io::ErrorKind::InvalidInput, "Blocks must be frozen in order"));
+				}
+
+				db_files.truncate_to_len()?;
+
+				for items in blocks {
+					db_files.hashes.append(items[0])?;
+					db_files.headers.append(items[1])?;
+					db_files.bodies.append(items[2])?;
+					db_files.receipts.append(items[3])?;
+				}
+				db_files.flush()
+			},
+			None => Err(other_io_err("Database is closed")),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use tempdir::TempDir;
+	use super::{Database, Kind, copy_files};
+
+	fn block(n: &str) -> [Vec<u8>; 4] {
+		[
+			format!("hash{}", n).into_bytes(),
+			format!("header{}", n).into_bytes(),
+			format!("body{}", n).into_bytes(),
+			format!("receipts{}", n).into_bytes(),
+		]
+	}
+
+	fn items(block: &[Vec<u8>; 4]) -> [&[u8]; 4] {
+		[&block[0], &block[1], &block[2], &block[3]]
+	}
+
+	#[test]
+	fn test_database() {
+		let tempdir = TempDir::new("").unwrap();
+		let mut database = Database::open(tempdir.path()).unwrap();
+		let (block0, block1, block2) = (block("0"), block("1"), block("2"));
+		database.append(0, Some(items(&block0))).unwrap();
+		assert!(database.append(2, Some(items(&block2))).is_err());
+		database.append(1, Some(items(&block1))).unwrap();
+
+		assert_eq!(database.len(), 2);
+		assert_eq!(database.get(Kind::Hash, 1).unwrap(), Some(b"hash1".to_vec()));
+		assert_eq!(database.get(Kind::Header, 0).unwrap(), Some(b"header0".to_vec()));
+		assert_eq!(database.get(Kind::Body, 1).unwrap(), Some(b"body1".to_vec()));
+		assert_eq!(database.get(Kind::Receipts, 0).unwrap(), Some(b"receipts0".to_vec()));
+		assert_eq!(database.get(Kind::Hash, 2).unwrap(), None);
+
+		database.close().unwrap();
+		assert_eq!(database.len(), 0);
+		assert!(database.get(Kind::Hash, 0).is_err());
+		database.reopen().unwrap();
+		assert_eq!(database.len(), 2);
+
+		database.clear().unwrap();
+		assert_eq!(database.len(), 0);
+		assert_eq!(database.get(Kind::Hash, 0).unwrap(), None);
+		database.append(0, Some(items(&block1))).unwrap();
+		assert_eq!(database.get(Kind::Hash, 0).unwrap(), Some(b"hash1".to_vec()));
+	}
+
+	#[test]
+	fn test_backup() {
+		let tempdir = TempDir::new("").unwrap();
+		let backup_dir = TempDir::new("").unwrap();
+		let backup_path = backup_dir.path().join("freezer");
+		let mut database = Database::open(tempdir.path()).unwrap();
+		let (block0, block1, block2) = (block("0"), block("1"), block("2"));
+		database.append(0, Some(items(&block0))).unwrap();
+		database.append(1, Some(items(&block1))).unwrap();
+		let files = database.backup_files(1).unwrap();
+		database.append(2, Some(items(&block2))).unwrap();
+		copy_files(&files, &backup_path).unwrap();
+
+		let backup = Database::open(&backup_path).unwrap();
+		assert_eq!(backup.len(), 1);
+		assert_eq!(backup.get(Kind::Body, 0).unwrap(), Some(b"body0".to_vec()));
+	}
+}
@@ -0,0 +1,217 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{Seek, SeekFrom, Write, Read};
+use std::path::Path;
+use std::{io, fs};
+
+use byteorder::{ByteOrder, LittleEndian};
+use snappy;
+
+/// Size of a single index entry.
+const INDEX_ENTRY_SIZE: u64 = 8;
+
+fn open_file<P>(path: P) -> io::Result<fs::File> where P: AsRef<Path> {
+	fs::OpenOptions::new()
+		.read(true)
+		.write(true)
+		.create(true)
+		// appending is done manually at the end of the used part of the file
+		.append(false)
+		.open(path)
+}
+
+/// Append-only table of snappy-compressed items addressed by their position.
+///
+/// Consists of a data file with the items stored one after another and an index file
+/// holding the end offset of every item in the data file.
+pub struct Table {
+	/// Index file.
+	index: fs::File,
+	/// Data file.
+	data: fs::File,
+	/// Number of items.
+	len: u64,
+	/// Length of the used part of the data file.
+	data_len: u64,
+}
+
+impl Table {
+	/// Opens table files `<name>.idx` and `<name>.dat` in given directory. Creates them if they do not exist.
+	///
+	/// Items partially written before an unclean shutdown are dropped.
+	pub fn open(path: &Path, name: &str) -> io::Result<Table> {
+		let index = open_file(path.join(format!("{}.idx", name)))?;
+		let data = open_file(path.join(format!("{}.dat", name)))?;
+		let data_file_len = data.metadata()?.len();
+
+		let mut table = Table {
+			len: index.metadata()?.len() / INDEX_ENTRY_SIZE,
+			index,
+			data,
+			data_len: 0,
+		};
+
+		while table.len > 0 && table.end_offset(table.len - 1)? > data_file_len {
+			table.len -= 1;
+		}
+
+		let len = table.len;
+		table.truncate(len)?;
+		Ok(table)
+	}
+
+	/// Returns number of items in the table.
+	pub fn len(&self) -> u64 {
+		self.len
+	}
+
+	/// Drops all items at positions `len` and above.
+	pub fn truncate(&mut self, len: u64) -> io::Result<()> {
+		let len = ::std::cmp::min(len, self.len);
+		let data_len = match len {
+			0 => 0,
+			_ => self.end_offset(len - 1)?,
+		};
+
+		self.index.set_len(len * INDEX_ENTRY_SIZE)?;
+		self.data.set_len(data_len)?;
+		self.len = len;
+		self.data_len = data_len;
+		Ok(())
+	}
+
+	/// Returns lengths of the parts of the index and data files holding the first `len` items.
+	pub fn file_lens(&self, len: u64) -> io::Result<(u64, u64)> {
+		let len = ::std::cmp::min(len, self.len);
+		let data_len = match len {
+			0 => 0,
+			_ => self.end_offset(len - 1)?,
+		};
+		Ok((len * INDEX_ENTRY_SIZE, data_len))
+	}
+
+	/// Reads end offset of the item at given position from the index file.
+	fn end_offset(&self, pos: u64) -> io::Result<u64> {
+		let mut file_ref = &self.index;
+		file_ref.seek(SeekFrom::Start(pos * INDEX_ENTRY_SIZE))?;
+		let mut buf = [0u8; INDEX_ENTRY_SIZE as usize];
+		file_ref.read_exact(&mut buf)?;
+		Ok(LittleEndian::read_u64(&buf))
+	}
+
+	/// Reads item at given position.
+	pub fn get(&self, pos: u64) -> io::Result<Option<Vec<u8>>> {
+		if pos >= self.len {
+			return Ok(None);
+		}
+
+		let start = match pos {
+			0 => 0,
+			_ => self.end_offset(pos - 1)?,
+		};
+		let end = self.end_offset(pos)?;
+		if end < start {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "Corrupted freezer index"));
+		}
+
+		let mut compressed = vec![0u8; (end - start) as usize];
+		let mut file_ref = &self.data;
+		file_ref.seek(SeekFrom::Start(start))?;
+		file_ref.read_exact(&mut compressed)?;
+
+		snappy::decompress(&compressed)
+			.map(Some)
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Corrupted freezer item"))
+	}
+
+	/// Appends item at the end of the table.
+	pub fn append(&mut self, item: &[u8]) -> io::Result<()> {
+		let compressed = snappy::compress(item);
+		let data_len = self.data_len + compressed.len() as u64;
+
+		let mut file_ref = &self.data;
+		file_ref.seek(SeekFrom::Start(self.data_len))?;
+		file_ref.write_all(&compressed)?;
+
+		let mut buf = [0u8; INDEX_ENTRY_SIZE as usize];
+		LittleEndian::write_u64(&mut buf, data_len);
+		let mut file_ref = &self.index;
+		file_ref.seek(SeekFrom::Start(self.len * INDEX_ENTRY_SIZE))?;
+		file_ref.write_all(&buf)?;
+
+		self.data_len = data_len;
+		self.len += 1;
+		Ok(())
+	}
+
+	/// Flushes outstanding modifications and syncs them to the disk.
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.data.flush()?;
+		self.index.flush()?;
+		self.data.sync_data()?;
+		self.index.sync_data()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+	use tempdir::TempDir;
+	use super::Table;
+
+	#[test]
+	fn test_table() {
+		let tempdir = TempDir::new("").unwrap();
+		let mut table = Table::open(tempdir.path(), "table").unwrap();
+		table.append(b"first").unwrap();
+		table.append(b"").unwrap();
+		table.append(&[7u8; 1000]).unwrap();
+		table.flush().unwrap();
+
+		assert_eq!(table.len(), 3);
+		assert_eq!(table.get(0).unwrap(), Some(b"first".to_vec()));
+		assert_eq!(table.get(1).unwrap(), Some(Vec::new()));
+		assert_eq!(table.get(2).unwrap(), Some(vec![7u8; 1000]));
+		assert_eq!(table.get(3).unwrap(), None);
+
+		table.truncate(1).unwrap();
+		table.append(b"second").unwrap();
+		drop(table);
+
+		let table = Table::open(tempdir.path(), "table").unwrap();
+		assert_eq!(table.len(), 2);
+		assert_eq!(table.get(1).unwrap(), Some(b"second".to_vec()));
+	}
+
+	#[test]
+	fn test_interrupted_append() {
+		let tempdir = TempDir::new("").unwrap();
+		let mut table = Table::open(tempdir.path(), "table").unwrap();
+		table.append(b"first").unwrap();
+		table.append(b"second").unwrap();
+		drop(table);
+
+		// cut the last item in half, as if the write was interrupted
+		let data = fs::OpenOptions::new().write(true).open(tempdir.path().join("table.dat")).unwrap();
+		let len = data.metadata().unwrap().len();
+		data.set_len(len - 2).unwrap();
+
+		let table = Table::open(tempdir.path(), "table").unwrap();
+		assert_eq!(table.len(), 1);
+		assert_eq!(table.get(0).unwrap(), Some(b"first".to_vec()));
+	}
+}
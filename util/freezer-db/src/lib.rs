@@ -0,0 +1,103 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Ancient blocks database.
+//!
+//! Keeps hashes, headers, bodies and receipts of old canonical blocks in
+//! compressed append-only files indexed by block number.
+
+extern crate byteorder;
+extern crate parking_lot;
+extern crate parity_snappy as snappy;
+
+#[cfg(test)]
+extern crate tempdir;
+
+mod db;
+mod file;
+
+use std::io;
+use std::path::Path;
+use parking_lot::Mutex;
+
+pub use db::Kind;
+
+/// Threadsafe API for freezer database.
+///
+/// # Warning
+///
+/// This database does not guarantee atomic writes. Blocks partially written
+/// before an unclean shutdown are dropped when the database is opened.
+pub struct Database {
+	database: Mutex<db::Database>,
+}
+
+impl Database {
+	/// Creates new database handle.
+	///
+	/// # Arguments
+	///
+	/// * `path` - database directory
+	pub fn open<P>(path: P) -> io::Result<Database> where P: AsRef<Path> {
+		let result = Database {
+			database: Mutex::new(db::Database::open(path)?),
+		};
+
+		Ok(result)
+	}
+
+	/// Closes the inner database
+	pub fn close(&self) -> io::Result<()> {
+		self.database.lock().close()
+	}
+
+	/// Reopens database at the same location.
+	pub fn reopen(&self) -> io::Result<()> {
+		self.database.lock().reopen()
+	}
+
+	/// Returns number of frozen blocks. Blocks `0..len` are frozen.
+	pub fn len(&self) -> u64 {
+		self.database.lock().len()
+	}
+
+	/// Returns data of given kind for block with given number, or `None` if the block is not frozen.
+	pub fn get(&self, kind: Kind, number: u64) -> io::Result<Option<Vec<u8>>> {
+		self.database.lock().get(kind, number)
+	}
+
+	/// Drops all frozen blocks.
+	pub fn clear(&self) -> io::Result<()> {
+		self.database.lock().clear()
+	}
+
+	/// Copies the first `len` frozen blocks into the directory at `path`.
+	/// Blocks can be appended while they are copied.
+	pub fn backup<P>(&self, path: P, len: u64) -> io::Result<()> where P: AsRef<Path> {
+		let files = self.database.lock().backup_files(len)?;
+		db::copy_files(&files, path.as_ref())
+	}
+
+	/// Appends consecutive blocks and syncs them to the disk.
+	///
+	/// # Arguments
+	///
+	/// * `from` - number of the first block, must be equal to `len()`
+	/// * `blocks` - iterator over hash, header, body and receipts of every block
+	pub fn append<'a, I>(&self, from: u64, blocks: I) -> io::Result<()> where I: IntoIterator<Item = [&'a [u8]; 4]> {
+		self.database.lock().append(from, blocks)
+	}
+}
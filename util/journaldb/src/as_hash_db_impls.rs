@@ -18,6 +18,7 @@
 use hashdb::{HashDB, AsHashDB};
 use keccak_hasher::KeccakHasher;
 use archivedb::ArchiveDB;
+use beamdb::BeamDB;
 use earlymergedb::EarlyMergeDB;
 use overlayrecentdb::OverlayRecentDB;
 use refcounteddb::RefCountedDB;
@@ -48,3 +49,8 @@ impl AsHashDB<KeccakHasher, DBValue> for OverlayDB {
 	fn as_hashdb(&self) -> &HashDB<KeccakHasher, DBValue> { self }
 	fn as_hashdb_mut(&mut self) -> &mut HashDB<KeccakHasher, DBValue> { self }
 }
+
+impl AsHashDB<KeccakHasher, DBValue> for BeamDB {
+	fn as_hashdb(&self) -> &HashDB<KeccakHasher, DBValue> { self }
+	fn as_hashdb_mut(&mut self) -> &mut HashDB<KeccakHasher, DBValue> { self }
+}
@@ -0,0 +1,264 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! `JournalDB` wrapper fetching missing state from the network.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use ethereum_types::H256;
+use hashdb::{HashDB, Hasher};
+use keccak_hasher::KeccakHasher;
+use kvdb::{KeyValueDB, DBTransaction, DBValue};
+use memorydb::MemoryDB;
+use parking_lot::RwLock;
+use super::DB_PREFIX_LEN;
+use traits::JournalDB;
+
+/// Source of state entries missing from the local database.
+pub trait NodeFetcher: Send + Sync {
+	/// Queue the given keys to be requested from the network, without waiting for the response.
+	/// Keys are queried by their first `DB_PREFIX_LEN` bytes, as for `JournalDB::state`.
+	fn request(&self, keys: &[H256]);
+
+	/// Take the values received since the last call, together with the key each one was
+	/// requested under. Values are checked with `is_value_for`.
+	fn take_fetched(&self) -> Vec<(H256, DBValue)>;
+
+	/// Number of requested keys whose values were not received yet.
+	fn pending(&self) -> usize;
+}
+
+thread_local! {
+	static MISSED_LOOKUPS: Cell<usize> = Cell::new(0);
+}
+
+/// Number of lookups made by the current thread which missed the database and were queued
+/// with the node fetcher. Work failing after the counter changed should be retried once the
+/// requested entries arrived.
+pub fn missed_lookups() -> usize {
+	MISSED_LOOKUPS.with(|missed| missed.get())
+}
+
+/// Shared, late-initialized node fetcher.
+pub type FetcherSlot = Arc<RwLock<Option<Arc<NodeFetcher>>>>;
+
+/// Check if `value` may be stored under `key`.
+///
+/// Account storage and code are stored under keys mangled with the address hash,
+/// which leave only the first `DB_PREFIX_LEN` bytes of the value hash intact.
+pub fn is_value_for(key: &H256, value: &[u8]) -> bool {
+	KeccakHasher::hash(value)[..DB_PREFIX_LEN] == key[..DB_PREFIX_LEN]
+}
+
+/// `JournalDB` used while beam syncing.
+///
+/// Lookups of keys unknown to the underlying database fail right away and the keys are queued
+/// with the node fetcher. The fetched values are meant to be written straight to the backing
+/// database, as they belong to the canonical state already. Only works with pruning
+/// algorithms which store values verbatim under their key, i.e. archive and overlay-recent.
+pub struct BeamDB {
+	inner: Box<JournalDB>,
+	fetcher: FetcherSlot,
+	column: Option<u32>,
+}
+
+impl BeamDB {
+	/// Wrap a journal database, fetching missing values with the fetcher in `fetcher` once it is set.
+	pub fn new(inner: Box<JournalDB>, fetcher: FetcherSlot, column: Option<u32>) -> BeamDB {
+		BeamDB {
+			inner,
+			fetcher,
+			column,
+		}
+	}
+
+	fn request(&self, key: &H256) {
+		if let Some(ref fetcher) = *self.fetcher.read() {
+			trace!(target: "journaldb", "Requesting missing state entry {:?}", key);
+			fetcher.request(&[*key]);
+			MISSED_LOOKUPS.with(|missed| missed.set(missed.get() + 1));
+		}
+	}
+}
+
+impl HashDB<KeccakHasher, DBValue> for BeamDB {
+	fn keys(&self) -> HashMap<H256, i32> {
+		self.inner.keys()
+	}
+
+	fn get(&self, key: &H256) -> Option<DBValue> {
+		let value = self.inner.get(key);
+		if value.is_none() {
+			self.request(key);
+		}
+		value
+	}
+
+	fn contains(&self, key: &H256) -> bool {
+		let contains = self.inner.contains(key);
+		if !contains {
+			self.request(key);
+		}
+		contains
+	}
+
+	fn insert(&mut self, value: &[u8]) -> H256 {
+		self.inner.insert(value)
+	}
+
+	fn emplace(&mut self, key: H256, value: DBValue) {
+		self.inner.emplace(key, value);
+	}
+
+	fn remove(&mut self, key: &H256) {
+		self.inner.remove(key);
+	}
+}
+
+impl JournalDB for BeamDB {
+	fn boxed_clone(&self) -> Box<JournalDB> {
+		Box::new(BeamDB {
+			inner: self.inner.boxed_clone(),
+			fetcher: self.fetcher.clone(),
+			column: self.column,
+		})
+	}
+
+	fn mem_used(&self) -> usize {
+		self.inner.mem_used()
+	}
+
+	fn journal_size(&self) -> usize {
+		self.inner.journal_size()
+	}
+
+	fn is_empty(&self) -> bool {
+		self.inner.is_empty()
+	}
+
+	fn earliest_era(&self) -> Option<u64> {
+		self.inner.earliest_era()
+	}
+
+	fn latest_era(&self) -> Option<u64> {
+		self.inner.latest_era()
+	}
+
+	fn journal_under(&mut self, batch: &mut DBTransaction, now: u64, id: &H256) -> io::Result<u32> {
+		self.inner.journal_under(batch, now, id)
+	}
+
+	fn mark_canonical(&mut self, batch: &mut DBTransaction, era: u64, id: &H256) -> io::Result<u32> {
+		self.inner.mark_canonical(batch, era, id)
+	}
+
+	fn inject(&mut self, batch: &mut DBTransaction) -> io::Result<u32> {
+		self.inner.inject(batch)
+	}
+
+	fn state(&self, id: &H256) -> Option<Bytes> {
+		self.inner.state(id)
+	}
+
+	fn is_pruned(&self) -> bool {
+		self.inner.is_pruned()
+	}
+
+	fn backing(&self) -> &Arc<KeyValueDB> {
+		self.inner.backing()
+	}
+
+	fn flush(&self) {
+		self.inner.flush()
+	}
+
+	fn consolidate(&mut self, overlay: MemoryDB<KeccakHasher, DBValue>) {
+		self.inner.consolidate(overlay)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use hashdb::HashDB;
+	use keccak::keccak;
+	use kvdb_memorydb;
+	use parking_lot::{Mutex, RwLock};
+	use {Algorithm, JournalDB};
+	use super::*;
+
+	#[derive(Default)]
+	struct QueueFetcher {
+		requests: Mutex<Vec<H256>>,
+	}
+
+	impl NodeFetcher for QueueFetcher {
+		fn request(&self, keys: &[H256]) {
+			self.requests.lock().extend_from_slice(keys);
+		}
+
+		fn take_fetched(&self) -> Vec<(H256, DBValue)> {
+			Vec::new()
+		}
+
+		fn pending(&self) -> usize {
+			self.requests.lock().len()
+		}
+	}
+
+	#[test]
+	fn requests_missing_values() {
+		let backing = Arc::new(kvdb_memorydb::create(0));
+		let fetcher = Arc::new(QueueFetcher::default());
+		let slot: FetcherSlot = Arc::new(RwLock::new(None));
+		let inner = ::new(backing.clone(), Algorithm::OverlayRecent, None);
+		let db = BeamDB::new(inner, slot.clone(), None);
+
+		let remote = keccak(b"remote");
+		assert!(db.get(&remote).is_none());
+		assert_eq!(missed_lookups(), 0);
+
+		*slot.write() = Some(fetcher.clone() as Arc<NodeFetcher>);
+		assert!(db.get(&remote).is_none());
+		assert!(!db.contains(&remote));
+		assert_eq!(*fetcher.requests.lock(), vec![remote, remote]);
+		assert_eq!(missed_lookups(), 2);
+
+		// values written to the backing database are found without a request.
+		let mut batch = DBTransaction::new();
+		batch.put(None, &remote, b"remote");
+		backing.write(batch).unwrap();
+		let clone = db.boxed_clone();
+		assert_eq!(clone.get(&remote).unwrap(), DBValue::from_slice(b"remote"));
+		assert_eq!(fetcher.requests.lock().len(), 2);
+		assert_eq!(missed_lookups(), 2);
+	}
+
+	#[test]
+	fn checks_values_against_keys() {
+		let mut mangled = keccak(b"remote");
+		mangled[0] ^= 1;
+		assert!(!is_value_for(&mangled, b"remote"));
+
+		let mut storage_key = keccak(b"remote");
+		storage_key[31] ^= 1;
+		assert!(is_value_for(&storage_key, b"remote"));
+	}
+}
@@ -48,11 +48,13 @@ mod overlayrecentdb;
 mod refcounteddb;
 mod util;
 mod as_hash_db_impls;
+mod beamdb;
 
 pub mod overlaydb;
 
 /// Export the `JournalDB` trait.
 pub use self::traits::JournalDB;
+pub use self::beamdb::{NodeFetcher, FetcherSlot, is_value_for, missed_lookups};
 
 /// Journal database operating strategy.
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -146,6 +148,12 @@ pub fn new(backing: Arc<::kvdb::KeyValueDB>, algorithm: Algorithm, col: Option<u
 	}
 }
 
+/// Create a new `JournalDB` trait object which fetches state missing from the backing database
+/// with the node fetcher in `fetcher`, once it is set.
+pub fn new_beam(backing: Arc<::kvdb::KeyValueDB>, algorithm: Algorithm, col: Option<u32>, fetcher: FetcherSlot) -> Box<JournalDB> {
+	Box::new(beamdb::BeamDB::new(new(backing, algorithm, col), fetcher, col))
+}
+
 // all keys must be at least 12 bytes
 const DB_PREFIX_LEN : usize = ::kvdb::PREFIX_LEN;
 const LATEST_ERA_KEY : [u8; ::kvdb::PREFIX_LEN] = [ b'l', b'a', b's', b't', 0, 0, 0, 0, 0, 0, 0, 0 ];
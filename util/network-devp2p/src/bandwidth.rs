@@ -0,0 +1,202 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Egress rate limiting and fair queueing of subprotocol packets.
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::time::Instant;
+use network::ProtocolId;
+
+/// Number of bytes a subprotocol queue is credited with on each round.
+const QUANTUM: usize = 16 * 1024;
+
+/// Token bucket limiting traffic to a number of bytes per second.
+///
+/// A packet may be sent whenever the bucket is not empty; its full size is then
+/// taken from the bucket, possibly leaving it in debt. This lets packets larger
+/// than the burst size through while keeping the long-term rate.
+pub struct RateLimiter {
+	rate: u64,
+	capacity: i64,
+	tokens: i64,
+	updated: Instant,
+}
+
+impl RateLimiter {
+	/// Create a new limiter allowing `rate` bytes per second, with up to a second worth of burst.
+	pub fn new(rate: u64) -> Self {
+		let capacity = cmp::max(rate, 1) as i64;
+		RateLimiter {
+			rate,
+			capacity,
+			tokens: capacity,
+			updated: Instant::now(),
+		}
+	}
+
+	fn refill(&mut self, now: Instant) {
+		if now <= self.updated {
+			return;
+		}
+		let elapsed = now - self.updated;
+		let micros = elapsed.as_secs() * 1_000_000 + u64::from(elapsed.subsec_nanos() / 1_000);
+		let added = self.rate.saturating_mul(micros) / 1_000_000;
+		// Keep accumulating fractional credit until at least one byte is earned.
+		if added > 0 {
+			self.tokens = cmp::min(self.capacity, self.tokens.saturating_add(added as i64));
+			self.updated = now;
+		}
+	}
+
+	/// Check if a packet can be sent now.
+	pub fn can_send(&mut self, now: Instant) -> bool {
+		self.refill(now);
+		self.tokens > 0
+	}
+
+	/// Account for a sent packet.
+	pub fn consume(&mut self, bytes: usize) {
+		self.tokens = self.tokens.saturating_sub(bytes as i64);
+	}
+}
+
+struct ProtocolQueue {
+	protocol: ProtocolId,
+	packets: VecDeque<Vec<u8>>,
+	deficit: usize,
+}
+
+/// Per-subprotocol packet queues served with deficit round robin, so that a protocol
+/// sending large packets (e.g. snapshot chunks) can't starve the others.
+/// Packets of the same protocol are always sent in order.
+#[derive(Default)]
+pub struct EgressQueue {
+	queues: Vec<ProtocolQueue>,
+	current: usize,
+	credited: bool,
+	len: usize,
+}
+
+impl EgressQueue {
+	/// Enqueue a packet for the given protocol.
+	pub fn push(&mut self, protocol: ProtocolId, packet: Vec<u8>) {
+		match self.queues.iter().position(|q| q.protocol == protocol) {
+			Some(i) => self.queues[i].packets.push_back(packet),
+			None => self.queues.push(ProtocolQueue {
+				protocol,
+				packets: vec![packet].into(),
+				deficit: 0,
+			}),
+		}
+		self.len += 1;
+	}
+
+	/// Dequeue the next packet to be sent.
+	pub fn pop(&mut self) -> Option<(ProtocolId, Vec<u8>)> {
+		if self.len == 0 {
+			return None;
+		}
+		loop {
+			let index = self.current % self.queues.len();
+			{
+				let queue = &mut self.queues[index];
+				if !self.credited {
+					queue.deficit += QUANTUM;
+					self.credited = true;
+				}
+				match queue.packets.front().map(Vec::len) {
+					Some(size) if size <= queue.deficit => {
+						queue.deficit -= size;
+						self.len -= 1;
+						let packet = queue.packets.pop_front().expect("front element exists; qed");
+						return Some((queue.protocol, packet));
+					},
+					Some(_) => {},
+					None => queue.deficit = 0,
+				}
+			}
+			self.current = (index + 1) % self.queues.len();
+			self.credited = false;
+		}
+	}
+
+	/// Check if there are no packets queued.
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	#[test]
+	fn rate_limiter_refills() {
+		let start = Instant::now();
+		let mut limiter = RateLimiter::new(1000);
+		assert!(limiter.can_send(start));
+		limiter.consume(1500);
+		assert!(!limiter.can_send(start));
+		assert!(!limiter.can_send(start + Duration::from_millis(400)));
+		assert!(limiter.can_send(start + Duration::from_millis(600)));
+		// Large packets leave the bucket in debt
+		limiter.consume(5000);
+		assert!(!limiter.can_send(start + Duration::from_millis(5000)));
+		assert!(limiter.can_send(start + Duration::from_millis(5600)));
+		// Idle time only earns up to a second of burst
+		limiter.consume(1);
+		assert!(limiter.can_send(start + Duration::from_millis(60000)));
+		limiter.consume(1000);
+		assert!(!limiter.can_send(start + Duration::from_millis(60000)));
+	}
+
+	#[test]
+	fn egress_queue_keeps_protocol_order() {
+		let mut queue = EgressQueue::default();
+		queue.push(*b"eth", vec![1]);
+		queue.push(*b"eth", vec![2]);
+		queue.push(*b"eth", vec![3]);
+		assert_eq!(queue.pop(), Some((*b"eth", vec![1])));
+		assert_eq!(queue.pop(), Some((*b"eth", vec![2])));
+		assert_eq!(queue.pop(), Some((*b"eth", vec![3])));
+		assert_eq!(queue.pop(), None);
+		assert!(queue.is_empty());
+	}
+
+	#[test]
+	fn egress_queue_is_fair() {
+		let mut queue = EgressQueue::default();
+		for _ in 0..4 {
+			queue.push(*b"par", vec![0u8; QUANTUM * 2]);
+		}
+		for _ in 0..16 {
+			queue.push(*b"eth", vec![0u8; QUANTUM / 4]);
+		}
+		queue.push(*b"shh", vec![0u8; 10]);
+
+		let order: Vec<_> = (0..21).map(|_| queue.pop().unwrap().0).collect();
+		// Small packets go through while the large ones wait for enough credit
+		assert_eq!(&order[0..5], &[*b"eth", *b"eth", *b"eth", *b"eth", *b"shh"]);
+		// Bytes sent by each protocol stay close to each other
+		let sent = |order: &[ProtocolId], protocol: &ProtocolId| order.iter().filter(|p| *p == protocol).count();
+		let eth = sent(&order[0..14], b"eth") * QUANTUM / 4;
+		let par = sent(&order[0..14], b"par") * QUANTUM * 2;
+		assert!((eth as isize - par as isize).abs() <= 2 * QUANTUM as isize);
+		assert!(queue.is_empty());
+	}
+}
@@ -120,6 +120,11 @@ impl<Socket: GenericSocket> GenericConnection<Socket> {
 		self.interest.is_writable()
 	}
 
+	/// Number of packets waiting to be written to the socket.
+	pub fn send_queue_len(&self) -> usize {
+		self.send_queue.len()
+	}
+
 	/// Writable IO handler. Called when the socket is ready to send.
 	pub fn writable<Message>(&mut self, io: &IoContext<Message>) -> Result<WriteStatus, Error> where Message: Send + Clone + Sync + 'static {
 		{
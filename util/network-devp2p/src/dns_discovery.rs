@@ -0,0 +1,845 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Node list discovery via DNS (EIP-1459).
+//!
+//! A node list is published as a merkle tree of TXT records under a domain. The root record
+//! is signed by a well-known key, which is part of the `enrtree://<key>@<domain>` URL, and every
+//! other record is referenced by the base32 encoded truncated hash of its content, so a single
+//! valid root authenticates the whole tree. The tree is walked lazily: every call to
+//! `DnsDiscovery::poll` resolves at most a few records.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::{self, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use base32;
+use base64;
+use ethereum_types::{H256, H264, H520};
+use ethkey::{recover, Public, Signature};
+use hash::keccak;
+use network::{Error, ErrorKind};
+use rand::{self, Rng};
+use rlp::{Rlp, RlpStream};
+use discovery::NodeEntry;
+use node_table::NodeEndpoint;
+
+/// Prefix of node tree URLs.
+pub const ENRTREE_PREFIX: &str = "enrtree://";
+
+const ROOT_PREFIX: &str = "enrtree-root:v1";
+const BRANCH_PREFIX: &str = "enrtree-branch:";
+const ENR_PREFIX: &str = "enr:";
+// Number of hash bytes used to name subdomains.
+const HASH_ABBREV_SIZE: usize = 16;
+// Maximal size of an encoded node record.
+const MAX_ENR_SIZE: usize = 300;
+// Maximal number of records resolved in a single `poll` call.
+const MAX_LOOKUPS_PER_POLL: usize = 8;
+// Root records are re-checked for updates after this period.
+const ROOT_RECHECK_INTERVAL: Duration = Duration::from_secs(30 * 60);
+// Timeout of a single DNS query.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+// DNS record type for TXT records.
+const DNS_TYPE_TXT: u16 = 16;
+const DNS_CLASS_IN: u16 = 1;
+const DNS_PORT: u16 = 53;
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+fn invalid_tree(reason: &str) -> Error {
+	ErrorKind::InvalidNodeTree(reason.into()).into()
+}
+
+/// Resolves DNS TXT records.
+pub trait Resolver: Send + Sync {
+	/// Returns all TXT records of the given fully qualified domain name. Character strings
+	/// belonging to the same record are concatenated.
+	fn txt_records(&self, name: &str) -> Result<Vec<String>, Error>;
+}
+
+/// Resolver querying the name servers configured for the system over UDP.
+pub struct SystemResolver {
+	servers: Vec<SocketAddr>,
+}
+
+impl SystemResolver {
+	/// Create a resolver using the given name servers.
+	pub fn new(servers: Vec<SocketAddr>) -> Self {
+		SystemResolver { servers }
+	}
+
+	/// Create a resolver using the name servers listed in `/etc/resolv.conf`.
+	pub fn from_system_conf() -> Self {
+		let mut conf = String::new();
+		if let Err(e) = fs::File::open(RESOLV_CONF).and_then(|mut f| f.read_to_string(&mut conf)) {
+			debug!(target: "network", "Error reading {}: {:?}", RESOLV_CONF, e);
+		}
+		let servers = conf.lines()
+			.filter_map(|line| {
+				let mut tokens = line.split_whitespace();
+				match (tokens.next(), tokens.next()) {
+					(Some("nameserver"), Some(addr)) => IpAddr::from_str(addr).ok(),
+					_ => None,
+				}
+			})
+			.map(|ip| SocketAddr::new(ip, DNS_PORT))
+			.collect();
+		SystemResolver::new(servers)
+	}
+
+	fn query(&self, server: &SocketAddr, name: &str) -> Result<Vec<String>, Error> {
+		let id: u16 = rand::thread_rng().gen();
+		let bind_address = match *server {
+			SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0),
+			SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0)), 0),
+		};
+		let socket = UdpSocket::bind(bind_address)?;
+		socket.set_read_timeout(Some(QUERY_TIMEOUT))?;
+		socket.send_to(&dns::txt_query(id, name)?, server)?;
+
+		let mut buf = [0u8; 4096];
+		loop {
+			let (len, from) = socket.recv_from(&mut buf)?;
+			if from != *server {
+				continue;
+			}
+			match dns::parse_txt_response(id, &buf[..len]) {
+				Err(ref e) if e.kind() == io::ErrorKind::InvalidData => {
+					trace!(target: "network", "Discarding DNS response from {}: {:?}", from, e);
+				},
+				result => return result.map_err(|e| ErrorKind::AddressResolve(Some(e)).into()),
+			}
+		}
+	}
+}
+
+impl Resolver for SystemResolver {
+	fn txt_records(&self, name: &str) -> Result<Vec<String>, Error> {
+		let mut last_error: Error = ErrorKind::AddressResolve(None).into();
+		for server in &self.servers {
+			match self.query(server, name) {
+				Ok(records) => return Ok(records),
+				Err(e) => {
+					debug!(target: "network", "DNS query for {} to {} failed: {:?}", name, server, e);
+					last_error = e;
+				},
+			}
+		}
+		Err(last_error)
+	}
+}
+
+/// Minimal DNS wire format support for TXT lookups (RFC 1035).
+mod dns {
+	use std::io;
+
+	const FLAG_RESPONSE: u16 = 0x8000;
+	const FLAG_TRUNCATED: u16 = 0x0200;
+	const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+	const RCODE_MASK: u16 = 0x000f;
+	const RCODE_NXDOMAIN: u16 = 3;
+	const MAX_LABEL_SIZE: usize = 63;
+
+	fn invalid(reason: &str) -> io::Error {
+		io::Error::new(io::ErrorKind::InvalidData, reason.to_owned())
+	}
+
+	/// Build a recursive TXT query for `name`.
+	pub fn txt_query(id: u16, name: &str) -> io::Result<Vec<u8>> {
+		let mut packet = Vec::with_capacity(512);
+		push_u16(&mut packet, id);
+		push_u16(&mut packet, FLAG_RECURSION_DESIRED);
+		push_u16(&mut packet, 1); // questions
+		push_u16(&mut packet, 0); // answers
+		push_u16(&mut packet, 0); // authorities
+		push_u16(&mut packet, 0); // additionals
+		for label in name.trim_right_matches('.').split('.') {
+			if label.is_empty() || label.len() > MAX_LABEL_SIZE {
+				return Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid domain name"));
+			}
+			packet.push(label.len() as u8);
+			packet.extend_from_slice(label.as_bytes());
+		}
+		packet.push(0);
+		push_u16(&mut packet, super::DNS_TYPE_TXT);
+		push_u16(&mut packet, super::DNS_CLASS_IN);
+		Ok(packet)
+	}
+
+	/// Parse the answer section of a response to a query with the given `id`.
+	/// Responses not matching the query are reported with `io::ErrorKind::InvalidData`.
+	pub fn parse_txt_response(id: u16, packet: &[u8]) -> io::Result<Vec<String>> {
+		if packet.len() < 12 || read_u16(packet, 0)? != id {
+			return Err(invalid("Unexpected DNS response"));
+		}
+		let flags = read_u16(packet, 2)?;
+		if flags & FLAG_RESPONSE == 0 {
+			return Err(invalid("DNS packet is not a response"));
+		}
+		match flags & RCODE_MASK {
+			0 => {},
+			RCODE_NXDOMAIN => return Err(io::Error::new(io::ErrorKind::NotFound, "No such domain")),
+			_ => return Err(io::Error::new(io::ErrorKind::Other, "DNS server failure")),
+		}
+		if flags & FLAG_TRUNCATED != 0 {
+			return Err(io::Error::new(io::ErrorKind::Other, "Truncated DNS response"));
+		}
+
+		let questions = read_u16(packet, 4)?;
+		let answers = read_u16(packet, 6)?;
+		let mut offset = 12;
+		for _ in 0..questions {
+			offset = skip_name(packet, offset)? + 4;
+		}
+
+		let mut records = Vec::new();
+		for _ in 0..answers {
+			offset = skip_name(packet, offset)?;
+			let record_type = read_u16(packet, offset)?;
+			let len = read_u16(packet, offset + 8)? as usize;
+			let start = offset + 10;
+			let end = start + len;
+			if end > packet.len() {
+				return Err(invalid("DNS record out of bounds"));
+			}
+			if record_type == super::DNS_TYPE_TXT {
+				let mut text = Vec::new();
+				let mut pos = start;
+				while pos < end {
+					let chunk = packet[pos] as usize;
+					if pos + 1 + chunk > end {
+						return Err(invalid("TXT string out of bounds"));
+					}
+					text.extend_from_slice(&packet[pos + 1..pos + 1 + chunk]);
+					pos += 1 + chunk;
+				}
+				records.push(String::from_utf8(text).map_err(|_| invalid("TXT record is not valid UTF-8"))?);
+			}
+			offset = end;
+		}
+		Ok(records)
+	}
+
+	fn skip_name(packet: &[u8], mut offset: usize) -> io::Result<usize> {
+		loop {
+			let len = *packet.get(offset).ok_or_else(|| invalid("Name out of bounds"))? as usize;
+			match len {
+				0 => return Ok(offset + 1),
+				// compression pointer, the name ends here
+				l if l & 0xc0 == 0xc0 => return Ok(offset + 2),
+				l => offset += l + 1,
+			}
+		}
+	}
+
+	fn read_u16(packet: &[u8], offset: usize) -> io::Result<u16> {
+		match (packet.get(offset), packet.get(offset + 1)) {
+			(Some(hi), Some(lo)) => Ok((*hi as u16) << 8 | *lo as u16),
+			_ => Err(invalid("Packet too short")),
+		}
+	}
+
+	fn push_u16(packet: &mut Vec<u8>, value: u16) {
+		packet.push((value >> 8) as u8);
+		packet.push(value as u8);
+	}
+}
+
+/// Parsed `enrtree://<public key>@<domain>` URL.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EnrTreeUrl {
+	/// Compressed secp256k1 public key signing the tree root.
+	pub public: H264,
+	/// Domain the tree is published under.
+	pub domain: String,
+}
+
+impl FromStr for EnrTreeUrl {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if !s.starts_with(ENRTREE_PREFIX) {
+			bail!(invalid_tree("URL must start with enrtree://"));
+		}
+		let mut parts = s[ENRTREE_PREFIX.len()..].splitn(2, '@');
+		let (key, domain) = match (parts.next(), parts.next()) {
+			(Some(key), Some(domain)) if !domain.is_empty() => (key, domain),
+			_ => bail!(invalid_tree("URL must be of the form enrtree://<key>@<domain>")),
+		};
+		let public = decode_base32(key)
+			.filter(|k| k.len() == 33 && (k[0] == 2 || k[0] == 3))
+			.ok_or_else(|| invalid_tree("Invalid compressed public key"))?;
+
+		Ok(EnrTreeUrl {
+			public: H264::from_slice(&public),
+			domain: domain.trim_right_matches('.').to_owned(),
+		})
+	}
+}
+
+/// Signed root of a node tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Root {
+	/// Hash of the root of the node record subtree.
+	pub enr_root: String,
+	/// Hash of the root of the link subtree.
+	pub link_root: String,
+	/// Sequence number, increased on every update of the tree.
+	pub seq: u32,
+	/// Signature over the record content without the signature itself.
+	pub signature: Signature,
+}
+
+impl Root {
+	/// The content the signature covers.
+	fn signed_text(&self) -> String {
+		format!("{} e={} l={} seq={}", ROOT_PREFIX, self.enr_root, self.link_root, self.seq)
+	}
+
+	/// Check that the root was signed with the given compressed public key.
+	pub fn verify(&self, public: &H264) -> bool {
+		let message = keccak(self.signed_text());
+		match recover(&self.signature, &message) {
+			Ok(signer) => compress(&signer) == *public,
+			Err(_) => false,
+		}
+	}
+}
+
+/// A single record of a node tree.
+#[derive(Debug, Clone)]
+pub enum Entry {
+	/// Tree root.
+	Root(Root),
+	/// Inner node, referencing its children by hash.
+	Branch(Vec<String>),
+	/// Leaf containing a node record.
+	Node(NodeEntry),
+	/// Leaf referencing another tree.
+	Link(EnrTreeUrl),
+}
+
+impl Entry {
+	/// Parse a TXT record.
+	pub fn parse(text: &str) -> Result<Entry, Error> {
+		if text.starts_with(ROOT_PREFIX) {
+			parse_root(text).map(Entry::Root)
+		} else if text.starts_with(BRANCH_PREFIX) {
+			let hashes = text[BRANCH_PREFIX.len()..]
+				.split(',')
+				.filter(|h| !h.is_empty())
+				.map(|h| {
+					if is_valid_hash(h) { Ok(h.to_owned()) } else { Err(invalid_tree("Invalid branch child hash")) }
+				})
+				.collect::<Result<_, _>>()?;
+			Ok(Entry::Branch(hashes))
+		} else if text.starts_with(ENR_PREFIX) {
+			let data = base64::decode_config(&text[ENR_PREFIX.len()..], base64::URL_SAFE_NO_PAD)
+				.map_err(|_| invalid_tree("Invalid base64 in node record"))?;
+			parse_enr(&data).map(Entry::Node)
+		} else if text.starts_with(ENRTREE_PREFIX) {
+			text.parse().map(Entry::Link)
+		} else {
+			Err(invalid_tree("Unknown record type"))
+		}
+	}
+}
+
+fn parse_root(text: &str) -> Result<Root, Error> {
+	let mut enr_root = None;
+	let mut link_root = None;
+	let mut seq = None;
+	let mut signature = None;
+	for field in text[ROOT_PREFIX.len()..].split_whitespace() {
+		let mut kv = field.splitn(2, '=');
+		match (kv.next(), kv.next()) {
+			(Some("e"), Some(v)) if is_valid_hash(v) => enr_root = Some(v.to_owned()),
+			(Some("l"), Some(v)) if is_valid_hash(v) => link_root = Some(v.to_owned()),
+			(Some("seq"), Some(v)) => seq = v.parse().ok(),
+			(Some("sig"), Some(v)) => {
+				signature = base64::decode_config(v, base64::URL_SAFE_NO_PAD).ok()
+					.filter(|s| s.len() == 65)
+					.map(|s| Signature::from(H520::from_slice(&s)));
+			},
+			_ => bail!(invalid_tree("Invalid root record field")),
+		}
+	}
+	match (enr_root, link_root, seq, signature) {
+		(Some(enr_root), Some(link_root), Some(seq), Some(signature)) => Ok(Root { enr_root, link_root, seq, signature }),
+		_ => Err(invalid_tree("Incomplete root record")),
+	}
+}
+
+/// Parse and verify an RLP encoded node record using the "v4" identity scheme (EIP-778).
+fn parse_enr(data: &[u8]) -> Result<NodeEntry, Error> {
+	if data.len() > MAX_ENR_SIZE {
+		bail!(invalid_tree("Node record too large"));
+	}
+	let rlp = Rlp::new(data);
+	let item_count = rlp.item_count()?;
+	if item_count < 2 || item_count % 2 != 0 {
+		bail!(invalid_tree("Invalid node record layout"));
+	}
+
+	let signature: Vec<u8> = rlp.val_at(0)?;
+	let mut content = RlpStream::new_list(item_count - 1);
+	content.append_raw(rlp.at(1)?.as_raw(), 1);
+
+	let mut fields = HashMap::new();
+	for i in (2..item_count).step_by(2) {
+		let key: Vec<u8> = rlp.val_at(i)?;
+		let value = rlp.at(i + 1)?;
+		content.append_raw(rlp.at(i)?.as_raw(), 1);
+		content.append_raw(value.as_raw(), 1);
+		fields.insert(key, value);
+	}
+
+	match fields.get(&b"id"[..]).map(|id| id.data()) {
+		Some(Ok(b"v4")) => {},
+		_ => bail!(invalid_tree("Unsupported identity scheme")),
+	}
+	let public = match fields.get(&b"secp256k1"[..]).map(|k| k.data()) {
+		Some(Ok(key)) if key.len() == 33 => H264::from_slice(key),
+		_ => bail!(invalid_tree("Missing node public key")),
+	};
+	if signature.len() != 64 {
+		bail!(invalid_tree("Invalid node record signature"));
+	}
+
+	// the "v4" scheme signature does not carry the recovery id, so try both.
+	let message = keccak(content.out());
+	let r = H256::from_slice(&signature[..32]);
+	let s = H256::from_slice(&signature[32..]);
+	let id = (0..2)
+		.filter_map(|v| recover(&Signature::from_rsv(&r, &s, v), &message).ok())
+		.find(|recovered| compress(recovered) == public)
+		.ok_or_else(|| invalid_tree("Node record signature does not match its key"))?;
+
+	let ip = match fields.get(&b"ip"[..]).map(|ip| ip.data()) {
+		Some(Ok(ip)) if ip.len() == 4 => IpAddr::V4(Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3])),
+		_ => bail!(invalid_tree("Node record has no IPv4 address")),
+	};
+	let tcp_port: u16 = match fields.get(&b"tcp"[..]) {
+		Some(port) => port.as_val()?,
+		None => bail!(invalid_tree("Node record has no TCP port")),
+	};
+	let udp_port = match fields.get(&b"udp"[..]) {
+		Some(port) => port.as_val()?,
+		None => tcp_port,
+	};
+
+	Ok(NodeEntry {
+		id,
+		endpoint: NodeEndpoint { address: SocketAddr::new(ip, tcp_port), udp_port },
+	})
+}
+
+/// Compressed form of an uncompressed public key.
+fn compress(public: &Public) -> H264 {
+	let mut compressed = H264::default();
+	compressed[0] = 2 + (public[63] & 1);
+	compressed[1..].copy_from_slice(&public[..32]);
+	compressed
+}
+
+/// Subdomain name of a record: abbreviated keccak hash in base32.
+pub fn subdomain(text: &str) -> String {
+	base32::encode(base32::Alphabet::RFC4648 { padding: false }, &keccak(text)[..HASH_ABBREV_SIZE])
+}
+
+fn is_valid_hash(hash: &str) -> bool {
+	decode_base32(hash).map_or(false, |h| h.len() == HASH_ABBREV_SIZE)
+}
+
+fn decode_base32(data: &str) -> Option<Vec<u8>> {
+	// the base32 crate accepts lowercase letters, which EIP-1459 does not allow.
+	if data.bytes().any(|b| b.is_ascii_lowercase()) {
+		return None;
+	}
+	base32::decode(base32::Alphabet::RFC4648 { padding: false }, data)
+}
+
+/// Walking state of a single tree.
+struct Tree {
+	url: EnrTreeUrl,
+	root: Option<Root>,
+	last_root_check: Option<Instant>,
+	// Hashes of node subtree records yet to be resolved.
+	pending_nodes: VecDeque<String>,
+	// Hashes of link subtree records yet to be resolved.
+	pending_links: VecDeque<String>,
+}
+
+impl Tree {
+	fn new(url: EnrTreeUrl) -> Self {
+		Tree {
+			url,
+			root: None,
+			last_root_check: None,
+			pending_nodes: VecDeque::new(),
+			pending_links: VecDeque::new(),
+		}
+	}
+
+	fn is_exhausted(&self) -> bool {
+		self.root.is_some() && self.pending_nodes.is_empty() && self.pending_links.is_empty()
+	}
+
+	fn needs_root_check(&self) -> bool {
+		self.last_root_check.map_or(true, |t| t.elapsed() >= ROOT_RECHECK_INTERVAL)
+	}
+}
+
+/// Result of polling the DNS trees.
+#[derive(Default, Debug)]
+pub struct DnsUpdates {
+	/// Newly discovered nodes.
+	pub nodes: Vec<NodeEntry>,
+}
+
+/// Lazily walks EIP-1459 node trees.
+pub struct DnsDiscovery {
+	resolver: Arc<Resolver>,
+	trees: Vec<Tree>,
+	known_trees: HashSet<EnrTreeUrl>,
+	next_tree: usize,
+}
+
+impl DnsDiscovery {
+	/// Create a new instance using the given resolver.
+	pub fn new(resolver: Arc<Resolver>) -> Self {
+		DnsDiscovery {
+			resolver,
+			trees: Vec::new(),
+			known_trees: HashSet::new(),
+			next_tree: 0,
+		}
+	}
+
+	/// Add a tree to walk.
+	pub fn add_tree(&mut self, url: EnrTreeUrl) {
+		if self.known_trees.insert(url.clone()) {
+			self.trees.push(Tree::new(url));
+		}
+	}
+
+	/// Returns `true` if there are no trees to walk.
+	pub fn is_empty(&self) -> bool {
+		self.trees.is_empty()
+	}
+
+	/// Resolve a bounded number of records, round-robin across trees, and return the nodes found.
+	pub fn poll(&mut self) -> DnsUpdates {
+		let mut updates = DnsUpdates::default();
+		let mut lookups = 0;
+		let mut idle_trees = 0;
+		while lookups < MAX_LOOKUPS_PER_POLL && idle_trees < self.trees.len() {
+			let index = self.next_tree % self.trees.len();
+			self.next_tree = index + 1;
+			match self.step(index, &mut updates) {
+				Ok(true) => {
+					lookups += 1;
+					idle_trees = 0;
+				},
+				Ok(false) => idle_trees += 1,
+				Err(e) => {
+					debug!(target: "network", "Error walking node tree {}: {:?}", self.trees[index].url.domain, e);
+					lookups += 1;
+					idle_trees += 1;
+				},
+			}
+		}
+		updates
+	}
+
+	/// Resolve the next record of a tree. Returns `false` if there was nothing to do.
+	fn step(&mut self, index: usize, updates: &mut DnsUpdates) -> Result<bool, Error> {
+		if self.trees[index].needs_root_check() {
+			self.sync_root(index)?;
+			return Ok(true);
+		}
+		if self.trees[index].is_exhausted() {
+			return Ok(false);
+		}
+
+		let (hash, is_link) = {
+			let tree = &mut self.trees[index];
+			match tree.pending_nodes.pop_front() {
+				Some(hash) => (hash, false),
+				None => match tree.pending_links.pop_front() {
+					Some(hash) => (hash, true),
+					None => return Ok(false),
+				},
+			}
+		};
+
+		match self.resolve_entry(index, &hash)? {
+			Entry::Branch(children) => {
+				let tree = &mut self.trees[index];
+				let pending = if is_link { &mut tree.pending_links } else { &mut tree.pending_nodes };
+				pending.extend(children);
+			},
+			Entry::Node(ref node) if !is_link => updates.nodes.push(node.clone()),
+			Entry::Link(ref url) if is_link => self.add_tree(url.clone()),
+			_ => bail!(invalid_tree("Unexpected record type in subtree")),
+		}
+		Ok(true)
+	}
+
+	/// Fetch and verify the root record, restarting the walk if the tree changed.
+	fn sync_root(&mut self, index: usize) -> Result<(), Error> {
+		let tree = &mut self.trees[index];
+		tree.last_root_check = Some(Instant::now());
+
+		let root = self.resolver.txt_records(&tree.url.domain)?
+			.into_iter()
+			.filter(|record| record.starts_with(ROOT_PREFIX))
+			.filter_map(|record| parse_root(&record).ok())
+			.find(|root| root.verify(&tree.url.public))
+			.ok_or_else(|| invalid_tree("No valid root record"))?;
+
+		if tree.root.as_ref().map_or(false, |r| r.seq >= root.seq) {
+			trace!(target: "network", "Node tree {} unchanged", tree.url.domain);
+			return Ok(());
+		}
+
+		debug!(target: "network", "Syncing node tree {} with sequence number {}", tree.url.domain, root.seq);
+		tree.pending_nodes = vec![root.enr_root.clone()].into();
+		tree.pending_links = vec![root.link_root.clone()].into();
+		tree.root = Some(root);
+		Ok(())
+	}
+
+	/// Resolve the record with the given hash, checking it against its hash.
+	fn resolve_entry(&self, index: usize, hash: &str) -> Result<Entry, Error> {
+		let name = format!("{}.{}", hash, self.trees[index].url.domain);
+		let record = self.resolver.txt_records(&name)?
+			.into_iter()
+			.find(|record| subdomain(record) == hash)
+			.ok_or_else(|| invalid_tree("Record content does not match its hash"))?;
+		Entry::parse(&record)
+	}
+}
+
+/// Check if a node tree URL is valid.
+pub fn validate_tree_url(url: &str) -> Option<Error> {
+	EnrTreeUrl::from_str(url).err()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::HashMap;
+	use ethkey::{sign, Generator, KeyPair, Random};
+	use parking_lot::Mutex;
+
+	/// Serves TXT records from memory, counting lookups.
+	#[derive(Default)]
+	struct FakeResolver {
+		records: HashMap<String, Vec<String>>,
+		lookups: Mutex<usize>,
+	}
+
+	impl FakeResolver {
+		fn insert(&mut self, name: String, record: String) {
+			self.records.entry(name).or_insert_with(Vec::new).push(record);
+		}
+	}
+
+	impl Resolver for FakeResolver {
+		fn txt_records(&self, name: &str) -> Result<Vec<String>, Error> {
+			*self.lookups.lock() += 1;
+			self.records.get(name).cloned().ok_or_else(|| ErrorKind::AddressResolve(None).into())
+		}
+	}
+
+	fn enr(key: &KeyPair, port: u16) -> String {
+		let public = compress(key.public());
+		let mut content = RlpStream::new_list(9);
+		content.append(&1u64);
+		content.append(&"id").append(&"v4");
+		content.append(&"ip").append(&vec![127u8, 0, 0, 1]);
+		content.append(&"secp256k1").append(&&public[..]);
+		content.append(&"tcp").append(&port);
+		let signature = sign(key.secret(), &keccak(content.out())).unwrap();
+
+		let mut record = RlpStream::new_list(10);
+		record.append(&&signature[..64]);
+		record.append(&1u64);
+		record.append(&"id").append(&"v4");
+		record.append(&"ip").append(&vec![127u8, 0, 0, 1]);
+		record.append(&"secp256k1").append(&&public[..]);
+		record.append(&"tcp").append(&port);
+		format!("{}{}", ENR_PREFIX, base64::encode_config(&record.out(), base64::URL_SAFE_NO_PAD))
+	}
+
+	fn url(key: &KeyPair, domain: &str) -> String {
+		format!("{}{}@{}", ENRTREE_PREFIX, base32::encode(base32::Alphabet::RFC4648 { padding: false }, &compress(key.public())), domain)
+	}
+
+	fn signed_root(key: &KeyPair, enr_root: &str, link_root: &str, seq: u32) -> String {
+		let mut root = Root { enr_root: enr_root.into(), link_root: link_root.into(), seq, signature: Signature::default() };
+		root.signature = sign(key.secret(), &keccak(root.signed_text())).unwrap();
+		let signature: [u8; 65] = root.signature.clone().into();
+		format!("{} sig={}", root.signed_text(), base64::encode_config(&signature[..], base64::URL_SAFE_NO_PAD))
+	}
+
+	/// Publish `nodes` in a tree of the given branching factor, linking to `links`.
+	fn publish(resolver: &mut FakeResolver, key: &KeyPair, domain: &str, nodes: &[KeyPair], links: &[String], width: usize) -> String {
+		let mut insert = |resolver: &mut FakeResolver, record: String| {
+			let hash = subdomain(&record);
+			resolver.insert(format!("{}.{}", hash, domain), record);
+			hash
+		};
+
+		let leaves: Vec<_> = nodes.iter().enumerate().map(|(i, n)| insert(resolver, enr(n, 30303 + i as u16))).collect();
+		let branches: Vec<_> = leaves.chunks(width).map(|c| insert(resolver, format!("{}{}", BRANCH_PREFIX, c.join(",")))).collect();
+		let enr_root = insert(resolver, format!("{}{}", BRANCH_PREFIX, branches.join(",")));
+		let link_leaves: Vec<_> = links.iter().map(|l| insert(resolver, l.clone())).collect();
+		let link_root = insert(resolver, format!("{}{}", BRANCH_PREFIX, link_leaves.join(",")));
+
+		resolver.insert(domain.into(), signed_root(key, &enr_root, &link_root, 1));
+		url(key, domain)
+	}
+
+	fn walk(discovery: &mut DnsDiscovery) -> Vec<NodeEntry> {
+		let mut nodes = Vec::new();
+		loop {
+			let updates = discovery.poll();
+			if updates.nodes.is_empty() && discovery.trees.iter().all(|t| t.is_exhausted()) {
+				return nodes;
+			}
+			nodes.extend(updates.nodes);
+		}
+	}
+
+	#[test]
+	fn parses_tree_url() {
+		let key = Random.generate().unwrap();
+		let url: EnrTreeUrl = url(&key, "nodes.example.org").parse().unwrap();
+		assert_eq!(url.public, compress(key.public()));
+		assert_eq!(url.domain, "nodes.example.org");
+
+		assert!(validate_tree_url("enrtree://nodes.example.org").is_some());
+		assert!(validate_tree_url("enrtree://AAAA@nodes.example.org").is_some());
+		assert!(validate_tree_url("enode://AAAA@nodes.example.org").is_some());
+	}
+
+	#[test]
+	fn verifies_eip778_record() {
+		// the example record from EIP-778 is validly signed, but only advertises a UDP port
+		let record = "enr:-IS4QHCYrYZbAKWCBRlAy5zzaDZXJBGkcnh4MHcBFZntXNFrdvJjX04jRzjzCBOonrkTfj499SZuOh8R33Ls8RRcy5wBgmlkgnY0gmlwhH8AAAGJc2VjcDI1NmsxoQPKY0yuDUmstAHYpMa2_oxVtw0RW_QAdpzBQA8yWM0xOIN1ZHCCdl8";
+		assert_matches!(Entry::parse(record).unwrap_err().kind(), &ErrorKind::InvalidNodeTree(ref r) if r == "Node record has no TCP port");
+
+		let mut tampered = base64::decode_config(&record[ENR_PREFIX.len()..], base64::URL_SAFE_NO_PAD).unwrap();
+		tampered[10] ^= 1;
+		assert_matches!(parse_enr(&tampered).unwrap_err().kind(), &ErrorKind::InvalidNodeTree(ref r) if r == "Node record signature does not match its key");
+	}
+
+	#[test]
+	fn walks_whole_tree_lazily() {
+		let key = Random.generate().unwrap();
+		let nodes: Vec<_> = (0..10).map(|_| Random.generate().unwrap()).collect();
+		let mut resolver = FakeResolver::default();
+		let url = publish(&mut resolver, &key, "nodes.example.org", &nodes, &[], 3);
+		let resolver = Arc::new(resolver);
+
+		let mut discovery = DnsDiscovery::new(resolver.clone());
+		discovery.add_tree(url.parse().unwrap());
+
+		// a single poll never resolves more than the lookup limit
+		let first = discovery.poll();
+		assert_eq!(*resolver.lookups.lock(), MAX_LOOKUPS_PER_POLL);
+
+		let mut found: Vec<_> = first.nodes.into_iter().chain(walk(&mut discovery)).map(|n| n.id).collect();
+		let mut expected: Vec<_> = nodes.iter().map(|n| *n.public()).collect();
+		found.sort();
+		expected.sort();
+		assert_eq!(found, expected);
+
+		// root, enr root, 4 branches, 10 leaves, link root
+		assert_eq!(*resolver.lookups.lock(), 17);
+		assert!(discovery.poll().nodes.is_empty());
+	}
+
+	#[test]
+	fn follows_links() {
+		let key = Random.generate().unwrap();
+		let other_key = Random.generate().unwrap();
+		let nodes: Vec<_> = (0..2).map(|_| Random.generate().unwrap()).collect();
+		let other_nodes: Vec<_> = (0..3).map(|_| Random.generate().unwrap()).collect();
+
+		let mut resolver = FakeResolver::default();
+		let other_url = publish(&mut resolver, &other_key, "other.example.org", &other_nodes, &[], 2);
+		let url = publish(&mut resolver, &key, "nodes.example.org", &nodes, &[other_url], 2);
+
+		let mut discovery = DnsDiscovery::new(Arc::new(resolver));
+		discovery.add_tree(url.parse().unwrap());
+		assert_eq!(walk(&mut discovery).len(), 5);
+	}
+
+	#[test]
+	fn rejects_root_with_wrong_signature() {
+		let key = Random.generate().unwrap();
+		let impostor = Random.generate().unwrap();
+		let nodes: Vec<_> = (0..2).map(|_| Random.generate().unwrap()).collect();
+		let mut resolver = FakeResolver::default();
+		publish(&mut resolver, &impostor, "nodes.example.org", &nodes, &[], 2);
+
+		let mut discovery = DnsDiscovery::new(Arc::new(resolver));
+		discovery.add_tree(url(&key, "nodes.example.org").parse().unwrap());
+		assert!(discovery.poll().nodes.is_empty());
+		assert!(discovery.trees[0].root.is_none());
+	}
+
+	#[test]
+	fn rejects_record_not_matching_hash() {
+		let key = Random.generate().unwrap();
+		let node = Random.generate().unwrap();
+		let mut resolver = FakeResolver::default();
+		let url = publish(&mut resolver, &key, "nodes.example.org", &[node], &[], 1);
+
+		// replace every leaf with a record that doesn't hash to its name
+		let forged = enr(&Random.generate().unwrap(), 1);
+		for records in resolver.records.values_mut() {
+			for record in records.iter_mut().filter(|r| r.starts_with(ENR_PREFIX)) {
+				*record = forged.clone();
+			}
+		}
+
+		let mut discovery = DnsDiscovery::new(Arc::new(resolver));
+		discovery.add_tree(url.parse().unwrap());
+		assert!(walk(&mut discovery).is_empty());
+	}
+
+	#[test]
+	fn dns_txt_roundtrip() {
+		let query = dns::txt_query(0x1234, "nodes.example.org").unwrap();
+		assert_eq!(&query[..2], &[0x12, 0x34]);
+		assert_eq!(&query[12..], &b"\x05nodes\x07example\x03org\x00\x00\x10\x00\x01"[..]);
+
+		// response: header, echoed question, one answer with two character strings using name compression
+		let mut response = vec![0x12, 0x34, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0];
+		response.extend_from_slice(&query[12..]);
+		response.extend_from_slice(&[0xc0, 0x0c, 0, 16, 0, 1, 0, 0, 0, 60, 0, 8]);
+		response.extend_from_slice(b"\x03abc\x03def");
+		assert_eq!(dns::parse_txt_response(0x1234, &response).unwrap(), vec!["abcdef".to_owned()]);
+		assert_eq!(dns::parse_txt_response(0x4321, &response).unwrap_err().kind(), io::ErrorKind::InvalidData);
+	}
+}
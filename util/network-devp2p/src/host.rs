@@ -17,13 +17,14 @@
 use std::net::{SocketAddr, SocketAddrV4, Ipv4Addr};
 use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, mpsc};
 use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use std::ops::*;
 use std::cmp::{min, max};
 use std::path::{Path, PathBuf};
 use std::io::{Read, Write, self};
 use std::fs;
+use std::thread;
 use std::time::Duration;
 use ethkey::{KeyPair, Secret, Random, Generator};
 use hash::keccak;
@@ -41,8 +42,12 @@ use node_table::*;
 use network::{NetworkConfiguration, NetworkIoMessage, ProtocolId, PeerId, PacketId};
 use network::{NonReservedPeerMode, NetworkContext as NetworkContextTrait};
 use network::{SessionInfo, Error, ErrorKind, DisconnectReason, NetworkProtocolHandler};
+use network::{NodeReputation, PeerBehaviour};
 use discovery::{Discovery, TableUpdates, NodeEntry, MAX_DATAGRAM_SIZE};
+use bandwidth::RateLimiter;
+use dns_discovery::{DnsDiscovery, EnrTreeUrl, Resolver, ENRTREE_PREFIX};
 use ip_utils::{map_external_address, select_public_address};
+use nat_pmp::Lease;
 use parity_path::restrict_permissions_owner;
 use parking_lot::{Mutex, RwLock};
 use network::{ConnectionFilter, ConnectionDirection};
@@ -62,6 +67,9 @@ const DISCOVERY_REFRESH: TimerToken = SYS_TIMER + 4;
 const FAST_DISCOVERY_REFRESH: TimerToken = SYS_TIMER + 5;
 const DISCOVERY_ROUND: TimerToken = SYS_TIMER + 6;
 const NODE_TABLE: TimerToken = SYS_TIMER + 7;
+const DNS_DISCOVERY: TimerToken = SYS_TIMER + 8;
+const EGRESS: TimerToken = SYS_TIMER + 9;
+const NAT_RENEWAL: TimerToken = SYS_TIMER + 10;
 const FIRST_SESSION: StreamToken = 0;
 const LAST_SESSION: StreamToken = FIRST_SESSION + MAX_SESSIONS - 1;
 const USER_TIMER: TimerToken = LAST_SESSION + 256;
@@ -78,6 +86,12 @@ const FAST_DISCOVERY_REFRESH_TIMEOUT: Duration = Duration::from_secs(10);
 const DISCOVERY_ROUND_TIMEOUT: Duration = Duration::from_millis(300);
 // for NODE_TABLE TimerToken
 const NODE_TABLE_TIMEOUT: Duration = Duration::from_secs(300);
+// for DNS_DISCOVERY TimerToken
+const DNS_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
+// for EGRESS TimerToken
+const EGRESS_TIMEOUT: Duration = Duration::from_millis(50);
+// for NAT_RENEWAL TimerToken
+const NAT_RENEWAL_TIMEOUT: Duration = Duration::from_secs(60);
 
 #[derive(Debug, PartialEq, Eq)]
 /// Protocol info
@@ -166,6 +180,11 @@ impl<'s> NetworkContextTrait for NetworkContext<'s> {
 			.unwrap_or_else(|e| warn!("Error sending network IO message: {:?}", e));
 	}
 
+	fn report_peer(&self, peer: PeerId, behaviour: PeerBehaviour) {
+		self.io.message(NetworkIoMessage::ReportPeer(peer, behaviour))
+			.unwrap_or_else(|e| warn!("Error sending network IO message: {:?}", e));
+	}
+
 	fn is_expired(&self) -> bool {
 		self.session.as_ref().map_or(false, |s| s.lock().expired())
 	}
@@ -218,6 +237,8 @@ pub struct HostInfo {
 	pub local_endpoint: NodeEndpoint,
 	/// Public address + discovery port
 	pub public_endpoint: Option<NodeEndpoint>,
+	/// Egress limit shared by all sessions
+	pub egress_limit: Option<Arc<Mutex<RateLimiter>>>,
 }
 
 impl HostInfo {
@@ -237,6 +258,10 @@ impl HostInfo {
 	pub(crate) fn id(&self) -> &NodeId {
 		self.keys.public()
 	}
+
+	pub(crate) fn max_peer_egress_rate(&self) -> Option<u64> {
+		self.config.max_peer_egress_rate
+	}
 }
 
 type SharedSession = Arc<Mutex<Session>>;
@@ -254,6 +279,13 @@ pub struct Host {
 	tcp_listener: Mutex<TcpListener>,
 	sessions: Arc<RwLock<Slab<SharedSession>>>,
 	discovery: Mutex<Option<Discovery<'static>>>,
+	dns_discovery: Arc<Mutex<DnsDiscovery>>,
+	/// Set while the DNS discovery worker walks the node trees.
+	dns_polling: Arc<AtomicBool>,
+	/// Nodes resolved by the DNS discovery worker, merged into the node table on the IO thread.
+	dns_nodes: Mutex<mpsc::Receiver<NodeEntry>>,
+	dns_nodes_sender: Mutex<mpsc::Sender<NodeEntry>>,
+	nat_lease: Arc<Mutex<Option<Lease>>>,
 	nodes: RwLock<NodeTable>,
 	handlers: RwLock<HashMap<ProtocolId, Arc<NetworkProtocolHandler + Sync>>>,
 	timers: RwLock<HashMap<TimerToken, ProtocolTimer>>,
@@ -265,7 +297,8 @@ pub struct Host {
 
 impl Host {
 	/// Create a new instance
-	pub fn new(mut config: NetworkConfiguration, filter: Option<Arc<ConnectionFilter>>) -> Result<Host, Error> {
+	pub fn new(mut config: NetworkConfiguration, filter: Option<Arc<ConnectionFilter>>, dns_resolver: Arc<Resolver>) -> Result<Host, Error> {
+		let (dns_nodes_sender, dns_nodes) = mpsc::channel();
 		let mut listen_address = match config.listen_address {
 			None => SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), DEFAULT_PORT)),
 			Some(addr) => addr,
@@ -295,6 +328,7 @@ impl Host {
 		let boot_nodes = config.boot_nodes.clone();
 		let reserved_nodes = config.reserved_nodes.clone();
 		config.max_handshakes = min(config.max_handshakes, MAX_HANDSHAKES as u32);
+		let egress_limit = config.max_egress_rate.map(|rate| Arc::new(Mutex::new(RateLimiter::new(rate))));
 
 		let mut host = Host {
 			info: RwLock::new(HostInfo {
@@ -305,8 +339,14 @@ impl Host {
 				capabilities: Vec::new(),
 				public_endpoint: None,
 				local_endpoint,
+				egress_limit,
 			}),
 			discovery: Mutex::new(None),
+			dns_discovery: Arc::new(Mutex::new(DnsDiscovery::new(dns_resolver))),
+			dns_polling: Arc::new(AtomicBool::new(false)),
+			dns_nodes: Mutex::new(dns_nodes),
+			dns_nodes_sender: Mutex::new(dns_nodes_sender),
+			nat_lease: Arc::new(Mutex::new(None)),
 			udp_socket: Mutex::new(None),
 			tcp_listener: Mutex::new(tcp_listener),
 			sessions: Arc::new(RwLock::new(Slab::new_starting_at(FIRST_SESSION, MAX_SESSIONS))),
@@ -332,6 +372,14 @@ impl Host {
 	}
 
 	pub fn add_node(&mut self, id: &str) {
+		if id.starts_with(ENRTREE_PREFIX) {
+			match EnrTreeUrl::from_str(id) {
+				Err(e) => { debug!(target: "network", "Could not add node tree {}: {:?}", id, e); },
+				Ok(url) => self.dns_discovery.lock().add_tree(url),
+			}
+			return;
+		}
+
 		match Node::from_str(id) {
 			Err(e) => { debug!(target: "network", "Could not add node {}: {:?}", id, e); },
 			Ok(n) => {
@@ -418,9 +466,17 @@ impl Host {
 			trace!(target: "network", "Disconnecting on shutdown: {}", p);
 			self.kill_connection(p, io, true);
 		}
+		if let Some(lease) = self.nat_lease.lock().take() {
+			lease.release();
+		}
 		io.unregister_handler();
 	}
 
+	/// Get the reputation of all known nodes with a non-neutral reputation.
+	pub fn node_reputations(&self) -> Vec<NodeReputation> {
+		self.nodes.read().reputations()
+	}
+
 	/// Get all connected peers.
 	pub fn connected_peers(&self) -> Vec<PeerId> {
 		let sessions = self.sessions.read();
@@ -447,10 +503,15 @@ impl Host {
 				let public_address = select_public_address(local_endpoint.address.port());
 				let public_endpoint = NodeEndpoint { address: public_address, udp_port: local_endpoint.udp_port };
 				if self.info.read().config.nat_enabled {
-					match map_external_address(&local_endpoint) {
-						Some(endpoint) => {
-							info!("NAT mapped to external address {}", endpoint.address);
-							endpoint
+					let nat_type = self.info.read().config.nat_type;
+					match map_external_address(&local_endpoint, nat_type) {
+						Some(mapping) => {
+							info!("NAT mapped to external address {}", mapping.endpoint.address);
+							if mapping.lease.is_some() {
+								io.register_timer(NAT_RENEWAL, NAT_RENEWAL_TIMEOUT)?;
+							}
+							*self.nat_lease.lock() = mapping.lease;
+							mapping.endpoint
 						},
 						None => public_endpoint
 					}
@@ -488,6 +549,17 @@ impl Host {
 			io.register_timer(DISCOVERY_REFRESH, DISCOVERY_REFRESH_TIMEOUT)?;
 			io.register_timer(DISCOVERY_ROUND, DISCOVERY_ROUND_TIMEOUT)?;
 		}
+		let dns_discovery_enabled = self.info.read().config.non_reserved_mode == NonReservedPeerMode::Accept;
+		if dns_discovery_enabled && !self.dns_discovery.lock().is_empty() {
+			io.register_timer(DNS_DISCOVERY, DNS_DISCOVERY_TIMEOUT)?;
+		}
+		let egress_limited = {
+			let info = self.info.read();
+			info.config.max_egress_rate.is_some() || info.config.max_peer_egress_rate.is_some()
+		};
+		if egress_limited {
+			io.register_timer(EGRESS, EGRESS_TIMEOUT)?;
+		}
 		io.register_timer(NODE_TABLE, NODE_TABLE_TIMEOUT)?;
 		io.register_stream(TCP_ACCEPT)?;
 		Ok(())
@@ -536,6 +608,59 @@ impl Host {
 		}
 	}
 
+	fn flush_egress(&self, io: &IoContext<NetworkIoMessage>) {
+		for e in self.sessions.read().iter() {
+			let mut s = e.lock();
+			if s.has_pending_egress() {
+				if let Err(e) = s.flush_egress(io) {
+					debug!(target: "network", "Error sending queued packets to {}: {:?}", s.token(), e);
+				}
+			}
+		}
+	}
+
+	fn renew_nat_lease(&self) {
+		{
+			// The lease is locked while it's being renewed, try again on the next tick.
+			let lease = match self.nat_lease.try_lock() {
+				Some(lease) => lease,
+				None => return,
+			};
+			match *lease {
+				Some(ref lease) => {
+					// Pick up the address returned by the last renewal.
+					let mut info = self.info.write();
+					if info.public_endpoint.as_ref() != Some(&lease.external) {
+						info!("NAT external address changed to {}", lease.external.address);
+						info.public_endpoint = Some(lease.external.clone());
+					}
+					if !lease.needs_renewal() {
+						return;
+					}
+				},
+				None => return,
+			}
+		}
+
+		// Renewing may take several retries, keep the gateway round trips off the IO thread.
+		let nat_lease = self.nat_lease.clone();
+		let renewal = thread::Builder::new()
+			.name("NAT renewal".into())
+			.spawn(move || {
+				if let Some(ref mut lease) = *nat_lease.lock() {
+					if !lease.needs_renewal() {
+						return;
+					}
+					if let Err(e) = lease.renew() {
+						debug!(target: "network", "Error renewing {:?} port mapping: {}", lease.protocol(), e);
+					}
+				}
+			});
+		if let Err(e) = renewal {
+			debug!(target: "network", "Error spawning the NAT renewal thread: {}", e);
+		}
+	}
+
 	fn has_enough_peers(&self) -> bool {
 		let min_peers = {
 			let info = self.info.read();
@@ -761,6 +886,13 @@ impl Host {
 								}
 							}
 
+							if self.nodes.read().is_banned(&id) && !self.reserved_nodes.read().contains(&id) {
+								trace!(target: "network", "Disconnecting banned peer {:?}", id);
+								s.disconnect(io, DisconnectReason::UselessPeer);
+								kill = true;
+								break;
+							}
+
 							if !self.filter.as_ref().map_or(true, |f| f.connection_allowed(&self_id, &id, ConnectionDirection::Inbound)) {
 								trace!(target: "network", "Inbound connection not allowed for {:?}", id);
 								s.disconnect(io, DisconnectReason::UnexpectedIdentity);
@@ -970,6 +1102,55 @@ impl Host {
 		self.nodes.write().update(node_changes, &*self.reserved_nodes.read());
 	}
 
+	fn update_dns_nodes(&self) {
+		self.add_dns_nodes();
+
+		// The trees are still being walked, try again on the next tick.
+		if self.dns_polling.compare_and_swap(false, true, AtomicOrdering::SeqCst) {
+			return;
+		}
+
+		// Lookups may time out one after another, keep them off the IO thread.
+		let dns_discovery = self.dns_discovery.clone();
+		let dns_polling = self.dns_polling.clone();
+		let sender = self.dns_nodes_sender.lock().clone();
+		let poll = thread::Builder::new()
+			.name("DNS discovery".into())
+			.spawn(move || {
+				let updates = dns_discovery.lock().poll();
+				dns_polling.store(false, AtomicOrdering::SeqCst);
+				for entry in updates.nodes {
+					if sender.send(entry).is_err() {
+						return;
+					}
+				}
+			});
+		if let Err(e) = poll {
+			debug!(target: "network", "Error spawning the DNS discovery thread: {}", e);
+			self.dns_polling.store(false, AtomicOrdering::SeqCst);
+		}
+	}
+
+	/// Add the nodes resolved by the last DNS discovery poll to the node table.
+	fn add_dns_nodes(&self) {
+		let entries: Vec<_> = self.dns_nodes.lock().try_iter().collect();
+		if entries.is_empty() {
+			return;
+		}
+		trace!(target: "network", "Adding {} nodes from DNS node trees", entries.len());
+
+		let mut nodes = self.nodes.write();
+		let mut discovery = self.discovery.lock();
+		for entry in entries {
+			if !nodes.contains(&entry.id) {
+				nodes.add_node(Node::new(entry.id, entry.endpoint.clone()));
+			}
+			if let Some(ref mut discovery) = *discovery {
+				discovery.add_node(entry);
+			}
+		}
+	}
+
 	pub fn with_context<F>(&self, protocol: ProtocolId, io: &IoContext<NetworkIoMessage>, action: F) where F: FnOnce(&NetworkContextTrait) {
 		let reserved = { self.reserved_nodes.read() };
 
@@ -1056,6 +1237,15 @@ impl IoHandler<NetworkIoMessage> for Host {
 				self.nodes.write().clear_useless();
 				self.nodes.write().save();
 			},
+			DNS_DISCOVERY => {
+				// Only walk the node trees while we are short of peers
+				if self.has_enough_peers() {
+					return;
+				}
+				self.update_dns_nodes();
+			},
+			EGRESS => self.flush_egress(io),
+			NAT_RENEWAL => self.renew_nat_lease(),
 			_ => match self.timers.read().get(&token).cloned() {
 				Some(timer) => match self.handlers.read().get(&timer.protocol).cloned() {
 					None => { warn!(target: "network", "No handler found for protocol: {:?}", timer.protocol) },
@@ -1130,6 +1320,21 @@ impl IoHandler<NetworkIoMessage> for Host {
 				trace!(target: "network", "Disabling peer {}", peer);
 				self.kill_connection(*peer, io, false);
 			},
+			NetworkIoMessage::ReportPeer(ref peer, behaviour) => {
+				let session = { self.sessions.read().get(*peer).cloned() };
+				if let Some(session) = session {
+					let id = session.lock().id().cloned();
+					let banned = match id {
+						Some(ref id) if !self.reserved_nodes.read().contains(id) => self.nodes.write().note_behaviour(id, behaviour),
+						_ => false,
+					};
+					if banned {
+						session.lock().disconnect(io, DisconnectReason::UselessPeer);
+						trace!(target: "network", "Disconnecting banned peer {}", peer);
+						self.kill_connection(*peer, io, false);
+					}
+				}
+			},
 			NetworkIoMessage::InitPublicInterface =>
 				self.init_public_interface(io).unwrap_or_else(|e| warn!("Error initializing public interface: {:?}", e)),
 			_ => {}	// ignore others.
@@ -1265,6 +1470,32 @@ fn host_client_url() {
 	let mut config = NetworkConfiguration::new_local();
 	let key = "6f7b0d801bc7b5ce7bbd930b84fd0369b3eb25d09be58d64ba811091046f3aa2".parse().unwrap();
 	config.use_secret = Some(key);
-	let host: Host = Host::new(config, None).unwrap();
+	let host: Host = Host::new(config, None, Arc::new(::dns_discovery::SystemResolver::new(Vec::new()))).unwrap();
 	assert!(host.local_url().starts_with("enode://101b3ef5a4ea7a1c7928e24c4c75fd053c235d7b80c22ae5c03d145d0ac7396e2a4ffff9adee3133a7b05044a5cee08115fd65145e5165d646bde371010d803c@"));
 }
+
+#[test]
+fn dns_discovery_does_not_block_io_thread() {
+	use std::time::Instant;
+
+	struct SlowResolver;
+
+	impl Resolver for SlowResolver {
+		fn txt_records(&self, _name: &str) -> Result<Vec<String>, Error> {
+			thread::sleep(Duration::from_secs(1));
+			Err(ErrorKind::AddressResolve(None).into())
+		}
+	}
+
+	let mut host = Host::new(NetworkConfiguration::new_local(), None, Arc::new(SlowResolver)).unwrap();
+	host.add_node("enrtree://AKA3AM6LPBYEUDMVNU3BSVQJ5AD45Y7YPOHJLEF6W26QOE4VTUDPE@nodes.example.org");
+	assert!(!host.dns_discovery.lock().is_empty());
+
+	let start = Instant::now();
+	host.update_dns_nodes();
+	// a poll is still running, so the next tick doesn't start another one.
+	host.update_dns_nodes();
+	assert!(start.elapsed() < Duration::from_millis(500));
+	assert!(host.dns_polling.load(AtomicOrdering::SeqCst));
+}
+
@@ -22,6 +22,8 @@ use igd::{PortMappingProtocol, search_gateway_from_timeout};
 use std::time::Duration;
 use node_table::NodeEndpoint;
 use ipnetwork::IpNetwork;
+use nat_pmp::{self, Lease, Protocol};
+use network::NatType;
 
 /// Socket address extension for rustc beta. To be replaces with now unstable API
 pub trait SocketAddrExt {
@@ -306,7 +308,54 @@ pub fn select_public_address(port: u16) -> SocketAddr {
 	SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), port))
 }
 
-pub fn map_external_address(local: &NodeEndpoint) -> Option<NodeEndpoint> {
+/// Port mappings obtained from the gateway.
+pub struct NatMapping {
+	/// Endpoint the node is reachable at from the outside.
+	pub endpoint: NodeEndpoint,
+	/// Lease to be renewed, for NAT-PMP and PCP mappings.
+	pub lease: Option<Lease>,
+}
+
+/// Map the local endpoint on the gateway, trying each mechanism allowed by `nat_type` in turn.
+pub fn map_external_address(local: &NodeEndpoint, nat_type: NatType) -> Option<NatMapping> {
+	let methods = match nat_type {
+		NatType::Any => vec![NatType::UPnP, NatType::Pcp, NatType::NatPmp],
+		method => vec![method],
+	};
+	methods.into_iter().filter_map(|method| match method {
+		NatType::UPnP => map_upnp_address(local).map(|endpoint| NatMapping { endpoint, lease: None }),
+		NatType::Pcp => map_leased_address(Protocol::Pcp, local),
+		NatType::NatPmp => map_leased_address(Protocol::NatPmp, local),
+		NatType::Any => None,
+	}).next()
+}
+
+fn map_leased_address(protocol: Protocol, local: &NodeEndpoint) -> Option<NatMapping> {
+	let local_ip = match local.address {
+		SocketAddr::V4(ref a) if !a.ip().is_unspecified() => *a.ip(),
+		SocketAddr::V4(_) => match select_public_address(0) {
+			SocketAddr::V4(a) => *a.ip(),
+			SocketAddr::V6(_) => return None,
+		},
+		SocketAddr::V6(_) => return None,
+	};
+	let gateway = match nat_pmp::default_gateway(&local_ip) {
+		Some(gateway) => gateway,
+		None => {
+			debug!("Unable to find the default gateway for {:?} port mapping", protocol);
+			return None;
+		},
+	};
+	match Lease::obtain(protocol, gateway, local) {
+		Ok(lease) => Some(NatMapping { endpoint: lease.external.clone(), lease: Some(lease) }),
+		Err(ref err) => {
+			debug!("{:?} port mapping error: {}", protocol, err);
+			None
+		},
+	}
+}
+
+fn map_upnp_address(local: &NodeEndpoint) -> Option<NodeEndpoint> {
 	if let SocketAddr::V4(ref local_addr) = local.address {
 		match search_gateway_from_timeout(*local_addr.ip(), Duration::new(5, 0)) {
 			Err(ref err) => debug!("Gateway search error: {}", err),
@@ -349,7 +398,7 @@ fn can_select_public_address() {
 #[test]
 fn can_map_external_address_or_fail() {
 	let pub_address = select_public_address(40478);
-	let _ = map_external_address(&NodeEndpoint { address: pub_address, udp_port: 40478 });
+	let _ = map_external_address(&NodeEndpoint { address: pub_address, udp_port: 40478 }, NatType::Any);
 }
 
 #[test]
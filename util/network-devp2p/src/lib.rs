@@ -85,6 +85,8 @@ extern crate keccak_hash as hash;
 extern crate serde;
 extern crate serde_json;
 extern crate parity_snappy as snappy;
+extern crate base32;
+extern crate base64;
 
 #[macro_use]
 extern crate error_chain;
@@ -99,18 +101,22 @@ extern crate tempdir;
 extern crate assert_matches;
 
 mod host;
+mod bandwidth;
 mod connection;
 mod handshake;
 mod session;
 mod discovery;
+mod dns_discovery;
 mod service;
 mod node_table;
 mod ip_utils;
+mod nat_pmp;
 
 pub use service::NetworkService;
 pub use host::NetworkContext;
 
 pub use io::TimerToken;
 pub use node_table::{validate_node_url, NodeId};
+pub use dns_discovery::{Resolver, SystemResolver};
 
 const PROTOCOL_VERSION: u32 = 5;
@@ -0,0 +1,468 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! NAT-PMP (RFC 6886) and PCP (RFC 6887) port mapping.
+//!
+//! Unlike UPnP mappings, these are leased for a limited time and have to be
+//! renewed periodically.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::{Duration, Instant};
+use rand;
+use node_table::NodeEndpoint;
+
+/// Port the gateway listens on for both NAT-PMP and PCP requests.
+const SERVER_PORT: u16 = 5351;
+const NATPMP_VERSION: u8 = 0;
+const NATPMP_OPCODE_ADDRESS: u8 = 0;
+const PCP_VERSION: u8 = 2;
+const PCP_OPCODE_MAP: u8 = 1;
+const RESPONSE_FLAG: u8 = 0x80;
+const RESULT_SUCCESS: u16 = 0;
+const PCP_REQUEST_LEN: usize = 60;
+const PCP_RESPONSE_LEN: usize = 60;
+/// Initial request timeout, doubled on every retransmission.
+const INITIAL_TIMEOUT: Duration = Duration::from_millis(250);
+const MAX_ATTEMPTS: u32 = 5;
+/// Requested lifetime of a mapping, in seconds.
+const LEASE_LIFETIME: u32 = 7200;
+
+/// Port mapping protocol spoken to the gateway.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+	/// NAT Port Mapping Protocol
+	NatPmp,
+	/// Port Control Protocol
+	Pcp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transport {
+	Tcp,
+	Udp,
+}
+
+impl Transport {
+	fn natpmp_opcode(&self) -> u8 {
+		match *self {
+			Transport::Udp => 1,
+			Transport::Tcp => 2,
+		}
+	}
+
+	fn protocol_number(&self) -> u8 {
+		match *self {
+			Transport::Tcp => 6,
+			Transport::Udp => 17,
+		}
+	}
+}
+
+/// Port mapping granted by the gateway.
+#[derive(Debug, PartialEq)]
+struct Mapping {
+	/// External address, only reported by PCP.
+	external_ip: Option<Ipv4Addr>,
+	external_port: u16,
+	/// Lifetime in seconds.
+	lifetime: u32,
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u16(data: &[u8]) -> u16 {
+	(u16::from(data[0]) << 8) | u16::from(data[1])
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+	(u32::from(read_u16(data)) << 16) | u32::from(read_u16(&data[2..]))
+}
+
+fn write_u16(data: &mut [u8], value: u16) {
+	data[0] = (value >> 8) as u8;
+	data[1] = value as u8;
+}
+
+fn write_u32(data: &mut [u8], value: u32) {
+	write_u16(data, (value >> 16) as u16);
+	write_u16(&mut data[2..], value as u16);
+}
+
+/// Write an IPv4 address in its IPv4-mapped IPv6 form.
+fn write_mapped_ip(data: &mut [u8], ip: &Ipv4Addr) {
+	data[..10].copy_from_slice(&[0u8; 10]);
+	data[10] = 0xff;
+	data[11] = 0xff;
+	data[12..16].copy_from_slice(&ip.octets());
+}
+
+fn read_mapped_ip(data: &[u8]) -> Option<Ipv4Addr> {
+	if data[..10].iter().all(|b| *b == 0) && data[10] == 0xff && data[11] == 0xff {
+		Some(Ipv4Addr::new(data[12], data[13], data[14], data[15]))
+	} else {
+		None
+	}
+}
+
+fn natpmp_address_request() -> [u8; 2] {
+	[NATPMP_VERSION, NATPMP_OPCODE_ADDRESS]
+}
+
+fn natpmp_parse_address(data: &[u8]) -> io::Result<Ipv4Addr> {
+	if data.len() < 12 || data[0] != NATPMP_VERSION || data[1] != RESPONSE_FLAG | NATPMP_OPCODE_ADDRESS {
+		return Err(invalid_data("Unexpected NAT-PMP response"));
+	}
+	match read_u16(&data[2..]) {
+		RESULT_SUCCESS => Ok(Ipv4Addr::new(data[8], data[9], data[10], data[11])),
+		code => Err(io::Error::new(io::ErrorKind::Other, format!("NAT-PMP address request failed with code {}", code))),
+	}
+}
+
+fn natpmp_map_request(transport: Transport, internal_port: u16, external_port: u16, lifetime: u32) -> [u8; 12] {
+	let mut request = [0u8; 12];
+	request[0] = NATPMP_VERSION;
+	request[1] = transport.natpmp_opcode();
+	write_u16(&mut request[4..], internal_port);
+	write_u16(&mut request[6..], external_port);
+	write_u32(&mut request[8..], lifetime);
+	request
+}
+
+fn natpmp_parse_map(transport: Transport, internal_port: u16, data: &[u8]) -> io::Result<Mapping> {
+	if data.len() < 16 || data[0] != NATPMP_VERSION || data[1] != RESPONSE_FLAG | transport.natpmp_opcode() {
+		return Err(invalid_data("Unexpected NAT-PMP response"));
+	}
+	match read_u16(&data[2..]) {
+		RESULT_SUCCESS => {},
+		code => return Err(io::Error::new(io::ErrorKind::Other, format!("NAT-PMP mapping failed with code {}", code))),
+	}
+	if read_u16(&data[8..]) != internal_port {
+		return Err(invalid_data("NAT-PMP response is for another port"));
+	}
+	Ok(Mapping {
+		external_ip: None,
+		external_port: read_u16(&data[10..]),
+		lifetime: read_u32(&data[12..]),
+	})
+}
+
+fn pcp_map_request(client: &Ipv4Addr, nonce: &[u8; 12], transport: Transport, internal_port: u16, external: &SocketAddrV4, lifetime: u32) -> [u8; PCP_REQUEST_LEN] {
+	let mut request = [0u8; PCP_REQUEST_LEN];
+	request[0] = PCP_VERSION;
+	request[1] = PCP_OPCODE_MAP;
+	write_u32(&mut request[4..], lifetime);
+	write_mapped_ip(&mut request[8..24], client);
+	request[24..36].copy_from_slice(nonce);
+	request[36] = transport.protocol_number();
+	write_u16(&mut request[40..], internal_port);
+	write_u16(&mut request[42..], external.port());
+	write_mapped_ip(&mut request[44..60], external.ip());
+	request
+}
+
+fn pcp_parse_map(nonce: &[u8; 12], transport: Transport, internal_port: u16, data: &[u8]) -> io::Result<Mapping> {
+	if data.len() >= 4 && data[0] == NATPMP_VERSION && data[1] & RESPONSE_FLAG != 0 {
+		return Err(io::Error::new(io::ErrorKind::Other, "Gateway only supports NAT-PMP"));
+	}
+	if data.len() < PCP_RESPONSE_LEN || data[0] != PCP_VERSION || data[1] != RESPONSE_FLAG | PCP_OPCODE_MAP {
+		return Err(invalid_data("Unexpected PCP response"));
+	}
+	match data[3] {
+		0 => {},
+		code => return Err(io::Error::new(io::ErrorKind::Other, format!("PCP mapping failed with code {}", code))),
+	}
+	if &data[24..36] != &nonce[..] || data[36] != transport.protocol_number() || read_u16(&data[40..]) != internal_port {
+		return Err(invalid_data("PCP response does not match the request"));
+	}
+	Ok(Mapping {
+		external_ip: Some(read_mapped_ip(&data[44..60]).ok_or_else(|| invalid_data("PCP mapped to a non-IPv4 address"))?),
+		external_port: read_u16(&data[42..]),
+		lifetime: read_u32(&data[4..]),
+	})
+}
+
+/// Send a request and wait for the response, retransmitting with exponential back-off.
+fn transact(socket: &UdpSocket, request: &[u8]) -> io::Result<Vec<u8>> {
+	let mut timeout = INITIAL_TIMEOUT;
+	let mut buf = [0u8; 1100];
+	for _ in 0..MAX_ATTEMPTS {
+		socket.send(request)?;
+		socket.set_read_timeout(Some(timeout))?;
+		match socket.recv(&mut buf) {
+			Ok(len) => return Ok(buf[..len].to_vec()),
+			Err(ref e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {
+				timeout = timeout * 2;
+			},
+			Err(e) => return Err(e),
+		}
+	}
+	Err(io::Error::new(io::ErrorKind::TimedOut, "No response from gateway"))
+}
+
+/// Parse the default gateway out of the contents of `/proc/net/route`.
+fn parse_route_table(table: &str) -> Option<Ipv4Addr> {
+	const RTF_GATEWAY: u32 = 0x2;
+	table.lines().skip(1).filter_map(|line| {
+		let fields: Vec<_> = line.split_whitespace().collect();
+		if fields.len() < 4 || fields[1] != "00000000" {
+			return None;
+		}
+		let gateway = u32::from_str_radix(fields[2], 16).ok()?;
+		let flags = u32::from_str_radix(fields[3], 16).ok()?;
+		if flags & RTF_GATEWAY == 0 {
+			return None;
+		}
+		// The address is stored in host (little endian) byte order
+		Some(Ipv4Addr::new(gateway as u8, (gateway >> 8) as u8, (gateway >> 16) as u8, (gateway >> 24) as u8))
+	}).next()
+}
+
+/// Find the default IPv4 gateway. Falls back to guessing the `.1` address
+/// of the local network when the routing table is not available.
+pub fn default_gateway(local: &Ipv4Addr) -> Option<Ipv4Addr> {
+	if cfg!(target_os = "linux") {
+		if let Some(gateway) = ::std::fs::read_to_string("/proc/net/route").ok().and_then(|t| parse_route_table(&t)) {
+			return Some(gateway);
+		}
+	}
+	if local.is_private() {
+		let octets = local.octets();
+		Some(Ipv4Addr::new(octets[0], octets[1], octets[2], 1))
+	} else {
+		None
+	}
+}
+
+/// TCP and UDP port mappings of the node, leased from the gateway.
+pub struct Lease {
+	protocol: Protocol,
+	socket: UdpSocket,
+	client: Ipv4Addr,
+	tcp_port: u16,
+	udp_port: u16,
+	tcp_nonce: [u8; 12],
+	udp_nonce: [u8; 12],
+	/// Endpoint the node is reachable at from the outside.
+	pub external: NodeEndpoint,
+	renew_at: Instant,
+}
+
+impl Lease {
+	/// Map the TCP and UDP ports of the local endpoint on the gateway.
+	pub fn obtain(protocol: Protocol, gateway: Ipv4Addr, local: &NodeEndpoint) -> io::Result<Lease> {
+		Lease::obtain_from(protocol, SocketAddr::V4(SocketAddrV4::new(gateway, SERVER_PORT)), local)
+	}
+
+	fn obtain_from(protocol: Protocol, gateway: SocketAddr, local: &NodeEndpoint) -> io::Result<Lease> {
+		let socket = UdpSocket::bind(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 0)))?;
+		socket.connect(gateway)?;
+		let client = match socket.local_addr()?.ip() {
+			IpAddr::V4(ip) => ip,
+			IpAddr::V6(_) => return Err(io::Error::new(io::ErrorKind::Other, "IPv6 gateways are not supported")),
+		};
+		let mut lease = Lease {
+			protocol,
+			socket,
+			client,
+			tcp_port: local.address.port(),
+			udp_port: local.udp_port,
+			tcp_nonce: rand::random(),
+			udp_nonce: rand::random(),
+			external: local.clone(),
+			renew_at: Instant::now(),
+		};
+		let external_ip = match protocol {
+			Protocol::NatPmp => Some(natpmp_parse_address(&transact(&lease.socket, &natpmp_address_request())?)?),
+			Protocol::Pcp => None,
+		};
+		let (tcp_port, udp_port) = (lease.tcp_port, lease.udp_port);
+		lease.map(external_ip, tcp_port, udp_port, LEASE_LIFETIME)?;
+		Ok(lease)
+	}
+
+	fn map(&mut self, external_ip: Option<Ipv4Addr>, tcp_port: u16, udp_port: u16, lifetime: u32) -> io::Result<()> {
+		let tcp = self.request(Transport::Tcp, tcp_port, external_ip, lifetime)?;
+		let udp = self.request(Transport::Udp, udp_port, external_ip, lifetime)?;
+		let external_ip = tcp.external_ip.or(external_ip)
+			.expect("NAT-PMP queries the external address before mapping and PCP reports it in the response; qed");
+		self.external = NodeEndpoint {
+			address: SocketAddr::V4(SocketAddrV4::new(external_ip, tcp.external_port)),
+			udp_port: udp.external_port,
+		};
+		// Renew halfway through the shorter of the two lifetimes, as recommended by both RFCs.
+		let lifetime = ::std::cmp::min(tcp.lifetime, udp.lifetime);
+		self.renew_at = Instant::now() + Duration::from_secs(u64::from(lifetime / 2));
+		Ok(())
+	}
+
+	fn request(&self, transport: Transport, external_port: u16, external_ip: Option<Ipv4Addr>, lifetime: u32) -> io::Result<Mapping> {
+		let (internal_port, nonce) = match transport {
+			Transport::Tcp => (self.tcp_port, &self.tcp_nonce),
+			Transport::Udp => (self.udp_port, &self.udp_nonce),
+		};
+		match self.protocol {
+			Protocol::NatPmp => {
+				let request = natpmp_map_request(transport, internal_port, external_port, lifetime);
+				natpmp_parse_map(transport, internal_port, &transact(&self.socket, &request)?)
+			},
+			Protocol::Pcp => {
+				let suggested = SocketAddrV4::new(external_ip.unwrap_or_else(|| Ipv4Addr::new(0, 0, 0, 0)), external_port);
+				let request = pcp_map_request(&self.client, nonce, transport, internal_port, &suggested, lifetime);
+				pcp_parse_map(nonce, transport, internal_port, &transact(&self.socket, &request)?)
+			},
+		}
+	}
+
+	/// Protocol used to obtain the lease.
+	pub fn protocol(&self) -> Protocol {
+		self.protocol
+	}
+
+	/// Check if the lease is due for renewal.
+	pub fn needs_renewal(&self) -> bool {
+		Instant::now() >= self.renew_at
+	}
+
+	/// Renew the mappings, asking the gateway to keep the current external ports.
+	pub fn renew(&mut self) -> io::Result<()> {
+		let (external_ip, tcp_port, udp_port) = match self.external.address {
+			SocketAddr::V4(ref a) => (*a.ip(), a.port(), self.external.udp_port),
+			SocketAddr::V6(_) => unreachable!("only IPv4 mappings are created; qed"),
+		};
+		let external_ip = match self.protocol {
+			Protocol::NatPmp => natpmp_parse_address(&transact(&self.socket, &natpmp_address_request())?)?,
+			Protocol::Pcp => external_ip,
+		};
+		self.map(Some(external_ip), tcp_port, udp_port, LEASE_LIFETIME)
+	}
+
+	/// Delete the mappings from the gateway.
+	pub fn release(&self) {
+		let (external_ip, tcp_port, udp_port) = match self.external.address {
+			SocketAddr::V4(ref a) => (*a.ip(), a.port(), self.external.udp_port),
+			SocketAddr::V6(_) => return,
+		};
+		// NAT-PMP deletion requests must have the external port set to zero
+		let (tcp_port, udp_port) = match self.protocol {
+			Protocol::NatPmp => (0, 0),
+			Protocol::Pcp => (tcp_port, udp_port),
+		};
+		for &(transport, port) in &[(Transport::Tcp, tcp_port), (Transport::Udp, udp_port)] {
+			if let Err(e) = self.request(transport, port, Some(external_ip), 0) {
+				debug!(target: "network", "Error removing {:?} port mapping: {}", self.protocol, e);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::thread;
+
+	const NONCE: [u8; 12] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+
+	#[test]
+	fn natpmp_messages() {
+		let request = natpmp_map_request(Transport::Tcp, 30303, 30303, 7200);
+		assert_eq!(request, [0, 2, 0, 0, 0x76, 0x5f, 0x76, 0x5f, 0, 0, 0x1c, 0x20]);
+
+		let response = [0, 130, 0, 0, 0, 0, 0, 9, 0x76, 0x5f, 0x9c, 0x40, 0, 0, 0x0e, 0x10];
+		assert_eq!(natpmp_parse_map(Transport::Tcp, 30303, &response).unwrap(), Mapping {
+			external_ip: None,
+			external_port: 40000,
+			lifetime: 3600,
+		});
+		assert!(natpmp_parse_map(Transport::Udp, 30303, &response).is_err());
+		assert!(natpmp_parse_map(Transport::Tcp, 30304, &response).is_err());
+
+		let refused = [0, 130, 0, 3, 0, 0, 0, 9, 0x76, 0x5f, 0, 0, 0, 0, 0, 0];
+		assert!(natpmp_parse_map(Transport::Tcp, 30303, &refused).is_err());
+
+		let address = [0, 128, 0, 0, 0, 0, 0, 9, 203, 0, 113, 7];
+		assert_eq!(natpmp_parse_address(&address).unwrap(), Ipv4Addr::new(203, 0, 113, 7));
+	}
+
+	#[test]
+	fn pcp_messages() {
+		let client = Ipv4Addr::new(192, 168, 1, 20);
+		let suggested = SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 30303);
+		let request = pcp_map_request(&client, &NONCE, Transport::Udp, 30301, &suggested, 7200);
+		assert_eq!(&request[0..8], &[2, 1, 0, 0, 0, 0, 0x1c, 0x20]);
+		assert_eq!(&request[8..24], &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 192, 168, 1, 20]);
+		assert_eq!(&request[24..36], &NONCE);
+		assert_eq!(&request[36..44], &[17, 0, 0, 0, 0x76, 0x5d, 0x76, 0x5f]);
+		assert_eq!(&request[44..60], &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0, 0, 0]);
+
+		let mut response = request;
+		response[1] = 0x81;
+		response[3] = 0;
+		write_u32(&mut response[4..], 3600);
+		write_u16(&mut response[42..], 40000);
+		write_mapped_ip(&mut response[44..60], &Ipv4Addr::new(203, 0, 113, 7));
+		assert_eq!(pcp_parse_map(&NONCE, Transport::Udp, 30301, &response).unwrap(), Mapping {
+			external_ip: Some(Ipv4Addr::new(203, 0, 113, 7)),
+			external_port: 40000,
+			lifetime: 3600,
+		});
+		assert!(pcp_parse_map(&[0u8; 12], Transport::Udp, 30301, &response).is_err());
+
+		response[3] = 2;
+		assert!(pcp_parse_map(&NONCE, Transport::Udp, 30301, &response).is_err());
+
+		let unsupported = [0, 0x81, 0, 1, 0, 0, 0, 9];
+		assert!(pcp_parse_map(&NONCE, Transport::Udp, 30301, &unsupported).is_err());
+	}
+
+	#[test]
+	fn route_table_gateway() {
+		let table = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+			eth0\t0001A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\t0\t0\t0\n\
+			eth0\t00000000\t0101A8C0\t0003\t0\t0\t0\t00000000\t0\t0\t0\n";
+		assert_eq!(parse_route_table(table), Some(Ipv4Addr::new(192, 168, 1, 1)));
+		assert_eq!(parse_route_table("Iface\tDestination\tGateway\n"), None);
+	}
+
+	#[test]
+	fn obtains_pcp_lease() {
+		let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+		let gateway = server.local_addr().unwrap();
+		let handle = thread::spawn(move || {
+			let mut buf = [0u8; 1100];
+			for _ in 0..2 {
+				let (len, from) = server.recv_from(&mut buf).unwrap();
+				assert_eq!(len, PCP_REQUEST_LEN);
+				let mut response = [0u8; PCP_RESPONSE_LEN];
+				response.copy_from_slice(&buf[..PCP_RESPONSE_LEN]);
+				response[1] |= RESPONSE_FLAG;
+				let port = read_u16(&buf[40..]) + 10000;
+				write_u16(&mut response[42..], port);
+				write_mapped_ip(&mut response[44..60], &Ipv4Addr::new(203, 0, 113, 7));
+				server.send_to(&response, from).unwrap();
+			}
+		});
+
+		let local = NodeEndpoint { address: "127.0.0.1:30303".parse().unwrap(), udp_port: 30301 };
+		let lease = Lease::obtain_from(Protocol::Pcp, gateway, &local).unwrap();
+		handle.join().unwrap();
+		assert_eq!(lease.external.address, "203.0.113.7:40303".parse().unwrap());
+		assert_eq!(lease.external.udp_port, 40301);
+		assert!(!lease.needs_renewal());
+	}
+}
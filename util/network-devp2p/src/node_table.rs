@@ -15,9 +15,10 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use discovery::{TableUpdates, NodeEntry};
+use dns_discovery::{validate_tree_url, ENRTREE_PREFIX};
 use ethereum_types::H512;
 use ip_utils::*;
-use network::{Error, ErrorKind, AllowIP, IpFilter};
+use network::{Error, ErrorKind, AllowIP, IpFilter, NodeReputation, PeerBehaviour};
 use rlp::{Rlp, RlpStream, DecoderError};
 use serde_json;
 use std::collections::{HashMap, HashSet};
@@ -173,12 +174,99 @@ impl NodeContact {
 	}
 }
 
+// Reputation score is halved every `REPUTATION_HALF_LIFE`.
+const REPUTATION_HALF_LIFE: Duration = Duration::from_secs(60 * 60);
+const MAX_REPUTATION: i32 = 100;
+const MIN_REPUTATION: i32 = -1000;
+// Nodes are banned once their reputation drops to this value.
+const BAN_THRESHOLD: i32 = -100;
+const BAN_DURATION: Duration = Duration::from_secs(30 * 60);
+
+/// Reputation change for the given peer behaviour.
+fn reputation_change(behaviour: PeerBehaviour) -> i32 {
+	match behaviour {
+		PeerBehaviour::GoodDelivery => 1,
+		PeerBehaviour::UselessResponse => -10,
+		PeerBehaviour::Timeout => -20,
+		PeerBehaviour::InvalidBlock => -100,
+	}
+}
+
+/// Reputation of a node, built from the behaviour reported by protocol handlers.
+/// The score decays towards zero over time, so that both good and bad behaviour are eventually forgotten.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reputation {
+	score: i32,
+	/// Time the score was last decayed.
+	updated: SystemTime,
+	banned_until: Option<SystemTime>,
+}
+
+impl Default for Reputation {
+	fn default() -> Self {
+		Reputation {
+			score: 0,
+			updated: SystemTime::now(),
+			banned_until: None,
+		}
+	}
+}
+
+impl Reputation {
+	/// Current score, with decay applied.
+	pub fn score(&self) -> i32 {
+		self.decayed(SystemTime::now()).score
+	}
+
+	/// Returns `true` if the node is currently banned.
+	pub fn is_banned(&self) -> bool {
+		self.banned_until.map_or(false, |t| t > SystemTime::now())
+	}
+
+	/// Apply the reputation change caused by `behaviour`. Returns `true` if this resulted in a new ban.
+	fn note(&mut self, behaviour: PeerBehaviour) -> bool {
+		let now = SystemTime::now();
+		let was_banned = self.is_banned();
+		*self = self.decayed(now);
+		let score = self.score + reputation_change(behaviour);
+		self.score = match behaviour {
+			// an invalid block bans the node however well it behaved before.
+			PeerBehaviour::InvalidBlock => score.min(BAN_THRESHOLD),
+			_ => score.min(MAX_REPUTATION),
+		}.max(MIN_REPUTATION);
+		if !was_banned && self.score <= BAN_THRESHOLD {
+			self.banned_until = Some(now + BAN_DURATION);
+			return true;
+		}
+		false
+	}
+
+	/// Halve the score for every full half-life elapsed since the last update.
+	fn decayed(&self, now: SystemTime) -> Reputation {
+		let elapsed = now.duration_since(self.updated).unwrap_or_default();
+		let periods = elapsed.as_secs() / REPUTATION_HALF_LIFE.as_secs();
+		if periods == 0 {
+			return *self;
+		}
+		Reputation {
+			score: if periods >= 32 { 0 } else { self.score / (1 << periods) },
+			updated: self.updated + REPUTATION_HALF_LIFE * periods as u32,
+			banned_until: self.banned_until.filter(|t| *t > now),
+		}
+	}
+
+	fn is_neutral(&self) -> bool {
+		self.score() == 0 && !self.is_banned()
+	}
+}
+
 #[derive(Debug)]
 pub struct Node {
 	pub id: NodeId,
 	pub endpoint: NodeEndpoint,
 	pub peer_type: PeerType,
 	pub last_contact: Option<NodeContact>,
+	pub reputation: Reputation,
 }
 
 impl Node {
@@ -188,6 +276,7 @@ impl Node {
 			endpoint,
 			peer_type: PeerType::Optional,
 			last_contact: None,
+			reputation: Reputation::default(),
 		}
 	}
 }
@@ -218,6 +307,7 @@ impl FromStr for Node {
 			endpoint,
 			peer_type: PeerType::Optional,
 			last_contact: None,
+			reputation: Reputation::default(),
 		})
 	}
 }
@@ -256,18 +346,22 @@ impl NodeTable {
 
 	/// Add a node to table
 	pub fn add_node(&mut self, mut node: Node) {
-		// preserve node last_contact
-		node.last_contact = self.nodes.get(&node.id).and_then(|n| n.last_contact);
+		// preserve node last_contact and reputation
+		if let Some(existing) = self.nodes.get(&node.id) {
+			node.last_contact = existing.last_contact;
+			node.reputation = existing.reputation;
+		}
 		self.nodes.insert(node.id, node);
 	}
 
 	/// Returns a list of ordered nodes according to their most recent contact
-	/// and filtering useless nodes. The algorithm for creating the sorted nodes
-	/// is:
+	/// and reputation, filtering useless and banned nodes. The algorithm for
+	/// creating the sorted nodes is:
 	/// - Contacts that aren't recent (older than 1 week) are discarded
 	/// - (1) Nodes with a successful contact are ordered (most recent success first)
 	/// - (2) Nodes with unknown contact (older than 1 week or new nodes) are randomly shuffled
 	/// - (3) Nodes with a failed contact are ordered (oldest failure first)
+	/// - Within each group, nodes with a higher reputation come first
 	/// - The final result is the concatenation of (1), (2) and (3)
 	fn ordered_entries(&self) -> Vec<&Node> {
 		let mut success = Vec::new();
@@ -275,7 +369,7 @@ impl NodeTable {
 		let mut unknown = Vec::new();
 
 		let nodes = self.nodes.values()
-			.filter(|n| !self.useless_nodes.contains(&n.id));
+			.filter(|n| !self.useless_nodes.contains(&n.id) && !n.reputation.is_banned());
 
 		for node in nodes {
 			// discard contact points older that aren't recent
@@ -293,20 +387,24 @@ impl NodeTable {
 		}
 
 		success.sort_by(|a, b| {
-			let a = a.last_contact.expect("vector only contains values with defined last_contact; qed");
-			let b = b.last_contact.expect("vector only contains values with defined last_contact; qed");
+			let a_contact = a.last_contact.expect("vector only contains values with defined last_contact; qed");
+			let b_contact = b.last_contact.expect("vector only contains values with defined last_contact; qed");
 			// inverse ordering, most recent successes come first
-			b.time().cmp(&a.time())
+			b.reputation.score().cmp(&a.reputation.score())
+				.then_with(|| b_contact.time().cmp(&a_contact.time()))
 		});
 
 		failures.sort_by(|a, b| {
-			let a = a.last_contact.expect("vector only contains values with defined last_contact; qed");
-			let b = b.last_contact.expect("vector only contains values with defined last_contact; qed");
+			let a_contact = a.last_contact.expect("vector only contains values with defined last_contact; qed");
+			let b_contact = b.last_contact.expect("vector only contains values with defined last_contact; qed");
 			// normal ordering, most distant failures come first
-			a.time().cmp(&b.time())
+			b.reputation.score().cmp(&a.reputation.score())
+				.then_with(|| a_contact.time().cmp(&b_contact.time()))
 		});
 
 		rand::thread_rng().shuffle(&mut unknown);
+		// stable sort, keeps nodes with the same reputation shuffled
+		unknown.sort_by(|a, b| b.reputation.score().cmp(&a.reputation.score()));
 
 		success.append(&mut unknown);
 		success.append(&mut failures);
@@ -368,6 +466,41 @@ impl NodeTable {
 		}
 	}
 
+	/// Update the reputation of a node according to its behaviour.
+	/// Returns `true` if the node got banned as a result.
+	pub fn note_behaviour(&mut self, id: &NodeId, behaviour: PeerBehaviour) -> bool {
+		match self.nodes.get_mut(id) {
+			Some(node) => {
+				let banned = node.reputation.note(behaviour);
+				if banned {
+					debug!(target: "network", "Banning node {} until {:?}", id, node.reputation.banned_until);
+				}
+				banned
+			},
+			None => false,
+		}
+	}
+
+	/// Check if a node is temporarily banned due to its bad reputation.
+	pub fn is_banned(&self, id: &NodeId) -> bool {
+		self.nodes.get(id).map_or(false, |n| n.reputation.is_banned())
+	}
+
+	/// Reputations of all nodes which are not neutral, best first.
+	pub fn reputations(&self) -> Vec<NodeReputation> {
+		let mut reputations: Vec<_> = self.nodes.values()
+			.filter(|n| !n.reputation.is_neutral())
+			.map(|n| NodeReputation {
+				id: n.id,
+				url: format!("{}", n),
+				score: n.reputation.score(),
+				banned_until: n.reputation.banned_until.filter(|_| n.reputation.is_banned()),
+			})
+			.collect();
+		reputations.sort_by(|a, b| b.score.cmp(&a.score));
+		reputations
+	}
+
 	/// Mark as useless, no further attempts to connect until next call to `clear_useless`.
 	pub fn mark_as_useless(&mut self, id: &NodeId) {
 		self.useless_nodes.insert(id.clone());
@@ -390,8 +523,12 @@ impl NodeTable {
 		}
 		path.push(NODES_FILE);
 		let node_ids = self.nodes(&IpFilter::default());
+		// banned nodes are excluded from `nodes()`, but still saved to persist the ban.
+		let banned = self.nodes.values()
+			.filter(|n| n.reputation.is_banned() && !self.useless_nodes.contains(&n.id));
 		let nodes = node_ids.into_iter()
 			.map(|id| self.nodes.get(&id).expect("self.nodes() only returns node IDs from self.nodes"))
+			.chain(banned)
 			.take(MAX_NODES)
 			.map(Into::into)
 			.collect();
@@ -444,8 +581,12 @@ impl Drop for NodeTable {
 	}
 }
 
-/// Check if node url is valid
+/// Check if node url is valid. `enrtree://` node list URLs are accepted as well.
 pub fn validate_node_url(url: &str) -> Option<Error> {
+	if url.starts_with(ENRTREE_PREFIX) {
+		return validate_tree_url(url);
+	}
+
 	match Node::from_str(url) {
 		Ok(_) => None,
 		Err(e) => Some(e)
@@ -481,10 +622,29 @@ mod json {
 		}
 	}
 
+	#[derive(Serialize, Deserialize)]
+	pub struct Reputation {
+		pub score: i32,
+		pub updated: u64,
+		pub banned_until: Option<u64>,
+	}
+
+	impl Reputation {
+		pub fn into_reputation(self) -> super::Reputation {
+			super::Reputation {
+				score: self.score,
+				updated: time::UNIX_EPOCH + Duration::from_secs(self.updated),
+				banned_until: self.banned_until.map(|t| time::UNIX_EPOCH + Duration::from_secs(t)),
+			}
+		}
+	}
+
 	#[derive(Serialize, Deserialize)]
 	pub struct Node {
 		pub url: String,
 		pub last_contact: Option<NodeContact>,
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		pub reputation: Option<Reputation>,
 	}
 
 	impl Node {
@@ -492,6 +652,7 @@ mod json {
 			match super::Node::from_str(&self.url) {
 				Ok(mut node) => {
 					node.last_contact = self.last_contact.map(|c| c.into_node_contact());
+					node.reputation = self.reputation.map_or_else(Default::default, |r| r.into_reputation());
 					Some(node)
 				},
 				_ => None,
@@ -499,6 +660,10 @@ mod json {
 		}
 	}
 
+	fn as_secs(t: SystemTime) -> Option<u64> {
+		t.duration_since(time::UNIX_EPOCH).ok().map(|d| d.as_secs())
+	}
+
 	impl<'a> From<&'a super::Node> for Node {
 		fn from(node: &'a super::Node) -> Self {
 			let last_contact = node.last_contact.and_then(|c| {
@@ -510,9 +675,20 @@ mod json {
 				}
 			});
 
+			let reputation = if node.reputation.is_neutral() {
+				None
+			} else {
+				as_secs(node.reputation.updated).map(|updated| Reputation {
+					score: node.reputation.score,
+					updated,
+					banned_until: node.reputation.banned_until.and_then(as_secs),
+				})
+			};
+
 			Node {
 				url: format!("{}", node),
-				last_contact
+				last_contact,
+				reputation,
 			}
 		}
 	}
@@ -588,6 +764,13 @@ mod tests {
 		assert_matches!(node.unwrap_err().kind(), &ErrorKind::AddressParse);
 	}
 
+	#[test]
+	fn node_tree_url_validation() {
+		assert!(validate_node_url("enrtree://AM5FCQLWIZX2QFPNJAP7VUERCCRNGRHWZG3YYHIUV7BVDQ5FDPRT2@nodes.example.org").is_none());
+		assert!(validate_node_url("enrtree://AM5FCQLWIZX2QFPNJAP7VUERCCRNGRHWZG3YYHIUV7BVDQ5FDPRT2").is_some());
+		assert!(validate_node_url("enrtree://foo@nodes.example.org").is_some());
+	}
+
 	#[test]
 	fn table_last_contact_order() {
 		let node1 = Node::from_str("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
@@ -678,6 +861,90 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn table_reputation_order_and_ban() {
+		let node1 = Node::from_str("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let node2 = Node::from_str("enode://b979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let node3 = Node::from_str("enode://c979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let id1 = node1.id;
+		let id2 = node2.id;
+		let id3 = node3.id;
+		let mut table = NodeTable::new(None);
+		table.add_node(node1);
+		table.add_node(node2);
+		table.add_node(node3);
+
+		// all nodes have a successful contact, node 1 being the most recent one
+		table.note_success(&id3);
+		table.note_success(&id2);
+		table.note_success(&id1);
+		table.get_mut(&id1).unwrap().last_contact = Some(NodeContact::Success(SystemTime::now() + Duration::from_secs(10)));
+
+		// reputation takes precedence over the contact time
+		table.note_behaviour(&id2, PeerBehaviour::GoodDelivery);
+		table.note_behaviour(&id1, PeerBehaviour::Timeout);
+		assert_eq!(table.nodes(&IpFilter::default()), vec![id2, id3, id1]);
+
+		assert!(!table.note_behaviour(&id1, PeerBehaviour::UselessResponse));
+		assert!(table.note_behaviour(&id3, PeerBehaviour::InvalidBlock));
+		// no new ban while already banned
+		assert!(!table.note_behaviour(&id3, PeerBehaviour::InvalidBlock));
+		assert!(table.is_banned(&id3));
+		assert_eq!(table.nodes(&IpFilter::default()), vec![id2, id1]);
+
+		let reputations = table.reputations();
+		assert_eq!(reputations.iter().map(|r| (r.id, r.score)).collect::<Vec<_>>(), vec![(id2, 1), (id1, -30), (id3, -200)]);
+		assert!(reputations[2].banned_until.is_some());
+	}
+
+	#[test]
+	fn invalid_block_bans_reputable_node() {
+		let mut reputation = Reputation { score: MAX_REPUTATION, ..Default::default() };
+		assert!(!reputation.note(PeerBehaviour::Timeout));
+		assert!(reputation.note(PeerBehaviour::InvalidBlock));
+		assert!(reputation.is_banned());
+		assert_eq!(reputation.score(), BAN_THRESHOLD);
+	}
+
+	#[test]
+	fn reputation_decays() {
+		let now = SystemTime::now();
+		let reputation = Reputation {
+			score: -100,
+			updated: now - REPUTATION_HALF_LIFE * 2 - Duration::from_secs(10),
+			banned_until: Some(now - Duration::from_secs(1)),
+		};
+		let decayed = reputation.decayed(now);
+		assert_eq!(decayed.score, -25);
+		assert_eq!(decayed.updated, now - Duration::from_secs(10));
+		assert_eq!(decayed.banned_until, None);
+		assert!(!reputation.is_banned());
+	}
+
+	#[test]
+	fn table_save_load_reputation() {
+		let tempdir = TempDir::new("").unwrap();
+		let node1 = Node::from_str("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let node2 = Node::from_str("enode://b979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let id1 = node1.id;
+		let id2 = node2.id;
+
+		{
+			let mut table = NodeTable::new(Some(tempdir.path().to_str().unwrap().to_owned()));
+			table.add_node(node1);
+			table.add_node(node2);
+			table.note_behaviour(&id1, PeerBehaviour::GoodDelivery);
+			table.note_behaviour(&id2, PeerBehaviour::InvalidBlock);
+		}
+
+		{
+			let table = NodeTable::new(Some(tempdir.path().to_str().unwrap().to_owned()));
+			assert_eq!(table.nodes(&IpFilter::default()), vec![id1]);
+			assert!(table.is_banned(&id2));
+			assert_eq!(table.reputations().iter().map(|r| r.score).collect::<Vec<_>>(), vec![1, -100]);
+		}
+	}
+
 	#[test]
 	fn custom_allow() {
 		let filter = IpFilter {
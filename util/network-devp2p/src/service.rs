@@ -15,8 +15,9 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use network::{Error, NetworkConfiguration, NetworkProtocolHandler, NonReservedPeerMode};
-use network::{NetworkContext, PeerId, ProtocolId, NetworkIoMessage};
+use network::{NetworkContext, PeerId, ProtocolId, NetworkIoMessage, NodeReputation};
 use host::Host;
+use dns_discovery::{Resolver, SystemResolver};
 use io::*;
 use parking_lot::RwLock;
 use std::net::SocketAddr;
@@ -50,6 +51,7 @@ pub struct NetworkService {
 	host_handler: Arc<HostHandler>,
 	config: NetworkConfiguration,
 	filter: Option<Arc<ConnectionFilter>>,
+	dns_resolver: RwLock<Arc<Resolver>>,
 }
 
 impl NetworkService {
@@ -65,9 +67,15 @@ impl NetworkService {
 			config,
 			host_handler,
 			filter,
+			dns_resolver: RwLock::new(Arc::new(SystemResolver::from_system_conf())),
 		})
 	}
 
+	/// Use the given resolver for `enrtree://` node lists. Takes effect on the next call to `start`.
+	pub fn set_dns_resolver(&self, resolver: Arc<Resolver>) {
+		*self.dns_resolver.write() = resolver;
+	}
+
 	/// Register a new protocol handler with the event loop.
 	pub fn register_protocol(
 		&self,
@@ -122,7 +130,7 @@ impl NetworkService {
 		let mut host = self.host.write();
 		let listen_addr = self.config.listen_address;
 		if host.is_none() {
-			let h = Arc::new(Host::new(self.config.clone(), self.filter.clone())
+			let h = Arc::new(Host::new(self.config.clone(), self.filter.clone(), self.dns_resolver.read().clone())
 				.map_err(|err| (err, listen_addr))?);
 			self.io_service.register_handler(h.clone())
 				.map_err(|err| (err.into(), listen_addr))?;
@@ -152,6 +160,11 @@ impl NetworkService {
 		self.host.read().as_ref().map(|h| h.connected_peers()).unwrap_or_else(Vec::new)
 	}
 
+	/// Get the reputation of known nodes, best first. Nodes with a neutral reputation are omitted.
+	pub fn node_reputations(&self) -> Vec<NodeReputation> {
+		self.host.read().as_ref().map(|h| h.node_reputations()).unwrap_or_else(Vec::new)
+	}
+
 	/// Try to add a reserved peer.
 	pub fn add_reserved_peer(&self, peer: &str) -> Result<(), Error> {
 		let host = self.host.read();
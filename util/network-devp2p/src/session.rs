@@ -17,13 +17,16 @@
 use std::{str, io};
 use std::net::SocketAddr;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use mio::*;
 use mio::deprecated::{Handler, EventLoop};
 use mio::tcp::*;
 use ethereum_types::H256;
+use parking_lot::Mutex;
 use rlp::{Rlp, RlpStream, EMPTY_LIST_RLP};
+use bandwidth::{EgressQueue, RateLimiter};
 use connection::{EncryptedConnection, Packet, Connection, MAX_PAYLOAD_SIZE};
 use handshake::Handshake;
 use io::{IoContext, StreamToken};
@@ -38,6 +41,8 @@ const PING_TIMEOUT: Duration = Duration::from_secs(60);
 const PING_INTERVAL: Duration = Duration::from_secs(120);
 const MIN_PROTOCOL_VERSION: u32 = 4;
 const MIN_COMPRESSION_PROTOCOL_VERSION: u32 = 5;
+// Maximum number of packets handed over to the connection ahead of the socket.
+const MAX_CONNECTION_QUEUE: usize = 4;
 
 #[derive(Debug, Clone)]
 enum ProtocolState {
@@ -63,6 +68,12 @@ pub struct Session {
 	// Protocol states -- accumulates pending packets until signaled as ready.
 	protocol_states: HashMap<ProtocolId, ProtocolState>,
 	compression: bool,
+	// Subprotocol packets waiting to be sent.
+	egress: EgressQueue,
+	// Egress limit for this session.
+	peer_egress_limit: Option<RateLimiter>,
+	// Egress limit shared by all sessions.
+	global_egress_limit: Option<Arc<Mutex<RateLimiter>>>,
 }
 
 enum State {
@@ -120,12 +131,17 @@ impl Session {
 				originated,
 				remote_address: "Handshake".to_owned(),
 				local_address: local_addr,
+				traffic: Default::default(),
+				protocol_traffic: HashMap::new(),
 			},
 			ping_time: Instant::now(),
 			pong_time: None,
 			expired: false,
 			protocol_states: HashMap::new(),
 			compression: false,
+			egress: EgressQueue::default(),
+			peer_egress_limit: host.max_peer_egress_rate().map(RateLimiter::new),
+			global_egress_limit: host.egress_limit.clone(),
 		})
 	}
 
@@ -213,9 +229,47 @@ impl Session {
 	/// Writable IO handler. Sends pending packets.
 	pub fn writable<Message>(&mut self, io: &IoContext<Message>, _host: &HostInfo) -> Result<(), Error> where Message: Send + Sync + Clone {
 		match self.state {
-			State::Handshake(ref mut h) => h.writable(io),
-			State::Session(ref mut s) => s.writable(io),
+			State::Handshake(ref mut h) => h.writable(io)?,
+			State::Session(ref mut s) => s.writable(io)?,
 		}
+		self.flush_egress(io)
+	}
+
+	/// Check if there are subprotocol packets waiting to be sent.
+	pub fn has_pending_egress(&self) -> bool {
+		!self.egress.is_empty()
+	}
+
+	/// Hand queued subprotocol packets over to the connection, as far as egress limits allow.
+	/// Protocols take turns so that each gets a fair share of the bandwidth.
+	pub fn flush_egress<Message>(&mut self, io: &IoContext<Message>) -> Result<(), Error> where Message: Send + Sync + Clone {
+		let now = Instant::now();
+		while !self.egress.is_empty() && !self.expired() && self.connection().send_queue_len() < MAX_CONNECTION_QUEUE {
+			if !self.peer_egress_limit.as_mut().map_or(true, |l| l.can_send(now)) {
+				break;
+			}
+			if !self.global_egress_limit.as_ref().map_or(true, |l| l.lock().can_send(now)) {
+				break;
+			}
+			let (protocol, data) = self.egress.pop().expect("egress queue is not empty; qed");
+			if let Some(ref mut limit) = self.peer_egress_limit {
+				limit.consume(data.len());
+			}
+			if let Some(ref limit) = self.global_egress_limit {
+				limit.lock().consume(data.len());
+			}
+			self.send_protocol_packet(io, protocol, &data)?;
+		}
+		Ok(())
+	}
+
+	fn is_egress_limited(&self) -> bool {
+		self.peer_egress_limit.is_some() || self.global_egress_limit.is_some()
+	}
+
+	fn send_protocol_packet<Message>(&mut self, io: &IoContext<Message>, protocol: ProtocolId, data: &[u8]) -> Result<(), Error> where Message: Send + Sync + Clone {
+		self.info.protocol_traffic.entry(protocol).or_insert_with(Default::default).bytes_out += data.len() as u64;
+		self.send(io, data)
 	}
 
 	/// Checks if peer supports given capability
@@ -286,7 +340,15 @@ impl Session {
 			payload = &compressed[0..len];
 		}
 		rlp.append_raw(payload, 1);
-		self.send(io, &rlp.drain())
+		match protocol {
+			// Without egress limits packets go straight to the connection.
+			Some(protocol) if !self.is_egress_limited() => self.send_protocol_packet(io, protocol, &rlp.drain()),
+			Some(protocol) => {
+				self.egress.push(protocol, rlp.out());
+				self.flush_egress(io)
+			},
+			None => self.send(io, &rlp.drain()),
+		}
 	}
 
 	/// Keep this session alive. Returns false if ping timeout happened
@@ -331,6 +393,7 @@ impl Session {
 		if packet.data.len() < 2 {
 			return Err(ErrorKind::BadProtocol.into());
 		}
+		self.info.traffic.bytes_in += packet.data.len() as u64;
 		let packet_id = packet.data[0];
 		if packet_id != PACKET_HELLO && packet_id != PACKET_DISCONNECT && !self.had_hello {
 			return Err(ErrorKind::BadProtocol.into());
@@ -383,6 +446,7 @@ impl Session {
 				// map to protocol
 				let protocol = self.info.capabilities[i].protocol;
 				let protocol_packet_id = packet_id - self.info.capabilities[i].id_offset;
+				self.info.protocol_traffic.entry(protocol).or_insert_with(Default::default).bytes_in += packet.data.len() as u64;
 
 				match *self.protocol_states.entry(protocol).or_insert_with(|| ProtocolState::Pending(Vec::new())) {
 					ProtocolState::Connected => {
@@ -506,7 +570,8 @@ impl Session {
 				warn!(target:"network", "Unexpected send request");
 			},
 			State::Session(ref mut s) => {
-				s.send_packet(io, data)?
+				s.send_packet(io, data)?;
+				self.info.traffic.bytes_out += data.len() as u64;
 			},
 		}
 		Ok(())
@@ -146,3 +146,75 @@ fn net_timeout() {
 		thread::sleep(Duration::from_millis(50));
 	}
 }
+
+/// Sends a batch of packets to each connected peer and records the packets it receives.
+struct BulkProtocol {
+	protocol: ProtocolId,
+	send: bool,
+	received: Arc<Mutex<Vec<(ProtocolId, Instant)>>>,
+}
+
+const BULK_PACKETS: usize = 6;
+const BULK_PACKET_SIZE: usize = 8 * 1024;
+
+/// Pseudo-random packet contents, so that compression doesn't shrink the packet.
+fn bulk_packet() -> Vec<u8> {
+	let mut state = 0x2545_f491u32;
+	(0..BULK_PACKET_SIZE).map(|_| {
+		state ^= state << 13;
+		state ^= state >> 17;
+		state ^= state << 5;
+		state as u8
+	}).collect()
+}
+
+impl NetworkProtocolHandler for BulkProtocol {
+	fn read(&self, _io: &NetworkContext, _peer: &PeerId, _packet_id: u8, _data: &[u8]) {
+		self.received.lock().push((self.protocol, Instant::now()));
+	}
+
+	fn connected(&self, io: &NetworkContext, peer: &PeerId) {
+		if self.send {
+			for _ in 0..BULK_PACKETS {
+				io.send(*peer, 1, bulk_packet()).unwrap();
+			}
+		}
+	}
+
+	fn disconnected(&self, _io: &NetworkContext, _peer: &PeerId) {}
+}
+
+#[test]
+fn net_egress_limit() {
+	let received = Arc::new(Mutex::new(Vec::new()));
+	let register = |service: &NetworkService, send: bool| {
+		for protocol in &[*b"aaa", *b"bbb"] {
+			let handler = Arc::new(BulkProtocol { protocol: *protocol, send, received: received.clone() });
+			service.register_protocol(handler, *protocol, &[(1u8, 2u8)]).unwrap();
+		}
+	};
+
+	let mut config1 = NetworkConfiguration::new_local();
+	config1.max_peer_egress_rate = Some(4 * BULK_PACKET_SIZE as u64);
+	let service1 = NetworkService::new(config1, None).unwrap();
+	service1.start().unwrap();
+	register(&service1, true);
+	let mut config2 = NetworkConfiguration::new_local();
+	config2.boot_nodes = vec![ service1.local_url().unwrap() ];
+	let service2 = NetworkService::new(config2, None).unwrap();
+	service2.start().unwrap();
+	register(&service2, false);
+
+	while received.lock().len() < 2 * BULK_PACKETS {
+		thread::sleep(Duration::from_millis(50));
+	}
+
+	let received = received.lock();
+	// 12 packets at 4 packets per second, with a second worth of burst
+	let elapsed = received[received.len() - 1].1 - received[0].1;
+	assert!(elapsed >= Duration::from_millis(1500), "packets were not delayed: {:?}", elapsed);
+	// The protocol that started sending later doesn't wait for the other one to finish
+	let first = received[0].0;
+	let last_of_first = received.iter().rposition(|r| r.0 == first).unwrap();
+	assert!(received[..last_of_first].iter().any(|r| r.0 != first));
+}
@@ -137,6 +137,12 @@ error_chain! {
 			display("Invalid node id"),
 		}
 
+		#[doc = "Invalid or unverifiable DNS node tree (EIP-1459)"]
+		InvalidNodeTree(reason: String) {
+			description("Invalid DNS node tree"),
+			display("Invalid DNS node tree: {}", reason),
+		}
+
 		#[doc = "Packet size is over the protocol limit"]
 		OversizedPacket {
 			description("Packet is too large"),
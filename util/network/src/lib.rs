@@ -43,7 +43,7 @@ use std::collections::HashMap;
 use std::net::{SocketAddr, SocketAddrV4, Ipv4Addr};
 use std::str::{self, FromStr};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use ipnetwork::{IpNetwork, IpNetworkError};
 use ethkey::Secret;
 use ethereum_types::H512;
@@ -87,6 +87,8 @@ pub enum NetworkIoMessage {
 	Disconnect(PeerId),
 	/// Disconnect and temporary disable peer.
 	DisablePeer(PeerId),
+	/// Update the reputation of a peer.
+	ReportPeer(PeerId, PeerBehaviour),
 	/// Network has been started with the host as the given enode.
 	NetworkStarted(String),
 }
@@ -112,6 +114,45 @@ pub struct SessionInfo {
 	pub remote_address: String,
 	/// Local endpoint address of the session
 	pub local_address: String,
+	/// Total traffic of the session, including base protocol packets
+	pub traffic: TrafficStats,
+	/// Traffic of the session broken down by subprotocol
+	pub protocol_traffic: HashMap<ProtocolId, TrafficStats>,
+}
+
+/// Byte counters for a session or a subprotocol.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrafficStats {
+	/// Number of payload bytes received
+	pub bytes_in: u64,
+	/// Number of payload bytes sent
+	pub bytes_out: u64,
+}
+
+/// Peer behaviour observed by a protocol handler, affecting the reputation of the peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerBehaviour {
+	/// Peer delivered requested data.
+	GoodDelivery,
+	/// Peer sent a response which was of no use to us.
+	UselessResponse,
+	/// Peer did not respond to a request in time.
+	Timeout,
+	/// Peer sent an invalid block, header, body or receipt.
+	InvalidBlock,
+}
+
+/// Persistent reputation of a known node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeReputation {
+	/// Node public key
+	pub id: NodeId,
+	/// Node URL
+	pub url: String,
+	/// Current score. Positive for well-behaving nodes, negative for misbehaving ones.
+	pub score: i32,
+	/// If the node is temporarily banned, the time the ban ends.
+	pub banned_until: Option<SystemTime>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -181,6 +222,8 @@ pub struct NetworkConfiguration {
 	pub udp_port: Option<u16>,
 	/// Enable NAT configuration
 	pub nat_enabled: bool,
+	/// Port mapping mechanism to use when NAT configuration is enabled
+	pub nat_type: NatType,
 	/// Enable discovery
 	pub discovery_enabled: bool,
 	/// List of initial node addresses
@@ -203,6 +246,10 @@ pub struct NetworkConfiguration {
 	pub ip_filter: IpFilter,
 	/// Client identifier
 	pub client_version: String,
+	/// Maximum egress rate across all peers in bytes per second. None means unlimited
+	pub max_egress_rate: Option<u64>,
+	/// Maximum egress rate for a single peer in bytes per second. None means unlimited
+	pub max_peer_egress_rate: Option<u64>,
 }
 
 impl Default for NetworkConfiguration {
@@ -221,6 +268,7 @@ impl NetworkConfiguration {
 			public_address: None,
 			udp_port: None,
 			nat_enabled: true,
+			nat_type: NatType::Any,
 			discovery_enabled: true,
 			boot_nodes: Vec::new(),
 			use_secret: None,
@@ -232,6 +280,8 @@ impl NetworkConfiguration {
 			reserved_nodes: Vec::new(),
 			non_reserved_mode: NonReservedPeerMode::Accept,
 			client_version: "Parity-network".into(),
+			max_egress_rate: None,
+			max_peer_egress_rate: None,
 		}
 	}
 
@@ -268,6 +318,9 @@ pub trait NetworkContext {
 	/// Disconnect peer. Reconnect can be attempted later.
 	fn disconnect_peer(&self, peer: PeerId);
 
+	/// Report peer behaviour, updating its persistent reputation.
+	fn report_peer(&self, peer: PeerId, behaviour: PeerBehaviour);
+
 	/// Check if the session is still active.
 	fn is_expired(&self) -> bool;
 
@@ -311,6 +364,10 @@ impl<'a, T> NetworkContext for &'a T where T: ?Sized + NetworkContext {
 		(**self).disconnect_peer(peer)
 	}
 
+	fn report_peer(&self, peer: PeerId, behaviour: PeerBehaviour) {
+		(**self).report_peer(peer, behaviour)
+	}
+
 	fn is_expired(&self) -> bool {
 		(**self).is_expired()
 	}
@@ -376,6 +433,32 @@ impl NonReservedPeerMode {
 	}
 }
 
+/// Port mapping mechanisms used to traverse NAT.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NatType {
+	/// Try UPnP, PCP and NAT-PMP in turn. This is the default.
+	Any,
+	/// UPnP IGD.
+	UPnP,
+	/// NAT Port Mapping Protocol.
+	NatPmp,
+	/// Port Control Protocol.
+	Pcp,
+}
+
+impl NatType {
+	/// Attempt to parse the NAT type from a string.
+	pub fn parse(s: &str) -> Option<Self> {
+		match s {
+			"any" => Some(NatType::Any),
+			"upnp" => Some(NatType::UPnP),
+			"natpmp" => Some(NatType::NatPmp),
+			"pcp" => Some(NatType::Pcp),
+			_ => None,
+		}
+	}
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct IpFilter {
     pub predefined: AllowIP,